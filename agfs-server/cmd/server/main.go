@@ -272,6 +272,11 @@ func main() {
 		}()
 	}
 
+	// Plugins loaded later through the API are checked too
+	if err := mfs.GetPluginLoader().SetTrustedKeys(cfg.ExternalPlugins.TrustedKeys); err != nil {
+		log.Fatalf("Invalid external_plugins.trusted_keys: %v", err)
+	}
+
 	// Load external plugins if enabled
 	if cfg.ExternalPlugins.Enabled {
 		log.Info("Loading external plugins...")
@@ -0,0 +1,169 @@
+//! Request path canonicalization shared by the AGFS Rust SDKs
+//!
+//! Both `agfs-ffi` (native plugins) and `agfs-wasm-ffi` (WASM plugins) run
+//! every request path through `canonicalize` before a plugin sees it, after
+//! decoding the raw bytes as the plugin's `PathPolicy` asks. Each SDK
+//! converts `InvalidPath` into its own error type.
+
+pub mod nfc;
+
+use std::fmt;
+
+/// A request path that cannot be used, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidPath(pub &'static str);
+
+impl fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for InvalidPath {}
+
+/// Result type for path operations
+pub type Result<T> = std::result::Result<T, InvalidPath>;
+
+/// Bring a request path into canonical form
+///
+/// The result starts with `/`, has no empty or `.` segments and no
+/// trailing slash (except for the root itself). Paths containing `..`
+/// segments or NUL bytes are rejected rather than resolved, so no path can
+/// climb above the mount root or a prefix it is joined to.
+pub fn canonicalize(path: &str) -> Result<String> {
+    if path.contains('\0') {
+        return Err(InvalidPath("path contains a NUL byte"));
+    }
+    let mut out = String::with_capacity(path.len() + 1);
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return Err(InvalidPath("path traversal is not allowed")),
+            s => {
+                out.push('/');
+                out.push_str(s);
+            }
+        }
+    }
+    if out.is_empty() {
+        out.push('/');
+    }
+    Ok(out)
+}
+
+/// Join a canonical request path below a host prefix
+///
+/// `path` is canonicalized first, so the result never leaves `prefix`.
+pub fn join(prefix: &str, path: &str) -> Result<String> {
+    let path = canonicalize(path)?;
+    let prefix = prefix.trim_end_matches('/');
+    if path == "/" {
+        return Ok(if prefix.is_empty() { path } else { prefix.to_string() });
+    }
+    Ok(format!("{}{}", prefix, path))
+}
+
+/// How to treat path bytes that are not valid UTF-8
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidUtf8 {
+    /// Fail the request (default)
+    #[default]
+    Reject,
+    /// Replace invalid sequences with U+FFFD
+    Replace,
+}
+
+/// Unicode normalization applied to request paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Pass names through as sent (default)
+    #[default]
+    None,
+    /// Compose names to NFC, so decomposed (macOS) and precomposed
+    /// spellings of a name are the same path
+    Nfc,
+}
+
+/// Policy for decoding request paths, chosen by the plugin through
+/// `FileSystem::path_policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathPolicy {
+    pub invalid_utf8: InvalidUtf8,
+    pub normalization: Normalization,
+}
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathPolicy {
+    /// The default policy: strict UTF-8, no normalization
+    pub const fn new() -> Self {
+        Self {
+            invalid_utf8: InvalidUtf8::Reject,
+            normalization: Normalization::None,
+        }
+    }
+
+    /// Decode, normalize and canonicalize raw path bytes
+    pub fn apply(&self, raw: &[u8]) -> Result<String> {
+        let decoded = match self.invalid_utf8 {
+            InvalidUtf8::Reject => {
+                std::str::from_utf8(raw).map_err(|_| InvalidPath("path is not valid UTF-8"))?
+            }
+            InvalidUtf8::Replace => &String::from_utf8_lossy(raw),
+        };
+        match self.normalization {
+            Normalization::None => canonicalize(decoded),
+            Normalization::Nfc => canonicalize(&nfc::to_nfc(decoded)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize("").unwrap(), "/");
+        assert_eq!(canonicalize("/").unwrap(), "/");
+        assert_eq!(canonicalize("//a/./b//").unwrap(), "/a/b");
+        assert_eq!(canonicalize("a/b").unwrap(), "/a/b");
+        assert_eq!(canonicalize("/a/..b/c..").unwrap(), "/a/..b/c..");
+    }
+
+    #[test]
+    fn test_rejects_traversal() {
+        assert_eq!(
+            canonicalize("/host/../../etc/passwd"),
+            Err(InvalidPath("path traversal is not allowed"))
+        );
+        assert!(canonicalize("..").is_err());
+        assert_eq!(canonicalize("/a/\0"), Err(InvalidPath("path contains a NUL byte")));
+        assert!(join("/srv/data", "/../etc").is_err());
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join("/srv/data/", "/a/b").unwrap(), "/srv/data/a/b");
+        assert_eq!(join("/srv/data", "/").unwrap(), "/srv/data");
+        assert_eq!(join("", "x").unwrap(), "/x");
+    }
+
+    #[test]
+    fn test_path_policy() {
+        let strict = PathPolicy::default();
+        assert!(strict.apply(b"/bad\xff").is_err());
+        assert_eq!(strict.apply("/cafe\u{301}".as_bytes()).unwrap(), "/cafe\u{301}");
+
+        let relaxed = PathPolicy {
+            invalid_utf8: InvalidUtf8::Replace,
+            normalization: Normalization::Nfc,
+        };
+        assert_eq!(relaxed.apply(b"/bad\xff").unwrap(), "/bad\u{fffd}");
+        assert_eq!(relaxed.apply("//cafe\u{301}/".as_bytes()).unwrap(), "/caf\u{e9}");
+    }
+}
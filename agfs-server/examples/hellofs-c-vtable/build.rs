@@ -0,0 +1,30 @@
+//! Compile hellofs.c into a static library linked into the plugin
+//!
+//! Uses the system C compiler (`$CC`, default `cc`) and `ar` directly.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn run(cmd: &mut Command) {
+    let status = cmd.status().unwrap_or_else(|e| panic!("failed to run {:?}: {}", cmd, e));
+    assert!(status.success(), "{:?} failed with {}", cmd, status);
+}
+
+fn main() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let obj = out.join("hellofs.o");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    run(Command::new(cc)
+        .args(["-c", "-fPIC", "-O2", "-Wall", "-I../hellofs-rust/agfs-ffi/include"])
+        .arg("hellofs.c")
+        .arg("-o")
+        .arg(&obj));
+    run(Command::new("ar").arg("crs").arg(out.join("libhellofs.a")).arg(&obj));
+
+    println!("cargo:rustc-link-search=native={}", out.display());
+    println!("cargo:rustc-link-lib=static=hellofs");
+    println!("cargo:rerun-if-changed=hellofs.c");
+    println!("cargo:rerun-if-changed=../hellofs-rust/agfs-ffi/include/agfs_ffi.h");
+}
@@ -0,0 +1,108 @@
+/*
+ * HelloFS written against the agfs-ffi plugin vtable
+ *
+ * Same filesystem as examples/hellofs-c, but the plugin only fills in an
+ * AgfsPluginVTable; the Rust shim (src/lib.rs) exports the functions
+ * agfs-server loads and handles memory ownership.
+ */
+
+#include <stdlib.h>
+#include <string.h>
+
+#include "agfs_ffi.h"
+
+static const char HELLO[] = "Hello from C via the agfs-ffi vtable!\n";
+
+typedef struct {
+    int initialized;
+} HelloFS;
+
+static void *hello_create(void) {
+    return calloc(1, sizeof(HelloFS));
+}
+
+static void hello_destroy(void *self) {
+    free(self);
+}
+
+static const char *hello_initialize(void *self, const char *config_json) {
+    ((HelloFS *)self)->initialized = 1;
+    return NULL;
+}
+
+static const char *hello_shutdown(void *self) {
+    ((HelloFS *)self)->initialized = 0;
+    return NULL;
+}
+
+static const char *hello_read(void *self, const char *path, int64_t offset, int64_t size,
+                              const char **out_data, int64_t *out_len) {
+    int64_t len = (int64_t)strlen(HELLO);
+
+    if (strcmp(path, "/hello") != 0) {
+        return "not found";
+    }
+    if (offset > len) {
+        offset = len;
+    }
+    *out_data = HELLO + offset;
+    *out_len = (size < 0 || offset + size > len) ? len - offset : size;
+    return NULL;
+}
+
+static void hello_entry(FileInfoC *info) {
+    info->name = "hello";
+    info->size = (int64_t)strlen(HELLO);
+    info->mode = 0644;
+    info->mod_time = 0;
+    info->is_dir = 0;
+    info->meta_name = "hellofs-c-vtable";
+    info->meta_type = "text";
+    info->meta_content = "{\"language\":\"c\"}";
+}
+
+static const char *hello_stat(void *self, const char *path, FileInfoC *out) {
+    if (strcmp(path, "/") == 0) {
+        out->name = "";
+        out->mode = 0755;
+        out->is_dir = 1;
+        return NULL;
+    }
+    if (strcmp(path, "/hello") == 0) {
+        hello_entry(out);
+        return NULL;
+    }
+    return "not found";
+}
+
+static const char *hello_readdir(void *self, const char *path, AgfsDirEmit emit,
+                                 void *cookie) {
+    FileInfoC info;
+
+    if (strcmp(path, "/") != 0) {
+        return "not a directory";
+    }
+    hello_entry(&info);
+    emit(cookie, &info);
+    return NULL;
+}
+
+static const AgfsPluginVTable VTABLE = {
+    .abi_version = AGFS_VTABLE_ABI_VERSION,
+    .name = "hellofs-c-vtable",
+    .readme = "# HelloFS C Plugin (vtable)\n\n"
+              "A read-only filesystem with a single file, /hello, written in C\n"
+              "against the agfs-ffi plugin vtable.\n",
+    .create = hello_create,
+    .destroy = hello_destroy,
+    .initialize = hello_initialize,
+    .shutdown = hello_shutdown,
+    .read = hello_read,
+    .stat = hello_stat,
+    .readdir = hello_readdir,
+    /* Writes are left NULL: the filesystem is read-only */
+};
+
+const AgfsPluginVTable *hellofs_vtable(void) {
+    return &VTABLE;
+}
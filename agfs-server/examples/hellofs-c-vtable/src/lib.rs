@@ -0,0 +1,3 @@
+//! Exports the C plugin in hellofs.c through the agfs-ffi vtable shim
+
+agfs_ffi::export_c_plugin!(hellofs_vtable);
@@ -0,0 +1,126 @@
+/*
+ * agfs_ffi.h - C interface of agfs native plugins
+ *
+ * Mirrors the ABI of the agfs-ffi crate: src/ffi.rs for the data types,
+ * the export_plugin! macro in src/lib.rs for the exported functions, and
+ * src/cshim.rs for the plugin vtable. Keep it in sync when any of those
+ * change.
+ *
+ * A C plugin can either export the functions below directly (see
+ * examples/hellofs-c) or fill in an AgfsPluginVTable and let the Rust
+ * shim export them (see examples/hellofs-c-vtable). The shim takes care of
+ * path canonicalization, locking and memory ownership.
+ */
+
+#ifndef AGFS_FFI_H
+#define AGFS_FFI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Entry returned by stat and readdir */
+typedef struct FileInfoC {
+    const char *name;
+    int64_t size;
+    uint32_t mode;
+    int64_t mod_time; /* Unix seconds */
+    int32_t is_dir;
+    const char *meta_name;
+    const char *meta_type;
+    const char *meta_content; /* JSON */
+} FileInfoC;
+
+typedef struct FileInfoArray {
+    FileInfoC *items;
+    int count;
+} FileInfoArray;
+
+/*
+ * Functions agfs-server loads from a native plugin. Calls returning
+ * const char* return NULL on success and an error message otherwise.
+ * Plugin errors take the form "CODE: message", where CODE is an errno
+ * name (ENOENT, EEXIST, ENOTEMPTY, EACCES, ...) or EOTHER.
+ */
+void *PluginNew(void);
+void PluginFree(void *plugin);
+const char *PluginName(void *plugin);
+const char *PluginGetReadme(void *plugin);
+const char *PluginValidate(void *plugin, const char *config_json);
+const char *PluginInitialize(void *plugin, const char *config_json);
+const char *PluginShutdown(void *plugin);
+
+/* Returns the data, or an error message with *out_len set to -1 */
+const char *FSRead(void *plugin, const char *path, int64_t offset, int64_t size,
+                   int *out_len);
+/* NULL if the path does not exist */
+FileInfoC *FSStat(void *plugin, const char *path);
+/* NULL with *out_count set to -1 on failure */
+FileInfoArray *FSReadDir(void *plugin, const char *path, int *out_count);
+const char *FSWrite(void *plugin, const char *path, const char *data, int data_len);
+/* Sets *out_written to the number of bytes written */
+const char *FSWriteAt(void *plugin, const char *path, int64_t offset, const char *data,
+                      int data_len, int64_t *out_written);
+const char *FSCreate(void *plugin, const char *path);
+const char *FSCreateExclusive(void *plugin, const char *path);
+const char *FSMkdir(void *plugin, const char *path, uint32_t mode);
+const char *FSRemove(void *plugin, const char *path);
+const char *FSRemoveAll(void *plugin, const char *path);
+const char *FSRename(void *plugin, const char *old_path, const char *new_path);
+const char *FSChmod(void *plugin, const char *path, uint32_t mode);
+const char *FSTruncate(void *plugin, const char *path, int64_t size);
+const char *FSSetTimes(void *plugin, const char *path, int64_t atime, int64_t mtime);
+const char *FSFlush(void *plugin, const char *path);
+const char *FSFsync(void *plugin, const char *path);
+
+/*
+ * Plugin vtable for the Rust shim (agfs_ffi::export_c_plugin!)
+ *
+ * Callbacks return NULL on success and an error message otherwise. The
+ * messages "not found", "permission denied", "already exists",
+ * "not a directory", "is a directory" and "directory not empty" map to the
+ * matching errors. Every string or buffer a callback hands back stays
+ * owned by the plugin and only has to stay valid until the callback
+ * returns. A NULL callback makes the operation unsupported.
+ *
+ * Paths are canonical ("/a/b", no "..") by the time they reach the
+ * plugin, and calls on one instance never overlap.
+ */
+#define AGFS_VTABLE_ABI_VERSION 1
+
+/* Hands one directory entry to the shim; call once per entry */
+typedef void (*AgfsDirEmit)(void *cookie, const FileInfoC *info);
+
+typedef struct AgfsPluginVTable {
+    uint32_t abi_version; /* AGFS_VTABLE_ABI_VERSION */
+    const char *name;
+    const char *readme; /* may be NULL */
+
+    void *(*create)(void);
+    void (*destroy)(void *self);
+    const char *(*validate)(void *self, const char *config_json);
+    const char *(*initialize)(void *self, const char *config_json);
+    const char *(*shutdown)(void *self);
+
+    /* Point *out_data at up to size bytes (all when size < 0) */
+    const char *(*read)(void *self, const char *path, int64_t offset, int64_t size,
+                        const char **out_data, int64_t *out_len);
+    const char *(*stat)(void *self, const char *path, FileInfoC *out);
+    const char *(*readdir)(void *self, const char *path, AgfsDirEmit emit, void *cookie);
+
+    const char *(*write)(void *self, const char *path, const char *data, int64_t len);
+    const char *(*create_file)(void *self, const char *path);
+    const char *(*mkdir)(void *self, const char *path, uint32_t mode);
+    const char *(*remove)(void *self, const char *path);
+    const char *(*remove_all)(void *self, const char *path);
+    const char *(*rename)(void *self, const char *old_path, const char *new_path);
+    const char *(*chmod)(void *self, const char *path, uint32_t mode);
+} AgfsPluginVTable;
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AGFS_FFI_H */
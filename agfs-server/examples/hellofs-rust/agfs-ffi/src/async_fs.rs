@@ -0,0 +1,375 @@
+//! Async filesystem plugins
+//!
+//! Backends that talk to HTTP services or databases are naturally async.
+//! They implement `AsyncFileSystem` and are exported through `AsyncAdapter`,
+//! which drives each call to completion on the plugin's own runtime:
+//!
+//! ```rust,ignore
+//! use agfs_ffi::prelude::*;
+//!
+//! #[derive(Default)]
+//! struct HttpFS { /* client */ }
+//!
+//! impl AsyncFileSystem for HttpFS {
+//!     fn name(&self) -> &str {
+//!         "httpfs"
+//!     }
+//!
+//!     async fn read(&self, path: &str, offset: i64, size: i64) -> Result<String> {
+//!         self.get(path, offset, size).await
+//!     }
+//!
+//!     // stat, readdir, ...
+//! }
+//!
+//! export_plugin!(AsyncAdapter<HttpFS>);
+//! ```
+//!
+//! The default `ParkRuntime` polls the future on the calling thread and
+//! parks it while the future is pending, which suits futures that are
+//! woken from other threads. Futures tied to a reactor (tokio sockets and
+//! timers) need that reactor's runtime; wrap it in a `Runtime` and export
+//! `AsyncAdapter<HttpFS, TokioRuntime>` instead.
+
+use crate::error::{FileSystemError, Result};
+use crate::filesystem::FileSystem;
+use crate::path::PathPolicy;
+use crate::types::FileInfo;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Async counterpart of `FileSystem`
+///
+/// Methods and defaults mirror `FileSystem`; only the calls that may wait
+/// on a backend are async.
+// Futures are driven by the adapter on the calling thread, so they need
+// not be Send.
+#[allow(async_fn_in_trait)]
+pub trait AsyncFileSystem: Default + Send + Sync {
+    /// Get the plugin name
+    fn name(&self) -> &str;
+
+    /// Get the plugin README (markdown format)
+    fn readme(&self) -> &str {
+        "# Plugin\n\nNo documentation provided."
+    }
+
+    /// Validate plugin configuration (JSON string)
+    fn validate(&self, _config: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Initialize the plugin with given configuration
+    async fn initialize(&mut self, _config: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// How request paths are decoded before they reach this plugin
+    fn path_policy(&self) -> PathPolicy {
+        PathPolicy::default()
+    }
+
+    /// Shutdown the plugin
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read file contents
+    async fn read(&self, path: &str, offset: i64, size: i64) -> Result<String>;
+
+    /// Get file or directory information
+    async fn stat(&self, path: &str) -> Result<FileInfo>;
+
+    /// List directory contents
+    async fn readdir(&self, path: &str) -> Result<Vec<FileInfo>>;
+
+    /// Write data to a file
+    async fn write(&self, _path: &str, _data: &[u8]) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Write data at `offset`; see `FileSystem::write_at`
+    async fn write_at(&self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        if offset != 0 {
+            return Err(FileSystemError::ReadOnly);
+        }
+        self.write(path, data).await?;
+        Ok(data.len() as i64)
+    }
+
+    /// Create a new file
+    async fn create(&self, _path: &str) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Create a new file, failing with AlreadyExists if the path exists
+    async fn create_exclusive(&self, path: &str) -> Result<()> {
+        match self.stat(path).await {
+            Ok(_) => Err(FileSystemError::AlreadyExists),
+            Err(FileSystemError::NotFound) => self.create(path).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a directory
+    async fn mkdir(&self, _path: &str, _mode: u32) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Remove a file
+    async fn remove(&self, _path: &str) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Remove a directory and all its contents
+    async fn remove_all(&self, _path: &str) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Rename a file or directory
+    async fn rename(&self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Change file or directory permissions
+    async fn chmod(&self, _path: &str, _mode: u32) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Shrink or zero-extend a file to `size` bytes
+    async fn truncate(&self, _path: &str, _size: i64) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Set access and modification times, in Unix seconds
+    async fn set_times(&self, _path: &str, _atime: i64, _mtime: i64) -> Result<()> {
+        Err(FileSystemError::ReadOnly)
+    }
+
+    /// Push buffered writes to `path` to the backend
+    async fn flush(&self, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Make writes to `path` durable before returning
+    async fn fsync(&self, _path: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Drives futures to completion for `AsyncAdapter`
+///
+/// Each exported plugin instance owns one.
+pub trait Runtime: Default + Send + Sync {
+    /// Run `future` to completion on the calling thread
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+}
+
+/// Minimal runtime that parks the calling thread between polls
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParkRuntime;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+impl Runtime for ParkRuntime {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            // Spurious wakeups only cost an extra poll
+            thread::park();
+        }
+    }
+}
+
+/// A `FileSystem` that runs an `AsyncFileSystem` on runtime `R`
+#[derive(Debug, Default)]
+pub struct AsyncAdapter<A, R = ParkRuntime> {
+    fs: A,
+    runtime: R,
+}
+
+impl<A: AsyncFileSystem, R: Runtime> AsyncAdapter<A, R> {
+    /// Wrap `fs`, running its calls on `runtime`
+    pub fn new(fs: A, runtime: R) -> Self {
+        Self { fs, runtime }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &A {
+        &self.fs
+    }
+}
+
+impl<A: AsyncFileSystem, R: Runtime> FileSystem for AsyncAdapter<A, R> {
+    fn name(&self) -> &str {
+        self.fs.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.fs.readme()
+    }
+
+    fn validate(&self, config: &str) -> Result<()> {
+        self.fs.validate(config)
+    }
+
+    fn initialize(&mut self, config: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.initialize(config))
+    }
+
+    fn path_policy(&self) -> PathPolicy {
+        self.fs.path_policy()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.runtime.block_on(self.fs.shutdown())
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<String> {
+        self.runtime.block_on(self.fs.read(path, offset, size))
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.runtime.block_on(self.fs.stat(path))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.runtime.block_on(self.fs.readdir(path))
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.runtime.block_on(self.fs.write(path, data))
+    }
+
+    fn write_at(&self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        self.runtime.block_on(self.fs.write_at(path, offset, data))
+    }
+
+    fn create(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.create(path))
+    }
+
+    fn create_exclusive(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.create_exclusive(path))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.runtime.block_on(self.fs.mkdir(path, mode))
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.remove(path))
+    }
+
+    fn remove_all(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.remove_all(path))
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.rename(old_path, new_path))
+    }
+
+    fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.runtime.block_on(self.fs.chmod(path, mode))
+    }
+
+    fn truncate(&self, path: &str, size: i64) -> Result<()> {
+        self.runtime.block_on(self.fs.truncate(path, size))
+    }
+
+    fn set_times(&self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        self.runtime.block_on(self.fs.set_times(path, atime, mtime))
+    }
+
+    fn flush(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.flush(path))
+    }
+
+    fn fsync(&self, path: &str) -> Result<()> {
+        self.runtime.block_on(self.fs.fsync(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // Pending until another thread has had time to wake it
+    async fn wait_for_backend() {
+        let mut woken = false;
+        std::future::poll_fn(|cx| {
+            if woken {
+                return Poll::Ready(());
+            }
+            woken = true;
+            let waker = cx.waker().clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(5));
+                waker.wake();
+            });
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[derive(Default)]
+    struct SlowFS;
+
+    impl AsyncFileSystem for SlowFS {
+        fn name(&self) -> &str {
+            "slow-fs"
+        }
+
+        async fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<String> {
+            wait_for_backend().await;
+            match path {
+                "/a" => Ok("remote".to_string()),
+                _ => Err(FileSystemError::NotFound),
+            }
+        }
+
+        async fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/a" => Ok(FileInfo::file("a", 6, 0o644)),
+                _ => Err(FileSystemError::NotFound),
+            }
+        }
+
+        async fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![FileInfo::file("a", 6, 0o644)])
+        }
+    }
+
+    #[test]
+    fn test_async_adapter() {
+        let fs = AsyncAdapter::<SlowFS>::default();
+        assert_eq!(fs.name(), "slow-fs");
+        assert_eq!(fs.read("/a", 0, 0).unwrap(), "remote");
+        assert_eq!(fs.read("/b", 0, 0), Err(FileSystemError::NotFound));
+        assert_eq!(fs.readdir("/").unwrap().len(), 1);
+        assert_eq!(
+            fs.create_exclusive("/a"),
+            Err(FileSystemError::AlreadyExists)
+        );
+        assert_eq!(fs.write("/a", b"x"), Err(FileSystemError::ReadOnly));
+    }
+}
@@ -0,0 +1,421 @@
+//! C plugin shim
+//!
+//! Lets a plugin written in C implement the `FileSystem` trait through a
+//! table of function pointers (`AgfsPluginVTable` in
+//! `include/agfs_ffi.h`) instead of the raw `PluginNew`/`FS*` exports. The
+//! C side defines a function returning its table; a small Rust crate
+//! exports it with
+//!
+//! ```rust,ignore
+//! agfs_ffi::export_c_plugin!(hellofs_vtable);
+//! ```
+//!
+//! and the shim takes care of path canonicalization, threading, and
+//! copying results out of C memory. Every string a callback returns
+//! (errors, read data, entry fields) stays owned by the C plugin and only
+//! has to remain valid until the callback returns; the shim copies it
+//! before doing anything else.
+
+// Pointers handed to the callbacks come from the shim itself or from the
+// vtable the C plugin registered.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::error::{FileSystemError, Result};
+use crate::filesystem::FileSystem;
+use crate::ffi::FileInfoC;
+use crate::types::{FileInfo, FileMetadata};
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Version of the vtable layout; `AGFS_VTABLE_ABI_VERSION` in the header
+pub const VTABLE_ABI_VERSION: u32 = 1;
+
+/// Callback receiving one directory entry from `readdir`
+pub type DirEmit = extern "C" fn(cookie: *mut c_void, info: *const FileInfoC);
+
+type PathCall = extern "C" fn(*mut c_void, *const c_char) -> *const c_char;
+type ModeCall = extern "C" fn(*mut c_void, *const c_char, u32) -> *const c_char;
+
+/// Function table a C plugin provides
+///
+/// Callbacks return NULL on success and an error message otherwise.
+/// `NULL` callbacks make the operation unsupported (read-only).
+#[repr(C)]
+pub struct AgfsPluginVTable {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub readme: *const c_char,
+    pub create: Option<extern "C" fn() -> *mut c_void>,
+    pub destroy: Option<extern "C" fn(*mut c_void)>,
+    pub validate: Option<extern "C" fn(*mut c_void, *const c_char) -> *const c_char>,
+    pub initialize: Option<extern "C" fn(*mut c_void, *const c_char) -> *const c_char>,
+    pub shutdown: Option<extern "C" fn(*mut c_void) -> *const c_char>,
+    pub read: Option<
+        extern "C" fn(
+            *mut c_void,
+            *const c_char,
+            i64,
+            i64,
+            *mut *const c_char,
+            *mut i64,
+        ) -> *const c_char,
+    >,
+    pub stat: Option<extern "C" fn(*mut c_void, *const c_char, *mut FileInfoC) -> *const c_char>,
+    pub readdir: Option<
+        extern "C" fn(*mut c_void, *const c_char, DirEmit, *mut c_void) -> *const c_char,
+    >,
+    pub write: Option<extern "C" fn(*mut c_void, *const c_char, *const c_char, i64) -> *const c_char>,
+    pub create_file: Option<PathCall>,
+    pub mkdir: Option<ModeCall>,
+    pub remove: Option<PathCall>,
+    pub remove_all: Option<PathCall>,
+    pub rename: Option<extern "C" fn(*mut c_void, *const c_char, *const c_char) -> *const c_char>,
+    pub chmod: Option<ModeCall>,
+}
+
+// Vtables are immutable tables of static strings and functions
+unsafe impl Sync for AgfsPluginVTable {}
+
+/// Where `CPlugin` gets its vtable; implemented by `export_c_plugin!`
+pub trait VTableSource {
+    fn vtable() -> &'static AgfsPluginVTable;
+}
+
+/// `FileSystem` backed by a C plugin's vtable
+pub struct CPlugin<S> {
+    vtable: &'static AgfsPluginVTable,
+    instance: *mut c_void,
+    name: String,
+    readme: String,
+    _source: PhantomData<S>,
+}
+
+// The export glue serializes calls through a mutex, so the C instance is
+// never entered from two threads at once.
+unsafe impl<S> Send for CPlugin<S> {}
+unsafe impl<S> Sync for CPlugin<S> {}
+
+fn copy_str(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+}
+
+/// Map a C error message onto `FileSystemError`
+///
+/// Messages in the `CODE: message` form of `FileSystemError::to_wire`
+/// decode exactly. The messages agfs-server itself uses for common
+/// failures map to the matching variant; anything else becomes `Custom`.
+pub fn error_from_c(ptr: *const c_char) -> Result<()> {
+    if ptr.is_null() {
+        return Ok(());
+    }
+    let msg = copy_str(ptr);
+    if let Some(e) = FileSystemError::from_wire(&msg) {
+        return Err(e);
+    }
+    Err(match msg.as_str() {
+        "not found" | "file not found" => FileSystemError::NotFound,
+        "permission denied" => FileSystemError::PermissionDenied,
+        "already exists" | "file already exists" => FileSystemError::AlreadyExists,
+        "not a directory" => FileSystemError::NotADirectory,
+        "is a directory" => FileSystemError::IsADirectory,
+        "directory not empty" => FileSystemError::DirectoryNotEmpty,
+        "operation not supported: read-only filesystem" => FileSystemError::ReadOnly,
+        "operation not supported" => FileSystemError::NotSupported,
+        _ => FileSystemError::Custom(msg),
+    })
+}
+
+fn to_c(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|_| FileSystemError::InvalidPath)
+}
+
+/// Copy an entry the C plugin filled in
+///
+/// # Safety
+/// The string fields must be NULL or valid C strings.
+pub unsafe fn file_info_from_c(info: &FileInfoC) -> FileInfo {
+    FileInfo {
+        name: copy_str(info.name),
+        size: info.size,
+        mode: info.mode,
+        mod_time: info.mod_time,
+        is_dir: info.is_dir != 0,
+        metadata: FileMetadata {
+            name: copy_str(info.meta_name),
+            file_type: copy_str(info.meta_type),
+            content: match copy_str(info.meta_content) {
+                s if s.is_empty() => "{}".to_string(),
+                s => s,
+            },
+        },
+    }
+}
+
+extern "C" fn collect_entry(cookie: *mut c_void, info: *const FileInfoC) {
+    if cookie.is_null() || info.is_null() {
+        return;
+    }
+    let entries = unsafe { &mut *(cookie as *mut Vec<FileInfo>) };
+    entries.push(unsafe { file_info_from_c(&*info) });
+}
+
+impl<S> CPlugin<S> {
+    fn path_call(&self, call: Option<PathCall>, path: &str) -> Result<()> {
+        let call = call.ok_or(FileSystemError::ReadOnly)?;
+        error_from_c(call(self.instance, to_c(path)?.as_ptr()))
+    }
+
+    fn mode_call(&self, call: Option<ModeCall>, path: &str, mode: u32) -> Result<()> {
+        let call = call.ok_or(FileSystemError::ReadOnly)?;
+        error_from_c(call(self.instance, to_c(path)?.as_ptr(), mode))
+    }
+}
+
+impl<S: VTableSource> Default for CPlugin<S> {
+    fn default() -> Self {
+        let vtable = S::vtable();
+        assert_eq!(
+            vtable.abi_version, VTABLE_ABI_VERSION,
+            "C plugin was built against a different agfs_ffi.h"
+        );
+        let instance = vtable.create.map_or(ptr::null_mut(), |create| create());
+        Self {
+            vtable,
+            instance,
+            name: copy_str(vtable.name),
+            readme: copy_str(vtable.readme),
+            _source: PhantomData,
+        }
+    }
+}
+
+impl<S> Drop for CPlugin<S> {
+    fn drop(&mut self) {
+        if let Some(destroy) = self.vtable.destroy {
+            destroy(self.instance);
+        }
+    }
+}
+
+impl<S: VTableSource> FileSystem for CPlugin<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn readme(&self) -> &str {
+        if self.readme.is_empty() {
+            "# Plugin\n\nNo documentation provided."
+        } else {
+            &self.readme
+        }
+    }
+
+    fn validate(&self, config: &str) -> Result<()> {
+        match self.vtable.validate {
+            Some(validate) => error_from_c(validate(self.instance, to_c(config)?.as_ptr())),
+            None => Ok(()),
+        }
+    }
+
+    fn initialize(&mut self, config: &str) -> Result<()> {
+        match self.vtable.initialize {
+            Some(initialize) => error_from_c(initialize(self.instance, to_c(config)?.as_ptr())),
+            None => Ok(()),
+        }
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        match self.vtable.shutdown {
+            Some(shutdown) => error_from_c(shutdown(self.instance)),
+            None => Ok(()),
+        }
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<String> {
+        let read = self.vtable.read.ok_or(FileSystemError::NotFound)?;
+        let mut data: *const c_char = ptr::null();
+        let mut len: i64 = 0;
+        error_from_c(read(
+            self.instance,
+            to_c(path)?.as_ptr(),
+            offset,
+            size,
+            &mut data,
+            &mut len,
+        ))?;
+        if data.is_null() || len <= 0 {
+            return Ok(String::new());
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, len as usize) };
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| FileSystemError::IoError("read returned non-UTF-8 data".to_string()))
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        let stat = self.vtable.stat.ok_or(FileSystemError::NotFound)?;
+        // The strings belong to the C plugin; FileInfoC's Drop must not free them
+        let mut info = ManuallyDrop::new(FileInfoC::empty());
+        error_from_c(stat(self.instance, to_c(path)?.as_ptr(), &mut *info))?;
+        Ok(unsafe { file_info_from_c(&info) })
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let readdir = self.vtable.readdir.ok_or(FileSystemError::NotFound)?;
+        let mut entries: Vec<FileInfo> = Vec::new();
+        error_from_c(readdir(
+            self.instance,
+            to_c(path)?.as_ptr(),
+            collect_entry,
+            &mut entries as *mut Vec<FileInfo> as *mut c_void,
+        ))?;
+        Ok(entries)
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        let write = self.vtable.write.ok_or(FileSystemError::ReadOnly)?;
+        error_from_c(write(
+            self.instance,
+            to_c(path)?.as_ptr(),
+            data.as_ptr() as *const c_char,
+            data.len() as i64,
+        ))
+    }
+
+    fn create(&self, path: &str) -> Result<()> {
+        self.path_call(self.vtable.create_file, path)
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.mode_call(self.vtable.mkdir, path, mode)
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.path_call(self.vtable.remove, path)
+    }
+
+    fn remove_all(&self, path: &str) -> Result<()> {
+        self.path_call(self.vtable.remove_all, path)
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        let rename = self.vtable.rename.ok_or(FileSystemError::ReadOnly)?;
+        error_from_c(rename(
+            self.instance,
+            to_c(old_path)?.as_ptr(),
+            to_c(new_path)?.as_ptr(),
+        ))
+    }
+
+    fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.mode_call(self.vtable.chmod, path, mode)
+    }
+}
+
+/// Export a C plugin's vtable as an agfs plugin
+///
+/// `$vtable_fn` is the C function returning `const AgfsPluginVTable*`.
+#[macro_export]
+macro_rules! export_c_plugin {
+    ($vtable_fn:ident) => {
+        extern "C" {
+            fn $vtable_fn() -> *const $crate::cshim::AgfsPluginVTable;
+        }
+
+        #[doc(hidden)]
+        pub struct AgfsCVTable;
+
+        impl $crate::cshim::VTableSource for AgfsCVTable {
+            fn vtable() -> &'static $crate::cshim::AgfsPluginVTable {
+                unsafe { &*$vtable_fn() }
+            }
+        }
+
+        $crate::export_plugin!($crate::cshim::CPlugin<AgfsCVTable>);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    extern "C" fn read(
+        _instance: *mut c_void,
+        path: *const c_char,
+        _offset: i64,
+        _size: i64,
+        data: *mut *const c_char,
+        len: *mut i64,
+    ) -> *const c_char {
+        if unsafe { CStr::from_ptr(path) }.to_bytes() != b"/hello" {
+            return c"not found".as_ptr();
+        }
+        unsafe {
+            *data = b"hi".as_ptr() as *const c_char;
+            *len = 2;
+        }
+        ptr::null()
+    }
+
+    extern "C" fn readdir(
+        _instance: *mut c_void,
+        _path: *const c_char,
+        emit: DirEmit,
+        cookie: *mut c_void,
+    ) -> *const c_char {
+        let mut info = ManuallyDrop::new(FileInfoC::empty());
+        info.name = c"hello".as_ptr();
+        info.size = 2;
+        info.mode = 0o644;
+        emit(cookie, &*info);
+        ptr::null()
+    }
+
+    static VTABLE: AgfsPluginVTable = AgfsPluginVTable {
+        abi_version: VTABLE_ABI_VERSION,
+        name: c"vt".as_ptr(),
+        readme: ptr::null(),
+        create: None,
+        destroy: None,
+        validate: None,
+        initialize: None,
+        shutdown: None,
+        read: Some(read),
+        stat: None,
+        readdir: Some(readdir),
+        write: None,
+        create_file: None,
+        mkdir: None,
+        remove: None,
+        remove_all: None,
+        rename: None,
+        chmod: None,
+    };
+
+    struct Test;
+
+    impl VTableSource for Test {
+        fn vtable() -> &'static AgfsPluginVTable {
+            &VTABLE
+        }
+    }
+
+    #[test]
+    fn test_vtable_plugin() {
+        let fs = CPlugin::<Test>::default();
+        assert_eq!(fs.name(), "vt");
+        assert_eq!(fs.read("/hello", 0, -1).unwrap(), "hi");
+        assert_eq!(fs.read("/nope", 0, -1), Err(FileSystemError::NotFound));
+        let entries = fs.readdir("/").unwrap();
+        assert_eq!((entries[0].name.as_str(), entries[0].size), ("hello", 2));
+        assert_eq!(entries[0].metadata.content, "{}");
+        assert_eq!(fs.mkdir("/d", 0o755), Err(FileSystemError::ReadOnly));
+        assert!(fs.stat("/hello").is_err());
+    }
+}
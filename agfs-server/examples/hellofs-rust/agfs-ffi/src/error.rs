@@ -10,8 +10,12 @@ pub enum FileSystemError {
     NotFound,
     /// Operation not supported (e.g., writes on read-only filesystem)
     ReadOnly,
+    /// The plugin does not implement the operation
+    NotSupported,
     /// Invalid path
     InvalidPath,
+    /// Invalid argument, with the reason
+    InvalidInput(String),
     /// Permission denied
     PermissionDenied,
     /// File or directory already exists
@@ -35,7 +39,9 @@ impl std::fmt::Display for FileSystemError {
             FileSystemError::ReadOnly => {
                 write!(f, "operation not supported: read-only filesystem")
             }
+            FileSystemError::NotSupported => write!(f, "operation not supported"),
             FileSystemError::InvalidPath => write!(f, "invalid path"),
+            FileSystemError::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
             FileSystemError::PermissionDenied => write!(f, "permission denied"),
             FileSystemError::AlreadyExists => write!(f, "file already exists"),
             FileSystemError::NotADirectory => write!(f, "not a directory"),
@@ -49,6 +55,78 @@ impl std::fmt::Display for FileSystemError {
 
 impl std::error::Error for FileSystemError {}
 
+impl FileSystemError {
+    /// Code that identifies the variant across the FFI boundary
+    ///
+    /// Errno names, as in agfs-wasm-ffi's wire format; `Custom` is `EOTHER`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FileSystemError::NotFound => "ENOENT",
+            FileSystemError::ReadOnly => "EROFS",
+            FileSystemError::NotSupported => "ENOSYS",
+            FileSystemError::InvalidPath | FileSystemError::InvalidInput(_) => "EINVAL",
+            FileSystemError::PermissionDenied => "EACCES",
+            FileSystemError::AlreadyExists => "EEXIST",
+            FileSystemError::NotADirectory => "ENOTDIR",
+            FileSystemError::IsADirectory => "EISDIR",
+            FileSystemError::DirectoryNotEmpty => "ENOTEMPTY",
+            FileSystemError::IoError(_) => "EIO",
+            FileSystemError::Custom(_) => "EOTHER",
+        }
+    }
+
+    /// Linux errno value of the variant's code; `Custom` maps to `EIO`
+    pub fn errno(&self) -> i32 {
+        match self {
+            FileSystemError::NotFound => 2,
+            FileSystemError::IoError(_) | FileSystemError::Custom(_) => 5,
+            FileSystemError::PermissionDenied => 13,
+            FileSystemError::AlreadyExists => 17,
+            FileSystemError::NotADirectory => 20,
+            FileSystemError::IsADirectory => 21,
+            FileSystemError::InvalidPath | FileSystemError::InvalidInput(_) => 22,
+            FileSystemError::ReadOnly => 30,
+            FileSystemError::NotSupported => 38,
+            FileSystemError::DirectoryNotEmpty => 39,
+        }
+    }
+
+    /// Encode as `CODE: message`, the form the exports return
+    pub fn to_wire(&self) -> String {
+        match self {
+            FileSystemError::IoError(msg)
+            | FileSystemError::InvalidInput(msg)
+            | FileSystemError::Custom(msg) => {
+                format!("{}: {}", self.code(), msg)
+            }
+            _ => format!("{}: {}", self.code(), self),
+        }
+    }
+
+    /// Decode an error string produced by `to_wire`
+    ///
+    /// Returns `None` if it does not start with a known code.
+    pub fn from_wire(s: &str) -> Option<FileSystemError> {
+        let (code, msg) = s.split_once(": ").unwrap_or((s, ""));
+        let msg = msg.to_string();
+        Some(match code {
+            "ENOENT" => FileSystemError::NotFound,
+            "EROFS" => FileSystemError::ReadOnly,
+            "ENOSYS" => FileSystemError::NotSupported,
+            "EINVAL" if msg == "invalid path" => FileSystemError::InvalidPath,
+            "EINVAL" => FileSystemError::InvalidInput(msg),
+            "EACCES" => FileSystemError::PermissionDenied,
+            "EEXIST" => FileSystemError::AlreadyExists,
+            "ENOTDIR" => FileSystemError::NotADirectory,
+            "EISDIR" => FileSystemError::IsADirectory,
+            "ENOTEMPTY" => FileSystemError::DirectoryNotEmpty,
+            "EIO" => FileSystemError::IoError(msg),
+            "EOTHER" => FileSystemError::Custom(msg),
+            _ => return None,
+        })
+    }
+}
+
 impl From<std::io::Error> for FileSystemError {
     fn from(err: std::io::Error) -> Self {
         FileSystemError::IoError(err.to_string())
@@ -72,6 +150,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_wire_round_trip() {
+        for e in [
+            FileSystemError::NotFound,
+            FileSystemError::ReadOnly,
+            FileSystemError::NotSupported,
+            FileSystemError::InvalidPath,
+            FileSystemError::InvalidInput("bad offset".to_string()),
+            FileSystemError::PermissionDenied,
+            FileSystemError::AlreadyExists,
+            FileSystemError::NotADirectory,
+            FileSystemError::IsADirectory,
+            FileSystemError::DirectoryNotEmpty,
+            FileSystemError::IoError("disk: gone".to_string()),
+            FileSystemError::Custom("EEXIST: looks coded".to_string()),
+        ] {
+            let wire = e.to_wire();
+            assert_eq!(FileSystemError::from_wire(&wire), Some(e), "{}", wire);
+        }
+        assert_eq!(
+            FileSystemError::DirectoryNotEmpty.to_wire(),
+            "ENOTEMPTY: directory not empty"
+        );
+        assert_eq!(FileSystemError::DirectoryNotEmpty.errno(), 39);
+        assert_eq!(FileSystemError::NotSupported.errno(), 38);
+        assert_eq!(FileSystemError::from_wire("file not found"), None);
+    }
+
     #[test]
     fn test_io_error_conversion() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "test");
@@ -2,7 +2,13 @@
 //!
 //! This module handles all C interop safely. All unsafe code is contained here.
 
+// The helpers below are only called from the generated `extern "C"` exports,
+// whose pointer arguments come straight from the host.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 use crate::filesystem::FileSystem;
+use crate::path::PathPolicy;
+use crate::range::validate_offset;
 use crate::types::FileInfo;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_void};
@@ -12,14 +18,30 @@ use std::sync::Mutex;
 /// C-compatible FileInfo structure
 #[repr(C)]
 pub struct FileInfoC {
-    name: *const c_char,
-    size: i64,
-    mode: u32,
-    mod_time: i64,
-    is_dir: c_int,
-    meta_name: *const c_char,
-    meta_type: *const c_char,
-    meta_content: *const c_char,
+    pub(crate) name: *const c_char,
+    pub(crate) size: i64,
+    pub(crate) mode: u32,
+    pub(crate) mod_time: i64,
+    pub(crate) is_dir: c_int,
+    pub(crate) meta_name: *const c_char,
+    pub(crate) meta_type: *const c_char,
+    pub(crate) meta_content: *const c_char,
+}
+
+impl FileInfoC {
+    /// An entry with no strings, for C code to fill in
+    pub(crate) fn empty() -> Self {
+        FileInfoC {
+            name: ptr::null(),
+            size: 0,
+            mode: 0,
+            mod_time: 0,
+            is_dir: 0,
+            meta_name: ptr::null(),
+            meta_type: ptr::null(),
+            meta_content: ptr::null(),
+        }
+    }
 }
 
 /// C-compatible array of FileInfo structures
@@ -78,6 +100,7 @@ pub struct PluginWrapper<T: FileSystem> {
     pub fs: Mutex<T>,
     pub name: CString,
     pub readme: CString,
+    pub path_policy: Mutex<PathPolicy>,
 }
 
 impl<T: FileSystem> PluginWrapper<T> {
@@ -85,15 +108,23 @@ impl<T: FileSystem> PluginWrapper<T> {
         let fs = T::default();
         let name = CString::new(fs.name()).expect("plugin name contains null byte");
         let readme = CString::new(fs.readme()).expect("readme contains null byte");
+        let path_policy = Mutex::new(fs.path_policy());
 
         Self {
             fs: Mutex::new(fs),
             name,
             readme,
+            path_policy,
         }
     }
 }
 
+impl<T: FileSystem> Default for PluginWrapper<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Helper to safely convert C string to Rust str
 unsafe fn c_str_to_str<'a>(ptr: *const c_char) -> Result<&'a str, &'static str> {
     if ptr.is_null() {
@@ -102,6 +133,25 @@ unsafe fn c_str_to_str<'a>(ptr: *const c_char) -> Result<&'a str, &'static str>
     CStr::from_ptr(ptr).to_str().map_err(|_| "invalid UTF-8")
 }
 
+/// Helper to read a request path from C and canonicalize it
+///
+/// The bytes are decoded under the plugin's `PathPolicy`. Paths that try to
+/// climb out of the mount (`..`) are rejected here, so plugins never see
+/// them.
+unsafe fn c_path_to_string<T: FileSystem>(
+    plugin: *mut c_void,
+    ptr: *const c_char,
+) -> Result<String, &'static str> {
+    if ptr.is_null() {
+        return Err("null pointer");
+    }
+    let wrapper = &*(plugin as *const PluginWrapper<T>);
+    let policy = *wrapper.path_policy.lock().unwrap();
+    policy
+        .apply(CStr::from_ptr(ptr).to_bytes())
+        .map_err(|_| "invalid path")
+}
+
 /// Helper to create error C string
 fn error_to_c_string(msg: &str) -> *const c_char {
     CString::new(msg)
@@ -136,7 +186,7 @@ pub fn plugin_validate<T: FileSystem>(
         let fs = wrapper.fs.lock().unwrap();
         match fs.validate(config) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -159,9 +209,11 @@ pub fn plugin_initialize<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let mut fs = wrapper.fs.lock().unwrap();
-        match fs.initialize(config) {
+        let result = fs.initialize(config);
+        *wrapper.path_policy.lock().unwrap() = fs.path_policy();
+        match result {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -176,7 +228,7 @@ pub fn plugin_shutdown<T: FileSystem>(plugin: *mut c_void) -> *const c_char {
         let mut fs = wrapper.fs.lock().unwrap();
         match fs.shutdown() {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -196,7 +248,7 @@ pub fn fs_read<T: FileSystem>(
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => {
                 *out_len = -1;
@@ -208,7 +260,11 @@ pub fn fs_read<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.read(path_str, offset, size) {
+        if let Err(e) = validate_offset(offset) {
+            *out_len = -1;
+            return error_to_c_string(&e.to_wire());
+        }
+        match fs.read(&path_str, offset, size) {
             Ok(content) => {
                 *out_len = content.len() as c_int;
                 CString::new(content)
@@ -217,7 +273,7 @@ pub fn fs_read<T: FileSystem>(
             }
             Err(e) => {
                 *out_len = -1;
-                error_to_c_string(&e.to_string())
+                error_to_c_string(&e.to_wire())
             }
         }
     }
@@ -229,7 +285,7 @@ pub fn fs_stat<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *mut
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(_) => return ptr::null_mut(),
         }
@@ -238,7 +294,7 @@ pub fn fs_stat<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *mut
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.stat(path_str) {
+        match fs.stat(&path_str) {
             Ok(info) => Box::into_raw(Box::new(FileInfoC::from(&info))),
             Err(_) => ptr::null_mut(),
         }
@@ -258,7 +314,7 @@ pub fn fs_readdir<T: FileSystem>(
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(_) => {
                 *out_count = -1;
@@ -270,7 +326,7 @@ pub fn fs_readdir<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.readdir(path_str) {
+        match fs.readdir(&path_str) {
             Ok(files) => {
                 let count = files.len();
                 let items: Vec<FileInfoC> = files.iter().map(FileInfoC::from).collect();
@@ -301,7 +357,7 @@ pub fn fs_create<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -310,9 +366,31 @@ pub fn fs_create<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.create(path_str) {
+        match fs.create(&path_str) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_create_exclusive<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.create_exclusive(&path_str) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -327,7 +405,7 @@ pub fn fs_mkdir<T: FileSystem>(
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -336,9 +414,9 @@ pub fn fs_mkdir<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.mkdir(path_str, mode) {
+        match fs.mkdir(&path_str, mode) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -349,7 +427,7 @@ pub fn fs_remove<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -358,9 +436,9 @@ pub fn fs_remove<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.remove(path_str) {
+        match fs.remove(&path_str) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -371,7 +449,7 @@ pub fn fs_remove_all<T: FileSystem>(plugin: *mut c_void, path: *const c_char) ->
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -380,9 +458,9 @@ pub fn fs_remove_all<T: FileSystem>(plugin: *mut c_void, path: *const c_char) ->
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.remove_all(path_str) {
+        match fs.remove_all(&path_str) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -398,7 +476,7 @@ pub fn fs_write<T: FileSystem>(
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -414,9 +492,53 @@ pub fn fs_write<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.write(path_str, data_slice) {
+        match fs.write(&path_str, data_slice) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_write_at<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    offset: i64,
+    data: *const c_char,
+    data_len: c_int,
+    out_written: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+    if offset < 0 {
+        return error_to_c_string("negative offset");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    let data_slice = unsafe {
+        if data.is_null() || data_len < 0 {
+            return error_to_c_string("invalid data");
+        }
+        std::slice::from_raw_parts(data as *const u8, data_len as usize)
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.write_at(&path_str, offset, data_slice) {
+            Ok(written) => {
+                if !out_written.is_null() {
+                    *out_written = written;
+                }
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -431,14 +553,14 @@ pub fn fs_rename<T: FileSystem>(
     }
 
     let old_path_str = unsafe {
-        match c_str_to_str(old_path) {
+        match c_path_to_string::<T>(plugin, old_path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
     };
 
     let new_path_str = unsafe {
-        match c_str_to_str(new_path) {
+        match c_path_to_string::<T>(plugin, new_path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -447,9 +569,9 @@ pub fn fs_rename<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.rename(old_path_str, new_path_str) {
+        match fs.rename(&old_path_str, &new_path_str) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -464,7 +586,107 @@ pub fn fs_chmod<T: FileSystem>(
     }
 
     let path_str = unsafe {
-        match c_str_to_str(path) {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.chmod(&path_str, mode) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_truncate<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    size: i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+    if size < 0 {
+        return error_to_c_string("negative size");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.truncate(&path_str, size) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_flush<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.flush(&path_str) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_fsync<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.fs.lock().unwrap();
+        match fs.fsync(&path_str) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_wire()),
+        }
+    }
+}
+
+pub fn fs_set_times<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    atime: i64,
+    mtime: i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_path_to_string::<T>(plugin, path) {
             Ok(s) => s,
             Err(e) => return error_to_c_string(e),
         }
@@ -473,9 +695,9 @@ pub fn fs_chmod<T: FileSystem>(
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
         let fs = wrapper.fs.lock().unwrap();
-        match fs.chmod(path_str, mode) {
+        match fs.set_times(&path_str, atime, mtime) {
             Ok(_) => success(),
-            Err(e) => error_to_c_string(&e.to_string()),
+            Err(e) => error_to_c_string(&e.to_wire()),
         }
     }
 }
@@ -1,6 +1,7 @@
 //! FileSystem trait definition
 
 use crate::error::{FileSystemError, Result};
+use crate::path::PathPolicy;
 use crate::types::FileInfo;
 
 /// Main trait that all filesystem plugins must implement
@@ -73,6 +74,13 @@ pub trait FileSystem: Default + Send + Sync {
         Ok(())
     }
 
+    /// How request paths are decoded before they reach this plugin
+    ///
+    /// Queried after `initialize`, so it may depend on the config.
+    fn path_policy(&self) -> PathPolicy {
+        PathPolicy::default()
+    }
+
     /// Shutdown the plugin
     fn shutdown(&mut self) -> Result<()> {
         Ok(())
@@ -120,6 +128,18 @@ pub trait FileSystem: Default + Send + Sync {
         Err(FileSystemError::ReadOnly)
     }
 
+    /// Write data at `offset` without replacing the rest of the file
+    ///
+    /// Returns the number of bytes written. The default handles offset 0
+    /// with `write`, replacing the file, and returns ReadOnly otherwise.
+    fn write_at(&self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        if offset != 0 {
+            return Err(FileSystemError::ReadOnly);
+        }
+        self.write(path, data)?;
+        Ok(data.len() as i64)
+    }
+
     /// Create a new file
     ///
     /// Default implementation returns ReadOnly error.
@@ -127,6 +147,19 @@ pub trait FileSystem: Default + Send + Sync {
         Err(FileSystemError::ReadOnly)
     }
 
+    /// Create a new file, failing with AlreadyExists if the path exists
+    ///
+    /// Default implementation checks with `stat` and then calls `create`.
+    /// Calls into a plugin are serialized, so this is atomic unless the
+    /// backing store can change underneath the plugin; override it then.
+    fn create_exclusive(&self, path: &str) -> Result<()> {
+        match self.stat(path) {
+            Ok(_) => Err(FileSystemError::AlreadyExists),
+            Err(FileSystemError::NotFound) => self.create(path),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Create a directory
     ///
     /// Default implementation returns ReadOnly error.
@@ -157,10 +190,40 @@ pub trait FileSystem: Default + Send + Sync {
 
     /// Change file or directory permissions
     ///
-    /// Default implementation returns ReadOnly error.
+    /// Default implementation returns NotSupported error.
     fn chmod(&self, _path: &str, _mode: u32) -> Result<()> {
+        Err(FileSystemError::NotSupported)
+    }
+
+    /// Shrink or zero-extend a file to `size` bytes
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn truncate(&self, _path: &str, _size: i64) -> Result<()> {
         Err(FileSystemError::ReadOnly)
     }
+
+    /// Set access and modification times, in Unix seconds
+    ///
+    /// Default implementation returns NotSupported error.
+    fn set_times(&self, _path: &str, _atime: i64, _mtime: i64) -> Result<()> {
+        Err(FileSystemError::NotSupported)
+    }
+
+    /// Push buffered writes to `path` to the backend
+    ///
+    /// Called when a client closes or flushes a file. Default
+    /// implementation does nothing.
+    fn flush(&self, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Make writes to `path` durable before returning
+    ///
+    /// Called when a client asks for fsync. Default implementation does
+    /// nothing.
+    fn fsync(&self, _path: &str) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -202,7 +265,7 @@ mod tests {
 
     #[test]
     fn test_filesystem_trait() {
-        let fs = TestFS::default();
+        let fs = TestFS;
         assert_eq!(fs.name(), "test-fs");
         assert!(fs.validate("{}").is_ok());
 
@@ -218,9 +281,11 @@ mod tests {
 
     #[test]
     fn test_default_readonly_operations() {
-        let fs = TestFS::default();
+        let fs = TestFS;
         assert!(matches!(fs.write("/test", b"data"), Err(FileSystemError::ReadOnly)));
         assert!(matches!(fs.create("/new"), Err(FileSystemError::ReadOnly)));
         assert!(matches!(fs.mkdir("/dir", 0o755), Err(FileSystemError::ReadOnly)));
+        assert!(matches!(fs.create_exclusive("/test"), Err(FileSystemError::AlreadyExists)));
+        assert!(matches!(fs.create_exclusive("/new"), Err(FileSystemError::ReadOnly)));
     }
 }
@@ -41,23 +41,34 @@
 //! // export_plugin!(MyFS);
 //! ```
 
+pub mod async_fs;
+pub mod cshim;
 pub mod error;
 pub mod ffi;
 pub mod filesystem;
+pub mod path;
+pub mod range;
 pub mod types;
+pub mod walk;
+
+pub use agfs_path::nfc;
 
 /// Prelude module for convenient imports
 pub mod prelude {
+    pub use crate::async_fs::{AsyncAdapter, AsyncFileSystem};
     pub use crate::error::{FileSystemError, Result};
     pub use crate::filesystem::FileSystem;
+    pub use crate::range::slice_range;
     pub use crate::types::{FileInfo, FileMetadata};
     pub use crate::export_plugin;
 }
 
 // Re-export main types
+pub use async_fs::{AsyncAdapter, AsyncFileSystem, ParkRuntime, Runtime};
 pub use error::{FileSystemError, Result};
 pub use filesystem::FileSystem;
 pub use types::{FileInfo, FileMetadata};
+pub use walk::{ParallelWalk, WalkEntry};
 
 /// Macro to export a FileSystem implementation as a C-compatible plugin
 ///
@@ -173,6 +184,14 @@ macro_rules! export_plugin {
             $crate::ffi::fs_create::<$fs_type>(plugin, path)
         }
 
+        #[no_mangle]
+        pub extern "C" fn FSCreateExclusive(
+            plugin: *mut c_void,
+            path: *const c_char,
+        ) -> *const c_char {
+            $crate::ffi::fs_create_exclusive::<$fs_type>(plugin, path)
+        }
+
         #[no_mangle]
         pub extern "C" fn FSMkdir(
             plugin: *mut c_void,
@@ -205,6 +224,18 @@ macro_rules! export_plugin {
             $crate::ffi::fs_write::<$fs_type>(plugin, path, data, data_len)
         }
 
+        #[no_mangle]
+        pub extern "C" fn FSWriteAt(
+            plugin: *mut c_void,
+            path: *const c_char,
+            offset: i64,
+            data: *const c_char,
+            data_len: c_int,
+            out_written: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::fs_write_at::<$fs_type>(plugin, path, offset, data, data_len, out_written)
+        }
+
         #[no_mangle]
         pub extern "C" fn FSRename(
             plugin: *mut c_void,
@@ -222,5 +253,34 @@ macro_rules! export_plugin {
         ) -> *const c_char {
             $crate::ffi::fs_chmod::<$fs_type>(plugin, path, mode)
         }
+
+        #[no_mangle]
+        pub extern "C" fn FSTruncate(
+            plugin: *mut c_void,
+            path: *const c_char,
+            size: i64,
+        ) -> *const c_char {
+            $crate::ffi::fs_truncate::<$fs_type>(plugin, path, size)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSSetTimes(
+            plugin: *mut c_void,
+            path: *const c_char,
+            atime: i64,
+            mtime: i64,
+        ) -> *const c_char {
+            $crate::ffi::fs_set_times::<$fs_type>(plugin, path, atime, mtime)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSFlush(plugin: *mut c_void, path: *const c_char) -> *const c_char {
+            $crate::ffi::fs_flush::<$fs_type>(plugin, path)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSFsync(plugin: *mut c_void, path: *const c_char) -> *const c_char {
+            $crate::ffi::fs_fsync::<$fs_type>(plugin, path)
+        }
     };
 }
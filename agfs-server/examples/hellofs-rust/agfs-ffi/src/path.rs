@@ -0,0 +1,42 @@
+//! Request path canonicalization
+//!
+//! The export glue runs every path through `canonicalize` before the
+//! plugin sees it. How raw path bytes are decoded first is up to the
+//! plugin's `PathPolicy`. The rules live in the `agfs-path` crate, shared
+//! with agfs-wasm-ffi.
+
+use crate::error::{FileSystemError, Result};
+
+pub use agfs_path::{InvalidPath, InvalidUtf8, Normalization, PathPolicy};
+
+impl From<InvalidPath> for FileSystemError {
+    fn from(err: InvalidPath) -> Self {
+        FileSystemError::InvalidInput(err.0.to_string())
+    }
+}
+
+/// Bring a request path into canonical form, see `agfs_path::canonicalize`
+pub fn canonicalize(path: &str) -> Result<String> {
+    Ok(agfs_path::canonicalize(path)?)
+}
+
+/// Join a canonical request path below a host prefix, see `agfs_path::join`
+pub fn join(prefix: &str, path: &str) -> Result<String> {
+    Ok(agfs_path::join(prefix, path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_keep_message() {
+        assert_eq!(
+            canonicalize("/a/../b"),
+            Err(FileSystemError::InvalidInput(
+                "path traversal is not allowed".to_string()
+            ))
+        );
+        assert_eq!(join("/srv", "x").unwrap(), "/srv/x");
+    }
+}
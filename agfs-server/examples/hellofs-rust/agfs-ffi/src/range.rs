@@ -0,0 +1,59 @@
+//! Read range helpers
+//!
+//! Every plugin that serves reads from an in-memory buffer needs the same
+//! offset/size clamping; `slice_range` does it without overflow or panics.
+
+use crate::error::{FileSystemError, Result};
+
+/// Check the offset of a read request
+///
+/// The export glue rejects negative offsets before the plugin is called.
+pub fn validate_offset(offset: i64) -> Result<()> {
+    if offset < 0 {
+        return Err(FileSystemError::Custom("negative read offset".to_string()));
+    }
+    Ok(())
+}
+
+/// The part of `data` a read of `size` bytes at `offset` returns
+///
+/// A negative `size` reads to the end. Offsets past the end (or negative
+/// ones) yield an empty slice, and `offset + size` may exceed `i64::MAX`.
+pub fn slice_range<T>(data: &[T], offset: i64, size: i64) -> &[T] {
+    if offset < 0 || offset as u64 >= data.len() as u64 {
+        return &[];
+    }
+    let start = offset as usize;
+    let end = if size < 0 {
+        data.len()
+    } else {
+        start
+            .saturating_add(usize::try_from(size).unwrap_or(usize::MAX))
+            .min(data.len())
+    };
+    &data[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_range() {
+        let data = b"0123456789";
+        assert_eq!(slice_range(data, 0, -1), data);
+        assert_eq!(slice_range(data, 2, 3), b"234");
+        assert_eq!(slice_range(data, 8, 100), b"89");
+        assert_eq!(slice_range(data, 8, i64::MAX), b"89");
+        assert!(slice_range(data, 20, 4).is_empty());
+        assert!(slice_range(data, -5, 4).is_empty());
+        assert!(slice_range(data, i64::MAX, i64::MAX).is_empty());
+        assert!(slice_range(data, 3, 0).is_empty());
+    }
+
+    #[test]
+    fn test_validate_offset() {
+        assert!(validate_offset(0).is_ok());
+        assert!(validate_offset(-1).is_err());
+    }
+}
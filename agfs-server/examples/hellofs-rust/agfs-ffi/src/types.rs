@@ -63,6 +63,32 @@ impl FileInfo {
         }
     }
 
+    /// Build a FileInfo from host metadata
+    pub fn from_metadata(name: impl Into<String>, metadata: &std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o7777
+        };
+        #[cfg(not(unix))]
+        let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+
+        let mod_time = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+
+        Self {
+            name: name.into(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() as i64 },
+            mode,
+            mod_time,
+            is_dir: metadata.is_dir(),
+            metadata: FileMetadata::default(),
+        }
+    }
+
     /// Set the modification time
     pub fn with_mod_time(mut self, mod_time: i64) -> Self {
         self.mod_time = mod_time;
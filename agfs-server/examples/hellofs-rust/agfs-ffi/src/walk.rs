@@ -0,0 +1,401 @@
+//! Parallel host directory scanning
+//!
+//! Native plugins that mirror a host tree usually start by indexing it.
+//! `ParallelWalk` lists and stats directories on a pool of worker threads
+//! while handing entries back in a stable depth-first order, so the caller
+//! can stream results as soon as the prefix of the tree is known.
+//!
+//! With the `rayon` feature every listing is a task on a rayon pool of
+//! that many threads, and the entries of one directory are stat'ed in
+//! parallel too, which is what keeps very wide directories fast.
+
+use crate::types::FileInfo;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// A single entry produced by the walk
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    /// Full host path of the entry
+    pub path: PathBuf,
+    /// Depth below the walk root (direct children are depth 1)
+    pub depth: usize,
+    /// File information gathered from `symlink_metadata`
+    pub info: FileInfo,
+}
+
+/// Builder for a parallel directory walk
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use agfs_ffi::walk::ParallelWalk;
+///
+/// for entry in ParallelWalk::new("/srv/data").threads(8).run() {
+///     let entry = entry.unwrap();
+///     println!("{} {}", entry.path.display(), entry.info.size);
+/// }
+/// ```
+pub struct ParallelWalk {
+    root: PathBuf,
+    threads: usize,
+    max_depth: Option<usize>,
+}
+
+impl ParallelWalk {
+    /// Walk everything below `root` (the root itself is not yielded)
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            threads: thread::available_parallelism().map_or(4, |n| n.get()),
+            max_depth: None,
+        }
+    }
+
+    /// Number of worker threads (at least one)
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Do not descend below this depth
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Start the walk; entries are streamed through the returned iterator
+    pub fn run(self) -> WalkIter {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::from([Job {
+                    id: 0,
+                    path: self.root,
+                    depth: 0,
+                }]),
+                results: HashMap::new(),
+                next_id: 1,
+                #[cfg(not(feature = "rayon"))]
+                in_flight: 0,
+                stopped: false,
+            }),
+            work: Condvar::new(),
+            done: Condvar::new(),
+        });
+
+        WalkIter {
+            workers: Workers::start(&shared, self.threads, self.max_depth),
+            shared,
+            stack: Vec::new(),
+            pending: Some(0),
+        }
+    }
+}
+
+/// Threads listing directories for a `WalkIter`
+#[cfg(not(feature = "rayon"))]
+struct Workers(Vec<thread::JoinHandle<()>>);
+
+#[cfg(not(feature = "rayon"))]
+impl Workers {
+    fn start(shared: &Arc<Shared>, threads: usize, max_depth: Option<usize>) -> Self {
+        Self(
+            (0..threads)
+                .map(|_| {
+                    let shared = Arc::clone(shared);
+                    thread::spawn(move || worker(&shared, max_depth))
+                })
+                .collect(),
+        )
+    }
+
+    fn join(&mut self) {
+        for handle in self.0.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Pool running the listing tasks of a `WalkIter`
+#[cfg(feature = "rayon")]
+struct Workers(Option<rayon::ThreadPool>);
+
+#[cfg(feature = "rayon")]
+impl Workers {
+    fn start(shared: &Arc<Shared>, threads: usize, max_depth: Option<usize>) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("agfs-walk-{}", i))
+            .build()
+            .expect("failed to start walk threads");
+        let job = shared.state.lock().unwrap().queue.pop_front();
+        if let Some(job) = job {
+            let shared = Arc::clone(shared);
+            pool.spawn(move || list_task(shared, job, max_depth));
+        }
+        Self(Some(pool))
+    }
+
+    fn join(&mut self) {
+        // Queued tasks see `stopped` and return; dropping the pool lets its
+        // threads exit once they have
+        self.0.take();
+    }
+}
+
+struct Job {
+    id: u64,
+    path: PathBuf,
+    depth: usize,
+}
+
+struct Child {
+    entry: WalkEntry,
+    /// Listing id when this child is a directory that will be descended into
+    dir_id: Option<u64>,
+}
+
+struct State {
+    queue: VecDeque<Job>,
+    results: HashMap<u64, io::Result<Vec<Child>>>,
+    next_id: u64,
+    /// Jobs taken off `queue` by worker threads and not finished yet
+    #[cfg(not(feature = "rayon"))]
+    in_flight: usize,
+    stopped: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    work: Condvar,
+    done: Condvar,
+}
+
+#[cfg(not(feature = "rayon"))]
+fn worker(shared: &Shared, max_depth: Option<usize>) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if state.stopped {
+                    return;
+                }
+                if let Some(job) = state.queue.pop_front() {
+                    state.in_flight += 1;
+                    break job;
+                }
+                if state.in_flight == 0 {
+                    // Nothing queued and nobody can enqueue more: walk finished
+                    shared.work.notify_all();
+                    return;
+                }
+                state = shared.work.wait(state).unwrap();
+            }
+        };
+
+        let listing = list_dir(&job.path, job.depth + 1);
+
+        let mut state = shared.state.lock().unwrap();
+        let jobs = finish_listing(&mut state, job.id, listing, max_depth);
+        state.queue.extend(jobs);
+        state.in_flight -= 1;
+        shared.work.notify_all();
+        shared.done.notify_all();
+    }
+}
+
+/// List one directory, then spawn the listings of its subdirectories
+#[cfg(feature = "rayon")]
+fn list_task(shared: Arc<Shared>, job: Job, max_depth: Option<usize>) {
+    if shared.state.lock().unwrap().stopped {
+        return;
+    }
+    let listing = list_dir(&job.path, job.depth + 1);
+
+    let jobs = {
+        let mut state = shared.state.lock().unwrap();
+        finish_listing(&mut state, job.id, listing, max_depth)
+    };
+    shared.done.notify_all();
+    for job in jobs {
+        let shared = Arc::clone(&shared);
+        rayon::spawn(move || list_task(shared, job, max_depth));
+    }
+}
+
+/// Store the listing of job `id` for the iterator and return the jobs
+/// listing the subdirectories it found
+fn finish_listing(
+    state: &mut State,
+    id: u64,
+    listing: io::Result<Vec<WalkEntry>>,
+    max_depth: Option<usize>,
+) -> Vec<Job> {
+    let mut jobs = Vec::new();
+    let result = listing.map(|entries| {
+        entries
+            .into_iter()
+            .map(|entry| {
+                let descend = entry.info.is_dir && max_depth.is_none_or(|d| entry.depth < d);
+                let dir_id = descend.then(|| {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    jobs.push(Job {
+                        id,
+                        path: entry.path.clone(),
+                        depth: entry.depth,
+                    });
+                    id
+                });
+                Child { entry, dir_id }
+            })
+            .collect()
+    });
+    state.results.insert(id, result);
+    jobs
+}
+
+fn list_dir(dir: &Path, depth: usize) -> io::Result<Vec<WalkEntry>> {
+    let dirents = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    #[cfg(feature = "rayon")]
+    let dirents = dirents.into_par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let dirents = dirents.into_iter();
+    let mut entries = dirents
+        .map(|dirent| {
+            let path = dirent.path();
+            let metadata = fs::symlink_metadata(&path)?;
+            let name = dirent.file_name().to_string_lossy().into_owned();
+            Ok(WalkEntry {
+                info: FileInfo::from_metadata(name, &metadata),
+                path,
+                depth,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+    Ok(entries)
+}
+
+/// Iterator over a running walk, in sorted depth-first pre-order
+///
+/// Dropping the iterator stops the workers early.
+pub struct WalkIter {
+    shared: Arc<Shared>,
+    workers: Workers,
+    /// Remaining children per open directory, reversed so `pop` yields in order
+    stack: Vec<Vec<Child>>,
+    /// Listing the iterator must wait for before yielding anything else
+    pending: Option<u64>,
+}
+
+impl WalkIter {
+    fn take_listing(&self, id: u64) -> io::Result<Vec<Child>> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(result) = state.results.remove(&id) {
+                return result;
+            }
+            state = self.shared.done.wait(state).unwrap();
+        }
+    }
+}
+
+impl Iterator for WalkIter {
+    type Item = io::Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(id) = self.pending.take() {
+            match self.take_listing(id) {
+                Ok(mut children) => {
+                    children.reverse();
+                    self.stack.push(children);
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        loop {
+            let top = self.stack.last_mut()?;
+            match top.pop() {
+                Some(child) => {
+                    self.pending = child.dir_id;
+                    return Some(Ok(child.entry));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WalkIter {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.work.notify_all();
+        self.workers.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("agfs-walk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("b/nested")).unwrap();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/one.txt"), b"1").unwrap();
+        fs::write(root.join("b/nested/two.txt"), b"22").unwrap();
+        fs::write(root.join("c.txt"), b"333").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let root = scratch_tree("order");
+        let paths: Vec<String> = ParallelWalk::new(&root)
+            .threads(4)
+            .run()
+            .map(|e| {
+                let e = e.unwrap();
+                e.path.strip_prefix(&root).unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["a", "a/one.txt", "b", "b/nested", "b/nested/two.txt", "c.txt"]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_max_depth() {
+        let root = scratch_tree("depth");
+        let entries: Vec<WalkEntry> = ParallelWalk::new(&root)
+            .max_depth(1)
+            .run()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.depth == 1));
+        assert_eq!(entries[2].info.size, 3);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_missing_root() {
+        let mut iter = ParallelWalk::new("/nonexistent/agfs-walk").run();
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
@@ -30,24 +30,8 @@ A simple read-only filesystem plugin written in Rust using the AGFS Plugin SDK.
         match path {
             "/hello" => {
                 let content = Self::hello_content();
-                let content_len = content.len() as i64;
-
-                // Handle offset beyond file size
-                if offset >= content_len {
-                    return Ok(String::new());
-                }
-
-                // Calculate actual read length
-                let remaining = content_len - offset;
-                let read_len = if size > 0 && size < remaining {
-                    size
-                } else {
-                    remaining
-                };
-
-                let start = offset as usize;
-                let end = (offset + read_len) as usize;
-                Ok(content[start..end].to_string())
+                let data = slice_range(content.as_bytes(), offset, size);
+                Ok(String::from_utf8_lossy(data).into_owned())
             }
             _ => Err(FileSystemError::NotFound),
         }
@@ -119,7 +103,7 @@ mod tests {
 
     #[test]
     fn test_read_hello_file() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/hello", 0, 100);
         assert!(result.is_ok());
         let content = result.unwrap();
@@ -128,7 +112,7 @@ mod tests {
 
     #[test]
     fn test_read_nonexistent_file() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/nonexistent", 0, 100);
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), FileSystemError::NotFound));
@@ -136,7 +120,7 @@ mod tests {
 
     #[test]
     fn test_stat_root() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.stat("/");
         assert!(result.is_ok());
         let info = result.unwrap();
@@ -145,7 +129,7 @@ mod tests {
 
     #[test]
     fn test_stat_hello() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.stat("/hello");
         assert!(result.is_ok());
         let info = result.unwrap();
@@ -156,7 +140,7 @@ mod tests {
 
     #[test]
     fn test_readdir_root() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.readdir("/");
         assert!(result.is_ok());
         let files = result.unwrap();
@@ -166,7 +150,7 @@ mod tests {
 
     #[test]
     fn test_read_with_offset() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/hello", 6, 100);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "from Rust dynamic library!\n");
@@ -174,7 +158,7 @@ mod tests {
 
     #[test]
     fn test_write_fails() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.write("/hello", b"new content");
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), FileSystemError::ReadOnly));
@@ -182,13 +166,13 @@ mod tests {
 
     #[test]
     fn test_plugin_name() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         assert_eq!(fs.name(), "hellofs-rust");
     }
 
     #[test]
     fn test_plugin_readme() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         assert!(fs.readme().contains("HelloFS Rust Plugin"));
     }
 }
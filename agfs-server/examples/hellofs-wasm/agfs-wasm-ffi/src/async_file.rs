@@ -0,0 +1,315 @@
+//! tokio `AsyncRead + AsyncSeek + AsyncWrite` over a plugin file (feature
+//! `tokio`)
+//!
+//! Lets an application that embeds a `FileSystem` use tokio's combinators
+//! on its files:
+//!
+//! ```ignore
+//! use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+//!
+//! let mut file = FsFile::open(&mut fs, "/data/log")?;
+//! file.seek(SeekFrom::End(0)).await?;
+//! file.write_all(b"entry\n").await?;
+//! ```
+//!
+//! Reads are one ranged `read` each and writes one `write_at`, with no
+//! buffering; wrap the file in `tokio::io::BufReader` or `BufWriter` for
+//! small accesses. Every poll completes at once: the call runs on the
+//! polling task, so a filesystem that blocks should be driven from
+//! `spawn_blocking`.
+
+use crate::filesystem::FileSystem;
+use crate::types::{Error, Result};
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+/// Unbuffered async stream over one file of a `FileSystem`
+///
+/// Writing needs a plugin with `write_at`; on others only writes at offset
+/// 0 succeed, and they replace the file.
+pub struct FsFile<'a, F: ?Sized> {
+    fs: &'a mut F,
+    path: String,
+    pos: u64,
+    // Target of a started seek, until poll_complete reports it
+    seek: Option<u64>,
+}
+
+impl<'a, F: FileSystem + ?Sized> FsFile<'a, F> {
+    /// Open `path`, which must be an existing file, at offset 0
+    pub fn open(fs: &'a mut F, path: &str) -> Result<Self> {
+        if fs.stat(path)?.is_dir {
+            return Err(Error::IsDirectory);
+        }
+        Ok(Self {
+            fs,
+            path: path.to_string(),
+            pos: 0,
+            seek: None,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Current offset in the file
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    fn offset(&self) -> io::Result<i64> {
+        i64::try_from(self.pos)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset too large"))
+    }
+}
+
+impl<F: FileSystem + ?Sized> AsyncRead for FsFile<'_, F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let offset = this.offset()?;
+        let data = this.fs.read(&this.path, offset, buf.remaining() as i64)?;
+        // A plugin returning more than asked is cut to what fits
+        let n = data.len().min(buf.remaining());
+        buf.put_slice(&data[..n]);
+        this.pos += n as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<F: FileSystem + ?Sized> AsyncSeek for FsFile<'_, F> {
+    fn start_seek(self: Pin<&mut Self>, to: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let (base, delta) = match to {
+            SeekFrom::Start(offset) => (0, offset as i128),
+            SeekFrom::Current(delta) => (this.pos as i128, delta as i128),
+            // The size is looked up now, so the end follows earlier writes
+            SeekFrom::End(delta) => {
+                let size = this.fs.stat(&this.path)?.size.max(0);
+                (size as i128, delta as i128)
+            }
+        };
+        let pos = u64::try_from(base + delta).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of file")
+        })?;
+        this.seek = Some(pos);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        if let Some(pos) = this.seek.take() {
+            this.pos = pos;
+        }
+        Poll::Ready(Ok(this.pos))
+    }
+}
+
+impl<F: FileSystem + ?Sized> AsyncWrite for FsFile<'_, F> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let offset = this.offset()?;
+        let written = this.fs.write_at(&this.path, offset, data)?;
+        let n = usize::try_from(written)
+            .ok()
+            .filter(|&n| n <= data.len())
+            .ok_or_else(|| io::Error::other("plugin reported a bad write length"))?;
+        this.pos += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        Poll::Ready(Ok(this.fs.flush(&this.path)?))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::slice_range;
+    use crate::types::FileInfo;
+    use std::cell::Cell;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    struct MemFS {
+        data: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "mem"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            if path != "/f" {
+                return Err(Error::NotFound);
+            }
+            Ok(slice_range(&self.data, offset, size).to_vec())
+        }
+
+        fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+            if path != "/f" {
+                return Err(Error::NotFound);
+            }
+            let end = offset as usize + data.len();
+            if self.data.len() < end {
+                self.data.resize(end, 0);
+            }
+            self.data[offset as usize..end].copy_from_slice(data);
+            Ok(data.len() as i64)
+        }
+
+        fn flush(&mut self, _path: &str) -> Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                "/f" => Ok(FileInfo::file("f", self.data.len() as i64, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_seek_write() {
+        let mut fs = MemFS {
+            data: b"hello world".to_vec(),
+            flushes: 0,
+        };
+        assert_eq!(FsFile::open(&mut fs, "/").err(), Some(Error::IsDirectory));
+        assert_eq!(FsFile::open(&mut fs, "/x").err(), Some(Error::NotFound));
+
+        let mut file = FsFile::open(&mut fs, "/f").unwrap();
+        let mut all = String::new();
+        file.read_to_string(&mut all).await.unwrap();
+        assert_eq!(all, "hello world");
+
+        assert_eq!(file.seek(SeekFrom::Start(6)).await.unwrap(), 6);
+        file.write_all(b"tokio!").await.unwrap();
+        assert_eq!(file.position(), 12);
+        file.shutdown().await.unwrap();
+
+        assert_eq!(file.seek(SeekFrom::End(-6)).await.unwrap(), 6);
+        let mut tail = [0u8; 6];
+        file.read_exact(&mut tail).await.unwrap();
+        assert_eq!(&tail, b"tokio!");
+        assert!(file.seek(SeekFrom::Current(-20)).await.is_err());
+
+        assert_eq!(fs.data, b"hello tokio!");
+        assert_eq!(fs.flushes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_round_trips() {
+        let mut fs = MemFS {
+            data: Vec::new(),
+            flushes: 0,
+        };
+        let mut file = FsFile::open(&mut fs, "/f").unwrap();
+        file.write_all(b"0123456789").await.unwrap();
+
+        // Overwrite in the middle, then extend past the end
+        file.seek(SeekFrom::Start(2)).await.unwrap();
+        file.write_all(b"ab").await.unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 4);
+        file.seek(SeekFrom::End(2)).await.unwrap();
+        file.write_all(b"zz").await.unwrap();
+
+        file.rewind().await.unwrap();
+        let mut all = Vec::new();
+        file.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, b"01ab456789\0\0zz");
+
+        // Reads at and past the end return nothing
+        let mut buf = [0u8; 4];
+        assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+        file.seek(SeekFrom::Current(100)).await.unwrap();
+        assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+
+        file.seek(SeekFrom::Start(8)).await.unwrap();
+        let mut tail = String::new();
+        file.take(2).read_to_string(&mut tail).await.unwrap();
+        assert_eq!(tail, "89");
+        assert_eq!(fs.flushes, 0);
+    }
+
+    // Readable file that is denied to readers and vanishes after one stat
+    #[derive(Default)]
+    struct Flaky {
+        stats: Cell<usize>,
+    }
+
+    impl FileSystem for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            Err(Error::PermissionDenied)
+        }
+
+        fn stat(&self, _path: &str) -> Result<FileInfo> {
+            self.stats.set(self.stats.get() + 1);
+            match self.stats.get() {
+                1 => Ok(FileInfo::file("f", 4, 0o444)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_propagate() {
+        let mut fs = Flaky::default();
+        let mut file = FsFile::open(&mut fs, "/f").unwrap();
+
+        let mut buf = [0u8; 4];
+        let err = file.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+
+        // The default write_at only writes at 0, and write is read-only
+        let err = file.write_all(b"x").await.unwrap_err();
+        assert_eq!(err.to_string(), "read-only filesystem");
+        file.seek(SeekFrom::Start(2)).await.unwrap();
+        assert!(file.write_all(b"x").await.is_err());
+
+        let err = file.seek(SeekFrom::End(0)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        file.seek(SeekFrom::Start(1)).await.unwrap();
+        let err = file.seek(SeekFrom::Current(-2)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(file.position(), 1);
+    }
+}
@@ -0,0 +1,649 @@
+//! Audit logging of security-relevant operations
+//!
+//! `AuditFS` wraps a filesystem and records who did what to which path:
+//!
+//! ```ignore
+//! // Audits writes, deletes, renames and permission failures
+//! export_plugin!(AuditFS<SecretsFS>);
+//! ```
+//!
+//! Events go to the sink installed with `set_sink`. By default each one is
+//! written as a JSON line to the server's log through `host_log`, target
+//! `audit` (stderr off wasm32), so nothing is held in the guest waiting to
+//! be collected.
+
+use crate::context::{Context, OpContext};
+use crate::filesystem::FileSystem;
+use crate::host_fs::HostCapabilities;
+use crate::path::PathPolicy;
+use crate::stream::ReaddirStream;
+use crate::types::{
+    CachePolicy, Capabilities, Config, CreateFlags, Error, FileInfo, FsStats, Handle, HealthStatus,
+    LockKind, OpenFlags, QuotaInfo, RawJson, ReadResult, RenameFlags, Result, WriteResult,
+};
+use crate::watch::WatchId;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Which operations to audit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditPolicy {
+    /// write, create, create_exclusive, mkdir, chmod
+    pub writes: bool,
+    /// remove, remove_all
+    pub deletes: bool,
+    /// rename
+    pub renames: bool,
+    /// Any operation, reads included, that fails with `PermissionDenied`
+    pub denials: bool,
+}
+
+impl Default for AuditPolicy {
+    fn default() -> Self {
+        Self {
+            writes: true,
+            deletes: true,
+            renames: true,
+            denials: true,
+        }
+    }
+}
+
+/// One audited operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEvent {
+    /// Username of the caller, if the host sent an identity
+    #[serde(rename = "User", skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(rename = "Uid", skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(rename = "Op")]
+    pub op: &'static str,
+    #[serde(rename = "Path")]
+    pub path: String,
+    /// Destination of a rename
+    #[serde(rename = "Target", skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(rename = "Ok")]
+    pub ok: bool,
+    #[serde(rename = "Error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+type Sink = fn(&AuditEvent);
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Send audit events to `sink` instead of the server's log
+pub fn set_sink(sink: Sink) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+fn emit(event: AuditEvent) {
+    let sink = *SINK.lock().unwrap();
+    match sink {
+        Some(sink) => sink(&event),
+        None => {
+            if let Ok(json) = serde_json::to_string(&event) {
+                log_event(&json);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn log_event(json: &str) {
+    crate::host_log::log(crate::host_log::LogLevel::Info, "audit", json);
+}
+
+// Off wasm32 there is no host log to write to
+#[cfg(not(target_arch = "wasm32"))]
+fn log_event(json: &str) {
+    eprintln!("audit: {}", json);
+}
+
+/// Filesystem wrapper that audits operations on `inner`
+pub struct AuditFS<F> {
+    inner: F,
+    policy: AuditPolicy,
+}
+
+impl<F: Default> Default for AuditFS<F> {
+    fn default() -> Self {
+        Self::new(F::default(), AuditPolicy::default())
+    }
+}
+
+impl<F> AuditFS<F> {
+    /// Audit `inner` according to `policy`
+    pub fn new(inner: F, policy: AuditPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn record<T>(
+        &self,
+        audited: bool,
+        op: &'static str,
+        path: &str,
+        target: Option<&str>,
+        result: Result<T>,
+    ) -> Result<T> {
+        self.record_as(&Context::current(), audited, op, path, target, result)
+    }
+
+    // As `record`, attributing the call to the caller in `ctx`
+    fn record_as<T>(
+        &self,
+        ctx: &OpContext,
+        audited: bool,
+        op: &'static str,
+        path: &str,
+        target: Option<&str>,
+        result: Result<T>,
+    ) -> Result<T> {
+        let denied = matches!(result, Err(Error::PermissionDenied));
+        if audited || (denied && self.policy.denials) {
+            let user = &ctx.user;
+            emit(AuditEvent {
+                user: user.as_ref().map(|u| u.username.clone()),
+                uid: user.as_ref().map(|u| u.uid),
+                op,
+                path: path.to_string(),
+                target: target.map(str::to_string),
+                ok: result.is_ok(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+        }
+        result
+    }
+}
+
+impl<F: FileSystem> FileSystem for AuditFS<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.inner.initialize(config)
+    }
+
+    fn reconfigure(&mut self, config: &Config) -> Result<()> {
+        self.inner.reconfigure(config)
+    }
+
+    fn path_policy(&self) -> PathPolicy {
+        self.inner.path_policy()
+    }
+
+    fn host_capabilities(&self) -> Option<HostCapabilities> {
+        self.inner.host_capabilities()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn health(&self) -> Result<HealthStatus> {
+        self.inner.health()
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.inner.cache_policy()
+    }
+
+    fn snapshot(&self) -> Result<Vec<u8>> {
+        self.inner.snapshot()
+    }
+
+    fn restore(&mut self, data: &[u8]) -> Result<()> {
+        let result = self.inner.restore(data);
+        self.record(self.policy.writes, "restore", "/", None, result)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn trim(&mut self) {
+        self.inner.trim()
+    }
+
+    fn statfs(&self) -> Result<FsStats> {
+        self.inner.statfs()
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.record(false, "read", path, None, self.inner.read(path, offset, size))
+    }
+
+    fn read_result(&self, path: &str, offset: i64, size: i64) -> Result<ReadResult> {
+        self.record(false, "read", path, None, self.inner.read_result(path, offset, size))
+    }
+
+    fn read_ranges(&self, path: &str, ranges: &[(i64, i64)]) -> Result<Vec<Vec<u8>>> {
+        self.record(false, "read", path, None, self.inner.read_ranges(path, ranges))
+    }
+
+    fn open(&mut self, path: &str, flags: OpenFlags) -> Result<Handle> {
+        let result = self.inner.open(path, flags);
+        let audited = self.policy.writes && !flags.is_read_only();
+        self.record(audited, "open", path, None, result)
+    }
+
+    fn read_at(&self, handle: &Handle, offset: i64, size: i64) -> Result<Vec<u8>> {
+        let result = self.inner.read_at(handle, offset, size);
+        self.record(false, "read", &handle.path, None, result)
+    }
+
+    fn close(&mut self, handle: Handle) -> Result<()> {
+        self.inner.close(handle)
+    }
+
+    fn watch(&mut self, path: &str) -> Result<WatchId> {
+        self.inner.watch(path)
+    }
+
+    fn unwatch(&mut self, id: WatchId) -> Result<()> {
+        self.inner.unwatch(id)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let result = self.inner.write(path, data);
+        self.record(self.policy.writes, "write", path, None, result)
+    }
+
+    fn write_result(&mut self, path: &str, data: &[u8]) -> Result<WriteResult> {
+        let result = self.inner.write_result(path, data);
+        self.record(self.policy.writes, "write", path, None, result)
+    }
+
+    fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        let result = self.inner.write_at(path, offset, data);
+        self.record(self.policy.writes, "write", path, None, result)
+    }
+
+    fn append(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        let result = self.inner.append(path, data);
+        self.record(self.policy.writes, "append", path, None, result)
+    }
+
+    fn copy_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        src_offset: i64,
+        dst_offset: i64,
+        len: i64,
+    ) -> Result<i64> {
+        let result = self.inner.copy_range(src, dst, src_offset, dst_offset, len);
+        self.record(self.policy.writes, "copy_range", dst, Some(src), result)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        let result = self.inner.create(path);
+        self.record(self.policy.writes, "create", path, None, result)
+    }
+
+    fn create_exclusive(&mut self, path: &str) -> Result<()> {
+        let result = self.inner.create_exclusive(path);
+        self.record(self.policy.writes, "create_exclusive", path, None, result)
+    }
+
+    fn create_with_flags(&mut self, path: &str, flags: CreateFlags) -> Result<()> {
+        let result = self.inner.create_with_flags(path, flags);
+        self.record(self.policy.writes, "create", path, None, result)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        let result = self.inner.mkdir(path, perm);
+        self.record(self.policy.writes, "mkdir", path, None, result)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        let result = self.inner.remove(path);
+        self.record(self.policy.deletes, "remove", path, None, result)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        let result = self.inner.remove_all(path);
+        self.record(self.policy.deletes, "remove_all", path, None, result)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.record(false, "stat", path, None, self.inner.stat(path))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.record(false, "readdir", path, None, self.inner.readdir(path))
+    }
+
+    fn readdir_plus(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.record(false, "readdir", path, None, self.inner.readdir_plus(path))
+    }
+
+    fn readdir_stream(&self, path: &str) -> Result<ReaddirStream> {
+        self.record(false, "readdir", path, None, self.inner.readdir_stream(path))
+    }
+
+    fn readdir_page(
+        &self,
+        path: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FileInfo>, Option<String>)> {
+        self.record(false, "readdir", path, None, self.inner.readdir_page(path, cursor, limit))
+    }
+
+    fn stat_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        let result = self.inner.stat_passthrough(path)?;
+        Some(self.record(false, "stat", path, None, result))
+    }
+
+    fn readdir_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        let result = self.inner.readdir_passthrough(path)?;
+        Some(self.record(false, "readdir", path, None, result))
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        let result = self.inner.rename(old_path, new_path);
+        self.record(self.policy.renames, "rename", old_path, Some(new_path), result)
+    }
+
+    fn read_with_ctx(
+        &self,
+        ctx: &OpContext,
+        path: &str,
+        offset: i64,
+        size: i64,
+    ) -> Result<Vec<u8>> {
+        let result = self.inner.read_with_ctx(ctx, path, offset, size);
+        self.record_as(ctx, false, "read", path, None, result)
+    }
+
+    fn write_with_ctx(&mut self, ctx: &OpContext, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let result = self.inner.write_with_ctx(ctx, path, data);
+        self.record_as(ctx, self.policy.writes, "write", path, None, result)
+    }
+
+    fn stat_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<FileInfo> {
+        let result = self.inner.stat_with_ctx(ctx, path);
+        self.record_as(ctx, false, "stat", path, None, result)
+    }
+
+    fn readdir_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<Vec<FileInfo>> {
+        let result = self.inner.readdir_with_ctx(ctx, path);
+        self.record_as(ctx, false, "readdir", path, None, result)
+    }
+
+    fn create_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let result = self.inner.create_with_ctx(ctx, path);
+        self.record_as(ctx, self.policy.writes, "create", path, None, result)
+    }
+
+    fn mkdir_with_ctx(&mut self, ctx: &OpContext, path: &str, perm: u32) -> Result<()> {
+        let result = self.inner.mkdir_with_ctx(ctx, path, perm);
+        self.record_as(ctx, self.policy.writes, "mkdir", path, None, result)
+    }
+
+    fn remove_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let result = self.inner.remove_with_ctx(ctx, path);
+        self.record_as(ctx, self.policy.deletes, "remove", path, None, result)
+    }
+
+    fn rename_with_flags(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        let result = self.inner.rename_with_flags(old_path, new_path, flags);
+        self.record(self.policy.renames, "rename", old_path, Some(new_path), result)
+    }
+
+    fn rename_with_ctx(
+        &mut self,
+        ctx: &OpContext,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<()> {
+        let result = self.inner.rename_with_ctx(ctx, old_path, new_path);
+        self.record_as(ctx, self.policy.renames, "rename", old_path, Some(new_path), result)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        let result = self.inner.chmod(path, mode);
+        self.record(self.policy.writes, "chmod", path, None, result)
+    }
+
+    fn chown(&mut self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        let result = self.inner.chown(path, uid, gid);
+        self.record(self.policy.writes, "chown", path, None, result)
+    }
+
+    fn set_times(&mut self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        let result = self.inner.set_times(path, atime, mtime);
+        self.record(self.policy.writes, "set_times", path, None, result)
+    }
+
+    fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+        let result = self.inner.truncate(path, size);
+        self.record(self.policy.writes, "truncate", path, None, result)
+    }
+
+    fn allocate(&mut self, path: &str, offset: i64, len: i64) -> Result<()> {
+        let result = self.inner.allocate(path, offset, len);
+        self.record(self.policy.writes, "allocate", path, None, result)
+    }
+
+    fn quota(&self, path: &str) -> Result<QuotaInfo> {
+        self.inner.quota(path)
+    }
+
+    fn set_quota(&mut self, path: &str, limits: QuotaInfo) -> Result<()> {
+        let result = self.inner.set_quota(path, limits);
+        self.record(self.policy.writes, "set_quota", path, None, result)
+    }
+
+    fn flush(&mut self, path: &str) -> Result<()> {
+        self.inner.flush(path)
+    }
+
+    fn fsync(&mut self, path: &str) -> Result<()> {
+        self.inner.fsync(path)
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<()> {
+        let result = self.inner.link(existing, new);
+        self.record(self.policy.writes, "link", new, Some(existing), result)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        let result = self.inner.symlink(target, link_path);
+        self.record(self.policy.writes, "symlink", link_path, Some(target), result)
+    }
+
+    fn access(&self, path: &str, mode: u32) -> Result<()> {
+        self.record(false, "access", path, None, self.inner.access(path, mode))
+    }
+
+    fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_data(path, offset)
+    }
+
+    fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_hole(path, offset)
+    }
+
+    fn lock(&mut self, path: &str, kind: LockKind) -> Result<()> {
+        self.inner.lock(path, kind)
+    }
+
+    fn unlock(&mut self, path: &str) -> Result<()> {
+        self.inner.unlock(path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.record(false, "readlink", path, None, self.inner.readlink(path))
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.getxattr(path, name)
+    }
+
+    fn setxattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        let result = self.inner.setxattr(path, name, value);
+        self.record(self.policy.writes, "setxattr", path, Some(name), result)
+    }
+
+    fn listxattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.listxattr(path)
+    }
+
+    fn removexattr(&mut self, path: &str, name: &str) -> Result<()> {
+        let result = self.inner.removexattr(path, name);
+        self.record(self.policy.writes, "removexattr", path, Some(name), result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static SEEN: RefCell<Vec<AuditEvent>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Tests run on their own threads, so each sees only its own events
+    fn collect(event: &AuditEvent) {
+        SEEN.with(|seen| seen.borrow_mut().push(event.clone()));
+    }
+
+    fn take() -> Vec<AuditEvent> {
+        set_sink(collect);
+        SEEN.with(|seen| seen.take())
+    }
+
+    #[derive(Default)]
+    struct Locked;
+
+    impl FileSystem for Locked {
+        fn name(&self) -> &str {
+            "locked"
+        }
+
+        fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            Err(Error::PermissionDenied)
+        }
+
+        fn write(&mut self, _path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.to_vec())
+        }
+
+        fn remove(&mut self, _path: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn rename(&mut self, _old_path: &str, _new_path: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn stat(&self, _path: &str) -> Result<FileInfo> {
+            Ok(FileInfo::file("f", 0, 0o600))
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn ops(events: &[AuditEvent]) -> Vec<&'static str> {
+        events.iter().map(|e| e.op).collect()
+    }
+
+    #[test]
+    fn test_audit_events() {
+        take();
+        let mut fs = AuditFS::<Locked>::default();
+        fs.stat("/f").unwrap();
+        fs.write("/f", b"x").unwrap();
+        fs.read("/f", 0, -1).unwrap_err();
+        fs.remove("/f").unwrap();
+        fs.rename("/f", "/g").unwrap();
+
+        let events = take();
+        assert_eq!(ops(&events), vec!["write", "read", "remove", "rename"]);
+        let json = serde_json::to_string(&events[0]).unwrap();
+        assert_eq!(json, r#"{"Op":"write","Path":"/f","Ok":true}"#);
+    }
+
+    #[test]
+    fn test_denied_read() {
+        take();
+        let fs = AuditFS::<Locked>::default();
+        fs.read("/secret", 0, -1).unwrap_err();
+
+        let events = take();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].op, "read");
+        assert_eq!(events[0].path, "/secret");
+        assert!(!events[0].ok);
+        assert_eq!(events[0].error.as_deref(), Some("permission denied"));
+    }
+
+    #[test]
+    fn test_rename_target() {
+        take();
+        let mut fs = AuditFS::<Locked>::default();
+        fs.rename("/a", "/b").unwrap();
+
+        let events = take();
+        assert_eq!(events[0].path, "/a");
+        assert_eq!(events[0].target.as_deref(), Some("/b"));
+        let json = serde_json::to_string(&events[0]).unwrap();
+        assert_eq!(json, r#"{"Op":"rename","Path":"/a","Target":"/b","Ok":true}"#);
+    }
+
+    #[test]
+    fn test_policy_flags_off() {
+        let all = AuditPolicy::default();
+        let cases = [
+            (AuditPolicy { writes: false, ..all }, vec!["read", "remove", "rename"]),
+            (AuditPolicy { deletes: false, ..all }, vec!["write", "read", "rename"]),
+            (AuditPolicy { renames: false, ..all }, vec!["write", "read", "remove"]),
+            (AuditPolicy { denials: false, ..all }, vec!["write", "remove", "rename"]),
+        ];
+        for (policy, want) in cases {
+            take();
+            let mut fs = AuditFS::new(Locked, policy);
+            fs.write("/f", b"x").unwrap();
+            fs.read("/f", 0, -1).unwrap_err();
+            fs.remove("/f").unwrap();
+            fs.rename("/f", "/g").unwrap();
+            assert_eq!(ops(&take()), want, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_no_events_dropped() {
+        take();
+        let mut fs = AuditFS::<Locked>::default();
+        for _ in 0..3000 {
+            fs.write("/f", b"x").unwrap();
+        }
+        assert_eq!(take().len(), 3000);
+    }
+}
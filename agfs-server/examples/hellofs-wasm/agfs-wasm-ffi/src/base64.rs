@@ -0,0 +1,85 @@
+//! Base64 as Go's encoding/json encodes `[]byte`
+//!
+//! Standard alphabet with padding. JSON replies that carry bytes (batch
+//! reads, write responses) use it so the host decodes them natively.
+
+use crate::types::{Error, Result};
+use serde::{Deserialize, Deserializer, Serializer};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `data`
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode `text`, which must be padded
+pub fn decode(text: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::InvalidInput("invalid base64".to_string());
+    if !text.len().is_multiple_of(4) {
+        return Err(invalid());
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.as_bytes().chunks(4) {
+        let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if pad > 2 {
+            return Err(invalid());
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - pad] {
+            let v = ALPHABET.iter().position(|&a| a == c).ok_or_else(invalid)?;
+            n = n << 6 | v as u32;
+        }
+        n <<= 6 * pad;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+    }
+    Ok(out)
+}
+
+/// `serialize_with` for byte fields
+pub fn serialize<S: Serializer>(data: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&encode(data))
+}
+
+/// `deserialize_with` for byte fields
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<u8>, D::Error> {
+    let text = String::deserialize(d)?;
+    decode(&text).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        for (data, want) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"hello", "aGVsbG8="),
+            (b"\xff\xfe", "//4="),
+        ] {
+            assert_eq!(encode(data), want);
+            assert_eq!(decode(want).unwrap(), data);
+        }
+        assert!(decode("Zg=").is_err());
+        assert!(decode("Z===").is_err());
+        assert!(decode("Zm9v!===").is_err());
+    }
+}
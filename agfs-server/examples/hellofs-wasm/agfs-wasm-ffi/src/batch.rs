@@ -0,0 +1,149 @@
+//! Batched read-only calls
+//!
+//! Listing a tree costs one stat or readdir crossing per entry. The host
+//! can instead send many calls at once through the `fs_batch` export as a
+//! JSON array:
+//!
+//! ```json
+//! [{"Op": "stat", "Path": "/a"},
+//!  {"Op": "read", "Path": "/a", "Offset": 0, "Size": 4096},
+//!  {"Op": "readdir", "Path": "/d"}]
+//! ```
+//!
+//! and gets one result per call back, in order:
+//!
+//! ```json
+//! [{"Info": {...}}, {"Data": "aGVsbG8="}, {"Error": "ENOENT: file not found"}]
+//! ```
+//!
+//! Read data is base64, as Go encodes `[]byte`; errors are in the form of
+//! `Error::to_wire`. One failed call does not affect the others.
+//!
+//! On the server these are sent by `WASMFileSystem.Batch`, which runs the
+//! calls one at a time against plugins without `fs_batch`.
+
+use crate::base64;
+use crate::filesystem::FileSystem;
+use crate::types::{Error, FileInfo};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Most calls accepted in one batch
+pub const MAX_BATCH_OPS: usize = 4096;
+
+/// One call in a batch
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "Op", rename_all = "lowercase")]
+pub enum FsOp {
+    Stat {
+        #[serde(rename = "Path")]
+        path: String,
+    },
+    Read {
+        #[serde(rename = "Path")]
+        path: String,
+        #[serde(rename = "Offset", default)]
+        offset: i64,
+        /// Negative reads to the end
+        #[serde(rename = "Size", default = "to_end")]
+        size: i64,
+    },
+    Readdir {
+        #[serde(rename = "Path")]
+        path: String,
+    },
+}
+
+fn to_end() -> i64 {
+    -1
+}
+
+impl FsOp {
+    pub fn path(&self) -> &str {
+        match self {
+            FsOp::Stat { path } | FsOp::Read { path, .. } | FsOp::Readdir { path } => path,
+        }
+    }
+
+    pub fn path_mut(&mut self) -> &mut String {
+        match self {
+            FsOp::Stat { path } | FsOp::Read { path, .. } | FsOp::Readdir { path } => path,
+        }
+    }
+}
+
+/// Result of one call in a batch
+#[derive(Debug, Clone, Serialize)]
+pub enum FsResult {
+    Info(FileInfo),
+    #[serde(serialize_with = "base64::serialize")]
+    Data(Vec<u8>),
+    Entries(Vec<FileInfo>),
+    #[serde(serialize_with = "serialize_error")]
+    Error(Error),
+}
+
+impl<T: Into<FsResult>> From<crate::types::Result<T>> for FsResult {
+    fn from(result: crate::types::Result<T>) -> Self {
+        result.map_or_else(FsResult::Error, Into::into)
+    }
+}
+
+impl From<FileInfo> for FsResult {
+    fn from(info: FileInfo) -> Self {
+        FsResult::Info(info)
+    }
+}
+
+impl From<Vec<u8>> for FsResult {
+    fn from(data: Vec<u8>) -> Self {
+        FsResult::Data(data)
+    }
+}
+
+impl From<Vec<FileInfo>> for FsResult {
+    fn from(entries: Vec<FileInfo>) -> Self {
+        FsResult::Entries(entries)
+    }
+}
+
+/// Run one call against `fs`, as the default `FileSystem::batch` does
+pub fn run<FS: FileSystem + ?Sized>(fs: &FS, op: FsOp) -> FsResult {
+    match op {
+        FsOp::Stat { path } => fs.stat(&path).into(),
+        FsOp::Read { path, offset, size } => fs.read(&path, offset, size).into(),
+        FsOp::Readdir { path } => fs.readdir(&path).into(),
+    }
+}
+
+fn serialize_error<S: Serializer>(e: &Error, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&e.to_wire())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_json() {
+        let ops: Vec<FsOp> = serde_json::from_str(
+            r#"[{"Op":"stat","Path":"/a"},{"Op":"read","Path":"/a","Offset":2}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ops[1],
+            FsOp::Read {
+                path: "/a".to_string(),
+                offset: 2,
+                size: -1
+            }
+        );
+        let results = vec![
+            FsResult::Data(b"hello".to_vec()),
+            FsResult::Error(Error::NotFound),
+        ];
+        assert_eq!(
+            serde_json::to_string(&results).unwrap(),
+            r#"[{"Data":"aGVsbG8="},{"Error":"ENOENT: file not found"}]"#
+        );
+    }
+}
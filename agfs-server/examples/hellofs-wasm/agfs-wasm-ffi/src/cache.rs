@@ -0,0 +1,257 @@
+//! Block cache for hot ranges of large files
+//!
+//! `BlockCache` keeps fixed-size blocks keyed by (path, block offset) and
+//! evicts the least recently used ones once the byte budget is exceeded.
+//! Plugins typically hold it in a `RefCell` so it can be used from `read`.
+//!
+//! ```ignore
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     self.cache
+//!         .borrow_mut()
+//!         .read_through(path, offset, size, |off, len| HostFS::read(path, off, len))
+//! }
+//! ```
+
+use crate::types::Result;
+use std::collections::{BTreeMap, HashMap};
+
+/// Hit/miss counters for a cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Bytes currently held
+    pub bytes: usize,
+    /// Blocks currently held
+    pub blocks: usize,
+}
+
+struct Block {
+    data: Vec<u8>,
+    tick: u64,
+}
+
+/// LRU cache of file blocks keyed by (path, offset)
+pub struct BlockCache {
+    block_size: usize,
+    budget: usize,
+    blocks: HashMap<(String, u64), Block>,
+    // Access order: tick -> key, oldest first
+    lru: BTreeMap<u64, (String, u64)>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl BlockCache {
+    /// Create a cache of `block_size`-byte blocks holding at most
+    /// `byte_budget` bytes
+    pub fn new(block_size: usize, byte_budget: usize) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            budget: byte_budget,
+            blocks: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Block size in bytes
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Look up the block starting at `offset` (must be block aligned)
+    pub fn get(&mut self, path: &str, offset: u64) -> Option<&[u8]> {
+        let key = (path.to_string(), offset);
+        let tick = self.next_tick();
+        match self.blocks.get_mut(&key) {
+            Some(block) => {
+                self.stats.hits += 1;
+                self.lru.remove(&block.tick);
+                block.tick = tick;
+                self.lru.insert(tick, key.clone());
+                self.blocks.get(&key).map(|b| b.data.as_slice())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store the block starting at `offset` (must be block aligned)
+    ///
+    /// A block shorter than the block size marks the end of the file.
+    pub fn insert(&mut self, path: &str, offset: u64, data: Vec<u8>) {
+        let key = (path.to_string(), offset);
+        if let Some(old) = self.blocks.remove(&key) {
+            self.lru.remove(&old.tick);
+            self.stats.bytes -= old.data.len();
+        }
+        if data.len() > self.budget {
+            self.update_counts();
+            return;
+        }
+
+        let tick = self.next_tick();
+        self.stats.bytes += data.len();
+        self.lru.insert(tick, key.clone());
+        self.blocks.insert(key, Block { data, tick });
+
+        while self.stats.bytes > self.budget {
+            let Some((_, victim)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(block) = self.blocks.remove(&victim) {
+                self.stats.bytes -= block.data.len();
+                self.stats.evictions += 1;
+            }
+        }
+        self.update_counts();
+    }
+
+    /// Drop every cached block of `path` (call after writes)
+    pub fn invalidate(&mut self, path: &str) {
+        let stale: Vec<(String, u64)> = self
+            .blocks
+            .keys()
+            .filter(|(p, _)| p == path)
+            .cloned()
+            .collect();
+        for key in stale {
+            if let Some(block) = self.blocks.remove(&key) {
+                self.lru.remove(&block.tick);
+                self.stats.bytes -= block.data.len();
+            }
+        }
+        self.update_counts();
+    }
+
+    /// Drop everything
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.lru.clear();
+        self.stats.bytes = 0;
+        self.update_counts();
+    }
+
+    /// Serve a read from cached blocks, fetching missing ones with `fetch`
+    ///
+    /// `fetch(offset, len)` must return the bytes of the file starting at
+    /// `offset`, at most `len` of them; a short result means end of file.
+    /// A negative `size` reads to the end of the file.
+    pub fn read_through<F>(&mut self, path: &str, offset: i64, size: i64, mut fetch: F) -> Result<Vec<u8>>
+    where
+        F: FnMut(i64, i64) -> Result<Vec<u8>>,
+    {
+        let offset = offset.max(0) as u64;
+        let end = if size < 0 { u64::MAX } else { offset.saturating_add(size as u64) };
+        let bs = self.block_size as u64;
+        let mut out = Vec::new();
+        let mut block_off = offset - offset % bs;
+
+        while block_off < end {
+            let block = match self.get(path, block_off) {
+                Some(data) => data.to_vec(),
+                None => {
+                    let data = fetch(block_off as i64, bs as i64)?;
+                    self.insert(path, block_off, data.clone());
+                    data
+                }
+            };
+
+            let from = (offset.max(block_off) - block_off) as usize;
+            let to = ((end - block_off).min(block.len() as u64)) as usize;
+            if from < to {
+                out.extend_from_slice(&block[from..to]);
+            }
+            if (block.len() as u64) < bs {
+                break;
+            }
+            block_off += bs;
+        }
+
+        Ok(out)
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn update_counts(&mut self) {
+        self.stats.blocks = self.blocks.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &[u8] = b"0123456789abcdefghij";
+
+    fn fetch(off: i64, len: i64) -> Result<Vec<u8>> {
+        let start = (off as usize).min(FILE.len());
+        let end = (start + len as usize).min(FILE.len());
+        Ok(FILE[start..end].to_vec())
+    }
+
+    #[test]
+    fn test_read_through_hits_cache() {
+        let mut cache = BlockCache::new(4, 1024);
+        assert_eq!(cache.read_through("/f", 2, 7, fetch).unwrap(), b"2345678");
+        assert_eq!(cache.stats().misses, 3);
+
+        let mut calls = 0;
+        let data = cache
+            .read_through("/f", 3, 4, |o, l| {
+                calls += 1;
+                fetch(o, l)
+            })
+            .unwrap();
+        assert_eq!(data, b"3456");
+        assert_eq!(calls, 0);
+        assert_eq!(cache.stats().hits, 2);
+    }
+
+    #[test]
+    fn test_read_to_eof() {
+        let mut cache = BlockCache::new(8, 1024);
+        assert_eq!(cache.read_through("/f", 10, -1, fetch).unwrap(), b"abcdefghij");
+        assert_eq!(cache.read_through("/f", 30, 5, fetch).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = BlockCache::new(4, 8);
+        cache.insert("/a", 0, vec![0; 4]);
+        cache.insert("/b", 0, vec![0; 4]);
+        assert!(cache.get("/a", 0).is_some());
+        cache.insert("/c", 0, vec![0; 4]);
+
+        assert!(cache.get("/b", 0).is_none());
+        assert!(cache.get("/a", 0).is_some());
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.bytes, 8);
+        assert_eq!(stats.blocks, 2);
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut cache = BlockCache::new(4, 64);
+        cache.insert("/a", 0, vec![1; 4]);
+        cache.insert("/a", 4, vec![1; 4]);
+        cache.insert("/b", 0, vec![1; 4]);
+        cache.invalidate("/a");
+        assert_eq!(cache.stats().blocks, 1);
+        assert_eq!(cache.stats().bytes, 4);
+    }
+}
@@ -0,0 +1,119 @@
+//! Cancellation of long-running calls
+//!
+//! The server may give up on a call while the guest is still working on it,
+//! for instance when the plugin is unmounted. WASM calls cannot be
+//! interrupted from outside, so plugins that loop over a slow backend take
+//! a token for the call and check it between steps:
+//!
+//! ```ignore
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     let token = CancelToken::current();
+//!     let mut data = Vec::new();
+//!     for block in self.blocks(path, offset, size) {
+//!         token.check()?;
+//!         data.extend(self.fetch(block)?);
+//!     }
+//!     Ok(data)
+//! }
+//! ```
+//!
+//! Checks ask the host through the `host_should_cancel` import with the
+//! `RequestId` of the call context, so only calls the host gave an id can
+//! be cancelled by it. Native builds have no such import and are only
+//! cancelled through `CancelToken::cancel`.
+
+use crate::context::Context;
+use crate::types::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_should_cancel(request_id: *const u8) -> u32;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn host_cancelled(request_id: &str) -> bool {
+    let Ok(id) = std::ffi::CString::new(request_id) else {
+        return false;
+    };
+    unsafe { host_should_cancel(id.as_ptr() as *const u8) != 0 }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn host_cancelled(_request_id: &str) -> bool {
+    false
+}
+
+/// Cancellation state of one call
+///
+/// Clones share the state, so a token handed to helpers is cancelled
+/// along with the original. Once cancelled, a token stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    request_id: Option<String>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Token for the call being served
+    pub fn current() -> Self {
+        Self {
+            request_id: Context::current().request_id,
+            ..Self::default()
+        }
+    }
+
+    /// Token for the host request `request_id`
+    pub fn for_request(request_id: &str) -> Self {
+        Self {
+            request_id: Some(request_id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Cancel from within the guest, e.g. when a plugin-side deadline passes
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the call was cancelled; asks the host if not already known
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        match &self.request_id {
+            Some(id) if host_cancelled(id) => {
+                self.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// `Err(Error::Cancelled)` if the call was cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::for_request("r1");
+        let shared = token.clone();
+        assert_eq!(token.check(), Ok(()));
+        shared.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(Error::Cancelled));
+        assert!(!CancelToken::default().is_cancelled());
+    }
+}
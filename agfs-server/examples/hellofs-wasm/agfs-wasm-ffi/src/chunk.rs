@@ -0,0 +1,158 @@
+//! Adaptive chunk sizing for streaming transfers
+//!
+//! A fixed chunk size is either too small for fast local files (many host
+//! calls) or too large for a guest with little memory to spare. `ChunkSizer`
+//! doubles the chunk while calls finish well under the target latency and
+//! halves it when they run long or the heap approaches its limit.
+//!
+//! Config keys, all optional:
+//! - `chunk_min` / `chunk_max`: bounds in bytes (default 16 KiB / 4 MiB)
+//! - `chunk_initial`: first chunk size (default 256 KiB)
+//! - `chunk_target_ms`: target latency of one transfer (default 10)
+//! - `chunk_memory_limit`: heap bytes above which chunks shrink (default off;
+//!   needs the `heap-stats` feature)
+
+use crate::memory::heap_stats;
+use crate::metrics;
+use crate::types::Config;
+
+/// Default lower bound on the chunk size
+pub const DEFAULT_CHUNK_MIN: usize = 16 * 1024;
+/// Default upper bound on the chunk size
+pub const DEFAULT_CHUNK_MAX: usize = 4 * 1024 * 1024;
+/// Default first chunk size
+pub const DEFAULT_CHUNK_INITIAL: usize = 256 * 1024;
+/// Default target latency of a single transfer
+pub const DEFAULT_TARGET_NANOS: u64 = 10_000_000;
+
+/// Picks the size of the next chunk from how the previous ones went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSizer {
+    min: usize,
+    max: usize,
+    current: usize,
+    target_nanos: u64,
+    memory_limit: Option<usize>,
+}
+
+impl Default for ChunkSizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_MIN, DEFAULT_CHUNK_MAX)
+    }
+}
+
+impl ChunkSizer {
+    /// Create a sizer bounded by `min` and `max` bytes
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            current: DEFAULT_CHUNK_INITIAL.clamp(min, max),
+            target_nanos: DEFAULT_TARGET_NANOS,
+            memory_limit: None,
+        }
+    }
+
+    /// Read the bounds and tuning from the mount config
+    pub fn from_config(config: &Config) -> Self {
+        let size = |key: &str| config.get_i64(key).map(|v| v.max(0) as usize);
+        let mut sizer = Self::new(
+            size("chunk_min").unwrap_or(DEFAULT_CHUNK_MIN),
+            size("chunk_max").unwrap_or(DEFAULT_CHUNK_MAX),
+        );
+        if let Some(initial) = size("chunk_initial") {
+            sizer.current = initial.clamp(sizer.min, sizer.max);
+        }
+        if let Some(ms) = config.get_i64("chunk_target_ms") {
+            sizer.target_nanos = ms.max(1) as u64 * 1_000_000;
+        }
+        sizer.memory_limit = size("chunk_memory_limit");
+        sizer
+    }
+
+    /// Size of the next chunk in bytes
+    pub fn next_size(&self) -> usize {
+        match self.memory_limit {
+            Some(limit) => {
+                let free = limit.saturating_sub(heap_stats().unwrap_or_default().current);
+                self.current.min(free).max(self.min)
+            }
+            None => self.current,
+        }
+    }
+
+    /// Feed back how a transfer of `bytes` went
+    ///
+    /// `nanos` is its duration when a clock is available. Without one the
+    /// chunk only grows while transfers come back full.
+    pub fn observe(&mut self, bytes: usize, nanos: Option<u64>) {
+        let heap = heap_stats().unwrap_or_default().current;
+        let under_pressure = self
+            .memory_limit
+            .is_some_and(|limit| heap.saturating_add(self.current * 2) > limit);
+
+        let grow = match nanos {
+            Some(nanos) => nanos < self.target_nanos / 2 && bytes >= self.current,
+            None => bytes >= self.current,
+        };
+        let shrink = under_pressure || nanos.is_some_and(|n| n > self.target_nanos * 2);
+
+        if shrink {
+            self.current = (self.current / 2).max(self.min);
+        } else if grow {
+            self.current = self.current.saturating_mul(2).min(self.max);
+        }
+    }
+
+    /// Time a transfer of the next chunk and adapt to it
+    pub fn transfer<T, E>(
+        &mut self,
+        f: impl FnOnce(usize) -> std::result::Result<T, E>,
+        len: impl FnOnce(&T) -> usize,
+    ) -> std::result::Result<T, E> {
+        let start = metrics::now_nanos();
+        let out = f(self.next_size())?;
+        let elapsed = start.and_then(|s| metrics::now_nanos().map(|now| now.saturating_sub(s)));
+        self.observe(len(&out), elapsed);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grows_when_fast_and_shrinks_when_slow() {
+        let mut sizer = ChunkSizer::new(1024, 8192);
+        assert_eq!(sizer.next_size(), 8192);
+        sizer.observe(8192, Some(1));
+        assert_eq!(sizer.next_size(), 8192);
+
+        sizer.observe(8192, Some(DEFAULT_TARGET_NANOS * 3));
+        sizer.observe(4096, Some(DEFAULT_TARGET_NANOS * 3));
+        sizer.observe(2048, Some(DEFAULT_TARGET_NANOS * 3));
+        sizer.observe(1024, Some(DEFAULT_TARGET_NANOS * 3));
+        assert_eq!(sizer.next_size(), 1024);
+
+        sizer.observe(1024, None);
+        assert_eq!(sizer.next_size(), 2048);
+        sizer.observe(10, None);
+        assert_eq!(sizer.next_size(), 2048);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config::from(serde_json::json!({
+            "chunk_min": 4096,
+            "chunk_max": 65536,
+            "chunk_initial": 1_000_000,
+            "chunk_target_ms": 50
+        }));
+        let sizer = ChunkSizer::from_config(&config);
+        assert_eq!(sizer.next_size(), 65536);
+        assert_eq!(sizer.target_nanos, 50_000_000);
+    }
+}
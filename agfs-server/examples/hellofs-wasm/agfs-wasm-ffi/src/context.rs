@@ -0,0 +1,243 @@
+//! Per-call request context
+//!
+//! Before a filesystem call the host may hand the guest a JSON context via
+//! `plugin_set_context`; it stays current until the next one is set. The Go
+//! server sends one before every call, with a request id and, as the user,
+//! the identity the mount declares in `call_user`. Plugins read it with
+//! `Context::current()` to build per-user views:
+//!
+//! ```ignore
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     let ctx = Context::current();
+//!     let info = self.stat(path)?;
+//!     if !ctx.permits(info.mode, self.owner(path), Access::Read) {
+//!         return Err(Error::PermissionDenied);
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! Plugins that record owners in `FileInfo` metadata can use
+//! `check_permission(&info, &ctx, Access::Read)?` instead. The glue also
+//! passes the context to `FileSystem`'s `*_with_ctx` methods, so plugins
+//! can take it as an argument rather than reading it.
+
+use crate::types::{Error, FileInfo, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Authenticated user on whose behalf a call is made
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    #[serde(rename = "Uid")]
+    pub uid: u32,
+    #[serde(rename = "Gid")]
+    pub gid: u32,
+    #[serde(rename = "Username", default)]
+    pub username: String,
+    /// Supplementary groups
+    #[serde(rename = "Groups", default)]
+    pub groups: Vec<u32>,
+}
+
+/// Kind of access being checked against mode bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Access {
+    // The "other" bit for this access; owner and group are shifted left
+    fn bit(self) -> u32 {
+        match self {
+            Access::Read => 0o4,
+            Access::Write => 0o2,
+            Access::Execute => 0o1,
+        }
+    }
+}
+
+/// Owner of a file, for permission checks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Owner {
+    /// The owner recorded in `info`'s metadata content as `Uid` and `Gid`
+    ///
+    /// `None` if the plugin did not record both.
+    pub fn of(info: &FileInfo) -> Option<Owner> {
+        let content = &info.meta.as_ref()?.content;
+        let id = |key: &str| content.get(key)?.as_u64().and_then(|v| u32::try_from(v).ok());
+        Some(Owner {
+            uid: id("Uid")?,
+            gid: id("Gid")?,
+        })
+    }
+}
+
+impl Identity {
+    /// Whether the user is in group `gid` (primary or supplementary)
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+
+    /// Check `want` against `mode` with owner/group/other semantics
+    ///
+    /// uid 0 may read and write anything, and execute anything that has at
+    /// least one execute bit set.
+    pub fn permits(&self, mode: u32, owner: Owner, want: Access) -> bool {
+        self.permits_owned(mode, Some(owner), want)
+    }
+
+    // As `permits`; without an owner only the "other" bits apply
+    fn permits_owned(&self, mode: u32, owner: Option<Owner>, want: Access) -> bool {
+        let bit = want.bit();
+        if self.uid == 0 {
+            return want != Access::Execute || mode & 0o111 != 0;
+        }
+        let shift = match owner {
+            Some(owner) if self.uid == owner.uid => 6,
+            Some(owner) if self.in_group(owner.gid) => 3,
+            _ => 0,
+        };
+        mode & (bit << shift) != 0
+    }
+}
+
+/// Context of the current call
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Context {
+    /// Caller identity, if the server authenticated one
+    #[serde(rename = "User", default)]
+    pub user: Option<Identity>,
+    /// W3C `traceparent` of the server's span for this call
+    #[serde(rename = "TraceParent", default, skip_serializing_if = "Option::is_none")]
+    pub trace_parent: Option<String>,
+    /// Name of the mount the call arrived through
+    #[serde(rename = "Mount", default, skip_serializing_if = "Option::is_none")]
+    pub mount: Option<String>,
+    /// Server-assigned id of the request, for correlating logs
+    #[serde(rename = "RequestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// The context `FileSystem`'s `*_with_ctx` methods receive
+pub type OpContext = Context;
+
+static CURRENT: Mutex<Option<Context>> = Mutex::new(None);
+
+impl Context {
+    /// Parse a context sent by the host
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::InvalidInput(format!("invalid call context: {}", e)))
+    }
+
+    /// The context of the call being served (empty if the host sent none)
+    pub fn current() -> Self {
+        CURRENT.lock().unwrap().clone().unwrap_or_default()
+    }
+
+    /// Make `ctx` the current context
+    pub fn set_current(ctx: Option<Context>) {
+        *CURRENT.lock().unwrap() = ctx;
+    }
+
+    /// Check `want` on a file for the caller
+    ///
+    /// Calls without an authenticated user are not restricted here; plugins
+    /// that require one should check `user` themselves.
+    pub fn permits(&self, mode: u32, owner: Owner, want: Access) -> bool {
+        self.user
+            .as_ref()
+            .is_none_or(|user| user.permits(mode, owner, want))
+    }
+}
+
+/// Check `want` on `info` for the caller in `ctx`
+///
+/// The owner comes from `Owner::of(info)`; files without one are checked
+/// against their "other" bits only. As with `Context::permits`, calls
+/// without an authenticated user are allowed.
+pub fn check_permission(info: &FileInfo, ctx: &Context, want: Access) -> Result<()> {
+    match &ctx.user {
+        Some(user) if !user.permits_owned(info.mode, Owner::of(info), want) => {
+            Err(Error::PermissionDenied)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(uid: u32, gid: u32, groups: Vec<u32>) -> Identity {
+        Identity {
+            uid,
+            gid,
+            username: format!("u{}", uid),
+            groups,
+        }
+    }
+
+    #[test]
+    fn test_mode_bits() {
+        let owner = Owner { uid: 1000, gid: 100 };
+        assert!(user(1000, 1, vec![]).permits(0o600, owner, Access::Write));
+        assert!(!user(1001, 100, vec![]).permits(0o600, owner, Access::Read));
+        assert!(user(1001, 5, vec![100]).permits(0o640, owner, Access::Read));
+        assert!(!user(1001, 5, vec![100]).permits(0o640, owner, Access::Write));
+        assert!(user(2000, 5, vec![]).permits(0o604, owner, Access::Read));
+        // Owner class applies even when other bits would allow more
+        assert!(!user(1000, 1, vec![]).permits(0o077, owner, Access::Read));
+        assert!(user(0, 0, vec![]).permits(0o000, owner, Access::Write));
+        assert!(!user(0, 0, vec![]).permits(0o644, owner, Access::Execute));
+    }
+
+    #[test]
+    fn test_context_json() {
+        let ctx = Context::from_json(
+            r#"{"User":{"Uid":7,"Gid":8,"Username":"alice"},"Mount":"/home","RequestId":"r1"}"#,
+        )
+        .unwrap();
+        assert_eq!((ctx.mount.as_deref(), ctx.request_id.as_deref()), (Some("/home"), Some("r1")));
+        let alice = ctx.user.as_ref().unwrap();
+        assert_eq!((alice.uid, alice.gid, alice.username.as_str()), (7, 8, "alice"));
+        assert!(Context::from_json("{}").unwrap().user.is_none());
+        assert!(Context::default().permits(0, Owner::default(), Access::Write));
+    }
+
+    #[test]
+    fn test_check_permission() {
+        use crate::types::MetaData;
+
+        let owned = FileInfo::file("f", 0, 0o640).with_meta(
+            MetaData::new("owner", "owner").with_content(serde_json::json!({"Uid": 7, "Gid": 8})),
+        );
+        assert_eq!(Owner::of(&owned), Some(Owner { uid: 7, gid: 8 }));
+        let ctx = |uid, gid| Context {
+            user: Some(user(uid, gid, vec![])),
+            ..Context::default()
+        };
+        assert!(check_permission(&owned, &ctx(7, 1), Access::Write).is_ok());
+        assert!(check_permission(&owned, &ctx(9, 8), Access::Read).is_ok());
+        assert_eq!(
+            check_permission(&owned, &ctx(9, 8), Access::Write),
+            Err(Error::PermissionDenied)
+        );
+        assert!(check_permission(&owned, &ctx(9, 9), Access::Read).is_err());
+
+        // Unowned files fall back to the "other" bits
+        let unowned = FileInfo::file("f", 0, 0o604);
+        assert_eq!(Owner::of(&unowned), None);
+        assert!(check_permission(&unowned, &ctx(7, 8), Access::Read).is_ok());
+        assert!(check_permission(&unowned, &ctx(7, 8), Access::Write).is_err());
+        assert!(check_permission(&unowned, &Context::default(), Access::Write).is_ok());
+    }
+}
@@ -3,18 +3,111 @@
 //! This module handles the low-level FFI details, converting between
 //! C-compatible types and safe Rust types.
 
-use crate::memory::{pack_u64, Buffer, CString};
-use crate::types::{Config, Error, FileInfo, Result};
+// The handlers below are only called from the generated `extern "C"` exports,
+// whose pointer arguments come straight from the host.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::batch::{FsOp, MAX_BATCH_OPS};
+use crate::context::Context;
+use crate::lifecycle;
+use crate::memory::{
+    borrow_slice, heap_stats, pack_payload, pack_u64, reset_heap_peak, set_read_limits, CString,
+    ReadLimits,
+};
+use crate::metrics;
+use crate::path::PathPolicy;
+use crate::range::{encode_ranges, slice_range, validate_offset, MAX_READ_RANGES};
+use crate::stream;
+use crate::trace;
+use crate::types::{
+    Config, CreateFlags, DirPage, Error, FileInfo, FsStats, Handle, HealthStatus, LockKind,
+    OpenFlags, QuotaInfo, RawJson, RenameFlags, Result,
+};
+use crate::watch::{self, WatchId};
 use crate::FileSystem;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Settings the export glue itself takes from the mount config
+#[derive(Debug, Clone)]
+pub struct GlueOptions {
+    /// Count and time every call and serve `/.metrics` (`metrics: true`)
+    pub metrics: bool,
+    /// Record a span for every traced call (`tracing: true`)
+    pub tracing: bool,
+    /// Reject every mutating call without consulting the plugin
+    /// (`read_only: true`)
+    pub read_only: bool,
+    /// Decoding of request paths, from `FileSystem::path_policy`
+    pub path_policy: PathPolicy,
+    /// Largest write payload accepted, in bytes (`max_write_size`, a number
+    /// or a size such as "16MB")
+    pub max_write_size: Option<usize>,
+}
+
+impl GlueOptions {
+    const fn new() -> Self {
+        Self {
+            metrics: false,
+            tracing: false,
+            read_only: false,
+            path_policy: PathPolicy::new(),
+            max_write_size: None,
+        }
+    }
+
+    /// Extract the glue settings from a mount config
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            metrics: config.get_bool("metrics").unwrap_or(false),
+            tracing: config.get_bool("tracing").unwrap_or(false),
+            read_only: config.get_bool("read_only").unwrap_or(false),
+            path_policy: PathPolicy::new(),
+            max_write_size: config
+                .get_size("max_write_size")
+                .filter(|&n| n > 0)
+                .map(|n| n as usize),
+        }
+    }
+}
+
+impl Default for GlueOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static GLUE_OPTIONS: Mutex<GlueOptions> = Mutex::new(GlueOptions::new());
+
+/// Apply the glue settings found in `config` (called on initialize)
+pub fn configure_glue(config: &Config) {
+    set_read_limits(ReadLimits::from_config(config));
+    *GLUE_OPTIONS.lock().unwrap() = GlueOptions::from_config(config);
+}
+
+/// Install the plugin's path policy (called after initialize)
+pub fn set_path_policy(policy: PathPolicy) {
+    GLUE_OPTIONS.lock().unwrap().path_policy = policy;
+}
+
+/// Current glue settings
+pub fn glue_options() -> GlueOptions {
+    GLUE_OPTIONS.lock().unwrap().clone()
+}
 
 /// Convert a Result to an error pointer (null = success)
 pub fn result_to_error_ptr<T>(result: Result<T>) -> *mut u8 {
     match result {
         Ok(_) => CString::null(),
-        Err(e) => CString::new(&e.to_string()).into_raw(),
+        Err(e) => error_ptr(e),
     }
 }
 
+/// Encode an error for the host (see `Error::to_wire`)
+pub fn error_ptr(e: Error) -> *mut u8 {
+    CString::new(&e.to_wire()).into_raw()
+}
+
 /// Read config from JSON pointer
 pub fn read_config(config_ptr: *const u8) -> Result<Config> {
     if config_ptr.is_null() {
@@ -23,7 +116,7 @@ pub fn read_config(config_ptr: *const u8) -> Result<Config> {
         });
     }
 
-    let json_str = unsafe { CString::from_ptr(config_ptr) };
+    let json_str = unsafe { CString::from_ptr(config_ptr) }?;
 
     serde_json::from_str::<serde_json::Value>(&json_str)
         .map(Config::from)
@@ -31,7 +124,10 @@ pub fn read_config(config_ptr: *const u8) -> Result<Config> {
 }
 
 /// Serialize FileInfo to JSON and return as C string
+///
+/// Entries that break `FileInfo::validate` are rejected rather than sent.
 pub fn fileinfo_to_json_ptr(info: &FileInfo) -> Result<*mut u8> {
+    info.validate()?;
     let json = serde_json::to_string(info)
         .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
 
@@ -40,108 +136,827 @@ pub fn fileinfo_to_json_ptr(info: &FileInfo) -> Result<*mut u8> {
 
 /// Serialize Vec<FileInfo> to JSON array and return as C string
 pub fn fileinfo_vec_to_json_ptr(infos: &[FileInfo]) -> Result<*mut u8> {
+    validate_all(infos)?;
     let json = serde_json::to_string(infos)
         .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
 
     Ok(CString::new(&json).into_raw())
 }
 
+fn validate_all(infos: &[FileInfo]) -> Result<()> {
+    infos.iter().try_for_each(FileInfo::validate)
+}
+
+/// Number of entries returned per page when the host passes a zero limit
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// Build an opaque continuation token for `path` at `position`
+///
+/// The token embeds a hash of the directory path so a token issued for
+/// one directory is rejected when replayed against another.
+pub fn encode_dir_token(path: &str, position: usize) -> String {
+    format!("{:016x}{:016x}", path_hash(path), position as u64)
+}
+
+/// Decode a continuation token produced by `encode_dir_token`
+pub fn decode_dir_token(path: &str, token: &str) -> Result<usize> {
+    let invalid = || Error::InvalidInput("invalid continuation token".to_string());
+    if token.len() != 32 || !token.is_ascii() {
+        return Err(invalid());
+    }
+    let hash = u64::from_str_radix(&token[..16], 16).map_err(|_| invalid())?;
+    let position = u64::from_str_radix(&token[16..], 16).map_err(|_| invalid())?;
+    if hash != path_hash(path) {
+        return Err(invalid());
+    }
+    Ok(position as usize)
+}
+
+// FNV-1a, stable across builds and targets
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Serialize a DirPage to JSON and return as C string
+pub fn dir_page_to_json_ptr(page: &DirPage) -> Result<*mut u8> {
+    validate_all(&page.entries)?;
+    let json = serde_json::to_string(page)
+        .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+
+    Ok(CString::new(&json).into_raw())
+}
+
+// Mutating calls go through here so a read-only mount never reaches the
+// plugin's write paths
+fn mutate<T>(op: &'static str, path: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    if glue_options().read_only {
+        return observe(op, path, || Err(Error::ReadOnly));
+    }
+    observe(op, path, f)
+}
+
+// Enforce max_write_size on a write payload
+fn check_write_size(data: &[u8], options: &GlueOptions) -> Result<()> {
+    match options.max_write_size {
+        Some(limit) if data.len() > limit => Err(Error::TooLarge),
+        _ => Ok(()),
+    }
+}
+
+// Read a request path from the host and canonicalize it, so plugins never
+// see `..` segments or other spellings of the same path
+fn request_path(ptr: *const u8) -> Result<String> {
+    let raw = unsafe { CString::bytes_from_ptr(ptr) }?;
+    Ok(glue_options().path_policy.apply(raw)?)
+}
+
+// Run one plugin call, counting and timing it when metrics are enabled and
+// recording a span when tracing is; calls outside the Initialized state
+// never reach the plugin
+fn observe<T>(op: &'static str, path: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    lifecycle::ensure_serving(op)?;
+    let options = glue_options();
+    let f = || {
+        if options.tracing {
+            trace::in_span(op, path, f)
+        } else {
+            f()
+        }
+    };
+    if !options.metrics {
+        return f();
+    }
+    let start = metrics::now_nanos();
+    let result = f();
+    metrics::record(op, start, result.is_ok());
+    result
+}
+
+// The glue serves /.metrics itself when metrics are enabled
+fn is_metrics_path(path: &str) -> bool {
+    path == metrics::METRICS_PATH
+        && glue_options().metrics
+        && lifecycle::state() == lifecycle::State::Initialized
+}
+
+fn metrics_info(len: usize) -> FileInfo {
+    FileInfo::file(&metrics::METRICS_PATH[1..], len as i64, 0o444)
+}
+
+fn read_metrics(offset: i64, size: i64) -> Vec<u8> {
+    slice_range(metrics::render().as_bytes(), offset, size).to_vec()
+}
+
+// List a directory, adding the metrics file to the root when enabled
+fn list_dir<FS: FileSystem>(fs: &FS, op: &'static str, path: &str) -> Result<Vec<FileInfo>> {
+    let mut entries = observe(op, path, || fs.readdir_with_ctx(&Context::current(), path))?;
+    if path == "/" && glue_options().metrics {
+        entries.push(metrics_info(metrics::render().len()));
+    }
+    Ok(entries)
+}
+
+// A reply either passed through from the host or decoded by the plugin
+enum Reply<T> {
+    Raw(RawJson),
+    Decoded(T),
+}
+
+fn raw_json_result(raw: &RawJson) -> u64 {
+    pack_u64(CString::new(raw.as_str()).into_raw() as u32, 0)
+}
+
+fn error_result(e: Error) -> u64 {
+    pack_u64(0, error_ptr(e) as u32)
+}
+
+fn json_result(json: Result<*mut u8>) -> u64 {
+    match json {
+        Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle plugin_set_context FFI call
+///
+/// A null or empty pointer clears the context.
+pub fn handle_set_context(json_ptr: *const u8) -> *mut u8 {
+    let json = match unsafe { CString::from_ptr(json_ptr) } {
+        Ok(json) => json,
+        Err(e) => return error_ptr(e),
+    };
+    if json.is_empty() {
+        Context::set_current(None);
+        return CString::null();
+    }
+    result_to_error_ptr(Context::from_json(&json).map(|ctx| Context::set_current(Some(ctx))))
+}
+
+/// Handle fs_watch FFI call
+///
+/// Returns (watch id, 0), or (0, error string pointer) on failure.
+pub fn handle_watch<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    match observe("watch", &path, || fs.watch(&path)) {
+        Ok(WatchId(id)) => pack_u64(id, 0),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_unwatch FFI call
+pub fn handle_unwatch<FS: FileSystem>(fs: &mut FS, id: u32) -> *mut u8 {
+    result_to_error_ptr(observe("unwatch", "/", || fs.unwatch(WatchId(id))))
+}
+
+/// Handle fs_next_event FFI call
+///
+/// Returns the oldest queued change event as JSON, or null if there is
+/// none.
+pub fn handle_next_event() -> *mut u8 {
+    match watch::next_event().and_then(|event| serde_json::to_string(&event).ok()) {
+        Some(json) => CString::new(&json).into_raw(),
+        None => CString::null(),
+    }
+}
+
+/// Handle plugin_trace_drain FFI call
+///
+/// Returns the queued spans as an OTLP/JSON export request.
+pub fn handle_trace_drain<FS: FileSystem>(fs: &FS) -> *mut u8 {
+    CString::new(&trace::otlp_json(fs.name(), &trace::drain())).into_raw()
+}
+
+/// Handle plugin_reconfigure FFI call
+///
+/// Glue options follow the new config.
+pub fn handle_reconfigure<FS: FileSystem>(fs: &mut FS, config_ptr: *const u8) -> *mut u8 {
+    let config = match read_config(config_ptr) {
+        Ok(c) => c,
+        Err(e) => return error_ptr(e),
+    };
+    if let Err(e) = lifecycle::ensure_serving("reconfigure") {
+        return error_ptr(e);
+    }
+    let result = fs.reconfigure(&config).map(|_| {
+        configure_glue(&config);
+        set_path_policy(fs.path_policy());
+    });
+    result_to_error_ptr(result)
+}
+
+/// Handle plugin_snapshot FFI call
+pub fn handle_snapshot<FS: FileSystem>(fs: &FS) -> u64 {
+    if let Err(e) = lifecycle::ensure_serving("snapshot") {
+        return error_result(e);
+    }
+    match fs.snapshot() {
+        Ok(data) => pack_payload(data),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle plugin_restore FFI call
+///
+/// Restoring replaces plugin state, so a `read_only` mount refuses it.
+pub fn handle_restore<FS: FileSystem>(fs: &mut FS, data_ptr: *const u8, size: usize) -> *mut u8 {
+    if let Err(e) = lifecycle::ensure_serving("restore") {
+        return error_ptr(e);
+    }
+    if glue_options().read_only {
+        return error_ptr(Error::ReadOnly);
+    }
+    let data = match unsafe { borrow_slice(data_ptr, size) } {
+        Ok(data) => data,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(fs.restore(data))
+}
+
+/// Handle plugin_trim FFI call
+///
+/// Lets the plugin drop its caches, then restarts peak tracking so the
+/// host can watch the mount grow back. Returns the heap bytes released, or
+/// `TRIM_UNTRACKED` when the plugin was built without `heap-stats`.
+pub fn handle_trim<FS: FileSystem>(fs: &mut FS) -> u64 {
+    if lifecycle::ensure_serving("trim").is_err() {
+        return 0;
+    }
+    let Some(before) = heap_stats() else {
+        fs.trim();
+        return TRIM_UNTRACKED;
+    };
+    fs.trim();
+    reset_heap_peak();
+    let after = heap_stats().unwrap_or(before);
+    before.current.saturating_sub(after.current) as u64
+}
+
+/// `plugin_trim` result of a plugin that cannot count the bytes it released
+pub const TRIM_UNTRACKED: u64 = u64::MAX;
+
 /// Handle fs_read FFI call
+///
+/// Returns (data pointer, length), or (0, error string pointer) on failure.
 pub fn handle_read<FS: FileSystem>(fs: &FS, path_ptr: *const u8, offset: i64, size: i64) -> u64 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-
-    match fs.read(&path, offset, size) {
-        Ok(data) => {
-            let len = data.len() as u32;
-            let buffer = Buffer::from_bytes(&data);
-            let ptr = buffer.into_raw() as u32;
-            pack_u64(ptr, len)
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = validate_offset(offset) {
+        return error_result(e);
+    }
+
+    if is_metrics_path(&path) {
+        return pack_payload(read_metrics(offset, size));
+    }
+
+    match observe("read", &path, || {
+        fs.read_with_ctx(&Context::current(), &path, offset, size)
+    }) {
+        Ok(data) => pack_payload(data),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_read_result FFI call
+///
+/// The payload is `ReadResult::to_payload`: an EOF flag byte, then data.
+pub fn handle_read_result<FS: FileSystem>(
+    fs: &FS,
+    path_ptr: *const u8,
+    offset: i64,
+    size: i64,
+) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = validate_offset(offset) {
+        return error_result(e);
+    }
+
+    match observe("read", &path, || fs.read_result(&path, offset, size)) {
+        Ok(result) => pack_payload(result.to_payload()),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_read_ranges FFI call
+///
+/// `ranges_ptr` is a JSON array of `[offset, size]` pairs; the reply is
+/// framed by `range::encode_ranges`.
+pub fn handle_read_ranges<FS: FileSystem>(
+    fs: &FS,
+    path_ptr: *const u8,
+    ranges_ptr: *const u8,
+) -> u64 {
+    let request = request_path(path_ptr).and_then(|path| {
+        let json = unsafe { CString::from_ptr(ranges_ptr) }?;
+        let ranges: Vec<(i64, i64)> = serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid read ranges: {}", e)))?;
+        if ranges.len() > MAX_READ_RANGES {
+            return Err(Error::TooLarge);
         }
-        Err(_) => 0, // Return 0 to indicate error
+        for &(offset, _) in &ranges {
+            validate_offset(offset)?;
+        }
+        Ok((path, ranges))
+    });
+    let (path, ranges) = match request {
+        Ok(request) => request,
+        Err(e) => return error_result(e),
+    };
+
+    match observe("read_ranges", &path, || fs.read_ranges(&path, &ranges)) {
+        Ok(parts) => match encode_ranges(parts) {
+            Ok(data) => pack_payload(data),
+            Err(e) => error_result(e),
+        },
+        Err(e) => error_result(e),
     }
 }
 
+// Open handles by the number the host holds; 0 is never handed out
+struct HandleTable {
+    next: u32,
+    open: BTreeMap<u32, Handle>,
+}
+
+static HANDLES: Mutex<HandleTable> = Mutex::new(HandleTable {
+    next: 1,
+    open: BTreeMap::new(),
+});
+
+impl HandleTable {
+    fn insert(&mut self, handle: Handle) -> u32 {
+        while self.next == 0 || self.open.contains_key(&self.next) {
+            self.next = self.next.wrapping_add(1);
+        }
+        let number = self.next;
+        self.next = self.next.wrapping_add(1);
+        self.open.insert(number, handle);
+        number
+    }
+}
+
+fn unknown_handle() -> Error {
+    Error::InvalidInput("unknown file handle".to_string())
+}
+
+/// Handle fs_open FFI call
+///
+/// Returns (handle number, 0), or (0, error string pointer) on failure.
+/// On a `read_only` mount, opens whose flags can modify the file fail with
+/// `ReadOnly`.
+pub fn handle_open<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, flags: u32) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    let flags = OpenFlags(flags);
+    let open = || fs.open(&path, flags);
+    let result = if flags.modifies() {
+        mutate("open", &path, open)
+    } else {
+        observe("open", &path, open)
+    };
+    match result {
+        Ok(handle) => pack_u64(HANDLES.lock().unwrap().insert(handle), 0),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_read_at FFI call
+///
+/// Returns data like `handle_read`.
+pub fn handle_read_at<FS: FileSystem>(fs: &FS, handle: u32, offset: i64, size: i64) -> u64 {
+    if let Err(e) = validate_offset(offset) {
+        return error_result(e);
+    }
+    let handles = HANDLES.lock().unwrap();
+    let Some(handle) = handles.open.get(&handle) else {
+        return error_result(unknown_handle());
+    };
+    match observe("read", &handle.path, || fs.read_at(handle, offset, size)) {
+        Ok(data) => pack_payload(data),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_close FFI call
+///
+/// The handle is released even if the plugin reports an error.
+pub fn handle_close<FS: FileSystem>(fs: &mut FS, handle: u32) -> *mut u8 {
+    let Some(handle) = HANDLES.lock().unwrap().open.remove(&handle) else {
+        return error_ptr(unknown_handle());
+    };
+    let path = handle.path.clone();
+    result_to_error_ptr(observe("close", &path, || fs.close(handle)))
+}
+
 /// Handle fs_stat FFI call
 pub fn handle_stat<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-
-    match fs.stat(&path) {
-        Ok(info) => match fileinfo_to_json_ptr(&info) {
-            Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
-            Err(e) => {
-                let err_ptr = CString::new(&e.to_string()).into_raw();
-                pack_u64(0, err_ptr as u32)
-            }
-        },
-        Err(e) => {
-            let err_ptr = CString::new(&e.to_string()).into_raw();
-            pack_u64(0, err_ptr as u32)
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+
+    if is_metrics_path(&path) {
+        return json_result(fileinfo_to_json_ptr(&metrics_info(metrics::render().len())));
+    }
+
+    let result = observe("stat", &path, || match fs.stat_passthrough(&path) {
+        Some(raw) => raw.map(Reply::Raw),
+        None => fs.stat_with_ctx(&Context::current(), &path).map(Reply::Decoded),
+    });
+
+    match result {
+        Ok(Reply::Raw(raw)) => raw_json_result(&raw),
+        Ok(Reply::Decoded(info)) => json_result(fileinfo_to_json_ptr(&info)),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_statfs FFI call
+///
+/// Returns the `FsStats` as JSON.
+pub fn handle_statfs<FS: FileSystem>(fs: &FS) -> u64 {
+    json_result(observe("statfs", "/", || fs.statfs()).and_then(|stats: FsStats| {
+        let json = serde_json::to_string(&stats)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    }))
+}
+
+/// Handle plugin_health FFI call
+pub fn handle_health<FS: FileSystem>(fs: &FS) -> u64 {
+    json_result(fs.health().and_then(|status: HealthStatus| {
+        let json = serde_json::to_string(&status)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    }))
+}
+
+/// Handle plugin_cache_policy FFI call
+pub fn handle_cache_policy<FS: FileSystem>(fs: &FS) -> u64 {
+    let json = serde_json::to_string(&fs.cache_policy())
+        .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)));
+    json_result(json.map(|json| CString::new(&json).into_raw()))
+}
+
+/// Handle fs_batch FFI call
+///
+/// Paths are canonicalized like those of single calls; one that cannot be
+/// fails the whole batch.
+pub fn handle_batch<FS: FileSystem>(fs: &FS, json_ptr: *const u8) -> u64 {
+    let ops = unsafe { CString::from_ptr(json_ptr) }.and_then(|json| {
+        serde_json::from_str::<Vec<FsOp>>(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid batch: {}", e)))
+    });
+    let mut ops = match ops {
+        Ok(ops) => ops,
+        Err(e) => return error_result(e),
+    };
+    if ops.len() > MAX_BATCH_OPS {
+        return error_result(Error::TooLarge);
+    }
+    let policy = glue_options().path_policy;
+    for op in &mut ops {
+        match policy.apply(op.path().as_bytes()) {
+            Ok(path) => *op.path_mut() = path,
+            Err(e) => return error_result(e.into()),
         }
     }
+
+    json_result(observe("batch", "/", || Ok(fs.batch(ops))).and_then(|results| {
+        let json = serde_json::to_string(&results)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    }))
 }
 
 /// Handle fs_readdir FFI call
 pub fn handle_readdir<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-
-    match fs.readdir(&path) {
-        Ok(infos) => match fileinfo_vec_to_json_ptr(&infos) {
-            Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
-            Err(e) => {
-                let err_ptr = CString::new(&e.to_string()).into_raw();
-                pack_u64(0, err_ptr as u32)
-            }
-        },
-        Err(e) => {
-            let err_ptr = CString::new(&e.to_string()).into_raw();
-            pack_u64(0, err_ptr as u32)
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+
+    // The root listing may need the metrics entry added, so decode it
+    if !(path == "/" && glue_options().metrics) {
+        let result = observe("readdir", &path, || match fs.readdir_passthrough(&path) {
+            Some(raw) => raw.map(Reply::Raw),
+            None => fs.readdir_with_ctx(&Context::current(), &path).map(Reply::Decoded),
+        });
+        return match result {
+            Ok(Reply::Raw(raw)) => raw_json_result(&raw),
+            Ok(Reply::Decoded(infos)) => json_result(fileinfo_vec_to_json_ptr(&infos)),
+            Err(e) => error_result(e),
+        };
+    }
+
+    match list_dir(fs, "readdir", &path) {
+        Ok(infos) => json_result(fileinfo_vec_to_json_ptr(&infos)),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_readdir_plus FFI call
+///
+/// Like `fs_readdir`, but every entry is checked with
+/// `FileInfo::validate`, since the host caches them as stat results.
+pub fn handle_readdir_plus<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    let entries = observe("readdir_plus", &path, || {
+        let mut entries = fs.readdir_plus(&path)?;
+        for entry in &entries {
+            entry.validate()?;
+        }
+        if path == "/" && glue_options().metrics {
+            entries.push(metrics_info(metrics::render().len()));
+        }
+        Ok(entries)
+    });
+    match entries {
+        Ok(infos) => json_result(fileinfo_vec_to_json_ptr(&infos)),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_readdir_page FFI call
+///
+/// `token_ptr` is null (or empty) for the first page, otherwise the `Next`
+/// token from the previous page. `limit` caps the entries in this page;
+/// zero selects `DEFAULT_PAGE_SIZE`. Pages come from
+/// `FileSystem::readdir_page`; with metrics enabled the last page of the
+/// root also lists the metrics file.
+pub fn handle_readdir_page<FS: FileSystem>(
+    fs: &FS,
+    path_ptr: *const u8,
+    token_ptr: *const u8,
+    limit: u32,
+) -> u64 {
+    let result = (|| {
+        let token = unsafe { CString::from_ptr(token_ptr) }?;
+        let path = request_path(path_ptr)?;
+        let cursor = Some(token.as_str()).filter(|t| !t.is_empty());
+        let limit = if limit == 0 { DEFAULT_PAGE_SIZE } else { limit as usize };
+        let (mut entries, next) =
+            observe("readdir_page", &path, || fs.readdir_page(&path, cursor, limit))?;
+        if path == "/" && next.is_none() && glue_options().metrics {
+            entries.push(metrics_info(metrics::render().len()));
+        }
+        dir_page_to_json_ptr(&DirPage { entries, next })
+    })();
+
+    json_result(result)
+}
+
+/// Handle fs_readdir_open FFI call
+///
+/// Returns (cursor, 0) for `fs_readdir_next`, or (0, error string
+/// pointer) on failure.
+pub fn handle_readdir_open<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    let result = observe("readdir_open", &path, || {
+        let stream = fs.readdir_stream(&path)?;
+        if path == "/" && glue_options().metrics {
+            return stream::open(stream.chain(metrics_info(metrics::render().len())));
         }
+        stream::open(stream)
+    });
+    match result {
+        Ok(cursor) => pack_u64(cursor, 0),
+        Err(e) => error_result(e),
     }
 }
 
+/// Handle fs_readdir_next FFI call
+///
+/// Returns up to `max` entries (zero selects `DEFAULT_PAGE_SIZE`) as a
+/// JSON array; an empty array ends the listing and releases the cursor.
+pub fn handle_readdir_next(cursor: u32, max: u32) -> u64 {
+    let max = if max == 0 { DEFAULT_PAGE_SIZE } else { max as usize };
+    let result = lifecycle::ensure_serving("readdir_next").and_then(|_| stream::next(cursor, max));
+    json_result(result.and_then(|entries| fileinfo_vec_to_json_ptr(&entries)))
+}
+
+/// Handle fs_readdir_close FFI call
+pub fn handle_readdir_close(cursor: u32) -> *mut u8 {
+    result_to_error_ptr(stream::close(cursor))
+}
+
 /// Handle fs_write FFI call
+///
+/// The payload is borrowed from the region the host wrote into guest
+/// memory. Payloads over `max_write_size` are rejected with `TooLarge`.
+/// Failures return (0, error string pointer).
 pub fn handle_write<FS: FileSystem>(
     fs: &mut FS,
     path_ptr: *const u8,
     data_ptr: *const u8,
     size: usize,
 ) -> u64 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    let data = unsafe { std::slice::from_raw_parts(data_ptr, size) };
-
-    match fs.write(&path, data) {
-        Ok(response) => {
-            let len = response.len() as u32;
-            let buffer = Buffer::from_bytes(&response);
-            let ptr = buffer.into_raw() as u32;
-            pack_u64(ptr, len)
-        }
-        Err(_) => 0, // Return 0 to indicate error
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    let data = match unsafe { borrow_slice(data_ptr, size) } {
+        Ok(data) => data,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = check_write_size(data, &glue_options()) {
+        return error_result(e);
+    }
+
+    match mutate("write", &path, || {
+        fs.write_with_ctx(&Context::current(), &path, data)
+    }) {
+        Ok(response) => pack_payload(response),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_write_result FFI call
+///
+/// Takes data like `handle_write` and returns the `WriteResult` as JSON.
+pub fn handle_write_result<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    data_ptr: *const u8,
+    size: usize,
+) -> u64 {
+    let result = (|| {
+        let path = request_path(path_ptr)?;
+        let data = unsafe { borrow_slice(data_ptr, size) }?;
+        check_write_size(data, &glue_options())?;
+        let result = mutate("write", &path, || fs.write_result(&path, data))?;
+        let json = serde_json::to_string(&result)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    })();
+
+    json_result(result)
+}
+
+/// Handle fs_write_at FFI call
+///
+/// Returns (bytes written, 0), or (0, error string pointer) on failure.
+pub fn handle_write_at<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    offset: i64,
+    data_ptr: *const u8,
+    size: usize,
+) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = validate_offset(offset) {
+        return error_result(e);
+    }
+    let data = match unsafe { borrow_slice(data_ptr, size) } {
+        Ok(data) => data,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = check_write_size(data, &glue_options()) {
+        return error_result(e);
+    }
+
+    match mutate("write", &path, || fs.write_at(&path, offset, data)) {
+        Ok(written) => pack_u64(written as u32, 0),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_append FFI call
+///
+/// Returns (bytes appended, 0), or (0, error string pointer) on failure.
+pub fn handle_append<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    data_ptr: *const u8,
+    size: usize,
+) -> u64 {
+    let result = (|| {
+        let path = request_path(path_ptr)?;
+        let data = unsafe { borrow_slice(data_ptr, size) }?;
+        check_write_size(data, &glue_options())?;
+        mutate("append", &path, || fs.append(&path, data))
+    })();
+
+    match result {
+        Ok(written) => pack_u64(written as u32, 0),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_copy_range FFI call
+///
+/// Returns (bytes copied, 0), or (0, error string pointer) on failure. One
+/// call copies less than 4 GiB; the host repeats it for the rest.
+pub fn handle_copy_range<FS: FileSystem>(
+    fs: &mut FS,
+    src_ptr: *const u8,
+    dst_ptr: *const u8,
+    src_offset: i64,
+    dst_offset: i64,
+    len: i64,
+) -> u64 {
+    let paths = request_path(src_ptr).and_then(|src| Ok((src, request_path(dst_ptr)?)));
+    let (src, dst) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return error_result(e),
+    };
+    if let Err(e) = validate_offset(src_offset).and_then(|_| validate_offset(dst_offset)) {
+        return error_result(e);
+    }
+    let len = if len < 0 { u32::MAX as i64 } else { len.min(u32::MAX as i64) };
+
+    match mutate("copy_range", &dst, || {
+        fs.copy_range(&src, &dst, src_offset, dst_offset, len)
+    }) {
+        Ok(copied) => pack_u64(copied as u32, 0),
+        Err(e) => error_result(e),
     }
 }
 
 /// Handle fs_create FFI call
 pub fn handle_create<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    result_to_error_ptr(fs.create(&path))
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("create", &path, || {
+        fs.create_with_ctx(&Context::current(), &path)
+    }))
+}
+
+/// Handle fs_create_exclusive FFI call
+pub fn handle_create_exclusive<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("create_exclusive", &path, || fs.create_exclusive(&path)))
+}
+
+/// Handle fs_create_flags FFI call
+pub fn handle_create_flags<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    flags: u32,
+    mode: u32,
+) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    let flags = CreateFlags::from_bits(flags, mode);
+    result_to_error_ptr(mutate("create", &path, || fs.create_with_flags(&path, flags)))
 }
 
 /// Handle fs_mkdir FFI call
 pub fn handle_mkdir<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, perm: u32) -> *mut u8 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    result_to_error_ptr(fs.mkdir(&path, perm))
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("mkdir", &path, || {
+        fs.mkdir_with_ctx(&Context::current(), &path, perm)
+    }))
 }
 
 /// Handle fs_remove FFI call
 pub fn handle_remove<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    result_to_error_ptr(fs.remove(&path))
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("remove", &path, || {
+        fs.remove_with_ctx(&Context::current(), &path)
+    }))
 }
 
 /// Handle fs_remove_all FFI call
 pub fn handle_remove_all<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    result_to_error_ptr(fs.remove_all(&path))
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("remove_all", &path, || fs.remove_all(&path)))
 }
 
 /// Handle fs_rename FFI call
@@ -150,13 +965,369 @@ pub fn handle_rename<FS: FileSystem>(
     old_path_ptr: *const u8,
     new_path_ptr: *const u8,
 ) -> *mut u8 {
-    let old_path = unsafe { CString::from_ptr(old_path_ptr) };
-    let new_path = unsafe { CString::from_ptr(new_path_ptr) };
-    result_to_error_ptr(fs.rename(&old_path, &new_path))
+    let paths = request_path(old_path_ptr).and_then(|old| Ok((old, request_path(new_path_ptr)?)));
+    let (old_path, new_path) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("rename", &old_path, || {
+        fs.rename_with_ctx(&Context::current(), &old_path, &new_path)
+    }))
+}
+
+/// Handle fs_rename_flags FFI call
+pub fn handle_rename_flags<FS: FileSystem>(
+    fs: &mut FS,
+    old_path_ptr: *const u8,
+    new_path_ptr: *const u8,
+    flags: u32,
+) -> *mut u8 {
+    let request = request_path(old_path_ptr).and_then(|old| {
+        let new = request_path(new_path_ptr)?;
+        Ok((old, new, RenameFlags(flags).validate()?))
+    });
+    let (old_path, new_path, flags) = match request {
+        Ok(request) => request,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("rename", &old_path, || {
+        fs.rename_with_flags(&old_path, &new_path, flags)
+    }))
 }
 
 /// Handle fs_chmod FFI call
 pub fn handle_chmod<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, mode: u32) -> *mut u8 {
-    let path = unsafe { CString::from_ptr(path_ptr) };
-    result_to_error_ptr(fs.chmod(&path, mode))
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("chmod", &path, || fs.chmod(&path, mode)))
+}
+
+/// Handle fs_chown FFI call
+pub fn handle_chown<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, uid: u32, gid: u32) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("chown", &path, || fs.chown(&path, uid, gid)))
+}
+
+/// Handle fs_set_times FFI call
+pub fn handle_set_times<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    atime: i64,
+    mtime: i64,
+) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("set_times", &path, || fs.set_times(&path, atime, mtime)))
+}
+
+/// Handle fs_truncate FFI call
+pub fn handle_truncate<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, size: i64) -> *mut u8 {
+    if size < 0 {
+        return error_ptr(Error::InvalidInput("negative size".to_string()));
+    }
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("truncate", &path, || fs.truncate(&path, size)))
+}
+
+/// Handle fs_allocate FFI call
+pub fn handle_allocate<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    offset: i64,
+    len: i64,
+) -> *mut u8 {
+    if offset < 0 || len <= 0 {
+        return error_ptr(Error::InvalidInput("invalid allocation range".to_string()));
+    }
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("allocate", &path, || fs.allocate(&path, offset, len)))
+}
+
+/// Handle fs_quota FFI call
+///
+/// Returns the `QuotaInfo` as JSON.
+pub fn handle_quota<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    json_result(observe("quota", &path, || fs.quota(&path)).and_then(|quota| {
+        let json = serde_json::to_string(&quota)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    }))
+}
+
+/// Handle fs_set_quota FFI call
+///
+/// `limits_ptr` is a `QuotaInfo` as JSON; only the limits are read.
+pub fn handle_set_quota<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    limits_ptr: *const u8,
+) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    let limits = unsafe { CString::from_ptr(limits_ptr) }.and_then(|json| {
+        serde_json::from_str::<QuotaInfo>(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid quota: {}", e)))
+    });
+    let limits = match limits {
+        Ok(limits) => limits,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("set_quota", &path, || fs.set_quota(&path, limits)))
+}
+
+/// Handle fs_flush FFI call
+pub fn handle_flush<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(observe("flush", &path, || fs.flush(&path)))
+}
+
+/// Handle fs_fsync FFI call
+pub fn handle_fsync<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(observe("fsync", &path, || fs.fsync(&path)))
+}
+
+/// Handle fs_link FFI call
+pub fn handle_link<FS: FileSystem>(
+    fs: &mut FS,
+    existing_ptr: *const u8,
+    new_ptr: *const u8,
+) -> *mut u8 {
+    let paths = request_path(existing_ptr).and_then(|existing| Ok((existing, request_path(new_ptr)?)));
+    let (existing, new) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("link", &new, || fs.link(&existing, &new)))
+}
+
+/// Handle fs_symlink FFI call
+///
+/// The target is passed through as given; only the link path is a
+/// request path.
+pub fn handle_symlink<FS: FileSystem>(
+    fs: &mut FS,
+    target_ptr: *const u8,
+    link_path_ptr: *const u8,
+) -> *mut u8 {
+    let target = unsafe { CString::bytes_from_ptr(target_ptr) }.and_then(|raw| {
+        match String::from_utf8(raw.to_vec()) {
+            Ok(target) if !target.is_empty() && !target.contains('\0') => Ok(target),
+            _ => Err(Error::InvalidInput("invalid symlink target".to_string())),
+        }
+    });
+    let paths = target.and_then(|target| Ok((target, request_path(link_path_ptr)?)));
+    let (target, link_path) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("symlink", &link_path, || fs.symlink(&target, &link_path)))
+}
+
+/// Handle fs_readlink FFI call
+///
+/// Returns (target pointer, length), or (0, error string pointer) on
+/// failure.
+pub fn handle_readlink<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    match observe("readlink", &path, || fs.readlink(&path)) {
+        Ok(target) => pack_payload(target.into_bytes()),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_access FFI call
+pub fn handle_access<FS: FileSystem>(fs: &FS, path_ptr: *const u8, mode: u32) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    if mode & !7 != 0 {
+        return error_ptr(Error::InvalidInput(format!("invalid access mode {:o}", mode)));
+    }
+    result_to_error_ptr(observe("access", &path, || fs.access(&path, mode)))
+}
+
+// errno lseek(2) fails with when there is no data or hole past the offset
+const ENXIO: i64 = 6;
+
+/// Handle fs_seek_data and fs_seek_hole FFI calls
+///
+/// Returns the offset found, or a negated errno: `-ENXIO` if there is
+/// none, `-e.errno()` on failure.
+pub fn handle_seek<FS: FileSystem>(fs: &FS, path_ptr: *const u8, offset: i64, hole: bool) -> i64 {
+    let result = request_path(path_ptr).and_then(|path| {
+        validate_offset(offset)?;
+        if hole {
+            observe("seek_hole", &path, || fs.seek_hole(&path, offset))
+        } else {
+            observe("seek_data", &path, || fs.seek_data(&path, offset))
+        }
+    });
+    match result {
+        Ok(Some(found)) => found,
+        Ok(None) => -ENXIO,
+        Err(e) => -(e.errno() as i64),
+    }
+}
+
+/// Handle fs_lock FFI call
+pub fn handle_lock<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8, kind: u32) -> *mut u8 {
+    let request = request_path(path_ptr).and_then(|path| Ok((path, LockKind::from_bits(kind)?)));
+    let (path, kind) = match request {
+        Ok(request) => request,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(observe("lock", &path, || fs.lock(&path, kind)))
+}
+
+/// Handle fs_unlock FFI call
+pub fn handle_unlock<FS: FileSystem>(fs: &mut FS, path_ptr: *const u8) -> *mut u8 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(observe("unlock", &path, || fs.unlock(&path)))
+}
+
+// Read an extended attribute name; names are namespaced keys like
+// `user.sha1`, limited to 255 bytes as on Linux
+fn xattr_name(ptr: *const u8) -> Result<String> {
+    let name = unsafe { CString::from_ptr(ptr) }?;
+    if name.is_empty() || name.len() > 255 {
+        return Err(Error::InvalidInput("invalid xattr name".to_string()));
+    }
+    Ok(name)
+}
+
+/// Handle fs_getxattr FFI call
+///
+/// Returns the value like `fs_read` returns data.
+pub fn handle_getxattr<FS: FileSystem>(fs: &FS, path_ptr: *const u8, name_ptr: *const u8) -> u64 {
+    let request = request_path(path_ptr).and_then(|path| Ok((path, xattr_name(name_ptr)?)));
+    let (path, name) = match request {
+        Ok(request) => request,
+        Err(e) => return error_result(e),
+    };
+    match observe("getxattr", &path, || fs.getxattr(&path, &name)) {
+        Ok(value) => pack_payload(value),
+        Err(e) => error_result(e),
+    }
+}
+
+/// Handle fs_setxattr FFI call
+pub fn handle_setxattr<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    name_ptr: *const u8,
+    value_ptr: *const u8,
+    size: usize,
+) -> *mut u8 {
+    let request = request_path(path_ptr).and_then(|path| Ok((path, xattr_name(name_ptr)?)));
+    let (path, name) = match request {
+        Ok(request) => request,
+        Err(e) => return error_ptr(e),
+    };
+    let value = match unsafe { borrow_slice(value_ptr, size) } {
+        Ok(value) => value,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("setxattr", &path, || fs.setxattr(&path, &name, value)))
+}
+
+/// Handle fs_listxattr FFI call
+///
+/// Returns the names as a JSON array.
+pub fn handle_listxattr<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
+    let path = match request_path(path_ptr) {
+        Ok(path) => path,
+        Err(e) => return error_result(e),
+    };
+    json_result(observe("listxattr", &path, || fs.listxattr(&path)).and_then(|names| {
+        let json = serde_json::to_string(&names)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Ok(CString::new(&json).into_raw())
+    }))
+}
+
+/// Handle fs_removexattr FFI call
+pub fn handle_removexattr<FS: FileSystem>(
+    fs: &mut FS,
+    path_ptr: *const u8,
+    name_ptr: *const u8,
+) -> *mut u8 {
+    let request = request_path(path_ptr).and_then(|path| Ok((path, xattr_name(name_ptr)?)));
+    let (path, name) = match request {
+        Ok(request) => request,
+        Err(e) => return error_ptr(e),
+    };
+    result_to_error_ptr(mutate("removexattr", &path, || fs.removexattr(&path, &name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_token_roundtrip() {
+        let token = encode_dir_token("/data", 42);
+        assert_eq!(decode_dir_token("/data", &token).unwrap(), 42);
+        assert!(decode_dir_token("/other", &token).is_err());
+        assert!(decode_dir_token("/data", "garbage").is_err());
+    }
+
+    #[test]
+    fn test_handle_numbers() {
+        let mut table = HandleTable {
+            next: u32::MAX,
+            open: BTreeMap::new(),
+        };
+        let a = table.insert(Handle::new("/a", OpenFlags::READ_ONLY));
+        let b = table.insert(Handle::new("/b", OpenFlags::READ_ONLY).with_id(7));
+        assert_eq!((a, b), (u32::MAX, 1));
+        table.next = 1;
+        assert_eq!(table.insert(Handle::new("/c", OpenFlags::READ_WRITE)), 2);
+        assert_eq!(table.open[&1].id, 7);
+        assert!(!table.open[&2].flags.is_read_only());
+    }
+
+    #[test]
+    fn test_check_write_size() {
+        let config = Config::from(serde_json::json!({"max_write_size": 1024}));
+        let options = GlueOptions::from_config(&config);
+        assert!(check_write_size(&[0; 2048], &GlueOptions::default()).is_ok());
+        assert!(check_write_size(&[0; 1024], &options).is_ok());
+        assert_eq!(check_write_size(&[0; 2048], &options), Err(Error::TooLarge));
+
+        let config = Config::from(serde_json::json!({"max_write_size": "1KB"}));
+        assert_eq!(GlueOptions::from_config(&config).max_write_size, Some(1024));
+    }
 }
@@ -1,6 +1,16 @@
 //! High-level agfs filesystem trait for WASM plugins
 
-use crate::types::{Config, FileInfo, Result};
+use crate::batch::{self, FsOp, FsResult};
+use crate::context::{check_permission, Access, Context, OpContext};
+use crate::host_fs::HostCapabilities;
+use crate::path::{self, PathPolicy};
+use crate::reader::FileReader;
+use crate::stream::ReaddirStream;
+use crate::types::{
+    CachePolicy, Capabilities, Config, CreateFlags, FileInfo, FsStats, Handle, HealthStatus,
+    LockKind, OpenFlags, QuotaInfo, RawJson, ReadResult, RenameFlags, Result, WriteResult,
+};
+use crate::watch::WatchId;
 
 /// Filesystem trait that plugin developers should implement
 ///
@@ -31,6 +41,90 @@ pub trait FileSystem {
         Ok(())
     }
 
+    /// Apply updated configuration to a running plugin
+    ///
+    /// The server calls this through `plugin_reconfigure` to push rotated
+    /// credentials or changed settings without unmounting. `path_policy`
+    /// is queried again afterwards; `host_capabilities` is not, since the
+    /// declaration made at initialize is final. The default validates the
+    /// config and initializes again.
+    fn reconfigure(&mut self, config: &Config) -> Result<()> {
+        self.validate(config)?;
+        self.initialize(config)
+    }
+
+    /// How request paths are decoded before they reach this plugin
+    ///
+    /// Queried after `initialize`, so it may depend on the config.
+    fn path_policy(&self) -> PathPolicy {
+        PathPolicy::default()
+    }
+
+    /// Host paths and verbs this plugin needs through `HostFS`
+    ///
+    /// Queried after `initialize`. Returning a declaration makes `HostFS`
+    /// refuse everything outside it for the rest of the mount's life;
+    /// `None` (the default) leaves host access unrestricted.
+    fn host_capabilities(&self) -> Option<HostCapabilities> {
+        None
+    }
+
+    /// Optional operations this plugin supports
+    ///
+    /// Queried after `initialize` through `plugin_capabilities`. Without
+    /// `WRITABLE` the server fails modifying calls without making them and
+    /// lists the mount as read-only; without `WATCH` it does not watch the
+    /// mount. The default claims everything, so the host calls every
+    /// operation and relies on the errors of those that are not
+    /// implemented.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::ALL
+    }
+
+    /// Probe the plugin's backend
+    ///
+    /// The server calls this through `plugin_health` every
+    /// `health_interval_secs` of the mount config (30 by default), logs
+    /// changes of state and lists the last result with the mount, so a
+    /// plugin whose credentials or backend broke is noticed before the
+    /// first failed call. An error counts as unhealthy. The default
+    /// reports healthy.
+    fn health(&self) -> Result<HealthStatus> {
+        Ok(HealthStatus::healthy())
+    }
+
+    /// How long the server may cache this plugin's results
+    ///
+    /// Queried after `initialize` through `plugin_cache_policy`. The server
+    /// answers `stat` (and, with `data_cache`, whole-file reads) from its
+    /// cache until the TTL runs out or a call through the mount changes the
+    /// path. Plugins with static contents can ask for long TTLs; ones whose
+    /// contents change on every read should return `CachePolicy::none()`.
+    /// Per-entry `stat_valid_secs` hints take precedence over `attr_ttl`.
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::default()
+    }
+
+    /// Serialize the plugin's in-memory state
+    ///
+    /// The server stores the result through `plugin_snapshot` and passes
+    /// it to `restore` after the plugin is next initialized (see the
+    /// `state` module). The default, for plugins without state, is empty.
+    fn snapshot(&self) -> Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+
+    /// Replace the plugin's state with a `snapshot`
+    ///
+    /// The default accepts only the empty snapshot.
+    fn restore(&mut self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::state::bad_snapshot("plugin keeps no state"))
+        }
+    }
+
     /// Shutdown the filesystem
     ///
     /// This is called when the filesystem is being unmounted.
@@ -39,6 +133,18 @@ pub trait FileSystem {
         Ok(())
     }
 
+    /// Release memory held by pools and caches
+    ///
+    /// Called when the mount has gone `idle_trim_secs` without a call (the
+    /// host never trims mounts without that setting). Anything dropped here
+    /// must be rebuildable on demand; the filesystem stays mounted.
+    fn trim(&mut self) {}
+
+    /// Space and inode usage of the whole filesystem, for `df`
+    fn statfs(&self) -> Result<FsStats> {
+        Err(crate::types::Error::NotSupported("statfs".to_string()))
+    }
+
     /// Read data from a file
     ///
     /// # Arguments
@@ -46,7 +152,29 @@ pub trait FileSystem {
     /// * `offset` - Starting position (0 for beginning)
     /// * `size` - Number of bytes to read (-1 for all)
     fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
-        Err(crate::types::Error::ReadOnly)
+        Err(crate::types::Error::NotSupported("read".to_string()))
+    }
+
+    /// Read data from a file, saying whether the end was reached
+    ///
+    /// This is what `fs_read_result` serves. The default calls `read` and
+    /// takes a short read as the end of the file (`ReadResult::from_read`);
+    /// plugins whose reads can come back short mid-file, such as streams,
+    /// override it.
+    fn read_result(&self, path: &str, offset: i64, size: i64) -> Result<ReadResult> {
+        Ok(ReadResult::from_read(self.read(path, offset, size)?, size))
+    }
+
+    /// Read several `(offset, size)` ranges of one file in one call
+    ///
+    /// For scattered reads such as archive indexes or parquet footers.
+    /// The result has one entry per range, in order. The default calls
+    /// `read` for each range.
+    fn read_ranges(&self, path: &str, ranges: &[(i64, i64)]) -> Result<Vec<Vec<u8>>> {
+        ranges
+            .iter()
+            .map(|&(offset, size)| self.read(path, offset, size))
+            .collect()
     }
 
     /// Write data to a file
@@ -55,11 +183,164 @@ pub trait FileSystem {
         Err(crate::types::Error::ReadOnly)
     }
 
+    /// Write data to a file, with the outcome as a `WriteResult`
+    ///
+    /// This is what `fs_write_result` serves. The default calls `write`
+    /// and treats a non-empty reply as the response. Control files that
+    /// answer writes should override it (and have `write` return
+    /// `into_reply` of it), so the server can tell their responses from
+    /// ordinary writes.
+    fn write_result(&mut self, path: &str, data: &[u8]) -> Result<WriteResult> {
+        let reply = self.write(path, data)?;
+        Ok(WriteResult::from_reply(data.len(), reply))
+    }
+
+    /// Write data at `offset` without replacing the rest of the file
+    ///
+    /// Returns the number of bytes written. The default handles offset 0
+    /// with `write`, replacing the file, and returns `ReadOnly` otherwise.
+    fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        if offset != 0 {
+            return Err(crate::types::Error::ReadOnly);
+        }
+        self.write(path, data)?;
+        Ok(data.len() as i64)
+    }
+
+    /// Add `data` to the end of `path`, creating it if needed (`O_APPEND`)
+    ///
+    /// Returns the number of bytes appended. The default reads the whole
+    /// file and writes it back with `data` added, which works on plugins
+    /// that only take whole-buffer writes; plugins with `write_at` or a
+    /// native append should override it.
+    fn append(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        let mut contents = match self.read(path, 0, -1) {
+            Ok(contents) => contents,
+            Err(crate::types::Error::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        contents.extend_from_slice(data);
+        self.write(path, &contents)?;
+        Ok(data.len() as i64)
+    }
+
+    /// Copy `len` bytes of `src` from `src_offset` into `dst` at `dst_offset`
+    ///
+    /// A negative `len` copies to the end of `src`. Returns the number of
+    /// bytes copied, which is short at the end of `src`. The default reads
+    /// the range and writes it with `write_at`; backends that can copy
+    /// without moving the data through the plugin should override it.
+    fn copy_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        src_offset: i64,
+        dst_offset: i64,
+        len: i64,
+    ) -> Result<i64> {
+        let data = self.read(src, src_offset, len)?;
+        self.write_at(dst, dst_offset, &data)
+    }
+
+    /// Offset of the first data at or after `offset` (`SEEK_DATA`)
+    ///
+    /// `None` if there is no data there, i.e. `offset` is at or past the
+    /// end of the file. The default treats the whole file as data;
+    /// sparse backends override this and `seek_hole` so backup tools can
+    /// skip holes.
+    fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        let size = self.stat(path)?.size;
+        Ok((offset < size).then_some(offset))
+    }
+
+    /// Offset of the first hole at or after `offset` (`SEEK_HOLE`)
+    ///
+    /// The end of the file counts as a hole. `None` if `offset` is past
+    /// the end. The default finds only the one at the end.
+    fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        let size = self.stat(path)?.size;
+        Ok((offset <= size).then_some(size))
+    }
+
+    /// Take an advisory lock on `path` for the mount
+    ///
+    /// A lock that conflicts with one held elsewhere fails at once rather
+    /// than waiting; the host retries for clients that asked to block.
+    /// Taking a lock again converts it to `kind`. Plugins over shared
+    /// storage pass these through so that git or sqlite work over a mount.
+    fn lock(&mut self, _path: &str, _kind: LockKind) -> Result<()> {
+        Err(crate::types::Error::NotSupported("lock".to_string()))
+    }
+
+    /// Release the lock `lock` took on `path`
+    fn unlock(&mut self, _path: &str) -> Result<()> {
+        Err(crate::types::Error::NotSupported("unlock".to_string()))
+    }
+
+    /// Check whether the caller may access `path` as `mode` asks
+    /// (access(2))
+    ///
+    /// `mode` is 0 to check existence, or any of 4 (read), 2 (write) and
+    /// 1 (execute). The default checks `stat`'s mode bits against the
+    /// caller in `Context::current` with `check_permission`, and refuses
+    /// writes with `ReadOnly` to plugins that do not claim `WRITABLE`.
+    /// Plugins with their own permission model should override it.
+    fn access(&self, path: &str, mode: u32) -> Result<()> {
+        let info = self.stat(path)?;
+        if mode & 2 != 0 && !self.capabilities().contains(Capabilities::WRITABLE) {
+            return Err(crate::types::Error::ReadOnly);
+        }
+        let ctx = Context::current();
+        for (bit, want) in [(4, Access::Read), (2, Access::Write), (1, Access::Execute)] {
+            if mode & bit != 0 {
+                check_permission(&info, &ctx, want)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Create a new empty file
     fn create(&mut self, _path: &str) -> Result<()> {
         Err(crate::types::Error::ReadOnly)
     }
 
+    /// Create a new empty file, failing with `AlreadyExists` if the path
+    /// exists
+    ///
+    /// The default checks with `stat` and then calls `create`. Calls into a
+    /// plugin instance are serialized, so no other request can slip in
+    /// between; plugins whose backing store can change underneath them
+    /// should override this with an atomic operation.
+    fn create_exclusive(&mut self, path: &str) -> Result<()> {
+        match self.stat(path) {
+            Ok(_) => Err(crate::types::Error::AlreadyExists),
+            Err(crate::types::Error::NotFound) => self.create(path),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a file as `flags` asks, as open(2) with `O_CREAT`
+    ///
+    /// The default builds on `create_exclusive`, `stat`, `create` and
+    /// `chmod`, so it carries their caveats; `mode` is only applied to a
+    /// file this call created.
+    fn create_with_flags(&mut self, path: &str, flags: CreateFlags) -> Result<()> {
+        if flags.exclusive {
+            self.create_exclusive(path)?;
+        } else {
+            match self.stat(path) {
+                Ok(_) if !flags.truncate => return Ok(()),
+                Ok(_) => return self.create(path),
+                Err(crate::types::Error::NotFound) => self.create(path)?,
+                Err(e) => return Err(e),
+            }
+        }
+        match flags.mode {
+            Some(mode) => self.chmod(path, mode),
+            None => Ok(()),
+        }
+    }
+
     /// Create a new directory
     fn mkdir(&mut self, _path: &str, _perm: u32) -> Result<()> {
         Err(crate::types::Error::ReadOnly)
@@ -81,15 +362,343 @@ pub trait FileSystem {
     /// List directory contents
     fn readdir(&self, path: &str) -> Result<Vec<FileInfo>>;
 
+    /// List a directory for `fs_readdir_plus`
+    ///
+    /// The server lists through this instead of `readdir` when it caches
+    /// the plugin's results (see `cache_policy`), and answers `stat` for
+    /// every entry from the listing until its `stat_valid_secs`, or the
+    /// policy's `entry_ttl`, run out. Entries must therefore be as full as
+    /// `stat` would return them. The default is `readdir`; plugins whose
+    /// listings leave fields out must override it.
+    fn readdir_plus(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.readdir(path)
+    }
+
+    /// List a directory as a stream the host pulls batches from
+    ///
+    /// Served through `fs_readdir_open` and `fs_readdir_next` (see the
+    /// `stream` module). The default streams the full `readdir`; plugins
+    /// that can produce entries lazily should override it so the listing
+    /// is never held in memory whole.
+    fn readdir_stream(&self, path: &str) -> Result<ReaddirStream> {
+        self.readdir(path).map(ReaddirStream::from_entries)
+    }
+
+    /// List at most `limit` entries of a directory, starting at `cursor`
+    ///
+    /// `cursor` is `None` for the first page and otherwise the cursor the
+    /// previous page returned; a `None` cursor in the result ends the
+    /// listing. The default opens `readdir_stream` on the first page and
+    /// keeps it between pages, so each entry is listed once; a cursor can
+    /// only be used once. Plugins with cursors of their own (an object
+    /// store continuation token) can override it.
+    fn readdir_page(
+        &self,
+        path: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FileInfo>, Option<String>)> {
+        let stream = match cursor {
+            Some(token) => u32::try_from(crate::ffi::decode_dir_token(path, token)?).map_err(
+                |_| crate::types::Error::InvalidInput("invalid continuation token".to_string()),
+            )?,
+            None => crate::stream::open(self.readdir_stream(path)?)?,
+        };
+        let (entries, more) = crate::stream::page(stream, limit.max(1))?;
+        let next = more.then(|| crate::ffi::encode_dir_token(path, stream as usize));
+        Ok((entries, next))
+    }
+
+    /// `read` on behalf of the caller in `ctx`
+    ///
+    /// The glue calls the `*_with_ctx` methods with the context the host
+    /// set for the call (see the `context` module). Their defaults ignore
+    /// it and call the plain method, so plugins only override these to
+    /// serve per-user views or log who asked.
+    fn read_with_ctx(
+        &self,
+        _ctx: &OpContext,
+        path: &str,
+        offset: i64,
+        size: i64,
+    ) -> Result<Vec<u8>> {
+        self.read(path, offset, size)
+    }
+
+    /// `write` on behalf of the caller in `ctx`
+    fn write_with_ctx(&mut self, _ctx: &OpContext, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        self.write(path, data)
+    }
+
+    /// `stat` on behalf of the caller in `ctx`
+    fn stat_with_ctx(&self, _ctx: &OpContext, path: &str) -> Result<FileInfo> {
+        self.stat(path)
+    }
+
+    /// `readdir` on behalf of the caller in `ctx`
+    fn readdir_with_ctx(&self, _ctx: &OpContext, path: &str) -> Result<Vec<FileInfo>> {
+        self.readdir(path)
+    }
+
+    /// `create` on behalf of the caller in `ctx`
+    fn create_with_ctx(&mut self, _ctx: &OpContext, path: &str) -> Result<()> {
+        self.create(path)
+    }
+
+    /// `mkdir` on behalf of the caller in `ctx`
+    fn mkdir_with_ctx(&mut self, _ctx: &OpContext, path: &str, perm: u32) -> Result<()> {
+        self.mkdir(path, perm)
+    }
+
+    /// `remove` on behalf of the caller in `ctx`
+    fn remove_with_ctx(&mut self, _ctx: &OpContext, path: &str) -> Result<()> {
+        self.remove(path)
+    }
+
+    /// `rename` on behalf of the caller in `ctx`
+    fn rename_with_ctx(
+        &mut self,
+        _ctx: &OpContext,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<()> {
+        self.rename(old_path, new_path)
+    }
+
+    /// Run a batch of read-only calls, one result per call in order
+    ///
+    /// The glue serves `fs_batch` with this. The default runs each call
+    /// through `stat`, `read` or `readdir`; plugins with a backend that
+    /// can answer many lookups in one request override it.
+    fn batch(&self, ops: Vec<FsOp>) -> Vec<FsResult> {
+        ops.into_iter().map(|op| batch::run(self, op)).collect()
+    }
+
+    /// Return the host's stat response for `path` unmodified
+    ///
+    /// Proxy plugins can forward `HostFS::stat_raw` here for proxied
+    /// subtrees. `None` (the default) falls back to `stat`.
+    fn stat_passthrough(&self, _path: &str) -> Option<Result<RawJson>> {
+        None
+    }
+
+    /// Return the host's readdir response for `path` unmodified
+    ///
+    /// Used for `fs_readdir` only; paged and streamed listings go through
+    /// `readdir_page` and `readdir_stream`. `None` (the default) falls back to `readdir`.
+    fn readdir_passthrough(&self, _path: &str) -> Option<Result<RawJson>> {
+        None
+    }
+
     /// Rename/move a file or directory
     fn rename(&mut self, _old_path: &str, _new_path: &str) -> Result<()> {
         Err(crate::types::Error::ReadOnly)
     }
 
+    /// Rename as `flags` asks, as renameat2(2)
+    ///
+    /// The default handles `NOREPLACE` with a `stat` before `rename`, so
+    /// it can still replace a path created in between; it rejects
+    /// `EXCHANGE`, which cannot be emulated without a window where one
+    /// of the paths is missing. Plugins over a store with transactions
+    /// should override it.
+    fn rename_with_flags(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        if flags.contains(RenameFlags::EXCHANGE) {
+            return Err(crate::types::Error::NotSupported("rename exchange".to_string()));
+        }
+        if flags.contains(RenameFlags::NOREPLACE) {
+            match self.stat(new_path) {
+                Ok(_) => return Err(crate::types::Error::AlreadyExists),
+                Err(crate::types::Error::NotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.rename(old_path, new_path)
+    }
+
     /// Change file permissions
     fn chmod(&mut self, _path: &str, _mode: u32) -> Result<()> {
+        Err(crate::types::Error::NotSupported("chmod".to_string()))
+    }
+
+    /// Change the owner and group of a file
+    fn chown(&mut self, _path: &str, _uid: u32, _gid: u32) -> Result<()> {
+        Err(crate::types::Error::NotSupported("chown".to_string()))
+    }
+
+    /// Set access and modification times, in Unix seconds
+    fn set_times(&mut self, _path: &str, _atime: i64, _mtime: i64) -> Result<()> {
+        Err(crate::types::Error::NotSupported("set_times".to_string()))
+    }
+
+    /// Shrink or zero-extend a file to `size` bytes
+    fn truncate(&mut self, _path: &str, _size: i64) -> Result<()> {
         Err(crate::types::Error::ReadOnly)
     }
+
+    /// Reserve space for `len` bytes at `offset` (fallocate(2) mode 0)
+    ///
+    /// The file grows to at least `offset + len` bytes; existing data is
+    /// kept. The default only extends the size with `truncate`, without
+    /// reserving anything; backends that can allocate ahead should
+    /// override it.
+    fn allocate(&mut self, path: &str, offset: i64, len: i64) -> Result<()> {
+        let end = offset.checked_add(len).ok_or(crate::types::Error::TooLarge)?;
+        if self.stat(path)?.size < end {
+            self.truncate(path, end)?;
+        }
+        Ok(())
+    }
+
+    /// Usage and limits of the tree under `path`
+    ///
+    /// The default reports neither, for plugins without quotas; `QuotaFS`
+    /// enforces limits around any filesystem.
+    fn quota(&self, _path: &str) -> Result<QuotaInfo> {
+        Ok(QuotaInfo::default())
+    }
+
+    /// Set the limits of the tree under `path`
+    ///
+    /// The usage fields of `limits` are ignored; zero limits remove the
+    /// quota.
+    fn set_quota(&mut self, _path: &str, _limits: QuotaInfo) -> Result<()> {
+        Err(crate::types::Error::NotSupported("set_quota".to_string()))
+    }
+
+    /// Push buffered writes to `path` to the backend
+    ///
+    /// The server calls this after a client finishes writing a file
+    /// through a stream; does nothing by default.
+    fn flush(&mut self, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Make writes to `path` durable before returning
+    ///
+    /// Called for `POST /api/v1/fsync`; does nothing by default.
+    fn fsync(&mut self, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create `new` as a hard link to the file `existing`
+    ///
+    /// Plugins supporting links should report the link count in
+    /// `FileInfo::nlink`.
+    fn link(&mut self, _existing: &str, _new: &str) -> Result<()> {
+        Err(crate::types::Error::NotSupported("link".to_string()))
+    }
+
+    /// Create a symbolic link at `link_path` pointing to `target`
+    ///
+    /// `target` is stored as given, relative or absolute, and need not
+    /// exist. `stat` and `readdir` should report the link with
+    /// `FileInfo::symlink`.
+    fn symlink(&mut self, _target: &str, _link_path: &str) -> Result<()> {
+        Err(crate::types::Error::NotSupported("symlink".to_string()))
+    }
+
+    /// Target of the symbolic link at `path`
+    fn readlink(&self, _path: &str) -> Result<String> {
+        Err(crate::types::Error::NotSupported("readlink".to_string()))
+    }
+
+    /// Value of the extended attribute `name` on `path`
+    ///
+    /// `NotFound` if the attribute is not set.
+    fn getxattr(&self, _path: &str, _name: &str) -> Result<Vec<u8>> {
+        Err(crate::types::Error::NotFound)
+    }
+
+    /// Set the extended attribute `name` on `path`
+    fn setxattr(&mut self, _path: &str, _name: &str, _value: &[u8]) -> Result<()> {
+        Err(crate::types::Error::NotSupported("setxattr".to_string()))
+    }
+
+    /// Names of the extended attributes set on `path`
+    fn listxattr(&self, _path: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Remove the extended attribute `name` from `path`
+    fn removexattr(&mut self, _path: &str, _name: &str) -> Result<()> {
+        Err(crate::types::Error::NotSupported("removexattr".to_string()))
+    }
+
+    /// Start reporting changes to `path` and anything below it
+    ///
+    /// Plugins hand out ids with `watch::Watches` and queue events with
+    /// `Watches::notify`; see the `watch` module.
+    fn watch(&mut self, _path: &str) -> Result<WatchId> {
+        Err(crate::types::Error::NotSupported("watch".to_string()))
+    }
+
+    /// Stop a watch started by `watch`
+    fn unwatch(&mut self, _id: WatchId) -> Result<()> {
+        Err(crate::types::Error::NotFound)
+    }
+
+    /// Open `path` for repeated reads through `read_at`
+    ///
+    /// The server opens a handle for each file it streams to a client and
+    /// reads it range by range.
+    ///
+    /// The default only checks that `path` exists and returns a handle
+    /// remembering it, so `read_at` falls back to a stateless `read`.
+    /// Backends that resolve paths expensively override `open`, `read_at`
+    /// and `close` together and keep what they resolved under `Handle::id`.
+    fn open(&mut self, path: &str, flags: OpenFlags) -> Result<Handle> {
+        self.stat(path)?;
+        Ok(Handle::new(path, flags))
+    }
+
+    /// Read from a handle returned by `open`
+    fn read_at(&self, handle: &Handle, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.read(&handle.path, offset, size)
+    }
+
+    /// Release a handle returned by `open`
+    fn close(&mut self, _handle: Handle) -> Result<()> {
+        Ok(())
+    }
+
+    /// Open `path` as a buffered `std::io::Read + Seek` stream
+    ///
+    /// For reusing `Read`-based libraries on another filesystem's files;
+    /// see `FileReader`.
+    fn open_read(&self, path: &str) -> Result<FileReader<'_, Self>>
+    where
+        Self: Sized,
+    {
+        FileReader::open(self, path)
+    }
+}
+
+/// Replace the contents of `path` without exposing a partial file
+///
+/// Writes `data` to a hidden sibling and renames it over `path`, so a
+/// crash mid-write leaves either the old file or the new one. The temp
+/// file is removed if the write or rename fails. `fs` must support
+/// `rename`; durability beyond that is whatever its `write` provides.
+pub fn atomic_write<F: FileSystem + ?Sized>(
+    fs: &mut F,
+    path: &str,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let temp = path::temp_sibling(path)?;
+    let result = fs.write(&temp, data).and_then(|response| {
+        fs.rename(&temp, path)?;
+        Ok(response)
+    });
+    if result.is_err() {
+        let _ = fs.remove(&temp);
+    }
+    result
 }
 
 /// Read-only filesystem helper
@@ -125,6 +734,10 @@ impl<T: ReadOnlyFileSystem> FileSystem for T {
         ReadOnlyFileSystem::readme(self)
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::NONE
+    }
+
     fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
         ReadOnlyFileSystem::read(self, path, offset, size)
     }
@@ -137,3 +750,185 @@ impl<T: ReadOnlyFileSystem> FileSystem for T {
         ReadOnlyFileSystem::readdir(self, path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Error;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+        fail_rename: bool,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(crate::range::slice_range(data, offset, size).to_vec())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            self.files.remove(path).map(|_| ()).ok_or(Error::NotFound)
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            if self.fail_rename {
+                return Err(Error::Io("rename failed".to_string()));
+            }
+            let data = self.files.remove(old_path).ok_or(Error::NotFound)?;
+            self.files.insert(new_path.to_string(), data);
+            Ok(())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(path, data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+
+        fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+            let data = self.files.get_mut(path).ok_or(Error::NotFound)?;
+            data.resize(size as usize, 0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_atomic_write() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"old").unwrap();
+        atomic_write(&mut fs, "/a", b"new").unwrap();
+        assert_eq!(fs.files.keys().collect::<Vec<_>>(), ["/a"]);
+        assert_eq!(fs.files["/a"], b"new");
+
+        // A failed rename keeps the old contents and cleans up the temp file
+        fs.fail_rename = true;
+        assert!(atomic_write(&mut fs, "/a", b"newer").is_err());
+        assert_eq!(fs.files.keys().collect::<Vec<_>>(), ["/a"]);
+        assert_eq!(fs.files["/a"], b"new");
+    }
+
+    #[test]
+    fn test_read_ranges_default() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"hello").unwrap();
+        let parts = fs.read_ranges("/a", &[(1, 2), (4, -1), (9, 1)]).unwrap();
+        assert_eq!(parts, [b"el".to_vec(), b"o".to_vec(), Vec::new()]);
+        assert_eq!(fs.read_ranges("/b", &[(0, 1)]), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn test_copy_range_fallback() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"hello").unwrap();
+        assert_eq!(fs.copy_range("/a", "/b", 1, 0, 3), Ok(3));
+        assert_eq!(fs.files["/b"], b"ell");
+        assert_eq!(fs.copy_range("/a", "/c", 2, 0, -1), Ok(3));
+        assert_eq!(fs.copy_range("/a", "/b", 0, 2, 1), Err(Error::ReadOnly));
+    }
+
+    #[test]
+    fn test_rename_flags_default() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"a").unwrap();
+        fs.write("/b", b"b").unwrap();
+        let noreplace = RenameFlags::NOREPLACE;
+        assert_eq!(fs.rename_with_flags("/a", "/b", noreplace), Err(Error::AlreadyExists));
+        assert_eq!(fs.rename_with_flags("/a", "/c", noreplace), Ok(()));
+        assert!(fs.rename_with_flags("/b", "/c", RenameFlags::EXCHANGE).is_err());
+        assert_eq!(fs.files["/b"], b"b");
+    }
+
+    #[test]
+    fn test_seek_defaults() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"hello").unwrap();
+        assert_eq!(fs.seek_data("/a", 2), Ok(Some(2)));
+        assert_eq!(fs.seek_data("/a", 5), Ok(None));
+        assert_eq!(fs.seek_hole("/a", 2), Ok(Some(5)));
+        assert_eq!(fs.seek_hole("/a", 5), Ok(Some(5)));
+        assert_eq!(fs.seek_hole("/a", 6), Ok(None));
+    }
+
+    #[test]
+    fn test_access_default() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"x").unwrap();
+        // No caller identity, so only existence and writability count
+        assert_eq!(fs.access("/a", 0), Ok(()));
+        assert_eq!(fs.access("/a", 6), Ok(()));
+        assert_eq!(fs.access("/b", 4), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn test_append_default() {
+        let mut fs = MemFS::default();
+        assert_eq!(fs.append("/a", b"x"), Ok(1));
+        assert_eq!(fs.append("/a", b"yz"), Ok(2));
+        assert_eq!(fs.files["/a"], b"xyz");
+    }
+
+    #[test]
+    fn test_readdir_page_default() {
+        struct Listing;
+
+        impl ReadOnlyFileSystem for Listing {
+            fn name(&self) -> &str {
+                "listing"
+            }
+
+            fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+                Err(Error::NotFound)
+            }
+
+            fn stat(&self, _path: &str) -> Result<FileInfo> {
+                Err(Error::NotFound)
+            }
+
+            fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+                Ok((0..5).map(|i| FileInfo::file(format!("f{}", i), 0, 0o644)).collect())
+            }
+        }
+
+        let fs = Listing;
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (entries, next) = fs.readdir_page("/d", cursor.as_deref(), 2).unwrap();
+            names.extend(entries.into_iter().map(|e| e.name));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(names, ["f0", "f1", "f2", "f3", "f4"]);
+
+        let (_, next) = fs.readdir_page("/d", None, 2).unwrap();
+        assert!(fs.readdir_page("/other", next.as_deref(), 2).is_err());
+    }
+
+    #[test]
+    fn test_allocate_extends() {
+        let mut fs = MemFS::default();
+        fs.write("/a", b"hello").unwrap();
+        fs.allocate("/a", 0, 3).unwrap();
+        assert_eq!(fs.files["/a"], b"hello");
+        fs.allocate("/a", 4, 4).unwrap();
+        assert_eq!(fs.files["/a"], b"hello\0\0\0");
+        assert_eq!(fs.allocate("/b", 0, 1), Err(Error::NotFound));
+    }
+}
@@ -0,0 +1,56 @@
+//! Name resolution through the host
+//!
+//! Plugins resolve names with the server's resolver instead of bundling
+//! one. The mount decides which names: those in `host_dns_allow`, or
+//! without it the hosts the plugin may connect to under `host_tcp_allow`.
+//! Other names fail with `PermissionDenied`, unknown ones with `NotFound`.
+
+use crate::host_fs::string_reply;
+use crate::types::{Error, Result};
+use std::ffi::CString;
+use std::net::IpAddr;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_dns_resolve(name: *const u8) -> u64;
+}
+
+/// The host's resolver
+pub struct HostDns;
+
+impl HostDns {
+    /// Addresses of `name`, IPv4 and IPv6, in the resolver's order
+    pub fn resolve(name: &str) -> Result<Vec<IpAddr>> {
+        if let Ok(ip) = name.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        let name_c =
+            CString::new(name).map_err(|_| Error::InvalidInput("invalid name".to_string()))?;
+        let json = unsafe { string_reply(host_dns_resolve(name_c.as_ptr() as *const u8))? };
+        parse_addrs(&json)
+    }
+}
+
+fn parse_addrs(json: &str) -> Result<Vec<IpAddr>> {
+    let addrs: Vec<String> = serde_json::from_str(json)
+        .map_err(|e| Error::Io(format!("host returned bad addresses: {}", e)))?;
+    addrs
+        .iter()
+        // Go reports scoped IPv6 addresses as "fe80::1%eth0"
+        .map(|a| a.split('%').next().unwrap_or(a).parse::<IpAddr>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| Error::Io(format!("host returned bad address: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addrs() {
+        let addrs = parse_addrs(r#"["10.0.0.1","::1","fe80::1%eth0"]"#).unwrap();
+        assert_eq!(addrs.len(), 3);
+        assert_eq!(addrs[0], "10.0.0.1".parse::<IpAddr>().unwrap());
+        assert!(parse_addrs(r#"["nope"]"#).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Environment variables from the host
+//!
+//! Credentials often live in the server's environment rather than in
+//! mount config. A plugin may read the variables the mount grants it with
+//! `host_env_allow` (names, or prefixes ending in `*`):
+//!
+//! ```json
+//! {"host_env_allow": ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"]}
+//! ```
+//!
+//! Reading anything else fails with `PermissionDenied`. Secrets are best
+//! read with `HostEnv::get_secret`, which leaves no plain copy behind.
+
+use crate::host_fs::string_reply;
+use crate::memory::SecretBuffer;
+use crate::types::{Config, Error, Result};
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_env_get(key: *const u8) -> u64;
+}
+
+/// Read access to allowlisted host environment variables
+pub struct HostEnv;
+
+impl HostEnv {
+    /// Value of `key`, or `None` if it is unset
+    pub fn get(key: &str) -> Result<Option<String>> {
+        let key_c = variable_name(key)?;
+        match unsafe { string_reply(host_env_get(key_c.as_ptr() as *const u8)) } {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Value of `key` as a `SecretBuffer`, or `None` if it is unset
+    ///
+    /// The copy the host wrote into linear memory is zeroed once read.
+    pub fn get_secret(key: &str) -> Result<Option<SecretBuffer>> {
+        let key_c = variable_name(key)?;
+        let result = unsafe { host_env_get(key_c.as_ptr() as *const u8) };
+        let ptr = (result & 0xFFFFFFFF) as u32;
+        let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+        if err_ptr != 0 {
+            let message = unsafe { crate::memory::CString::from_ptr(err_ptr as *const u8)? };
+            return Err(Error::from_host(&message));
+        }
+        if ptr == 0 {
+            return Ok(None);
+        }
+        unsafe { SecretBuffer::take_from_ptr(ptr as *mut u8) }.map(Some)
+    }
+
+    /// `config_key` from the mount config, else variable `env_key`
+    ///
+    /// The usual lookup for credentials: explicit config wins.
+    pub fn config_or_env(
+        config: &Config,
+        config_key: &str,
+        env_key: &str,
+    ) -> Result<Option<String>> {
+        match config.get_str(config_key) {
+            Some(value) => Ok(Some(value.to_string())),
+            None => Self::get(env_key),
+        }
+    }
+}
+
+fn variable_name(key: &str) -> Result<CString> {
+    if key.is_empty() || key.contains('=') {
+        return Err(Error::InvalidInput(format!("bad variable name {:?}", key)));
+    }
+    CString::new(key).map_err(|_| Error::InvalidInput("variable name contains NUL".to_string()))
+}
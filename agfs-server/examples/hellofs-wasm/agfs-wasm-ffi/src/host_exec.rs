@@ -0,0 +1,131 @@
+//! Running host programs
+//!
+//! Plugins that wrap command-line tools (git, ffmpeg) run them on the
+//! server with `host_exec`. It is off unless the mount lists the programs
+//! it grants:
+//!
+//! ```json
+//! {"host_exec_allow": ["git"]}
+//! ```
+//!
+//! ```ignore
+//! let out = HostCommand::new("git").args(&["log", "--oneline"]).output()?;
+//! if !out.success() {
+//!     return Err(Error::Io(String::from_utf8_lossy(&out.stderr).into_owned()));
+//! }
+//! ```
+//!
+//! Programs get no environment beyond `PATH`. A program not granted fails
+//! with `PermissionDenied`, one that runs too long with `Timeout`.
+
+use crate::host_fs::string_reply;
+use crate::types::{Error, Result};
+use serde::Deserialize;
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_exec(argv_json: *const u8, stdin: *const u8, stdin_len: u32, timeout_ms: u32) -> u64;
+}
+
+/// Timeout of a command that does not set one
+pub const DEFAULT_TIMEOUT_MS: u32 = 60_000;
+
+/// A program invocation being built; `output` runs it on the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostCommand {
+    argv: Vec<String>,
+    stdin: Vec<u8>,
+    timeout_ms: u32,
+}
+
+impl HostCommand {
+    /// Run `program`, a name looked up in the host's `PATH` or an
+    /// absolute path, exactly as granted in `host_exec_allow`
+    pub fn new(program: &str) -> Self {
+        Self {
+            argv: vec![program.to_string()],
+            stdin: Vec::new(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.argv.push(arg.to_string());
+        self
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.argv.extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    /// Bytes fed to the program's standard input
+    pub fn stdin(mut self, data: Vec<u8>) -> Self {
+        self.stdin = data;
+        self
+    }
+
+    pub fn timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Run the program to completion and collect its output
+    ///
+    /// A nonzero exit is not an error; check `ExecOutput::success`.
+    pub fn output(self) -> Result<ExecOutput> {
+        let argv = serde_json::to_string(&self.argv)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        let argv = CString::new(argv)
+            .map_err(|_| Error::InvalidInput("argument contains NUL".to_string()))?;
+        let stdin_len = u32::try_from(self.stdin.len()).map_err(|_| Error::TooLarge)?;
+        let json = unsafe {
+            string_reply(host_exec(
+                argv.as_ptr() as *const u8,
+                self.stdin.as_ptr(),
+                stdin_len,
+                self.timeout_ms,
+            ))?
+        };
+        ExecOutput::from_json(&json)
+    }
+}
+
+/// What a finished program returned
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ExecOutput {
+    #[serde(rename = "ExitCode")]
+    pub exit_code: i32,
+    #[serde(rename = "Stdout", default, deserialize_with = "crate::base64::deserialize")]
+    pub stdout: Vec<u8>,
+    #[serde(rename = "Stderr", default, deserialize_with = "crate::base64::deserialize")]
+    pub stderr: Vec<u8>,
+}
+
+impl ExecOutput {
+    fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::Io(format!("host returned bad exec result: {}", e)))
+    }
+
+    /// Whether the program exited with status 0
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_output_json() {
+        let out = ExecOutput::from_json(r#"{"ExitCode":1,"Stdout":"b2s=","Stderr":""}"#).unwrap();
+        assert_eq!(out.exit_code, 1);
+        assert!(!out.success());
+        assert_eq!(out.stdout, b"ok");
+        let cmd = HostCommand::new("git").arg("log").args(&["-n", "1"]);
+        assert_eq!(cmd.argv, ["git", "log", "-n", "1"]);
+    }
+}
@@ -3,31 +3,154 @@
 //! This module provides access to the host filesystem exposed by agfs-server.
 //! WASM plugins can use this to access files on the host system.
 
-use crate::types::{Error, FileInfo, Result};
+use crate::chunk::ChunkSizer;
+use crate::memory::borrow_slice;
+use crate::range::validate_offset;
+use crate::types::{CreateFlags, Error, FileInfo, LockKind, RawJson, RenameFlags, Result};
 use std::ffi::CString;
+use std::sync::Mutex;
 
 // Import host functions from the "env" module
 #[link(wasm_import_module = "env")]
 extern "C" {
     fn host_fs_read(path: *const u8, offset: i64, size: i64) -> u64;
     fn host_fs_write(path: *const u8, data: *const u8, len: u32) -> u64;
+    fn host_fs_append(path: *const u8, data: *const u8, len: u32) -> u32;
+    fn host_fs_write_at(path: *const u8, offset: i64, data: *const u8, len: u32) -> u64;
     fn host_fs_stat(path: *const u8) -> u64;
+    fn host_fs_lstat(path: *const u8) -> u64;
+    fn host_fs_realpath(path: *const u8) -> u64;
     fn host_fs_readdir(path: *const u8) -> u64;
     fn host_fs_create(path: *const u8) -> u32;
+    fn host_fs_create_exclusive(path: *const u8) -> u32;
+    fn host_fs_create_flags(path: *const u8, flags: u32, mode: u32) -> u32;
     fn host_fs_mkdir(path: *const u8, perm: u32) -> u32;
     fn host_fs_remove(path: *const u8) -> u32;
     fn host_fs_remove_all(path: *const u8) -> u32;
     fn host_fs_rename(old_path: *const u8, new_path: *const u8) -> u32;
+    fn host_fs_rename_flags(old_path: *const u8, new_path: *const u8, flags: u32) -> u32;
     fn host_fs_chmod(path: *const u8, mode: u32) -> u32;
+    fn host_fs_chown(path: *const u8, uid: u32, gid: u32) -> u32;
+    fn host_fs_set_times(path: *const u8, atime: i64, mtime: i64) -> u32;
+    fn host_fs_truncate(path: *const u8, size: i64) -> u32;
+    fn host_fs_allocate(path: *const u8, offset: i64, len: i64) -> u32;
+    fn host_fs_access(path: *const u8, mode: u32) -> u32;
+    fn host_fs_seek(path: *const u8, offset: i64, whence: u32) -> i64;
+    fn host_fs_lock(path: *const u8, kind: u32) -> u32;
+    fn host_fs_unlock(path: *const u8) -> u32;
+    fn host_fs_link(existing: *const u8, new: *const u8) -> u32;
+    fn host_fs_symlink(target: *const u8, link_path: *const u8) -> u32;
+    fn host_fs_readlink(path: *const u8) -> u64;
+    fn host_fs_open_reader(path: *const u8) -> u64;
+    fn host_fs_read_chunk(handle: u32, max: u32) -> u64;
+    fn host_fs_close_reader(handle: u32) -> u32;
+    fn host_fs_allowed_roots() -> u64;
+}
+
+/// Kinds of host access a plugin can declare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostVerb {
+    /// `read`, `stat`, `readdir`
+    Read,
+    /// `write`, `create`, `mkdir`, `chmod`, and the target of `rename`
+    Write,
+    /// `remove`, `remove_all`, and the source of `rename`
+    Delete,
+}
+
+/// Host paths and verbs a plugin is allowed to use
+///
+/// Returned from `FileSystem::host_capabilities`; once installed, every
+/// `HostFS` call outside the declaration fails with `PermissionDenied`, so
+/// compromised plugin logic cannot reach other host data.
+///
+/// ```
+/// use agfs_wasm_ffi::host_fs::{HostCapabilities, HostVerb};
+///
+/// let caps = HostCapabilities::new()
+///     .allow("/srv/data", &[HostVerb::Read])
+///     .allow("/srv/data/uploads", &[HostVerb::Read, HostVerb::Write]);
+/// assert!(caps.permits("/srv/data/a.txt", HostVerb::Read));
+/// assert!(!caps.permits("/srv/data/a.txt", HostVerb::Write));
+/// assert!(!caps.permits("/srv/database", HostVerb::Read));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostCapabilities {
+    grants: Vec<(String, Vec<HostVerb>)>,
+}
+
+impl HostCapabilities {
+    /// An empty declaration, which permits nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `verbs` on `prefix` and everything below it
+    pub fn allow(mut self, prefix: &str, verbs: &[HostVerb]) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        self.grants.push((prefix.to_string(), verbs.to_vec()));
+        self
+    }
+
+    /// Check whether `verb` is allowed on `path`
+    pub fn permits(&self, path: &str, verb: HostVerb) -> bool {
+        self.grants.iter().any(|(prefix, verbs)| {
+            verbs.contains(&verb)
+                && path
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.is_empty())
+        })
+    }
+}
+
+// None until the plugin declares its needs; after that it is sealed
+static HOST_CAPABILITIES: Mutex<Option<HostCapabilities>> = Mutex::new(None);
+
+fn check_access(path: &str, verb: HostVerb) -> Result<()> {
+    match &*HOST_CAPABILITIES.lock().unwrap() {
+        Some(caps) if !caps.permits(path, verb) => Err(Error::PermissionDenied),
+        _ => Ok(()),
+    }
+}
+
+// Check a host path against the declared capabilities and encode it
+fn host_path(path: &str, verb: HostVerb) -> Result<CString> {
+    check_access(path, verb)?;
+    encode_host_path(path)
+}
+
+// Encode a path for the host, refusing anything that could climb out of
+// the directory the host resolves it against
+fn encode_host_path(path: &str) -> Result<CString> {
+    if path.split('/').any(|segment| segment == "..") {
+        return Err(Error::InvalidInput("path traversal is not allowed".to_string()));
+    }
+    CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))
 }
 
 /// HostFS provides access to the host filesystem from WASM
+///
+/// With `host_call_timeout_ms` set on the mount, a call the host cannot
+/// finish in time fails with `Error::Timeout` instead of blocking the plugin.
 pub struct HostFS;
 
 impl HostFS {
+    /// Host path prefixes the mount lets this plugin reach
+    ///
+    /// The server enforces these on every `host_fs_*` call, from the
+    /// `host_allowed_roots` mount option; `None` means the mount sets no
+    /// limit. `HostCapabilities` narrows access further from inside the
+    /// plugin. Needs a host that exports `host_fs_allowed_roots`.
+    pub fn allowed_roots() -> Result<Option<Vec<String>>> {
+        let json = unsafe { string_reply(host_fs_allowed_roots()) }?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::Other(format!("failed to parse allowed roots: {}", e)))
+    }
+
     /// Read data from a file on the host filesystem
     pub fn read(path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        check_access(path, HostVerb::Read)?;
+        let path_c = encode_host_path(path)?;
 
         unsafe {
             let result = host_fs_read(path_c.as_ptr() as *const u8, offset, size);
@@ -36,19 +159,65 @@ impl HostFS {
             let data_ptr = (result & 0xFFFFFFFF) as u32;
             let data_size = ((result >> 32) & 0xFFFFFFFF) as u32;
 
+            // (0, error string) from hosts that report the cause
+            if data_ptr == 0 && data_size != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(data_size)?));
+            }
             if data_ptr == 0 {
                 return Err(Error::Io("read failed".to_string()));
             }
 
             // Read data from memory
-            let slice = std::slice::from_raw_parts(data_ptr as *const u8, data_size as usize);
-            Ok(slice.to_vec())
+            Ok(borrow_slice(data_ptr as *const u8, data_size as usize)?.to_vec())
+        }
+    }
+
+    /// Stream a range of a host file to `sink` in adaptively sized chunks
+    ///
+    /// A negative `size` reads to the end of the file. Returns the number of
+    /// bytes delivered. The file is stat'ed first: the host answers a read
+    /// at or past the end with 0, which `read` reports as an error, so the
+    /// transfer stops at the stat'ed size instead of asking for it.
+    pub fn read_chunked<F>(
+        path: &str,
+        offset: i64,
+        size: i64,
+        sizer: &mut ChunkSizer,
+        sink: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        let file_size = Self::stat(path)?.size;
+        read_chunks(Self::read, path, file_size, offset, size, sizer, sink)
+    }
+
+    /// Open a host file for reading from the start in chunks
+    ///
+    /// Unlike `read`, the file never has to fit in guest memory at once,
+    /// so plugins can stream multi-GB host files. Needs a host that
+    /// exports `host_fs_open_reader`, `host_fs_read_chunk` and
+    /// `host_fs_close_reader`.
+    pub fn open_reader(path: &str) -> Result<HostReader> {
+        let path_c = host_path(path, HostVerb::Read)?;
+
+        unsafe {
+            let result = host_fs_open_reader(path_c.as_ptr() as *const u8);
+            let handle = (result & 0xFFFFFFFF) as u32;
+            let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            if handle == 0 {
+                return Err(Error::Io("open failed".to_string()));
+            }
+            Ok(HostReader { handle, eof: false })
         }
     }
 
     /// Write data to a file on the host filesystem
     pub fn write(path: &str, data: &[u8]) -> Result<Vec<u8>> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Write)?;
 
         unsafe {
             let result = host_fs_write(
@@ -61,19 +230,94 @@ impl HostFS {
             let response_ptr = (result & 0xFFFFFFFF) as u32;
             let response_size = ((result >> 32) & 0xFFFFFFFF) as u32;
 
+            // (0, error string) from hosts that report the cause
+            if response_ptr == 0 && response_size != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(response_size)?));
+            }
             if response_ptr == 0 {
                 return Err(Error::Io("write failed".to_string()));
             }
 
             // Read response from memory
-            let slice = std::slice::from_raw_parts(response_ptr as *const u8, response_size as usize);
-            Ok(slice.to_vec())
+            Ok(borrow_slice(response_ptr as *const u8, response_size as usize)?.to_vec())
+        }
+    }
+
+    /// Replace a host file without exposing a partial write
+    ///
+    /// Writes to a hidden sibling and renames it over `path`; the temp file
+    /// is removed on failure. The host API has no fsync, so this protects
+    /// against a plugin or server crash mid-write, not against a power
+    /// loss before the host flushes.
+    pub fn write_atomic(path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let temp = crate::path::temp_sibling(path)?;
+        let result = Self::write(&temp, data).and_then(|response| {
+            Self::rename(&temp, path)?;
+            Ok(response)
+        });
+        if result.is_err() {
+            let _ = Self::remove(&temp);
+        }
+        result
+    }
+
+    /// Add `data` to the end of a file, creating it if needed
+    ///
+    /// The host opens the file with `O_APPEND`, so concurrent appenders do
+    /// not overwrite each other. Needs a host that exports
+    /// `host_fs_append`.
+    pub fn append(path: &str, data: &[u8]) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr =
+                host_fs_append(path_c.as_ptr() as *const u8, data.as_ptr(), data.len() as u32);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Write `data` at `offset`, keeping the rest of the file
+    ///
+    /// Returns the number of bytes written; a gap past the end reads as
+    /// zeros. Needs a host that exports `host_fs_write_at`, which returns
+    /// (bytes written, 0) or (0, error string).
+    pub fn write_at(path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        validate_offset(offset)?;
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let result = host_fs_write_at(
+                path_c.as_ptr() as *const u8,
+                offset,
+                data.as_ptr(),
+                data.len() as u32,
+            );
+            let written = (result & 0xFFFFFFFF) as u32;
+            let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(written as i64)
         }
     }
 
     /// Get file information
+    ///
+    /// The entry is checked with `FileInfo::validate`; a directory size
+    /// reported by the host is cleared first.
     pub fn stat(path: &str) -> Result<FileInfo> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let raw = Self::stat_raw(path)?;
+        let info = serde_json::from_str(raw.as_str())
+            .map_err(|e| Error::Other(format!("failed to parse stat result: {}", e)))?;
+        from_host(info)
+    }
+
+    /// Get file information as the host's JSON, without decoding it
+    pub fn stat_raw(path: &str) -> Result<RawJson> {
+        let path_c = host_path(path, HostVerb::Read)?;
 
         unsafe {
             let result = host_fs_stat(path_c.as_ptr() as *const u8);
@@ -84,23 +328,50 @@ impl HostFS {
 
             // Check for error
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
 
             if json_ptr == 0 {
                 return Err(Error::NotFound);
             }
 
-            let json_str = read_string_from_ptr(json_ptr);
-            serde_json::from_str(&json_str)
-                .map_err(|e| Error::Other(format!("failed to parse stat result: {}", e)))
+            Ok(RawJson(read_string_from_ptr(json_ptr)?))
         }
     }
 
+    /// Get file information without following a final symlink
+    ///
+    /// Needs a host that exports `host_fs_lstat`; the import is only linked
+    /// into plugins that call this.
+    pub fn stat_no_follow(path: &str) -> Result<FileInfo> {
+        let path_c = host_path(path, HostVerb::Read)?;
+        let json = unsafe { string_reply(host_fs_lstat(path_c.as_ptr() as *const u8)) }?;
+        let info = serde_json::from_str(&json)
+            .map_err(|e| Error::Other(format!("failed to parse lstat result: {}", e)))?;
+        from_host(info)
+    }
+
+    /// Resolve every symlink in `path`, returning the host path it names
+    ///
+    /// Needs a host that exports `host_fs_realpath`.
+    pub fn realpath(path: &str) -> Result<String> {
+        let path_c = host_path(path, HostVerb::Read)?;
+        unsafe { string_reply(host_fs_realpath(path_c.as_ptr() as *const u8)) }
+    }
+
     /// Read directory contents
+    ///
+    /// Every entry is checked as in `stat`.
     pub fn readdir(path: &str) -> Result<Vec<FileInfo>> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let raw = Self::readdir_raw(path)?;
+        let infos: Vec<FileInfo> = serde_json::from_str(raw.as_str())
+            .map_err(|e| Error::Other(format!("failed to parse readdir result: {}", e)))?;
+        infos.into_iter().map(from_host).collect()
+    }
+
+    /// Read directory contents as the host's JSON array, without decoding it
+    pub fn readdir_raw(path: &str) -> Result<RawJson> {
+        let path_c = host_path(path, HostVerb::Read)?;
 
         unsafe {
             let result = host_fs_readdir(path_c.as_ptr() as *const u8);
@@ -111,29 +382,58 @@ impl HostFS {
 
             // Check for error
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
 
             if json_ptr == 0 {
-                return Ok(Vec::new());
+                return Ok(RawJson("[]".to_string()));
             }
 
-            let json_str = read_string_from_ptr(json_ptr);
-            serde_json::from_str(&json_str)
-                .map_err(|e| Error::Other(format!("failed to parse readdir result: {}", e)))
+            Ok(RawJson(read_string_from_ptr(json_ptr)?))
         }
     }
 
     /// Create a new file
     pub fn create(path: &str) -> Result<()> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Write)?;
 
         unsafe {
             let err_ptr = host_fs_create(path_c.as_ptr() as *const u8);
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Create a new file, failing with `AlreadyExists` if the path exists
+    ///
+    /// The check and the create happen in one host call (`O_EXCL`), so it
+    /// is safe for lock files. Needs a host that exports
+    /// `host_fs_create_exclusive`.
+    pub fn create_exclusive(path: &str) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_create_exclusive(path_c.as_ptr() as *const u8);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Create a file as `flags` asks, in one host open(2) call
+    ///
+    /// Needs a host that exports `host_fs_create_flags`.
+    pub fn create_with_flags(path: &str, flags: CreateFlags) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+        let (flags, mode) = flags.to_bits();
+
+        unsafe {
+            let err_ptr = host_fs_create_flags(path_c.as_ptr() as *const u8, flags, mode);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
@@ -141,13 +441,12 @@ impl HostFS {
 
     /// Create a directory
     pub fn mkdir(path: &str, perm: u32) -> Result<()> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Write)?;
 
         unsafe {
             let err_ptr = host_fs_mkdir(path_c.as_ptr() as *const u8, perm);
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
@@ -155,13 +454,12 @@ impl HostFS {
 
     /// Remove a file or empty directory
     pub fn remove(path: &str) -> Result<()> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Delete)?;
 
         unsafe {
             let err_ptr = host_fs_remove(path_c.as_ptr() as *const u8);
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
@@ -169,13 +467,12 @@ impl HostFS {
 
     /// Remove a file or directory recursively
     pub fn remove_all(path: &str) -> Result<()> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Delete)?;
 
         unsafe {
             let err_ptr = host_fs_remove_all(path_c.as_ptr() as *const u8);
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
@@ -183,8 +480,8 @@ impl HostFS {
 
     /// Rename a file or directory
     pub fn rename(old_path: &str, new_path: &str) -> Result<()> {
-        let old_path_c = CString::new(old_path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
-        let new_path_c = CString::new(new_path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let old_path_c = host_path(old_path, HostVerb::Delete)?;
+        let new_path_c = host_path(new_path, HostVerb::Write)?;
 
         unsafe {
             let err_ptr = host_fs_rename(
@@ -192,42 +489,395 @@ impl HostFS {
                 new_path_c.as_ptr() as *const u8,
             );
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Rename as `flags` asks, in one host renameat2(2) call
+    ///
+    /// Needs a host that exports `host_fs_rename_flags`.
+    pub fn rename_with_flags(old_path: &str, new_path: &str, flags: RenameFlags) -> Result<()> {
+        let old_path_c = host_path(old_path, HostVerb::Delete)?;
+        let new_path_c = host_path(new_path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_rename_flags(
+                old_path_c.as_ptr() as *const u8,
+                new_path_c.as_ptr() as *const u8,
+                flags.0,
+            );
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
     }
 
+    /// Restrict all further host access to `caps`
+    ///
+    /// The first declaration is final: later calls fail with
+    /// `PermissionDenied` instead of widening it.
+    pub fn restrict(caps: HostCapabilities) -> Result<()> {
+        let mut current = HOST_CAPABILITIES.lock().unwrap();
+        if current.is_some() {
+            return Err(Error::PermissionDenied);
+        }
+        *current = Some(caps);
+        Ok(())
+    }
+
     /// Change file permissions
     pub fn chmod(path: &str, mode: u32) -> Result<()> {
-        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+        let path_c = host_path(path, HostVerb::Write)?;
 
         unsafe {
             let err_ptr = host_fs_chmod(path_c.as_ptr() as *const u8, mode);
             if err_ptr != 0 {
-                let err_str = read_string_from_ptr(err_ptr);
-                return Err(Error::Other(err_str));
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Change the owner and group of a file
+    ///
+    /// Needs a host that exports `host_fs_chown`, running with the
+    /// privileges to change ownership.
+    pub fn chown(path: &str, uid: u32, gid: u32) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_chown(path_c.as_ptr() as *const u8, uid, gid);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Set access and modification times, in Unix seconds
+    ///
+    /// Needs a host that exports `host_fs_set_times`.
+    pub fn set_times(path: &str, atime: i64, mtime: i64) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_set_times(path_c.as_ptr() as *const u8, atime, mtime);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
             }
             Ok(())
         }
     }
+
+    /// Shrink or zero-extend a file to `size` bytes
+    ///
+    /// Needs a host that exports `host_fs_truncate`.
+    pub fn truncate(path: &str, size: i64) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_truncate(path_c.as_ptr() as *const u8, size);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Reserve space for `len` bytes at `offset`, like fallocate(2)
+    ///
+    /// Needs a host that exports `host_fs_allocate`.
+    pub fn allocate(path: &str, offset: i64, len: i64) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_allocate(path_c.as_ptr() as *const u8, offset, len);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Check `path` with access(2) on the host
+    ///
+    /// The host checks its own permissions, not the caller's. Needs a host
+    /// that exports `host_fs_access`.
+    pub fn access(path: &str, mode: u32) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Read)?;
+
+        unsafe {
+            let err_ptr = host_fs_access(path_c.as_ptr() as *const u8, mode);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Offset of the first data at or after `offset`, like lseek(2) with
+    /// `SEEK_DATA`
+    ///
+    /// Needs a host that exports `host_fs_seek`.
+    pub fn seek_data(path: &str, offset: i64) -> Result<Option<i64>> {
+        seek(path, offset, SEEK_DATA)
+    }
+
+    /// Offset of the first hole at or after `offset`, like lseek(2) with
+    /// `SEEK_HOLE`
+    pub fn seek_hole(path: &str, offset: i64) -> Result<Option<i64>> {
+        seek(path, offset, SEEK_HOLE)
+    }
+
+    /// Take an advisory lock on `path`, like flock(2) with `LOCK_NB`
+    ///
+    /// Needs a host that exports `host_fs_lock`.
+    pub fn lock(path: &str, kind: LockKind) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Read)?;
+
+        unsafe {
+            let err_ptr = host_fs_lock(path_c.as_ptr() as *const u8, kind.to_bits());
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Release a lock taken with `lock`
+    pub fn unlock(path: &str) -> Result<()> {
+        let path_c = host_path(path, HostVerb::Read)?;
+
+        unsafe {
+            let err_ptr = host_fs_unlock(path_c.as_ptr() as *const u8);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Create `new` as a hard link to `existing`
+    ///
+    /// Needs a host that exports `host_fs_link`.
+    pub fn link(existing: &str, new: &str) -> Result<()> {
+        let existing_c = host_path(existing, HostVerb::Read)?;
+        let new_c = host_path(new, HostVerb::Write)?;
+
+        unsafe {
+            let err_ptr = host_fs_link(existing_c.as_ptr() as *const u8, new_c.as_ptr() as *const u8);
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Create a symlink at `link_path` pointing to `target`
+    ///
+    /// The target is passed to the host unchanged. Needs a host that
+    /// exports `host_fs_symlink`.
+    pub fn symlink(target: &str, link_path: &str) -> Result<()> {
+        let link_c = host_path(link_path, HostVerb::Write)?;
+        let target_c = CString::new(target)
+            .map_err(|_| Error::InvalidInput("invalid symlink target".to_string()))?;
+
+        unsafe {
+            let err_ptr = host_fs_symlink(
+                target_c.as_ptr() as *const u8,
+                link_c.as_ptr() as *const u8,
+            );
+            if err_ptr != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+            }
+            Ok(())
+        }
+    }
+
+    /// Target of the symlink at `path`, without resolving it
+    ///
+    /// Needs a host that exports `host_fs_readlink`.
+    pub fn readlink(path: &str) -> Result<String> {
+        let path_c = host_path(path, HostVerb::Read)?;
+        unsafe { string_reply(host_fs_readlink(path_c.as_ptr() as *const u8)) }
+    }
+}
+
+/// A host file open for streaming, from `HostFS::open_reader`
+///
+/// The host keeps the file open until the reader is dropped.
+pub struct HostReader {
+    handle: u32,
+    eof: bool,
+}
+
+impl HostReader {
+    /// The next at most `max` bytes; empty once the file is exhausted
+    pub fn read_chunk(&mut self, max: usize) -> Result<Vec<u8>> {
+        if self.eof || max == 0 {
+            return Ok(Vec::new());
+        }
+        let max = u32::try_from(max).unwrap_or(u32::MAX);
+
+        unsafe {
+            let result = host_fs_read_chunk(self.handle, max);
+            let data_ptr = (result & 0xFFFFFFFF) as u32;
+            let data_size = ((result >> 32) & 0xFFFFFFFF) as u32;
+
+            // (0, error string) on failure, (0, 0) at the end of the file
+            if data_ptr == 0 && data_size != 0 {
+                return Err(Error::from_host(&read_string_from_ptr(data_size)?));
+            }
+            if data_ptr == 0 {
+                self.eof = true;
+                return Ok(Vec::new());
+            }
+            if data_size > max {
+                return Err(Error::Io("host returned an oversized chunk".to_string()));
+            }
+            Ok(borrow_slice(data_ptr as *const u8, data_size as usize)?.to_vec())
+        }
+    }
+}
+
+impl std::io::Read for HostReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let chunk = self.read_chunk(buf.len())?;
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        Ok(chunk.len())
+    }
+}
+
+impl Drop for HostReader {
+    fn drop(&mut self) {
+        unsafe {
+            // Nothing to report to; the host drops the handle either way
+            host_fs_close_reader(self.handle);
+        }
+    }
+}
+
+const SEEK_DATA: u32 = 3;
+const SEEK_HOLE: u32 = 4;
+
+// The host replies with the offset, or a negated errno (`ENXIO` when
+// there is nothing to find)
+fn seek(path: &str, offset: i64, whence: u32) -> Result<Option<i64>> {
+    let path_c = host_path(path, HostVerb::Read)?;
+    match unsafe { host_fs_seek(path_c.as_ptr() as *const u8, offset, whence) } {
+        found if found >= 0 => Ok(Some(found)),
+        -6 => Ok(None),
+        errno => Err(Error::from_errno(-errno as i32)),
+    }
 }
 
-/// Read a null-terminated string from a pointer
-unsafe fn read_string_from_ptr(ptr: u32) -> String {
+// Unpack a (string pointer, error pointer) reply from the host
+pub(crate) unsafe fn string_reply(result: u64) -> Result<String> {
+    let ptr = (result & 0xFFFFFFFF) as u32;
+    let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+    if err_ptr != 0 {
+        return Err(Error::from_host(&read_string_from_ptr(err_ptr)?));
+    }
     if ptr == 0 {
-        return String::new();
+        return Err(Error::NotFound);
+    }
+    read_string_from_ptr(ptr)
+}
+
+// Check an entry decoded from the host. Host directories often report the
+// size of their on-disk block, so that is cleared rather than rejected.
+fn from_host(mut info: FileInfo) -> Result<FileInfo> {
+    if info.is_dir {
+        info.size = 0;
+    }
+    info.validate()
+        .map_err(|e| Error::Io(format!("host returned bad entry: {}", e)))?;
+    Ok(info)
+}
+
+/// Read a null-terminated string from a pointer (bounded, see
+/// `CString::bytes_from_ptr`)
+// The loop behind HostFS::read_chunked, with the host read passed in
+fn read_chunks<R, F>(
+    mut read: R,
+    path: &str,
+    file_size: i64,
+    offset: i64,
+    size: i64,
+    sizer: &mut ChunkSizer,
+    mut sink: F,
+) -> Result<u64>
+where
+    R: FnMut(&str, i64, i64) -> Result<Vec<u8>>,
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    let end = if size < 0 { file_size } else { file_size.min(offset.saturating_add(size)) };
+    let mut done: u64 = 0;
+    loop {
+        let remaining = end - offset - done as i64;
+        if remaining <= 0 {
+            return Ok(done);
+        }
+        let mut want = 0;
+        let data = sizer.transfer(
+            |chunk| {
+                want = (chunk as i64).min(remaining);
+                read(path, offset + done as i64, want)
+            },
+            Vec::len,
+        )?;
+        sink(&data)?;
+        done += data.len() as u64;
+        if (data.len() as i64) < want {
+            return Ok(done);
+        }
     }
+}
 
-    // Find the null terminator
-    let mut len = 0;
-    let start_ptr = ptr as *const u8;
-    while *start_ptr.add(len) != 0 {
-        len += 1;
+unsafe fn read_string_from_ptr(ptr: u32) -> Result<String> {
+    crate::memory::CString::from_ptr(ptr as *const u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Like the Go host: reads at or past the end fail, as HostFS::read
+    // sees a 0 reply
+    fn host_read(data: &[u8]) -> impl FnMut(&str, i64, i64) -> Result<Vec<u8>> + '_ {
+        |_, offset, size| {
+            let start = offset as usize;
+            if start >= data.len() {
+                return Err(Error::Io("read failed".to_string()));
+            }
+            Ok(data[start..(start + size as usize).min(data.len())].to_vec())
+        }
     }
 
-    // Read the string
-    let slice = std::slice::from_raw_parts(start_ptr, len);
-    String::from_utf8_lossy(slice).to_string()
+    #[test]
+    fn test_read_chunks_stops_at_size() {
+        let data: Vec<u8> = (0..8192u32).map(|i| i as u8).collect();
+        let mut sizer = ChunkSizer::new(1024, 1024);
+        let mut out = Vec::new();
+        let read = read_chunks(host_read(&data), "/f", 8192, 0, -1, &mut sizer, |chunk| {
+            out.extend_from_slice(chunk);
+            Ok(())
+        });
+        assert_eq!(read, Ok(8192));
+        assert_eq!(out, data);
+
+        let mut out = Vec::new();
+        let read = read_chunks(host_read(&data), "/f", 8192, 7000, 4096, &mut sizer, |chunk| {
+            out.extend_from_slice(chunk);
+            Ok(())
+        });
+        assert_eq!(read, Ok(1192));
+        assert_eq!(out, &data[7000..]);
+
+        let read = read_chunks(host_read(&data), "/f", 8192, 8192, -1, &mut sizer, |_| Ok(()));
+        assert_eq!(read, Ok(0));
+    }
 }
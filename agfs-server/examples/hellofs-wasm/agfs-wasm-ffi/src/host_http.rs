@@ -0,0 +1,200 @@
+//! Outbound HTTP through the host
+//!
+//! WASM plugins have no sockets, so plugins that front a web service
+//! (object stores, HTTP file trees, APIs) ask the server to make the
+//! request with the `host_http_request` import:
+//!
+//! ```ignore
+//! let resp = HostHttp::get("https://example.com/data.json")
+//!     .header("Accept", "application/json")
+//!     .timeout_ms(5_000)
+//!     .send()?;
+//! if !resp.is_success() {
+//!     return Err(Error::Io(format!("upstream returned {}", resp.status)));
+//! }
+//! ```
+//!
+//! The mount must list the server in `host_http_allow` (hosts, or
+//! `host:port`); other requests, redirects included, fail with
+//! `Error::PermissionDenied`:
+//!
+//! ```json
+//! {"host_http_allow": ["example.com", "*.s3.amazonaws.com:443"]}
+//! ```
+//!
+//! Every request has a timeout and a cap on the response body; the host
+//! applies its own maxima on top. A request that runs out of time fails
+//! with `Error::Timeout`, an oversized response with `Error::TooLarge`.
+
+use crate::host_fs::string_reply;
+use crate::types::{Error, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_http_request(
+        method: *const u8,
+        url: *const u8,
+        headers_json: *const u8,
+        body: *const u8,
+        body_len: u32,
+        timeout_ms: u32,
+        max_response: u32,
+    ) -> u64;
+}
+
+/// Timeout of a request that does not set one
+pub const DEFAULT_TIMEOUT_MS: u32 = 30_000;
+/// Largest response body accepted unless the request raises it
+pub const DEFAULT_MAX_RESPONSE: u32 = 16 << 20;
+
+/// Entry point for host HTTP requests
+pub struct HostHttp;
+
+impl HostHttp {
+    /// A request with any method
+    pub fn request(method: &str, url: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: BTreeMap::new(),
+            body: Vec::new(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            max_response: DEFAULT_MAX_RESPONSE,
+        }
+    }
+
+    pub fn get(url: &str) -> HttpRequest {
+        Self::request("GET", url)
+    }
+
+    pub fn put(url: &str, body: Vec<u8>) -> HttpRequest {
+        Self::request("PUT", url).body(body)
+    }
+
+    pub fn post(url: &str, body: Vec<u8>) -> HttpRequest {
+        Self::request("POST", url).body(body)
+    }
+
+    pub fn delete(url: &str) -> HttpRequest {
+        Self::request("DELETE", url)
+    }
+}
+
+/// An HTTP request being built; `send` runs it on the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: Vec<u8>,
+    pub timeout_ms: u32,
+    pub max_response: u32,
+}
+
+impl HttpRequest {
+    /// Set a header, replacing an earlier value
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Give up after `timeout_ms` milliseconds, connecting included
+    pub fn timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Fail with `TooLarge` rather than accept a longer response body
+    pub fn max_response(mut self, bytes: u32) -> Self {
+        self.max_response = bytes;
+        self
+    }
+
+    /// Run the request on the host
+    ///
+    /// Any status counts as a response; only transport failures, limits
+    /// and host refusals are errors.
+    pub fn send(self) -> Result<HttpResponse> {
+        let invalid = |what: &str| Error::InvalidInput(format!("invalid HTTP {}", what));
+        let method = CString::new(self.method).map_err(|_| invalid("method"))?;
+        let url = CString::new(self.url).map_err(|_| invalid("url"))?;
+        let headers = serde_json::to_string(&self.headers)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        let headers = CString::new(headers).map_err(|_| invalid("headers"))?;
+        let body_len = u32::try_from(self.body.len()).map_err(|_| Error::TooLarge)?;
+
+        unsafe {
+            let result = host_http_request(
+                method.as_ptr() as *const u8,
+                url.as_ptr() as *const u8,
+                headers.as_ptr() as *const u8,
+                self.body.as_ptr(),
+                body_len,
+                self.timeout_ms,
+                self.max_response,
+            );
+            HttpResponse::from_json(&string_reply(result)?)
+        }
+    }
+}
+
+/// A response from `HttpRequest::send`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct HttpResponse {
+    #[serde(rename = "Status")]
+    pub status: u16,
+    /// Header values joined with ", " when repeated; names as the host
+    /// canonicalizes them (`Content-Type`)
+    #[serde(rename = "Headers", default)]
+    pub headers: BTreeMap<String, String>,
+    /// Base64 in JSON, as Go encodes `[]byte`
+    #[serde(rename = "Body", default, deserialize_with = "crate::base64::deserialize")]
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::Other(format!("failed to parse HTTP response: {}", e)))
+    }
+
+    /// Status in 200..300
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Value of header `name`, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_json() {
+        let json = r#"{"Status":404,"Headers":{"Content-Type":"text/plain"},"Body":"bm9wZQ=="}"#;
+        let resp = HttpResponse::from_json(json).unwrap();
+        assert_eq!(resp.status, 404);
+        assert!(!resp.is_success());
+        assert_eq!(resp.header("content-type"), Some("text/plain"));
+        assert_eq!(resp.body, b"nope");
+
+        let req = HostHttp::get("http://x").header("A", "1").header("A", "2");
+        assert_eq!(req.headers["A"], "2");
+        assert_eq!(req.timeout_ms, DEFAULT_TIMEOUT_MS);
+    }
+}
@@ -0,0 +1,163 @@
+//! Durable key-value storage on the host
+//!
+//! Each plugin instance has a store meant for metadata: version lists,
+//! cache indexes, cursors. It outlives restarts only when the mount names
+//! a file with `host_kv_path`; mounts naming the same file share it.
+//! Without one the store is kept in memory until the plugin is unmounted.
+//!
+//! ```ignore
+//! HostKv::put_json("versions/a.txt", &versions)?;
+//! let versions: Vec<u64> = HostKv::get_json("versions/a.txt")?.unwrap_or_default();
+//! for (key, _) in HostKv::scan_all("versions/")? { /* ... */ }
+//! ```
+
+use crate::host_fs::string_reply;
+use crate::types::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_kv_get(key: *const u8) -> u64;
+    fn host_kv_put(key: *const u8, value: *const u8, value_len: u32) -> u32;
+    fn host_kv_delete(key: *const u8) -> u32;
+    fn host_kv_scan(prefix: *const u8, start_after: *const u8, limit: u32) -> u64;
+}
+
+/// Longest key the host accepts
+pub const MAX_KEY_LEN: usize = 1024;
+/// Largest value the host accepts
+pub const MAX_VALUE_LEN: usize = 1 << 20;
+/// Most entries one `scan` call returns
+pub const MAX_SCAN: u32 = 1000;
+
+/// The plugin's key-value store on the host
+pub struct HostKv;
+
+impl HostKv {
+    /// Value stored under `key`, or `None`
+    pub fn get(key: &str) -> Result<Option<Vec<u8>>> {
+        let key_c = kv_key(key)?;
+        let json = match unsafe { string_reply(host_kv_get(key_c.as_ptr() as *const u8)) } {
+            Ok(json) => json,
+            Err(Error::NotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let encoded: String = serde_json::from_str(&json)
+            .map_err(|e| Error::Io(format!("host returned bad value: {}", e)))?;
+        crate::base64::decode(&encoded).map(Some)
+    }
+
+    /// Store `value` under `key`, replacing any earlier value
+    pub fn put(key: &str, value: &[u8]) -> Result<()> {
+        let key_c = kv_key(key)?;
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::TooLarge);
+        }
+        unsafe {
+            let err_ptr =
+                host_kv_put(key_c.as_ptr() as *const u8, value.as_ptr(), value.len() as u32);
+            check(err_ptr)
+        }
+    }
+
+    /// Remove `key`; a missing key is not an error
+    pub fn delete(key: &str) -> Result<()> {
+        let key_c = kv_key(key)?;
+        unsafe { check(host_kv_delete(key_c.as_ptr() as *const u8)) }
+    }
+
+    /// Up to `limit` entries whose keys start with `prefix` and sort after
+    /// `start_after`, in key order
+    pub fn scan(prefix: &str, start_after: &str, limit: u32) -> Result<Vec<(String, Vec<u8>)>> {
+        let prefix_c =
+            CString::new(prefix).map_err(|_| Error::InvalidInput("key contains NUL".to_string()))?;
+        let after_c = CString::new(start_after)
+            .map_err(|_| Error::InvalidInput("key contains NUL".to_string()))?;
+        let json = unsafe {
+            string_reply(host_kv_scan(
+                prefix_c.as_ptr() as *const u8,
+                after_c.as_ptr() as *const u8,
+                limit.min(MAX_SCAN),
+            ))?
+        };
+        decode_entries(&json)
+    }
+
+    /// Every entry under `prefix`, fetched a page at a time
+    pub fn scan_all(prefix: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+        loop {
+            let after = entries.last().map(|(k, _)| k.as_str()).unwrap_or("");
+            let page = Self::scan(prefix, after, MAX_SCAN)?;
+            let done = page.len() < MAX_SCAN as usize;
+            entries.extend(page);
+            if done {
+                return Ok(entries);
+            }
+        }
+    }
+
+    /// `get` and deserialize a JSON value
+    pub fn get_json<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+        match Self::get(key)? {
+            Some(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| Error::Other(format!("failed to parse {}: {}", key, e))),
+            None => Ok(None),
+        }
+    }
+
+    /// Serialize `value` as JSON and `put` it
+    pub fn put_json<T: Serialize>(key: &str, value: &T) -> Result<()> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))?;
+        Self::put(key, &data)
+    }
+}
+
+fn kv_key(key: &str) -> Result<CString> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(Error::InvalidInput(format!("key must be 1..={} bytes", MAX_KEY_LEN)));
+    }
+    CString::new(key).map_err(|_| Error::InvalidInput("key contains NUL".to_string()))
+}
+
+unsafe fn check(err_ptr: u32) -> Result<()> {
+    if err_ptr != 0 {
+        return Err(Error::from_host(&crate::memory::CString::from_ptr(err_ptr as *const u8)?));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "Key")]
+    key: String,
+    #[serde(rename = "Value", default, deserialize_with = "crate::base64::deserialize")]
+    value: Vec<u8>,
+}
+
+fn decode_entries(json: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    let entries: Vec<Entry> = serde_json::from_str(json)
+        .map_err(|e| Error::Io(format!("host returned bad entries: {}", e)))?;
+    Ok(entries.into_iter().map(|e| (e.key, e.value)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entries_and_keys() {
+        let entries = decode_entries(r#"[{"Key":"a","Value":"aGk="},{"Key":"b","Value":""}]"#);
+        assert_eq!(
+            entries.unwrap(),
+            vec![("a".to_string(), b"hi".to_vec()), ("b".to_string(), Vec::new())]
+        );
+        assert!(kv_key("").is_err());
+        assert!(kv_key(&"k".repeat(MAX_KEY_LEN + 1)).is_err());
+        assert!(kv_key("a\0b").is_err());
+    }
+}
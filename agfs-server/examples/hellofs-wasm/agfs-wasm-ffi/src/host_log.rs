@@ -0,0 +1,99 @@
+//! Logging into the server's log stream
+//!
+//! `host_log` hands a line to the server, which writes it through its own
+//! logger tagged with the plugin and target, so plugin output shows up
+//! next to the server's instead of on a stderr nobody reads.
+//!
+//! With the `log` feature, `HostLogger` backs the `log` facade:
+//!
+//! ```ignore
+//! HostLogger::init(log::LevelFilter::Info).ok();
+//! log::info!("mounted {} buckets", buckets.len());
+//! ```
+
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_log(level: u32, target: *const u8, msg: *const u8);
+}
+
+/// Severity of a host log line; the numbers match `log::Level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+/// Send one line to the server's log
+///
+/// NUL bytes, which the import cannot carry, are replaced.
+pub fn log(level: LogLevel, target: &str, msg: &str) {
+    let target = c_text(target);
+    let msg = c_text(msg);
+    unsafe { host_log(level as u32, target.as_ptr() as *const u8, msg.as_ptr() as *const u8) }
+}
+
+fn c_text(s: &str) -> CString {
+    CString::new(s.replace('\0', "\u{FFFD}")).unwrap_or_default()
+}
+
+/// Logger that forwards to `host_log`
+pub struct HostLogger;
+
+#[cfg(feature = "log")]
+static LOGGER: HostLogger = HostLogger;
+
+#[cfg(feature = "log")]
+impl HostLogger {
+    /// Install as the `log` facade's logger, passing records up to
+    /// `level`; fails if another logger is already installed
+    pub fn init(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_logger(&LOGGER)?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "log")]
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl log::Log for HostLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            log(record.level().into(), record.target(), &record.args().to_string());
+        }
+    }
+
+    // Lines are handed over synchronously
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_text_replaces_nul() {
+        assert_eq!(c_text("a\0b").to_str().unwrap(), "a\u{FFFD}b");
+        assert!(LogLevel::Error < LogLevel::Trace);
+    }
+}
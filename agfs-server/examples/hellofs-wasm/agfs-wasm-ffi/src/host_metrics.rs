@@ -0,0 +1,102 @@
+//! Plugin metrics reported to the server
+//!
+//! Counters and histograms a plugin reports are aggregated by the server
+//! and served with its own at `/api/v1/metrics`, prefixed `agfs_plugin_`
+//! and labelled with the plugin:
+//!
+//! ```ignore
+//! HostMetrics::counter("cache_misses_total", 1.0, &[("bucket", "photos")])?;
+//! let page = HostMetrics::time("fetch_seconds", &[], || client.fetch(url))?;
+//! ```
+//!
+//! Names and label names are Prometheus identifiers. A name may only be
+//! used as one kind of metric, and the server caps the number of series,
+//! so keep ids and paths out of labels. Histograms use Prometheus' default
+//! buckets, suited to seconds.
+
+use crate::types::{Error, Result};
+use std::ffi::CString;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_metric_counter(name: *const u8, value: f64, labels_json: *const u8) -> u32;
+    fn host_metric_histogram(name: *const u8, value: f64, labels_json: *const u8) -> u32;
+}
+
+/// The server's metrics registry
+pub struct HostMetrics;
+
+impl HostMetrics {
+    /// Add `value`, which must not be negative, to a counter
+    pub fn counter(name: &str, value: f64, labels: &[(&str, &str)]) -> Result<()> {
+        let (name_c, labels_c) = encode(name, labels)?;
+        unsafe {
+            check(host_metric_counter(
+                name_c.as_ptr() as *const u8,
+                value,
+                labels_c.as_ptr() as *const u8,
+            ))
+        }
+    }
+
+    /// Record one observation in a histogram
+    pub fn histogram(name: &str, value: f64, labels: &[(&str, &str)]) -> Result<()> {
+        let (name_c, labels_c) = encode(name, labels)?;
+        unsafe {
+            check(host_metric_histogram(
+                name_c.as_ptr() as *const u8,
+                value,
+                labels_c.as_ptr() as *const u8,
+            ))
+        }
+    }
+
+    /// Run `f` and record how long it took, in seconds, in a histogram
+    ///
+    /// Failing to record is ignored so metrics never fail an operation.
+    pub fn time<T>(name: &str, labels: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+        let start = crate::time::monotonic_nanos();
+        let out = f();
+        let secs = crate::time::monotonic_nanos().saturating_sub(start) as f64 / 1e9;
+        let _ = Self::histogram(name, secs, labels);
+        out
+    }
+}
+
+fn encode(name: &str, labels: &[(&str, &str)]) -> Result<(CString, CString)> {
+    let name_c =
+        CString::new(name).map_err(|_| Error::InvalidInput("invalid metric name".to_string()))?;
+    let labels_c = CString::new(labels_json(labels)?)
+        .map_err(|_| Error::InvalidInput("label contains NUL".to_string()))?;
+    Ok((name_c, labels_c))
+}
+
+fn labels_json(labels: &[(&str, &str)]) -> Result<String> {
+    let map: serde_json::Map<String, serde_json::Value> = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), serde_json::Value::from(*v)))
+        .collect();
+    serde_json::to_string(&map)
+        .map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))
+}
+
+unsafe fn check(err_ptr: u32) -> Result<()> {
+    if err_ptr != 0 {
+        return Err(Error::from_host(&crate::memory::CString::from_ptr(err_ptr as *const u8)?));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_json() {
+        assert_eq!(labels_json(&[]).unwrap(), "{}");
+        let json = labels_json(&[("op", "read"), ("path", "a\"b")]).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["op"], "read");
+        assert_eq!(parsed["path"], "a\"b");
+    }
+}
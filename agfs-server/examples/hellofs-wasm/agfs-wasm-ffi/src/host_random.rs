@@ -0,0 +1,136 @@
+//! Secure random bytes from the host
+//!
+//! WASM guests have no entropy source, so anything that must not be
+//! guessable (tokens, keys, UUIDs) asks the host, whose
+//! `host_random_bytes` draws from the OS CSPRNG. Native builds read
+//! `/dev/urandom` directly.
+//!
+//! With the `getrandom` feature, WASM builds also register the host as
+//! getrandom's custom backend, so crates like `rand` and `uuid` work
+//! inside plugins.
+
+use crate::types::{Error, Result};
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_random_bytes(len: u32) -> u64;
+}
+
+/// Most bytes one host call returns; `fill` loops for more
+pub const MAX_CHUNK: usize = 64 * 1024;
+
+/// Cryptographically secure randomness
+pub struct HostRandom;
+
+impl HostRandom {
+    /// Fill `buf` with random bytes
+    pub fn fill(buf: &mut [u8]) -> Result<()> {
+        for chunk in buf.chunks_mut(MAX_CHUNK) {
+            fill_chunk(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// `len` random bytes
+    pub fn bytes(len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        Self::fill(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn u64() -> Result<u64> {
+        let mut buf = [0u8; 8];
+        Self::fill(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Uniform in `0..bound`, without modulo bias; `bound` must be nonzero
+    pub fn below(bound: u64) -> Result<u64> {
+        if bound == 0 {
+            return Err(Error::InvalidInput("bound must be nonzero".to_string()));
+        }
+        // Reject the top partial range so every residue is equally likely
+        let zone = u64::MAX - (u64::MAX % bound + 1) % bound;
+        loop {
+            let n = Self::u64()?;
+            if n <= zone {
+                return Ok(n % bound);
+            }
+        }
+    }
+
+    /// Hex string of `len` random bytes, for tokens and nonces
+    pub fn hex_token(len: usize) -> Result<String> {
+        Ok(Self::bytes(len)?.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Random (version 4) UUID in its hyphenated form
+    pub fn uuid_v4() -> Result<String> {
+        let mut b = [0u8; 16];
+        Self::fill(&mut b)?;
+        b[6] = (b[6] & 0x0f) | 0x40;
+        b[8] = (b[8] & 0x3f) | 0x80;
+        let hex: String = b.iter().map(|x| format!("{:02x}", x)).collect();
+        Ok(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn fill_chunk(buf: &mut [u8]) -> Result<()> {
+    let result = unsafe { host_random_bytes(buf.len() as u32) };
+    let ptr = (result & 0xFFFFFFFF) as u32;
+    let len = ((result >> 32) & 0xFFFFFFFF) as usize;
+    if ptr == 0 || len != buf.len() {
+        return Err(Error::Io("host returned no random bytes".to_string()));
+    }
+    let data = unsafe { crate::memory::borrow_slice(ptr as *const u8, len)? };
+    buf.copy_from_slice(data);
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fill_chunk(buf: &mut [u8]) -> Result<()> {
+    use std::io::Read;
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(buf))
+        .map_err(|e| Error::Io(format!("no entropy source: {}", e)))
+}
+
+#[cfg(all(feature = "getrandom", target_arch = "wasm32"))]
+fn getrandom_backend(buf: &mut [u8]) -> std::result::Result<(), getrandom::Error> {
+    HostRandom::fill(buf).map_err(|_| getrandom::Error::from(HOST_RANDOM_FAILED))
+}
+
+#[cfg(all(feature = "getrandom", target_arch = "wasm32"))]
+const HOST_RANDOM_FAILED: core::num::NonZeroU32 =
+    match core::num::NonZeroU32::new(getrandom::Error::CUSTOM_START) {
+        Some(code) => code,
+        None => unreachable!(),
+    };
+
+#[cfg(all(feature = "getrandom", target_arch = "wasm32"))]
+getrandom::register_custom_getrandom!(getrandom_backend);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_helpers() {
+        assert_ne!(HostRandom::bytes(32).unwrap(), HostRandom::bytes(32).unwrap());
+        assert!(HostRandom::below(10).unwrap() < 10);
+        assert!(HostRandom::below(0).is_err());
+        assert_eq!(HostRandom::hex_token(4).unwrap().len(), 8);
+        let uuid = HostRandom::uuid_v4().unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+    }
+}
@@ -0,0 +1,144 @@
+//! TCP connections through the host
+//!
+//! For protocols other than HTTP (Redis, MQTT, SMTP). A plugin may only
+//! connect to destinations the mount grants in `host_tcp_allow`, as
+//! `host:port` patterns where the host may be `*` or `*.domain` and the
+//! port `*`:
+//!
+//! ```json
+//! {"host_tcp_allow": ["redis.internal:6379", "*.mqtt.example.com:8883"]}
+//! ```
+//!
+//! `HostTcpStream` implements `Read` and `Write`, so it works with
+//! `BufReader` and friends. The server closes connections a plugin leaves
+//! open when it is unmounted.
+
+use crate::memory::borrow_slice;
+use crate::types::{Error, Result};
+use std::ffi::CString;
+use std::io;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_tcp_connect(addr: *const u8, timeout_ms: u32) -> u64;
+    fn host_tcp_read(handle: u32, max: u32, timeout_ms: u32) -> u64;
+    fn host_tcp_write(handle: u32, data: *const u8, len: u32, timeout_ms: u32) -> u64;
+    fn host_tcp_close(handle: u32) -> u32;
+}
+
+/// Timeout of connects, reads and writes unless changed
+pub const DEFAULT_TIMEOUT_MS: u32 = 30_000;
+/// Most bytes the host moves in one read
+pub const MAX_CHUNK: usize = 1 << 20;
+
+/// A TCP connection held by the host
+///
+/// Dropping the stream closes the connection.
+#[derive(Debug)]
+pub struct HostTcpStream {
+    handle: u32,
+    timeout_ms: u32,
+}
+
+impl HostTcpStream {
+    /// Connect to `addr` (`host:port`) with the default timeout
+    pub fn connect(addr: &str) -> Result<Self> {
+        Self::connect_timeout(addr, DEFAULT_TIMEOUT_MS)
+    }
+
+    pub fn connect_timeout(addr: &str, timeout_ms: u32) -> Result<Self> {
+        let addr_c =
+            CString::new(addr).map_err(|_| Error::InvalidInput("invalid address".to_string()))?;
+        let result = unsafe { host_tcp_connect(addr_c.as_ptr() as *const u8, timeout_ms) };
+        let handle = (result & 0xFFFFFFFF) as u32;
+        let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+        if err_ptr != 0 {
+            return Err(host_error(err_ptr));
+        }
+        if handle == 0 {
+            return Err(Error::Io(format!("failed to connect to {}", addr)));
+        }
+        Ok(Self {
+            handle,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        })
+    }
+
+    /// Timeout of each later read and write; a read that waits longer
+    /// fails with `Timeout`
+    pub fn set_timeout_ms(&mut self, timeout_ms: u32) {
+        self.timeout_ms = timeout_ms;
+    }
+
+    /// Up to `max` bytes as soon as any arrive; empty once the peer has
+    /// closed its side
+    pub fn read_chunk(&mut self, max: usize) -> Result<Vec<u8>> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+        let max = max.min(MAX_CHUNK) as u32;
+        unsafe {
+            let result = host_tcp_read(self.handle, max, self.timeout_ms);
+            let data_ptr = (result & 0xFFFFFFFF) as u32;
+            let data_size = ((result >> 32) & 0xFFFFFFFF) as u32;
+            // (0, error string) on failure, (0, 0) at end of stream
+            if data_ptr == 0 && data_size != 0 {
+                return Err(host_error(data_size));
+            }
+            if data_ptr == 0 {
+                return Ok(Vec::new());
+            }
+            if data_size > max {
+                return Err(Error::Io("host returned an oversized chunk".to_string()));
+            }
+            Ok(borrow_slice(data_ptr as *const u8, data_size as usize)?.to_vec())
+        }
+    }
+
+    /// Send some of `data`, returning how much was sent
+    pub fn write_some(&mut self, data: &[u8]) -> Result<usize> {
+        let len = data.len().min(u32::MAX as usize) as u32;
+        let result = unsafe { host_tcp_write(self.handle, data.as_ptr(), len, self.timeout_ms) };
+        let written = (result & 0xFFFFFFFF) as usize;
+        let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+        if err_ptr != 0 {
+            return Err(host_error(err_ptr));
+        }
+        Ok(written)
+    }
+}
+
+fn host_error(err_ptr: u32) -> Error {
+    match unsafe { crate::memory::CString::from_ptr(err_ptr as *const u8) } {
+        Ok(msg) => Error::from_host(&msg),
+        Err(e) => e,
+    }
+}
+
+impl io::Read for HostTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let chunk = self.read_chunk(buf.len())?;
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        Ok(chunk.len())
+    }
+}
+
+impl io::Write for HostTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_some(buf)?)
+    }
+
+    // Writes go straight to the host's socket
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for HostTcpStream {
+    fn drop(&mut self) {
+        unsafe {
+            // Nothing to report to; the host forgets the handle either way
+            host_tcp_close(self.handle);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Scratch files and directories on the host
+//!
+//! For work that needs real disk space (extracting archives, transcoding).
+//! The server gives each plugin a private temp directory, visible to
+//! `HostFS` under `ROOT` and removed with everything in it when the plugin
+//! is unmounted. It is outside `host_allowed_roots`, but a plugin that
+//! narrows itself with `HostFS::restrict` must grant `ROOT` too.
+//!
+//! ```ignore
+//! let dir = HostTemp::create_dir()?;
+//! HostFS::write(&format!("{}/part-0", dir), &chunk)?;
+//! ```
+
+use crate::host_fs::string_reply;
+use crate::types::Result;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_temp_create_file(prefix: *const u8) -> u64;
+    fn host_temp_create_dir(prefix: *const u8) -> u64;
+}
+
+/// Host path under which the plugin's scratch space appears
+pub const ROOT: &str = "/.host-tmp";
+
+// Name prefix of created entries, NUL-terminated for the host
+const PREFIX: &[u8] = b"tmp\0";
+
+/// The plugin's scratch space on the host
+pub struct HostTemp;
+
+impl HostTemp {
+    /// Create a new empty file and return its `HostFS` path
+    pub fn create_file() -> Result<String> {
+        unsafe { string_reply(host_temp_create_file(PREFIX.as_ptr())) }
+    }
+
+    /// Create a new empty directory and return its `HostFS` path
+    pub fn create_dir() -> Result<String> {
+        unsafe { string_reply(host_temp_create_dir(PREFIX.as_ptr())) }
+    }
+
+    /// Whether `path` is inside the scratch space
+    pub fn contains(path: &str) -> bool {
+        path == ROOT || path.strip_prefix(ROOT).is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        assert!(HostTemp::contains("/.host-tmp"));
+        assert!(HostTemp::contains("/.host-tmp/tmp-123/a"));
+        assert!(!HostTemp::contains("/.host-tmpx"));
+        assert!(!HostTemp::contains("/data"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Bare-bones HTTP/1.1 for the development gateways
+//!
+//! Just enough framing for the `s3` and `webdav` frontends to run on
+//! `std::net`: one request per connection, `Content-Length` or chunked
+//! bodies, no TLS.
+
+use crate::time::civil;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// Largest request body read
+pub const MAX_BODY: usize = 64 << 20;
+
+/// A parsed HTTP request
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    pub method: String,
+    /// Decoded path, without the query string
+    pub path: String,
+    /// Decoded query parameters in request order
+    pub query: Vec<(String, String)>,
+    /// Headers with lowercased names
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// An HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Empty response with `status`
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add a header
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// XML document response
+    pub fn xml(status: u16, body: String) -> Self {
+        let mut r = Self::new(status).header("Content-Type", "application/xml");
+        r.body = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", body).into_bytes();
+        r
+    }
+
+    /// Standard reason phrase for the status code
+    pub fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            206 => "Partial Content",
+            207 => "Multi-Status",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            423 => "Locked",
+            501 => "Not Implemented",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Escape text for XML content and attributes
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// ISO 8601 UTC timestamp, as S3 and WebDAV bodies use
+pub fn iso8601(secs: i64) -> String {
+    let (y, mo, d, h, mi, s, _) = civil(secs);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.000Z",
+        y, mo, d, h, mi, s
+    )
+}
+
+/// RFC 7231 date, for `Last-Modified` and friends
+pub fn http_date(secs: i64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (y, mo, d, h, mi, s, wd) = civil(secs);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAYS[wd as usize],
+        d,
+        MONTHS[mo as usize - 1],
+        y,
+        h,
+        mi,
+        s
+    )
+}
+
+/// Parse a single `bytes=` range against a body of `len` bytes into
+/// `(offset, size)`; `None` if it cannot be satisfied
+pub fn parse_range(spec: &str, len: i64) -> Option<(i64, i64)> {
+    let (start, end) = spec.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let n: i64 = suffix.parse().ok()?;
+            ((len - n).max(0), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len - 1),
+        (start, end) => (start.parse().ok()?, end.parse::<i64>().ok()?.min(len - 1)),
+    };
+    if start < 0 || start >= len || end < start {
+        return None;
+    }
+    Some((start, end - start + 1))
+}
+
+/// Escape a path for use in a URL, keeping `/` and unreserved characters
+pub fn percent_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes, and `+` as a space in query strings
+pub fn percent_decode(s: &str, plus_is_space: bool) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i + 3) {
+            Some([b'%', hi, lo]) => hex(*hi).zip(hex(*lo)).map(|(hi, lo)| hi << 4 | lo),
+            _ => None,
+        };
+        match (escaped, bytes[i]) {
+            (Some(b), _) => {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+            (None, b'+') if plus_is_space => out.push(b' '),
+            (None, b) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn bad_request(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Read one HTTP/1.1 request
+///
+/// Answers `Expect: 100-continue` on `stream` before reading the body.
+/// Bodies must carry a `Content-Length` or use chunked encoding.
+pub fn read_request<R: BufRead, W: Write>(reader: &mut R, stream: &mut W) -> io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("malformed request line"));
+    };
+    let (raw_path, raw_query) = target.split_once('?').unwrap_or((target, ""));
+    let mut req = Request {
+        method: method.to_string(),
+        path: percent_decode(raw_path, false),
+        query: raw_query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (percent_decode(k, true), percent_decode(v, true))
+            })
+            .collect(),
+        ..Request::default()
+    };
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(bad_request("truncated headers"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            req.headers
+                .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    if req
+        .header("expect")
+        .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+    {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    if req
+        .header("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim().split(';').next().unwrap_or("");
+            let size = usize::from_str_radix(size, 16).map_err(|_| bad_request("bad chunk"))?;
+            if req.body.len() + size > MAX_BODY {
+                return Err(bad_request("body too large"));
+            }
+            let start = req.body.len();
+            req.body.resize(start + size, 0);
+            reader.read_exact(&mut req.body[start..])?;
+            line.clear();
+            reader.read_line(&mut line)?;
+            if size == 0 {
+                break;
+            }
+        }
+    } else if let Some(len) = req.header("content-length") {
+        let len: usize = len.parse().map_err(|_| bad_request("bad content-length"))?;
+        if len > MAX_BODY {
+            return Err(bad_request("body too large"));
+        }
+        req.body.resize(len, 0);
+        reader.read_exact(&mut req.body)?;
+    }
+    Ok(req)
+}
+
+/// Write `resp`, adding `Content-Length` unless it is already set
+pub fn write_response<W: Write>(stream: &mut W, resp: &Response) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", resp.status, resp.reason());
+    for (name, value) in &resp.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !resp.headers.iter().any(|(n, _)| n == "Content-Length") {
+        head.push_str(&format!("Content-Length: {}\r\n", resp.body.len()));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&resp.body)?;
+    stream.flush()
+}
+
+fn serve_one(stream: TcpStream, handle: &mut impl FnMut(&Request) -> Response) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let resp = match read_request(&mut reader, &mut writer) {
+        Ok(req) => handle(&req),
+        Err(e) => {
+            let mut resp = Response::new(400).header("Content-Type", "text/plain");
+            resp.body = e.to_string().into_bytes();
+            resp
+        }
+    };
+    write_response(&mut writer, &resp)
+}
+
+/// Answer requests from `listener` with `handle` until accepting fails
+///
+/// Connections are handled one at a time and closed after each response,
+/// which keeps a slow client from holding the handler.
+pub fn serve(
+    listener: TcpListener,
+    mut handle: impl FnMut(&Request) -> Response,
+) -> io::Result<()> {
+    for stream in listener.incoming() {
+        // A broken connection only affects its own client
+        let _ = serve_one(stream?, &mut handle);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framing() {
+        let raw = b"PUT /dev/k HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\
+                    Expect: 100-continue\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        let mut sent = Vec::new();
+        let req = read_request(&mut &raw[..], &mut sent).unwrap();
+        assert_eq!(req.body, b"abcde");
+        assert!(sent.starts_with(b"HTTP/1.1 100 Continue"));
+
+        let mut out = Vec::new();
+        write_response(&mut out, &Response::new(204)).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert_eq!(percent_encode("/a b/ü"), "/a%20b/%C3%BC");
+        assert_eq!(percent_decode("/a%20b/%C3%BC", false), "/a b/ü");
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(iso8601(951782400), "2000-02-29T00:00:00.000Z");
+    }
+}
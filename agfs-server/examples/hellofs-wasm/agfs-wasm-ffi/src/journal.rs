@@ -0,0 +1,832 @@
+//! Write-ahead journaling for crash consistency
+//!
+//! `JournaledFS` records each mutating call in a journal before passing it
+//! to the wrapped filesystem and clears the record once the call returns.
+//! A record still present at `initialize` belongs to a call the server
+//! crashed in, and is applied again before the plugin serves requests:
+//!
+//! ```ignore
+//! // Journal to the host file named by the `journal_path` config key
+//! export_plugin!(JournaledFS<StateFS, HostFileJournal>);
+//! ```
+//!
+//! Replay repeats the whole call, so it must be safe to apply twice.
+//! Whole-file writes, chmod and the create/mkdir/remove family are; errors
+//! saying the call already took effect (`AlreadyExists` on create,
+//! `NotFound` on remove or rename) are ignored during replay.
+
+use crate::batch::{FsOp, FsResult};
+use crate::context::OpContext;
+use crate::filesystem::FileSystem;
+use crate::host_fs::{HostCapabilities, HostFS};
+use crate::path::PathPolicy;
+use crate::stream::ReaddirStream;
+use crate::types::{
+    CachePolicy, Capabilities, Config, CreateFlags, Error, FileInfo, FsStats, Handle, HealthStatus,
+    LockKind, OpenFlags, QuotaInfo, RawJson, ReadResult, RenameFlags, Result, WriteResult,
+};
+use crate::watch::WatchId;
+use serde::{Deserialize, Serialize};
+
+/// Config key naming the host file `HostFileJournal` writes to
+pub const JOURNAL_PATH_KEY: &str = "journal_path";
+
+/// Durable storage for journal records
+pub trait Journal {
+    /// Prepare the journal; called from `initialize` before replay
+    fn open(&mut self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+
+    /// Store `record` after any pending ones; it must survive a crash
+    /// once this returns
+    fn append(&mut self, record: &[u8]) -> Result<()>;
+
+    /// Records appended since the last `clear`, oldest first
+    fn pending(&mut self) -> Result<Vec<Vec<u8>>>;
+
+    /// Drop all records
+    fn clear(&mut self) -> Result<()>;
+}
+
+/// Journal kept in guest memory
+///
+/// Lost with the module instance, so it only helps with tests and with
+/// calls that fail half-way; use `HostFileJournal` to survive a crash.
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+    records: Vec<Vec<u8>>,
+}
+
+impl Journal for MemoryJournal {
+    fn append(&mut self, record: &[u8]) -> Result<()> {
+        self.records.push(record.to_vec());
+        Ok(())
+    }
+
+    fn pending(&mut self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.records.clone())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.records.clear();
+        Ok(())
+    }
+}
+
+/// Journal stored as newline-separated records in a host file
+///
+/// Each append rewrites the file with `HostFS::write_atomic`. Calls are
+/// cleared as they finish, so the file holds at most a record or two.
+#[derive(Debug, Default)]
+pub struct HostFileJournal {
+    path: Option<String>,
+}
+
+impl HostFileJournal {
+    /// Journal to the host file `path`
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: Some(path.to_string()),
+        }
+    }
+
+    fn path(&self) -> Result<&str> {
+        self.path
+            .as_deref()
+            .ok_or_else(|| Error::InvalidInput(format!("{} is not set", JOURNAL_PATH_KEY)))
+    }
+
+    fn load(&self) -> Result<Vec<u8>> {
+        match HostFS::read(self.path()?, 0, -1) {
+            Err(Error::NotFound) => Ok(Vec::new()),
+            other => other,
+        }
+    }
+}
+
+impl Journal for HostFileJournal {
+    /// Takes the path from `journal_path` unless one was given to `new`
+    fn open(&mut self, config: &Config) -> Result<()> {
+        if self.path.is_none() {
+            self.path = config.get_str(JOURNAL_PATH_KEY).map(str::to_string);
+        }
+        self.path().map(|_| ())
+    }
+
+    fn append(&mut self, record: &[u8]) -> Result<()> {
+        let mut data = self.load()?;
+        data.extend_from_slice(record);
+        data.push(b'\n');
+        HostFS::write_atomic(self.path()?, &data).map(|_| ())
+    }
+
+    fn pending(&mut self) -> Result<Vec<Vec<u8>>> {
+        let data = self.load()?;
+        Ok(data
+            .split(|&b| b == b'\n')
+            .filter(|r| !r.is_empty())
+            .map(<[u8]>::to_vec)
+            .collect())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        match HostFS::remove(self.path()?) {
+            Err(Error::NotFound) => Ok(()),
+            other => other,
+        }
+    }
+}
+
+// One journaled call; records are its JSON encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Op {
+    Write { path: String, data: Vec<u8> },
+    WriteAt { path: String, offset: i64, data: Vec<u8> },
+    Append { path: String, data: Vec<u8> },
+    CopyRange {
+        src: String,
+        dst: String,
+        src_offset: i64,
+        dst_offset: i64,
+        len: i64,
+    },
+    Create { path: String },
+    CreateExclusive { path: String },
+    CreateWithFlags { path: String, flags: CreateFlags },
+    Mkdir { path: String, perm: u32 },
+    Remove { path: String },
+    RemoveAll { path: String },
+    Rename { old_path: String, new_path: String },
+    RenameWithFlags { old_path: String, new_path: String, flags: RenameFlags },
+    Chmod { path: String, mode: u32 },
+    Chown { path: String, uid: u32, gid: u32 },
+    SetTimes { path: String, atime: i64, mtime: i64 },
+    Truncate { path: String, size: i64 },
+    Allocate { path: String, offset: i64, len: i64 },
+    SetQuota { path: String, limits: QuotaInfo },
+    Link { existing: String, new: String },
+    Symlink { target: String, link_path: String },
+    SetXattr { path: String, name: String, value: Vec<u8> },
+    RemoveXattr { path: String, name: String },
+}
+
+impl Op {
+    fn apply<F: FileSystem>(&self, fs: &mut F) -> Result<Vec<u8>> {
+        match self {
+            Op::Write { path, data } => fs.write(path, data),
+            Op::Append { path, data } => fs.append(path, data).map(|_| Vec::new()),
+            Op::WriteAt { path, offset, data } => {
+                fs.write_at(path, *offset, data).map(|_| Vec::new())
+            }
+            Op::CopyRange {
+                src,
+                dst,
+                src_offset,
+                dst_offset,
+                len,
+            } => fs
+                .copy_range(src, dst, *src_offset, *dst_offset, *len)
+                .map(|_| Vec::new()),
+            Op::Create { path } => fs.create(path).map(|_| Vec::new()),
+            Op::CreateExclusive { path } => fs.create_exclusive(path).map(|_| Vec::new()),
+            Op::CreateWithFlags { path, flags } => {
+                fs.create_with_flags(path, *flags).map(|_| Vec::new())
+            }
+            Op::Mkdir { path, perm } => fs.mkdir(path, *perm).map(|_| Vec::new()),
+            Op::Remove { path } => fs.remove(path).map(|_| Vec::new()),
+            Op::RemoveAll { path } => fs.remove_all(path).map(|_| Vec::new()),
+            Op::Rename { old_path, new_path } => fs.rename(old_path, new_path).map(|_| Vec::new()),
+            Op::RenameWithFlags { old_path, new_path, flags } => {
+                fs.rename_with_flags(old_path, new_path, *flags).map(|_| Vec::new())
+            }
+            Op::Chmod { path, mode } => fs.chmod(path, *mode).map(|_| Vec::new()),
+            Op::Chown { path, uid, gid } => fs.chown(path, *uid, *gid).map(|_| Vec::new()),
+            Op::SetTimes { path, atime, mtime } => {
+                fs.set_times(path, *atime, *mtime).map(|_| Vec::new())
+            }
+            Op::Truncate { path, size } => fs.truncate(path, *size).map(|_| Vec::new()),
+            Op::Allocate { path, offset, len } => {
+                fs.allocate(path, *offset, *len).map(|_| Vec::new())
+            }
+            Op::SetQuota { path, limits } => fs.set_quota(path, *limits).map(|_| Vec::new()),
+            Op::SetXattr { path, name, value } => {
+                fs.setxattr(path, name, value).map(|_| Vec::new())
+            }
+            Op::RemoveXattr { path, name } => fs.removexattr(path, name).map(|_| Vec::new()),
+            Op::Link { existing, new } => fs.link(existing, new).map(|_| Vec::new()),
+            Op::Symlink { target, link_path } => fs.symlink(target, link_path).map(|_| Vec::new()),
+        }
+    }
+
+    // Whether `err` means an earlier attempt already got this far
+    fn already_applied(&self, err: &Error) -> bool {
+        match self {
+            Op::Create { .. }
+            | Op::CreateExclusive { .. }
+            | Op::Mkdir { .. }
+            | Op::Link { .. }
+            | Op::Symlink { .. } => *err == Error::AlreadyExists,
+            Op::CreateWithFlags { flags, .. } => flags.exclusive && *err == Error::AlreadyExists,
+            Op::Remove { .. }
+            | Op::RemoveAll { .. }
+            | Op::Rename { .. }
+            | Op::RemoveXattr { .. } => *err == Error::NotFound,
+            // A swap that went through finds both paths again and would
+            // swap them back; only the host can tell, so it is replayed
+            Op::RenameWithFlags { flags, .. } => {
+                !flags.contains(RenameFlags::EXCHANGE) && *err == Error::NotFound
+            }
+            Op::Write { .. }
+            | Op::WriteAt { .. }
+            | Op::Append { .. }
+            | Op::Chmod { .. }
+            | Op::Chown { .. }
+            | Op::SetTimes { .. }
+            | Op::Truncate { .. }
+            | Op::Allocate { .. }
+            | Op::SetQuota { .. }
+            | Op::CopyRange { .. }
+            | Op::SetXattr { .. } => false,
+        }
+    }
+}
+
+/// Filesystem wrapper that journals mutating calls on `inner`
+pub struct JournaledFS<F, J> {
+    inner: F,
+    journal: J,
+}
+
+impl<F: Default, J: Default> Default for JournaledFS<F, J> {
+    fn default() -> Self {
+        Self::new(F::default(), J::default())
+    }
+}
+
+impl<F, J> JournaledFS<F, J> {
+    /// Journal calls on `inner` to `journal`
+    pub fn new(inner: F, journal: J) -> Self {
+        Self { inner, journal }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F: FileSystem, J: Journal> JournaledFS<F, J> {
+    /// Apply the records left by an interrupted call, then clear them
+    ///
+    /// Runs from `initialize`; an error leaves the records for the next
+    /// attempt.
+    pub fn replay(&mut self) -> Result<()> {
+        for record in self.journal.pending()? {
+            let op: Op = serde_json::from_slice(&record)
+                .map_err(|e| Error::Io(format!("corrupt journal record: {}", e)))?;
+            match op.apply(&mut self.inner) {
+                Err(e) if !op.already_applied(&e) => return Err(e),
+                _ => {}
+            }
+        }
+        self.journal.clear()
+    }
+
+    // Journal `op`, apply it, then clear the journal
+    fn journaled(&mut self, op: Op) -> Result<Vec<u8>> {
+        self.journal_around(&op, |fs| op.apply(fs))
+    }
+
+    // Journal `op`, run `f`, which has the same effect, then clear the
+    // journal. A failed clear is reported even though the call went
+    // through, since the record would otherwise be replayed after later
+    // calls.
+    fn journal_around<T>(&mut self, op: &Op, f: impl FnOnce(&mut F) -> Result<T>) -> Result<T> {
+        let record = serde_json::to_vec(op).map_err(|e| Error::Other(e.to_string()))?;
+        self.journal.append(&record)?;
+        let result = f(&mut self.inner);
+        self.journal.clear()?;
+        result
+    }
+}
+
+impl<F: FileSystem, J: Journal> FileSystem for JournaledFS<F, J> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.journal.open(config)?;
+        self.inner.initialize(config)?;
+        self.replay()
+    }
+
+    fn reconfigure(&mut self, config: &Config) -> Result<()> {
+        self.inner.reconfigure(config)
+    }
+
+    fn path_policy(&self) -> PathPolicy {
+        self.inner.path_policy()
+    }
+
+    fn host_capabilities(&self) -> Option<HostCapabilities> {
+        self.inner.host_capabilities()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn health(&self) -> Result<HealthStatus> {
+        self.inner.health()
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.inner.cache_policy()
+    }
+
+    fn snapshot(&self) -> Result<Vec<u8>> {
+        self.inner.snapshot()
+    }
+
+    fn restore(&mut self, data: &[u8]) -> Result<()> {
+        self.inner.restore(data)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn trim(&mut self) {
+        self.inner.trim()
+    }
+
+    fn statfs(&self) -> Result<FsStats> {
+        self.inner.statfs()
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read(path, offset, size)
+    }
+
+    fn read_result(&self, path: &str, offset: i64, size: i64) -> Result<ReadResult> {
+        self.inner.read_result(path, offset, size)
+    }
+
+    fn read_ranges(&self, path: &str, ranges: &[(i64, i64)]) -> Result<Vec<Vec<u8>>> {
+        self.inner.read_ranges(path, ranges)
+    }
+
+    fn open(&mut self, path: &str, flags: OpenFlags) -> Result<Handle> {
+        self.inner.open(path, flags)
+    }
+
+    fn read_at(&self, handle: &Handle, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read_at(handle, offset, size)
+    }
+
+    fn close(&mut self, handle: Handle) -> Result<()> {
+        self.inner.close(handle)
+    }
+
+    fn watch(&mut self, path: &str) -> Result<WatchId> {
+        self.inner.watch(path)
+    }
+
+    fn unwatch(&mut self, id: WatchId) -> Result<()> {
+        self.inner.unwatch(id)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        self.journaled(Op::Write {
+            path: path.to_string(),
+            data: data.to_vec(),
+        })
+    }
+
+    fn write_result(&mut self, path: &str, data: &[u8]) -> Result<WriteResult> {
+        let op = Op::Write {
+            path: path.to_string(),
+            data: data.to_vec(),
+        };
+        self.journal_around(&op, |fs| fs.write_result(path, data))
+    }
+
+    /// Journaled writes complete or fail as a whole, so on success all of
+    /// `data` was written
+    fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        self.journaled(Op::WriteAt {
+            path: path.to_string(),
+            offset,
+            data: data.to_vec(),
+        })
+        .map(|_| data.len() as i64)
+    }
+
+    /// An append interrupted after the inner filesystem applied it is
+    /// applied again on replay; there is no way to tell from the file
+    fn append(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        self.journaled(Op::Append {
+            path: path.to_string(),
+            data: data.to_vec(),
+        })
+        .map(|_| data.len() as i64)
+    }
+
+    fn copy_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        src_offset: i64,
+        dst_offset: i64,
+        len: i64,
+    ) -> Result<i64> {
+        let op = Op::CopyRange {
+            src: src.to_string(),
+            dst: dst.to_string(),
+            src_offset,
+            dst_offset,
+            len,
+        };
+        self.journal_around(&op, |fs| {
+            fs.copy_range(src, dst, src_offset, dst_offset, len)
+        })
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.journaled(Op::Create {
+            path: path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn create_exclusive(&mut self, path: &str) -> Result<()> {
+        self.journaled(Op::CreateExclusive {
+            path: path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn create_with_flags(&mut self, path: &str, flags: CreateFlags) -> Result<()> {
+        self.journaled(Op::CreateWithFlags {
+            path: path.to_string(),
+            flags,
+        })
+        .map(|_| ())
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.journaled(Op::Mkdir {
+            path: path.to_string(),
+            perm,
+        })
+        .map(|_| ())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.journaled(Op::Remove {
+            path: path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.journaled(Op::RemoveAll {
+            path: path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.inner.stat(path)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir(path)
+    }
+
+    fn readdir_plus(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir_plus(path)
+    }
+
+    fn readdir_stream(&self, path: &str) -> Result<ReaddirStream> {
+        self.inner.readdir_stream(path)
+    }
+
+    fn readdir_page(
+        &self,
+        path: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FileInfo>, Option<String>)> {
+        self.inner.readdir_page(path, cursor, limit)
+    }
+
+    fn batch(&self, ops: Vec<FsOp>) -> Vec<FsResult> {
+        self.inner.batch(ops)
+    }
+
+    fn stat_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        self.inner.stat_passthrough(path)
+    }
+
+    fn readdir_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        self.inner.readdir_passthrough(path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.journaled(Op::Rename {
+            old_path: old_path.to_string(),
+            new_path: new_path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn rename_with_flags(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        self.journaled(Op::RenameWithFlags {
+            old_path: old_path.to_string(),
+            new_path: new_path.to_string(),
+            flags,
+        })
+        .map(|_| ())
+    }
+
+    fn read_with_ctx(
+        &self,
+        ctx: &OpContext,
+        path: &str,
+        offset: i64,
+        size: i64,
+    ) -> Result<Vec<u8>> {
+        self.inner.read_with_ctx(ctx, path, offset, size)
+    }
+
+    fn write_with_ctx(&mut self, ctx: &OpContext, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let op = Op::Write {
+            path: path.to_string(),
+            data: data.to_vec(),
+        };
+        self.journal_around(&op, |fs| fs.write_with_ctx(ctx, path, data))
+    }
+
+    fn stat_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<FileInfo> {
+        self.inner.stat_with_ctx(ctx, path)
+    }
+
+    fn readdir_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir_with_ctx(ctx, path)
+    }
+
+    fn create_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let op = Op::Create {
+            path: path.to_string(),
+        };
+        self.journal_around(&op, |fs| fs.create_with_ctx(ctx, path))
+    }
+
+    fn mkdir_with_ctx(&mut self, ctx: &OpContext, path: &str, perm: u32) -> Result<()> {
+        let op = Op::Mkdir {
+            path: path.to_string(),
+            perm,
+        };
+        self.journal_around(&op, |fs| fs.mkdir_with_ctx(ctx, path, perm))
+    }
+
+    fn remove_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let op = Op::Remove {
+            path: path.to_string(),
+        };
+        self.journal_around(&op, |fs| fs.remove_with_ctx(ctx, path))
+    }
+
+    fn rename_with_ctx(
+        &mut self,
+        ctx: &OpContext,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<()> {
+        let op = Op::Rename {
+            old_path: old_path.to_string(),
+            new_path: new_path.to_string(),
+        };
+        self.journal_around(&op, |fs| fs.rename_with_ctx(ctx, old_path, new_path))
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.journaled(Op::Chmod {
+            path: path.to_string(),
+            mode,
+        })
+        .map(|_| ())
+    }
+
+    fn chown(&mut self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        self.journaled(Op::Chown {
+            path: path.to_string(),
+            uid,
+            gid,
+        })
+        .map(|_| ())
+    }
+
+    fn set_times(&mut self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        self.journaled(Op::SetTimes {
+            path: path.to_string(),
+            atime,
+            mtime,
+        })
+        .map(|_| ())
+    }
+
+    fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+        self.journaled(Op::Truncate {
+            path: path.to_string(),
+            size,
+        })
+        .map(|_| ())
+    }
+
+    fn allocate(&mut self, path: &str, offset: i64, len: i64) -> Result<()> {
+        self.journaled(Op::Allocate {
+            path: path.to_string(),
+            offset,
+            len,
+        })
+        .map(|_| ())
+    }
+
+    fn quota(&self, path: &str) -> Result<QuotaInfo> {
+        self.inner.quota(path)
+    }
+
+    fn set_quota(&mut self, path: &str, limits: QuotaInfo) -> Result<()> {
+        self.journaled(Op::SetQuota {
+            path: path.to_string(),
+            limits,
+        })
+        .map(|_| ())
+    }
+
+    fn flush(&mut self, path: &str) -> Result<()> {
+        self.inner.flush(path)
+    }
+
+    fn fsync(&mut self, path: &str) -> Result<()> {
+        self.inner.fsync(path)
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<()> {
+        self.journaled(Op::Link {
+            existing: existing.to_string(),
+            new: new.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        self.journaled(Op::Symlink {
+            target: target.to_string(),
+            link_path: link_path.to_string(),
+        })
+        .map(|_| ())
+    }
+
+    fn access(&self, path: &str, mode: u32) -> Result<()> {
+        self.inner.access(path, mode)
+    }
+
+    fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_data(path, offset)
+    }
+
+    fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_hole(path, offset)
+    }
+
+    fn lock(&mut self, path: &str, kind: LockKind) -> Result<()> {
+        self.inner.lock(path, kind)
+    }
+
+    fn unlock(&mut self, path: &str) -> Result<()> {
+        self.inner.unlock(path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.inner.readlink(path)
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.getxattr(path, name)
+    }
+
+    fn setxattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.journaled(Op::SetXattr {
+            path: path.to_string(),
+            name: name.to_string(),
+            value: value.to_vec(),
+        })
+        .map(|_| ())
+    }
+
+    fn listxattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.listxattr(path)
+    }
+
+    fn removexattr(&mut self, path: &str, name: &str) -> Result<()> {
+        self.journaled(Op::RemoveXattr {
+            path: path.to_string(),
+            name: name.to_string(),
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            let data = self.files.remove(old_path).ok_or(Error::NotFound)?;
+            self.files.insert(new_path.to_string(), data);
+            Ok(())
+        }
+
+        fn stat(&self, _path: &str) -> Result<FileInfo> {
+            Err(Error::NotFound)
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn record(op: Op) -> Vec<u8> {
+        serde_json::to_vec(&op).unwrap()
+    }
+
+    #[test]
+    fn test_replay_after_crash() {
+        // The server died during the rename, after it had taken effect,
+        // and before the write that followed it was cleared
+        let mut journal = MemoryJournal::default();
+        journal
+            .append(&record(Op::Rename {
+                old_path: "/a".to_string(),
+                new_path: "/b".to_string(),
+            }))
+            .unwrap();
+        journal
+            .append(&record(Op::Write {
+                path: "/c".to_string(),
+                data: b"c".to_vec(),
+            }))
+            .unwrap();
+        let mut inner = MemFS::default();
+        inner.files.insert("/b".to_string(), b"b".to_vec());
+
+        let mut fs = JournaledFS::new(inner, journal);
+        fs.initialize(&Config::from(serde_json::json!({}))).unwrap();
+        assert_eq!(fs.inner().files["/c"], b"c");
+        assert_eq!(fs.inner().files.len(), 2);
+        assert!(fs.journal.pending().unwrap().is_empty());
+
+        fs.write("/d", b"d").unwrap();
+        assert!(fs.rename("/missing", "/e").is_err());
+        assert!(fs.journal.pending().unwrap().is_empty());
+
+        fs.journal.append(b"not json").unwrap();
+        assert!(fs.replay().is_err());
+        assert_eq!(fs.journal.pending().unwrap().len(), 1);
+    }
+}
@@ -56,23 +56,114 @@
 //!
 //! export_plugin!(HelloFS);
 //! ```
+//!
+//! With the `native` feature the same plugin builds as a shared library
+//! for agfs-server's native loader instead (see the `native` module).
 
+#[cfg(feature = "tokio")]
+pub mod async_file;
+pub mod audit;
+pub mod base64;
+pub mod batch;
+pub mod cache;
+pub mod cancel;
+pub mod checksum;
+pub mod chunk;
+pub mod context;
 pub mod ffi;
 pub mod filesystem;
+#[cfg(any(feature = "s3-gateway", feature = "webdav"))]
+pub mod http;
+pub mod journal;
+pub mod lifecycle;
 pub mod macros;
+pub mod manifest;
 pub mod memory;
+pub mod metrics;
+pub mod mirror;
+#[cfg(feature = "native")]
+pub mod native;
+pub mod ninep;
+pub mod parquet;
+pub mod path;
+pub mod quota;
+pub mod range;
+pub mod rclone;
+pub mod reader;
+#[cfg(feature = "s3-gateway")]
+pub mod s3;
+pub mod sandbox;
+pub mod serde_file;
+pub mod state;
+pub mod stream;
+pub mod time;
+pub mod trace;
 pub mod types;
+#[cfg(feature = "webdav")]
+pub mod webdav;
+pub mod watch;
+pub mod host_dns;
+pub mod host_env;
+pub mod host_exec;
 pub mod host_fs;
+pub mod host_http;
+pub mod host_kv;
+pub mod host_log;
+pub mod host_metrics;
+pub mod host_random;
+pub mod host_tcp;
+pub mod host_temp;
+
+pub use agfs_path::nfc;
 
 // Re-exports for convenience
+pub use cache::{BlockCache, CacheStats};
+pub use cancel::CancelToken;
 pub use filesystem::{FileSystem, ReadOnlyFileSystem};
-pub use types::{Config, Error, FileInfo, MetaData, Result};
-pub use host_fs::HostFS;
+pub use quota::QuotaFS;
+pub use stream::ReaddirStream;
+pub use types::{
+    CachePolicy, Capabilities, Config, CreateFlags, DirPage, Error, FileInfo, FsStats, Handle,
+    HealthState, HealthStatus, LockKind, MetaData, OpenFlags, QuotaInfo, RawJson, ReadResult,
+    RenameFlags, Result, WriteResult,
+};
+pub use host_dns::HostDns;
+pub use host_env::HostEnv;
+pub use host_exec::{ExecOutput, HostCommand};
+pub use host_fs::{HostFS, HostReader};
+pub use host_http::{HostHttp, HttpRequest, HttpResponse};
+pub use host_kv::HostKv;
+pub use host_log::HostLogger;
+pub use host_metrics::HostMetrics;
+pub use host_random::HostRandom;
+pub use host_tcp::HostTcpStream;
+pub use host_temp::HostTemp;
+pub use sandbox::SafeHostFS;
 
 /// Prelude module with common imports
 pub mod prelude {
+    pub use crate::cancel::CancelToken;
+    pub use crate::context::{check_permission, Access, Context, Identity, OpContext, Owner};
     pub use crate::export_plugin;
     pub use crate::filesystem::{FileSystem, ReadOnlyFileSystem};
-    pub use crate::types::{Config, Error, FileInfo, MetaData, Result};
-    pub use crate::host_fs::HostFS;
+    pub use crate::range::slice_range;
+    pub use crate::stream::ReaddirStream;
+    pub use crate::types::{
+        CachePolicy, Capabilities, Config, CreateFlags, DirPage, Error, FileInfo, FsStats, Handle,
+        HealthState, HealthStatus, LockKind, MetaData, OpenFlags, QuotaInfo, RawJson, ReadResult,
+        RenameFlags, Result, WriteResult,
+    };
+    pub use crate::host_dns::HostDns;
+    pub use crate::host_env::HostEnv;
+    pub use crate::host_exec::{ExecOutput, HostCommand};
+    pub use crate::host_fs::{HostFS, HostReader};
+    pub use crate::host_http::{HostHttp, HttpRequest, HttpResponse};
+    pub use crate::host_kv::HostKv;
+    pub use crate::host_log::HostLogger;
+    pub use crate::host_metrics::HostMetrics;
+    pub use crate::host_random::HostRandom;
+    pub use crate::host_tcp::HostTcpStream;
+    pub use crate::host_temp::HostTemp;
+    pub use crate::sandbox::SafeHostFS;
+    pub use crate::watch::{EventKind, WatchId, Watches};
 }
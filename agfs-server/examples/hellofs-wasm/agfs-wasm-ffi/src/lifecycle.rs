@@ -0,0 +1,145 @@
+//! Plugin lifecycle tracking for the export glue
+//!
+//! The generated exports move the plugin through
+//! `Created → Initialized → ShuttingDown → Stopped` and reject calls that
+//! arrive out of order: filesystem calls before initialize or after
+//! shutdown, a second initialize, or a second shutdown. Transitions are
+//! compare-and-swap, so two racing initialize calls cannot both reach the
+//! plugin.
+
+use crate::types::{Error, Result};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Where the plugin is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum State {
+    /// Constructed by `plugin_new`, not yet initialized
+    Created = 0,
+    /// `plugin_initialize` is running
+    Initializing = 1,
+    /// Serving filesystem calls
+    Initialized = 2,
+    /// `plugin_shutdown` is running
+    ShuttingDown = 3,
+    /// Shut down; only `plugin_new` brings it back
+    Stopped = 4,
+}
+
+impl State {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => State::Created,
+            1 => State::Initializing,
+            2 => State::Initialized,
+            3 => State::ShuttingDown,
+            _ => State::Stopped,
+        }
+    }
+}
+
+static STATE: AtomicU8 = AtomicU8::new(State::Created as u8);
+
+/// Current lifecycle state
+pub fn state() -> State {
+    State::from_u8(STATE.load(Ordering::Acquire))
+}
+
+fn transition(from: State, to: State) -> std::result::Result<(), State> {
+    STATE
+        .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        .map_err(State::from_u8)
+}
+
+fn out_of_order(call: &str, state: State) -> Error {
+    let why = match state {
+        State::Created => "plugin is not initialized",
+        State::Initializing => "plugin is still initializing",
+        State::Initialized => "plugin is already initialized",
+        State::ShuttingDown => "plugin is shutting down",
+        State::Stopped => "plugin is stopped",
+    };
+    Error::Other(format!("{}: {}", call, why))
+}
+
+/// Start over with a fresh plugin instance (called by `plugin_new`)
+///
+/// Fails while an instance is initializing, serving or shutting down.
+pub fn reset() -> Result<()> {
+    match state() {
+        State::Created | State::Stopped => {
+            STATE.store(State::Created as u8, Ordering::Release);
+            Ok(())
+        }
+        s => Err(out_of_order("plugin_new", s)),
+    }
+}
+
+/// Claim the plugin for initialization
+pub fn begin_initialize() -> Result<()> {
+    transition(State::Created, State::Initializing).map_err(|s| out_of_order("initialize", s))
+}
+
+/// Finish initialization; a failed one may be retried
+pub fn finish_initialize(ok: bool) {
+    let to = if ok { State::Initialized } else { State::Created };
+    STATE.store(to as u8, Ordering::Release);
+}
+
+/// Claim the plugin for shutdown
+///
+/// Shutting down a plugin that was never initialized is allowed, so the
+/// host can clean up after a failed initialize.
+pub fn begin_shutdown() -> Result<()> {
+    transition(State::Initialized, State::ShuttingDown)
+        .or_else(|_| transition(State::Created, State::ShuttingDown))
+        .map_err(|s| out_of_order("shutdown", s))
+}
+
+/// Finish shutdown; the plugin stays stopped even if its shutdown failed
+pub fn finish_shutdown() {
+    STATE.store(State::Stopped as u8, Ordering::Release);
+}
+
+/// Check that the plugin can serve a filesystem call
+pub fn ensure_serving(call: &str) -> Result<()> {
+    match state() {
+        State::Initialized => Ok(()),
+        s => Err(out_of_order(call, s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The state is global, so the whole sequence runs in one test
+    #[test]
+    fn test_lifecycle_order() {
+        reset().unwrap();
+        assert!(ensure_serving("read").is_err());
+
+        begin_initialize().unwrap();
+        let err = begin_initialize().unwrap_err();
+        assert_eq!(err.to_string(), "initialize: plugin is still initializing");
+        finish_initialize(false);
+        assert_eq!(state(), State::Created);
+
+        begin_initialize().unwrap();
+        finish_initialize(true);
+        ensure_serving("read").unwrap();
+        assert!(begin_initialize().is_err());
+        assert!(reset().is_err());
+
+        begin_shutdown().unwrap();
+        assert!(ensure_serving("read").is_err());
+        finish_shutdown();
+        let err = begin_shutdown().unwrap_err();
+        assert_eq!(err.to_string(), "shutdown: plugin is stopped");
+        assert!(ensure_serving("write").is_err());
+
+        reset().unwrap();
+        assert_eq!(state(), State::Created);
+    }
+}
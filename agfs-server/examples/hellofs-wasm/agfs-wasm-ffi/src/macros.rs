@@ -1,19 +1,52 @@
 //! Macros for exporting WASM plugin functions
 
 /// Export a FileSystem implementation as a WASM plugin
+///
+/// `export_plugin!(T, manifest { name: .., version: .., build: .. })` also
+/// embeds a manifest for provenance checks (see the `manifest` module);
+/// `build` is optional and every value must expand to a string literal.
+///
+/// The plugin's global allocator is left alone unless the SDK's `heap-stats`
+/// feature is on, which installs `TrackingAllocator` for `plugin_heap_stats`
+/// and the byte counts of `plugin_trim`.
+#[cfg(not(feature = "native"))]
 #[macro_export]
 macro_rules! export_plugin {
+    ($plugin_type:ty, manifest {
+        name: $name:expr,
+        version: $version:expr
+        $(, build: $build:expr)?
+        $(,)?
+    }) => {
+        $crate::export_plugin!($plugin_type);
+
+        #[cfg(target_arch = "wasm32")]
+        #[link_section = "agfs.manifest"]
+        #[used]
+        static AGFS_MANIFEST: [u8; {
+            concat!("name=", $name, "\nversion=", $version, "\n" $(, "build=", $build, "\n")?).len()
+        }] = $crate::manifest::manifest_bytes(concat!(
+            "name=", $name, "\nversion=", $version, "\n" $(, "build=", $build, "\n")?
+        ));
+    };
     ($plugin_type:ty) => {
         static mut PLUGIN: Option<$plugin_type> = None;
 
+        $crate::__export_heap_stats!();
+
         // Force type checking
         const _: fn() = || {
             fn assert_impl<T: $crate::FileSystem + Default>() {}
             assert_impl::<$plugin_type>();
         };
 
+        /// Returns 0 if an instance is still initializing, serving or
+        /// shutting down
         #[no_mangle]
         pub extern "C" fn plugin_new() -> usize {
+            if $crate::lifecycle::reset().is_err() {
+                return 0;
+            }
             unsafe {
                 PLUGIN = Some(<$plugin_type>::default());
             }
@@ -40,7 +73,84 @@ macro_rules! export_plugin {
             }
         }
 
+        /// `Capabilities` bits of the plugin; the host reads them after
+        /// initialize and refuses modifying calls itself without `WRITABLE`
+        #[no_mangle]
+        pub extern "C" fn plugin_capabilities() -> u32 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                <$plugin_type as $crate::FileSystem>::capabilities(p).0
+            }
+        }
+
+        /// Probe the plugin; a `HealthStatus` as JSON, or an error the host
+        /// treats as unhealthy
+        #[no_mangle]
+        pub extern "C" fn plugin_health() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_health(p)
+            }
+        }
+
+        /// Returns the `CachePolicy` as JSON
+        #[no_mangle]
+        pub extern "C" fn plugin_cache_policy() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_cache_policy(p)
+            }
+        }
+
+        /// Set the context (caller identity, ...) of the following calls
         #[no_mangle]
+        pub extern "C" fn plugin_set_context(json_ptr: *const u8) -> *mut u8 {
+            $crate::ffi::handle_set_context(json_ptr)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_watch(path_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_watch(p, path_ptr)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_unwatch(id: u32) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_unwatch(p, id)
+            }
+        }
+
+        /// Poll the next change event as JSON; null when there is none
+        #[no_mangle]
+        pub extern "C" fn fs_next_event() -> *mut u8 {
+            $crate::ffi::handle_next_event()
+        }
+
+        /// Collect queued tracing spans as an OTLP/JSON export request
+        #[no_mangle]
+        pub extern "C" fn plugin_trace_drain() -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_trace_drain(p)
+            }
+        }
+
+        /// Drop pools and caches of an idle mount; returns the bytes released,
+        /// or u64::MAX without heap tracking
+        #[no_mangle]
+        pub extern "C" fn plugin_trim() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_trim(p)
+            }
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
         pub extern "C" fn plugin_validate(config_ptr: *const u8) -> *mut u8 {
             use $crate::ffi::{read_config, result_to_error_ptr};
             use $crate::FileSystem;
@@ -55,6 +165,7 @@ macro_rules! export_plugin {
         }
 
         #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
         pub extern "C" fn plugin_initialize(config_ptr: *const u8) -> *mut u8 {
             use $crate::ffi::{read_config, result_to_error_ptr};
             use $crate::FileSystem;
@@ -62,9 +173,52 @@ macro_rules! export_plugin {
                 Ok(c) => c,
                 Err(e) => return result_to_error_ptr::<()>(Err(e)),
             };
+            if let Err(e) = $crate::lifecycle::begin_initialize() {
+                return result_to_error_ptr::<()>(Err(e));
+            }
+            $crate::ffi::configure_glue(&config);
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::initialize(p, &config))
+                let result = <$plugin_type as $crate::FileSystem>::initialize(p, &config);
+                $crate::ffi::set_path_policy(<$plugin_type as $crate::FileSystem>::path_policy(p));
+                let result = result.and_then(|_| {
+                    match <$plugin_type as $crate::FileSystem>::host_capabilities(p) {
+                        Some(caps) => $crate::host_fs::HostFS::restrict(caps),
+                        None => Ok(()),
+                    }
+                });
+                $crate::lifecycle::finish_initialize(result.is_ok());
+                result_to_error_ptr::<()>(result)
+            }
+        }
+
+        /// Push updated configuration to the initialized plugin
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn plugin_reconfigure(config_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_reconfigure(p, config_ptr)
+            }
+        }
+
+        /// The plugin's state for a later `plugin_restore`, packed as
+        /// (pointer, length)
+        #[no_mangle]
+        pub extern "C" fn plugin_snapshot() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_snapshot(p)
+            }
+        }
+
+        /// Restore a `plugin_snapshot` result into the initialized plugin
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn plugin_restore(data_ptr: *const u8, size: usize) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_restore(p, data_ptr, size)
             }
         }
 
@@ -72,193 +226,451 @@ macro_rules! export_plugin {
         pub extern "C" fn plugin_shutdown() -> *mut u8 {
             use $crate::ffi::result_to_error_ptr;
             use $crate::FileSystem;
+            if let Err(e) = $crate::lifecycle::begin_shutdown() {
+                return result_to_error_ptr::<()>(Err(e));
+            }
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::shutdown(p))
+                let result = <$plugin_type as $crate::FileSystem>::shutdown(p);
+                $crate::lifecycle::finish_shutdown();
+                result_to_error_ptr::<()>(result)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_read(path_ptr: *const u8, offset: i64, size: i64) -> u64 {
-            use $crate::memory::{CString, Buffer, pack_u64};
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_read(p, path_ptr, offset, size)
+            }
+        }
+
+        /// Returns a flag byte (1 at end of file) followed by the data
+        #[no_mangle]
+        pub extern "C" fn fs_read_result(path_ptr: *const u8, offset: i64, size: i64) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_read_result(p, path_ptr, offset, size)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        /// `ranges_ptr`: JSON array of `[offset, size]` pairs
+        #[no_mangle]
+        pub extern "C" fn fs_read_ranges(path_ptr: *const u8, ranges_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_read_ranges(p, path_ptr, ranges_ptr)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_open(path_ptr: *const u8, flags: u32) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_open(p, path_ptr, flags)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_read_at(handle: u32, offset: i64, size: i64) -> u64 {
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::read(p, &path, offset, size) {
-                    Ok(data) => {
-                        let len = data.len() as u32;
-                        let buffer = Buffer::from_bytes(&data);
-                        let ptr = buffer.into_raw() as u32;
-                        pack_u64(ptr, len)
-                    }
-                    Err(_) => 0,
-                }
+                $crate::ffi::handle_read_at(p, handle, offset, size)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_close(handle: u32) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_close(p, handle)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_stat(path_ptr: *const u8) -> u64 {
-            use $crate::memory::{CString, pack_u64};
-            use $crate::ffi::fileinfo_to_json_ptr;
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_stat(p, path_ptr)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        #[no_mangle]
+        pub extern "C" fn fs_statfs() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_statfs(p)
+            }
+        }
 
+        /// Run a JSON array of stat/read/readdir calls; see the `batch`
+        /// module
+        #[no_mangle]
+        pub extern "C" fn fs_batch(json_ptr: *const u8) -> u64 {
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::stat(p, &path) {
-                    Ok(info) => match fileinfo_to_json_ptr(&info) {
-                        Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
-                        Err(e) => {
-                            let err_ptr = CString::new(&e.to_string()).into_raw();
-                            pack_u64(0, err_ptr as u32)
-                        }
-                    },
-                    Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
-                        pack_u64(0, err_ptr as u32)
-                    }
-                }
+                $crate::ffi::handle_batch(p, json_ptr)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_readdir(path_ptr: *const u8) -> u64 {
-            use $crate::memory::{CString, pack_u64};
-            use $crate::ffi::fileinfo_vec_to_json_ptr;
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_readdir(p, path_ptr)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        /// Returns (cursor, 0) for `fs_readdir_next`
+        #[no_mangle]
+        pub extern "C" fn fs_readdir_open(path_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_readdir_open(p, path_ptr)
+            }
+        }
 
+        /// Returns the next batch as a JSON array, empty at the end
+        #[no_mangle]
+        pub extern "C" fn fs_readdir_next(cursor: u32, max: u32) -> u64 {
+            $crate::ffi::handle_readdir_next(cursor, max)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_readdir_close(cursor: u32) -> *mut u8 {
+            $crate::ffi::handle_readdir_close(cursor)
+        }
+
+        /// Entries are complete stat results the host may cache
+        #[no_mangle]
+        pub extern "C" fn fs_readdir_plus(path_ptr: *const u8) -> u64 {
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::readdir(p, &path) {
-                    Ok(infos) => match fileinfo_vec_to_json_ptr(&infos) {
-                        Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
-                        Err(e) => {
-                            let err_ptr = CString::new(&e.to_string()).into_raw();
-                            pack_u64(0, err_ptr as u32)
-                        }
-                    },
-                    Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
-                        pack_u64(0, err_ptr as u32)
-                    }
-                }
+                $crate::ffi::handle_readdir_plus(p, path_ptr)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_readdir_page(
+            path_ptr: *const u8,
+            token_ptr: *const u8,
+            limit: u32,
+        ) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_readdir_page(p, path_ptr, token_ptr, limit)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_write(path_ptr: *const u8, data_ptr: *const u8, size: usize) -> u64 {
-            use $crate::memory::{CString, Buffer, pack_u64};
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_write(p, path_ptr, data_ptr, size)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
-            let data = unsafe { std::slice::from_raw_parts(data_ptr, size) };
+        /// Returns the `WriteResult` as JSON
+        #[no_mangle]
+        pub extern "C" fn fs_write_result(
+            path_ptr: *const u8,
+            data_ptr: *const u8,
+            size: usize,
+        ) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_write_result(p, path_ptr, data_ptr, size)
+            }
+        }
 
+        /// Returns (bytes appended, 0) like `fs_write_at`
+        #[no_mangle]
+        pub extern "C" fn fs_append(path_ptr: *const u8, data_ptr: *const u8, size: usize) -> u64 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::write(p, &path, data) {
-                    Ok(response) => {
-                        let len = response.len() as u32;
-                        let buffer = Buffer::from_bytes(&response);
-                        let ptr = buffer.into_raw() as u32;
-                        pack_u64(ptr, len)
-                    }
-                    Err(_) => 0,
-                }
+                $crate::ffi::handle_append(p, path_ptr, data_ptr, size)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_write_at(
+            path_ptr: *const u8,
+            offset: i64,
+            data_ptr: *const u8,
+            size: usize,
+        ) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_write_at(p, path_ptr, offset, data_ptr, size)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_copy_range(
+            src_ptr: *const u8,
+            dst_ptr: *const u8,
+            src_offset: i64,
+            dst_offset: i64,
+            len: i64,
+        ) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_copy_range(p, src_ptr, dst_ptr, src_offset, dst_offset, len)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_create(path_ptr: *const u8) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_create(p, path_ptr)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        /// Fails with `EEXIST` if the path exists
+        #[no_mangle]
+        pub extern "C" fn fs_create_exclusive(path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_create_exclusive(p, path_ptr)
+            }
+        }
 
+        /// `flags`: 1 exclusive, 2 truncate, 4 `mode` is set
+        #[no_mangle]
+        pub extern "C" fn fs_create_flags(path_ptr: *const u8, flags: u32, mode: u32) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::create(p, &path))
+                $crate::ffi::handle_create_flags(p, path_ptr, flags, mode)
             }
         }
 
         #[no_mangle]
         pub extern "C" fn fs_mkdir(path_ptr: *const u8, perm: u32) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_mkdir(p, path_ptr, perm)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        #[no_mangle]
+        pub extern "C" fn fs_remove(path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_remove(p, path_ptr)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_remove_all(path_ptr: *const u8) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::mkdir(p, &path, perm))
+                $crate::ffi::handle_remove_all(p, path_ptr)
             }
         }
 
         #[no_mangle]
-        pub extern "C" fn fs_remove(path_ptr: *const u8) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+        pub extern "C" fn fs_rename(old_path_ptr: *const u8, new_path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_rename(p, old_path_ptr, new_path_ptr)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        /// `flags`: 1 noreplace, 2 exchange
+        #[no_mangle]
+        pub extern "C" fn fs_rename_flags(
+            old_path_ptr: *const u8,
+            new_path_ptr: *const u8,
+            flags: u32,
+        ) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_rename_flags(p, old_path_ptr, new_path_ptr, flags)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_chmod(path_ptr: *const u8, mode: u32) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::remove(p, &path))
+                $crate::ffi::handle_chmod(p, path_ptr, mode)
             }
         }
 
         #[no_mangle]
-        pub extern "C" fn fs_remove_all(path_ptr: *const u8) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+        pub extern "C" fn fs_chown(path_ptr: *const u8, uid: u32, gid: u32) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_chown(p, path_ptr, uid, gid)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        #[no_mangle]
+        pub extern "C" fn fs_set_times(path_ptr: *const u8, atime: i64, mtime: i64) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_set_times(p, path_ptr, atime, mtime)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_truncate(path_ptr: *const u8, size: i64) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::remove_all(p, &path))
+                $crate::ffi::handle_truncate(p, path_ptr, size)
             }
         }
 
+        /// Returns the `QuotaInfo` as JSON
         #[no_mangle]
-        pub extern "C" fn fs_rename(old_path_ptr: *const u8, new_path_ptr: *const u8) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+        pub extern "C" fn fs_quota(path_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_quota(p, path_ptr)
+            }
+        }
 
-            let old_path = unsafe { CString::from_ptr(old_path_ptr) };
-            let new_path = unsafe { CString::from_ptr(new_path_ptr) };
+        #[no_mangle]
+        pub extern "C" fn fs_set_quota(path_ptr: *const u8, limits_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_set_quota(p, path_ptr, limits_ptr)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_allocate(path_ptr: *const u8, offset: i64, len: i64) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::rename(p, &old_path, &new_path))
+                $crate::ffi::handle_allocate(p, path_ptr, offset, len)
             }
         }
 
         #[no_mangle]
-        pub extern "C" fn fs_chmod(path_ptr: *const u8, mode: u32) -> *mut u8 {
-            use $crate::memory::CString;
-            use $crate::ffi::result_to_error_ptr;
-            use $crate::FileSystem;
+        pub extern "C" fn fs_flush(path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_flush(p, path_ptr)
+            }
+        }
 
-            let path = unsafe { CString::from_ptr(path_ptr) };
+        #[no_mangle]
+        pub extern "C" fn fs_fsync(path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_fsync(p, path_ptr)
+            }
+        }
 
+        #[no_mangle]
+        pub extern "C" fn fs_link(existing_ptr: *const u8, new_ptr: *const u8) -> *mut u8 {
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::chmod(p, &path, mode))
+                $crate::ffi::handle_link(p, existing_ptr, new_ptr)
             }
         }
 
-        // Export malloc and free for Go compatibility
+        #[no_mangle]
+        pub extern "C" fn fs_symlink(target_ptr: *const u8, link_path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_symlink(p, target_ptr, link_path_ptr)
+            }
+        }
+
+        /// Returns the link target like `fs_read` returns data
+        #[no_mangle]
+        pub extern "C" fn fs_readlink(path_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_readlink(p, path_ptr)
+            }
+        }
+
+        /// `mode` as for access(2): 0, or any of 4 (read), 2 (write), 1 (execute)
+        #[no_mangle]
+        pub extern "C" fn fs_access(path_ptr: *const u8, mode: u32) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_access(p, path_ptr, mode)
+            }
+        }
+
+        /// Returns the next data offset, or `-ENXIO`/`-errno`
+        #[no_mangle]
+        pub extern "C" fn fs_seek_data(path_ptr: *const u8, offset: i64) -> i64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_seek(p, path_ptr, offset, false)
+            }
+        }
+
+        /// Returns the next hole offset, or `-ENXIO`/`-errno`
+        #[no_mangle]
+        pub extern "C" fn fs_seek_hole(path_ptr: *const u8, offset: i64) -> i64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_seek(p, path_ptr, offset, true)
+            }
+        }
+
+        /// `kind`: 1 shared, 2 exclusive; fails at once on a conflict
+        #[no_mangle]
+        pub extern "C" fn fs_lock(path_ptr: *const u8, kind: u32) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_lock(p, path_ptr, kind)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_unlock(path_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_unlock(p, path_ptr)
+            }
+        }
+
+        /// Returns the value like `fs_read` returns data
+        #[no_mangle]
+        pub extern "C" fn fs_getxattr(path_ptr: *const u8, name_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_getxattr(p, path_ptr, name_ptr)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_setxattr(
+            path_ptr: *const u8,
+            name_ptr: *const u8,
+            value_ptr: *const u8,
+            size: usize,
+        ) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_setxattr(p, path_ptr, name_ptr, value_ptr, size)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_listxattr(path_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_listxattr(p, path_ptr)
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_removexattr(path_ptr: *const u8, name_ptr: *const u8) -> *mut u8 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_removexattr(p, path_ptr, name_ptr)
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
         #[no_mangle]
         pub extern "C" fn malloc(size: usize) -> *mut u8 {
             use std::alloc::{alloc, Layout};
@@ -273,7 +685,9 @@ macro_rules! export_plugin {
             }
         }
 
+        #[cfg(target_arch = "wasm32")]
         #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
         pub extern "C" fn free(ptr: *mut u8, size: usize) {
             use std::alloc::{dealloc, Layout};
 
@@ -288,3 +702,50 @@ macro_rules! export_plugin {
         }
     };
 }
+
+/// Heap tracking items of `export_plugin!` (feature `heap-stats`)
+///
+/// Installs `TrackingAllocator` as the plugin's global allocator and exports
+/// `plugin_heap_stats`. Picked by the SDK's own features, since a `cfg` in
+/// `export_plugin!` would test the plugin crate's.
+#[cfg(feature = "heap-stats")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_heap_stats {
+    () => {
+        #[cfg(target_arch = "wasm32")]
+        #[global_allocator]
+        static ALLOCATOR: $crate::memory::TrackingAllocator = $crate::memory::TrackingAllocator;
+
+        /// Current and peak heap bytes, packed as (current, peak)
+        #[no_mangle]
+        pub extern "C" fn plugin_heap_stats() -> u64 {
+            let stats = $crate::memory::heap_stats().unwrap_or_default();
+            $crate::memory::pack_u64(stats.current as u32, stats.peak as u32)
+        }
+    };
+}
+
+/// Without `heap-stats` the plugin keeps its own global allocator and the
+/// host finds no `plugin_heap_stats`
+#[cfg(not(feature = "heap-stats"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_heap_stats {
+    () => {};
+}
+
+/// Export a FileSystem implementation as a native plugin
+///
+/// Selected by the `native` feature (see the `native` module). The manifest
+/// form is accepted so plugins need no changes, but nothing is embedded.
+#[cfg(feature = "native")]
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin_type:ty, manifest { $($manifest:tt)* }) => {
+        $crate::export_plugin!($plugin_type);
+    };
+    ($plugin_type:ty) => {
+        $crate::native::agfs_ffi::export_plugin!($crate::native::Native<$plugin_type>);
+    };
+}
@@ -0,0 +1,262 @@
+//! Plugin manifests for provenance checks
+//!
+//! `export_plugin!` can embed a manifest in an `agfs.manifest` custom
+//! section of the module:
+//!
+//! ```ignore
+//! export_plugin!(HelloFS, manifest {
+//!     name: "hellofs",
+//!     version: env!("CARGO_PKG_VERSION"),
+//!     build: env!("GIT_HASH"),
+//! });
+//! ```
+//!
+//! After the build, `sign` appends an `agfs.signature` section holding a
+//! signature over `digest` (SHA-256 of the manifest and the code section),
+//! and a server checks it before loading the module. `sign` and `verify`
+//! take the signing and checking functions, so keys stay with the release
+//! tooling and the server. The agfs server expects an Ed25519 signature:
+//! once `external_plugins.trusted_keys` is set in its config, it refuses
+//! modules that are not signed by one of those keys before running any
+//! of their code.
+
+use crate::checksum::sha256;
+use crate::types::{Error, Result};
+
+/// Custom section holding the manifest
+pub const MANIFEST_SECTION: &str = "agfs.manifest";
+/// Custom section holding the signature, appended by `sign`
+pub const SIGNATURE_SECTION: &str = "agfs.signature";
+
+const WASM_HEADER: &[u8; 8] = b"\0asm\x01\0\0\0";
+const CODE_SECTION_ID: u8 = 10;
+
+/// What a plugin declares about itself
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// Build identifier such as a commit hash, if one was given
+    pub build: Option<String>,
+}
+
+impl Manifest {
+    /// Encode as `key=value` lines, the form `export_plugin!` embeds
+    pub fn encode(&self) -> String {
+        let mut out = format!("name={}\nversion={}\n", self.name, self.version);
+        if let Some(build) = &self.build {
+            out.push_str(&format!("build={}\n", build));
+        }
+        out
+    }
+
+    /// Parse an encoded manifest; unknown keys are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|_| invalid("manifest is not UTF-8"))?;
+        let mut manifest = Self::default();
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("malformed manifest line"))?;
+            match key {
+                "name" => manifest.name = value.to_string(),
+                "version" => manifest.version = value.to_string(),
+                "build" => manifest.build = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        if manifest.name.is_empty() {
+            return Err(invalid("manifest has no name"));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Copy a string into a fixed-size array, for the embedded section
+#[doc(hidden)]
+pub const fn manifest_bytes<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    let mut out = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidInput(msg.to_string())
+}
+
+fn read_leb(data: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut value: u64 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*pos).ok_or_else(|| invalid("truncated module"))?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return usize::try_from(value).map_err(|_| invalid("section too large"));
+        }
+    }
+    Err(invalid("malformed LEB128"))
+}
+
+fn write_leb(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+// Section id, custom section name (empty for others) and contents
+fn sections(wasm: &[u8]) -> Result<Vec<(u8, &str, &[u8])>> {
+    if !wasm.starts_with(WASM_HEADER) {
+        return Err(invalid("not a WASM module"));
+    }
+    let mut out = Vec::new();
+    let mut pos = WASM_HEADER.len();
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let len = read_leb(wasm, &mut pos)?;
+        let body = wasm
+            .get(pos..pos.saturating_add(len))
+            .ok_or_else(|| invalid("truncated section"))?;
+        pos += len;
+        if id == 0 {
+            let mut at = 0;
+            let name_len = read_leb(body, &mut at)?;
+            let name = body
+                .get(at..at.saturating_add(name_len))
+                .and_then(|n| std::str::from_utf8(n).ok())
+                .ok_or_else(|| invalid("bad custom section name"))?;
+            out.push((id, name, &body[at + name_len..]));
+        } else {
+            out.push((id, "", body));
+        }
+    }
+    Ok(out)
+}
+
+fn custom_section<'a>(sections: &[(u8, &str, &'a [u8])], name: &str) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|(id, n, _)| *id == 0 && *n == name)
+        .map(|(_, _, body)| *body)
+}
+
+/// The manifest embedded in a module
+pub fn read(wasm: &[u8]) -> Result<Manifest> {
+    let sections = sections(wasm)?;
+    Manifest::parse(custom_section(&sections, MANIFEST_SECTION).ok_or(Error::NotFound)?)
+}
+
+/// The digest a signature covers: SHA-256 of the manifest section, a
+/// zero byte, then the code section
+pub fn digest(wasm: &[u8]) -> Result<[u8; 32]> {
+    let sections = sections(wasm)?;
+    let manifest = custom_section(&sections, MANIFEST_SECTION).ok_or(Error::NotFound)?;
+    let code = sections
+        .iter()
+        .find(|(id, _, _)| *id == CODE_SECTION_ID)
+        .map_or(&[][..], |(_, _, body)| *body);
+    let mut covered = Vec::with_capacity(manifest.len() + 1 + code.len());
+    covered.extend_from_slice(manifest);
+    covered.push(0);
+    covered.extend_from_slice(code);
+    Ok(sha256(&covered))
+}
+
+/// Append a signature section made by `signer` over `digest(wasm)`
+pub fn sign(wasm: &[u8], signer: impl FnOnce(&[u8; 32]) -> Vec<u8>) -> Result<Vec<u8>> {
+    if custom_section(&sections(wasm)?, SIGNATURE_SECTION).is_some() {
+        return Err(Error::AlreadyExists);
+    }
+    let signature = signer(&digest(wasm)?);
+
+    let mut body = Vec::new();
+    write_leb(&mut body, SIGNATURE_SECTION.len());
+    body.extend_from_slice(SIGNATURE_SECTION.as_bytes());
+    body.extend_from_slice(&signature);
+
+    let mut out = wasm.to_vec();
+    out.push(0);
+    write_leb(&mut out, body.len());
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// Check the signature with `verifier(digest, signature)` and return the
+/// manifest it covers
+pub fn verify(wasm: &[u8], verifier: impl FnOnce(&[u8; 32], &[u8]) -> bool) -> Result<Manifest> {
+    let sections = sections(wasm)?;
+    let signature = custom_section(&sections, SIGNATURE_SECTION).ok_or(Error::PermissionDenied)?;
+    if !verifier(&digest(wasm)?, signature) {
+        return Err(Error::PermissionDenied);
+    }
+    read(wasm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(out: &mut Vec<u8>, id: u8, body: &[u8]) {
+        out.push(id);
+        write_leb(out, body.len());
+        out.extend_from_slice(body);
+    }
+
+    fn module(code: &[u8]) -> Vec<u8> {
+        let manifest = Manifest {
+            name: "hellofs".to_string(),
+            version: "0.1.0".to_string(),
+            build: Some("abc123".to_string()),
+        };
+        let mut custom = Vec::new();
+        write_leb(&mut custom, MANIFEST_SECTION.len());
+        custom.extend_from_slice(MANIFEST_SECTION.as_bytes());
+        custom.extend_from_slice(manifest.encode().as_bytes());
+
+        let mut wasm = WASM_HEADER.to_vec();
+        section(&mut wasm, CODE_SECTION_ID, code);
+        section(&mut wasm, 0, &custom);
+        wasm
+    }
+
+    // Stand-in for a real signature scheme: the digest under a fixed key
+    fn toy_sign(digest: &[u8; 32]) -> Vec<u8> {
+        digest.iter().map(|b| b ^ 0x5a).collect()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signed = sign(&module(b"\x01\x02"), toy_sign).unwrap();
+        let check = |d: &[u8; 32], sig: &[u8]| toy_sign(d) == sig;
+        let manifest = verify(&signed, check).unwrap();
+        assert_eq!(manifest.name, "hellofs");
+        assert_eq!(manifest.build.as_deref(), Some("abc123"));
+        assert!(sign(&signed, toy_sign).is_err());
+
+        // Same signature section, different code
+        let signature = &signed[module(b"\x01\x02").len()..];
+        let forged = [&module(b"\x01\x03")[..], signature].concat();
+        assert_eq!(verify(&forged, check), Err(Error::PermissionDenied));
+        assert_eq!(verify(&module(b""), check), Err(Error::PermissionDenied));
+    }
+
+    #[test]
+    fn test_embedded_form() {
+        const TEXT: &str = concat!("name=", "x", "\nversion=", "1", "\n");
+        let bytes: [u8; TEXT.len()] = manifest_bytes(TEXT);
+        let manifest = Manifest::parse(&bytes).unwrap();
+        assert_eq!((manifest.name.as_str(), manifest.version.as_str()), ("x", "1"));
+        assert_eq!(manifest.encode(), TEXT);
+    }
+}
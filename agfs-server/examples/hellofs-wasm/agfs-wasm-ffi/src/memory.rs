@@ -3,8 +3,94 @@
 //! This module provides safe wrappers around raw pointer operations
 //! needed for WASM<->Go communication.
 
-use std::alloc::{alloc, dealloc, Layout};
+use crate::types::{Config, Error, Result};
+use std::alloc::{alloc, dealloc, GlobalAlloc, Layout, System};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Default cap on a string read from a host pointer
+pub const DEFAULT_MAX_HOST_STRING: usize = 1024 * 1024;
+/// Default cap on a buffer read from a host pointer and length
+pub const DEFAULT_MAX_HOST_BUFFER: usize = 256 * 1024 * 1024;
+
+static MAX_HOST_STRING: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_HOST_STRING);
+static MAX_HOST_BUFFER: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_HOST_BUFFER);
+
+/// Caps on data read through pointers and lengths the host provides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// Longest NUL-terminated string, terminator excluded
+    pub max_string: usize,
+    /// Largest pointer-and-length buffer
+    pub max_buffer: usize,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_string: DEFAULT_MAX_HOST_STRING,
+            max_buffer: DEFAULT_MAX_HOST_BUFFER,
+        }
+    }
+}
+
+impl ReadLimits {
+    /// Read `max_host_string` / `max_host_buffer` from the mount config
+    pub fn from_config(config: &Config) -> Self {
+        let cap = |key: &str, default: usize| {
+            config
+                .get_i64(key)
+                .filter(|&n| n > 0)
+                .map_or(default, |n| n as usize)
+        };
+        Self {
+            max_string: cap("max_host_string", DEFAULT_MAX_HOST_STRING),
+            max_buffer: cap("max_host_buffer", DEFAULT_MAX_HOST_BUFFER),
+        }
+    }
+}
+
+/// Install the caps used by every host pointer read (called on initialize)
+pub fn set_read_limits(limits: ReadLimits) {
+    MAX_HOST_STRING.store(limits.max_string, Ordering::Relaxed);
+    MAX_HOST_BUFFER.store(limits.max_buffer, Ordering::Relaxed);
+}
+
+/// Caps currently in force
+pub fn read_limits() -> ReadLimits {
+    ReadLimits {
+        max_string: MAX_HOST_STRING.load(Ordering::Relaxed),
+        max_buffer: MAX_HOST_BUFFER.load(Ordering::Relaxed),
+    }
+}
+
+// End of the linear memory a pointer may reach; unbounded off wasm32
+// A full 4 GiB memory (65536 pages) ends past usize::MAX, so the product
+// saturates instead of wrapping to 0
+fn memory_end() -> usize {
+    #[cfg(target_arch = "wasm32")]
+    {
+        core::arch::wasm32::memory_size(0)
+            .checked_mul(65536)
+            .unwrap_or(usize::MAX)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        usize::MAX
+    }
+}
+
+/// Check that `len` bytes at `ptr` lie within linear memory
+pub fn check_range(ptr: *const u8, len: usize) -> Result<()> {
+    let start = ptr as usize;
+    match start.checked_add(len) {
+        Some(end) if end <= memory_end() => Ok(()),
+        _ => Err(Error::InvalidInput(format!(
+            "host range {:#x}+{} is outside linear memory",
+            start, len
+        ))),
+    }
+}
 
 /// A string allocated in WASM memory that can be passed to Go
 pub struct CString {
@@ -57,22 +143,44 @@ impl CString {
     }
 
     /// Read a C string from a pointer into a Rust String
-    pub unsafe fn from_ptr(ptr: *const u8) -> String {
+    ///
+    /// # Safety
+    ///
+    /// As for `bytes_from_ptr`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<String> {
+        Ok(String::from_utf8_lossy(Self::bytes_from_ptr(ptr)?).to_string())
+    }
+
+    /// Borrow the bytes of a C string, without the terminator
+    ///
+    /// The scan for the terminator stops at the end of linear memory and at
+    /// the `max_string` cap (see `ReadLimits`); a string that reaches
+    /// either is an error rather than a read past it. A null pointer is
+    /// the empty string.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or point into WASM memory whose bytes up to the
+    /// terminator (or the cap) stay untouched for the lifetime `'a`.
+    pub unsafe fn bytes_from_ptr<'a>(ptr: *const u8) -> Result<&'a [u8]> {
         if ptr.is_null() {
-            return String::new();
+            return Ok(&[]);
         }
 
+        let limit = read_limits()
+            .max_string
+            .min(memory_end().saturating_sub(ptr as usize));
         let mut len = 0;
-        while *ptr.add(len) != 0 {
+        while len < limit && *ptr.add(len) != 0 {
             len += 1;
         }
-
-        if len == 0 {
-            return String::new();
+        if len == limit {
+            return Err(Error::InvalidInput(
+                "host string is unterminated or over the size cap".to_string(),
+            ));
         }
 
-        let slice = std::slice::from_raw_parts(ptr, len);
-        String::from_utf8_lossy(slice).to_string()
+        Ok(std::slice::from_raw_parts(ptr, len))
     }
 }
 
@@ -126,6 +234,27 @@ impl Buffer {
         buf
     }
 
+    /// Take over the allocation of a Vec without copying its contents
+    ///
+    /// Falls back to a copy only if the Vec cannot be shrunk to an exact fit,
+    /// since the host frees buffers using their length as the layout size.
+    pub fn from_vec(mut data: Vec<u8>) -> Self {
+        if data.is_empty() {
+            return Self::new(0);
+        }
+
+        data.shrink_to_fit();
+        if data.capacity() != data.len() {
+            return Self::from_bytes(&data);
+        }
+
+        let mut data = std::mem::ManuallyDrop::new(data);
+        Self {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+        }
+    }
+
     /// Convert to raw pointer (consumes self, caller must free)
     pub fn into_raw(self) -> *mut u8 {
         let ptr = self.ptr;
@@ -165,8 +294,280 @@ impl Drop for Buffer {
     }
 }
 
+/// Bytes holding a secret, such as a credential read with
+/// `HostEnv::get_secret`
+///
+/// The contents are zeroed when the buffer is dropped or cleared, and when
+/// growing it moves them to a new allocation, so the buffer itself leaves
+/// no copy behind in linear memory. Copies made from `expose` are the
+/// caller's to wipe. `Debug` never prints the contents.
+pub struct SecretBuffer {
+    data: Vec<u8>,
+}
+
+impl SecretBuffer {
+    /// Create an empty buffer with room for `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Copy `data` into a new buffer; the caller still owns (and should
+    /// wipe) the source
+    pub fn from_slice(data: &[u8]) -> Self {
+        let mut buf = Self::with_capacity(data.len());
+        buf.data.extend_from_slice(data);
+        buf
+    }
+
+    /// Move the C string at `ptr` into a new buffer, zeroing the source
+    ///
+    /// # Safety
+    ///
+    /// As for `CString::bytes_from_ptr`; the bytes must also be writable
+    /// and not needed by anyone else.
+    pub unsafe fn take_from_ptr(ptr: *mut u8) -> Result<Self> {
+        let len = CString::bytes_from_ptr(ptr)?.len();
+        let buf = Self::from_slice(std::slice::from_raw_parts(ptr, len));
+        zero_raw(ptr, len);
+        Ok(buf)
+    }
+
+    /// The secret bytes
+    pub fn expose(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The secret bytes, mutably
+    pub fn expose_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Append bytes, zeroing the old allocation if it has to grow
+    pub fn extend_from_slice(&mut self, more: &[u8]) {
+        if self.data.capacity() - self.data.len() < more.len() {
+            let needed = self.data.len() + more.len();
+            let mut grown = Vec::with_capacity(needed.max(self.data.capacity() * 2));
+            grown.extend_from_slice(&self.data);
+            zero(&mut self.data);
+            self.data = grown;
+        }
+        self.data.extend_from_slice(more);
+    }
+
+    /// Zero the contents and truncate to empty, keeping the allocation
+    pub fn clear(&mut self) {
+        zero(&mut self.data);
+        self.data.clear();
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBuffer {
+    /// Take ownership of `data` without copying it
+    fn from(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+}
+
+impl std::fmt::Debug for SecretBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBuffer({} bytes)", self.data.len())
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        zero(&mut self.data);
+    }
+}
+
+// Zero the whole allocation, spare capacity included, with volatile writes
+// so the compiler cannot drop them as dead stores
+fn zero(data: &mut Vec<u8>) {
+    unsafe { zero_raw(data.as_mut_ptr(), data.capacity()) };
+}
+
+unsafe fn zero_raw(ptr: *mut u8, len: usize) {
+    for i in 0..len {
+        ptr::write_volatile(ptr.add(i), 0);
+    }
+    std::sync::atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Pack a result payload as (pointer, length) for the host
+///
+/// The Vec's allocation is handed over via `Buffer::from_vec`, so every
+/// result has its own buffer that stays valid until the host frees it.
+pub fn pack_payload(data: Vec<u8>) -> u64 {
+    let len = data.len() as u32;
+    let ptr = Buffer::from_vec(data).into_raw() as u32;
+    pack_u64(ptr, len)
+}
+
+/// Borrow a host-written region of WASM memory as a byte slice
+///
+/// No copy is made: the slice aliases the buffer the host allocated and
+/// filled before the call, and is only valid for the duration of that call.
+/// A zero length yields an empty slice. Lengths over the `max_buffer` cap,
+/// a null pointer with a length, and ranges past the end of linear memory
+/// are errors.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to at least `len` initialized bytes that stay
+/// untouched for the lifetime `'a`.
+pub unsafe fn borrow_slice<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(Error::InvalidInput("null host buffer".to_string()));
+    }
+    if len > read_limits().max_buffer {
+        return Err(Error::TooLarge);
+    }
+    check_range(ptr, len)?;
+    Ok(std::slice::from_raw_parts(ptr, len))
+}
+
 /// Pack two u32 values into a u64
 /// Used for returning multiple values from WASM functions
 pub fn pack_u64(low: u32, high: u32) -> u64 {
     ((high as u64) << 32) | (low as u64)
 }
+
+static HEAP_CURRENT: AtomicUsize = AtomicUsize::new(0);
+static HEAP_PEAK: AtomicUsize = AtomicUsize::new(0);
+static HEAP_TRACKED: AtomicBool = AtomicBool::new(false);
+
+/// Allocator that counts live heap bytes and remembers the peak
+///
+/// With the `heap-stats` feature `export_plugin!` installs it as the global
+/// allocator of WASM plugins, so the host can see how much a mount really
+/// uses through `heap_stats`.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            note_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        note_free(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            note_free(layout.size());
+            note_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn note_alloc(size: usize) {
+    HEAP_TRACKED.store(true, Ordering::Relaxed);
+    let current = HEAP_CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    HEAP_PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn note_free(size: usize) {
+    HEAP_CURRENT.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Heap usage seen by `TrackingAllocator`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Bytes currently allocated
+    pub current: usize,
+    /// Highest `current` since start or the last `reset_heap_peak`
+    pub peak: usize,
+}
+
+/// Current and peak heap usage; None until `TrackingAllocator` has served
+/// an allocation, i.e. when it is not the global allocator
+pub fn heap_stats() -> Option<HeapStats> {
+    if !HEAP_TRACKED.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(HeapStats {
+        current: HEAP_CURRENT.load(Ordering::Relaxed),
+        peak: HEAP_PEAK.load(Ordering::Relaxed),
+    })
+}
+
+/// Restart peak tracking from the current usage
+pub fn reset_heap_peak() {
+    HEAP_PEAK.store(HEAP_CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracking_allocator_peak() {
+        let layout = Layout::from_size_align(4096, 8).unwrap();
+        unsafe {
+            let a = TrackingAllocator.alloc(layout);
+            let b = TrackingAllocator.alloc(layout);
+            assert_eq!(heap_stats().unwrap().current, 8192);
+            TrackingAllocator.dealloc(a, layout);
+            TrackingAllocator.dealloc(b, layout);
+        }
+        assert_eq!(heap_stats(), Some(HeapStats { current: 0, peak: 8192 }));
+        reset_heap_peak();
+        assert_eq!(heap_stats().unwrap().peak, 0);
+    }
+
+    #[test]
+    fn test_bounded_host_reads() {
+        let s = b"abc\0";
+        assert_eq!(unsafe { CString::bytes_from_ptr(s.as_ptr()) }.unwrap(), b"abc");
+        assert_eq!(unsafe { CString::bytes_from_ptr(ptr::null()) }.unwrap(), b"");
+        assert!(unsafe { borrow_slice(ptr::null(), 4) }.is_err());
+        assert_eq!(unsafe { borrow_slice(s.as_ptr(), 2) }.unwrap(), b"ab");
+        assert!(check_range(usize::MAX as *const u8, 2).is_err());
+        let config = Config::from(serde_json::json!({"max_host_string": 8}));
+        assert_eq!(ReadLimits::from_config(&config).max_string, 8);
+        assert_eq!(ReadLimits::from_config(&config).max_buffer, DEFAULT_MAX_HOST_BUFFER);
+    }
+
+    #[test]
+    fn test_secret_buffer() {
+        let mut secret = SecretBuffer::with_capacity(2);
+        secret.extend_from_slice(b"hunter");
+        secret.extend_from_slice(b"2");
+        assert_eq!(secret.expose(), b"hunter2");
+        assert_eq!(format!("{:?}", secret), "SecretBuffer(7 bytes)");
+
+        let ptr = secret.expose().as_ptr();
+        secret.clear();
+        assert!(secret.is_empty());
+        // The allocation is kept, so its old contents can still be checked
+        let wiped = unsafe { std::slice::from_raw_parts(ptr, 7) };
+        assert_eq!(wiped, &[0; 7]);
+
+        let mut reply = b"s3cret\0".to_vec();
+        let secret = unsafe { SecretBuffer::take_from_ptr(reply.as_mut_ptr()) }.unwrap();
+        assert_eq!(secret.expose(), b"s3cret");
+        assert_eq!(reply, [0; 7]);
+    }
+}
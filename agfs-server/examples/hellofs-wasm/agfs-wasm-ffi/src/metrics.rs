@@ -0,0 +1,152 @@
+//! Per-operation timing for the export glue
+//!
+//! With `metrics: true` in the mount config, every exported filesystem
+//! call is counted and timed, and the SDK serves the numbers as a virtual
+//! `/.metrics` file in Prometheus text format:
+//!
+//! ```text
+//! $ cat /mnt/myplugin/.metrics
+//! agfs_plugin_ops_total{op="read"} 42
+//! agfs_plugin_op_duration_seconds_bucket{op="read",le="0.001"} 40
+//! ...
+//! ```
+//!
+//! Latencies are measured with `time::monotonic_nanos`, the host's clock
+//! in WASM guests, unless another is installed with `set_clock`. Metrics
+//! of a plugin's own go to the server with `HostMetrics` instead.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// Path of the virtual metrics file
+pub const METRICS_PATH: &str = "/.metrics";
+
+/// Histogram bucket upper bounds in seconds
+const BUCKETS: [f64; 12] = [
+    0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct OpStats {
+    calls: u64,
+    errors: u64,
+    timed: u64,
+    sum_nanos: u64,
+    buckets: [u64; BUCKETS.len()],
+}
+
+static REGISTRY: Mutex<BTreeMap<&'static str, OpStats>> = Mutex::new(BTreeMap::new());
+static CLOCK: Mutex<Option<fn() -> u64>> = Mutex::new(None);
+
+/// Install a monotonic clock returning nanoseconds
+pub fn set_clock(clock: fn() -> u64) {
+    *CLOCK.lock().unwrap() = Some(clock);
+}
+
+/// Current monotonic time in nanoseconds, if a clock is available
+pub fn now_nanos() -> Option<u64> {
+    match *CLOCK.lock().unwrap() {
+        Some(clock) => Some(clock()),
+        None => default_clock(),
+    }
+}
+
+fn default_clock() -> Option<u64> {
+    Some(crate::time::monotonic_nanos())
+}
+
+/// Record one call of `op` that started at `start` (from `now_nanos`)
+pub fn record(op: &'static str, start: Option<u64>, ok: bool) {
+    let elapsed = start.and_then(|s| now_nanos().map(|now| now.saturating_sub(s)));
+    let mut registry = REGISTRY.lock().unwrap();
+    let stats = registry.entry(op).or_default();
+    stats.calls += 1;
+    if !ok {
+        stats.errors += 1;
+    }
+    if let Some(nanos) = elapsed {
+        stats.timed += 1;
+        stats.sum_nanos += nanos;
+        let secs = nanos as f64 / 1e9;
+        for (bucket, bound) in stats.buckets.iter_mut().zip(BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+}
+
+/// Forget everything recorded so far
+pub fn reset() {
+    REGISTRY.lock().unwrap().clear();
+}
+
+/// Render the recorded metrics in Prometheus text exposition format
+pub fn render() -> String {
+    let registry = REGISTRY.lock().unwrap();
+    let mut out = String::new();
+
+    out.push_str("# HELP agfs_plugin_ops_total Plugin operations handled\n");
+    out.push_str("# TYPE agfs_plugin_ops_total counter\n");
+    for (op, s) in registry.iter() {
+        let _ = writeln!(out, "agfs_plugin_ops_total{{op=\"{}\"}} {}", op, s.calls);
+    }
+
+    out.push_str("# HELP agfs_plugin_op_errors_total Plugin operations that returned an error\n");
+    out.push_str("# TYPE agfs_plugin_op_errors_total counter\n");
+    for (op, s) in registry.iter() {
+        let _ = writeln!(out, "agfs_plugin_op_errors_total{{op=\"{}\"}} {}", op, s.errors);
+    }
+
+    out.push_str("# HELP agfs_plugin_op_duration_seconds Latency of plugin operations\n");
+    out.push_str("# TYPE agfs_plugin_op_duration_seconds histogram\n");
+    for (op, s) in registry.iter().filter(|(_, s)| s.timed > 0) {
+        for (count, bound) in s.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "agfs_plugin_op_duration_seconds_bucket{{op=\"{}\",le=\"{}\"}} {}",
+                op, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "agfs_plugin_op_duration_seconds_bucket{{op=\"{}\",le=\"+Inf\"}} {}",
+            op, s.timed
+        );
+        let _ = writeln!(
+            out,
+            "agfs_plugin_op_duration_seconds_sum{{op=\"{}\"}} {}",
+            op,
+            s.sum_nanos as f64 / 1e9
+        );
+        let _ = writeln!(
+            out,
+            "agfs_plugin_op_duration_seconds_count{{op=\"{}\"}} {}",
+            op, s.timed
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_render() {
+        let start = now_nanos();
+        record("test_op", start, true);
+        record("test_op", start, false);
+        record("test_untimed", None, true);
+
+        let text = render();
+        assert!(text.contains("agfs_plugin_ops_total{op=\"test_op\"} 2"));
+        assert!(text.contains("agfs_plugin_op_errors_total{op=\"test_op\"} 1"));
+        assert!(text.contains("agfs_plugin_op_duration_seconds_count{op=\"test_op\"} 2"));
+        assert!(text.contains("agfs_plugin_op_duration_seconds_bucket{op=\"test_op\",le=\"+Inf\"} 2"));
+        assert!(text.contains("agfs_plugin_ops_total{op=\"test_untimed\"} 1"));
+        assert!(!text.contains("agfs_plugin_op_duration_seconds_count{op=\"test_untimed\"}"));
+    }
+}
@@ -0,0 +1,720 @@
+//! Two-way sync between two filesystems
+//!
+//! `Mirror` keeps two `FileSystem`s in step, e.g. a local cache and a
+//! remote store, so a plugin can serve the local side while offline and
+//! reconcile later:
+//!
+//! ```ignore
+//! let mut mirror = Mirror::new(local, remote).with_policy(ConflictPolicy::RenameConflict);
+//! let report = mirror.plan()?; // dry run
+//! println!("{}", report);
+//! mirror.sync()?;
+//! ```
+//!
+//! Changes are found by comparing each side with what it looked like after
+//! the last sync: size, modification time and the ETag from the entry's
+//! metadata (`etag` or `ETag` in `Meta.Content`), plus an XXH64 of the
+//! contents with `with_content_hashes`. Entries that arrive over the FFI
+//! have no modification time, so without hashes a same-size edit can go
+//! unnoticed. Directories only count as changed when they appear or
+//! disappear.
+//!
+//! A change on one side is copied to the other, deletions included. When
+//! both sides changed a file and their contents differ, the policy decides.
+//! A deletion never wins over a modification, a directory never loses to a
+//! file, and a directory deleted on one side is kept if the other side
+//! changed something inside it.
+//!
+//! The state of the last sync lives in memory; save `state()` (it is
+//! serializable) and `restore` it to survive restarts. A sync that fails
+//! part-way leaves the state untouched, and the next one picks up where it
+//! stopped.
+
+use crate::checksum::xxh64;
+use crate::filesystem::FileSystem;
+use crate::types::{Error, FileInfo, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What one side looked like at a sync
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub is_dir: bool,
+    pub size: i64,
+    pub mod_time: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<u64>,
+}
+
+impl Signature {
+    fn of(info: &FileInfo) -> Self {
+        if info.is_dir {
+            return Self {
+                is_dir: true,
+                size: 0,
+                mod_time: 0,
+                etag: None,
+                hash: None,
+            };
+        }
+        let etag = info.meta.as_ref().and_then(|m| {
+            let content = &m.content;
+            content
+                .get("etag")
+                .or_else(|| content.get("ETag"))?
+                .as_str()
+                .map(str::to_string)
+        });
+        Self {
+            is_dir: false,
+            size: info.size,
+            mod_time: info.mod_time,
+            etag,
+            hash: None,
+        }
+    }
+}
+
+/// Signatures of both sides after the last sync, by path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub entries: BTreeMap<String, (Signature, Signature)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn other(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Side::Left => "left",
+            Side::Right => "right",
+        })
+    }
+}
+
+/// How to settle a file both sides changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// The version with the later modification time; left on a tie
+    #[default]
+    NewestWins,
+    /// The left version, with the right one saved next to it as
+    /// `<name>.conflict` on both sides
+    RenameConflict,
+}
+
+/// One step of a sync, in execution order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Copy the file at `path` from one side to the other
+    Copy {
+        path: String,
+        from: Side,
+    },
+    Mkdir {
+        path: String,
+        on: Side,
+    },
+    Delete {
+        path: String,
+        on: Side,
+    },
+    /// Both sides changed `path`; `winner` is copied over the other side
+    /// next, after the loser is saved as `saved_as` if set
+    Conflict {
+        path: String,
+        winner: Side,
+        saved_as: Option<String>,
+    },
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Copy { path, from } => write!(f, "copy {} {} -> {}", path, from, from.other()),
+            Action::Mkdir { path, on } => write!(f, "mkdir {} on {}", path, on),
+            Action::Delete { path, on } => write!(f, "delete {} on {}", path, on),
+            Action::Conflict {
+                path,
+                winner,
+                saved_as,
+            } => {
+                write!(f, "conflict {}: {} wins", path, winner)?;
+                match saved_as {
+                    Some(to) => write!(f, ", {} saved as {}", winner.other(), to),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// The actions of a sync or a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub actions: Vec<Action>,
+}
+
+impl SyncReport {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn conflicts(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|a| matches!(a, Action::Conflict { .. }))
+            .count()
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in &self.actions {
+            writeln!(f, "{}", action)?;
+        }
+        Ok(())
+    }
+}
+
+type Tree = BTreeMap<String, Signature>;
+
+fn child_path(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// Every entry below the root except symlinks, parents before children
+fn scan<F: FileSystem>(fs: &F, hashes: bool) -> Result<Tree> {
+    let mut tree = Tree::new();
+    let mut dirs = vec!["/".to_string()];
+    while let Some(dir) = dirs.pop() {
+        for info in fs.readdir(&dir)? {
+            if info.is_symlink() {
+                continue;
+            }
+            let path = child_path(&dir, &info.name);
+            let mut sig = Signature::of(&info);
+            if info.is_dir {
+                dirs.push(path.clone());
+            } else if hashes {
+                sig.hash = Some(xxh64(&fs.read(&path, 0, -1)?, 0));
+            }
+            tree.insert(path, sig);
+        }
+    }
+    Ok(tree)
+}
+
+fn conflict_name(path: &str, left: &Tree, right: &Tree) -> String {
+    let taken = |p: &String| left.contains_key(p) || right.contains_key(p);
+    let mut name = format!("{}.conflict", path);
+    let mut n = 1;
+    while taken(&name) {
+        n += 1;
+        name = format!("{}.conflict-{}", path, n);
+    }
+    name
+}
+
+// Make `to` on `dst` a copy of the file `path` on `src`
+fn copy_file<S: FileSystem, D: FileSystem>(
+    src: &S,
+    dst: &mut D,
+    path: &str,
+    to: &str,
+) -> Result<()> {
+    put(dst, to, &src.read(path, 0, -1)?)
+}
+
+fn put<F: FileSystem>(dst: &mut F, to: &str, data: &[u8]) -> Result<()> {
+    match dst.stat(to) {
+        Ok(info) if info.is_dir => {
+            dst.remove_all(to)?;
+            dst.create(to)?;
+        }
+        Ok(_) => {}
+        Err(Error::NotFound) => dst.create(to)?,
+        Err(e) => return Err(e),
+    }
+    dst.write(to, data)?;
+    Ok(())
+}
+
+fn make_dir<S: FileSystem, D: FileSystem>(src: &S, dst: &mut D, path: &str) -> Result<()> {
+    let perm = src.stat(path).map_or(0o755, |i| i.mode & 0o777);
+    match dst.stat(path) {
+        Ok(info) if info.is_dir => return Ok(()),
+        Ok(_) => dst.remove(path)?,
+        Err(Error::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+    dst.mkdir(path, perm)
+}
+
+fn delete<F: FileSystem>(fs: &mut F, path: &str) -> Result<()> {
+    match fs.remove(path) {
+        // Gone with a parent that was replaced
+        Err(Error::NotFound) => Ok(()),
+        result => result,
+    }
+}
+
+/// Keeps two filesystems in sync
+pub struct Mirror<A, B> {
+    left: A,
+    right: B,
+    policy: ConflictPolicy,
+    hashes: bool,
+    state: SyncState,
+}
+
+impl<A, B> Mirror<A, B> {
+    pub fn new(left: A, right: B) -> Self {
+        Self {
+            left,
+            right,
+            policy: ConflictPolicy::default(),
+            hashes: false,
+            state: SyncState::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Also hash file contents to detect changes; reads every file on both
+    /// sides at each sync
+    pub fn with_content_hashes(mut self) -> Self {
+        self.hashes = true;
+        self
+    }
+
+    pub fn left(&self) -> &A {
+        &self.left
+    }
+
+    pub fn left_mut(&mut self) -> &mut A {
+        &mut self.left
+    }
+
+    pub fn right(&self) -> &B {
+        &self.right
+    }
+
+    pub fn right_mut(&mut self) -> &mut B {
+        &mut self.right
+    }
+
+    /// The state of the last successful sync
+    pub fn state(&self) -> &SyncState {
+        &self.state
+    }
+
+    /// Continue from a saved state
+    pub fn restore(&mut self, state: SyncState) {
+        self.state = state;
+    }
+}
+
+impl<A: FileSystem, B: FileSystem> Mirror<A, B> {
+    fn same_contents(&self, path: &str) -> Result<bool> {
+        Ok(self.left.read(path, 0, -1)? == self.right.read(path, 0, -1)?)
+    }
+
+    // Copy or mkdir the winner's version of `path` onto the other side
+    fn propagate(actions: &mut Vec<Action>, path: &str, from: Side, sig: &Signature) {
+        actions.push(if sig.is_dir {
+            Action::Mkdir {
+                path: path.to_string(),
+                on: from.other(),
+            }
+        } else {
+            Action::Copy {
+                path: path.to_string(),
+                from,
+            }
+        });
+    }
+
+    fn decide(&self, left: &Tree, right: &Tree) -> Result<Vec<Action>> {
+        let mut paths: Vec<&String> = left.keys().chain(right.keys()).collect();
+        paths.extend(self.state.entries.keys());
+        paths.sort();
+        paths.dedup();
+
+        let mut actions = Vec::new();
+        for path in paths {
+            let (l, r) = (left.get(path), right.get(path));
+            let base = self.state.entries.get(path);
+            let left_changed = l != base.map(|b| &b.0);
+            let right_changed = r != base.map(|b| &b.1);
+            let changed = match (left_changed, right_changed) {
+                (false, false) => continue,
+                (true, false) => Some((Side::Left, l)),
+                (false, true) => Some((Side::Right, r)),
+                (true, true) => None,
+            };
+            if let Some((side, sig)) = changed {
+                let other = if side == Side::Left { r } else { l };
+                match sig {
+                    Some(sig) if sig.is_dir && other.is_some_and(|o| o.is_dir) => {}
+                    Some(sig) => Self::propagate(&mut actions, path, side, sig),
+                    None if other.is_some() => actions.push(Action::Delete {
+                        path: path.clone(),
+                        on: side.other(),
+                    }),
+                    None => {}
+                }
+                continue;
+            }
+
+            // Both changed
+            let (winner, sig) = match (l, r) {
+                (None, None) => continue,
+                (Some(l), Some(r)) if l.is_dir && r.is_dir => continue,
+                (Some(l), None) => (Side::Left, l),
+                (None, Some(r)) => (Side::Right, r),
+                (Some(l), Some(r)) if l.is_dir != r.is_dir => {
+                    if l.is_dir {
+                        (Side::Left, l)
+                    } else {
+                        (Side::Right, r)
+                    }
+                }
+                (Some(l), Some(r)) => {
+                    if self.same_contents(path)? {
+                        continue;
+                    }
+                    match self.policy {
+                        ConflictPolicy::RenameConflict => (Side::Left, l),
+                        ConflictPolicy::NewestWins if r.mod_time > l.mod_time => (Side::Right, r),
+                        ConflictPolicy::NewestWins => (Side::Left, l),
+                    }
+                }
+            };
+            let loser = if winner == Side::Left { r } else { l };
+            let saved_as = match (self.policy, loser) {
+                (ConflictPolicy::RenameConflict, Some(loser)) if !loser.is_dir => {
+                    Some(conflict_name(path, left, right))
+                }
+                _ => None,
+            };
+            actions.push(Action::Conflict {
+                path: path.clone(),
+                winner,
+                saved_as,
+            });
+            Self::propagate(&mut actions, path, winner, sig);
+        }
+
+        // A directory deleted on one side stays if the other side still
+        // puts something into it
+        for i in 0..actions.len() {
+            let Action::Delete { path, on } = &actions[i] else {
+                continue;
+            };
+            let prefix = format!("{}/", path);
+            let kept = actions[i + 1..].iter().any(|a| match a {
+                Action::Copy { path: p, from } => *from == *on && p.starts_with(&prefix),
+                Action::Mkdir { path: p, on: o } => *o != *on && p.starts_with(&prefix),
+                _ => false,
+            });
+            if kept {
+                actions[i] = Action::Mkdir {
+                    path: path.clone(),
+                    on: on.other(),
+                };
+            }
+        }
+
+        // Create parents first, delete children first
+        let (deletes, mut actions): (Vec<_>, Vec<_>) = actions
+            .into_iter()
+            .partition(|a| matches!(a, Action::Delete { .. }));
+        actions.extend(deletes.into_iter().rev());
+        Ok(actions)
+    }
+
+    /// What `sync` would do, without changing either side
+    pub fn plan(&self) -> Result<SyncReport> {
+        let left = scan(&self.left, self.hashes)?;
+        let right = scan(&self.right, self.hashes)?;
+        Ok(SyncReport {
+            actions: self.decide(&left, &right)?,
+        })
+    }
+
+    fn apply(&mut self, action: &Action) -> Result<()> {
+        match action {
+            Action::Copy {
+                path,
+                from: Side::Left,
+            } => copy_file(&self.left, &mut self.right, path, path),
+            Action::Copy {
+                path,
+                from: Side::Right,
+            } => copy_file(&self.right, &mut self.left, path, path),
+            Action::Mkdir {
+                path,
+                on: Side::Left,
+            } => make_dir(&self.right, &mut self.left, path),
+            Action::Mkdir {
+                path,
+                on: Side::Right,
+            } => make_dir(&self.left, &mut self.right, path),
+            Action::Delete {
+                path,
+                on: Side::Left,
+            } => delete(&mut self.left, path),
+            Action::Delete {
+                path,
+                on: Side::Right,
+            } => delete(&mut self.right, path),
+            Action::Conflict {
+                path,
+                winner,
+                saved_as: Some(to),
+            } => {
+                // Save the losing version on both sides
+                let data = match winner {
+                    Side::Left => self.right.read(path, 0, -1)?,
+                    Side::Right => self.left.read(path, 0, -1)?,
+                };
+                put(&mut self.left, to, &data)?;
+                put(&mut self.right, to, &data)
+            }
+            Action::Conflict { saved_as: None, .. } => Ok(()),
+        }
+    }
+
+    /// Bring both sides in sync and remember the result
+    pub fn sync(&mut self) -> Result<SyncReport> {
+        let report = self.plan()?;
+        for action in &report.actions {
+            self.apply(action)?;
+        }
+        let left = scan(&self.left, self.hashes)?;
+        let mut right = scan(&self.right, self.hashes)?;
+        self.state.entries = left
+            .into_iter()
+            .filter_map(|(path, l)| right.remove(&path).map(|r| (path, (l, r))))
+            .collect();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::slice_range;
+    use std::cell::Cell;
+
+    thread_local! {
+        static CLOCK: Cell<i64> = const { Cell::new(0) };
+    }
+
+    fn tick() -> i64 {
+        CLOCK.with(|c| {
+            c.set(c.get() + 1);
+            c.get()
+        })
+    }
+
+    // Directories are keys ending in '/'
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, (Vec<u8>, i64)>,
+    }
+
+    impl MemFS {
+        fn put(&mut self, path: &str, data: &str) {
+            self.files
+                .insert(path.to_string(), (data.as_bytes().to_vec(), tick()));
+        }
+
+        fn get(&self, path: &str) -> Option<&str> {
+            self.files
+                .get(path)
+                .map(|(d, _)| std::str::from_utf8(d).unwrap())
+        }
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let (data, _) = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(slice_range(data, offset, size).to_vec())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), (data.to_vec(), tick()));
+            Ok(Vec::new())
+        }
+
+        fn create(&mut self, path: &str) -> Result<()> {
+            self.write(path, b"").map(|_| ())
+        }
+
+        fn mkdir(&mut self, path: &str, _perm: u32) -> Result<()> {
+            self.files
+                .insert(format!("{}/", path), (Vec::new(), tick()));
+            Ok(())
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            let dir = format!("{}/", path);
+            if self.files.keys().any(|k| k.starts_with(&dir) && *k != dir) {
+                return Err(Error::NotEmpty);
+            }
+            self.files
+                .remove(path)
+                .or_else(|| self.files.remove(&dir))
+                .ok_or(Error::NotFound)?;
+            Ok(())
+        }
+
+        fn remove_all(&mut self, path: &str) -> Result<()> {
+            let dir = format!("{}/", path);
+            self.files.retain(|k, _| k != path && !k.starts_with(&dir));
+            Ok(())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let name = path.rsplit('/').next().unwrap();
+            if path == "/" || self.files.contains_key(&format!("{}/", path)) {
+                return Ok(FileInfo::dir(name, 0o755));
+            }
+            let (data, time) = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(name, data.len() as i64, 0o644).with_mod_time(*time))
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            let prefix = if path == "/" {
+                "/".to_string()
+            } else {
+                format!("{}/", path)
+            };
+            let names: Vec<String> = self
+                .files
+                .keys()
+                .filter_map(|k| k.strip_prefix(&prefix))
+                .filter(|rest| !rest.is_empty() && !rest.trim_end_matches('/').contains('/'))
+                .map(|rest| rest.trim_end_matches('/').to_string())
+                .collect();
+            names
+                .iter()
+                .map(|n| self.stat(&child_path(path, n)))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_two_way_sync() {
+        let mut left = MemFS::default();
+        left.put("/a", "one");
+        left.mkdir("/d", 0o755).unwrap();
+        left.put("/d/b", "bee");
+        let mut m = Mirror::new(left, MemFS::default());
+
+        let plan = m.plan().unwrap();
+        assert_eq!(plan.actions.len(), 3);
+        assert!(m.right().files.is_empty());
+        m.sync().unwrap();
+        assert_eq!(m.right().get("/d/b"), Some("bee"));
+        assert!(m.plan().unwrap().is_empty());
+
+        // One-sided edits, deletions and additions
+        m.left_mut().put("/a", "two!");
+        m.right_mut().remove("/d/b").unwrap();
+        m.right_mut().put("/c", "sea");
+        let report = m.sync().unwrap();
+        assert_eq!(report.conflicts(), 0);
+        assert_eq!(m.right().get("/a"), Some("two!"));
+        assert_eq!(m.left().get("/d/b"), None);
+        assert_eq!(m.left().get("/c"), Some("sea"));
+
+        // Both edited: the newer one wins
+        m.left_mut().put("/a", "left");
+        m.right_mut().put("/a", "right");
+        let report = m.sync().unwrap();
+        assert_eq!(report.conflicts(), 1);
+        assert_eq!(m.left().get("/a"), Some("right"));
+
+        // A deletion loses to an edit
+        m.left_mut().remove("/c").unwrap();
+        m.right_mut().put("/c", "sea2");
+        m.sync().unwrap();
+        assert_eq!(m.left().get("/c"), Some("sea2"));
+
+        // A directory deleted on one side keeps what the other side added
+        m.left_mut().remove_all("/d").unwrap();
+        m.right_mut().put("/d/new", "n");
+        let report = m.sync().unwrap();
+        assert!(report.to_string().contains("mkdir /d on left"));
+        assert_eq!(m.left().get("/d/new"), Some("n"));
+        assert!(m.plan().unwrap().is_empty());
+
+        m.left_mut().remove_all("/d").unwrap();
+        m.sync().unwrap();
+        assert!(m.right().stat("/d").is_err());
+    }
+
+    #[test]
+    fn test_rename_conflict() {
+        let mut left = MemFS::default();
+        left.put("/f", "same");
+        let mut right = MemFS::default();
+        right.put("/f", "same");
+        let mut m = Mirror::new(left, right).with_policy(ConflictPolicy::RenameConflict);
+        // Identical on first contact is not a conflict
+        assert!(m.sync().unwrap().is_empty());
+
+        m.right_mut().put("/f", "theirs");
+        m.left_mut().put("/f", "mine");
+        let report = m.sync().unwrap();
+        assert_eq!(
+            report.actions[0],
+            Action::Conflict {
+                path: "/f".to_string(),
+                winner: Side::Left,
+                saved_as: Some("/f.conflict".to_string()),
+            }
+        );
+        for side in [m.left(), m.right()] {
+            assert_eq!(side.get("/f"), Some("mine"));
+            assert_eq!(side.get("/f.conflict"), Some("theirs"));
+        }
+
+        // State survives a round trip
+        let saved = serde_json::to_string(m.state()).unwrap();
+        let (left, right) = (std::mem::take(m.left_mut()), std::mem::take(m.right_mut()));
+        let mut m = Mirror::new(left, right);
+        m.restore(serde_json::from_str(&saved).unwrap());
+        assert!(m.plan().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! Native (cdylib) backend
+//!
+//! With the `native` feature, `export_plugin!` exports the plugin through
+//! agfs-ffi's C ABI instead of the WASM one, so one `FileSystem` impl
+//! builds as either kind of plugin:
+//!
+//! ```toml
+//! [lib]
+//! crate-type = ["cdylib"]
+//!
+//! [features]
+//! native = ["agfs-wasm-ffi/native"]
+//! ```
+//!
+//! `cargo build --target wasm32-unknown-unknown` then gives the WASM module
+//! and `cargo build --features native` the shared library. `HostFS` and the
+//! other host imports exist only in WASM builds. The native ABI carries
+//! read results as strings, so there a read returning non-UTF-8 data
+//! fails with an I/O error.
+
+pub use agfs_ffi;
+
+use crate::filesystem::FileSystem;
+use crate::path::{InvalidUtf8, Normalization, PathPolicy};
+use crate::types::{Config, Error, FileInfo, Result};
+use agfs_ffi::FileSystemError;
+use std::sync::Mutex;
+
+/// Adapter presenting a plugin as an `agfs_ffi::FileSystem`
+pub struct Native<F> {
+    fs: Mutex<F>,
+    name: String,
+    readme: String,
+}
+
+impl<F: FileSystem + Default> Default for Native<F> {
+    fn default() -> Self {
+        let fs = F::default();
+        Self {
+            name: fs.name().to_string(),
+            readme: fs.readme().to_string(),
+            fs: Mutex::new(fs),
+        }
+    }
+}
+
+impl<F> Native<F> {
+    fn with<T>(&self, f: impl FnOnce(&mut F) -> Result<T>) -> agfs_ffi::Result<T> {
+        f(&mut self.fs.lock().unwrap()).map_err(native_error)
+    }
+}
+
+fn parse_config(config: &str) -> Result<Config> {
+    serde_json::from_str::<serde_json::Value>(config)
+        .map(Config::from)
+        .map_err(|e| Error::InvalidInput(format!("invalid config: {}", e)))
+}
+
+/// Map an SDK error onto the native error type
+pub fn native_error(e: Error) -> FileSystemError {
+    match e {
+        Error::NotFound => FileSystemError::NotFound,
+        Error::PermissionDenied => FileSystemError::PermissionDenied,
+        Error::AlreadyExists => FileSystemError::AlreadyExists,
+        Error::IsDirectory => FileSystemError::IsADirectory,
+        Error::NotDirectory => FileSystemError::NotADirectory,
+        Error::NotEmpty => FileSystemError::DirectoryNotEmpty,
+        Error::ReadOnly => FileSystemError::ReadOnly,
+        Error::NotSupported(_) => FileSystemError::NotSupported,
+        Error::Io(msg) => FileSystemError::IoError(msg),
+        other => FileSystemError::Custom(other.to_string()),
+    }
+}
+
+/// Convert an SDK entry to the native one
+pub fn native_info(info: FileInfo) -> agfs_ffi::FileInfo {
+    let metadata = match info.meta {
+        Some(meta) => {
+            agfs_ffi::FileMetadata::new(meta.name, meta.type_, meta.content.to_string())
+        }
+        None => agfs_ffi::FileMetadata::default(),
+    };
+    agfs_ffi::FileInfo {
+        name: info.name,
+        size: info.size,
+        mode: info.mode,
+        mod_time: info.mod_time,
+        is_dir: info.is_dir,
+        metadata,
+    }
+}
+
+fn native_policy(policy: PathPolicy) -> agfs_ffi::path::PathPolicy {
+    agfs_ffi::path::PathPolicy {
+        invalid_utf8: match policy.invalid_utf8 {
+            InvalidUtf8::Reject => agfs_ffi::path::InvalidUtf8::Reject,
+            InvalidUtf8::Replace => agfs_ffi::path::InvalidUtf8::Replace,
+        },
+        normalization: match policy.normalization {
+            Normalization::None => agfs_ffi::path::Normalization::None,
+            Normalization::Nfc => agfs_ffi::path::Normalization::Nfc,
+        },
+    }
+}
+
+impl<F: FileSystem + Default + Send> agfs_ffi::FileSystem for Native<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn readme(&self) -> &str {
+        &self.readme
+    }
+
+    fn validate(&self, config: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.validate(&parse_config(config)?))
+    }
+
+    fn initialize(&mut self, config: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.initialize(&parse_config(config)?))
+    }
+
+    fn path_policy(&self) -> agfs_ffi::path::PathPolicy {
+        native_policy(self.fs.lock().unwrap().path_policy())
+    }
+
+    fn shutdown(&mut self) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.shutdown())
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> agfs_ffi::Result<String> {
+        let data = self.with(|fs| fs.read(path, offset, size))?;
+        String::from_utf8(data).map_err(|_| {
+            FileSystemError::IoError("read returned non-UTF-8 data".to_string())
+        })
+    }
+
+    fn stat(&self, path: &str) -> agfs_ffi::Result<agfs_ffi::FileInfo> {
+        self.with(|fs| fs.stat(path)).map(native_info)
+    }
+
+    fn readdir(&self, path: &str) -> agfs_ffi::Result<Vec<agfs_ffi::FileInfo>> {
+        let infos = self.with(|fs| fs.readdir(path))?;
+        Ok(infos.into_iter().map(native_info).collect())
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.write(path, data)).map(|_| ())
+    }
+
+    fn create(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.create(path))
+    }
+
+    fn create_exclusive(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.create_exclusive(path))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.mkdir(path, mode))
+    }
+
+    fn remove(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.remove(path))
+    }
+
+    fn remove_all(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.remove_all(path))
+    }
+
+    fn write_at(&self, path: &str, offset: i64, data: &[u8]) -> agfs_ffi::Result<i64> {
+        self.with(|fs| fs.write_at(path, offset, data))
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.rename(old_path, new_path))
+    }
+
+    fn chmod(&self, path: &str, mode: u32) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.chmod(path, mode))
+    }
+
+    fn set_times(&self, path: &str, atime: i64, mtime: i64) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.set_times(path, atime, mtime))
+    }
+
+    fn truncate(&self, path: &str, size: i64) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.truncate(path, size))
+    }
+
+    fn flush(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.flush(path))
+    }
+
+    fn fsync(&self, path: &str) -> agfs_ffi::Result<()> {
+        self.with(|fs| fs.fsync(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agfs_ffi::FileSystem as _;
+
+    #[derive(Default)]
+    struct Notes {
+        text: Vec<u8>,
+    }
+
+    impl FileSystem for Notes {
+        fn name(&self) -> &str {
+            "notes"
+        }
+
+        fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            Ok(self.text.clone())
+        }
+
+        fn write(&mut self, _path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.text = data.to_vec();
+            Ok(Vec::new())
+        }
+
+        fn truncate(&mut self, _path: &str, size: i64) -> Result<()> {
+            self.text.resize(size as usize, 0);
+            Ok(())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/notes" => Ok(FileInfo::file("notes", self.text.len() as i64, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![self.stat("/notes")?])
+        }
+    }
+
+    #[test]
+    fn test_native_adapter() {
+        let mut fs = Native::<Notes>::default();
+        assert_eq!(fs.name(), "notes");
+        fs.initialize("{}").unwrap();
+        assert!(fs.validate("not json").is_err());
+
+        fs.write("/notes", b"hello").unwrap();
+        assert_eq!(fs.read("/notes", 0, -1).unwrap(), "hello");
+        assert_eq!(fs.stat("/notes").unwrap().size, 5);
+        fs.truncate("/notes", 4).unwrap();
+        assert_eq!(fs.read("/notes", 0, -1).unwrap(), "hell");
+        assert_eq!(fs.readdir("/").unwrap()[0].metadata.content, "{}");
+        assert_eq!(fs.stat("/x").unwrap_err(), FileSystemError::NotFound);
+        assert_eq!(fs.mkdir("/d", 0o755), Err(FileSystemError::ReadOnly));
+
+        assert_eq!(fs.write_at("/notes", 0, b"hi"), Ok(2));
+        assert_eq!(fs.write_at("/notes", 1, b"o"), Err(FileSystemError::ReadOnly));
+
+        fs.write("/notes", b"\xff").unwrap();
+        assert!(matches!(fs.read("/notes", 0, -1), Err(FileSystemError::IoError(_))));
+    }
+}
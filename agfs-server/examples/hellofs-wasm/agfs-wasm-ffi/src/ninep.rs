@@ -0,0 +1,593 @@
+//! 9P2000.L semantics over a `FileSystem`
+//!
+//! Maps the path-based trait onto what a 9P client expects: qids, a fid
+//! table, walk, getattr, lopen, read, readdir and write. No message
+//! encoding happens here. A transport decodes a request, calls the
+//! matching `Session` method and encodes the result, or `lerror(&e)` as an
+//! Rlerror:
+//!
+//! ```ignore
+//! let mut session = Session::new(MyFS::default());
+//! let root = session.attach(0, "")?;
+//! let qids = session.walk(0, 1, &["docs", "readme"])?;
+//! session.lopen(1, L_O_RDONLY)?;
+//! let data = session.read(1, 0, 4096)?;
+//! ```
+//!
+//! Paths follow agfs rules rather than the host's: `..` moves to the
+//! parent but never above the attach root, and names containing `/` are
+//! rejected. agfs has no inode numbers, so a qid's `path` is a hash of the
+//! canonical path and changes when a file is renamed.
+
+use crate::context::Owner;
+use crate::filesystem::FileSystem;
+use crate::path;
+use crate::types::{Error, FileInfo, Result};
+use std::collections::HashMap;
+
+/// Qid type bit for directories
+pub const QTDIR: u8 = 0x80;
+/// Qid type bit for symbolic links
+pub const QTSYMLINK: u8 = 0x02;
+/// Qid type of a plain file
+pub const QTFILE: u8 = 0x00;
+
+/// Most names a single Twalk may carry
+pub const MAXWELEM: usize = 16;
+
+/// Access modes and flags of Tlopen (Linux values)
+pub const L_O_RDONLY: u32 = 0o0;
+pub const L_O_WRONLY: u32 = 0o1;
+pub const L_O_RDWR: u32 = 0o2;
+pub const L_O_TRUNC: u32 = 0o1000;
+const L_O_ACCMODE: u32 = 0o3;
+
+// File type bits of a Linux st_mode
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Block size reported by getattr
+pub const BLOCK_SIZE: u64 = 4096;
+
+/// Server-side identity of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Qid {
+    pub ty: u8,
+    pub version: u32,
+    pub path: u64,
+}
+
+impl Qid {
+    /// The qid of the file at canonical `path` described by `info`
+    ///
+    /// `version` is the low 32 bits of the modification time, so a client
+    /// cache notices any change the plugin reports through `mod_time`.
+    pub fn of(path: &str, info: &FileInfo) -> Qid {
+        let ty = if info.is_dir {
+            QTDIR
+        } else if info.is_symlink() {
+            QTSYMLINK
+        } else {
+            QTFILE
+        };
+        Qid {
+            ty,
+            version: info.mod_time as u32,
+            path: path_hash(path),
+        }
+    }
+}
+
+// FNV-1a, stable across runs and builds
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Attributes returned by Tgetattr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attr {
+    pub qid: Qid,
+    /// Linux st_mode: file type bits plus permissions
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u64,
+    pub size: u64,
+    pub blksize: u64,
+    /// 512-byte blocks
+    pub blocks: u64,
+    /// Seconds; agfs only tracks one time, so atime and ctime repeat it
+    pub atime: i64,
+    pub mtime: i64,
+    pub ctime: i64,
+}
+
+impl Attr {
+    /// Attributes of the file at canonical `path`
+    ///
+    /// Owners recorded as `Uid`/`Gid` metadata are used when present,
+    /// otherwise the file belongs to root.
+    pub fn of(path: &str, info: &FileInfo) -> Attr {
+        let kind = if info.is_dir {
+            S_IFDIR
+        } else if info.is_symlink() {
+            S_IFLNK
+        } else {
+            S_IFREG
+        };
+        let owner = Owner::of(info).unwrap_or_default();
+        let size = info.size.max(0) as u64;
+        Attr {
+            qid: Qid::of(path, info),
+            mode: kind | (info.mode & 0o7777),
+            uid: owner.uid,
+            gid: owner.gid,
+            nlink: match info.nlink {
+                0 if info.is_dir => 2,
+                0 => 1,
+                n => n as u64,
+            },
+            size,
+            blksize: BLOCK_SIZE,
+            blocks: size.div_ceil(512),
+            atime: info.mod_time,
+            mtime: info.mod_time,
+            ctime: info.mod_time,
+        }
+    }
+}
+
+/// One entry of an Rreaddir
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dirent {
+    pub qid: Qid,
+    /// Offset to pass to the next Treaddir to continue after this entry
+    pub offset: u64,
+    /// `DT_*` value for the entry's type
+    pub ty: u8,
+    pub name: String,
+}
+
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+
+/// Linux errno for an Rlerror carrying `e`
+pub fn lerror(e: &Error) -> u32 {
+    e.errno() as u32
+}
+
+#[derive(Debug, Clone)]
+struct Fid {
+    path: String,
+    // Attach root; `..` stops here
+    root: String,
+    qid: Qid,
+    mode: Option<u32>,
+}
+
+fn bad_fid() -> Error {
+    Error::InvalidInput("unknown fid".to_string())
+}
+
+/// A 9P connection's fid table over a filesystem
+pub struct Session<F> {
+    fs: F,
+    fids: HashMap<u32, Fid>,
+}
+
+impl<F: Default> Default for Session<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> Session<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            fids: HashMap::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fs
+    }
+
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.fs
+    }
+
+    /// Whether `fid` is in use
+    pub fn has_fid(&self, fid: u32) -> bool {
+        self.fids.contains_key(&fid)
+    }
+
+    /// Tclunk: forget `fid`
+    pub fn clunk(&mut self, fid: u32) -> Result<()> {
+        self.fids.remove(&fid).map(|_| ()).ok_or_else(bad_fid)
+    }
+
+    fn fid(&self, fid: u32) -> Result<&Fid> {
+        self.fids.get(&fid).ok_or_else(bad_fid)
+    }
+
+    // The fid, which must have been opened with Tlopen
+    fn open_fid(&self, fid: u32) -> Result<&Fid> {
+        let f = self.fid(fid)?;
+        if f.mode.is_none() {
+            return Err(Error::InvalidInput("fid is not open".to_string()));
+        }
+        Ok(f)
+    }
+}
+
+// Parent of a canonical path, never above `root`
+fn parent<'a>(path: &'a str, root: &str) -> &'a str {
+    if path == root {
+        return path;
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn child(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name.contains('/') || name.contains('\0') {
+        return Err(Error::InvalidInput(format!("invalid name {:?}", name)));
+    }
+    Ok(())
+}
+
+impl<F: FileSystem> Session<F> {
+    /// Tattach: bind `fid` to the root of the tree named by `aname`
+    ///
+    /// An empty `aname` attaches the mount root; otherwise it must name a
+    /// directory inside the mount.
+    pub fn attach(&mut self, fid: u32, aname: &str) -> Result<Qid> {
+        if self.fids.contains_key(&fid) {
+            return Err(Error::InvalidInput("fid already in use".to_string()));
+        }
+        let root = path::canonicalize(aname)?;
+        let info = self.fs.stat(&root)?;
+        if !info.is_dir {
+            return Err(Error::NotDirectory);
+        }
+        let qid = Qid::of(&root, &info);
+        self.fids.insert(
+            fid,
+            Fid {
+                path: root.clone(),
+                root,
+                qid,
+                mode: None,
+            },
+        );
+        Ok(qid)
+    }
+
+    /// Twalk: walk `names` from `fid` and bind the result to `newfid`
+    ///
+    /// Returns one qid per name walked. As in 9P, a walk that fails after
+    /// the first name returns the qids up to the failure and leaves
+    /// `newfid` unbound; one that fails on the first name is an error. No
+    /// names clones `fid`. `newfid` may equal `fid`, which moves it.
+    pub fn walk(&mut self, fid: u32, newfid: u32, names: &[&str]) -> Result<Vec<Qid>> {
+        let from = self.fid(fid)?.clone();
+        if from.mode.is_some() {
+            return Err(Error::InvalidInput("cannot walk an open fid".to_string()));
+        }
+        if newfid != fid && self.fids.contains_key(&newfid) {
+            return Err(Error::InvalidInput("fid already in use".to_string()));
+        }
+        if names.len() > MAXWELEM {
+            return Err(Error::InvalidInput("too many names in walk".to_string()));
+        }
+
+        let mut path = from.path.clone();
+        let mut qid = from.qid;
+        let mut qids = Vec::with_capacity(names.len());
+        for name in names {
+            let step = self.step(&path, qid, &from.root, name);
+            match step {
+                Ok((next, next_qid)) => {
+                    path = next;
+                    qid = next_qid;
+                    qids.push(qid);
+                }
+                Err(e) if qids.is_empty() => return Err(e),
+                Err(_) => return Ok(qids),
+            }
+        }
+        self.fids.insert(
+            newfid,
+            Fid {
+                path,
+                root: from.root,
+                qid,
+                mode: None,
+            },
+        );
+        Ok(qids)
+    }
+
+    // Walk one name from the directory at `path`
+    fn step(&self, path: &str, qid: Qid, root: &str, name: &str) -> Result<(String, Qid)> {
+        if qid.ty & QTDIR == 0 {
+            return Err(Error::NotDirectory);
+        }
+        let next = if name == ".." {
+            parent(path, root).to_string()
+        } else {
+            check_name(name)?;
+            child(path, name)
+        };
+        let info = self.fs.stat(&next)?;
+        let qid = Qid::of(&next, &info);
+        Ok((next, qid))
+    }
+
+    /// Tgetattr
+    pub fn getattr(&self, fid: u32) -> Result<Attr> {
+        let path = &self.fid(fid)?.path;
+        Ok(Attr::of(path, &self.fs.stat(path)?))
+    }
+
+    /// Tlopen: open `fid` for I/O with Linux open `flags`
+    ///
+    /// Directories may only be opened read-only. `L_O_TRUNC` on a file
+    /// empties it.
+    pub fn lopen(&mut self, fid: u32, flags: u32) -> Result<Qid> {
+        let f = self.fid(fid)?;
+        if f.mode.is_some() {
+            return Err(Error::InvalidInput("fid is already open".to_string()));
+        }
+        let path = f.path.clone();
+        let info = self.fs.stat(&path)?;
+        let writing = flags & L_O_ACCMODE != L_O_RDONLY;
+        if info.is_dir && (writing || flags & L_O_TRUNC != 0) {
+            return Err(Error::IsDirectory);
+        }
+        if writing && flags & L_O_TRUNC != 0 {
+            self.fs.write(&path, &[])?;
+        }
+        let qid = Qid::of(&path, &info);
+        let f = self.fids.get_mut(&fid).ok_or_else(bad_fid)?;
+        f.qid = qid;
+        f.mode = Some(flags);
+        Ok(qid)
+    }
+
+    /// Tread: up to `count` bytes at `offset`
+    pub fn read(&self, fid: u32, offset: u64, count: u32) -> Result<Vec<u8>> {
+        let f = self.open_fid(fid)?;
+        if f.qid.ty & QTDIR != 0 {
+            return Err(Error::IsDirectory);
+        }
+        if f.mode.unwrap_or(0) & L_O_ACCMODE == L_O_WRONLY {
+            return Err(Error::PermissionDenied);
+        }
+        let offset = i64::try_from(offset).map_err(|_| Error::InvalidInput("offset".into()))?;
+        self.fs.read(&f.path, offset, count as i64)
+    }
+
+    /// Treaddir: up to `count` entries after `offset`
+    ///
+    /// Offsets are positions in the plugin's listing, so they stay valid
+    /// as long as the listing does not change between calls. `.` and `..`
+    /// are not listed; clients walk them instead.
+    pub fn readdir(&self, fid: u32, offset: u64, count: usize) -> Result<Vec<Dirent>> {
+        let f = self.open_fid(fid)?;
+        if f.qid.ty & QTDIR == 0 {
+            return Err(Error::NotDirectory);
+        }
+        let entries = self.fs.readdir(&f.path)?;
+        Ok(entries
+            .iter()
+            .enumerate()
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(count)
+            .map(|(i, info)| {
+                let qid = Qid::of(&child(&f.path, &info.name), info);
+                Dirent {
+                    qid,
+                    offset: i as u64 + 1,
+                    ty: match qid.ty {
+                        QTDIR => DT_DIR,
+                        QTSYMLINK => DT_LNK,
+                        _ => DT_REG,
+                    },
+                    name: info.name.clone(),
+                }
+            })
+            .collect())
+    }
+
+    /// Twrite: write `data` at `offset`, returning the count written
+    ///
+    /// The trait only replaces whole files, so a write anywhere but a
+    /// fresh file's start reads the file, splices `data` in (zero-filling
+    /// any gap) and writes it back.
+    pub fn write(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<u32> {
+        let f = self.open_fid(fid)?;
+        if f.mode.unwrap_or(0) & L_O_ACCMODE == L_O_RDONLY {
+            return Err(Error::PermissionDenied);
+        }
+        let path = f.path.clone();
+        let offset = usize::try_from(offset).map_err(|_| Error::TooLarge)?;
+        let mut content = self.fs.read(&path, 0, -1)?;
+        let end = offset.checked_add(data.len()).ok_or(Error::TooLarge)?;
+        if content.len() < end {
+            content.resize(end, 0);
+        }
+        content[offset..end].copy_from_slice(data);
+        self.fs.write(&path, &content)?;
+        Ok(data.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::slice_range;
+    use std::collections::BTreeMap;
+
+    // Directories are keys ending in '/'
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl MemFS {
+        fn with(paths: &[&str]) -> Self {
+            let mut fs = MemFS::default();
+            fs.files.insert("/".to_string(), Vec::new());
+            for p in paths {
+                fs.files.insert(p.to_string(), p.as_bytes().to_vec());
+            }
+            fs
+        }
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(slice_range(data, offset, size).to_vec())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let name = path.rsplit('/').next().unwrap_or("");
+            if path == "/" || self.files.contains_key(&format!("{}/", path)) {
+                return Ok(FileInfo::dir(name, 0o755));
+            }
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(name, data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            let prefix = if path == "/" {
+                "/".to_string()
+            } else {
+                format!("{}/", path)
+            };
+            Ok(self
+                .files
+                .keys()
+                .filter_map(|k| k.strip_prefix(&prefix))
+                .filter(|rest| !rest.is_empty())
+                .filter(|rest| !rest.trim_end_matches('/').contains('/'))
+                .map(|rest| self.stat(&child(path, rest.trim_end_matches('/'))).unwrap())
+                .collect())
+        }
+    }
+
+    fn session() -> Session<MemFS> {
+        let mut s = Session::new(MemFS::with(&["/a/", "/a/b", "/c"]));
+        s.attach(0, "").unwrap();
+        s
+    }
+
+    #[test]
+    fn test_walk_dotdot_stops_at_root() {
+        let mut s = session();
+        let root = s.getattr(0).unwrap().qid;
+        assert_eq!(root.ty, QTDIR);
+
+        assert_eq!(s.walk(0, 1, &[".."]).unwrap(), [root]);
+        let qids = s.walk(0, 2, &["a", "..", "..", "a", "b"]).unwrap();
+        assert_eq!(qids[1], root);
+        assert_eq!(qids[2], root);
+        assert_eq!(qids[4], s.walk(0, 3, &["a", "b"]).unwrap()[1]);
+
+        // An attach below the root cannot climb out of it either
+        let a = s.attach(10, "/a").unwrap();
+        assert_eq!(s.walk(10, 11, &[".."]).unwrap(), [a]);
+    }
+
+    #[test]
+    fn test_walk_partial_and_errors() {
+        let mut s = session();
+        assert_eq!(s.walk(0, 1, &["missing"]), Err(Error::NotFound));
+        assert!(!s.has_fid(1));
+
+        // Fails on the second name: the first qid comes back, no newfid
+        let qids = s.walk(0, 1, &["a", "missing"]).unwrap();
+        assert_eq!(qids.len(), 1);
+        assert!(!s.has_fid(1));
+        // Walking through a file
+        assert_eq!(s.walk(0, 1, &["c", "x"]).unwrap().len(), 1);
+        assert!(!s.has_fid(1));
+
+        assert!(s.walk(0, 1, &["a/b"]).is_err());
+        assert!(s.walk(0, 1, &["."]).is_err());
+        assert!(s.walk(0, 1, &["a"; MAXWELEM + 1]).is_err());
+        assert!(s.walk(0, 0, &[]).unwrap().is_empty());
+        assert!(s.walk(0, 1, &[]).unwrap().is_empty());
+        assert!(s.walk(0, 1, &[]).is_err());
+
+        // newfid == fid moves the fid
+        s.walk(1, 1, &["a"]).unwrap();
+        assert_eq!(s.getattr(1).unwrap().mode, S_IFDIR | 0o755);
+        s.clunk(1).unwrap();
+        assert!(s.clunk(1).is_err());
+    }
+
+    #[test]
+    fn test_qids_and_attrs() {
+        let mut s = session();
+        let b = s.walk(0, 1, &["a", "b"]).unwrap()[1];
+        let c = s.walk(0, 2, &["c"]).unwrap()[0];
+        assert_eq!(b.ty, QTFILE);
+        assert_ne!(b.path, c.path);
+        assert_eq!(s.walk(0, 3, &["a", "b"]).unwrap()[1], b);
+
+        let attr = s.getattr(1).unwrap();
+        assert_eq!(attr.mode, S_IFREG | 0o644);
+        assert_eq!((attr.size, attr.blocks, attr.nlink), (4, 1, 1));
+        assert_eq!(lerror(&Error::NotFound), 2);
+    }
+
+    #[test]
+    fn test_open_read_write_readdir() {
+        let mut s = session();
+        s.walk(0, 1, &["c"]).unwrap();
+        assert!(s.read(1, 0, 10).is_err());
+        s.lopen(1, L_O_RDWR).unwrap();
+        assert!(s.walk(1, 2, &[]).is_err());
+        assert_eq!(s.read(1, 1, 10).unwrap(), b"c");
+        assert_eq!(s.write(1, 4, b"xy").unwrap(), 2);
+        assert_eq!(s.read(1, 0, 10).unwrap(), b"/c\0\0xy");
+
+        s.walk(0, 3, &[]).unwrap();
+        assert_eq!(s.lopen(3, L_O_WRONLY), Err(Error::IsDirectory));
+        s.lopen(3, L_O_RDONLY).unwrap();
+        let all = s.readdir(3, 0, 100).unwrap();
+        let names: Vec<_> = all.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["a", "c"]);
+        assert_eq!(all[0].ty, DT_DIR);
+        let rest = s.readdir(3, all[0].offset, 100).unwrap();
+        assert_eq!(rest, all[1..]);
+        assert!(s.readdir(3, all[1].offset, 100).unwrap().is_empty());
+    }
+}
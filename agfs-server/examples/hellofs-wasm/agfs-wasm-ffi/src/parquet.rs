@@ -0,0 +1,1697 @@
+//! Parquet files as browsable directories
+//!
+//! `ParquetFS` wraps another filesystem read-only and shows every
+//! `*.parquet` file as a directory:
+//!
+//! ```text
+//! /sales/2024.parquet/schema.json         columns, row counts, row groups
+//! /sales/2024.parquet/data.csv            all rows
+//! /sales/2024.parquet/data.jsonl          all rows, one JSON object per line
+//! /sales/2024.parquet/row_groups/0.csv    one row group
+//! /sales/2024.parquet/columns/price.csv   one column
+//! /sales/2024.parquet/columns/id,price.jsonl
+//! ```
+//!
+//! `columns/` lists one file per column, but any comma-separated list of
+//! column names can be opened. Everything else in the wrapped filesystem
+//! is passed through unchanged.
+//!
+//! Only the footer and the column chunks a file needs are fetched, each
+//! with one ranged `read`, so projecting a column out of a large file does
+//! not read the rest of it. Projections are rendered when first opened or
+//! stat'ed (the size is only known then) and the last few are kept.
+//!
+//! Supported: flat and nested-struct schemas without repeated fields,
+//! PLAIN and dictionary encodings, data pages v1 and v2, and the
+//! UNCOMPRESSED, SNAPPY and LZ4_RAW codecs. Other codecs and encodings fail
+//! with `Error::Other` naming what is missing. `BYTE_ARRAY` values are
+//! rendered as text when they are valid UTF-8 and as hex otherwise.
+
+use crate::filesystem::FileSystem;
+use crate::range::slice_range;
+use crate::types::{Error, FileInfo, Result};
+use serde_json::{json, Map, Value as Json};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Magic at both ends of a Parquet file
+pub const MAGIC: &[u8; 4] = b"PAR1";
+/// Rendered projections kept for repeated stat/read calls
+pub const PROJECTION_CACHE_ENTRIES: usize = 4;
+
+const PARQUET_SUFFIX: &str = ".parquet";
+
+fn corrupt(what: &str) -> Error {
+    Error::InvalidInput(format!("corrupt parquet file: {}", what))
+}
+
+// Thrift compact protocol, decoded into a generic tree
+
+#[derive(Debug, Clone)]
+enum Thrift {
+    Bool(bool),
+    Int(i64),
+    Double,
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(Vec<(i16, Thrift)>),
+}
+
+impl Thrift {
+    fn field(&self, id: i16) -> Option<&Thrift> {
+        match self {
+            Thrift::Struct(fields) => fields.iter().find(|(f, _)| *f == id).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Thrift::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn bool(&self, id: i16) -> Option<bool> {
+        match self.field(id)? {
+            Thrift::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn string(&self, id: i16) -> Option<String> {
+        match self.field(id)? {
+            Thrift::Binary(v) => Some(String::from_utf8_lossy(v).into_owned()),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift] {
+        match self.field(id) {
+            Some(Thrift::List(items)) => items,
+            _ => &[],
+        }
+    }
+}
+
+struct CompactReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+// Deep enough for any real footer, shallow enough to bound recursion
+const MAX_THRIFT_DEPTH: usize = 32;
+
+impl<'a> CompactReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| corrupt("truncated metadata"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.data.len());
+        let end = end.ok_or_else(|| corrupt("truncated metadata"))?;
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(corrupt("varint too long"))
+    }
+
+    fn zigzag(&mut self) -> Result<i64> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn value(&mut self, ty: u8, depth: usize) -> Result<Thrift> {
+        if depth > MAX_THRIFT_DEPTH {
+            return Err(corrupt("metadata nested too deeply"));
+        }
+        Ok(match ty {
+            1 => Thrift::Bool(true),
+            2 => Thrift::Bool(false),
+            3 => Thrift::Int(self.byte()? as i8 as i64),
+            4..=6 => Thrift::Int(self.zigzag()?),
+            7 => {
+                self.bytes(8)?;
+                Thrift::Double
+            }
+            8 => {
+                let len = self.varint()? as usize;
+                Thrift::Binary(self.bytes(len)?.to_vec())
+            }
+            9 | 10 => {
+                let header = self.byte()?;
+                let len = match header >> 4 {
+                    15 => self.varint()? as usize,
+                    n => n as usize,
+                };
+                let elem = header & 0x0f;
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(match elem {
+                        // Booleans in collections take a byte each
+                        1 | 2 => Thrift::Bool(self.byte()? == 1),
+                        _ => self.value(elem, depth + 1)?,
+                    });
+                }
+                Thrift::List(items)
+            }
+            11 => {
+                let len = self.varint()? as usize;
+                let types = if len > 0 { self.byte()? } else { 0 };
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(self.value(types >> 4, depth + 1)?);
+                    items.push(self.value(types & 0x0f, depth + 1)?);
+                }
+                Thrift::List(items)
+            }
+            12 => {
+                let mut fields = Vec::new();
+                let mut last = 0i16;
+                loop {
+                    let header = self.byte()?;
+                    if header == 0 {
+                        break;
+                    }
+                    let id = match header >> 4 {
+                        0 => self.zigzag()? as i16,
+                        delta => last.wrapping_add(delta as i16),
+                    };
+                    last = id;
+                    fields.push((id, self.value(header & 0x0f, depth + 1)?));
+                }
+                Thrift::Struct(fields)
+            }
+            other => return Err(corrupt(&format!("unknown thrift type {}", other))),
+        })
+    }
+
+    fn structure(&mut self) -> Result<Thrift> {
+        self.value(12, 0)
+    }
+}
+
+// Metadata
+
+/// Parquet physical types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhysicalType {
+    Boolean,
+    Int32,
+    Int64,
+    Int96,
+    Float,
+    Double,
+    ByteArray,
+    FixedLenByteArray,
+}
+
+impl PhysicalType {
+    fn from_thrift(v: i64) -> Result<Self> {
+        Ok(match v {
+            0 => Self::Boolean,
+            1 => Self::Int32,
+            2 => Self::Int64,
+            3 => Self::Int96,
+            4 => Self::Float,
+            5 => Self::Double,
+            6 => Self::ByteArray,
+            7 => Self::FixedLenByteArray,
+            other => return Err(corrupt(&format!("unknown physical type {}", other))),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Boolean => "BOOLEAN",
+            Self::Int32 => "INT32",
+            Self::Int64 => "INT64",
+            Self::Int96 => "INT96",
+            Self::Float => "FLOAT",
+            Self::Double => "DOUBLE",
+            Self::ByteArray => "BYTE_ARRAY",
+            Self::FixedLenByteArray => "FIXED_LEN_BYTE_ARRAY",
+        }
+    }
+}
+
+/// A leaf column of the schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    /// Dotted path for columns inside groups
+    pub name: String,
+    pub physical_type: PhysicalType,
+    /// Logical or converted type annotation, e.g. `STRING`, `DATE`
+    pub logical_type: Option<String>,
+    pub type_length: i32,
+    pub max_def_level: u32,
+    pub max_rep_level: u32,
+}
+
+impl Column {
+    pub fn nullable(&self) -> bool {
+        self.max_def_level > 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    codec: i64,
+    num_values: i64,
+    // Byte range holding the dictionary and data pages
+    start: i64,
+    len: i64,
+}
+
+/// A row group: a horizontal slice of the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowGroup {
+    pub num_rows: i64,
+    chunks: Vec<Chunk>,
+}
+
+/// The parsed footer of a Parquet file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub num_rows: i64,
+    pub columns: Vec<Column>,
+    pub row_groups: Vec<RowGroup>,
+    pub created_by: Option<String>,
+}
+
+const CONVERTED_TYPES: &[&str] = &[
+    "UTF8",
+    "MAP",
+    "MAP_KEY_VALUE",
+    "LIST",
+    "ENUM",
+    "DECIMAL",
+    "DATE",
+    "TIME_MILLIS",
+    "TIME_MICROS",
+    "TIMESTAMP_MILLIS",
+    "TIMESTAMP_MICROS",
+    "UINT_8",
+    "UINT_16",
+    "UINT_32",
+    "UINT_64",
+    "INT_8",
+    "INT_16",
+    "INT_32",
+    "INT_64",
+    "JSON",
+    "BSON",
+    "INTERVAL",
+];
+
+// LogicalType is a union; the set field id names the type
+const LOGICAL_TYPES: &[(i16, &str)] = &[
+    (1, "STRING"),
+    (2, "MAP"),
+    (3, "LIST"),
+    (4, "ENUM"),
+    (5, "DECIMAL"),
+    (6, "DATE"),
+    (7, "TIME"),
+    (8, "TIMESTAMP"),
+    (10, "INTEGER"),
+    (11, "NULL"),
+    (12, "JSON"),
+    (13, "BSON"),
+    (14, "UUID"),
+    (15, "FLOAT16"),
+];
+
+fn logical_type(element: &Thrift) -> Option<String> {
+    if let Some(Thrift::Struct(fields)) = element.field(10) {
+        let id = fields.first()?.0;
+        if let Some((_, name)) = LOGICAL_TYPES.iter().find(|(i, _)| *i == id) {
+            return Some(name.to_string());
+        }
+    }
+    let converted = element.int(6)?;
+    CONVERTED_TYPES
+        .get(converted as usize)
+        .map(|s| s.to_string())
+}
+
+// Collect the leaves below schema[*next], which is a child of `prefix`
+fn walk_schema(
+    schema: &[Thrift],
+    next: &mut usize,
+    prefix: &str,
+    def: u32,
+    rep: u32,
+    out: &mut Vec<Column>,
+) -> Result<()> {
+    let element = schema
+        .get(*next)
+        .ok_or_else(|| corrupt("schema too short"))?;
+    *next += 1;
+    let name = element.string(4).unwrap_or_default();
+    let path = if prefix.is_empty() {
+        name
+    } else {
+        format!("{}.{}", prefix, name)
+    };
+    let (def, rep) = match element.int(3) {
+        Some(1) => (def + 1, rep),
+        Some(2) => (def + 1, rep + 1),
+        _ => (def, rep),
+    };
+    match element.int(5) {
+        Some(children) if children > 0 => {
+            for _ in 0..children {
+                walk_schema(schema, next, &path, def, rep, out)?;
+            }
+        }
+        _ => out.push(Column {
+            name: path,
+            physical_type: PhysicalType::from_thrift(element.int(1).unwrap_or(-1))?,
+            logical_type: logical_type(element),
+            type_length: element.int(2).unwrap_or(0) as i32,
+            max_def_level: def,
+            max_rep_level: rep,
+        }),
+    }
+    Ok(())
+}
+
+impl Metadata {
+    /// Decode a thrift-encoded `FileMetaData`
+    pub fn parse(footer: &[u8]) -> Result<Self> {
+        let meta = CompactReader::new(footer).structure()?;
+        let schema = meta.list(2);
+        let root = schema.first().ok_or_else(|| corrupt("empty schema"))?;
+        let mut columns = Vec::new();
+        let mut next = 1;
+        for _ in 0..root.int(5).unwrap_or(0) {
+            walk_schema(schema, &mut next, "", 0, 0, &mut columns)?;
+        }
+
+        let mut row_groups = Vec::new();
+        for rg in meta.list(4) {
+            let mut chunks = Vec::new();
+            for chunk in rg.list(1) {
+                if chunk.string(1).is_some() {
+                    return Err(Error::Other(
+                        "parquet column chunks in external files are not supported".to_string(),
+                    ));
+                }
+                let cmd = chunk
+                    .field(3)
+                    .ok_or_else(|| corrupt("column chunk without metadata"))?;
+                let data_page = cmd.int(9).unwrap_or(0);
+                let start = match cmd.int(11) {
+                    Some(dict) if dict > 0 && dict < data_page => dict,
+                    _ => data_page,
+                };
+                chunks.push(Chunk {
+                    codec: cmd.int(4).unwrap_or(0),
+                    num_values: cmd.int(5).unwrap_or(0),
+                    start,
+                    len: cmd.int(7).unwrap_or(0),
+                });
+            }
+            if chunks.len() != columns.len() {
+                return Err(corrupt("row group does not match schema"));
+            }
+            row_groups.push(RowGroup {
+                num_rows: rg.int(3).unwrap_or(0),
+                chunks,
+            });
+        }
+
+        Ok(Self {
+            num_rows: meta.int(3).unwrap_or(0),
+            columns,
+            row_groups,
+            created_by: meta.string(6),
+        })
+    }
+
+    /// Read the footer of the Parquet file at `path`
+    ///
+    /// Fetches the 8-byte trailer and then the footer itself.
+    pub fn read<F: FileSystem + ?Sized>(fs: &F, path: &str) -> Result<Self> {
+        let size = fs.stat(path)?.size;
+        if size < 12 {
+            return Err(corrupt("file too small"));
+        }
+        let trailer = fs.read(path, size - 8, 8)?;
+        if trailer.len() != 8 || &trailer[4..] != MAGIC {
+            return Err(corrupt("missing PAR1 trailer"));
+        }
+        let len = u32::from_le_bytes(trailer[..4].try_into().unwrap()) as i64;
+        if len > size - 12 {
+            return Err(corrupt("footer length out of range"));
+        }
+        let footer = fs.read(path, size - 8 - len, len)?;
+        if footer.len() as i64 != len {
+            return Err(corrupt("short footer read"));
+        }
+        Self::parse(&footer)
+    }
+
+    /// Index of the column called `name`
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+
+    /// Description served as `schema.json`
+    pub fn schema_json(&self) -> Json {
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .map(|c| {
+                json!({
+                    "name": c.name,
+                    "type": c.physical_type.name(),
+                    "logical_type": c.logical_type,
+                    "nullable": c.nullable(),
+                    "repeated": c.max_rep_level > 0,
+                })
+            })
+            .collect();
+        let row_groups: Vec<_> = self
+            .row_groups
+            .iter()
+            .map(|rg| json!({"num_rows": rg.num_rows}))
+            .collect();
+        json!({
+            "num_rows": self.num_rows,
+            "created_by": self.created_by,
+            "columns": columns,
+            "row_groups": row_groups,
+        })
+    }
+}
+
+// Codecs
+
+const CODEC_NAMES: &[&str] = &[
+    "UNCOMPRESSED",
+    "SNAPPY",
+    "GZIP",
+    "LZO",
+    "BROTLI",
+    "LZ4",
+    "ZSTD",
+    "LZ4_RAW",
+];
+
+fn decompress(codec: i64, data: &[u8], len: usize) -> Result<Vec<u8>> {
+    match codec {
+        0 => Ok(data.to_vec()),
+        1 => snappy_decompress(data),
+        7 => lz4_decompress(data, len),
+        other => Err(Error::Other(format!(
+            "parquet codec {} is not supported",
+            CODEC_NAMES.get(other as usize).unwrap_or(&"unknown")
+        ))),
+    }
+}
+
+/// Decompress a raw (unframed) Snappy block
+pub fn snappy_decompress(input: &[u8]) -> Result<Vec<u8>> {
+    let bad = || Error::InvalidInput("corrupt snappy block".to_string());
+    let mut r = CompactReader::new(input);
+    let len = r.varint().map_err(|_| bad())? as usize;
+    let mut out: Vec<u8> = Vec::with_capacity(len.min(input.len().saturating_mul(32)));
+    while r.pos < input.len() {
+        let tag = r.byte()?;
+        let (copy_len, offset) = match tag & 3 {
+            0 => {
+                let mut n = (tag >> 2) as usize;
+                if n >= 60 {
+                    let extra = r.bytes(n - 59).map_err(|_| bad())?;
+                    n = extra
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, &b| (acc << 8) | b as usize);
+                }
+                out.extend_from_slice(r.bytes(n + 1).map_err(|_| bad())?);
+                continue;
+            }
+            1 => {
+                let low = r.byte().map_err(|_| bad())? as usize;
+                (
+                    ((tag >> 2) & 7) as usize + 4,
+                    ((tag as usize >> 5) << 8) | low,
+                )
+            }
+            2 => {
+                let b = r.bytes(2).map_err(|_| bad())?;
+                (
+                    (tag >> 2) as usize + 1,
+                    u16::from_le_bytes([b[0], b[1]]) as usize,
+                )
+            }
+            _ => {
+                let b = r.bytes(4).map_err(|_| bad())?;
+                (
+                    (tag >> 2) as usize + 1,
+                    u32::from_le_bytes(b.try_into().unwrap()) as usize,
+                )
+            }
+        };
+        if offset == 0 || offset > out.len() || out.len() + copy_len > len {
+            return Err(bad());
+        }
+        // Copies may overlap their own output
+        let start = out.len() - offset;
+        for i in 0..copy_len {
+            out.push(out[start + i]);
+        }
+    }
+    if out.len() != len {
+        return Err(bad());
+    }
+    Ok(out)
+}
+
+const LZ4_MIN_MATCH: usize = 4;
+
+/// Decompress a raw LZ4 block of known decompressed size
+pub fn lz4_decompress(input: &[u8], original_len: usize) -> Result<Vec<u8>> {
+    let corrupt = || Error::InvalidInput("corrupt lz4 block".to_string());
+    // A block expands at most 255-fold, so a bogus page header cannot make
+    // this reserve more than the input could produce
+    let mut out: Vec<u8> = Vec::with_capacity(original_len.min(input.len().saturating_mul(255)));
+    let mut i = 0;
+
+    let read_length = |i: &mut usize, mut len: usize| -> Result<usize> {
+        loop {
+            let b = *input.get(*i).ok_or_else(corrupt)?;
+            *i += 1;
+            len += b as usize;
+            if b != 255 {
+                return Ok(len);
+            }
+        }
+    };
+
+    while i < input.len() {
+        let token = input[i];
+        i += 1;
+
+        let mut lit_len = (token >> 4) as usize;
+        if lit_len == 15 {
+            lit_len = read_length(&mut i, lit_len)?;
+        }
+        let literals = input.get(i..i + lit_len).ok_or_else(corrupt)?;
+        out.extend_from_slice(literals);
+        i += lit_len;
+
+        if i == input.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([
+            *input.get(i).ok_or_else(corrupt)?,
+            *input.get(i + 1).ok_or_else(corrupt)?,
+        ]) as usize;
+        i += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(corrupt());
+        }
+
+        let mut match_len = (token & 0x0f) as usize;
+        if match_len == 15 {
+            match_len = read_length(&mut i, match_len)?;
+        }
+        match_len += LZ4_MIN_MATCH;
+        if out.len() + match_len > original_len {
+            return Err(corrupt());
+        }
+
+        // Byte-by-byte copy: matches may overlap their own output
+        let start = out.len() - offset;
+        for k in 0..match_len {
+            let b = out[start + k];
+            out.push(b);
+        }
+    }
+
+    Ok(out)
+}
+
+// Encodings
+
+/// A decoded cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    fn to_json(&self) -> Json {
+        match self {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Int(i) => json!(i),
+            // NaN and infinities have no JSON form and become null
+            Value::Float(f) => serde_json::Number::from_f64(*f).map_or(Json::Null, Json::Number),
+            Value::Bytes(_) => Json::String(self.to_text()),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bytes(b) => match std::str::from_utf8(b) {
+                Ok(s) => s.to_string(),
+                Err(_) => b.iter().map(|b| format!("{:02x}", b)).collect(),
+            },
+        }
+    }
+}
+
+// Values of the RLE/bit-packing hybrid encoding
+fn decode_rle(data: &[u8], bit_width: u32, count: usize) -> Result<Vec<u32>> {
+    if bit_width > 32 {
+        return Err(corrupt("bit width too large"));
+    }
+    let mut r = CompactReader::new(data);
+    let mut out = Vec::with_capacity(count);
+    let byte_width = bit_width.div_ceil(8) as usize;
+    while out.len() < count {
+        let header = r.varint()?;
+        if header & 1 == 1 {
+            // Bit-packed groups of 8 values, least significant bit first
+            let n = (header >> 1) as usize * 8;
+            let packed = r.bytes((n * bit_width as usize).div_ceil(8))?;
+            for i in 0..n.min(count - out.len()) {
+                let mut v = 0u64;
+                for bit in 0..bit_width as usize {
+                    let at = i * bit_width as usize + bit;
+                    v |= (((packed[at / 8] >> (at % 8)) & 1) as u64) << bit;
+                }
+                out.push(v as u32);
+            }
+        } else {
+            let n = (header >> 1) as usize;
+            let mut v = [0u8; 4];
+            v[..byte_width].copy_from_slice(r.bytes(byte_width)?);
+            let run = n.min(count - out.len());
+            out.extend(std::iter::repeat_n(u32::from_le_bytes(v), run));
+        }
+    }
+    Ok(out)
+}
+
+// Days between the Julian day epoch and the Unix epoch
+const JULIAN_UNIX_EPOCH: i64 = 2_440_588;
+
+fn decode_plain(column: &Column, data: &[u8], count: usize) -> Result<Vec<Value>> {
+    let mut r = CompactReader::new(data);
+    let mut out = Vec::with_capacity(count.min(data.len() * 8 + 1));
+    for i in 0..count {
+        let value = match column.physical_type {
+            PhysicalType::Boolean => {
+                let byte = *data.get(i / 8).ok_or_else(|| corrupt("truncated page"))?;
+                Value::Bool((byte >> (i % 8)) & 1 == 1)
+            }
+            PhysicalType::Int32 => {
+                Value::Int(i32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as i64)
+            }
+            PhysicalType::Int64 => Value::Int(i64::from_le_bytes(r.bytes(8)?.try_into().unwrap())),
+            PhysicalType::Int96 => {
+                // Legacy timestamp: nanoseconds of day, then Julian day
+                let b = r.bytes(12)?;
+                let nanos = i64::from_le_bytes(b[..8].try_into().unwrap());
+                let day = u32::from_le_bytes(b[8..].try_into().unwrap()) as i64;
+                Value::Int((day - JULIAN_UNIX_EPOCH) * 86_400_000_000_000 + nanos)
+            }
+            PhysicalType::Float => {
+                Value::Float(f32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as f64)
+            }
+            PhysicalType::Double => {
+                Value::Float(f64::from_le_bytes(r.bytes(8)?.try_into().unwrap()))
+            }
+            PhysicalType::ByteArray => {
+                let len = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as usize;
+                Value::Bytes(r.bytes(len)?.to_vec())
+            }
+            PhysicalType::FixedLenByteArray => {
+                Value::Bytes(r.bytes(column.type_length.max(0) as usize)?.to_vec())
+            }
+        };
+        out.push(value);
+    }
+    Ok(out)
+}
+
+fn decode_values(
+    column: &Column,
+    encoding: i64,
+    data: &[u8],
+    count: usize,
+    dict: &[Value],
+) -> Result<Vec<Value>> {
+    match encoding {
+        0 => decode_plain(column, data, count),
+        // PLAIN_DICTIONARY and RLE_DICTIONARY
+        2 | 8 => {
+            let (&width, rest) = data.split_first().ok_or_else(|| corrupt("empty page"))?;
+            decode_rle(rest, width as u32, count)?
+                .into_iter()
+                .map(|i| {
+                    dict.get(i as usize)
+                        .cloned()
+                        .ok_or_else(|| corrupt("dictionary index out of range"))
+                })
+                .collect()
+        }
+        other => Err(Error::Other(format!(
+            "parquet encoding {} is not supported",
+            other
+        ))),
+    }
+}
+
+fn level_width(max: u32) -> u32 {
+    32 - max.leading_zeros()
+}
+
+// Decode all pages of one column chunk
+fn decode_chunk(column: &Column, chunk: &Chunk, data: &[u8]) -> Result<Vec<Value>> {
+    let mut dict = Vec::new();
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() && (out.len() as i64) < chunk.num_values {
+        let mut r = CompactReader::new(&data[pos..]);
+        let header = r.structure()?;
+        pos += r.pos;
+        let size = header.int(3).unwrap_or(-1);
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|s| pos.checked_add(s))
+            .filter(|&e| e <= data.len())
+            .ok_or_else(|| corrupt("page extends past column chunk"))?;
+        let body = &data[pos..end];
+        pos = end;
+        let uncompressed = header.int(2).unwrap_or(0).max(0) as usize;
+
+        match header.int(1) {
+            // DICTIONARY_PAGE
+            Some(2) => {
+                let h = header
+                    .field(7)
+                    .ok_or_else(|| corrupt("dictionary page header"))?;
+                let page = decompress(chunk.codec, body, uncompressed)?;
+                dict = decode_plain(column, &page, h.int(1).unwrap_or(0).max(0) as usize)?;
+            }
+            // DATA_PAGE
+            Some(0) => {
+                let h = header.field(5).ok_or_else(|| corrupt("data page header"))?;
+                let count = h.int(1).unwrap_or(0).max(0) as usize;
+                let page = decompress(chunk.codec, body, uncompressed)?;
+                let mut values = &page[..];
+                let levels = if column.max_def_level > 0 {
+                    let mut r = CompactReader::new(values);
+                    let len = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as usize;
+                    let levels = r.bytes(len)?;
+                    values = &values[4 + len..];
+                    Some(decode_rle(
+                        levels,
+                        level_width(column.max_def_level),
+                        count,
+                    )?)
+                } else {
+                    None
+                };
+                assemble(
+                    column,
+                    h.int(2).unwrap_or(0),
+                    values,
+                    count,
+                    levels,
+                    &dict,
+                    &mut out,
+                )?;
+            }
+            // DATA_PAGE_V2: levels are never compressed
+            Some(3) => {
+                let h = header
+                    .field(8)
+                    .ok_or_else(|| corrupt("data page v2 header"))?;
+                let count = h.int(1).unwrap_or(0).max(0) as usize;
+                let def_len = h.int(5).unwrap_or(0).max(0) as usize;
+                let rep_len = h.int(6).unwrap_or(0).max(0) as usize;
+                let levels_end = rep_len
+                    .checked_add(def_len)
+                    .filter(|&e| e <= body.len())
+                    .ok_or_else(|| corrupt("levels extend past page"))?;
+                let levels = if column.max_def_level > 0 {
+                    let data = &body[rep_len..levels_end];
+                    Some(decode_rle(data, level_width(column.max_def_level), count)?)
+                } else {
+                    None
+                };
+                let values = if h.bool(7).unwrap_or(true) {
+                    let len = uncompressed.saturating_sub(levels_end);
+                    decompress(chunk.codec, &body[levels_end..], len)?
+                } else {
+                    body[levels_end..].to_vec()
+                };
+                assemble(
+                    column,
+                    h.int(4).unwrap_or(0),
+                    &values,
+                    count,
+                    levels,
+                    &dict,
+                    &mut out,
+                )?;
+            }
+            // Index pages and unknown page types are skipped
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+// Interleave decoded values with nulls according to definition levels
+fn assemble(
+    column: &Column,
+    encoding: i64,
+    data: &[u8],
+    count: usize,
+    levels: Option<Vec<u32>>,
+    dict: &[Value],
+    out: &mut Vec<Value>,
+) -> Result<()> {
+    let Some(levels) = levels else {
+        out.extend(decode_values(column, encoding, data, count, dict)?);
+        return Ok(());
+    };
+    let present = levels
+        .iter()
+        .filter(|&&l| l == column.max_def_level)
+        .count();
+    let mut values = decode_values(column, encoding, data, present, dict)?.into_iter();
+    for level in levels {
+        out.push(if level == column.max_def_level {
+            values.next().ok_or_else(|| corrupt("too few values"))?
+        } else {
+            Value::Null
+        });
+    }
+    Ok(())
+}
+
+/// Decode column `col` of row group `rg`
+///
+/// Reads only that column chunk's byte range.
+pub fn read_column<F: FileSystem + ?Sized>(
+    fs: &F,
+    path: &str,
+    meta: &Metadata,
+    rg: usize,
+    col: usize,
+) -> Result<Vec<Value>> {
+    let column = meta.columns.get(col).ok_or(Error::NotFound)?;
+    let chunk = meta
+        .row_groups
+        .get(rg)
+        .and_then(|g| g.chunks.get(col))
+        .ok_or(Error::NotFound)?;
+    if column.max_rep_level > 0 {
+        return Err(Error::Other(format!(
+            "repeated parquet column {} is not supported",
+            column.name
+        )));
+    }
+    if chunk.start < 0 || chunk.len < 0 {
+        return Err(corrupt("column chunk range"));
+    }
+    let data = fs.read(path, chunk.start, chunk.len)?;
+    if data.len() as i64 != chunk.len {
+        return Err(corrupt("short column chunk read"));
+    }
+    decode_chunk(column, chunk, &data)
+}
+
+// Projections
+
+/// Output format of a projection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    JsonLines,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<(&str, Self)> {
+        if let Some(stem) = name.strip_suffix(".csv") {
+            Some((stem, Self::Csv))
+        } else {
+            name.strip_suffix(".jsonl")
+                .map(|stem| (stem, Self::JsonLines))
+        }
+    }
+}
+
+fn csv_field(out: &mut String, text: &str) {
+    if text.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&text.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(text);
+    }
+}
+
+/// Render the given row groups and columns
+pub fn render<F: FileSystem + ?Sized>(
+    fs: &F,
+    path: &str,
+    meta: &Metadata,
+    row_groups: &[usize],
+    columns: &[usize],
+    format: Format,
+) -> Result<Vec<u8>> {
+    let mut out = String::new();
+    if format == Format::Csv {
+        for (i, &col) in columns.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            csv_field(&mut out, &meta.columns[col].name);
+        }
+        out.push('\n');
+    }
+    for &rg in row_groups {
+        let rows = meta.row_groups[rg].num_rows.max(0) as usize;
+        let data = columns
+            .iter()
+            .map(|&col| read_column(fs, path, meta, rg, col))
+            .collect::<Result<Vec<_>>>()?;
+        if data.iter().any(|values| values.len() < rows) {
+            return Err(corrupt("column shorter than its row group"));
+        }
+        for row in 0..rows {
+            match format {
+                Format::Csv => {
+                    for (i, values) in data.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        csv_field(&mut out, &values[row].to_text());
+                    }
+                }
+                Format::JsonLines => {
+                    let object: Map<String, Json> = columns
+                        .iter()
+                        .zip(&data)
+                        .map(|(&col, values)| {
+                            (meta.columns[col].name.clone(), values[row].to_json())
+                        })
+                        .collect();
+                    out.push_str(&Json::Object(object).to_string());
+                }
+            }
+            out.push('\n');
+        }
+    }
+    Ok(out.into_bytes())
+}
+
+// The filesystem
+
+// Where a path lands inside a dataset
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    Root,
+    Schema,
+    RowGroupsDir,
+    ColumnsDir,
+    Projection {
+        row_groups: Option<usize>,
+        columns: Option<Vec<String>>,
+        format: Format,
+    },
+}
+
+fn parse_entry(rest: &str) -> Option<Entry> {
+    let parts: Vec<&str> = rest.split('/').filter(|p| !p.is_empty()).collect();
+    Some(match parts[..] {
+        [] => Entry::Root,
+        ["schema.json"] => Entry::Schema,
+        ["row_groups"] => Entry::RowGroupsDir,
+        ["columns"] => Entry::ColumnsDir,
+        ["data.csv"] => Entry::Projection {
+            row_groups: None,
+            columns: None,
+            format: Format::Csv,
+        },
+        ["data.jsonl"] => Entry::Projection {
+            row_groups: None,
+            columns: None,
+            format: Format::JsonLines,
+        },
+        ["row_groups", name] => {
+            let (stem, format) = Format::from_name(name)?;
+            Entry::Projection {
+                row_groups: Some(stem.parse().ok()?),
+                columns: None,
+                format,
+            }
+        }
+        ["columns", name] => {
+            let (stem, format) = Format::from_name(name)?;
+            Entry::Projection {
+                row_groups: None,
+                columns: Some(stem.split(',').map(str::to_string).collect()),
+                format,
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Read-only view of a filesystem with Parquet files opened up as
+/// directories
+pub struct ParquetFS<F> {
+    inner: F,
+    // Keyed by path, valid while the file keeps its size
+    metadata: RefCell<HashMap<String, (i64, Rc<Metadata>)>>,
+    // Most recently used last
+    rendered: RefCell<Vec<Rendered>>,
+}
+
+// (path, size of the parquet file, contents)
+type Rendered = (String, i64, Rc<Vec<u8>>);
+
+impl<F: Default> Default for ParquetFS<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> ParquetFS<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            metadata: RefCell::new(HashMap::new()),
+            rendered: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F: FileSystem> ParquetFS<F> {
+    // Split a path into the dataset file it is inside and the rest
+    fn locate<'p>(&self, path: &'p str) -> Result<Option<(&'p str, FileInfo, &'p str)>> {
+        let mut end = 0;
+        while let Some(i) = path[end..].find(PARQUET_SUFFIX) {
+            end += i + PARQUET_SUFFIX.len();
+            let rest = &path[end..];
+            if rest.is_empty() || rest.starts_with('/') {
+                let file = &path[..end];
+                match self.inner.stat(file) {
+                    Ok(info) if !info.is_dir => return Ok(Some((file, info, rest))),
+                    Ok(_) | Err(Error::NotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn metadata(&self, file: &str, size: i64) -> Result<Rc<Metadata>> {
+        if let Some((cached_size, meta)) = self.metadata.borrow().get(file) {
+            if *cached_size == size {
+                return Ok(meta.clone());
+            }
+        }
+        let meta = Rc::new(Metadata::read(&self.inner, file)?);
+        self.metadata
+            .borrow_mut()
+            .insert(file.to_string(), (size, meta.clone()));
+        Ok(meta)
+    }
+
+    fn contents(&self, path: &str, file: &str, size: i64, entry: &Entry) -> Result<Rc<Vec<u8>>> {
+        let meta = self.metadata(file, size)?;
+        let Entry::Projection {
+            row_groups,
+            columns,
+            format,
+        } = entry
+        else {
+            let mut data = serde_json::to_vec_pretty(&meta.schema_json())
+                .map_err(|e| Error::Other(e.to_string()))?;
+            data.push(b'\n');
+            return Ok(Rc::new(data));
+        };
+
+        {
+            let mut cache = self.rendered.borrow_mut();
+            if let Some(i) = cache.iter().position(|(p, s, _)| p == path && *s == size) {
+                let hit = cache.remove(i);
+                let data = hit.2.clone();
+                cache.push(hit);
+                return Ok(data);
+            }
+        }
+
+        let groups: Vec<usize> = match row_groups {
+            Some(rg) if *rg < meta.row_groups.len() => vec![*rg],
+            Some(_) => return Err(Error::NotFound),
+            None => (0..meta.row_groups.len()).collect(),
+        };
+        let cols: Vec<usize> = match columns {
+            Some(names) => names
+                .iter()
+                .map(|n| meta.column_index(n).ok_or(Error::NotFound))
+                .collect::<Result<_>>()?,
+            None => (0..meta.columns.len()).collect(),
+        };
+        let data = Rc::new(render(&self.inner, file, &meta, &groups, &cols, *format)?);
+
+        let mut cache = self.rendered.borrow_mut();
+        if cache.len() >= PROJECTION_CACHE_ENTRIES {
+            cache.remove(0);
+        }
+        cache.push((path.to_string(), size, data.clone()));
+        Ok(data)
+    }
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or("")
+}
+
+impl<F: FileSystem> FileSystem for ParquetFS<F> {
+    fn name(&self) -> &str {
+        "parquetfs"
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        let Some((file, info, rest)) = self.locate(path)? else {
+            return self.inner.read(path, offset, size);
+        };
+        match parse_entry(rest).ok_or(Error::NotFound)? {
+            Entry::Root | Entry::RowGroupsDir | Entry::ColumnsDir => Err(Error::IsDirectory),
+            entry => {
+                let data = self.contents(path, file, info.size, &entry)?;
+                Ok(slice_range(&data, offset, size).to_vec())
+            }
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        let Some((file, info, rest)) = self.locate(path)? else {
+            return self.inner.stat(path);
+        };
+        let name = base_name(path);
+        match parse_entry(rest).ok_or(Error::NotFound)? {
+            Entry::Root => Ok(FileInfo::dir(name, 0o555).with_mod_time(info.mod_time)),
+            Entry::RowGroupsDir | Entry::ColumnsDir => {
+                self.metadata(file, info.size)?;
+                Ok(FileInfo::dir(name, 0o555).with_mod_time(info.mod_time))
+            }
+            entry => {
+                let len = self.contents(path, file, info.size, &entry)?.len();
+                Ok(FileInfo::file(name, len as i64, 0o444).with_mod_time(info.mod_time))
+            }
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let Some((file, info, rest)) = self.locate(path)? else {
+            let mut entries = self.inner.readdir(path)?;
+            for entry in &mut entries {
+                if !entry.is_dir && entry.name.ends_with(PARQUET_SUFFIX) {
+                    *entry = FileInfo::dir(entry.name.clone(), 0o555).with_mod_time(entry.mod_time);
+                }
+            }
+            return Ok(entries);
+        };
+        let names: Vec<String> = match parse_entry(rest).ok_or(Error::NotFound)? {
+            Entry::Root => {
+                let mut entries = vec![
+                    FileInfo::dir("columns", 0o555),
+                    FileInfo::dir("row_groups", 0o555),
+                ];
+                for name in ["data.csv", "data.jsonl", "schema.json"] {
+                    entries.push(self.stat(&format!("{}/{}", file, name))?);
+                }
+                return Ok(entries);
+            }
+            Entry::RowGroupsDir => (0..self.metadata(file, info.size)?.row_groups.len())
+                .flat_map(|i| [format!("{}.csv", i), format!("{}.jsonl", i)])
+                .collect(),
+            Entry::ColumnsDir => self
+                .metadata(file, info.size)?
+                .columns
+                .iter()
+                .filter(|c| c.max_rep_level == 0 && !c.name.contains(','))
+                .flat_map(|c| [format!("{}.csv", c.name), format!("{}.jsonl", c.name)])
+                .collect(),
+            _ => return Err(Error::NotDirectory),
+        };
+        let dir = path.trim_end_matches('/');
+        names
+            .iter()
+            .map(|name| self.stat(&format!("{}/{}", dir, name)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+
+    // Compact protocol encoder for building test files
+    fn varint(out: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn zigzag(out: &mut Vec<u8>, v: i64) {
+        varint(out, ((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn type_of(v: &Thrift) -> u8 {
+        match v {
+            Thrift::Bool(true) => 1,
+            Thrift::Bool(false) => 2,
+            Thrift::Int(_) => 6,
+            Thrift::Double => 7,
+            Thrift::Binary(_) => 8,
+            Thrift::List(_) => 9,
+            Thrift::Struct(_) => 12,
+        }
+    }
+
+    fn encode(out: &mut Vec<u8>, v: &Thrift) {
+        match v {
+            Thrift::Bool(_) => {}
+            Thrift::Int(i) => zigzag(out, *i),
+            Thrift::Double => out.extend_from_slice(&[0; 8]),
+            Thrift::Binary(b) => {
+                varint(out, b.len() as u64);
+                out.extend_from_slice(b);
+            }
+            Thrift::List(items) => {
+                let elem = items.first().map_or(12, type_of);
+                out.push((15 << 4) | elem);
+                varint(out, items.len() as u64);
+                items.iter().for_each(|i| encode(out, i));
+            }
+            Thrift::Struct(fields) => {
+                for (id, v) in fields {
+                    // Long-form field headers; valid and simpler
+                    out.push(type_of(v));
+                    zigzag(out, *id as i64);
+                    encode(out, v);
+                }
+                out.push(0);
+            }
+        }
+    }
+
+    fn st(fields: Vec<(i16, Thrift)>) -> Thrift {
+        Thrift::Struct(fields)
+    }
+
+    fn int(v: i64) -> Thrift {
+        Thrift::Int(v)
+    }
+
+    fn text(s: &str) -> Thrift {
+        Thrift::Binary(s.as_bytes().to_vec())
+    }
+
+    // Snappy with literals only
+    fn snappy_literal(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint(&mut out, data.len() as u64);
+        out.extend_from_slice(&[60 << 2, (data.len() - 1) as u8]);
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn page(header: Thrift, body: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode(&mut out, &header);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn plain_strings(values: &[&str]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for v in values {
+            out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+            out.extend_from_slice(v.as_bytes());
+        }
+        out
+    }
+
+    // id INT64 required (v1, plain), name optional string (snappy,
+    // dictionary), score DOUBLE required (v2)
+    fn column_chunks(ids: &[i64], names: &[Option<&str>], scores: &[f64]) -> [(i64, Vec<u8>); 3] {
+        let body: Vec<u8> = ids.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let id = page(
+            st(vec![
+                (1, int(0)),
+                (2, int(body.len() as i64)),
+                (3, int(body.len() as i64)),
+                (
+                    5,
+                    st(vec![
+                        (1, int(ids.len() as i64)),
+                        (2, int(0)),
+                        (3, int(3)),
+                        (4, int(3)),
+                    ]),
+                ),
+            ]),
+            &body,
+        );
+
+        let mut dict_words: Vec<&str> = names.iter().flatten().copied().collect();
+        dict_words.dedup();
+        let dict_plain = plain_strings(&dict_words);
+        let dict_body = snappy_literal(&dict_plain);
+        let mut name = page(
+            st(vec![
+                (1, int(2)),
+                (2, int(dict_plain.len() as i64)),
+                (3, int(dict_body.len() as i64)),
+                (7, st(vec![(1, int(dict_words.len() as i64)), (2, int(0))])),
+            ]),
+            &dict_body,
+        );
+        // One bit-packed group each for levels and indices
+        let (mut levels, mut indices, mut n) = (0u8, 0u8, 0);
+        for (i, v) in names.iter().enumerate() {
+            if let Some(v) = v {
+                levels |= 1 << i;
+                indices |= (dict_words.iter().position(|w| w == v).unwrap() as u8) << n;
+                n += 1;
+            }
+        }
+        let data_plain = vec![2, 0, 0, 0, 3, levels, 1, 3, indices];
+        let data_body = snappy_literal(&data_plain);
+        name.extend(page(
+            st(vec![
+                (1, int(0)),
+                (2, int(data_plain.len() as i64)),
+                (3, int(data_body.len() as i64)),
+                (
+                    5,
+                    st(vec![
+                        (1, int(names.len() as i64)),
+                        (2, int(8)),
+                        (3, int(3)),
+                        (4, int(3)),
+                    ]),
+                ),
+            ]),
+            &data_body,
+        ));
+
+        let body: Vec<u8> = scores.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let score = page(
+            st(vec![
+                (1, int(3)),
+                (2, int(body.len() as i64)),
+                (3, int(body.len() as i64)),
+                (
+                    8,
+                    st(vec![
+                        (1, int(scores.len() as i64)),
+                        (2, int(0)),
+                        (3, int(scores.len() as i64)),
+                        (4, int(0)),
+                        (5, int(0)),
+                        (6, int(0)),
+                        (7, Thrift::Bool(false)),
+                    ]),
+                ),
+            ]),
+            &body,
+        );
+        [(0, id), (1, name), (0, score)]
+    }
+
+    fn parquet_file() -> Vec<u8> {
+        let groups = [
+            column_chunks(
+                &[1, 2, 3],
+                &[Some("a"), None, Some("c,d")],
+                &[1.5, 2.0, -0.5],
+            ),
+            column_chunks(&[4], &[Some("a")], &[f64::NAN]),
+        ];
+        let mut file = MAGIC.to_vec();
+        let mut row_groups = Vec::new();
+        for (rows, group) in [3, 1].into_iter().zip(groups) {
+            let mut chunks = Vec::new();
+            for (codec, data) in group {
+                let start = file.len() as i64;
+                file.extend_from_slice(&data);
+                chunks.push(st(vec![
+                    (2, int(start)),
+                    (
+                        3,
+                        st(vec![
+                            (1, int(0)),
+                            (4, int(codec)),
+                            (5, int(rows)),
+                            (7, int(data.len() as i64)),
+                            (9, int(start)),
+                        ]),
+                    ),
+                ]));
+            }
+            row_groups.push(st(vec![(1, Thrift::List(chunks)), (3, int(rows))]));
+        }
+        let schema = Thrift::List(vec![
+            st(vec![(4, text("schema")), (5, int(3))]),
+            st(vec![(1, int(2)), (3, int(0)), (4, text("id"))]),
+            st(vec![
+                (1, int(6)),
+                (3, int(1)),
+                (4, text("name")),
+                (6, int(0)),
+                (10, st(vec![(1, st(vec![]))])),
+            ]),
+            st(vec![(1, int(5)), (3, int(0)), (4, text("score"))]),
+        ]);
+        let meta = st(vec![
+            (1, int(1)),
+            (2, schema),
+            (3, int(4)),
+            (4, Thrift::List(row_groups)),
+            (6, text("agfs test")),
+        ]);
+        let mut footer = Vec::new();
+        encode(&mut footer, &meta);
+        file.extend_from_slice(&footer);
+        file.extend_from_slice(&(footer.len() as u32).to_le_bytes());
+        file.extend_from_slice(MAGIC);
+        file
+    }
+
+    struct FilesFS {
+        files: BTreeMap<String, Vec<u8>>,
+        bytes_read: Cell<usize>,
+    }
+
+    impl FileSystem for FilesFS {
+        fn name(&self) -> &str {
+            "files"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = slice_range(self.files.get(path).ok_or(Error::NotFound)?, offset, size);
+            self.bytes_read.set(self.bytes_read.get() + data.len());
+            Ok(data.to_vec())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match self.files.get(path) {
+                _ if path == "/" => Ok(FileInfo::dir("", 0o755)),
+                Some(data) => Ok(FileInfo::file(base_name(path), data.len() as i64, 0o644)),
+                None => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            self.files.keys().map(|p| self.stat(p)).collect()
+        }
+    }
+
+    fn fs() -> ParquetFS<FilesFS> {
+        let mut files = BTreeMap::new();
+        files.insert("/t.parquet".to_string(), parquet_file());
+        files.insert("/notes.txt".to_string(), b"hi".to_vec());
+        ParquetFS::new(FilesFS {
+            files,
+            bytes_read: Cell::new(0),
+        })
+    }
+
+    fn text_of(fs: &ParquetFS<FilesFS>, path: &str) -> String {
+        String::from_utf8(fs.read(path, 0, -1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_snappy() {
+        // "a" then a 9-byte overlapping copy at offset 1
+        assert_eq!(
+            snappy_decompress(&[10, 0, b'a', 0x15, 1]).unwrap(),
+            b"aaaaaaaaaa"
+        );
+        assert!(snappy_decompress(&[10, 0, b'a', 0x15, 2]).is_err());
+        assert!(snappy_decompress(&[3, 0, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_lz4() {
+        // "a" x 20: one literal, a 14-byte overlapping match, 5 trailing literals
+        let block = [0x1a, b'a', 0x01, 0x00, 0x50, b'a', b'a', b'a', b'a', b'a'];
+        assert_eq!(lz4_decompress(&block, 20).unwrap(), vec![b'a'; 20]);
+        assert!(lz4_decompress(&block, 10).is_err());
+        assert!(lz4_decompress(&[0x1f, b'a', 0x02, 0x00], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let fs = fs();
+        let meta = Metadata::read(fs.inner(), "/t.parquet").unwrap();
+        assert_eq!(meta.num_rows, 4);
+        assert_eq!(meta.created_by.as_deref(), Some("agfs test"));
+        let names: Vec<_> = meta.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "name", "score"]);
+        assert_eq!(meta.columns[1].logical_type.as_deref(), Some("STRING"));
+        assert!(meta.columns[1].nullable() && !meta.columns[0].nullable());
+        assert_eq!(meta.row_groups.len(), 2);
+
+        let names = read_column(fs.inner(), "/t.parquet", &meta, 0, 1).unwrap();
+        assert_eq!(
+            names,
+            [
+                Value::Bytes(b"a".to_vec()),
+                Value::Null,
+                Value::Bytes(b"c,d".to_vec())
+            ]
+        );
+
+        let mut bad = parquet_file();
+        let n = bad.len();
+        bad[n - 1] = b'X';
+        let bad_fs = FilesFS {
+            files: [("/b".to_string(), bad)].into_iter().collect(),
+            bytes_read: Cell::new(0),
+        };
+        assert!(Metadata::read(&bad_fs, "/b").is_err());
+    }
+
+    #[test]
+    fn test_projections() {
+        let fs = fs();
+        let root: Vec<_> = fs.readdir("/").unwrap();
+        assert!(root.iter().any(|e| e.name == "t.parquet" && e.is_dir));
+        assert!(root.iter().any(|e| e.name == "notes.txt" && !e.is_dir));
+        assert_eq!(fs.read("/notes.txt", 0, -1).unwrap(), b"hi");
+
+        let entries: Vec<_> = fs
+            .readdir("/t.parquet")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                "columns",
+                "row_groups",
+                "data.csv",
+                "data.jsonl",
+                "schema.json"
+            ]
+        );
+
+        assert_eq!(
+            text_of(&fs, "/t.parquet/data.csv"),
+            "id,name,score\n1,a,1.5\n2,,2\n3,\"c,d\",-0.5\n4,a,NaN\n"
+        );
+        assert_eq!(
+            text_of(&fs, "/t.parquet/row_groups/1.jsonl"),
+            "{\"id\":4,\"name\":\"a\",\"score\":null}\n"
+        );
+        let schema: Json = serde_json::from_str(&text_of(&fs, "/t.parquet/schema.json")).unwrap();
+        assert_eq!(schema["columns"][2]["type"], "DOUBLE");
+
+        // A projection only fetches its own column chunks
+        let file_len = fs.inner().files["/t.parquet"].len();
+        fs.inner().bytes_read.set(0);
+        let scores = text_of(&fs, "/t.parquet/columns/score,id.csv");
+        assert_eq!(scores, "score,id\n1.5,1\n2,2\n-0.5,3\nNaN,4\n");
+        assert!(fs.inner().bytes_read.get() < file_len);
+        let info = fs.stat("/t.parquet/columns/score,id.csv").unwrap();
+        assert_eq!(info.size, scores.len() as i64);
+
+        let cols: Vec<_> = fs
+            .readdir("/t.parquet/columns")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(cols.len(), 6);
+        assert_eq!(
+            fs.read("/t.parquet/columns/nope.csv", 0, -1),
+            Err(Error::NotFound)
+        );
+        assert_eq!(
+            fs.stat("/t.parquet/row_groups/2.csv").err(),
+            Some(Error::NotFound)
+        );
+        assert_eq!(
+            fs.read("/t.parquet/row_groups", 0, -1),
+            Err(Error::IsDirectory)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Request path canonicalization
+//!
+//! The export glue and the `HostFS` wrappers run every path through
+//! `canonicalize` before a plugin or the host sees it. How raw path bytes are
+//! decoded first is up to the plugin's `PathPolicy`. The rules live in the
+//! `agfs-path` crate, shared with agfs-ffi.
+
+use crate::types::{Error, Result};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub use agfs_path::{InvalidPath, InvalidUtf8, Normalization, PathPolicy};
+
+impl From<InvalidPath> for Error {
+    fn from(err: InvalidPath) -> Self {
+        Error::InvalidInput(err.0.to_string())
+    }
+}
+
+/// Bring a request path into canonical form, see `agfs_path::canonicalize`
+pub fn canonicalize(path: &str) -> Result<String> {
+    Ok(agfs_path::canonicalize(path)?)
+}
+
+/// Join a canonical request path below a host prefix, see `agfs_path::join`
+pub fn join(prefix: &str, path: &str) -> Result<String> {
+    Ok(agfs_path::join(prefix, path)?)
+}
+
+static TEMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A fresh hidden name next to `path`, for write-temp-then-rename
+///
+/// The temp file lives in the same directory so the final rename never
+/// crosses a filesystem boundary.
+pub fn temp_sibling(path: &str) -> Result<String> {
+    let path = canonicalize(path)?;
+    let slash = path.rfind('/').unwrap_or(0);
+    let (dir, name) = (&path[..slash], &path[slash + 1..]);
+    if name.is_empty() {
+        return Err(Error::InvalidInput("cannot replace the root".to_string()));
+    }
+    let seq = TEMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    Ok(format!("{}/.{}.tmp-{}", dir, name, seq))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_keep_message() {
+        assert_eq!(
+            canonicalize("/a/../b"),
+            Err(Error::InvalidInput("path traversal is not allowed".to_string()))
+        );
+        assert_eq!(join("/srv", "x").unwrap(), "/srv/x");
+    }
+
+    #[test]
+    fn test_temp_sibling() {
+        let a = temp_sibling("/dir/file.txt").unwrap();
+        let b = temp_sibling("/dir/file.txt").unwrap();
+        assert!(a.starts_with("/dir/.file.txt.tmp-"));
+        assert_ne!(a, b);
+        assert!(temp_sibling("/top").unwrap().starts_with("/.top.tmp-"));
+        assert!(temp_sibling("/").is_err());
+    }
+}
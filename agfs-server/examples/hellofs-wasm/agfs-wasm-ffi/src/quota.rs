@@ -0,0 +1,707 @@
+//! Per-directory quotas
+//!
+//! `QuotaFS` wraps a filesystem and enforces byte and file-count limits
+//! on directory trees below it:
+//!
+//! ```ignore
+//! let fs = QuotaFS::new(MemFS::default())
+//!     .with_limit("/home/alice", QuotaInfo::limits(1 << 30, 10_000));
+//! ```
+//!
+//! Limits can also be set at runtime through `set_quota`, and for the
+//! whole mount with the `quota_bytes` and `quota_files` config keys, so
+//! `export_plugin!(QuotaFS<MemFS>)` needs no code. Calls that would go
+//! over a limit fail with `TooLarge` before they reach the inner
+//! filesystem.
+//!
+//! A limited tree is walked once, the first time its usage is needed, and
+//! then kept up to date from the calls passing through. Changes made to
+//! the backend behind the wrapper's back are not seen until the limit is
+//! set again.
+
+use crate::context::OpContext;
+use crate::filesystem::FileSystem;
+use crate::host_fs::HostCapabilities;
+use crate::path::PathPolicy;
+use crate::stream::ReaddirStream;
+use crate::types::{
+    CachePolicy, Capabilities, Config, CreateFlags, Error, FileInfo, FsStats, Handle, HealthStatus,
+    LockKind, OpenFlags, QuotaInfo, RawJson, ReadResult, RenameFlags, Result, WriteResult,
+};
+use crate::watch::WatchId;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+// A limited directory; `counted` is false until its tree has been walked
+struct Quota {
+    info: QuotaInfo,
+    counted: bool,
+}
+
+/// Filesystem wrapper that enforces quotas on `inner`
+pub struct QuotaFS<F> {
+    inner: F,
+    quotas: RefCell<BTreeMap<String, Quota>>,
+}
+
+impl<F: Default> Default for QuotaFS<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> QuotaFS<F> {
+    /// Wrap `inner` without any limits
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            quotas: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Limit the tree under `dir`, a canonical path
+    pub fn with_limit(self, dir: &str, limits: QuotaInfo) -> Self {
+        self.limit(dir, limits);
+        self
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn limit(&self, dir: &str, limits: QuotaInfo) {
+        let mut quotas = self.quotas.borrow_mut();
+        if limits.bytes_limit == 0 && limits.files_limit == 0 {
+            quotas.remove(dir);
+            return;
+        }
+        let info = QuotaInfo::limits(limits.bytes_limit, limits.files_limit);
+        quotas.insert(
+            dir.to_string(),
+            Quota {
+                info,
+                counted: false,
+            },
+        );
+    }
+
+    // Limited directories `path` lies below
+    fn covering(&self, path: &str) -> Vec<String> {
+        let quotas = self.quotas.borrow();
+        quotas
+            .keys()
+            .filter(|dir| is_below(dir, path))
+            .cloned()
+            .collect()
+    }
+
+    // Have the quotas covering `path` walk their trees again
+    fn recount(&self, path: &str) {
+        let mut quotas = self.quotas.borrow_mut();
+        for (dir, quota) in quotas.iter_mut() {
+            if dir == path || is_below(dir, path) || is_below(path, dir) {
+                quota.counted = false;
+            }
+        }
+    }
+
+    // Account for `path` growing by `bytes` and `files` after a call
+    // made it so
+    fn commit(&self, path: &str, bytes: i64, files: i64) {
+        let mut quotas = self.quotas.borrow_mut();
+        for (dir, quota) in quotas.iter_mut() {
+            if quota.counted && is_below(dir, path) {
+                quota.info.bytes_used = quota.info.bytes_used.saturating_add_signed(bytes);
+                quota.info.files_used = quota.info.files_used.saturating_add_signed(files);
+            }
+        }
+    }
+}
+
+impl<F: FileSystem> QuotaFS<F> {
+    fn apply_config(&mut self, config: &Config) {
+        if config.contains("quota_bytes") || config.contains("quota_files") {
+            let bytes = config.get_i64("quota_bytes").unwrap_or(0).max(0) as u64;
+            let files = config.get_i64("quota_files").unwrap_or(0).max(0) as u64;
+            self.limit("/", QuotaInfo::limits(bytes, files));
+        }
+    }
+
+    // The quota of `dir` with its usage counted
+    fn counted(&self, dir: &str) -> Result<QuotaInfo> {
+        if let Some(quota) = self.quotas.borrow().get(dir).filter(|q| q.counted) {
+            return Ok(quota.info);
+        }
+        let (bytes, files) = usage(&self.inner, dir)?;
+        let mut quotas = self.quotas.borrow_mut();
+        let quota = quotas.get_mut(dir).ok_or(Error::NotFound)?;
+        quota.info.bytes_used = bytes;
+        quota.info.files_used = files;
+        quota.counted = true;
+        Ok(quota.info)
+    }
+
+    // Fail with `TooLarge` if `path` growing by `bytes` and `files` would
+    // exceed a quota
+    fn check(&self, path: &str, bytes: i64, files: i64) -> Result<()> {
+        if bytes <= 0 && files <= 0 {
+            return Ok(());
+        }
+        for dir in self.covering(path) {
+            if !self.counted(&dir)?.allows(bytes, files) {
+                return Err(Error::TooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    // Run `f` if `path` may grow by `bytes` and `files`, then account for it
+    fn charged<T>(
+        &mut self,
+        path: &str,
+        bytes: i64,
+        files: i64,
+        f: impl FnOnce(&mut F) -> Result<T>,
+    ) -> Result<T> {
+        self.check(path, bytes, files)?;
+        let result = f(&mut self.inner)?;
+        self.commit(path, bytes, files);
+        Ok(result)
+    }
+
+    // Size of `path`, `None` if it does not exist
+    fn size_of(&self, path: &str) -> Result<Option<i64>> {
+        match self.inner.stat(path) {
+            Ok(info) => Ok(Some(info.size)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Growth of a file written up to `end`
+    fn growth(&self, path: &str, end: i64) -> Result<(i64, i64)> {
+        Ok(match self.size_of(path)? {
+            Some(size) => ((end - size).max(0), 0),
+            None => (end, 1),
+        })
+    }
+
+    // Growth of a file replaced with `len` bytes, which may be negative
+    fn replacement(&self, path: &str, len: i64) -> Result<(i64, i64)> {
+        Ok(match self.size_of(path)? {
+            Some(size) => (len - size, 0),
+            None => (len, 1),
+        })
+    }
+
+    // Growth of a file (re)created empty, or left as is without `truncate`
+    fn creation(&self, path: &str, truncate: bool) -> Result<(i64, i64)> {
+        Ok(match self.size_of(path)? {
+            Some(size) if truncate => (-size, 0),
+            Some(_) => (0, 0),
+            None => (0, 1),
+        })
+    }
+
+    // Shrinkage of removing `path`
+    fn removal(&self, path: &str) -> Result<(i64, i64)> {
+        let info = self.inner.stat(path)?;
+        let size = if info.is_dir { 0 } else { info.size };
+        Ok((-size, -1))
+    }
+
+    // Check the quotas a rename would move `old_path` into
+    fn check_rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        let from = self.covering(old_path);
+        let gaining: Vec<_> = self
+            .covering(new_path)
+            .into_iter()
+            .filter(|dir| !from.contains(dir))
+            .collect();
+        if gaining.is_empty() {
+            return Ok(());
+        }
+        let info = self.inner.stat(old_path)?;
+        let (bytes, files) = if info.is_dir {
+            let (bytes, files) = usage(&self.inner, old_path)?;
+            (bytes as i64, files as i64 + 1)
+        } else {
+            (info.size, 1)
+        };
+        for dir in gaining {
+            if !self.counted(&dir)?.allows(bytes, files) {
+                return Err(Error::TooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    fn rename_with(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        f: impl FnOnce(&mut F) -> Result<()>,
+    ) -> Result<()> {
+        self.check_rename(old_path, new_path)?;
+        f(&mut self.inner)?;
+        self.recount(old_path);
+        self.recount(new_path);
+        Ok(())
+    }
+}
+
+// Whether `path` lies strictly below the directory `dir`
+fn is_below(dir: &str, path: &str) -> bool {
+    if dir == "/" {
+        return path != "/";
+    }
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Bytes and entries below `dir`, walking it on `fs`
+fn usage<F: FileSystem>(fs: &F, dir: &str) -> Result<(u64, u64)> {
+    let (mut bytes, mut files) = (0, 0);
+    let mut pending = vec![dir.to_string()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs.readdir(&dir)? {
+            files += 1;
+            let path = if dir == "/" {
+                format!("/{}", entry.name)
+            } else {
+                format!("{}/{}", dir, entry.name)
+            };
+            if entry.is_dir {
+                pending.push(path);
+            } else {
+                bytes += entry.size.max(0) as u64;
+            }
+        }
+    }
+    Ok((bytes, files))
+}
+
+impl<F: FileSystem> FileSystem for QuotaFS<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.inner.initialize(config)?;
+        self.apply_config(config);
+        Ok(())
+    }
+
+    fn reconfigure(&mut self, config: &Config) -> Result<()> {
+        self.inner.reconfigure(config)?;
+        self.apply_config(config);
+        Ok(())
+    }
+
+    fn path_policy(&self) -> PathPolicy {
+        self.inner.path_policy()
+    }
+
+    fn host_capabilities(&self) -> Option<HostCapabilities> {
+        self.inner.host_capabilities()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn health(&self) -> Result<HealthStatus> {
+        self.inner.health()
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.inner.cache_policy()
+    }
+
+    fn snapshot(&self) -> Result<Vec<u8>> {
+        self.inner.snapshot()
+    }
+
+    fn restore(&mut self, data: &[u8]) -> Result<()> {
+        self.inner.restore(data)?;
+        self.recount("/");
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn trim(&mut self) {
+        self.inner.trim()
+    }
+
+    fn statfs(&self) -> Result<FsStats> {
+        self.inner.statfs()
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read(path, offset, size)
+    }
+
+    fn read_result(&self, path: &str, offset: i64, size: i64) -> Result<ReadResult> {
+        self.inner.read_result(path, offset, size)
+    }
+
+    fn read_ranges(&self, path: &str, ranges: &[(i64, i64)]) -> Result<Vec<Vec<u8>>> {
+        self.inner.read_ranges(path, ranges)
+    }
+
+    fn open(&mut self, path: &str, flags: OpenFlags) -> Result<Handle> {
+        self.inner.open(path, flags)
+    }
+
+    fn read_at(&self, handle: &Handle, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read_at(handle, offset, size)
+    }
+
+    fn close(&mut self, handle: Handle) -> Result<()> {
+        self.inner.close(handle)
+    }
+
+    fn watch(&mut self, path: &str) -> Result<WatchId> {
+        self.inner.watch(path)
+    }
+
+    fn unwatch(&mut self, id: WatchId) -> Result<()> {
+        self.inner.unwatch(id)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let (bytes, files) = self.replacement(path, data.len() as i64)?;
+        self.charged(path, bytes, files, |fs| fs.write(path, data))
+    }
+
+    fn write_result(&mut self, path: &str, data: &[u8]) -> Result<WriteResult> {
+        let (bytes, files) = self.replacement(path, data.len() as i64)?;
+        self.charged(path, bytes, files, |fs| fs.write_result(path, data))
+    }
+
+    fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        let (bytes, files) = self.growth(path, offset.saturating_add(data.len() as i64))?;
+        self.charged(path, bytes, files, |fs| fs.write_at(path, offset, data))
+    }
+
+    fn append(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        let (bytes, files) = self.growth(path, 0)?;
+        let bytes = bytes + data.len() as i64;
+        self.charged(path, bytes, files, |fs| fs.append(path, data))
+    }
+
+    fn copy_range(
+        &mut self,
+        src: &str,
+        dst: &str,
+        src_offset: i64,
+        dst_offset: i64,
+        len: i64,
+    ) -> Result<i64> {
+        let available = (self.inner.stat(src)?.size - src_offset).max(0);
+        let len = if len < 0 {
+            available
+        } else {
+            len.min(available)
+        };
+        let (bytes, files) = self.growth(dst, dst_offset.saturating_add(len))?;
+        self.charged(dst, bytes, files, |fs| {
+            fs.copy_range(src, dst, src_offset, dst_offset, len)
+        })
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        let (bytes, files) = self.creation(path, true)?;
+        self.charged(path, bytes, files, |fs| fs.create(path))
+    }
+
+    fn create_exclusive(&mut self, path: &str) -> Result<()> {
+        self.charged(path, 0, 1, |fs| fs.create_exclusive(path))
+    }
+
+    fn create_with_flags(&mut self, path: &str, flags: CreateFlags) -> Result<()> {
+        let (bytes, files) = self.creation(path, flags.truncate)?;
+        self.charged(path, bytes, files, |fs| fs.create_with_flags(path, flags))
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.charged(path, 0, 1, |fs| fs.mkdir(path, perm))
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        let (bytes, files) = self.removal(path)?;
+        self.charged(path, bytes, files, |fs| fs.remove(path))
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.inner.remove_all(path)?;
+        self.recount(path);
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.inner.stat(path)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir(path)
+    }
+
+    fn readdir_plus(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir_plus(path)
+    }
+
+    fn readdir_stream(&self, path: &str) -> Result<ReaddirStream> {
+        self.inner.readdir_stream(path)
+    }
+
+    fn readdir_page(
+        &self,
+        path: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FileInfo>, Option<String>)> {
+        self.inner.readdir_page(path, cursor, limit)
+    }
+
+    fn stat_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        self.inner.stat_passthrough(path)
+    }
+
+    fn readdir_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        self.inner.readdir_passthrough(path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.rename_with(old_path, new_path, |fs| fs.rename(old_path, new_path))
+    }
+
+    fn rename_with_flags(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        if flags.contains(RenameFlags::EXCHANGE) {
+            self.check_rename(new_path, old_path)?;
+        }
+        self.rename_with(old_path, new_path, |fs| {
+            fs.rename_with_flags(old_path, new_path, flags)
+        })
+    }
+
+    fn read_with_ctx(
+        &self,
+        ctx: &OpContext,
+        path: &str,
+        offset: i64,
+        size: i64,
+    ) -> Result<Vec<u8>> {
+        self.inner.read_with_ctx(ctx, path, offset, size)
+    }
+
+    fn write_with_ctx(&mut self, ctx: &OpContext, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let (bytes, files) = self.replacement(path, data.len() as i64)?;
+        self.charged(path, bytes, files, |fs| fs.write_with_ctx(ctx, path, data))
+    }
+
+    fn stat_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<FileInfo> {
+        self.inner.stat_with_ctx(ctx, path)
+    }
+
+    fn readdir_with_ctx(&self, ctx: &OpContext, path: &str) -> Result<Vec<FileInfo>> {
+        self.inner.readdir_with_ctx(ctx, path)
+    }
+
+    fn create_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let (bytes, files) = self.creation(path, true)?;
+        self.charged(path, bytes, files, |fs| fs.create_with_ctx(ctx, path))
+    }
+
+    fn mkdir_with_ctx(&mut self, ctx: &OpContext, path: &str, perm: u32) -> Result<()> {
+        self.charged(path, 0, 1, |fs| fs.mkdir_with_ctx(ctx, path, perm))
+    }
+
+    fn remove_with_ctx(&mut self, ctx: &OpContext, path: &str) -> Result<()> {
+        let (bytes, files) = self.removal(path)?;
+        self.charged(path, bytes, files, |fs| fs.remove_with_ctx(ctx, path))
+    }
+
+    fn rename_with_ctx(&mut self, ctx: &OpContext, old_path: &str, new_path: &str) -> Result<()> {
+        self.rename_with(old_path, new_path, |fs| {
+            fs.rename_with_ctx(ctx, old_path, new_path)
+        })
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.inner.chmod(path, mode)
+    }
+
+    fn chown(&mut self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        self.inner.chown(path, uid, gid)
+    }
+
+    fn set_times(&mut self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        self.inner.set_times(path, atime, mtime)
+    }
+
+    fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+        let bytes = size - self.inner.stat(path)?.size;
+        self.charged(path, bytes, 0, |fs| fs.truncate(path, size))
+    }
+
+    fn allocate(&mut self, path: &str, offset: i64, len: i64) -> Result<()> {
+        let (bytes, files) = self.growth(path, offset.saturating_add(len))?;
+        self.charged(path, bytes, files, |fs| fs.allocate(path, offset, len))
+    }
+
+    fn quota(&self, path: &str) -> Result<QuotaInfo> {
+        if self.quotas.borrow().contains_key(path) {
+            return self.counted(path);
+        }
+        self.inner.quota(path)
+    }
+
+    fn set_quota(&mut self, path: &str, limits: QuotaInfo) -> Result<()> {
+        self.limit(path, limits);
+        Ok(())
+    }
+
+    fn flush(&mut self, path: &str) -> Result<()> {
+        self.inner.flush(path)
+    }
+
+    fn fsync(&mut self, path: &str) -> Result<()> {
+        self.inner.fsync(path)
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<()> {
+        // The walk counts every name of a file, so a link costs its size
+        let size = self.inner.stat(existing)?.size;
+        self.charged(new, size, 1, |fs| fs.link(existing, new))
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        let size = target.len() as i64;
+        self.charged(link_path, size, 1, |fs| fs.symlink(target, link_path))
+    }
+
+    fn access(&self, path: &str, mode: u32) -> Result<()> {
+        self.inner.access(path, mode)
+    }
+
+    fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_data(path, offset)
+    }
+
+    fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        self.inner.seek_hole(path, offset)
+    }
+
+    fn lock(&mut self, path: &str, kind: LockKind) -> Result<()> {
+        self.inner.lock(path, kind)
+    }
+
+    fn unlock(&mut self, path: &str) -> Result<()> {
+        self.inner.unlock(path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.inner.readlink(path)
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.getxattr(path, name)
+    }
+
+    fn setxattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.inner.setxattr(path, name, value)
+    }
+
+    fn listxattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.listxattr(path)
+    }
+
+    fn removexattr(&mut self, path: &str, name: &str) -> Result<()> {
+        self.inner.removexattr(path, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flat in-memory files below the root
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            self.files.remove(path).map(|_| ()).ok_or(Error::NotFound)
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(&path[1..], data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            self.files.keys().map(|path| self.stat(path)).collect()
+        }
+    }
+
+    #[test]
+    fn test_quota_limits() {
+        let mut inner = MemFS::default();
+        inner.write("/old", b"12345").unwrap();
+        let mut fs = QuotaFS::new(inner).with_limit("/", QuotaInfo::limits(10, 3));
+
+        fs.write("/a", b"12345").unwrap();
+        assert_eq!(fs.write("/b", b"x"), Err(Error::TooLarge));
+        // Replacing a file only costs the difference
+        fs.write("/a", b"123").unwrap();
+        fs.write("/b", b"12").unwrap();
+        assert_eq!(fs.write("/c", b""), Err(Error::TooLarge));
+
+        let quota = fs.quota("/").unwrap();
+        assert_eq!((quota.bytes_used, quota.files_used), (10, 3));
+        fs.remove("/old").unwrap();
+        assert_eq!(fs.quota("/").unwrap().bytes_used, 5);
+
+        fs.set_quota("/", QuotaInfo::default()).unwrap();
+        fs.write("/c", &[0; 64]).unwrap();
+        assert_eq!(fs.quota("/").unwrap(), QuotaInfo::default());
+    }
+
+    #[test]
+    fn test_is_below() {
+        assert!(is_below("/", "/a"));
+        assert!(is_below("/a", "/a/b"));
+        assert!(!is_below("/a", "/a"));
+        assert!(!is_below("/a", "/ab"));
+        assert!(!is_below("/", "/"));
+    }
+}
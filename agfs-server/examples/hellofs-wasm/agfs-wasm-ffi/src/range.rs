@@ -0,0 +1,104 @@
+//! Read range helpers
+//!
+//! Every plugin that serves reads from an in-memory buffer needs the same
+//! offset/size clamping; `slice_range` does it without overflow or panics.
+//! `encode_ranges` frames the results of `FileSystem::read_ranges`.
+
+use crate::types::{Error, Result};
+
+/// Check the offset of a read request
+///
+/// The export glue rejects negative offsets before the plugin is called.
+pub fn validate_offset(offset: i64) -> Result<()> {
+    if offset < 0 {
+        return Err(Error::InvalidInput("negative read offset".to_string()));
+    }
+    Ok(())
+}
+
+/// The part of `data` a read of `size` bytes at `offset` returns
+///
+/// A negative `size` reads to the end. Offsets past the end (or negative
+/// ones) yield an empty slice, and `offset + size` may exceed `i64::MAX`.
+pub fn slice_range<T>(data: &[T], offset: i64, size: i64) -> &[T] {
+    if offset < 0 || offset as u64 >= data.len() as u64 {
+        return &[];
+    }
+    let start = offset as usize;
+    let end = if size < 0 {
+        data.len()
+    } else {
+        start
+            .saturating_add(usize::try_from(size).unwrap_or(usize::MAX))
+            .min(data.len())
+    };
+    &data[start..end]
+}
+
+/// Ranges one `fs_read_ranges` call may ask for
+pub const MAX_READ_RANGES: usize = 4096;
+
+/// Frame the results of a vectored read as `fs_read_ranges` returns them
+///
+/// Each part follows its length as a little-endian u32.
+pub fn encode_ranges(parts: Vec<Vec<u8>>) -> Result<Vec<u8>> {
+    let total = parts.iter().map(|part| 4 + part.len()).sum();
+    let mut out = Vec::with_capacity(total);
+    for part in parts {
+        let len = u32::try_from(part.len()).map_err(|_| Error::TooLarge)?;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&part);
+    }
+    Ok(out)
+}
+
+/// Split a payload framed by `encode_ranges` back into its parts
+pub fn decode_ranges(mut data: &[u8]) -> Result<Vec<&[u8]>> {
+    let truncated = || Error::InvalidInput("truncated read ranges".to_string());
+    let mut parts = Vec::new();
+    while !data.is_empty() {
+        let (len, rest) = data.split_first_chunk::<4>().ok_or_else(truncated)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(truncated());
+        }
+        let (part, rest) = rest.split_at(len);
+        parts.push(part);
+        data = rest;
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_range() {
+        let data = b"0123456789";
+        assert_eq!(slice_range(data, 0, -1), data);
+        assert_eq!(slice_range(data, 2, 3), b"234");
+        assert_eq!(slice_range(data, 8, 100), b"89");
+        assert_eq!(slice_range(data, 8, i64::MAX), b"89");
+        assert!(slice_range(data, 20, 4).is_empty());
+        assert!(slice_range(data, -5, 4).is_empty());
+        assert!(slice_range(data, i64::MAX, i64::MAX).is_empty());
+        assert!(slice_range(data, 3, 0).is_empty());
+    }
+
+    #[test]
+    fn test_ranges_round_trip() {
+        let parts = vec![b"abc".to_vec(), Vec::new(), b"z".to_vec()];
+        let data = encode_ranges(parts.clone()).unwrap();
+        assert_eq!(data.len(), 12 + 4);
+        assert_eq!(decode_ranges(&data).unwrap(), parts);
+        assert!(decode_ranges(&data[..data.len() - 1]).is_err());
+        assert!(decode_ranges(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_offset() {
+        assert!(validate_offset(0).is_ok());
+        assert!(validate_offset(-1).is_err());
+    }
+}
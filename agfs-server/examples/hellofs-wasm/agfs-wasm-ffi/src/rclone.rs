@@ -0,0 +1,225 @@
+//! Mount configs from rclone remotes
+//!
+//! Reads the remotes defined in an `rclone.conf` and turns an rclone path
+//! such as `minio:photos/2024` into the plugin name and `Config` of the
+//! matching agfs backend, so remotes only have to be configured once:
+//!
+//! ```ignore
+//! let remotes = rclone::parse(&std::fs::read_to_string(conf)?)?;
+//! let mount = rclone::resolve(&remotes, "minio:photos/2024")?;
+//! // mount.plugin == "s3fs", mount.config has bucket "photos", prefix "2024"
+//! ```
+//!
+//! Only `s3` remotes map onto a backend (s3fs). `webdav`, `sftp` and
+//! `http` remotes are recognised but agfs has no client backend for them
+//! yet, so they are rejected with an error naming the type. Encrypted
+//! rclone configs must be decrypted with `rclone config show` first.
+
+use crate::types::{Config, Error, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// One `[name]` section of an rclone config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub name: String,
+    /// The section's `type`, e.g. `s3`
+    pub kind: String,
+    pub options: BTreeMap<String, String>,
+}
+
+/// A backend plugin and the config to mount it with
+#[derive(Debug, Clone)]
+pub struct Mount {
+    pub plugin: &'static str,
+    pub config: Config,
+}
+
+/// Parse the text of an rclone config
+///
+/// Blank lines and lines starting with `#` or `;` are skipped. Every
+/// section needs a `type`.
+pub fn parse(text: &str) -> Result<Vec<Remote>> {
+    if text.trim_start().starts_with("RCLONE_ENCRYPT_") {
+        return Err(Error::InvalidInput(
+            "rclone config is encrypted".to_string(),
+        ));
+    }
+    let bad = |line: usize, why: &str| {
+        Error::InvalidInput(format!("rclone config line {}: {}", line, why))
+    };
+
+    let mut remotes: Vec<Remote> = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| bad(i + 1, "unterminated section"))?;
+            if remotes.iter().any(|r| r.name == name) {
+                return Err(bad(i + 1, "duplicate remote"));
+            }
+            remotes.push(Remote {
+                name: name.to_string(),
+                kind: String::new(),
+                options: BTreeMap::new(),
+            });
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| bad(i + 1, "expected key = value"))?;
+        let remote = remotes
+            .last_mut()
+            .ok_or_else(|| bad(i + 1, "option outside a section"))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key == "type" {
+            remote.kind = value.to_string();
+        } else {
+            remote.options.insert(key.to_string(), value.to_string());
+        }
+    }
+    if let Some(r) = remotes.iter().find(|r| r.kind.is_empty()) {
+        return Err(Error::InvalidInput(format!(
+            "rclone remote {} has no type",
+            r.name
+        )));
+    }
+    Ok(remotes)
+}
+
+impl Remote {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// The mount for `path` within this remote
+    ///
+    /// For s3 the first path component is the bucket and the rest the
+    /// prefix.
+    pub fn to_mount(&self, path: &str) -> Result<Mount> {
+        match self.kind.as_str() {
+            "s3" => self.s3_mount(path),
+            "webdav" | "sftp" | "http" => Err(Error::Other(format!(
+                "rclone remote {}: agfs has no {} backend",
+                self.name, self.kind
+            ))),
+            other => Err(Error::Other(format!(
+                "rclone remote {}: unsupported type {}",
+                self.name, other
+            ))),
+        }
+    }
+
+    fn s3_mount(&self, path: &str) -> Result<Mount> {
+        let path = path.trim_matches('/');
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "rclone remote {}: path must start with a bucket",
+                self.name
+            )));
+        }
+        let mut config = json!({"bucket": bucket});
+        if !prefix.is_empty() {
+            config["prefix"] = json!(prefix);
+        }
+        // With env_auth rclone takes credentials from the environment, as
+        // s3fs does when none are configured
+        if self.get("env_auth") != Some("true") {
+            for key in ["access_key_id", "secret_access_key"] {
+                if let Some(v) = self.get(key) {
+                    config[key] = json!(v);
+                }
+            }
+        }
+        if let Some(region) = self.get("region") {
+            config["region"] = json!(region);
+        }
+        if let Some(endpoint) = self.get("endpoint") {
+            config["endpoint"] = json!(endpoint);
+            if endpoint.starts_with("http://") {
+                config["disable_ssl"] = Value::Bool(true);
+            }
+        }
+        Ok(Mount {
+            plugin: "s3fs",
+            config: Config::from(config),
+        })
+    }
+}
+
+/// The mount for an rclone path `remote:path`
+pub fn resolve(remotes: &[Remote], spec: &str) -> Result<Mount> {
+    let (name, path) = spec
+        .split_once(':')
+        .ok_or_else(|| Error::InvalidInput(format!("not an rclone path: {}", spec)))?;
+    let remote = remotes
+        .iter()
+        .find(|r| r.name == name)
+        .ok_or(Error::NotFound)?;
+    remote.to_mount(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = "
+# storage
+[minio]
+type = s3
+provider = Minio
+access_key_id = AKID
+secret_access_key = s3cr3t
+endpoint = http://127.0.0.1:9000
+region =
+
+[aws]
+type = s3
+env_auth = true
+access_key_id = ignored
+region = eu-west-1
+
+[box]
+type = sftp
+host = example.com
+";
+
+    #[test]
+    fn test_resolve() {
+        let remotes = parse(CONF).unwrap();
+        assert_eq!(remotes.len(), 3);
+        assert_eq!(remotes[0].get("provider"), Some("Minio"));
+
+        let mount = resolve(&remotes, "minio:photos/2024/").unwrap();
+        assert_eq!(mount.plugin, "s3fs");
+        let c = &mount.config;
+        assert_eq!(
+            (c.get_str("bucket"), c.get_str("prefix")),
+            (Some("photos"), Some("2024"))
+        );
+        assert_eq!(c.get_str("access_key_id"), Some("AKID"));
+        assert_eq!(c.get_bool("disable_ssl"), Some(true));
+        assert!(!c.contains("region"));
+
+        let c = resolve(&remotes, "aws:logs").unwrap().config;
+        assert_eq!(c.get_str("region"), Some("eu-west-1"));
+        assert!(!c.contains("access_key_id") && !c.contains("prefix"));
+
+        assert!(resolve(&remotes, "aws:").is_err());
+        assert!(resolve(&remotes, "box:/home").is_err());
+        assert_eq!(resolve(&remotes, "nope:x").err(), Some(Error::NotFound));
+
+        assert!(parse("[a]\nprovider = x\n").is_err());
+        assert!(parse("type = s3\n").is_err());
+        assert!(parse("[a]\ntype = s3\n[a]\ntype = s3\n").is_err());
+        assert!(parse("RCLONE_ENCRYPT_V0:\nabc").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Blocking `Read + Seek` over a plugin file
+//!
+//! Lets plugin code hand another filesystem's file to libraries that expect
+//! `std::io` streams, e.g. an archive parser:
+//!
+//! ```ignore
+//! let mut file = self.backing.open_read("/data/archive.zip")?;
+//! let mut header = [0u8; 4];
+//! file.read_exact(&mut header)?;
+//! file.seek(SeekFrom::End(-22))?;
+//! ```
+//!
+//! Every refill is one ranged `read` call, so reads stay bounded no matter
+//! how large the file is.
+
+use crate::filesystem::FileSystem;
+use crate::types::{Error, Result};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Bytes fetched per `read` call unless set with `with_chunk_size`
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Buffered reader over one file of a `FileSystem`
+///
+/// End of file is where the plugin's `read` returns no data; the size from
+/// `stat` is only used to resolve `SeekFrom::End`.
+pub struct FileReader<'a, F: ?Sized> {
+    fs: &'a F,
+    path: String,
+    size: i64,
+    chunk: usize,
+    // File offset of buf[0]
+    buf_start: u64,
+    buf: Vec<u8>,
+    pos: u64,
+}
+
+impl<'a, F: FileSystem + ?Sized> FileReader<'a, F> {
+    /// Open `path`, which must be a file
+    pub fn open(fs: &'a F, path: &str) -> Result<Self> {
+        let info = fs.stat(path)?;
+        if info.is_dir {
+            return Err(Error::IsDirectory);
+        }
+        Ok(Self {
+            fs,
+            path: path.to_string(),
+            size: info.size,
+            chunk: DEFAULT_CHUNK_SIZE,
+            buf_start: 0,
+            buf: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    /// Fetch `size` bytes per `read` call instead of the default
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk = size.max(1);
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Current offset in the file
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    // Buffered bytes at the current position
+    fn buffered(&self) -> &[u8] {
+        let end = self.buf_start + self.buf.len() as u64;
+        if self.pos < self.buf_start || self.pos >= end {
+            return &[];
+        }
+        &self.buf[(self.pos - self.buf_start) as usize..]
+    }
+
+    fn fetch(&self, len: usize) -> io::Result<Vec<u8>> {
+        let offset = i64::try_from(self.pos)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset too large"))?;
+        Ok(self.fs.read(&self.path, offset, len as i64)?)
+    }
+}
+
+impl<F: FileSystem + ?Sized> Read for FileReader<'_, F> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        if self.buffered().is_empty() {
+            // Large reads skip the buffer
+            if out.len() >= self.chunk {
+                let data = self.fetch(out.len())?;
+                let n = data.len().min(out.len());
+                out[..n].copy_from_slice(&data[..n]);
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            self.buf = self.fetch(self.chunk)?;
+            self.buf_start = self.pos;
+        }
+        let available = self.buffered();
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<F: FileSystem + ?Sized> Seek for FileReader<'_, F> {
+    // Seeking within the buffer keeps it
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match to {
+            SeekFrom::Start(offset) => (0, offset as i128),
+            SeekFrom::Current(delta) => (self.pos as i128, delta as i128),
+            SeekFrom::End(delta) => (self.size.max(0) as i128, delta as i128),
+        };
+        let pos = u64::try_from(base + delta).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of file")
+        })?;
+        self.pos = pos;
+        Ok(pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::slice_range;
+    use crate::types::FileInfo;
+    use std::cell::Cell;
+
+    struct CountingFS {
+        data: Vec<u8>,
+        reads: Cell<usize>,
+    }
+
+    impl FileSystem for CountingFS {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            if path != "/f" {
+                return Err(Error::NotFound);
+            }
+            self.reads.set(self.reads.get() + 1);
+            Ok(slice_range(&self.data, offset, size).to_vec())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                "/f" => Ok(FileInfo::file("f", self.data.len() as i64, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_read_and_seek() {
+        let fs = CountingFS {
+            data: (0..=255u8).cycle().take(1000).collect(),
+            reads: Cell::new(0),
+        };
+        assert_eq!(fs.open_read("/").err(), Some(Error::IsDirectory));
+        assert_eq!(fs.open_read("/x").err(), Some(Error::NotFound));
+
+        let mut r = fs.open_read("/f").unwrap().with_chunk_size(100);
+        let mut small = [0u8; 10];
+        r.read_exact(&mut small).unwrap();
+        r.read_exact(&mut small).unwrap();
+        assert_eq!(small[0], 10);
+        assert_eq!(fs.reads.get(), 1);
+
+        // Seeking inside the buffer does not read again
+        r.seek(SeekFrom::Current(-15)).unwrap();
+        r.read_exact(&mut small[..1]).unwrap();
+        assert_eq!((small[0], fs.reads.get()), (5, 1));
+
+        assert_eq!(r.seek(SeekFrom::End(-4)).unwrap(), 996);
+        let mut tail = Vec::new();
+        r.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, [228, 229, 230, 231]);
+        assert!(r.seek(SeekFrom::Current(-2000)).is_err());
+
+        // Large reads bypass the buffer
+        let mut all = vec![0u8; 1000];
+        r.seek(SeekFrom::Start(0)).unwrap();
+        r.read_exact(&mut all).unwrap();
+        assert_eq!(all, fs.data);
+
+        let err: io::Error = Error::NotFound.into();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,580 @@
+//! Minimal S3 API over a `FileSystem` (feature `s3-gateway`)
+//!
+//! Serves one bucket whose object keys are the filesystem's paths, so S3
+//! tooling can talk to a plugin directly during development:
+//!
+//! ```ignore
+//! let listener = std::net::TcpListener::bind("127.0.0.1:9000")?;
+//! s3::serve(listener, Gateway::new(MyFS::default(), "dev"))?;
+//! // aws --endpoint-url http://127.0.0.1:9000 s3 ls s3://dev/
+//! ```
+//!
+//! Supported: ListBuckets, ListObjectsV2, HeadObject, GetObject (with
+//! ranges), PutObject, DeleteObject and multipart uploads. Only path-style
+//! addressing works. Requests are not authenticated and signatures are
+//! ignored; do not expose the gateway beyond a trusted host. Streaming
+//! signed uploads (`aws-chunked`) are refused, so clients must send plain
+//! payloads.
+//!
+//! Keys map to paths one to one (`a/b.txt` is `/a/b.txt`). Putting an
+//! object creates its parent directories; empty directories do not show up
+//! in listings, as in S3.
+
+use crate::checksum::xxh64;
+use crate::filesystem::FileSystem;
+use crate::http::{self, escape, http_date, iso8601, parse_range, Request, Response};
+use crate::path;
+use crate::types::{Error, FileInfo, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::TcpListener;
+
+// Namespace of S3 response documents
+const S3_XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+/// Keys returned per ListObjectsV2 page unless the client asks for fewer
+pub const MAX_KEYS: usize = 1000;
+
+fn etag(data: &[u8]) -> String {
+    format!("\"{:016x}\"", xxh64(data, 0))
+}
+
+fn error(status: u16, code: &str, message: &str) -> Response {
+    Response::xml(
+        status,
+        format!(
+            "<Error><Code>{}</Code><Message>{}</Message></Error>",
+            code,
+            escape(message)
+        ),
+    )
+}
+
+fn from_error(e: &Error) -> Response {
+    let (status, code) = match e {
+        Error::NotFound => (404, "NoSuchKey"),
+        Error::PermissionDenied | Error::ReadOnly => (403, "AccessDenied"),
+        Error::TooLarge => (400, "EntityTooLarge"),
+        Error::InvalidInput(_) | Error::IsDirectory | Error::NotDirectory => {
+            (400, "InvalidArgument")
+        }
+        Error::AlreadyExists | Error::NotEmpty => (409, "OperationAborted"),
+        Error::Timeout | Error::Cancelled => (503, "SlowDown"),
+        Error::NotSupported(_) => (501, "NotImplemented"),
+        Error::Io(_) | Error::Other(_) => (500, "InternalError"),
+    };
+    error(status, code, &e.to_string())
+}
+
+struct Upload {
+    key: String,
+    parts: BTreeMap<u32, Vec<u8>>,
+}
+
+/// S3 request handler serving one bucket from a filesystem
+pub struct Gateway<F> {
+    fs: F,
+    bucket: String,
+    uploads: HashMap<String, Upload>,
+    next_upload: u64,
+}
+
+impl<F> Gateway<F> {
+    pub fn new(fs: F, bucket: impl Into<String>) -> Self {
+        Self {
+            fs,
+            bucket: bucket.into(),
+            uploads: HashMap::new(),
+            next_upload: 0,
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fs
+    }
+
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.fs
+    }
+}
+
+fn key_path(key: &str) -> Result<String> {
+    path::canonicalize(&format!("/{}", key))
+}
+
+impl<F: FileSystem> Gateway<F> {
+    /// Answer one request
+    pub fn handle(&mut self, req: &Request) -> Response {
+        let rest = req.path.trim_start_matches('/');
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return match req.method.as_str() {
+                "GET" => self.list_buckets(),
+                _ => error(405, "MethodNotAllowed", "unsupported method"),
+            };
+        }
+        if bucket != self.bucket {
+            return error(404, "NoSuchBucket", bucket);
+        }
+        if req
+            .header("x-amz-content-sha256")
+            .is_some_and(|v| v.starts_with("STREAMING-"))
+        {
+            return error(501, "NotImplemented", "aws-chunked uploads");
+        }
+        let result = match (req.method.as_str(), key.is_empty()) {
+            ("GET" | "HEAD", true) => Ok(self.list_objects(req)),
+            ("HEAD", false) => self.get_object(key, req, false),
+            ("GET", false) => self.get_object(key, req, true),
+            ("PUT", false) => match (req.query("uploadId"), req.query("partNumber")) {
+                (Some(id), Some(n)) => Ok(self.upload_part(id, n, &req.body)),
+                _ if req.header("x-amz-copy-source").is_some() => {
+                    return error(501, "NotImplemented", "CopyObject")
+                }
+                _ => self.put_object(key, &req.body),
+            },
+            ("POST", false) if req.query("uploads").is_some() => Ok(self.initiate(key)),
+            ("POST", false) => match req.query("uploadId") {
+                Some(id) => self.complete(key, id),
+                None => return error(400, "InvalidRequest", "missing uploadId"),
+            },
+            ("DELETE", false) => match req.query("uploadId") {
+                Some(id) => Ok(self.abort(id)),
+                None => self.delete_object(key),
+            },
+            _ => return error(405, "MethodNotAllowed", "unsupported method"),
+        };
+        result.unwrap_or_else(|e| from_error(&e))
+    }
+
+    fn list_buckets(&self) -> Response {
+        Response::xml(
+            200,
+            format!(
+                "<ListAllMyBucketsResult xmlns=\"{}\"><Owner><ID>agfs</ID></Owner><Buckets>\
+                 <Bucket><Name>{}</Name><CreationDate>{}</CreationDate></Bucket>\
+                 </Buckets></ListAllMyBucketsResult>",
+                S3_XMLNS,
+                escape(&self.bucket),
+                iso8601(0)
+            ),
+        )
+    }
+
+    // Every file below `dir` as (key, info), in key order
+    fn walk(&self, dir: &str, out: &mut BTreeMap<String, FileInfo>) -> Result<()> {
+        for info in self.fs.readdir(dir)? {
+            let child = if dir == "/" {
+                format!("/{}", info.name)
+            } else {
+                format!("{}/{}", dir, info.name)
+            };
+            if info.is_dir {
+                self.walk(&child, out)?;
+            } else {
+                out.insert(child[1..].to_string(), info);
+            }
+        }
+        Ok(())
+    }
+
+    fn list_objects(&self, req: &Request) -> Response {
+        let prefix = req.query("prefix").unwrap_or("");
+        let delimiter = req.query("delimiter").filter(|d| !d.is_empty());
+        let max_keys = req
+            .query("max-keys")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(MAX_KEYS)
+            .min(MAX_KEYS);
+        let after = req
+            .query("continuation-token")
+            .or(req.query("start-after"))
+            .unwrap_or("");
+
+        // Only walk the deepest directory the prefix names
+        let base = match prefix.rfind('/') {
+            Some(i) => key_path(&prefix[..i]),
+            None => Ok("/".to_string()),
+        };
+        let mut files = BTreeMap::new();
+        match base.and_then(|base| self.walk(&base, &mut files)) {
+            Ok(()) | Err(Error::NotFound) | Err(Error::NotDirectory) => {}
+            Err(e) => return from_error(&e),
+        }
+
+        // Keys and common prefixes, merged in key order
+        let mut entries: BTreeMap<String, Option<FileInfo>> = BTreeMap::new();
+        for (key, info) in files.into_iter().filter(|(k, _)| k.starts_with(prefix)) {
+            match delimiter.and_then(|d| key[prefix.len()..].find(d).map(|i| (d, i))) {
+                Some((d, i)) => {
+                    entries.insert(key[..prefix.len() + i + d.len()].to_string(), None);
+                }
+                None => {
+                    entries.insert(key, Some(info));
+                }
+            }
+        }
+        let mut page = entries
+            .into_iter()
+            .filter(|(k, _)| k.as_str() > after)
+            .peekable();
+        let mut contents = String::new();
+        let mut prefixes = String::new();
+        let mut count = 0;
+        let mut last = String::new();
+        while count < max_keys {
+            let Some((key, info)) = page.next() else {
+                break;
+            };
+            match info {
+                Some(info) => contents.push_str(&format!(
+                    "<Contents><Key>{}</Key><LastModified>{}</LastModified>\
+                     <Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+                    escape(&key),
+                    iso8601(info.mod_time),
+                    info.size
+                )),
+                None => prefixes.push_str(&format!(
+                    "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                    escape(&key)
+                )),
+            }
+            count += 1;
+            last = key;
+        }
+        let truncated = page.peek().is_some();
+        let next = if truncated {
+            format!(
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                escape(&last)
+            )
+        } else {
+            String::new()
+        };
+        Response::xml(
+            200,
+            format!(
+                "<ListBucketResult xmlns=\"{}\"><Name>{}</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount>\
+                 <MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>{}{}{}</ListBucketResult>",
+                S3_XMLNS,
+                escape(&self.bucket),
+                escape(prefix),
+                count,
+                max_keys,
+                truncated,
+                next,
+                contents,
+                prefixes
+            ),
+        )
+    }
+
+    fn get_object(&self, key: &str, req: &Request, body: bool) -> Result<Response> {
+        let path = key_path(key)?;
+        let info = self.fs.stat(&path)?;
+        if info.is_dir {
+            return Err(Error::NotFound);
+        }
+        let (status, offset, size) = match req.header("range") {
+            Some(spec) => match parse_range(spec, info.size) {
+                Some((offset, size)) => (206, offset, size),
+                None => {
+                    return Ok(error(416, "InvalidRange", spec)
+                        .header("Content-Range", format!("bytes */{}", info.size)))
+                }
+            },
+            None => (200, 0, info.size),
+        };
+        let mut resp = Response::new(status)
+            .header("Content-Type", "application/octet-stream")
+            .header("Last-Modified", http_date(info.mod_time))
+            .header("Accept-Ranges", "bytes");
+        if status == 206 {
+            resp = resp.header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", offset, offset + size - 1, info.size),
+            );
+        }
+        if body || status == 206 {
+            let data = self.fs.read(&path, offset, size)?;
+            if status == 200 {
+                resp = resp.header("ETag", etag(&data));
+            }
+            if body {
+                resp.body = data;
+            } else {
+                resp = resp.header("Content-Length", size.to_string());
+            }
+        } else {
+            resp = resp.header("Content-Length", info.size.to_string());
+        }
+        Ok(resp)
+    }
+
+    // mkdir -p for the parents of `path`
+    fn make_parents(&mut self, path: &str) -> Result<()> {
+        let mut dir = String::new();
+        let parents: Vec<&str> = path[1..].split('/').collect();
+        for name in &parents[..parents.len() - 1] {
+            dir.push('/');
+            dir.push_str(name);
+            match self.fs.mkdir(&dir, 0o755) {
+                Ok(()) | Err(Error::AlreadyExists) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn put_object(&mut self, key: &str, data: &[u8]) -> Result<Response> {
+        let path = key_path(key)?;
+        if path == "/" {
+            return Err(Error::InvalidInput("empty key".to_string()));
+        }
+        if key.ends_with('/') {
+            // Folder marker
+            self.make_parents(&format!("{}/_", path))?;
+            return Ok(Response::new(200).header("ETag", etag(&[])));
+        }
+        self.make_parents(&path)?;
+        self.fs.write(&path, data)?;
+        Ok(Response::new(200).header("ETag", etag(data)))
+    }
+
+    fn delete_object(&mut self, key: &str) -> Result<Response> {
+        match self.fs.remove(&key_path(key)?) {
+            Ok(()) | Err(Error::NotFound) => Ok(Response::new(204)),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn initiate(&mut self, key: &str) -> Response {
+        self.next_upload += 1;
+        let id = format!("{:016x}", xxh64(key.as_bytes(), self.next_upload));
+        self.uploads.insert(
+            id.clone(),
+            Upload {
+                key: key.to_string(),
+                parts: BTreeMap::new(),
+            },
+        );
+        Response::xml(
+            200,
+            format!(
+                "<InitiateMultipartUploadResult xmlns=\"{}\"><Bucket>{}</Bucket><Key>{}</Key>\
+                 <UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                S3_XMLNS,
+                escape(&self.bucket),
+                escape(key),
+                id
+            ),
+        )
+    }
+
+    fn upload_part(&mut self, id: &str, number: &str, data: &[u8]) -> Response {
+        let Some(upload) = self.uploads.get_mut(id) else {
+            return error(404, "NoSuchUpload", id);
+        };
+        let Some(number) = number
+            .parse::<u32>()
+            .ok()
+            .filter(|n| (1..=10000).contains(n))
+        else {
+            return error(400, "InvalidArgument", "bad part number");
+        };
+        upload.parts.insert(number, data.to_vec());
+        Response::new(200).header("ETag", etag(data))
+    }
+
+    // Parts listed in the CompleteMultipartUpload body are ignored beyond
+    // their presence: every uploaded part is joined in part-number order
+    fn complete(&mut self, key: &str, id: &str) -> Result<Response> {
+        let Some(upload) = self.uploads.remove(id) else {
+            return Ok(error(404, "NoSuchUpload", id));
+        };
+        if upload.key != key {
+            self.uploads.insert(id.to_string(), upload);
+            return Ok(error(400, "InvalidRequest", "upload is for another key"));
+        }
+        let count = upload.parts.len();
+        let data: Vec<u8> = upload.parts.into_values().flatten().collect();
+        let path = key_path(key)?;
+        self.make_parents(&path)?;
+        self.fs.write(&path, &data)?;
+        let tag = format!("\"{:016x}-{}\"", xxh64(&data, 0), count);
+        Ok(Response::xml(
+            200,
+            format!(
+                "<CompleteMultipartUploadResult xmlns=\"{}\"><Bucket>{}</Bucket><Key>{}</Key>\
+                 <ETag>{}</ETag></CompleteMultipartUploadResult>",
+                S3_XMLNS,
+                escape(&self.bucket),
+                escape(key),
+                escape(&tag)
+            ),
+        ))
+    }
+
+    fn abort(&mut self, id: &str) -> Response {
+        match self.uploads.remove(id) {
+            Some(_) => Response::new(204),
+            None => error(404, "NoSuchUpload", id),
+        }
+    }
+}
+
+/// Serve requests from `listener` until accepting fails
+pub fn serve<F: FileSystem>(listener: TcpListener, mut gateway: Gateway<F>) -> io::Result<()> {
+    http::serve(listener, |req| gateway.handle(req))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::read_request;
+    use crate::range::slice_range;
+
+    // Directories are keys ending in '/'
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(slice_range(data, offset, size).to_vec())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn mkdir(&mut self, path: &str, _perm: u32) -> Result<()> {
+            match self.files.insert(format!("{}/", path), Vec::new()) {
+                Some(_) => Err(Error::AlreadyExists),
+                None => Ok(()),
+            }
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            self.files.remove(path).map(|_| ()).ok_or(Error::NotFound)
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let name = path.rsplit('/').next().unwrap_or("");
+            if path == "/" || self.files.contains_key(&format!("{}/", path)) {
+                return Ok(FileInfo::dir(name, 0o755));
+            }
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(name, data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            let prefix = if path == "/" {
+                "/".to_string()
+            } else {
+                format!("{}/", path)
+            };
+            Ok(self
+                .files
+                .keys()
+                .filter_map(|k| k.strip_prefix(&prefix))
+                .filter(|rest| !rest.is_empty() && !rest.trim_end_matches('/').contains('/'))
+                .map(|rest| {
+                    let name = rest.trim_end_matches('/');
+                    self.stat(&format!("{}{}", prefix, name)).unwrap()
+                })
+                .collect())
+        }
+    }
+
+    fn request(method: &str, target: &str, body: &[u8]) -> Request {
+        let raw = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            method,
+            target,
+            body.len()
+        );
+        let mut input = raw.into_bytes();
+        input.extend_from_slice(body);
+        read_request(&mut &input[..], &mut Vec::new()).unwrap()
+    }
+
+    fn call(gw: &mut Gateway<MemFS>, method: &str, target: &str, body: &[u8]) -> Response {
+        gw.handle(&request(method, target, body))
+    }
+
+    fn text(resp: &Response) -> String {
+        String::from_utf8(resp.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_objects_and_listing() {
+        let mut gw = Gateway::new(MemFS::default(), "dev");
+        assert_eq!(call(&mut gw, "PUT", "/dev/a/b.txt", b"hello").status, 200);
+        assert_eq!(call(&mut gw, "PUT", "/dev/a/c%20d.txt", b"x").status, 200);
+        assert_eq!(call(&mut gw, "PUT", "/dev/top", b"t").status, 200);
+        assert!(gw.inner().files.contains_key("/a/c d.txt"));
+
+        let get = call(&mut gw, "GET", "/dev/a/b.txt", b"");
+        assert_eq!((get.status, get.body.as_slice()), (200, &b"hello"[..]));
+        let mut ranged = request("GET", "/dev/a/b.txt", b"");
+        ranged.headers.push(("range".into(), "bytes=1-3".into()));
+        let part = gw.handle(&ranged);
+        assert_eq!((part.status, part.body.as_slice()), (206, &b"ell"[..]));
+        assert_eq!(call(&mut gw, "GET", "/dev/nope", b"").status, 404);
+        assert_eq!(call(&mut gw, "GET", "/other/a", b"").status, 404);
+        assert_eq!(call(&mut gw, "GET", "/dev/../x", b"").status, 400);
+
+        let all = text(&call(&mut gw, "GET", "/dev?list-type=2", b""));
+        assert!(all.contains("<Key>a/b.txt</Key>") && all.contains("<Key>top</Key>"));
+        assert!(all.contains("<KeyCount>3</KeyCount>"));
+
+        let dirs = text(&call(&mut gw, "GET", "/dev?list-type=2&delimiter=%2F", b""));
+        assert!(dirs.contains("<CommonPrefixes><Prefix>a/</Prefix></CommonPrefixes>"));
+        assert!(!dirs.contains("<Key>a/b.txt</Key>"));
+
+        let page = text(&call(&mut gw, "GET", "/dev?list-type=2&max-keys=1", b""));
+        assert!(page.contains("<IsTruncated>true</IsTruncated>"));
+        assert!(page.contains("<NextContinuationToken>a/b.txt</NextContinuationToken>"));
+        let next = text(&call(
+            &mut gw,
+            "GET",
+            "/dev?list-type=2&prefix=a/&continuation-token=a%2Fb.txt",
+            b"",
+        ));
+        assert!(next.contains("<Key>a/c d.txt</Key>") && !next.contains("b.txt"));
+
+        assert_eq!(call(&mut gw, "DELETE", "/dev/top", b"").status, 204);
+        assert_eq!(call(&mut gw, "DELETE", "/dev/top", b"").status, 204);
+        assert!(!gw.inner().files.contains_key("/top"));
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let mut gw = Gateway::new(MemFS::default(), "dev");
+        let init = text(&call(&mut gw, "POST", "/dev/big?uploads", b""));
+        let id = init
+            .split("<UploadId>")
+            .nth(1)
+            .and_then(|s| s.split("</UploadId>").next())
+            .unwrap()
+            .to_string();
+        let part = |n: u32| format!("/dev/big?partNumber={}&uploadId={}", n, id);
+        assert_eq!(call(&mut gw, "PUT", &part(2), b"world").status, 200);
+        assert_eq!(call(&mut gw, "PUT", &part(1), b"hello ").status, 200);
+        assert_eq!(call(&mut gw, "PUT", &part(0), b"x").status, 400);
+
+        let done = call(&mut gw, "POST", &format!("/dev/big?uploadId={}", id), b"");
+        assert_eq!(done.status, 200);
+        assert_eq!(gw.inner().files["/big"], b"hello world");
+        let again = call(&mut gw, "POST", &format!("/dev/big?uploadId={}", id), b"");
+        assert_eq!(again.status, 404);
+    }
+}
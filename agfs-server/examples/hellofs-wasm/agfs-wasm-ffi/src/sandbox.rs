@@ -0,0 +1,275 @@
+//! Symlink-safe host filesystem access
+//!
+//! `path::join` keeps a request path lexically below a host prefix, but a
+//! symlink under the prefix can still point anywhere on the host.
+//! `SafeHostFS` resolves paths with `HostFS::realpath` and refuses every
+//! operation whose target would land outside the sandbox root:
+//!
+//! ```ignore
+//! let sandbox = SafeHostFS::new("/local/data");
+//! let data = sandbox.read("/reports/q3.csv", 0, -1)?;
+//! ```
+//!
+//! Calls that follow symlinks (read, stat, readdir, chmod) check the fully
+//! resolved target. Calls that act on the link itself (remove, rename)
+//! check the resolved parent directory. Calls that may create the target
+//! (write, write_atomic, create, create_exclusive, mkdir) check the parent
+//! and, if the target is already a symlink, where it points. The host is
+//! still free to change a link between the check and the operation; this
+//! guards against links that are already in place, not against a
+//! concurrent attacker on the host.
+//!
+//! Requires a host that exports `host_fs_realpath` and `host_fs_lstat`.
+
+use crate::host_fs::HostFS;
+use crate::path;
+use crate::types::{CreateFlags, Error, FileInfo, LockKind, RenameFlags, Result};
+use std::sync::OnceLock;
+
+/// Host filesystem access confined to `root`, symlinks included
+pub struct SafeHostFS {
+    root: String,
+    // realpath of the root, resolved on first use
+    resolved_root: OnceLock<String>,
+}
+
+impl SafeHostFS {
+    /// Confine access to the host directory `root`
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: root.trim_end_matches('/').to_string(),
+            resolved_root: OnceLock::new(),
+        }
+    }
+
+    /// The host directory everything is confined to
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    fn resolved_root(&self) -> Result<&str> {
+        if let Some(root) = self.resolved_root.get() {
+            return Ok(root);
+        }
+        let root = HostFS::realpath(if self.root.is_empty() { "/" } else { &self.root })?;
+        Ok(self.resolved_root.get_or_init(|| root))
+    }
+
+    // Fail unless `host` resolves to somewhere under the root
+    fn check_resolved(&self, host: &str) -> Result<()> {
+        let real = HostFS::realpath(host)?;
+        if !is_within(self.resolved_root()?, &real) {
+            return Err(Error::PermissionDenied);
+        }
+        Ok(())
+    }
+
+    // Map a request path below the root and check where it really points
+    fn target(&self, path: &str) -> Result<String> {
+        let host = path::join(&self.root, path)?;
+        self.check_resolved(&host)?;
+        Ok(host)
+    }
+
+    // For calls on the entry itself: only its directory must resolve inside
+    fn entry(&self, path: &str) -> Result<String> {
+        let host = path::join(&self.root, path)?;
+        self.check_resolved(parent_of(&host))?;
+        Ok(host)
+    }
+
+    // For calls that may create the target: its directory must resolve
+    // inside, and an existing symlink must point inside (dangling ones are
+    // refused, since following them would create a file wherever they point)
+    fn new_target(&self, path: &str) -> Result<String> {
+        let host = self.entry(path)?;
+        match HostFS::stat_no_follow(&host) {
+            Ok(info) if info.is_symlink() => match self.check_resolved(&host) {
+                Err(Error::NotFound) => Err(Error::PermissionDenied),
+                other => other.map(|_| host),
+            },
+            Ok(_) | Err(Error::NotFound) => Ok(host),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read `size` bytes at `offset` (negative size reads to the end)
+    pub fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        HostFS::read(&self.target(path)?, offset, size)
+    }
+
+    /// Write a file, creating it if needed
+    pub fn write(&self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        HostFS::write(&self.new_target(path)?, data)
+    }
+
+    /// Add `data` to the end of a file, creating it if needed
+    pub fn append(&self, path: &str, data: &[u8]) -> Result<()> {
+        HostFS::append(&self.new_target(path)?, data)
+    }
+
+    /// Write `data` at `offset` in the resolved target
+    pub fn write_at(&self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        HostFS::write_at(&self.new_target(path)?, offset, data)
+    }
+
+    /// Replace a file via a temp sibling and rename (see
+    /// `HostFS::write_atomic`)
+    pub fn write_atomic(&self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        HostFS::write_atomic(&self.new_target(path)?, data)
+    }
+
+    /// Get file information
+    pub fn stat(&self, path: &str) -> Result<FileInfo> {
+        HostFS::stat(&self.target(path)?)
+    }
+
+    /// Read directory contents
+    pub fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        HostFS::readdir(&self.target(path)?)
+    }
+
+    /// Create an empty file
+    pub fn create(&self, path: &str) -> Result<()> {
+        HostFS::create(&self.new_target(path)?)
+    }
+
+    /// Create a new file, failing with `AlreadyExists` if the path exists
+    pub fn create_exclusive(&self, path: &str) -> Result<()> {
+        HostFS::create_exclusive(&self.new_target(path)?)
+    }
+
+    /// Create a file as `flags` asks
+    pub fn create_with_flags(&self, path: &str, flags: CreateFlags) -> Result<()> {
+        HostFS::create_with_flags(&self.new_target(path)?, flags)
+    }
+
+    /// Create a directory
+    pub fn mkdir(&self, path: &str, perm: u32) -> Result<()> {
+        HostFS::mkdir(&self.new_target(path)?, perm)
+    }
+
+    /// Remove a file, empty directory or symlink
+    pub fn remove(&self, path: &str) -> Result<()> {
+        HostFS::remove(&self.entry(path)?)
+    }
+
+    /// Remove recursively; symlinks inside are removed, not followed
+    pub fn remove_all(&self, path: &str) -> Result<()> {
+        HostFS::remove_all(&self.entry(path)?)
+    }
+
+    /// Rename an entry; a symlink is moved as a link
+    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        HostFS::rename(&self.entry(old_path)?, &self.entry(new_path)?)
+    }
+
+    /// Rename an entry as `flags` asks; a symlink is moved as a link
+    pub fn rename_with_flags(
+        &self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        HostFS::rename_with_flags(&self.entry(old_path)?, &self.entry(new_path)?, flags)
+    }
+
+    /// Change permissions of the resolved target
+    pub fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        HostFS::chmod(&self.target(path)?, mode)
+    }
+
+    /// Change ownership of the resolved target
+    pub fn chown(&self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        HostFS::chown(&self.target(path)?, uid, gid)
+    }
+
+    /// Set the times of the resolved target
+    pub fn set_times(&self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        HostFS::set_times(&self.target(path)?, atime, mtime)
+    }
+
+    /// Truncate the resolved target
+    pub fn truncate(&self, path: &str, size: i64) -> Result<()> {
+        HostFS::truncate(&self.target(path)?, size)
+    }
+
+    /// Reserve space in the resolved target
+    pub fn allocate(&self, path: &str, offset: i64, len: i64) -> Result<()> {
+        HostFS::allocate(&self.target(path)?, offset, len)
+    }
+
+    /// Hard link an entry; like link(2), a symlink is linked, not followed
+    pub fn link(&self, existing: &str, new: &str) -> Result<()> {
+        HostFS::link(&self.entry(existing)?, &self.entry(new)?)
+    }
+
+    /// Create a symlink; the target is not checked here but wherever
+    /// the link is later followed
+    pub fn symlink(&self, target: &str, link_path: &str) -> Result<()> {
+        HostFS::symlink(target, &self.entry(link_path)?)
+    }
+
+    /// Target of a symlink inside the root, as stored
+    pub fn readlink(&self, path: &str) -> Result<String> {
+        HostFS::readlink(&self.entry(path)?)
+    }
+
+    /// Check the resolved target with access(2) on the host
+    pub fn access(&self, path: &str, mode: u32) -> Result<()> {
+        HostFS::access(&self.target(path)?, mode)
+    }
+
+    /// Offset of the first data at or after `offset`
+    pub fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        HostFS::seek_data(&self.target(path)?, offset)
+    }
+
+    /// Offset of the first hole at or after `offset`
+    pub fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        HostFS::seek_hole(&self.target(path)?, offset)
+    }
+
+    /// Take an advisory lock on the resolved target
+    pub fn lock(&self, path: &str, kind: LockKind) -> Result<()> {
+        HostFS::lock(&self.target(path)?, kind)
+    }
+
+    /// Release a lock on the resolved target
+    pub fn unlock(&self, path: &str) -> Result<()> {
+        HostFS::unlock(&self.target(path)?)
+    }
+}
+
+// Whether the resolved `path` is `root` or lies below it
+fn is_within(root: &str, path: &str) -> bool {
+    let root = root.trim_end_matches('/');
+    match path.strip_prefix(root) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+// Parent directory of a canonical host path
+fn parent_of(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("/data", "/data"));
+        assert!(is_within("/data/", "/data/a/b"));
+        assert!(!is_within("/data", "/database"));
+        assert!(!is_within("/data", "/etc/passwd"));
+        assert!(is_within("/", "/etc/passwd"));
+        assert_eq!(parent_of("/data/a"), "/data");
+        assert_eq!(parent_of("/data"), "/");
+    }
+}
@@ -0,0 +1,192 @@
+//! A struct exposed as an editable JSON file
+//!
+//! `SerdeFile` keeps a value and serves it as pretty-printed JSON. Writes
+//! are parsed, checked by an optional validator and only then replace the
+//! value, so plugins can expose settings and state as files:
+//!
+//! ```ignore
+//! struct MyFS {
+//!     settings: SerdeFile<Settings>,
+//! }
+//!
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     match path {
+//!         "/settings.json" => self.settings.read(offset, size),
+//!         _ => Err(Error::NotFound),
+//!     }
+//! }
+//!
+//! fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+//!     match path {
+//!         "/settings.json" => self.settings.write(data),
+//!         _ => Err(Error::NotFound),
+//!     }
+//! }
+//! ```
+//!
+//! A write is applied as a JSON merge patch (RFC 7386) to the current
+//! value: `{"ttl": 30}` changes one field and leaves the rest alone, while
+//! a complete document replaces everything.
+
+use crate::range::slice_range;
+use crate::types::{Error, FileInfo, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+type Validator<T> = Box<dyn Fn(&T) -> Result<()> + Send + Sync>;
+
+/// A value served as a JSON file
+pub struct SerdeFile<T> {
+    value: T,
+    validator: Option<Validator<T>>,
+}
+
+impl<T: Default> Default for SerdeFile<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> SerdeFile<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            validator: None,
+        }
+    }
+
+    /// Reject writes whose result `validate` refuses
+    pub fn with_validator(
+        mut self,
+        validate: impl Fn(&T) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Box::new(validate));
+        self
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Replace the value directly, bypassing the validator
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+    }
+}
+
+// RFC 7386: objects merge key by key, null removes, anything else replaces
+fn merge_patch(target: &mut Value, patch: Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            let Value::Object(target) = target else {
+                unreachable!()
+            };
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    merge_patch(target.entry(key).or_insert(Value::Null), value);
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> SerdeFile<T> {
+    /// The file's contents: the value as pretty JSON with a final newline
+    pub fn render(&self) -> Result<Vec<u8>> {
+        let mut data = serde_json::to_vec_pretty(&self.value)
+            .map_err(|e| Error::Other(format!("cannot render value: {}", e)))?;
+        data.push(b'\n');
+        Ok(data)
+    }
+
+    pub fn read(&self, offset: i64, size: i64) -> Result<Vec<u8>> {
+        Ok(slice_range(&self.render()?, offset, size).to_vec())
+    }
+
+    /// Apply `data` as a merge patch
+    ///
+    /// Fails with `InvalidInput` if it is not JSON, the result does not
+    /// deserialize into `T`, or the validator rejects it; the value is
+    /// unchanged then. Empty writes (truncation before a rewrite) are
+    /// ignored. Returns no response data.
+    pub fn write(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Vec::new());
+        }
+        let patch: Value = serde_json::from_slice(data)
+            .map_err(|e| Error::InvalidInput(format!("invalid JSON: {}", e)))?;
+        let mut merged = serde_json::to_value(&self.value)
+            .map_err(|e| Error::Other(format!("cannot render value: {}", e)))?;
+        merge_patch(&mut merged, patch);
+        let value: T =
+            serde_json::from_value(merged).map_err(|e| Error::InvalidInput(e.to_string()))?;
+        if let Some(validate) = &self.validator {
+            validate(&value)?;
+        }
+        self.value = value;
+        Ok(Vec::new())
+    }
+
+    /// A `FileInfo` for the file, sized to the current rendering
+    pub fn info(&self, name: &str, mode: u32) -> Result<FileInfo> {
+        Ok(FileInfo::file(name, self.render()?.len() as i64, mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        ttl: u32,
+        name: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn test_patch_and_validate() {
+        let mut file = SerdeFile::new(Settings {
+            ttl: 10,
+            name: "a".to_string(),
+            tags: vec!["x".to_string()],
+        })
+        .with_validator(|s: &Settings| {
+            if s.ttl == 0 {
+                return Err(Error::InvalidInput("ttl must be positive".to_string()));
+            }
+            Ok(())
+        });
+
+        let text = String::from_utf8(file.read(0, -1).unwrap()).unwrap();
+        assert!(text.contains("\"ttl\": 10") && text.ends_with("}\n"));
+        assert_eq!(file.info("s.json", 0o644).unwrap().size, text.len() as i64);
+
+        file.write(br#"{"ttl": 30}"#).unwrap();
+        assert_eq!((file.get().ttl, file.get().name.as_str()), (30, "a"));
+        file.write(br#"{"tags": null}"#).unwrap();
+        assert!(file.get().tags.is_empty());
+        file.write(b"\n").unwrap();
+
+        let before = file.get().clone();
+        assert!(file.write(br#"{"ttl": 0}"#).is_err());
+        assert!(file.write(br#"{"ttl": "soon"}"#).is_err());
+        assert!(file.write(br#"{"name": null}"#).is_err());
+        assert!(file.write(b"ttl=5").is_err());
+        assert_eq!(file.get(), &before);
+
+        // Writing back what was read is a no-op
+        let current = file.read(0, -1).unwrap();
+        file.write(&current).unwrap();
+        assert_eq!(file.get(), &before);
+    }
+}
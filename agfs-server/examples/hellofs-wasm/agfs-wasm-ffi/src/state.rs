@@ -0,0 +1,100 @@
+//! Persistent plugin state
+//!
+//! Plugins that keep their data in memory lose it when the server
+//! restarts. They implement `FileSystem::snapshot` and `restore`, and the
+//! server saves a snapshot through the `plugin_snapshot` export before
+//! unloading and hands it back through `plugin_restore` after the next
+//! initialize.
+//!
+//! A plugin may also keep its state in the host's store itself, e.g. after
+//! every change, which survives crashes as well:
+//!
+//! ```ignore
+//! fn initialize(&mut self, _config: &Config) -> Result<()> {
+//!     match state::load()? {
+//!         Some(data) => self.restore(&data),
+//!         None => Ok(()),
+//!     }
+//! }
+//!
+//! fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+//!     // ...
+//!     state::save(&self.snapshot()?)?;
+//! }
+//! ```
+//!
+//! The store holds one blob per mount. Native builds have no host store
+//! and keep it in process memory.
+
+use crate::types::{Error, Result};
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_state_save(data: *const u8, len: u32) -> u32;
+    fn host_state_load() -> u64;
+}
+
+/// Replace the mount's stored state with `data`
+#[cfg(target_arch = "wasm32")]
+pub fn save(data: &[u8]) -> Result<()> {
+    unsafe {
+        let err_ptr = host_state_save(data.as_ptr(), data.len() as u32);
+        if err_ptr != 0 {
+            let msg = crate::memory::CString::from_ptr(err_ptr as *const u8)?;
+            return Err(Error::from_host(&msg));
+        }
+    }
+    Ok(())
+}
+
+/// The mount's stored state, if any was saved
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> Result<Option<Vec<u8>>> {
+    let result = unsafe { host_state_load() };
+    let ptr = (result & 0xFFFFFFFF) as u32;
+    let len = ((result >> 32) & 0xFFFFFFFF) as u32;
+    // (0, error string) on failure, (0, 0) when nothing was saved
+    if ptr == 0 {
+        if len == 0 {
+            return Ok(None);
+        }
+        let msg = unsafe { crate::memory::CString::from_ptr(len as *const u8)? };
+        return Err(Error::from_host(&msg));
+    }
+    let data = unsafe { crate::memory::borrow_slice(ptr as *const u8, len as usize)? };
+    Ok(Some(data.to_vec()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static STORE: std::sync::Mutex<Option<Vec<u8>>> = std::sync::Mutex::new(None);
+
+/// Replace the mount's stored state with `data`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(data: &[u8]) -> Result<()> {
+    *STORE.lock().unwrap() = Some(data.to_vec());
+    Ok(())
+}
+
+/// The mount's stored state, if any was saved
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> Result<Option<Vec<u8>>> {
+    Ok(STORE.lock().unwrap().clone())
+}
+
+/// Error for a snapshot a plugin cannot restore
+pub fn bad_snapshot(why: &str) -> Error {
+    Error::InvalidInput(format!("invalid snapshot: {}", why))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        save(b"v1").unwrap();
+        save(b"v2").unwrap();
+        assert_eq!(load().unwrap().as_deref(), Some(&b"v2"[..]));
+    }
+}
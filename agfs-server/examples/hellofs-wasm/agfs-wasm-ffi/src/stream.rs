@@ -0,0 +1,168 @@
+//! Streamed directory listings
+//!
+//! `fs_readdir` returns a whole directory as one JSON array, which for
+//! huge directories has to fit in linear memory twice. A plugin can
+//! instead produce entries as the host asks for them by overriding
+//! `FileSystem::readdir_stream`:
+//!
+//! ```ignore
+//! fn readdir_stream(&self, path: &str) -> Result<ReaddirStream> {
+//!     let keys = self.store.clone().scan(path);
+//!     Ok(ReaddirStream::new(keys.map(|key| Ok(FileInfo::file(key, 0, 0o644)))))
+//! }
+//! ```
+//!
+//! The host opens a stream with `fs_readdir_open`, pulls batches with
+//! `fs_readdir_next(cursor, max)` until one comes back empty, and may
+//! drop it early with `fs_readdir_close`. The stream outlives the call
+//! that opened it, so it cannot borrow from the plugin.
+
+use crate::types::{Error, FileInfo, Result};
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::sync::Mutex;
+
+/// Streams open at once; opening more fails until some are closed
+pub const MAX_OPEN_STREAMS: usize = 256;
+
+type Entries = Box<dyn Iterator<Item = Result<FileInfo>> + Send>;
+
+/// Entries of one directory, produced one at a time
+pub struct ReaddirStream {
+    entries: Peekable<Entries>,
+}
+
+impl ReaddirStream {
+    /// Stream the entries `entries` yields
+    pub fn new(entries: impl Iterator<Item = Result<FileInfo>> + Send + 'static) -> Self {
+        Self {
+            entries: (Box::new(entries) as Entries).peekable(),
+        }
+    }
+
+    /// Stream an already listed directory
+    pub fn from_entries(entries: Vec<FileInfo>) -> Self {
+        Self::new(entries.into_iter().map(Ok))
+    }
+
+    /// Add `entry` after the last entry
+    pub fn chain(self, entry: FileInfo) -> Self {
+        Self::new(self.entries.chain(std::iter::once(Ok(entry))))
+    }
+}
+
+impl Iterator for ReaddirStream {
+    type Item = Result<FileInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+}
+
+// Open streams by the cursor the host holds; 0 is never handed out
+struct Streams {
+    next: u32,
+    open: BTreeMap<u32, ReaddirStream>,
+}
+
+static STREAMS: Mutex<Streams> = Mutex::new(Streams {
+    next: 1,
+    open: BTreeMap::new(),
+});
+
+fn unknown_cursor() -> Error {
+    Error::InvalidInput("unknown readdir cursor".to_string())
+}
+
+/// Keep `stream` for the host to pull from, returning its cursor
+pub fn open(stream: ReaddirStream) -> Result<u32> {
+    let mut streams = STREAMS.lock().unwrap();
+    if streams.open.len() >= MAX_OPEN_STREAMS {
+        return Err(Error::TooLarge);
+    }
+    while streams.next == 0 || streams.open.contains_key(&streams.next) {
+        streams.next = streams.next.wrapping_add(1);
+    }
+    let cursor = streams.next;
+    streams.next = streams.next.wrapping_add(1);
+    streams.open.insert(cursor, stream);
+    Ok(cursor)
+}
+
+/// Up to `max` further entries of the stream at `cursor`
+///
+/// An empty batch ends the stream and releases the cursor, as does an
+/// error.
+pub fn next(cursor: u32, max: usize) -> Result<Vec<FileInfo>> {
+    // Taken out so that a slow plugin iterator does not hold the lock
+    let mut stream = STREAMS
+        .lock()
+        .unwrap()
+        .open
+        .remove(&cursor)
+        .ok_or_else(unknown_cursor)?;
+    let batch = stream.by_ref().take(max).collect::<Result<Vec<_>>>()?;
+    if !batch.is_empty() {
+        STREAMS.lock().unwrap().open.insert(cursor, stream);
+    }
+    Ok(batch)
+}
+
+/// Up to `max` further entries of the stream at `cursor`, and whether any
+/// remain
+///
+/// Unlike `next`, the cursor is released with the last entry, so a caller
+/// that stops once nothing remains leaves nothing open.
+pub fn page(cursor: u32, max: usize) -> Result<(Vec<FileInfo>, bool)> {
+    let mut stream = STREAMS
+        .lock()
+        .unwrap()
+        .open
+        .remove(&cursor)
+        .ok_or_else(unknown_cursor)?;
+    let batch = stream.by_ref().take(max).collect::<Result<Vec<_>>>()?;
+    let more = stream.entries.peek().is_some();
+    if more {
+        STREAMS.lock().unwrap().open.insert(cursor, stream);
+    }
+    Ok((batch, more))
+}
+
+/// Drop the stream at `cursor` before its end
+pub fn close(cursor: u32) -> Result<()> {
+    let stream = STREAMS.lock().unwrap().open.remove(&cursor);
+    stream.map(drop).ok_or_else(unknown_cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_batches() {
+        let entries = (0..5)
+            .map(|i| FileInfo::file(format!("f{}", i), 0, 0o644))
+            .collect();
+        let cursor = open(ReaddirStream::from_entries(entries)).unwrap();
+        assert_eq!(next(cursor, 2).unwrap().len(), 2);
+        let names: Vec<_> = next(cursor, 10)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["f2", "f3", "f4"]);
+        assert!(next(cursor, 10).unwrap().is_empty());
+        assert_eq!(next(cursor, 10).unwrap_err(), unknown_cursor());
+
+        let entries = (0..3).map(|i| FileInfo::file(format!("f{}", i), 0, 0o644)).collect();
+        let cursor = open(ReaddirStream::from_entries(entries)).unwrap();
+        assert_eq!(page(cursor, 2).map(|(e, more)| (e.len(), more)), Ok((2, true)));
+        assert_eq!(page(cursor, 2).map(|(e, more)| (e.len(), more)), Ok((1, false)));
+        assert_eq!(page(cursor, 2).unwrap_err(), unknown_cursor());
+
+        let failing = ReaddirStream::new(std::iter::once(Err(Error::NotFound)));
+        let cursor = open(failing).unwrap();
+        assert_eq!(next(cursor, 1).unwrap_err(), Error::NotFound);
+        assert_eq!(close(cursor), Err(unknown_cursor()));
+    }
+}
@@ -0,0 +1,202 @@
+//! Clocks and calendar conversions for Unix timestamps
+//!
+//! Just what the wire formats need: RFC 3339 for `FileInfo`'s `ModTime`
+//! and the civil breakdown the HTTP gateways format dates from. All times
+//! are UTC seconds.
+//!
+//! WASM guests have no clock of their own; `now` and `monotonic_nanos`
+//! ask the host through `host_time_now` and `host_time_monotonic`. Native
+//! builds use `SystemTime` and `Instant`.
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_time_now() -> i64;
+    fn host_time_monotonic() -> u64;
+}
+
+/// `ModTime` of an entry without a modification time, Go's zero time
+pub const ZERO_TIME: &str = "0001-01-01T00:00:00Z";
+
+/// Current Unix time in nanoseconds
+#[cfg(target_arch = "wasm32")]
+pub fn now_nanos() -> i64 {
+    unsafe { host_time_now() }
+}
+
+/// Current Unix time in nanoseconds
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_nanos() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as i64)
+}
+
+/// Current Unix time in seconds
+pub fn now() -> i64 {
+    now_nanos().div_euclid(1_000_000_000)
+}
+
+/// Nanoseconds since an arbitrary fixed point, never going backwards
+///
+/// For timeouts and TTLs; unlike `now` it does not jump when the host's
+/// clock is set.
+#[cfg(target_arch = "wasm32")]
+pub fn monotonic_nanos() -> u64 {
+    unsafe { host_time_monotonic() }
+}
+
+/// Nanoseconds since an arbitrary fixed point, never going backwards
+#[cfg(not(target_arch = "wasm32"))]
+pub fn monotonic_nanos() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+/// (year, month, day, hour, minute, second, weekday with 0 = Sunday)
+pub fn civil(secs: i64) -> (i64, u32, u32, u32, u32, u32, u32) {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400) as u32;
+    let weekday = (days + 4).rem_euclid(7) as u32;
+    // Howard Hinnant's days_from_civil, inverted
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+        weekday,
+    )
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// RFC 3339 form of a timestamp, with 0 as Go's zero time
+pub fn format_rfc3339(secs: i64) -> String {
+    if secs == 0 {
+        return ZERO_TIME.to_string();
+    }
+    let (y, mo, d, h, mi, s, _) = civil(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// Parse an RFC 3339 timestamp as Go formats them
+///
+/// Fractional seconds are dropped. Go's zero time parses as 0.
+pub fn parse_rfc3339(s: &str) -> Option<i64> {
+    if s == ZERO_TIME {
+        return Some(0);
+    }
+    let num = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = s.get(range)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    if b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let (year, month, day) = (num(0..4)?, num(5..7)? as u32, num(8..10)? as u32);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    // Leap seconds fold into the next second
+    if second > 60 {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    if let Some(frac) = rest.strip_prefix('.') {
+        let digits = frac.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &frac[digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (h, m) = rest[1..].split_once(':')?;
+            if h.len() != 2 || m.len() != 2 {
+                return None;
+            }
+            let (h, m): (i64, i64) = (h.parse().ok()?, m.parse().ok()?);
+            sign * (h * 3600 + m * 60)
+        }
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clocks() {
+        assert!(now() > 1_700_000_000);
+        let start = monotonic_nanos();
+        assert!(monotonic_nanos() >= start);
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        for secs in [1, -1, 951782400, 1700000000, 4102444800] {
+            assert_eq!(parse_rfc3339(&format_rfc3339(secs)), Some(secs));
+        }
+        assert_eq!(format_rfc3339(0), ZERO_TIME);
+        assert_eq!(format_rfc3339(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(parse_rfc3339(ZERO_TIME), Some(0));
+        assert_eq!(
+            parse_rfc3339("2023-11-14T23:13:20.123456789+01:00"),
+            Some(1700000000)
+        );
+        assert_eq!(
+            parse_rfc3339("2023-11-14T20:13:20-02:00"),
+            Some(1700000000)
+        );
+        for bad in [
+            "",
+            "2023-11-14",
+            "2023-13-01T00:00:00Z",
+            "2023-11-14T22:13:20",
+            "2023-11-14T22:13:20+1",
+        ] {
+            assert_eq!(parse_rfc3339(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Per-call tracing spans for the export glue
+//!
+//! With `tracing: true` in the mount config, every exported filesystem call
+//! made under a sampled trace becomes a span. The server passes its trace
+//! as a W3C `traceparent` in the call context:
+//!
+//! ```json
+//! {"TraceParent": "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"}
+//! ```
+//!
+//! Spans are children of that parent and carry the operation, path and
+//! latency. By default they are queued in the guest, and after every
+//! traced call the host collects them through the `plugin_trace_drain`
+//! export as an OTLP/JSON `ExportTraceServiceRequest` and exports them
+//! with its own span (`trace_endpoint` in the mount config). Calls without
+//! a sampled parent are not traced.
+//!
+//! Span start times come from `time::now_nanos`, the host's clock in WASM
+//! guests, unless another is installed with `set_wall_clock`.
+
+use crate::checksum::xxh64;
+use crate::context::Context;
+use crate::metrics;
+use crate::types::Result;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Spans kept in the default queue before the oldest are dropped
+pub const TRACE_QUEUE_LIMIT: usize = 1024;
+
+/// A parsed W3C `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl TraceParent {
+    /// Parse `version-traceid-parentid-flags`
+    ///
+    /// All-zero ids are invalid, as in the W3C spec.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().split('-');
+        let (version, trace, span, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version.len() != 2 || version == "ff" {
+            return None;
+        }
+        let trace_id = parse_hex::<16>(trace)?;
+        let span_id = parse_hex::<8>(span)?;
+        let flags = parse_hex::<1>(flags)?[0];
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags & 1 != 0,
+        })
+    }
+}
+
+/// One traced call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    #[serde(rename = "TraceId")]
+    pub trace_id: String,
+    #[serde(rename = "SpanId")]
+    pub span_id: String,
+    #[serde(rename = "ParentSpanId")]
+    pub parent_span_id: String,
+    #[serde(rename = "Op")]
+    pub op: &'static str,
+    #[serde(rename = "Path")]
+    pub path: String,
+    /// Unix time in nanoseconds, 0 without a wall clock
+    #[serde(rename = "StartUnixNanos")]
+    pub start_unix_nanos: u64,
+    /// `None` without a monotonic clock
+    #[serde(rename = "DurationNanos")]
+    pub duration_nanos: Option<u64>,
+    #[serde(rename = "Error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+type Sink = fn(&Span);
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+static QUEUE: Mutex<VecDeque<Span>> = Mutex::new(VecDeque::new());
+static WALL_CLOCK: Mutex<Option<fn() -> u64>> = Mutex::new(None);
+static SPAN_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Send spans to `sink` instead of the default queue
+pub fn set_sink(sink: Sink) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+/// Install a clock returning Unix time in nanoseconds
+pub fn set_wall_clock(clock: fn() -> u64) {
+    *WALL_CLOCK.lock().unwrap() = Some(clock);
+}
+
+fn wall_nanos() -> u64 {
+    match *WALL_CLOCK.lock().unwrap() {
+        Some(clock) => clock(),
+        None => default_wall_clock(),
+    }
+}
+
+fn default_wall_clock() -> u64 {
+    crate::time::now_nanos().max(0) as u64
+}
+
+// Unique within the trace; span ids need not be random, only distinct
+fn next_span_id(parent: &TraceParent) -> [u8; 8] {
+    let seq = SPAN_SEQ.fetch_add(1, Ordering::Relaxed);
+    let id = xxh64(&seq.to_le_bytes(), u64::from_le_bytes(parent.span_id));
+    id.max(1).to_be_bytes()
+}
+
+fn emit(span: Span) {
+    let sink = *SINK.lock().unwrap();
+    match sink {
+        Some(sink) => sink(&span),
+        None => {
+            let mut queue = QUEUE.lock().unwrap();
+            if queue.len() >= TRACE_QUEUE_LIMIT {
+                queue.pop_front();
+            }
+            queue.push_back(span);
+        }
+    }
+}
+
+/// Run `f` as a span of `op` on `path` if the current call is traced
+pub fn in_span<T>(op: &'static str, path: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let parent = Context::current()
+        .trace_parent
+        .as_deref()
+        .and_then(TraceParent::parse)
+        .filter(|p| p.sampled);
+    let Some(parent) = parent else {
+        return f();
+    };
+    let start_wall = wall_nanos();
+    let start = metrics::now_nanos();
+    let result = f();
+    let duration = start.and_then(|s| metrics::now_nanos().map(|now| now.saturating_sub(s)));
+    emit(Span {
+        trace_id: hex(&parent.trace_id),
+        span_id: hex(&next_span_id(&parent)),
+        parent_span_id: hex(&parent.span_id),
+        op,
+        path: path.to_string(),
+        start_unix_nanos: start_wall,
+        duration_nanos: duration,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    result
+}
+
+/// Take all queued spans, oldest first
+pub fn drain() -> Vec<Span> {
+    QUEUE.lock().unwrap().drain(..).collect()
+}
+
+/// Encode spans as an OTLP/JSON `ExportTraceServiceRequest`
+///
+/// `service` becomes the `service.name` resource attribute.
+pub fn otlp_json(service: &str, spans: &[Span]) -> String {
+    let attr = |key: &str, value: &str| json!({"key": key, "value": {"stringValue": value}});
+    let spans: Vec<_> = spans
+        .iter()
+        .map(|s| {
+            let end = s.start_unix_nanos + s.duration_nanos.unwrap_or(0);
+            let status = match &s.error {
+                // STATUS_CODE_ERROR
+                Some(msg) => json!({"code": 2, "message": msg}),
+                None => json!({"code": 1}),
+            };
+            json!({
+                "traceId": s.trace_id,
+                "spanId": s.span_id,
+                "parentSpanId": s.parent_span_id,
+                "name": format!("agfs.{}", s.op),
+                // SPAN_KIND_SERVER
+                "kind": 2,
+                "startTimeUnixNano": s.start_unix_nanos.to_string(),
+                "endTimeUnixNano": end.to_string(),
+                "attributes": [attr("agfs.op", s.op), attr("agfs.path", &s.path)],
+                "status": status,
+            })
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": {"attributes": [attr("service.name", service)]},
+            "scopeSpans": [{
+                "scope": {"name": "agfs-wasm-ffi", "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans,
+            }],
+        }],
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Error;
+
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_traceparent() {
+        let p = TraceParent::parse(PARENT).unwrap();
+        assert_eq!(hex(&p.trace_id), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex(&p.span_id), "00f067aa0ba902b7");
+        assert!(p.sampled);
+        assert!(
+            !TraceParent::parse(&PARENT.replace("-01", "-00"))
+                .unwrap()
+                .sampled
+        );
+        assert_eq!(
+            TraceParent::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+            None
+        );
+        assert_eq!(
+            TraceParent::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            None
+        );
+        assert_eq!(TraceParent::parse("00-4bf92f35-00f067aa0ba902b7-01"), None);
+    }
+
+    #[test]
+    fn test_spans_follow_context() {
+        drain();
+        Context::set_current(None);
+        in_span("read", "/a", || Ok(())).unwrap();
+        assert!(drain().is_empty());
+
+        Context::set_current(Some(Context {
+            trace_parent: Some(PARENT.to_string()),
+            ..Context::default()
+        }));
+        in_span("read", "/a", || Ok(())).unwrap();
+        in_span::<()>("stat", "/b", || Err(Error::NotFound)).unwrap_err();
+        Context::set_current(None);
+
+        let spans = drain();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(spans[0].parent_span_id, "00f067aa0ba902b7");
+        assert_ne!(spans[0].span_id, spans[1].span_id);
+        assert_eq!((spans[1].op, spans[1].path.as_str()), ("stat", "/b"));
+        assert!(spans[0].duration_nanos.is_some() && spans[0].error.is_none());
+
+        let otlp: serde_json::Value = serde_json::from_str(&otlp_json("memfs", &spans)).unwrap();
+        let exported = &otlp["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(exported[1]["name"], "agfs.stat");
+        assert_eq!(exported[1]["status"]["code"], 2);
+        assert_eq!(
+            otlp["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "memfs"
+        );
+    }
+}
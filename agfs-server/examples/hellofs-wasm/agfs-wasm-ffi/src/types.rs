@@ -1,19 +1,33 @@
 //! Type definitions for AGFS filesystem operations
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Result type for filesystem operations
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Error type for filesystem operations
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     NotFound,
     PermissionDenied,
     AlreadyExists,
     IsDirectory,
     NotDirectory,
+    /// Removing or replacing a directory that still has entries
+    NotEmpty,
     ReadOnly,
+    /// The plugin does not implement the named operation
+    ///
+    /// Trait defaults for calls that are not writes return this, so the
+    /// host can answer `ENOSYS` rather than `EROFS`.
+    NotSupported(String),
+    /// Payload larger than the mount allows
+    TooLarge,
+    /// A host call did not finish before its deadline
+    Timeout,
+    /// The host cancelled the call (see the `cancel` module)
+    Cancelled,
     InvalidInput(String),
     Io(String),
     Other(String),
@@ -27,7 +41,12 @@ impl std::fmt::Display for Error {
             Error::AlreadyExists => write!(f, "file already exists"),
             Error::IsDirectory => write!(f, "is a directory"),
             Error::NotDirectory => write!(f, "not a directory"),
+            Error::NotEmpty => write!(f, "directory not empty"),
             Error::ReadOnly => write!(f, "read-only filesystem"),
+            Error::NotSupported(op) => write!(f, "operation not supported: {}", op),
+            Error::TooLarge => write!(f, "payload too large"),
+            Error::Timeout => write!(f, "host call timed out"),
+            Error::Cancelled => write!(f, "operation cancelled"),
             Error::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
             Error::Io(msg) => write!(f, "I/O error: {}", msg),
             Error::Other(msg) => write!(f, "{}", msg),
@@ -37,6 +56,146 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        use std::io::ErrorKind;
+        let kind = match e {
+            Error::NotFound => ErrorKind::NotFound,
+            Error::PermissionDenied => ErrorKind::PermissionDenied,
+            Error::AlreadyExists => ErrorKind::AlreadyExists,
+            Error::NotEmpty => ErrorKind::DirectoryNotEmpty,
+            Error::Timeout => ErrorKind::TimedOut,
+            Error::Cancelled => ErrorKind::Interrupted,
+            Error::InvalidInput(_) => ErrorKind::InvalidInput,
+            Error::NotSupported(_) => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+// Wire codes, one per variant; errno names where one fits
+const WIRE_CODES: &[&str] = &[
+    "ENOENT", "EACCES", "EEXIST", "EISDIR", "ENOTDIR", "ENOTEMPTY", "EROFS", "EFBIG",
+    "ETIMEDOUT", "ECANCELED", "EINVAL", "EIO", "EOTHER", "ENOSYS",
+];
+
+impl Error {
+    /// Code that identifies the variant across the FFI boundary
+    pub fn code(&self) -> &'static str {
+        let i = match self {
+            Error::NotFound => 0,
+            Error::PermissionDenied => 1,
+            Error::AlreadyExists => 2,
+            Error::IsDirectory => 3,
+            Error::NotDirectory => 4,
+            Error::NotEmpty => 5,
+            Error::ReadOnly => 6,
+            Error::TooLarge => 7,
+            Error::Timeout => 8,
+            Error::Cancelled => 9,
+            Error::InvalidInput(_) => 10,
+            Error::Io(_) => 11,
+            Error::Other(_) => 12,
+            Error::NotSupported(_) => 13,
+        };
+        WIRE_CODES[i]
+    }
+
+    /// Linux errno value of the variant's code
+    ///
+    /// Frontends that speak errno (FUSE, 9P) use this; `Io` and `Other`
+    /// map to `EIO`.
+    pub fn errno(&self) -> i32 {
+        match self {
+            Error::NotFound => 2,
+            Error::PermissionDenied => 13,
+            Error::AlreadyExists => 17,
+            Error::NotDirectory => 20,
+            Error::IsDirectory => 21,
+            Error::InvalidInput(_) => 22,
+            Error::TooLarge => 27,
+            Error::ReadOnly => 30,
+            Error::NotSupported(_) => 38,
+            Error::NotEmpty => 39,
+            Error::Timeout => 110,
+            Error::Cancelled => 125,
+            Error::Io(_) | Error::Other(_) => 5,
+        }
+    }
+
+    /// Inverse of `errno`, for replies that carry only the number
+    ///
+    /// Unknown values become `Io`.
+    pub fn from_errno(errno: i32) -> Error {
+        match errno {
+            2 => Error::NotFound,
+            13 => Error::PermissionDenied,
+            17 => Error::AlreadyExists,
+            20 => Error::NotDirectory,
+            21 => Error::IsDirectory,
+            22 => Error::InvalidInput("invalid argument".to_string()),
+            27 => Error::TooLarge,
+            30 => Error::ReadOnly,
+            38 => Error::NotSupported("operation".to_string()),
+            39 => Error::NotEmpty,
+            110 => Error::Timeout,
+            125 => Error::Cancelled,
+            _ => Error::Io(format!("errno {}", errno)),
+        }
+    }
+
+    /// Encode as `CODE: message` for the host
+    ///
+    /// `from_wire` turns the result back into an equal `Error`.
+    pub fn to_wire(&self) -> String {
+        match self {
+            Error::InvalidInput(msg) | Error::Io(msg) | Error::Other(msg) => {
+                format!("{}: {}", self.code(), msg)
+            }
+            Error::NotSupported(op) => format!("{}: {}", self.code(), op),
+            _ => format!("{}: {}", self.code(), self),
+        }
+    }
+
+    /// Decode an error string produced by `to_wire`
+    ///
+    /// Returns `None` if it does not start with a known code.
+    pub fn from_wire(s: &str) -> Option<Error> {
+        let (code, msg) = s.split_once(": ").unwrap_or((s, ""));
+        let msg = msg.to_string();
+        Some(match code {
+            "ENOENT" => Error::NotFound,
+            "EACCES" => Error::PermissionDenied,
+            "EEXIST" => Error::AlreadyExists,
+            "EISDIR" => Error::IsDirectory,
+            "ENOTDIR" => Error::NotDirectory,
+            "ENOTEMPTY" => Error::NotEmpty,
+            "EROFS" => Error::ReadOnly,
+            "EFBIG" => Error::TooLarge,
+            "ETIMEDOUT" => Error::Timeout,
+            "ECANCELED" => Error::Cancelled,
+            "EINVAL" => Error::InvalidInput(msg),
+            "EIO" => Error::Io(msg),
+            "EOTHER" => Error::Other(msg),
+            "ENOSYS" if msg.is_empty() => Error::NotSupported("operation".to_string()),
+            "ENOSYS" => Error::NotSupported(msg),
+            _ => return None,
+        })
+    }
+
+    /// Decode an error string returned by a host import
+    ///
+    /// The host sends `CODE: message` like `to_wire`; a string without a
+    /// known code is kept whole as `Other`.
+    pub fn from_host(s: &str) -> Error {
+        Self::from_wire(s).unwrap_or_else(|| Error::Other(s.to_string()))
+    }
+}
+
+/// Mode bit marking a symbolic link (Go's `os.ModeSymlink`)
+pub const MODE_SYMLINK: u32 = 1 << 27;
+
 /// File information structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
@@ -53,37 +212,63 @@ pub struct FileInfo {
     #[serde(rename = "Meta")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<MetaData>,
+    /// Extended attributes listed with the entry
+    ///
+    /// Only attributes with text values belong here; `getxattr` returns
+    /// any attribute as bytes.
+    #[serde(rename = "Xattrs", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+    /// Number of hard links, 0 if the plugin does not track them
+    #[serde(rename = "Nlink", default, skip_serializing_if = "is_zero")]
+    pub nlink: u32,
+    /// Seconds the host may answer `stat` from this entry, 0 for its
+    /// default
+    ///
+    /// Set by plugins whose metadata changes behind the host's back (a
+    /// short TTL) or never does (a long one).
+    #[serde(rename = "StatValidSecs", default, skip_serializing_if = "is_zero")]
+    pub stat_valid_secs: u32,
 }
 
-// Serialize Unix timestamp to RFC3339 string
-fn serialize_timestamp<S>(_timestamp: &i64, serializer: S) -> std::result::Result<S::Ok, S::Error>
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+// Serialize Unix timestamp to RFC3339 string; 0 is Go's zero time
+fn serialize_timestamp<S>(timestamp: &i64, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
-    // Always serialize as zero time for simplicity
-    serializer.serialize_str("0001-01-01T00:00:00Z")
+    serializer.serialize_str(&crate::time::format_rfc3339(*timestamp))
 }
 
-// Deserialize RFC3339 string to Unix timestamp
+// Deserialize RFC3339 string to Unix timestamp; times the host formats
+// unexpectedly read as 0 rather than failing the whole entry
 fn deserialize_timestamp<'de, D>(deserializer: D) -> std::result::Result<i64, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let _s = String::deserialize(deserializer)?;
-    // Always return 0 for simplicity
-    Ok(0)
+    let s = String::deserialize(deserializer)?;
+    Ok(crate::time::parse_rfc3339(&s).unwrap_or(0))
 }
 
 impl FileInfo {
     /// Create a file info for a regular file
+    ///
+    /// Like `dir` and `symlink`, the modification time is the current
+    /// time, rather than Go's zero time that clients show as year 1; set
+    /// the real one with `with_mod_time`.
     pub fn file(name: impl Into<String>, size: i64, mode: u32) -> Self {
         Self {
             name: name.into(),
             size,
             mode,
-            mod_time: 0,
+            mod_time: crate::time::now(),
             is_dir: false,
             meta: None,
+            xattrs: BTreeMap::new(),
+            nlink: 0,
+            stat_valid_secs: 0,
         }
     }
 
@@ -93,9 +278,29 @@ impl FileInfo {
             name: name.into(),
             size: 0,
             mode,
-            mod_time: 0,
+            mod_time: crate::time::now(),
             is_dir: true,
             meta: None,
+            xattrs: BTreeMap::new(),
+            nlink: 0,
+            stat_valid_secs: 0,
+        }
+    }
+
+    /// Create a file info for a symbolic link to `target`
+    ///
+    /// Like `lstat`, the size is the length of the target.
+    pub fn symlink(name: impl Into<String>, target: &str) -> Self {
+        Self {
+            name: name.into(),
+            size: target.len() as i64,
+            mode: MODE_SYMLINK | 0o777,
+            mod_time: crate::time::now(),
+            is_dir: false,
+            meta: None,
+            xattrs: BTreeMap::new(),
+            nlink: 0,
+            stat_valid_secs: 0,
         }
     }
 
@@ -105,11 +310,619 @@ impl FileInfo {
         self
     }
 
+    /// Add an extended attribute to list with the entry
+    pub fn with_xattr(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.xattrs.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the hard link count
+    pub fn with_nlink(mut self, nlink: u32) -> Self {
+        self.nlink = nlink;
+        self
+    }
+
+    /// Let the host cache this entry for `secs` seconds
+    pub fn with_stat_ttl(mut self, secs: u32) -> Self {
+        self.stat_valid_secs = secs;
+        self
+    }
+
     /// Set modification time (Unix timestamp)
     pub fn with_mod_time(mut self, timestamp: i64) -> Self {
         self.mod_time = timestamp;
         self
     }
+
+    /// Whether this entry is a symbolic link
+    pub fn is_symlink(&self) -> bool {
+        self.mode & MODE_SYMLINK != 0
+    }
+
+    /// Check the invariants clients rely on
+    ///
+    /// The name is a single path component (`""` or `"/"` only for the
+    /// root), the size is not negative, and directories have size 0.
+    pub fn validate(&self) -> Result<()> {
+        let why = if self.name.contains('\0') {
+            "name contains NUL"
+        } else if self.name.contains('/') && self.name != "/" {
+            "name contains '/'"
+        } else if self.name == "." || self.name == ".." {
+            "name is a relative path component"
+        } else if self.size < 0 {
+            "negative size"
+        } else if self.is_dir && self.size != 0 {
+            "directory with non-zero size"
+        } else {
+            return Ok(());
+        };
+        Err(Error::InvalidInput(format!("invalid file info {:?}: {}", self.name, why)))
+    }
+}
+
+/// A partial directory listing returned by `fs_readdir_page`
+///
+/// `next` is an opaque continuation token; pass it back to fetch the
+/// following page. `None` means the listing is complete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirPage {
+    #[serde(rename = "Entries")]
+    pub entries: Vec<FileInfo>,
+    #[serde(rename = "Next")]
+    pub next: Option<String>,
+}
+
+/// Filesystem-wide statistics, as reported by `statfs`
+///
+/// Counts are in blocks of `block_size` bytes. `available_blocks` is what
+/// an unprivileged client may still use, which can be less than
+/// `free_blocks`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsStats {
+    #[serde(rename = "BlockSize")]
+    pub block_size: u64,
+    #[serde(rename = "Blocks")]
+    pub total_blocks: u64,
+    #[serde(rename = "BlocksFree")]
+    pub free_blocks: u64,
+    #[serde(rename = "BlocksAvailable")]
+    pub available_blocks: u64,
+    #[serde(rename = "Files")]
+    pub total_files: u64,
+    #[serde(rename = "FilesFree")]
+    pub free_files: u64,
+    /// Longest file name the filesystem accepts, in bytes
+    #[serde(rename = "NameMax")]
+    pub name_max: u64,
+}
+
+impl FsStats {
+    /// Stats for `total` bytes of which `free` are unused, in 4 KiB blocks
+    pub fn from_bytes(total: u64, free: u64) -> Self {
+        const BLOCK: u64 = 4096;
+        Self {
+            block_size: BLOCK,
+            total_blocks: total / BLOCK,
+            free_blocks: free / BLOCK,
+            available_blocks: free / BLOCK,
+            name_max: 255,
+            ..Self::default()
+        }
+    }
+
+    /// Set the inode counts
+    pub fn with_files(mut self, total: u64, free: u64) -> Self {
+        self.total_files = total;
+        self.free_files = free;
+        self
+    }
+}
+
+/// Overall state of a plugin, from `FileSystem::health`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthState {
+    Healthy,
+    /// Serving, but with reduced function (e.g. a fallback backend)
+    Degraded,
+    /// Calls are expected to fail
+    Unhealthy,
+}
+
+/// Result of a health probe
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    #[serde(rename = "State")]
+    pub state: HealthState,
+    /// What is wrong, for operators
+    #[serde(rename = "Message", default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+}
+
+impl HealthStatus {
+    pub fn healthy() -> Self {
+        Self {
+            state: HealthState::Healthy,
+            message: String::new(),
+        }
+    }
+
+    pub fn degraded(message: impl Into<String>) -> Self {
+        Self {
+            state: HealthState::Degraded,
+            message: message.into(),
+        }
+    }
+
+    pub fn unhealthy(message: impl Into<String>) -> Self {
+        Self {
+            state: HealthState::Unhealthy,
+            message: message.into(),
+        }
+    }
+}
+
+/// How long the server may cache what a plugin returns, from
+/// `FileSystem::cache_policy`
+///
+/// TTLs are in seconds; 0 disables that cache. The default matches the
+/// FUSE defaults: one second for attributes and entries, no negative
+/// entries, and file data dropped on open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachePolicy {
+    /// `stat` results
+    #[serde(rename = "AttrTTL")]
+    pub attr_ttl: u32,
+    /// Entries of `readdir_plus` listings, answering `stat` for their
+    /// paths
+    #[serde(rename = "EntryTTL")]
+    pub entry_ttl: u32,
+    /// Name lookups that found nothing
+    #[serde(rename = "NegativeTTL")]
+    pub negative_ttl: u32,
+    /// Keep whole-file reads cached for as long as the file's `stat`
+    /// result
+    #[serde(rename = "DataCache")]
+    pub data_cache: bool,
+}
+
+impl CachePolicy {
+    /// Cache nothing, for plugins whose contents change on every read
+    pub fn none() -> Self {
+        Self {
+            attr_ttl: 0,
+            entry_ttl: 0,
+            negative_ttl: 0,
+            data_cache: false,
+        }
+    }
+
+    /// Cache everything for `ttl` seconds, for static contents
+    pub fn static_for(ttl: u32) -> Self {
+        Self {
+            attr_ttl: ttl,
+            entry_ttl: ttl,
+            negative_ttl: ttl,
+            data_cache: true,
+        }
+    }
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            attr_ttl: 1,
+            entry_ttl: 1,
+            negative_ttl: 0,
+            data_cache: false,
+        }
+    }
+}
+
+/// Outcome of a read, from `FileSystem::read_result`
+///
+/// An empty `data` alone cannot tell the end of the file from a
+/// zero-length range; `eof` says that nothing follows `data`, so the
+/// server stops reading and clients see a proper short read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadResult {
+    pub data: Vec<u8>,
+    pub eof: bool,
+}
+
+impl ReadResult {
+    /// `data` with more of the file after it
+    pub fn more(data: Vec<u8>) -> Self {
+        Self { data, eof: false }
+    }
+
+    /// `data` runs to the end of the file
+    pub fn at_eof(data: Vec<u8>) -> Self {
+        Self { data, eof: true }
+    }
+
+    /// Judge a `read` of `size` bytes from its length: a short read, or
+    /// one to the end (`size < 0`), reached the end of the file
+    pub fn from_read(data: Vec<u8>, size: i64) -> Self {
+        let eof = size < 0 || (data.len() as u64) < size as u64;
+        Self { data, eof }
+    }
+
+    /// Encode as `fs_read_result` returns it: a flag byte, 1 at the end
+    /// of the file, then the data
+    pub fn to_payload(self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(1 + self.data.len());
+        payload.push(self.eof as u8);
+        payload.extend_from_slice(&self.data);
+        payload
+    }
+
+    /// Decode a payload made by `to_payload`
+    pub fn from_payload(payload: &[u8]) -> Result<Self> {
+        match payload.split_first() {
+            Some((&flag, data)) if flag <= 1 => Ok(Self {
+                data: data.to_vec(),
+                eof: flag == 1,
+            }),
+            _ => Err(Error::InvalidInput("invalid read result".to_string())),
+        }
+    }
+}
+
+/// Outcome of a write, from `FileSystem::write_result`
+///
+/// `response` is for control files: a plugin that computes something
+/// from what was written returns it here, and the server hands it to the
+/// writer. Ordinary files leave it `None`, so the server can tell "no
+/// response" from an empty one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteResult {
+    #[serde(rename = "BytesWritten")]
+    pub bytes_written: i64,
+    /// Base64 in JSON, as Go encodes `[]byte`
+    #[serde(
+        rename = "Response",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "response_base64"
+    )]
+    pub response: Option<Vec<u8>>,
+}
+
+impl WriteResult {
+    /// All `bytes_written` bytes were stored; there is no response
+    pub fn written(bytes_written: i64) -> Self {
+        Self {
+            bytes_written,
+            response: None,
+        }
+    }
+
+    /// Add a response for the writer
+    pub fn with_response(mut self, response: Vec<u8>) -> Self {
+        self.response = Some(response);
+        self
+    }
+
+    /// Convert the reply of `FileSystem::write` for `len` bytes, where an
+    /// empty reply means no response
+    pub fn from_reply(len: usize, reply: Vec<u8>) -> Self {
+        let result = Self::written(len as i64);
+        if reply.is_empty() {
+            return result;
+        }
+        result.with_response(reply)
+    }
+
+    /// The reply `FileSystem::write` returns for this result
+    pub fn into_reply(self) -> Vec<u8> {
+        self.response.unwrap_or_default()
+    }
+}
+
+mod response_base64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &Option<Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+        match data {
+            Some(data) => crate::base64::serialize(data, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<u8>>, D::Error> {
+        match Option::<String>::deserialize(d)? {
+            Some(text) => crate::base64::decode(&text)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Usage and limits of a directory tree, from `FileSystem::quota`
+///
+/// A limit of 0 means none. `files` counts every entry below the
+/// directory: files, directories and links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaInfo {
+    #[serde(rename = "BytesUsed", default)]
+    pub bytes_used: u64,
+    #[serde(rename = "BytesLimit", default)]
+    pub bytes_limit: u64,
+    #[serde(rename = "FilesUsed", default)]
+    pub files_used: u64,
+    #[serde(rename = "FilesLimit", default)]
+    pub files_limit: u64,
+}
+
+impl QuotaInfo {
+    /// Limits without usage, as passed to `FileSystem::set_quota`
+    pub fn limits(bytes: u64, files: u64) -> Self {
+        Self {
+            bytes_limit: bytes,
+            files_limit: files,
+            ..Self::default()
+        }
+    }
+
+    /// Whether growing by `bytes` and `files` stays within the limits
+    ///
+    /// Shrinking is always allowed, even when usage is already over.
+    pub fn allows(&self, bytes: i64, files: i64) -> bool {
+        let within = |used: u64, delta: i64, limit: u64| {
+            delta <= 0 || limit == 0 || used.saturating_add(delta as u64) <= limit
+        };
+        within(self.bytes_used, bytes, self.bytes_limit)
+            && within(self.files_used, files, self.files_limit)
+    }
+}
+
+/// A JSON response from the host, passed on to the server unparsed
+///
+/// Proxy plugins return it from `FileSystem::stat_passthrough` and
+/// `readdir_passthrough` to skip decoding and re-encoding host entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawJson(pub String);
+
+impl RawJson {
+    /// The JSON text
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Access flags for `FileSystem::open`, with their open(2) values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpenFlags(pub u32);
+
+impl OpenFlags {
+    pub const READ_ONLY: Self = Self(0);
+    pub const WRITE_ONLY: Self = Self(1);
+    pub const READ_WRITE: Self = Self(2);
+    pub const CREATE: Self = Self(0o100);
+    /// Writes through the handle go to the end of the file
+    pub const APPEND: Self = Self(0o2000);
+    /// The file was truncated to zero length on open
+    pub const TRUNCATE: Self = Self(0o1000);
+
+    pub fn is_read_only(self) -> bool {
+        self.0 & 3 == 0
+    }
+
+    /// Whether opening with these flags can change the file: write access,
+    /// or CREATE, TRUNCATE or APPEND
+    pub fn modifies(self) -> bool {
+        !self.is_read_only() || self.0 & (Self::CREATE.0 | Self::TRUNCATE.0 | Self::APPEND.0) != 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for OpenFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// How `FileSystem::create_with_flags` treats an existing path
+///
+/// The default matches `create`: an existing file is truncated and the
+/// mode is left to the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreateFlags {
+    /// Fail with `AlreadyExists` if the path exists (`O_EXCL`)
+    pub exclusive: bool,
+    /// Truncate an existing file; otherwise it is left as is (`O_TRUNC`)
+    pub truncate: bool,
+    /// Permission bits for a new file
+    pub mode: Option<u32>,
+}
+
+impl CreateFlags {
+    const EXCLUSIVE: u32 = 1;
+    const TRUNCATE: u32 = 1 << 1;
+    const MODE: u32 = 1 << 2;
+
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_mode(self, mode: u32) -> Self {
+        Self {
+            mode: Some(mode),
+            ..self
+        }
+    }
+
+    /// Decode the `flags` and `mode` arguments of `fs_create_flags`
+    pub fn from_bits(flags: u32, mode: u32) -> Self {
+        Self {
+            exclusive: flags & Self::EXCLUSIVE != 0,
+            truncate: flags & Self::TRUNCATE != 0,
+            mode: (flags & Self::MODE != 0).then_some(mode),
+        }
+    }
+
+    /// Encode as the `flags` and `mode` arguments of `host_fs_create_flags`
+    pub fn to_bits(self) -> (u32, u32) {
+        let mut flags = 0;
+        if self.exclusive {
+            flags |= Self::EXCLUSIVE;
+        }
+        if self.truncate {
+            flags |= Self::TRUNCATE;
+        }
+        if self.mode.is_some() {
+            flags |= Self::MODE;
+        }
+        (flags, self.mode.unwrap_or(0))
+    }
+}
+
+impl Default for CreateFlags {
+    fn default() -> Self {
+        Self {
+            exclusive: false,
+            truncate: true,
+            mode: None,
+        }
+    }
+}
+
+/// Flags for `FileSystem::rename_with_flags`, as renameat2(2) takes them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RenameFlags(pub u32);
+
+impl RenameFlags {
+    pub const NONE: Self = Self(0);
+    /// Fail with `AlreadyExists` rather than replace the new path
+    pub const NOREPLACE: Self = Self(1);
+    /// Swap the two paths; both must exist
+    pub const EXCHANGE: Self = Self(1 << 1);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Reject unknown bits and `NOREPLACE` together with `EXCHANGE`
+    pub fn validate(self) -> Result<Self> {
+        if self.0 & !(Self::NOREPLACE.0 | Self::EXCHANGE.0) != 0 {
+            return Err(Error::InvalidInput(format!("unknown rename flags {:#x}", self.0)));
+        }
+        if self.contains(Self::NOREPLACE | Self::EXCHANGE) {
+            return Err(Error::InvalidInput(
+                "rename flags NOREPLACE and EXCHANGE are exclusive".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+impl std::ops::BitOr for RenameFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Advisory lock requested with `FileSystem::lock`, as flock(2) takes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockKind {
+    /// Any number of holders; excludes `Exclusive`
+    Shared,
+    /// A single holder
+    Exclusive,
+}
+
+impl LockKind {
+    /// Decode the `kind` argument of `fs_lock`: 1 shared, 2 exclusive
+    pub fn from_bits(kind: u32) -> Result<Self> {
+        match kind {
+            1 => Ok(LockKind::Shared),
+            2 => Ok(LockKind::Exclusive),
+            _ => Err(Error::InvalidInput(format!("invalid lock kind {}", kind))),
+        }
+    }
+
+    /// Encode as the `kind` argument of `host_fs_lock`
+    pub fn to_bits(self) -> u32 {
+        match self {
+            LockKind::Shared => 1,
+            LockKind::Exclusive => 2,
+        }
+    }
+}
+
+/// Optional operations a plugin supports, from `FileSystem::capabilities`
+///
+/// The host fails modifying calls itself for plugins without `WRITABLE`
+/// and lists their mounts as read-only, and only watches plugins that
+/// claim `WATCH`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// `write`, `create`, `mkdir`, `remove`, `rename` and the other
+    /// modifying calls
+    pub const WRITABLE: Self = Self(1);
+    pub const SYMLINKS: Self = Self(1 << 1);
+    pub const HARD_LINKS: Self = Self(1 << 2);
+    pub const XATTRS: Self = Self(1 << 3);
+    pub const WATCH: Self = Self(1 << 4);
+    /// `open`, `read_at` and `close` keep per-handle state
+    pub const HANDLES: Self = Self(1 << 5);
+    pub const STATFS: Self = Self(1 << 6);
+    pub const COPY_RANGE: Self = Self(1 << 7);
+    pub const ALL: Self = Self((1 << 8) - 1);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// An open file, as returned by `FileSystem::open`
+///
+/// `id` is the plugin's own: a plugin keeping per-handle state sets it
+/// with `with_id` and looks the state up by it. The host only ever sees
+/// a number the glue assigns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handle {
+    pub id: u64,
+    pub path: String,
+    pub flags: OpenFlags,
+}
+
+impl Handle {
+    pub fn new(path: impl Into<String>, flags: OpenFlags) -> Self {
+        Self {
+            id: 0,
+            path: path.into(),
+            flags,
+        }
+    }
+
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+        self
+    }
 }
 
 /// Metadata structure
@@ -158,6 +971,16 @@ impl Config {
         self.inner.get(key)?.as_i64()
     }
 
+    /// Get a size in bytes: a number, or a string with an optional
+    /// B/KB/MB/GB/TB unit ("512KB", "1.5MB"), as the host's `GetSizeConfig`
+    /// reads it
+    pub fn get_size(&self, key: &str) -> Option<i64> {
+        match self.inner.get(key)? {
+            serde_json::Value::String(s) => parse_size(s),
+            v => v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)),
+        }
+    }
+
     /// Get a boolean value
     pub fn get_bool(&self, key: &str) -> Option<bool> {
         self.inner.get(key)?.as_bool()
@@ -169,6 +992,30 @@ impl Config {
     }
 }
 
+// Units `parse_size` accepts, longest first so "MB" is not read as "B"
+const SIZE_UNITS: &[(&str, i64)] = &[
+    ("KB", 1 << 10),
+    ("MB", 1 << 20),
+    ("GB", 1 << 30),
+    ("TB", 1 << 40),
+    ("B", 1),
+];
+
+// Parse a size string the way the host's config.ParseSize does
+fn parse_size(s: &str) -> Option<i64> {
+    let s = s.trim().to_ascii_uppercase();
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(n);
+    }
+    SIZE_UNITS.iter().find_map(|(unit, multiplier)| {
+        let num = s.strip_suffix(unit)?.trim();
+        match num.parse::<i64>() {
+            Ok(n) => n.checked_mul(*multiplier),
+            Err(_) => num.parse::<f64>().ok().map(|f| (f * *multiplier as f64) as i64),
+        }
+    })
+}
+
 impl From<serde_json::Value> for Config {
     fn from(value: serde_json::Value) -> Self {
         match value {
@@ -179,3 +1026,243 @@ impl From<serde_json::Value> for Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_variants() -> Vec<Error> {
+        vec![
+            Error::NotFound,
+            Error::PermissionDenied,
+            Error::AlreadyExists,
+            Error::IsDirectory,
+            Error::NotDirectory,
+            Error::NotEmpty,
+            Error::ReadOnly,
+            Error::TooLarge,
+            Error::Timeout,
+            Error::Cancelled,
+            Error::InvalidInput("bad: offset".to_string()),
+            Error::Io(String::new()),
+            Error::Other("ENOENT: looks coded".to_string()),
+            Error::NotSupported("watch".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_wire_round_trip() {
+        for e in all_variants() {
+            let wire = e.to_wire();
+            assert_eq!(Error::from_wire(&wire), Some(e.clone()), "{}", wire);
+            assert_eq!(Error::from_host(&wire), e);
+        }
+        assert_eq!(Error::NotFound.to_wire(), "ENOENT: file not found");
+        assert_eq!(Error::from_wire("file not found"), None);
+        assert_eq!(Error::NotEmpty.errno(), 39);
+        assert_eq!(Error::NotSupported("chmod".to_string()).to_wire(), "ENOSYS: chmod");
+        assert_eq!(Error::from_wire("ENOSYS: fly"), Some(Error::NotSupported("fly".to_string())));
+        assert_eq!(Error::from_wire("ENOSYS"), Some(Error::NotSupported("operation".to_string())));
+        assert_eq!(Error::Other(String::new()).errno(), 5);
+        for e in all_variants() {
+            assert_eq!(Error::from_errno(e.errno()).errno(), e.errno());
+        }
+    }
+
+    #[test]
+    fn test_config_get_size() {
+        let config = Config::from(serde_json::json!({
+            "plain": 4096,
+            "float": 1.5,
+            "bytes": "512",
+            "kb": "512KB",
+            "mb": " 16mb ",
+            "frac": "1.5MB",
+            "b": "10B",
+            "bad": "lots",
+        }));
+        assert_eq!(config.get_size("plain"), Some(4096));
+        assert_eq!(config.get_size("float"), Some(1));
+        assert_eq!(config.get_size("bytes"), Some(512));
+        assert_eq!(config.get_size("kb"), Some(512 * 1024));
+        assert_eq!(config.get_size("mb"), Some(16 << 20));
+        assert_eq!(config.get_size("frac"), Some(3 << 19));
+        assert_eq!(config.get_size("b"), Some(10));
+        assert_eq!(config.get_size("bad"), None);
+        assert_eq!(config.get_size("missing"), None);
+    }
+
+    #[test]
+    fn test_open_flags_modifies() {
+        assert!(!OpenFlags::READ_ONLY.modifies());
+        assert!(OpenFlags::WRITE_ONLY.modifies());
+        assert!(OpenFlags::READ_WRITE.modifies());
+        assert!((OpenFlags::READ_ONLY | OpenFlags::CREATE).modifies());
+        assert!((OpenFlags::READ_ONLY | OpenFlags::TRUNCATE).modifies());
+        assert!((OpenFlags::READ_ONLY | OpenFlags::APPEND).modifies());
+    }
+
+    #[test]
+    fn test_lock_kind_bits() {
+        for kind in [LockKind::Shared, LockKind::Exclusive] {
+            assert_eq!(LockKind::from_bits(kind.to_bits()), Ok(kind));
+        }
+        assert!(LockKind::from_bits(0).is_err());
+    }
+
+    #[test]
+    fn test_rename_flags_validate() {
+        assert!(RenameFlags::NONE.validate().is_ok());
+        assert!(RenameFlags::EXCHANGE.validate().is_ok());
+        assert!((RenameFlags::NOREPLACE | RenameFlags::EXCHANGE).validate().is_err());
+        assert!(RenameFlags(8).validate().is_err());
+    }
+
+    #[test]
+    fn test_create_flags_bits() {
+        for flags in [
+            CreateFlags::default(),
+            CreateFlags::exclusive(),
+            CreateFlags::exclusive().with_mode(0o600),
+        ] {
+            let (bits, mode) = flags.to_bits();
+            assert_eq!(CreateFlags::from_bits(bits, mode), flags);
+        }
+        assert_eq!(CreateFlags::default().to_bits(), (2, 0));
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let caps = Capabilities::WRITABLE | Capabilities::SYMLINKS;
+        assert!(caps.contains(Capabilities::SYMLINKS));
+        assert!(!caps.contains(Capabilities::WRITABLE | Capabilities::XATTRS));
+        assert!(Capabilities::ALL.contains(Capabilities::COPY_RANGE));
+        assert!(caps.contains(Capabilities::NONE));
+    }
+
+    #[test]
+    fn test_health_json() {
+        let json = serde_json::to_string(&HealthStatus::healthy()).unwrap();
+        assert_eq!(json, r#"{"State":"Healthy"}"#);
+        let json = serde_json::to_value(HealthStatus::degraded("credentials expire soon")).unwrap();
+        assert_eq!(json["State"], "Degraded");
+        assert_eq!(json["Message"], "credentials expire soon");
+    }
+
+    #[test]
+    fn test_cache_policy_json() {
+        let json = serde_json::to_string(&CachePolicy::none()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"AttrTTL":0,"EntryTTL":0,"NegativeTTL":0,"DataCache":false}"#
+        );
+        let policy: CachePolicy = serde_json::from_str(&json).unwrap();
+        assert_eq!(policy, CachePolicy::none());
+    }
+
+    #[test]
+    fn test_read_result_eof() {
+        assert!(ReadResult::from_read(b"ab".to_vec(), 4).eof);
+        assert!(!ReadResult::from_read(b"ab".to_vec(), 2).eof);
+        assert!(!ReadResult::from_read(Vec::new(), 0).eof);
+        assert!(ReadResult::from_read(Vec::new(), -1).eof);
+        let result = ReadResult::at_eof(b"xy".to_vec());
+        assert_eq!(ReadResult::from_payload(&result.clone().to_payload()), Ok(result));
+        assert!(ReadResult::from_payload(&[]).is_err());
+    }
+
+    #[test]
+    fn test_write_result_json() {
+        let json = serde_json::to_string(&WriteResult::written(5)).unwrap();
+        assert_eq!(json, r#"{"BytesWritten":5}"#);
+        let result = WriteResult::from_reply(2, b"hello".to_vec());
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(json, r#"{"BytesWritten":2,"Response":"aGVsbG8="}"#);
+        assert_eq!(serde_json::from_str::<WriteResult>(&json).unwrap(), result);
+        assert_eq!(WriteResult::from_reply(2, Vec::new()), WriteResult::written(2));
+    }
+
+    #[test]
+    fn test_quota_allows() {
+        let mut quota = QuotaInfo::limits(100, 2);
+        quota.bytes_used = 90;
+        quota.files_used = 2;
+        assert!(quota.allows(10, 0));
+        assert!(!quota.allows(11, 0));
+        assert!(!quota.allows(0, 1));
+        assert!(quota.allows(-50, -1));
+        assert!(QuotaInfo::default().allows(i64::MAX, i64::MAX));
+    }
+
+    #[test]
+    fn test_fs_stats_json() {
+        let stats = FsStats::from_bytes(1 << 30, 5000).with_files(10, 4);
+        assert_eq!((stats.total_blocks, stats.free_blocks), (262144, 1));
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["BlockSize"], 4096);
+        assert_eq!(json["FilesFree"], 4);
+        assert_eq!(serde_json::from_value::<FsStats>(json).unwrap(), stats);
+    }
+
+    #[test]
+    fn test_stat_ttl_json() {
+        let json = serde_json::to_value(FileInfo::file("a", 1, 0o644)).unwrap();
+        assert!(json.get("StatValidSecs").is_none());
+        let info = FileInfo::file("a", 1, 0o644).with_stat_ttl(30);
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["StatValidSecs"], 30);
+        let back: FileInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(back.stat_valid_secs, 30);
+    }
+
+    #[test]
+    fn test_mod_time_json() {
+        let info = FileInfo::file("a", 1, 0o644);
+        assert!(info.mod_time >= crate::time::now() - 60);
+        let json = serde_json::to_value(&info).unwrap();
+        let back: FileInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(back.mod_time, info.mod_time);
+
+        // The stored time is sent as is, zero included
+        let json = serde_json::to_value(FileInfo::file("a", 1, 0o644).with_mod_time(0)).unwrap();
+        assert_eq!(json["ModTime"], crate::time::ZERO_TIME);
+        let info = FileInfo::file("a", 1, 0o644).with_mod_time(951782400);
+        let json = serde_json::to_value(info).unwrap();
+        assert_eq!(json["ModTime"], "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_fileinfo_validate() {
+        assert!(FileInfo::dir("", 0o755).validate().is_ok());
+        assert!(FileInfo::dir("/", 0o755).validate().is_ok());
+        assert!(FileInfo::file("a.txt", 3, 0o644).validate().is_ok());
+        assert!(FileInfo::file("a/b", 3, 0o644).validate().is_err());
+        assert!(FileInfo::file("..", 0, 0o644).validate().is_err());
+        assert!(FileInfo::file("a", -1, 0o644).validate().is_err());
+        let mut dir = FileInfo::dir("d", 0o755);
+        dir.size = 4096;
+        let err = dir.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid input: invalid file info \"d\": directory with non-zero size"
+        );
+    }
+
+    #[test]
+    fn test_from_host_codes() {
+        assert_eq!(Error::from_host("ENOENT: stat: /a: not found"), Error::NotFound);
+        assert_eq!(
+            Error::from_host("ETIMEDOUT: host_fs_read did not finish in time"),
+            Error::Timeout
+        );
+        assert_eq!(
+            Error::from_host("EIO: disk on fire"),
+            Error::Io("disk on fire".to_string())
+        );
+        // Text alone is not decoded
+        assert_eq!(
+            Error::from_host("stat: /a: not found"),
+            Error::Other("stat: /a: not found".to_string())
+        );
+    }
+}
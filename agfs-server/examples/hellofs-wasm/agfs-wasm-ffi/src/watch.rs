@@ -0,0 +1,187 @@
+//! Change notifications from plugins
+//!
+//! A plugin whose data changes behind the server's back (host files, a
+//! remote API) accepts watches in `FileSystem::watch` and reports changes
+//! as it notices them. Events are queued in the guest and the host polls
+//! them one at a time through the `fs_next_event` export. A server that
+//! caches the plugin's results (see `FileSystem::cache_policy`) watches
+//! `/` after initialize and drops what it cached for every reported path;
+//! an `Overflow` drops everything:
+//!
+//! ```ignore
+//! fn watch(&mut self, path: &str) -> Result<WatchId> {
+//!     Ok(self.watches.add(path))
+//! }
+//!
+//! fn unwatch(&mut self, id: WatchId) -> Result<()> {
+//!     self.watches.remove(id)
+//! }
+//!
+//! // after noticing that /feeds/today changed upstream
+//! self.watches.notify(EventKind::Modified, "/feeds/today");
+//! ```
+
+use crate::types::{Error, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+/// Events kept in the queue; later ones are dropped until it drains
+pub const EVENT_QUEUE_LIMIT: usize = 1024;
+
+/// A watch, as returned by `FileSystem::watch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct WatchId(pub u32);
+
+/// What happened to a watched path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EventKind {
+    Created,
+    Modified,
+    Removed,
+    /// Events were lost because the queue was full; everything the host
+    /// derived from this mount may be stale
+    Overflow,
+}
+
+/// One change notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WatchEvent {
+    #[serde(rename = "Watch")]
+    pub watch: WatchId,
+    #[serde(rename = "Kind")]
+    pub kind: EventKind,
+    #[serde(rename = "Path")]
+    pub path: String,
+}
+
+struct Queue {
+    events: VecDeque<WatchEvent>,
+    overflowed: bool,
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    events: VecDeque::new(),
+    overflowed: false,
+});
+
+/// Queue an event for watch `watch`
+pub fn notify(watch: WatchId, kind: EventKind, path: &str) {
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.events.len() >= EVENT_QUEUE_LIMIT {
+        queue.overflowed = true;
+        return;
+    }
+    queue.events.push_back(WatchEvent {
+        watch,
+        kind,
+        path: path.to_string(),
+    });
+}
+
+/// Take the oldest queued event
+///
+/// After an overflow, the events that made it into the queue are followed
+/// by a single `Overflow` event for watch 0 on `/`.
+pub fn next_event() -> Option<WatchEvent> {
+    let mut queue = QUEUE.lock().unwrap();
+    if let Some(event) = queue.events.pop_front() {
+        return Some(event);
+    }
+    if !std::mem::take(&mut queue.overflowed) {
+        return None;
+    }
+    Some(WatchEvent {
+        watch: WatchId(0),
+        kind: EventKind::Overflow,
+        path: "/".to_string(),
+    })
+}
+
+/// The watches a plugin has handed out
+///
+/// A watch on a directory sees changes anywhere below it.
+#[derive(Debug, Default)]
+pub struct Watches {
+    last: u32,
+    paths: BTreeMap<WatchId, String>,
+}
+
+impl Watches {
+    /// Watch `path`, which need not exist yet
+    pub fn add(&mut self, path: &str) -> WatchId {
+        // 0 is reserved for overflow events
+        self.last = self.last.wrapping_add(1).max(1);
+        while self.paths.contains_key(&WatchId(self.last)) {
+            self.last = self.last.wrapping_add(1).max(1);
+        }
+        let id = WatchId(self.last);
+        self.paths.insert(id, path.to_string());
+        id
+    }
+
+    /// Drop a watch; `NotFound` if it is not active
+    pub fn remove(&mut self, id: WatchId) -> Result<()> {
+        self.paths.remove(&id).map(|_| ()).ok_or(Error::NotFound)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Queue an event for every watch covering `path`
+    pub fn notify(&self, kind: EventKind, path: &str) {
+        for (id, watched) in &self.paths {
+            if covers(watched, path) {
+                notify(*id, kind, path);
+            }
+        }
+    }
+}
+
+fn covers(watched: &str, path: &str) -> bool {
+    let watched = watched.trim_end_matches('/');
+    match path.strip_prefix(watched) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watches() {
+        let mut watches = Watches::default();
+        let feeds = watches.add("/feeds");
+        let root = watches.add("/");
+        let today = watches.add("/feeds/today");
+        watches.notify(EventKind::Modified, "/feeds/today");
+        watches.notify(EventKind::Created, "/feedsx");
+        watches.remove(root).unwrap();
+        assert_eq!(watches.remove(root), Err(Error::NotFound));
+        watches.notify(EventKind::Removed, "/feeds");
+
+        let seen: Vec<_> = std::iter::from_fn(next_event)
+            .map(|e| (e.watch, e.kind))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                (feeds, EventKind::Modified),
+                (root, EventKind::Modified),
+                (today, EventKind::Modified),
+                (root, EventKind::Created),
+                (feeds, EventKind::Removed),
+            ]
+        );
+
+        for _ in 0..=EVENT_QUEUE_LIMIT {
+            notify(feeds, EventKind::Modified, "/feeds");
+        }
+        assert_eq!(std::iter::from_fn(next_event).count(), EVENT_QUEUE_LIMIT + 1);
+        assert!(next_event().is_none());
+    }
+}
@@ -0,0 +1,730 @@
+//! WebDAV frontend over a `FileSystem` (feature `webdav`)
+//!
+//! Lets Finder, Explorer or davfs2 mount a plugin without agfs-server in
+//! between:
+//!
+//! ```ignore
+//! let listener = std::net::TcpListener::bind("127.0.0.1:8080")?;
+//! webdav::serve(listener, WebDav::new(MyFS::default()))?;
+//! ```
+//!
+//! Implements class 1 and 2: OPTIONS, PROPFIND (depth 0 and 1), GET, HEAD,
+//! PUT, DELETE, MKCOL, COPY, MOVE, PROPPATCH, LOCK and UNLOCK. Dead
+//! properties are not stored; PROPPATCH reports success without keeping
+//! them, so clients that set timestamps carry on. Locks are exclusive write
+//! locks held by the adapter, never expire and are lost when it stops.
+//! Requests are not authenticated.
+
+use crate::checksum::xxh64;
+use crate::filesystem::FileSystem;
+use crate::http::{
+    self, escape, http_date, iso8601, parse_range, percent_encode, Request, Response,
+};
+use crate::path;
+use crate::types::{Error, FileInfo, Result};
+use std::collections::HashMap;
+use std::io;
+use std::net::TcpListener;
+
+const DAV_METHODS: &str =
+    "OPTIONS, PROPFIND, PROPPATCH, GET, HEAD, PUT, DELETE, MKCOL, COPY, MOVE, LOCK, UNLOCK";
+
+#[derive(Debug, Clone)]
+struct Lock {
+    token: String,
+    // Covers everything below the path too
+    infinite: bool,
+}
+
+/// WebDAV request handler serving a filesystem at the URL root
+pub struct WebDav<F> {
+    fs: F,
+    locks: HashMap<String, Lock>,
+    next_lock: u64,
+}
+
+impl<F: Default> Default for WebDav<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> WebDav<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            locks: HashMap::new(),
+            next_lock: 0,
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fs
+    }
+
+    pub fn inner_mut(&mut self) -> &mut F {
+        &mut self.fs
+    }
+
+    // The lock covering `path`, from the path itself or a depth-infinity
+    // lock on an ancestor
+    fn lock_on(&self, path: &str) -> Option<(&str, &Lock)> {
+        let mut p = path;
+        loop {
+            if let Some((root, lock)) = self.locks.get_key_value(p) {
+                if p == path || lock.infinite {
+                    return Some((root, lock));
+                }
+            }
+            if p == "/" {
+                return None;
+            }
+            p = parent(p);
+        }
+    }
+
+    // Fail with 423 unless the request's If header names the lock
+    // covering `path` (or a lock below it, for deletes and moves)
+    fn check_locks(
+        &self,
+        req: &Request,
+        path: &str,
+        subtree: bool,
+    ) -> std::result::Result<(), Response> {
+        let submitted = req.header("if").unwrap_or("");
+        let below = |p: &String| {
+            subtree
+                && (p.as_str() == path
+                    || p.starts_with(&format!("{}/", path.trim_end_matches('/'))))
+        };
+        let held = self
+            .lock_on(path)
+            .map(|(_, lock)| lock)
+            .into_iter()
+            .chain(self.locks.iter().filter(|(p, _)| below(p)).map(|(_, l)| l));
+        for lock in held {
+            if !submitted.contains(&lock.token) {
+                return Err(text(423, "resource is locked"));
+            }
+        }
+        Ok(())
+    }
+
+    fn drop_locks(&mut self, path: &str) {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.locks
+            .retain(|p, _| p.as_str() != path && !p.starts_with(&prefix));
+    }
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn child(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn text(status: u16, msg: &str) -> Response {
+    let mut resp = Response::new(status).header("Content-Type", "text/plain; charset=utf-8");
+    resp.body = msg.as_bytes().to_vec();
+    resp
+}
+
+fn from_error(e: &Error) -> Response {
+    let status = match e {
+        Error::NotFound => 404,
+        Error::PermissionDenied | Error::ReadOnly => 403,
+        Error::AlreadyExists => 405,
+        Error::IsDirectory | Error::NotDirectory | Error::NotEmpty => 409,
+        Error::InvalidInput(_) => 400,
+        Error::TooLarge => 413,
+        Error::Timeout | Error::Cancelled => 503,
+        Error::NotSupported(_) => 501,
+        Error::Io(_) | Error::Other(_) => 500,
+    };
+    text(status, &e.to_string())
+}
+
+fn href(path: &str, is_dir: bool) -> String {
+    let mut href = percent_encode(path);
+    if is_dir && path != "/" {
+        href.push('/');
+    }
+    href
+}
+
+fn etag(info: &FileInfo) -> String {
+    let key = format!("{}:{}", info.size, info.mod_time);
+    format!("\"{:016x}\"", xxh64(key.as_bytes(), 0))
+}
+
+fn lock_xml(root: &str, lock: &Lock) -> String {
+    format!(
+        "<D:activelock><D:locktype><D:write/></D:locktype>\
+         <D:lockscope><D:exclusive/></D:lockscope><D:depth>{}</D:depth>\
+         <D:timeout>Infinite</D:timeout><D:locktoken><D:href>{}</D:href></D:locktoken>\
+         <D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock>",
+        if lock.infinite { "infinity" } else { "0" },
+        lock.token,
+        escape(&percent_encode(root))
+    )
+}
+
+fn lock_prop(root: &str, lock: &Lock) -> String {
+    format!(
+        "<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery>{}</D:lockdiscovery></D:prop>",
+        lock_xml(root, lock)
+    )
+}
+
+fn multistatus(body: &str) -> Response {
+    Response::xml(
+        207,
+        format!("<D:multistatus xmlns:D=\"DAV:\">{}</D:multistatus>", body),
+    )
+}
+
+// The path part of a Destination header, which may be a full URL
+fn destination(req: &Request) -> Result<String> {
+    let dest = req
+        .header("destination")
+        .ok_or_else(|| Error::InvalidInput("missing Destination".to_string()))?;
+    let path = match dest.find("://") {
+        Some(i) => dest[i + 3..].find('/').map_or("/", |j| &dest[i + 3 + j..]),
+        None => dest,
+    };
+    path::canonicalize(&http::percent_decode(path, false))
+}
+
+impl<F: FileSystem> WebDav<F> {
+    /// Answer one request
+    pub fn handle(&mut self, req: &Request) -> Response {
+        let path = match path::canonicalize(&req.path) {
+            Ok(path) => path,
+            Err(e) => return from_error(&e),
+        };
+        let result = match req.method.as_str() {
+            "OPTIONS" => Ok(Response::new(200)
+                .header("DAV", "1, 2")
+                .header("MS-Author-Via", "DAV")
+                .header("Allow", DAV_METHODS)),
+            "PROPFIND" => self.propfind(req, &path),
+            "PROPPATCH" => self.proppatch(req, &path),
+            "GET" => self.get(req, &path, true),
+            "HEAD" => self.get(req, &path, false),
+            "PUT" => self.put(req, &path),
+            "DELETE" => self.delete(req, &path),
+            "MKCOL" => self.mkcol(req, &path),
+            "COPY" => self.copy_or_move(req, &path, false),
+            "MOVE" => self.copy_or_move(req, &path, true),
+            "LOCK" => self.lock(req, &path),
+            "UNLOCK" => Ok(self.unlock(req, &path)),
+            _ => Ok(text(405, "unsupported method").header("Allow", DAV_METHODS)),
+        };
+        result.unwrap_or_else(|e| from_error(&e))
+    }
+
+    fn exists(&self, path: &str) -> Result<Option<FileInfo>> {
+        match self.fs.stat(path) {
+            Ok(info) => Ok(Some(info)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn entry_xml(&self, path: &str, info: &FileInfo) -> String {
+        let name = if path == "/" { "" } else { &info.name };
+        let mut props = format!(
+            "<D:displayname>{}</D:displayname><D:getlastmodified>{}</D:getlastmodified>\
+             <D:creationdate>{}</D:creationdate>",
+            escape(name),
+            http_date(info.mod_time),
+            iso8601(info.mod_time)
+        );
+        if info.is_dir {
+            props.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            props.push_str(&format!(
+                "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>\
+                 <D:getcontenttype>application/octet-stream</D:getcontenttype>\
+                 <D:getetag>{}</D:getetag>",
+                info.size,
+                escape(&etag(info))
+            ));
+        }
+        props.push_str(
+            "<D:supportedlock><D:lockentry><D:lockscope><D:exclusive/></D:lockscope>\
+             <D:locktype><D:write/></D:locktype></D:lockentry></D:supportedlock>",
+        );
+        props.push_str("<D:lockdiscovery>");
+        if let Some((root, lock)) = self.lock_on(path) {
+            props.push_str(&lock_xml(root, lock));
+        }
+        props.push_str("</D:lockdiscovery>");
+        format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop>\
+             <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+            escape(&href(path, info.is_dir)),
+            props
+        )
+    }
+
+    // Every property is returned whatever the body asks for
+    fn propfind(&self, req: &Request, path: &str) -> Result<Response> {
+        let info = self.fs.stat(path)?;
+        let mut body = self.entry_xml(path, &info);
+        if info.is_dir {
+            match req.header("depth").unwrap_or("infinity") {
+                "0" => {}
+                "1" => {
+                    for entry in self.fs.readdir(path)? {
+                        body.push_str(&self.entry_xml(&child(path, &entry.name), &entry));
+                    }
+                }
+                _ => {
+                    return Ok(Response::xml(
+                        403,
+                        "<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>"
+                            .to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(multistatus(&body))
+    }
+
+    fn proppatch(&self, req: &Request, path: &str) -> Result<Response> {
+        if let Err(resp) = self.check_locks(req, path, false) {
+            return Ok(resp);
+        }
+        let info = self.fs.stat(path)?;
+        Ok(multistatus(&format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop/>\
+             <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+            escape(&href(path, info.is_dir))
+        )))
+    }
+
+    fn get(&self, req: &Request, path: &str, body: bool) -> Result<Response> {
+        let info = self.fs.stat(path)?;
+        if info.is_dir {
+            let mut page = String::from("<html><body><ul>");
+            for entry in self.fs.readdir(path)? {
+                let target = child(path, &entry.name);
+                page.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a></li>",
+                    escape(&href(&target, entry.is_dir)),
+                    escape(&entry.name)
+                ));
+            }
+            page.push_str("</ul></body></html>");
+            let mut resp = Response::new(200).header("Content-Type", "text/html; charset=utf-8");
+            if body {
+                resp.body = page.into_bytes();
+            }
+            return Ok(resp);
+        }
+        let (status, offset, size) =
+            match req.header("range").map(|r| (r, parse_range(r, info.size))) {
+                Some((_, Some((offset, size)))) => (206, offset, size),
+                Some((_, None)) => {
+                    return Ok(text(416, "invalid range")
+                        .header("Content-Range", format!("bytes */{}", info.size)))
+                }
+                None => (200, 0, info.size),
+            };
+        let mut resp = Response::new(status)
+            .header("Content-Type", "application/octet-stream")
+            .header("Last-Modified", http_date(info.mod_time))
+            .header("ETag", etag(&info))
+            .header("Accept-Ranges", "bytes");
+        if status == 206 {
+            resp = resp.header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", offset, offset + size - 1, info.size),
+            );
+        }
+        if body {
+            resp.body = self.fs.read(path, offset, size)?;
+        } else {
+            resp = resp.header("Content-Length", size.to_string());
+        }
+        Ok(resp)
+    }
+
+    fn put(&mut self, req: &Request, path: &str) -> Result<Response> {
+        if let Err(resp) = self.check_locks(req, path, false) {
+            return Ok(resp);
+        }
+        let existing = self.exists(path)?;
+        if existing.as_ref().is_some_and(|i| i.is_dir) {
+            return Ok(text(405, "cannot PUT a collection"));
+        }
+        if !self.exists(parent(path))?.is_some_and(|i| i.is_dir) {
+            return Ok(text(409, "parent collection does not exist"));
+        }
+        self.fs.write(path, &req.body)?;
+        Ok(Response::new(if existing.is_some() { 204 } else { 201 }))
+    }
+
+    fn delete(&mut self, req: &Request, path: &str) -> Result<Response> {
+        if path == "/" {
+            return Ok(text(403, "cannot delete the root"));
+        }
+        if let Err(resp) = self.check_locks(req, path, true) {
+            return Ok(resp);
+        }
+        if self.fs.stat(path)?.is_dir {
+            self.fs.remove_all(path)?;
+        } else {
+            self.fs.remove(path)?;
+        }
+        self.drop_locks(path);
+        Ok(Response::new(204))
+    }
+
+    fn mkcol(&mut self, req: &Request, path: &str) -> Result<Response> {
+        if !req.body.is_empty() {
+            return Ok(text(415, "MKCOL bodies are not supported"));
+        }
+        if let Err(resp) = self.check_locks(req, path, false) {
+            return Ok(resp);
+        }
+        if self.exists(path)?.is_some() {
+            return Ok(text(405, "resource exists"));
+        }
+        if !self.exists(parent(path))?.is_some_and(|i| i.is_dir) {
+            return Ok(text(409, "parent collection does not exist"));
+        }
+        self.fs.mkdir(path, 0o755)?;
+        Ok(Response::new(201))
+    }
+
+    fn copy_tree(&mut self, from: &str, to: &str, info: &FileInfo, recurse: bool) -> Result<()> {
+        if !info.is_dir {
+            let data = self.fs.read(from, 0, -1)?;
+            self.fs.write(to, &data)?;
+            return Ok(());
+        }
+        self.fs.mkdir(to, info.mode & 0o7777)?;
+        if recurse {
+            for entry in self.fs.readdir(from)? {
+                self.copy_tree(
+                    &child(from, &entry.name),
+                    &child(to, &entry.name),
+                    &entry,
+                    true,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_or_move(&mut self, req: &Request, path: &str, is_move: bool) -> Result<Response> {
+        let dest = destination(req)?;
+        if dest == path || dest.starts_with(&format!("{}/", path)) || path == "/" {
+            return Ok(text(403, "source and destination overlap"));
+        }
+        let info = self.fs.stat(path)?;
+        if is_move {
+            if let Err(resp) = self.check_locks(req, path, true) {
+                return Ok(resp);
+            }
+        }
+        if let Err(resp) = self.check_locks(req, &dest, true) {
+            return Ok(resp);
+        }
+        if !self.exists(parent(&dest))?.is_some_and(|i| i.is_dir) {
+            return Ok(text(409, "parent collection does not exist"));
+        }
+        let overwrite = !req
+            .header("overwrite")
+            .is_some_and(|v| v.eq_ignore_ascii_case("f"));
+        let replaced = match self.exists(&dest)? {
+            Some(_) if !overwrite => return Ok(text(412, "destination exists")),
+            Some(existing) => {
+                if existing.is_dir {
+                    self.fs.remove_all(&dest)?;
+                } else {
+                    self.fs.remove(&dest)?;
+                }
+                self.drop_locks(&dest);
+                true
+            }
+            None => false,
+        };
+        if is_move {
+            self.fs.rename(path, &dest)?;
+            self.drop_locks(path);
+        } else {
+            let recurse = req.header("depth") != Some("0");
+            self.copy_tree(path, &dest, &info, recurse)?;
+        }
+        Ok(Response::new(if replaced { 204 } else { 201 }))
+    }
+
+    // Refreshes come without a body and name the lock in the If header
+    fn lock(&mut self, req: &Request, path: &str) -> Result<Response> {
+        if req.body.is_empty() {
+            let submitted = req.header("if").unwrap_or("");
+            return Ok(match self.lock_on(path) {
+                Some((root, lock)) if submitted.contains(&lock.token) => {
+                    Response::xml(200, lock_prop(root, lock))
+                }
+                _ => text(412, "no matching lock to refresh"),
+            });
+        }
+        if self.lock_on(path).is_some() || self.check_locks(req, path, true).is_err() {
+            return Ok(text(423, "resource is locked"));
+        }
+        let created = match self.exists(path)? {
+            Some(_) => false,
+            None => {
+                if !self.exists(parent(path))?.is_some_and(|i| i.is_dir) {
+                    return Ok(text(409, "parent collection does not exist"));
+                }
+                // Locking an unmapped URL creates an empty resource
+                self.fs.write(path, &[])?;
+                true
+            }
+        };
+        self.next_lock += 1;
+        let lock = Lock {
+            token: format!(
+                "opaquelocktoken:{:016x}",
+                xxh64(path.as_bytes(), self.next_lock)
+            ),
+            infinite: req.header("depth") != Some("0"),
+        };
+        let resp = Response::xml(if created { 201 } else { 200 }, lock_prop(path, &lock))
+            .header("Lock-Token", format!("<{}>", lock.token));
+        self.locks.insert(path.to_string(), lock);
+        Ok(resp)
+    }
+
+    fn unlock(&mut self, req: &Request, path: &str) -> Response {
+        let token = req
+            .header("lock-token")
+            .unwrap_or("")
+            .trim_matches(|c| c == '<' || c == '>');
+        match self.lock_on(path) {
+            Some((root, lock)) if lock.token == token => {
+                let root = root.to_string();
+                self.locks.remove(&root);
+                Response::new(204)
+            }
+            _ => text(409, "lock token does not match"),
+        }
+    }
+}
+
+/// Serve requests from `listener` until accepting fails
+pub fn serve<F: FileSystem>(listener: TcpListener, mut dav: WebDav<F>) -> io::Result<()> {
+    http::serve(listener, |req| dav.handle(req))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::slice_range;
+    use std::collections::BTreeMap;
+
+    // Directories are keys ending in '/'
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(slice_range(data, offset, size).to_vec())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(Vec::new())
+        }
+
+        fn mkdir(&mut self, path: &str, _perm: u32) -> Result<()> {
+            self.files.insert(format!("{}/", path), Vec::new());
+            Ok(())
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            self.files.remove(path).map(|_| ()).ok_or(Error::NotFound)
+        }
+
+        fn remove_all(&mut self, path: &str) -> Result<()> {
+            let prefix = format!("{}/", path);
+            self.files
+                .retain(|k, _| k != path && !k.starts_with(&prefix));
+            Ok(())
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            let moved: Vec<_> = self
+                .files
+                .keys()
+                .filter(|k| *k == old_path || k.starts_with(&format!("{}/", old_path)))
+                .cloned()
+                .collect();
+            for k in moved {
+                let data = self.files.remove(&k).unwrap();
+                self.files
+                    .insert(format!("{}{}", new_path, &k[old_path.len()..]), data);
+            }
+            Ok(())
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let name = path.rsplit('/').next().unwrap_or("");
+            if path == "/" || self.files.contains_key(&format!("{}/", path)) {
+                return Ok(FileInfo::dir(name, 0o755));
+            }
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(name, data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            let prefix = if path == "/" {
+                "/".to_string()
+            } else {
+                format!("{}/", path)
+            };
+            Ok(self
+                .files
+                .keys()
+                .filter_map(|k| k.strip_prefix(&prefix))
+                .filter(|rest| !rest.is_empty() && !rest.trim_end_matches('/').contains('/'))
+                .map(|rest| {
+                    self.stat(&format!("{}{}", prefix, rest.trim_end_matches('/')))
+                        .unwrap()
+                })
+                .collect())
+        }
+    }
+
+    fn req(method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_vec(),
+            ..Request::default()
+        }
+    }
+
+    fn body(resp: &Response) -> String {
+        String::from_utf8(resp.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_collections_and_files() {
+        let mut dav = WebDav::new(MemFS::default());
+        assert_eq!(dav.handle(&req("PUT", "/d/f", &[], b"x")).status, 409);
+        assert_eq!(dav.handle(&req("MKCOL", "/d", &[], b"")).status, 201);
+        assert_eq!(dav.handle(&req("MKCOL", "/d", &[], b"")).status, 405);
+        assert_eq!(dav.handle(&req("PUT", "/d/a b", &[], b"hello")).status, 201);
+        assert_eq!(
+            dav.handle(&req("PUT", "/d/a b", &[], b"hello!")).status,
+            204
+        );
+
+        let list = dav.handle(&req("PROPFIND", "/d", &[("depth", "1")], b""));
+        assert_eq!(list.status, 207);
+        let xml = body(&list);
+        assert!(xml.contains("<D:href>/d/</D:href>") && xml.contains("<D:collection/>"));
+        assert!(xml.contains("<D:href>/d/a%20b</D:href>"));
+        assert!(xml.contains("<D:getcontentlength>6</D:getcontentlength>"));
+        let only = body(&dav.handle(&req("PROPFIND", "/d", &[("depth", "0")], b"")));
+        assert!(!only.contains("a%20b"));
+        assert_eq!(dav.handle(&req("PROPFIND", "/d", &[], b"")).status, 403);
+
+        let get = dav.handle(&req("GET", "/d/a b", &[("range", "bytes=0-4")], b""));
+        assert_eq!((get.status, get.body.as_slice()), (206, &b"hello"[..]));
+
+        let copy = [("destination", "http://localhost/e"), ("overwrite", "F")];
+        assert_eq!(dav.handle(&req("COPY", "/d", &copy, b"")).status, 201);
+        assert_eq!(dav.handle(&req("COPY", "/d", &copy, b"")).status, 412);
+        assert_eq!(dav.inner().files["/e/a b"], b"hello!");
+        let mv = [("destination", "/d/b")];
+        assert_eq!(dav.handle(&req("MOVE", "/d/a b", &mv, b"")).status, 201);
+        assert!(dav.inner().files.contains_key("/d/b"));
+        assert_eq!(
+            dav.handle(&req("MOVE", "/d", &[("destination", "/d/x")], b""))
+                .status,
+            403
+        );
+
+        assert_eq!(dav.handle(&req("DELETE", "/e", &[], b"")).status, 204);
+        assert_eq!(dav.handle(&req("GET", "/e/a b", &[], b"")).status, 404);
+        assert_eq!(dav.handle(&req("GET", "/../x", &[], b"")).status, 400);
+    }
+
+    #[test]
+    fn test_locking() {
+        let mut dav = WebDav::new(MemFS::default());
+        let info = b"<?xml version=\"1.0\"?><D:lockinfo xmlns:D=\"DAV:\"/>";
+        let lock = dav.handle(&req("LOCK", "/f", &[("depth", "0")], info));
+        assert_eq!(lock.status, 201);
+        let token = lock
+            .headers
+            .iter()
+            .find(|(k, _)| k == "Lock-Token")
+            .map(|(_, v)| v.clone())
+            .unwrap();
+        assert_eq!(dav.handle(&req("LOCK", "/f", &[], info)).status, 423);
+
+        assert_eq!(dav.handle(&req("PUT", "/f", &[], b"x")).status, 423);
+        assert_eq!(dav.handle(&req("DELETE", "/f", &[], b"")).status, 423);
+        let cond = format!("({})", token);
+        assert_eq!(
+            dav.handle(&req("PUT", "/f", &[("if", &cond)], b"x")).status,
+            204
+        );
+        assert_eq!(
+            dav.handle(&req("LOCK", "/f", &[("if", &cond)], b"")).status,
+            200
+        );
+        let found = body(&dav.handle(&req("PROPFIND", "/f", &[("depth", "0")], b"")));
+        assert!(found.contains(token.trim_matches(|c| c == '<' || c == '>')));
+
+        assert_eq!(
+            dav.handle(&req("UNLOCK", "/f", &[("lock-token", "<nope>")], b""))
+                .status,
+            409
+        );
+        assert_eq!(
+            dav.handle(&req("UNLOCK", "/f", &[("lock-token", &token)], b""))
+                .status,
+            204
+        );
+        assert_eq!(dav.handle(&req("PUT", "/f", &[], b"y")).status, 204);
+
+        // A depth-infinity lock on a collection covers its members
+        dav.handle(&req("MKCOL", "/d", &[], b""));
+        assert_eq!(dav.handle(&req("LOCK", "/d", &[], info)).status, 200);
+        assert_eq!(dav.handle(&req("PUT", "/d/g", &[], b"z")).status, 423);
+        assert_eq!(
+            dav.handle(&req("MOVE", "/f", &[("destination", "/d/f")], b""))
+                .status,
+            423
+        );
+    }
+}
@@ -3,6 +3,8 @@
 //! Returns a single file with "Hello World" content
 //! Also demonstrates accessing the host filesystem
 
+use agfs_wasm_ffi::host_fs::{HostCapabilities, HostVerb};
+use agfs_wasm_ffi::path;
 use agfs_wasm_ffi::prelude::*;
 
 #[derive(Default)]
@@ -10,6 +12,21 @@ pub struct HelloFS {
     host_prefix: String,
 }
 
+impl HelloFS {
+    /// Host path behind a `/host` path, if host access is configured
+    fn host_path(&self, path: &str) -> Option<String> {
+        if self.host_prefix.is_empty() {
+            return None;
+        }
+        match path {
+            "/host" => Some(self.host_prefix.clone()),
+            p => p
+                .strip_prefix("/host/")
+                .and_then(|rest| path::join(&self.host_prefix, rest).ok()),
+        }
+    }
+}
+
 impl FileSystem for HelloFS {
     fn name(&self) -> &str {
         "hellofs-wasm"
@@ -29,13 +46,26 @@ impl FileSystem for HelloFS {
         Ok(())
     }
 
+    fn reconfigure(&mut self, config: &Config) -> Result<()> {
+        // HostFS keeps the capabilities declared at initialize, so a new
+        // prefix has to lie within the old one
+        let old = self.host_prefix.as_str();
+        let prefix = config.get_str("host_prefix").unwrap_or(old);
+        let within = prefix == old
+            || (!old.is_empty()
+                && prefix.strip_prefix(old).is_some_and(|rest| rest.starts_with('/')));
+        if !within {
+            return Err(Error::PermissionDenied);
+        }
+        self.initialize(config)
+    }
+
     fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
         match path {
             "/hello.txt" => Ok(b"Hello World\n".to_vec()),
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
+                let full_path = path::join(&self.host_prefix, p.strip_prefix("/host").unwrap())?;
                 HostFS::read(&full_path, offset, size)
                     .map_err(|e| Error::Other(format!("host fs: {}", e)))
             }
@@ -51,11 +81,15 @@ impl FileSystem for HelloFS {
                 Ok(FileInfo::dir("host", 0o755))
             }
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
-                // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
-                let host_info = HostFS::stat(&full_path)
-                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
+                // Proxy to host filesystem; symlinks are reported as links
+                // so that readlink and symlinked trees work through /host.
+                // Host mounts without links answer lstat as not supported
+                let full_path = path::join(&self.host_prefix, p.strip_prefix("/host").unwrap())?;
+                let host_info = match HostFS::stat_no_follow(&full_path) {
+                    Err(Error::NotSupported(_)) => HostFS::stat(&full_path),
+                    result => result,
+                }
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
 
                 // Convert and return
                 Ok(FileInfo {
@@ -65,12 +99,98 @@ impl FileSystem for HelloFS {
                     mod_time: host_info.mod_time,
                     is_dir: host_info.is_dir,
                     meta: host_info.meta,
+                    xattrs: host_info.xattrs,
+                    nlink: host_info.nlink,
+                    stat_valid_secs: host_info.stat_valid_secs,
                 })
             }
             _ => Err(Error::NotFound),
         }
     }
 
+    fn host_capabilities(&self) -> Option<HostCapabilities> {
+        // Only the configured prefix is ever proxied; without one, nothing is
+        let caps = HostCapabilities::new();
+        if self.host_prefix.is_empty() {
+            return Some(caps);
+        }
+        let verbs = [HostVerb::Read, HostVerb::Write, HostVerb::Delete];
+        Some(caps.allow(&self.host_prefix, &verbs))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        // Everything writable lives under /host
+        if self.host_prefix.is_empty() {
+            return Capabilities::NONE;
+        }
+        Capabilities::WRITABLE | Capabilities::SYMLINKS | Capabilities::HARD_LINKS
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        // /hello.txt never changes; host files can at any time
+        if self.host_prefix.is_empty() {
+            return CachePolicy::static_for(3600);
+        }
+        CachePolicy::default()
+    }
+
+    fn stat_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        let full_path = self.host_path(path).filter(|_| path != "/host")?;
+        Some(HostFS::stat_raw(&full_path).map_err(|e| Error::Other(format!("host fs: {}", e))))
+    }
+
+    fn readdir_passthrough(&self, path: &str) -> Option<Result<RawJson>> {
+        let full_path = self.host_path(path)?;
+        Some(HostFS::readdir_raw(&full_path).map_err(|e| Error::Other(format!("host fs: {}", e))))
+    }
+
+    fn access(&self, path: &str, mode: u32) -> Result<()> {
+        match self.host_path(path) {
+            // Denials have to reach the caller as they are
+            Some(full_path) => HostFS::access(&full_path, mode),
+            None if mode & 2 != 0 => Err(Error::ReadOnly),
+            None => self.stat(path).map(|_| ()),
+        }
+    }
+
+    fn seek_data(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        match self.host_path(path) {
+            // Host files may be sparse
+            Some(full_path) => HostFS::seek_data(&full_path, offset)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            None => {
+                let size = self.stat(path)?.size;
+                Ok((offset < size).then_some(offset))
+            }
+        }
+    }
+
+    fn seek_hole(&self, path: &str, offset: i64) -> Result<Option<i64>> {
+        match self.host_path(path) {
+            Some(full_path) => HostFS::seek_hole(&full_path, offset)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            None => {
+                let size = self.stat(path)?.size;
+                Ok((offset <= size).then_some(size))
+            }
+        }
+    }
+
+    fn lock(&mut self, path: &str, kind: LockKind) -> Result<()> {
+        match self.host_path(path) {
+            // Conflicts have to reach the caller as they are
+            Some(full_path) => HostFS::lock(&full_path, kind),
+            None => Err(Error::NotSupported("lock".to_string())),
+        }
+    }
+
+    fn unlock(&mut self, path: &str) -> Result<()> {
+        match self.host_path(path) {
+            Some(full_path) => HostFS::unlock(&full_path),
+            None => Err(Error::NotSupported("unlock".to_string())),
+        }
+    }
+
     fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
         match path {
             "/" => {
@@ -94,13 +214,15 @@ impl FileSystem for HelloFS {
                         mod_time: info.mod_time,
                         is_dir: info.is_dir,
                         meta: info.meta,
+                        xattrs: info.xattrs,
+                        nlink: info.nlink,
+                        stat_valid_secs: info.stat_valid_secs,
                     })
                     .collect())
             }
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
+                let full_path = path::join(&self.host_prefix, p.strip_prefix("/host").unwrap())?;
                 let host_infos = HostFS::readdir(&full_path)
                     .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
 
@@ -113,6 +235,9 @@ impl FileSystem for HelloFS {
                         mod_time: info.mod_time,
                         is_dir: info.is_dir,
                         meta: info.meta,
+                        xattrs: info.xattrs,
+                        nlink: info.nlink,
+                        stat_valid_secs: info.stat_valid_secs,
                     })
                     .collect())
             }
@@ -123,8 +248,7 @@ impl FileSystem for HelloFS {
     fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
         if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
             HostFS::write(&full_path, data)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -132,11 +256,27 @@ impl FileSystem for HelloFS {
         }
     }
 
+    fn write_at(&mut self, path: &str, offset: i64, data: &[u8]) -> Result<i64> {
+        match self.host_path(path) {
+            Some(full_path) => HostFS::write_at(&full_path, offset, data)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            None => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn append(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        match self.host_path(path) {
+            Some(full_path) => HostFS::append(&full_path, data)
+                .map(|_| data.len() as i64)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            None => Err(Error::PermissionDenied),
+        }
+    }
+
     fn create(&mut self, path: &str) -> Result<()> {
         if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
             HostFS::create(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -144,11 +284,21 @@ impl FileSystem for HelloFS {
         }
     }
 
+    fn create_with_flags(&mut self, path: &str, flags: CreateFlags) -> Result<()> {
+        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+            // Proxy to host filesystem
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
+            HostFS::create_with_flags(&full_path, flags)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+
     fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
         if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
             HostFS::mkdir(&full_path, perm)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -159,8 +309,7 @@ impl FileSystem for HelloFS {
     fn remove(&mut self, path: &str) -> Result<()> {
         if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
             HostFS::remove(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -171,8 +320,7 @@ impl FileSystem for HelloFS {
     fn remove_all(&mut self, path: &str) -> Result<()> {
         if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
             HostFS::remove_all(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -194,9 +342,99 @@ impl FileSystem for HelloFS {
         }
     }
 
+    fn rename_with_flags(
+        &mut self,
+        old_path: &str,
+        new_path: &str,
+        flags: RenameFlags,
+    ) -> Result<()> {
+        match (self.host_path(old_path), self.host_path(new_path)) {
+            (Some(full_old_path), Some(full_new_path)) => {
+                HostFS::rename_with_flags(&full_old_path, &full_new_path, flags)
+                    .map_err(|e| Error::Other(format!("host fs: {}", e)))
+            }
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
     fn chmod(&mut self, _path: &str, _mode: u32) -> Result<()> {
         Ok(())
     }
+
+    fn chown(&mut self, path: &str, uid: u32, gid: u32) -> Result<()> {
+        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+            // Proxy to host filesystem
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
+            HostFS::chown(&full_path, uid, gid)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+
+    fn set_times(&mut self, path: &str, atime: i64, mtime: i64) -> Result<()> {
+        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+            // Proxy to host filesystem
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
+            HostFS::set_times(&full_path, atime, mtime)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+
+    fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+            // Proxy to host filesystem
+            let full_path = path::join(&self.host_prefix, path.strip_prefix("/host").unwrap())?;
+            HostFS::truncate(&full_path, size)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+
+    fn allocate(&mut self, path: &str, offset: i64, len: i64) -> Result<()> {
+        match self.host_path(path) {
+            Some(full_path) => HostFS::allocate(&full_path, offset, len)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            None => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<()> {
+        match (self.host_path(existing), self.host_path(new)) {
+            (Some(existing), Some(new)) if existing != self.host_prefix && new != self.host_prefix => {
+                HostFS::link(&existing, &new).map_err(|e| Error::Other(format!("host fs: {}", e)))
+            }
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        if link_path.starts_with("/host/") && !self.host_prefix.is_empty() {
+            // Proxy to host filesystem; the target is stored as given
+            let full_path = path::join(&self.host_prefix, link_path.strip_prefix("/host").unwrap())?;
+            HostFS::symlink(target, &full_path)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        match self.host_path(path) {
+            Some(full_path) if path != "/host" => HostFS::readlink(&full_path)
+                .map_err(|e| Error::Other(format!("host fs: {}", e))),
+            _ if matches!(path, "/" | "/hello.txt" | "/host") => {
+                Err(Error::InvalidInput("not a symlink".to_string()))
+            }
+            _ => Err(Error::NotFound),
+        }
+    }
 }
 
-export_plugin!(HelloFS);
+export_plugin!(HelloFS, manifest {
+    name: "hellofs-wasm",
+    version: env!("CARGO_PKG_VERSION"),
+});
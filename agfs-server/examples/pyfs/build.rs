@@ -0,0 +1,31 @@
+//! Link against the embeddable libpython
+//!
+//! Flags come from `python3-config --ldflags --embed`; set `PYTHON_CONFIG`
+//! to use a different interpreter's config script.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PYTHON_CONFIG");
+    let config = env::var("PYTHON_CONFIG").unwrap_or_else(|_| "python3-config".to_string());
+    let output = Command::new(&config)
+        .args(["--ldflags", "--embed"])
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run {}: {}", config, e));
+    assert!(
+        output.status.success(),
+        "{} --ldflags --embed failed",
+        config
+    );
+
+    for flag in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+        if let Some(dir) = flag.strip_prefix("-L") {
+            println!("cargo:rustc-link-search=native={}", dir);
+            // Let the plugin find libpython without LD_LIBRARY_PATH
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", dir);
+        } else if let Some(lib) = flag.strip_prefix("-l") {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+    }
+}
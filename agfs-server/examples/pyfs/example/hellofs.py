@@ -0,0 +1,52 @@
+"""Example PyFS module: /hello plus writable files kept in memory.
+
+Mount with {"module": "hellofs", "path": "<this directory>"}.
+"""
+
+files = {"hello": b"Hello from Python!\n"}
+
+
+def _name(path):
+    name = path.lstrip("/")
+    if "/" in name:
+        raise FileNotFoundError(path)
+    return name
+
+
+def read(path, offset, size):
+    data = files.get(_name(path))
+    if data is None:
+        raise FileNotFoundError(path)
+    end = len(data) if size < 0 else offset + size
+    return data[offset:end]
+
+
+def stat(path):
+    if path == "/":
+        return {"name": "/", "is_dir": True}
+    name = _name(path)
+    if name not in files:
+        raise FileNotFoundError(path)
+    return {"name": name, "size": len(files[name]), "mode": 0o644}
+
+
+def readdir(path):
+    if path != "/":
+        raise NotADirectoryError(path)
+    return [stat("/" + name) for name in sorted(files)]
+
+
+def write(path, data):
+    files[_name(path)] = bytes(data)
+
+
+def create(path):
+    name = _name(path)
+    if name in files:
+        raise FileExistsError(path)
+    files[name] = b""
+
+
+def remove(path):
+    if files.pop(_name(path), None) is None:
+        raise FileNotFoundError(path)
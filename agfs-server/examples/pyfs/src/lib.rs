@@ -0,0 +1,283 @@
+//! PyFS: an agfs plugin whose filesystem is a Python module
+//!
+//! The plugin embeds CPython and forwards every call to module-level
+//! functions of the module named in the mount config:
+//!
+//! ```json
+//! {"module": "hellofs", "path": "/etc/agfs/python"}
+//! ```
+//!
+//! `path` (optional) is put in front of `sys.path`. See `example/hellofs.py`
+//! for the functions a module provides.
+
+mod python;
+
+use agfs_ffi::prelude::*;
+use python::{with_gil, Obj};
+
+const README: &str = r#"# PyFS
+
+Serves a filesystem implemented by a Python module.
+
+## Configuration
+
+- `module` (required): module to import, e.g. `hellofs` or `pkg.fs`
+- `path`: directory to put in front of `sys.path`
+
+## Module functions
+
+Required: `read(path, offset, size)` returning `str` or `bytes`,
+`stat(path)` returning an entry and `readdir(path)` returning a list of
+entries. An entry is a dict with `name` and optionally `size`, `mode`,
+`is_dir` and `mod_time`.
+
+Optional: `initialize(config_json)`, `shutdown()`, `write(path, data)`,
+`create(path)`, `mkdir(path, mode)`, `remove(path)`, `remove_all(path)`,
+`rename(old, new)` and `chmod(path, mode)`. Missing ones, or ones raising
+`NotImplementedError`, make the operation unsupported.
+
+`FileNotFoundError`, `FileExistsError`, `PermissionError`,
+`NotADirectoryError` and `IsADirectoryError` map to the matching agfs
+errors.
+"#;
+
+/// Filesystem backed by a Python module
+#[derive(Default)]
+pub struct PyFS {
+    module: Option<Obj>,
+}
+
+// The module is only touched with the GIL held, from calls the export glue
+// already serializes.
+unsafe impl Send for PyFS {}
+unsafe impl Sync for PyFS {}
+
+impl Drop for PyFS {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            with_gil(|| drop(module));
+        }
+    }
+}
+
+struct Settings {
+    module: String,
+    path: Option<String>,
+}
+
+fn parse_config(config: &str) -> Result<Settings> {
+    let config: serde_json::Value = serde_json::from_str(config)
+        .map_err(|e| FileSystemError::Custom(format!("invalid config: {}", e)))?;
+    let module = config
+        .get("module")
+        .and_then(|m| m.as_str())
+        .ok_or_else(|| FileSystemError::Custom("config needs a \"module\" name".to_string()))?;
+    Ok(Settings {
+        module: module.to_string(),
+        path: config
+            .get("path")
+            .and_then(|p| p.as_str())
+            .map(str::to_string),
+    })
+}
+
+fn file_info(entry: &Obj) -> Result<FileInfo> {
+    let name = entry
+        .get_item("name")?
+        .ok_or_else(|| FileSystemError::Custom("entry has no name".to_string()))?
+        .to_string()?;
+    let is_dir = match entry.get_item("is_dir")? {
+        Some(v) => v.is_true()?,
+        None => false,
+    };
+    let int = |key: &str, default: i64| -> Result<i64> {
+        entry.get_item(key)?.map_or(Ok(default), |v| v.to_i64())
+    };
+    let mode = int("mode", if is_dir { 0o755 } else { 0o644 })? as u32;
+    let info = if is_dir {
+        FileInfo::directory(name, mode)
+    } else {
+        FileInfo::file(name, int("size", 0)?, mode)
+    };
+    Ok(info.with_mod_time(int("mod_time", 0)?))
+}
+
+impl PyFS {
+    fn module(&self) -> Result<&Obj> {
+        self.module
+            .as_ref()
+            .ok_or_else(|| FileSystemError::Custom("pyfs is not initialized".to_string()))
+    }
+
+    // Call a required module function
+    fn call<T>(
+        &self,
+        name: &str,
+        args: impl FnOnce() -> Result<Vec<Obj>>,
+        f: impl FnOnce(Obj) -> Result<T>,
+    ) -> Result<T> {
+        with_gil(|| f(self.module()?.call_method(name, args()?)?))
+    }
+
+    // Call an optional module function; a missing one means read-only
+    fn call_optional(&self, name: &str, args: impl FnOnce() -> Result<Vec<Obj>>) -> Result<()> {
+        with_gil(|| {
+            let module = self.module()?;
+            if !module.has_attr(name) {
+                return Err(FileSystemError::ReadOnly);
+            }
+            module.call_method(name, args()?).map(|_| ())
+        })
+    }
+}
+
+impl FileSystem for PyFS {
+    fn name(&self) -> &str {
+        "pyfs"
+    }
+
+    fn readme(&self) -> &str {
+        README
+    }
+
+    fn validate(&self, config: &str) -> Result<()> {
+        parse_config(config).map(|_| ())
+    }
+
+    fn initialize(&mut self, config: &str) -> Result<()> {
+        let settings = parse_config(config)?;
+        let module = with_gil(|| -> Result<Obj> {
+            if let Some(path) = &settings.path {
+                python::prepend_sys_path(path)?;
+            }
+            let module = Obj::import(&settings.module)?;
+            for required in ["read", "stat", "readdir"] {
+                if !module.has_attr(required) {
+                    return Err(FileSystemError::Custom(format!(
+                        "module {} has no {}()",
+                        settings.module, required
+                    )));
+                }
+            }
+            if module.has_attr("initialize") {
+                module.call_method("initialize", vec![Obj::str(config)?])?;
+            }
+            Ok(module)
+        })?;
+        if let Some(old) = self.module.replace(module) {
+            with_gil(|| drop(old));
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        let Some(module) = self.module.take() else {
+            return Ok(());
+        };
+        with_gil(|| {
+            let result = if module.has_attr("shutdown") {
+                module.call_method("shutdown", Vec::new()).map(|_| ())
+            } else {
+                Ok(())
+            };
+            drop(module);
+            result
+        })
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<String> {
+        let data = self.call(
+            "read",
+            || Ok(vec![Obj::str(path)?, Obj::int(offset)?, Obj::int(size)?]),
+            |data| data.to_bytes(),
+        )?;
+        String::from_utf8(data)
+            .map_err(|_| FileSystemError::IoError("read returned non-UTF-8 data".to_string()))
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.call(
+            "stat",
+            || Ok(vec![Obj::str(path)?]),
+            |entry| file_info(&entry),
+        )
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.call(
+            "readdir",
+            || Ok(vec![Obj::str(path)?]),
+            |entries| entries.iter()?.iter().map(file_info).collect(),
+        )
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.call_optional("write", || Ok(vec![Obj::str(path)?, Obj::bytes(data)?]))
+    }
+
+    fn create(&self, path: &str) -> Result<()> {
+        self.call_optional("create", || Ok(vec![Obj::str(path)?]))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.call_optional("mkdir", || {
+            Ok(vec![Obj::str(path)?, Obj::int(mode as i64)?])
+        })
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        self.call_optional("remove", || Ok(vec![Obj::str(path)?]))
+    }
+
+    fn remove_all(&self, path: &str) -> Result<()> {
+        self.call_optional("remove_all", || Ok(vec![Obj::str(path)?]))
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.call_optional("rename", || {
+            Ok(vec![Obj::str(old_path)?, Obj::str(new_path)?])
+        })
+    }
+
+    fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.call_optional("chmod", || {
+            Ok(vec![Obj::str(path)?, Obj::int(mode as i64)?])
+        })
+    }
+}
+
+export_plugin!(PyFS);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_module() {
+        let example = concat!(env!("CARGO_MANIFEST_DIR"), "/example");
+        let mut fs = PyFS::default();
+        assert!(fs.validate("{}").is_err());
+        let config = format!(r#"{{"module": "hellofs", "path": "{}"}}"#, example);
+        fs.initialize(&config).unwrap();
+
+        assert_eq!(fs.read("/hello", 0, -1).unwrap(), "Hello from Python!\n");
+        assert_eq!(fs.read("/hello", 6, 4).unwrap(), "from");
+        assert_eq!(fs.read("/missing", 0, -1), Err(FileSystemError::NotFound));
+        assert!(fs.stat("/").unwrap().is_dir);
+
+        fs.write("/notes", b"hi").unwrap();
+        let names: Vec<_> = fs
+            .readdir("/")
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, ["hello", "notes"]);
+        assert_eq!(fs.stat("/notes").unwrap().size, 2);
+        assert_eq!(fs.mkdir("/d", 0o755), Err(FileSystemError::ReadOnly));
+        assert_eq!(fs.create("/hello"), Err(FileSystemError::AlreadyExists));
+
+        fs.shutdown().unwrap();
+        assert!(fs.read("/hello", 0, -1).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Minimal bindings to the CPython C API
+//!
+//! Only what the plugin needs: importing a module, calling its functions,
+//! converting a handful of types and mapping exceptions. Everything here
+//! must run with the GIL held; `with_gil` takes care of that.
+
+use agfs_ffi::FileSystemError;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::sync::Once;
+
+#[repr(C)]
+pub struct PyObject {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn Py_IsInitialized() -> c_int;
+    fn Py_InitializeEx(initsigs: c_int);
+    fn PyEval_SaveThread() -> *mut c_void;
+    fn PyGILState_Ensure() -> c_int;
+    fn PyGILState_Release(state: c_int);
+
+    fn Py_DecRef(o: *mut PyObject);
+    fn PyImport_ImportModule(name: *const c_char) -> *mut PyObject;
+    fn PyObject_GetAttrString(o: *mut PyObject, name: *const c_char) -> *mut PyObject;
+    fn PyObject_HasAttrString(o: *mut PyObject, name: *const c_char) -> c_int;
+    fn PyObject_CallObject(callable: *mut PyObject, args: *mut PyObject) -> *mut PyObject;
+    fn PyObject_IsTrue(o: *mut PyObject) -> c_int;
+    fn PyObject_Str(o: *mut PyObject) -> *mut PyObject;
+    fn PyObject_GetIter(o: *mut PyObject) -> *mut PyObject;
+    fn PyIter_Next(o: *mut PyObject) -> *mut PyObject;
+    fn PyMapping_GetItemString(o: *mut PyObject, key: *const c_char) -> *mut PyObject;
+
+    fn PyTuple_New(len: isize) -> *mut PyObject;
+    fn PyTuple_SetItem(tuple: *mut PyObject, pos: isize, item: *mut PyObject) -> c_int;
+    fn PyUnicode_FromStringAndSize(s: *const c_char, len: isize) -> *mut PyObject;
+    fn PyUnicode_AsUTF8AndSize(o: *mut PyObject, len: *mut isize) -> *const c_char;
+    fn PyBytes_FromStringAndSize(s: *const c_char, len: isize) -> *mut PyObject;
+    fn PyBytes_AsStringAndSize(o: *mut PyObject, buf: *mut *mut c_char, len: *mut isize) -> c_int;
+    fn PyLong_FromLongLong(v: i64) -> *mut PyObject;
+    fn PyLong_AsLongLong(o: *mut PyObject) -> i64;
+
+    fn PySys_GetObject(name: *const c_char) -> *mut PyObject;
+    fn PyList_Insert(list: *mut PyObject, index: isize, item: *mut PyObject) -> c_int;
+
+    fn PyErr_Occurred() -> *mut PyObject;
+    fn PyErr_Fetch(
+        kind: *mut *mut PyObject,
+        value: *mut *mut PyObject,
+        traceback: *mut *mut PyObject,
+    );
+    fn PyErr_Clear();
+    fn PyErr_GivenExceptionMatches(given: *mut PyObject, exc: *mut PyObject) -> c_int;
+
+    static mut PyExc_FileNotFoundError: *mut PyObject;
+    static mut PyExc_FileExistsError: *mut PyObject;
+    static mut PyExc_PermissionError: *mut PyObject;
+    static mut PyExc_NotADirectoryError: *mut PyObject;
+    static mut PyExc_IsADirectoryError: *mut PyObject;
+    static mut PyExc_NotImplementedError: *mut PyObject;
+}
+
+pub type Result<T> = std::result::Result<T, FileSystemError>;
+
+static INIT: Once = Once::new();
+
+/// Run `f` with the GIL held, starting the interpreter on first use
+///
+/// The interpreter is started without signal handlers (the host owns
+/// those) and the GIL is released again right away, so any thread can
+/// enter through here.
+pub fn with_gil<T>(f: impl FnOnce() -> T) -> T {
+    INIT.call_once(|| unsafe {
+        if Py_IsInitialized() == 0 {
+            Py_InitializeEx(0);
+            PyEval_SaveThread();
+        }
+    });
+    unsafe {
+        let state = PyGILState_Ensure();
+        let result = f();
+        PyGILState_Release(state);
+        result
+    }
+}
+
+/// Owned reference to a Python object; only touch it with the GIL held
+pub struct Obj(ptr::NonNull<PyObject>);
+
+impl Drop for Obj {
+    fn drop(&mut self) {
+        // Objects are dropped inside `with_gil` closures
+        unsafe { Py_DecRef(self.0.as_ptr()) }
+    }
+}
+
+fn c_string(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|_| FileSystemError::InvalidPath)
+}
+
+impl Obj {
+    // Take ownership of a new reference, turning NULL into the pending
+    // Python exception
+    fn new(ptr: *mut PyObject) -> Result<Obj> {
+        ptr::NonNull::new(ptr).map(Obj).ok_or_else(take_error)
+    }
+
+    fn as_ptr(&self) -> *mut PyObject {
+        self.0.as_ptr()
+    }
+
+    /// Import a module by dotted name
+    pub fn import(name: &str) -> Result<Obj> {
+        let name = c_string(name)?;
+        Obj::new(unsafe { PyImport_ImportModule(name.as_ptr()) })
+    }
+
+    pub fn str(s: &str) -> Result<Obj> {
+        Obj::new(unsafe {
+            PyUnicode_FromStringAndSize(s.as_ptr() as *const c_char, s.len() as isize)
+        })
+    }
+
+    pub fn bytes(b: &[u8]) -> Result<Obj> {
+        Obj::new(unsafe {
+            PyBytes_FromStringAndSize(b.as_ptr() as *const c_char, b.len() as isize)
+        })
+    }
+
+    pub fn int(v: i64) -> Result<Obj> {
+        Obj::new(unsafe { PyLong_FromLongLong(v) })
+    }
+
+    /// Whether the object has attribute `name`
+    pub fn has_attr(&self, name: &str) -> bool {
+        let Ok(name) = c_string(name) else {
+            return false;
+        };
+        unsafe { PyObject_HasAttrString(self.as_ptr(), name.as_ptr()) == 1 }
+    }
+
+    /// Call attribute `name` with positional `args`
+    pub fn call_method(&self, name: &str, args: Vec<Obj>) -> Result<Obj> {
+        let cname = c_string(name)?;
+        let method = Obj::new(unsafe { PyObject_GetAttrString(self.as_ptr(), cname.as_ptr()) })?;
+        let tuple = Obj::new(unsafe { PyTuple_New(args.len() as isize) })?;
+        for (i, arg) in args.into_iter().enumerate() {
+            // PyTuple_SetItem steals the reference
+            let ptr = arg.as_ptr();
+            std::mem::forget(arg);
+            unsafe { PyTuple_SetItem(tuple.as_ptr(), i as isize, ptr) };
+        }
+        Obj::new(unsafe { PyObject_CallObject(method.as_ptr(), tuple.as_ptr()) })
+    }
+
+    /// `self[key]` for a mapping; `None` if the key is missing
+    pub fn get_item(&self, key: &str) -> Result<Option<Obj>> {
+        let key = c_string(key)?;
+        let item = unsafe { PyMapping_GetItemString(self.as_ptr(), key.as_ptr()) };
+        if item.is_null() {
+            unsafe { PyErr_Clear() };
+            return Ok(None);
+        }
+        Obj::new(item).map(Some)
+    }
+
+    pub fn to_i64(&self) -> Result<i64> {
+        let v = unsafe { PyLong_AsLongLong(self.as_ptr()) };
+        if v == -1 && unsafe { !PyErr_Occurred().is_null() } {
+            return Err(take_error());
+        }
+        Ok(v)
+    }
+
+    pub fn is_true(&self) -> Result<bool> {
+        match unsafe { PyObject_IsTrue(self.as_ptr()) } {
+            -1 => Err(take_error()),
+            v => Ok(v == 1),
+        }
+    }
+
+    /// Contents of a `str` (UTF-8) or `bytes` object
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut len: isize = 0;
+        let s = unsafe { PyUnicode_AsUTF8AndSize(self.as_ptr(), &mut len) };
+        if !s.is_null() {
+            return Ok(
+                unsafe { std::slice::from_raw_parts(s as *const u8, len as usize) }.to_vec(),
+            );
+        }
+        unsafe { PyErr_Clear() };
+        let mut buf: *mut c_char = ptr::null_mut();
+        if unsafe { PyBytes_AsStringAndSize(self.as_ptr(), &mut buf, &mut len) } == -1 {
+            return Err(take_error());
+        }
+        Ok(unsafe { std::slice::from_raw_parts(buf as *const u8, len as usize) }.to_vec())
+    }
+
+    pub fn to_string(&self) -> Result<String> {
+        String::from_utf8(self.to_bytes()?)
+            .map_err(|_| FileSystemError::Custom("expected a UTF-8 string".to_string()))
+    }
+
+    /// Collect an iterable
+    pub fn iter(&self) -> Result<Vec<Obj>> {
+        let iter = Obj::new(unsafe { PyObject_GetIter(self.as_ptr()) })?;
+        let mut items = Vec::new();
+        loop {
+            let item = unsafe { PyIter_Next(iter.as_ptr()) };
+            if item.is_null() {
+                if unsafe { !PyErr_Occurred().is_null() } {
+                    return Err(take_error());
+                }
+                return Ok(items);
+            }
+            items.push(Obj::new(item)?);
+        }
+    }
+}
+
+/// Put `dir` at the front of `sys.path`
+pub fn prepend_sys_path(dir: &str) -> Result<()> {
+    let path = unsafe { PySys_GetObject(c"path".as_ptr()) };
+    if path.is_null() {
+        return Err(FileSystemError::Custom("sys.path is missing".to_string()));
+    }
+    let dir = Obj::str(dir)?;
+    if unsafe { PyList_Insert(path, 0, dir.as_ptr()) } == -1 {
+        return Err(take_error());
+    }
+    Ok(())
+}
+
+/// Turn the pending Python exception into a `FileSystemError`
+///
+/// The OS error subclasses map to the matching variants;
+/// `NotImplementedError` means the operation is unsupported.
+pub fn take_error() -> FileSystemError {
+    let (mut kind, mut value, mut traceback) = (ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+    unsafe { PyErr_Fetch(&mut kind, &mut value, &mut traceback) };
+    if kind.is_null() {
+        return FileSystemError::Custom("python call failed".to_string());
+    }
+    let matches = |exc: *mut PyObject| unsafe { PyErr_GivenExceptionMatches(kind, exc) == 1 };
+    let error = unsafe {
+        if matches(PyExc_FileNotFoundError) {
+            FileSystemError::NotFound
+        } else if matches(PyExc_FileExistsError) {
+            FileSystemError::AlreadyExists
+        } else if matches(PyExc_PermissionError) {
+            FileSystemError::PermissionDenied
+        } else if matches(PyExc_NotADirectoryError) {
+            FileSystemError::NotADirectory
+        } else if matches(PyExc_IsADirectoryError) {
+            FileSystemError::IsADirectory
+        } else if matches(PyExc_NotImplementedError) {
+            FileSystemError::ReadOnly
+        } else {
+            FileSystemError::Custom(describe(value))
+        }
+    };
+    for obj in [kind, value, traceback] {
+        if !obj.is_null() {
+            unsafe { Py_DecRef(obj) };
+        }
+    }
+    error
+}
+
+// str(exception), without letting a failure there mask the original error
+fn describe(value: *mut PyObject) -> String {
+    if value.is_null() {
+        return "python exception".to_string();
+    }
+    let text = unsafe { PyObject_Str(value) };
+    if text.is_null() {
+        unsafe { PyErr_Clear() };
+        return "python exception".to_string();
+    }
+    let mut len: isize = 0;
+    let s = unsafe { PyUnicode_AsUTF8AndSize(text, &mut len) };
+    let msg = if s.is_null() {
+        unsafe { PyErr_Clear() };
+        "python exception".to_string()
+    } else {
+        unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+    };
+    unsafe { Py_DecRef(text) };
+    msg
+}
@@ -2,38 +2,28 @@
 //!
 //! Write a number to /generate to set the length, then read to get a random string
 
-use core::cell::Cell;
 use agfs_wasm_ffi::prelude::*;
 
 const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
 
-pub struct RandomStringFS {
-    seed: Cell<u64>,
-}
-
-impl Default for RandomStringFS {
-    fn default() -> Self {
-        Self {
-            seed: Cell::new(12345),
-        }
-    }
-}
+#[derive(Default)]
+pub struct RandomStringFS;
 
 impl RandomStringFS {
-    fn generate_random_string(&self, length: usize) -> Vec<u8> {
+    // Strings come from the host's CSPRNG, so they are safe to use as tokens
+    fn generate_random_string(&self, length: usize) -> Result<Vec<u8>> {
+        // 248 is the largest multiple of 62 that fits a byte; rejecting
+        // bytes above it keeps every character equally likely
+        let limit = 256 - 256 % CHARSET.len();
         let mut result = Vec::with_capacity(length);
-        let mut seed = self.seed.get();
-
-        for _ in 0..length {
-            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-            let rand_byte = ((seed / 65536) % 256) as u8;
-            result.push(CHARSET[(rand_byte as usize) % CHARSET.len()]);
+        while result.len() < length {
+            for b in HostRandom::bytes(length - result.len() + 8)? {
+                if (b as usize) < limit && result.len() < length {
+                    result.push(CHARSET[b as usize % CHARSET.len()]);
+                }
+            }
         }
-
-        // Update seed for next read
-        self.seed.set(seed);
-
-        result
+        Ok(result)
     }
 }
 
@@ -50,11 +40,16 @@ impl FileSystem for RandomStringFS {
         Ok(())
     }
 
+    // Every read of /generate returns something new
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::none()
+    }
+
     fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
         match path {
             "/generate" => {
                 // Default: return 6 character random string
-                Ok(self.generate_random_string(6))
+                self.generate_random_string(6)
             }
             _ => Err(Error::NotFound),
         }
@@ -76,6 +71,11 @@ impl FileSystem for RandomStringFS {
     }
 
     fn write(&mut self, path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        self.write_result(path, data).map(WriteResult::into_reply)
+    }
+
+    // /generate is a control file: the string is the response to the write
+    fn write_result(&mut self, path: &str, data: &[u8]) -> Result<WriteResult> {
         match path {
             "/generate" => {
                 let content = core::str::from_utf8(data)
@@ -90,7 +90,8 @@ impl FileSystem for RandomStringFS {
                 }
 
                 // Generate and return random string directly
-                Ok(self.generate_random_string(length))
+                let response = self.generate_random_string(length)?;
+                Ok(WriteResult::written(data.len() as i64).with_response(response))
             }
             _ => Err(Error::NotFound),
         }
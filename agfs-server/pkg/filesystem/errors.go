@@ -23,6 +23,9 @@ var (
 
 	// ErrNotDirectory indicates the path is not a directory when one was expected
 	ErrNotDirectory = errors.New("not a directory")
+
+	// ErrNotSupported indicates the file system does not implement the operation
+	ErrNotSupported = errors.New("not supported")
 )
 
 // NotFoundError represents a file or directory not found error with context
@@ -111,6 +114,20 @@ func (e *NotDirectoryError) Is(target error) bool {
 	return target == ErrNotDirectory
 }
 
+// NotSupportedError represents an operation the file system does not implement
+type NotSupportedError struct {
+	Path string
+	Op   string
+}
+
+func (e *NotSupportedError) Error() string {
+	return fmt.Sprintf("%s: %s: not supported", e.Op, e.Path)
+}
+
+func (e *NotSupportedError) Is(target error) bool {
+	return target == ErrNotSupported
+}
+
 // Helper functions to create common errors
 
 // NewNotFoundError creates a new NotFoundError
@@ -137,3 +154,8 @@ func NewAlreadyExistsError(resource, path string) error {
 func NewNotDirectoryError(path string) error {
 	return &NotDirectoryError{Path: path}
 }
+
+// NewNotSupportedError creates a new NotSupportedError
+func NewNotSupportedError(op, path string) error {
+	return &NotSupportedError{Op: op, Path: path}
+}
@@ -97,3 +97,163 @@ type Toucher interface {
 	// Returns error if the operation fails
 	Touch(path string) error
 }
+
+// Lstater is implemented by file systems that can stat a symlink itself
+// rather than the file it points to
+type Lstater interface {
+	// Lstat returns file information without following a final symlink
+	Lstat(path string) (*FileInfo, error)
+}
+
+// RealPather is implemented by file systems that can resolve symlinks
+type RealPather interface {
+	// RealPath resolves every symlink in path and returns the path it names
+	// in the same file system
+	RealPath(path string) (string, error)
+}
+
+// ExclusiveCreator is implemented by file systems that can create a file
+// only if it does not exist, as one atomic step
+type ExclusiveCreator interface {
+	// CreateExclusive creates an empty file, failing with ErrAlreadyExists
+	// if the path exists
+	CreateExclusive(path string) error
+}
+
+// Symlinker is implemented by file systems that support symbolic links
+type Symlinker interface {
+	// Symlink creates linkPath as a symlink pointing to target
+	// The target is stored as given and may be relative to linkPath
+	Symlink(target, linkPath string) error
+
+	// Readlink returns the target of the symlink at path, unresolved
+	Readlink(path string) (string, error)
+}
+
+// Truncater is implemented by file systems that can resize a file in place
+type Truncater interface {
+	// Truncate shrinks or zero-extends the file at path to size bytes
+	Truncate(path string, size int64) error
+}
+
+// Allocator is implemented by file systems that can reserve file space
+type Allocator interface {
+	// Allocate makes sure the file at path holds at least offset+length
+	// bytes, zero-extending it if needed; existing data is kept
+	Allocate(path string, offset, length int64) error
+}
+
+// Mode bits for Accesser.Access, as access(2) takes them; 0 checks that
+// the path exists
+const (
+	AccessExecute = 1
+	AccessWrite   = 1 << 1
+	AccessRead    = 1 << 2
+)
+
+// Accesser is implemented by file systems that can check whether the
+// server may use a path
+type Accesser interface {
+	// Access fails with a permission error if any mode bit is not granted
+	Access(path string, mode uint32) error
+}
+
+// Appender is implemented by file systems that can add to the end of a
+// file without rewriting it
+type Appender interface {
+	// Append writes data at the end of the file at path, creating it if
+	// needed; concurrent appends do not overwrite each other
+	Append(path string, data []byte) error
+}
+
+// RenameFlag bits for FlagRenamer.RenameWithFlags, as renameat2(2)
+// takes them
+const (
+	RenameNoReplace = 1      // fail if the new path exists
+	RenameExchange  = 1 << 1 // swap the two paths; both must exist
+)
+
+// FlagRenamer is implemented by file systems that can rename with
+// renameat2(2) semantics
+type FlagRenamer interface {
+	// RenameWithFlags renames oldPath to newPath as flags ask; 0 behaves
+	// as Rename
+	RenameWithFlags(oldPath, newPath string, flags uint32) error
+}
+
+// ValidateRenameFlags rejects unknown bits and RenameNoReplace together
+// with RenameExchange
+func ValidateRenameFlags(flags uint32) error {
+	if flags&^uint32(RenameNoReplace|RenameExchange) != 0 {
+		return NewInvalidArgumentError("flags", flags, "unknown rename flags")
+	}
+	if flags&RenameNoReplace != 0 && flags&RenameExchange != 0 {
+		return NewInvalidArgumentError("flags", flags, "noreplace and exchange are exclusive")
+	}
+	return nil
+}
+
+// WriterAt is implemented by file systems that can write inside a file
+// without rewriting the rest
+type WriterAt interface {
+	// WriteAt writes data at offset, creating the file if needed; a gap
+	// past the end reads as zeros. Returns the bytes written
+	WriteAt(path string, offset int64, data []byte) (int64, error)
+}
+
+// Syncer is implemented by file systems that buffer writes
+type Syncer interface {
+	// Flush pushes buffered writes to path to the backend
+	Flush(path string) error
+
+	// Fsync makes writes to path durable before returning
+	Fsync(path string) error
+}
+
+// TimeSetter is implemented by file systems that can set file times
+type TimeSetter interface {
+	// SetTimes sets the access and modification times of path
+	SetTimes(path string, atime, mtime time.Time) error
+}
+
+// Chowner is implemented by file systems that can change file ownership
+type Chowner interface {
+	// Chown changes the owner and group of path
+	Chown(path string, uid, gid uint32) error
+}
+
+// Linker is implemented by file systems that support hard links
+type Linker interface {
+	// Link creates newPath as another name for the file at existing
+	Link(existing, newPath string) error
+}
+
+// Flags for FlagCreator.CreateWithFlags
+const (
+	CreateFlagExclusive uint32 = 1      // fail if the path exists (O_EXCL)
+	CreateFlagTruncate  uint32 = 1 << 1 // truncate an existing file (O_TRUNC)
+	CreateFlagMode      uint32 = 1 << 2 // the mode argument applies to a new file
+)
+
+// FlagCreator is implemented by file systems that can create a file as
+// open(2) with O_CREAT and the given flags would
+type FlagCreator interface {
+	// CreateWithFlags creates path if needed, as the CreateFlag* bits in
+	// flags ask; mode holds the permission bits when CreateFlagMode is set
+	CreateWithFlags(path string, flags, mode uint32) error
+}
+
+// Whence values for DataSeeker.SeekData, as lseek(2) takes them
+const (
+	SeekData = 3 // find the next data
+	SeekHole = 4 // find the next hole
+)
+
+// DataSeeker is implemented by file systems that can find the data and
+// holes of sparse files
+type DataSeeker interface {
+	// SeekData returns the offset of the first data (SeekData) or hole
+	// (SeekHole) at or after offset; found is false if there is none
+	// The end of a file counts as a hole
+	SeekData(path string, offset int64, whence int) (next int64, found bool, err error)
+}
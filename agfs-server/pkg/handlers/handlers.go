@@ -13,9 +13,11 @@ import (
 	"path/filepath"
 	"regexp"
 	"strconv"
+	"syscall"
 	"time"
 
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin/api"
 	log "github.com/sirupsen/logrus"
 	"github.com/zeebo/xxh3"
 )
@@ -122,6 +124,12 @@ func mapErrorToStatus(err error) int {
 	if errors.Is(err, filesystem.ErrAlreadyExists) {
 		return http.StatusConflict
 	}
+	if errors.Is(err, filesystem.ErrNotSupported) {
+		return http.StatusNotImplemented
+	}
+	if errors.Is(err, syscall.EFBIG) {
+		return http.StatusRequestEntityTooLarge
+	}
 	return http.StatusInternalServerError
 }
 
@@ -514,6 +522,19 @@ func (h *Handler) Health(w http.ResponseWriter, r *http.Request) {
 	writeJSON(w, http.StatusOK, response)
 }
 
+// Metrics handles GET /metrics
+// Serves the metrics WASM plugins report, in the Prometheus text format
+func (h *Handler) Metrics(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodGet {
+		writeError(w, http.StatusMethodNotAllowed, "method not allowed")
+		return
+	}
+	w.Header().Set("Content-Type", "text/plain; version=0.0.4")
+	if err := api.PluginMetrics.WriteText(w); err != nil {
+		log.Warnf("failed to write metrics: %v", err)
+	}
+}
+
 // Touch handles POST /touch?path=<path>
 // Updates file timestamp without changing content
 // If file doesn't exist, creates it with empty content
@@ -544,7 +565,7 @@ func (h *Handler) Touch(w http.ResponseWriter, r *http.Request) {
 		// File exists - read current content and write it back to update timestamp
 		if !info.IsDir {
 			data, readErr := h.fs.Read(path, 0, -1)
-			if readErr != nil {
+			if readErr != nil && readErr != io.EOF {
 				status := mapErrorToStatus(readErr)
 				writeError(w, status, readErr.Error())
 				return
@@ -573,9 +594,29 @@ func (h *Handler) Touch(w http.ResponseWriter, r *http.Request) {
 	writeJSON(w, http.StatusOK, SuccessResponse{Message: "touched"})
 }
 
+// Fsync handles POST /fsync?path=<path>
+// Makes writes to the file durable; file systems that do not buffer
+// writes succeed without doing anything
+func (h *Handler) Fsync(w http.ResponseWriter, r *http.Request) {
+	path := r.URL.Query().Get("path")
+	if path == "" {
+		writeError(w, http.StatusBadRequest, "path parameter is required")
+		return
+	}
+
+	if syncer, ok := h.fs.(filesystem.Syncer); ok {
+		if err := syncer.Fsync(path); err != nil {
+			writeError(w, mapErrorToStatus(err), err.Error())
+			return
+		}
+	}
+	writeJSON(w, http.StatusOK, SuccessResponse{Message: "synced"})
+}
+
 // SetupRoutes sets up all HTTP routes with /api/v1 prefix
 func (h *Handler) SetupRoutes(mux *http.ServeMux) {
 	mux.HandleFunc("/api/v1/health", h.Health)
+	mux.HandleFunc("/api/v1/metrics", h.Metrics)
 	mux.HandleFunc("/api/v1/files", func(w http.ResponseWriter, r *http.Request) {
 		switch r.Method {
 		case http.MethodPost:
@@ -644,6 +685,13 @@ func (h *Handler) SetupRoutes(mux *http.ServeMux) {
 		}
 		h.Touch(w, r)
 	})
+	mux.HandleFunc("/api/v1/fsync", func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodPost {
+			writeError(w, http.StatusMethodNotAllowed, "method not allowed")
+			return
+		}
+		h.Fsync(w, r)
+	})
 }
 
 // streamFile handles streaming file reads with HTTP chunked transfer encoding
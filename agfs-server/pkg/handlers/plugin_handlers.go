@@ -14,6 +14,7 @@ import (
 
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
 	"github.com/c4pt0r/agfs/agfs-server/pkg/mountablefs"
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin"
 	log "github.com/sirupsen/logrus"
 )
 
@@ -32,6 +33,8 @@ type MountInfo struct {
 	Path       string                 `json:"path"`
 	PluginName string                 `json:"pluginName"`
 	Config     map[string]interface{} `json:"config,omitempty"`
+	ReadOnly   bool                   `json:"readOnly,omitempty"`
+	Health     *plugin.HealthStatus   `json:"health,omitempty"`
 }
 
 // ListMountsResponse represents the response for listing mounts
@@ -45,11 +48,20 @@ func (ph *PluginHandler) ListMounts(w http.ResponseWriter, r *http.Request) {
 
 	var mountInfos []MountInfo
 	for _, mount := range mounts {
-		mountInfos = append(mountInfos, MountInfo{
+		info := MountInfo{
 			Path:       mount.Path,
 			PluginName: mount.Plugin.Name(),
 			Config:     mount.Config,
-		})
+		}
+		if ro, ok := mount.Plugin.(plugin.ReadOnlyPlugin); ok {
+			info.ReadOnly = ro.ReadOnly()
+		}
+		if hp, ok := mount.Plugin.(plugin.HealthPlugin); ok {
+			if status, probed := hp.Health(); probed {
+				info.Health = &status
+			}
+		}
+		mountInfos = append(mountInfos, info)
 	}
 
 	writeJSON(w, http.StatusOK, ListMountsResponse{Mounts: mountInfos})
@@ -128,6 +140,38 @@ func (ph *PluginHandler) Mount(w http.ResponseWriter, r *http.Request) {
 	writeJSON(w, http.StatusOK, SuccessResponse{Message: "plugin mounted"})
 }
 
+// RemountRequest represents a request to change the config of a mount
+type RemountRequest struct {
+	Path   string                 `json:"path"`
+	Config map[string]interface{} `json:"config"`
+}
+
+// Remount handles PUT /mount
+func (ph *PluginHandler) Remount(w http.ResponseWriter, r *http.Request) {
+	var req RemountRequest
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		writeError(w, http.StatusBadRequest, "invalid request body")
+		return
+	}
+
+	if req.Path == "" {
+		writeError(w, http.StatusBadRequest, "path is required")
+		return
+	}
+
+	if err := ph.mfs.Remount(req.Path, req.Config); err != nil {
+		if errors.Is(err, filesystem.ErrNotFound) {
+			writeError(w, http.StatusNotFound, err.Error())
+		} else if strings.Contains(err.Error(), "failed to validate") {
+			writeError(w, http.StatusBadRequest, err.Error())
+		} else {
+			writeError(w, http.StatusInternalServerError, err.Error())
+		}
+		return
+	}
+
+	writeJSON(w, http.StatusOK, SuccessResponse{Message: "plugin reconfigured"})
+}
 
 // LoadPluginRequest represents a request to load an external plugin
 type LoadPluginRequest struct {
@@ -356,11 +400,14 @@ func (ph *PluginHandler) SetupRoutes(mux *http.ServeMux) {
 	})
 
 	mux.HandleFunc("/api/v1/mount", func(w http.ResponseWriter, r *http.Request) {
-		if r.Method != http.MethodPost {
+		switch r.Method {
+		case http.MethodPost:
+			ph.Mount(w, r)
+		case http.MethodPut:
+			ph.Remount(w, r)
+		default:
 			writeError(w, http.StatusMethodNotAllowed, "method not allowed")
-			return
 		}
-		ph.Mount(w, r)
 	})
 
 	mux.HandleFunc("/api/v1/unmount", func(w http.ResponseWriter, r *http.Request) {
@@ -1,6 +1,7 @@
 package mountablefs
 
 import (
+	"errors"
 	"fmt"
 	"io"
 	"sort"
@@ -25,6 +26,7 @@ type MountPoint struct {
 	Path   string
 	Plugin plugin.ServicePlugin
 	Config map[string]interface{} // Plugin configuration
+	FSType string                 // Factory the plugin came from; empty for static mounts
 }
 
 // PluginFactory is a function that creates a new plugin instance
@@ -177,12 +179,7 @@ func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[strin
 		log.Debugf("Set rootFS for plugin %s at %s", fstype, path)
 	}
 
-	// Inject mount_path into config for plugins that need to know their virtual path
-	configWithPath := make(map[string]interface{})
-	for k, v := range config {
-		configWithPath[k] = v
-	}
-	configWithPath["mount_path"] = path
+	configWithPath := withMountPath(config, path)
 
 	// Validate plugin configuration
 	if err := pluginInstance.Validate(configWithPath); err != nil {
@@ -199,6 +196,7 @@ func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[strin
 		Path:   path,
 		Plugin: pluginInstance,
 		Config: config,
+		FSType: fstype,
 	}
 
 	// Update mount paths list and sort by length (longest first)
@@ -209,6 +207,81 @@ func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[strin
 	return nil
 }
 
+// withMountPath copies config and injects mount_path, for plugins that
+// need to know their virtual path
+func withMountPath(config map[string]interface{}, path string) map[string]interface{} {
+	configWithPath := make(map[string]interface{})
+	for k, v := range config {
+		configWithPath[k] = v
+	}
+	configWithPath["mount_path"] = path
+	return configWithPath
+}
+
+// Remount applies a new configuration to the plugin mounted at path
+// Plugins implementing plugin.ReconfigurablePlugin take it in place;
+// otherwise, or when they report it as not supported, the plugin is shut
+// down and mounted again from its factory with the new configuration
+func (mfs *MountableFS) Remount(path string, config map[string]interface{}) error {
+	mfs.mu.Lock()
+	defer mfs.mu.Unlock()
+
+	path = filesystem.NormalizePath(path)
+
+	mount, exists := mfs.mounts[path]
+	if !exists {
+		return filesystem.NewNotFoundError("remount", path)
+	}
+
+	configWithPath := withMountPath(config, path)
+	if err := mount.Plugin.Validate(configWithPath); err != nil {
+		return fmt.Errorf("failed to validate plugin: %v", err)
+	}
+
+	if rp, ok := mount.Plugin.(plugin.ReconfigurablePlugin); ok {
+		err := rp.Reconfigure(configWithPath)
+		if err == nil {
+			mount.Config = config
+			log.Infof("Reconfigured plugin at %s", path)
+			return nil
+		}
+		if !errors.Is(err, filesystem.ErrNotSupported) {
+			return fmt.Errorf("failed to reconfigure plugin: %v", err)
+		}
+	}
+
+	factory, ok := mfs.pluginFactories[mount.FSType]
+	if !ok {
+		return fmt.Errorf("cannot remount %s: unknown filesystem type: %q", path, mount.FSType)
+	}
+
+	// External plugins hand out the mounted instance again, so it is shut
+	// down before being initialized with the new configuration
+	if err := mount.Plugin.Shutdown(); err != nil {
+		return fmt.Errorf("failed to shutdown plugin: %v", err)
+	}
+	pluginInstance := factory()
+	type rootFSSetter interface {
+		SetRootFS(filesystem.FileSystem)
+	}
+	if setter, ok := pluginInstance.(rootFSSetter); ok {
+		setter.SetRootFS(mfs)
+	}
+	if err := pluginInstance.Validate(configWithPath); err != nil {
+		mfs.removeMount(path)
+		return fmt.Errorf("failed to validate plugin: %v", err)
+	}
+	if err := pluginInstance.Initialize(configWithPath); err != nil {
+		mfs.removeMount(path)
+		return fmt.Errorf("failed to initialize plugin: %v", err)
+	}
+
+	mount.Plugin = pluginInstance
+	mount.Config = config
+	log.Infof("Remounted %s at %s", mount.FSType, path)
+	return nil
+}
+
 // Unmount unmounts a plugin from the specified path
 func (mfs *MountableFS) Unmount(path string) error {
 	mfs.mu.Lock()
@@ -226,6 +299,15 @@ func (mfs *MountableFS) Unmount(path string) error {
 		return fmt.Errorf("failed to shutdown plugin: %v", err)
 	}
 
+	mfs.removeMount(path)
+
+	log.Infof("Unmounted plugin at %s", path)
+	return nil
+}
+
+// removeMount drops the mount at path, whose plugin is already shut down
+// Must be called with mfs.mu held (write lock)
+func (mfs *MountableFS) removeMount(path string) {
 	delete(mfs.mounts, path)
 
 	// Remove from mount paths
@@ -235,9 +317,6 @@ func (mfs *MountableFS) Unmount(path string) error {
 			break
 		}
 	}
-
-	log.Infof("Unmounted plugin at %s", path)
-	return nil
 }
 
 // LoadExternalPluginWithType loads a plugin with an explicitly specified type
@@ -408,6 +487,36 @@ func (mfs *MountableFS) Create(path string) error {
 	return filesystem.NewPermissionDeniedError("create", path, "not allowed to create file in rootfs, use mount instead")
 }
 
+// CreateExclusive implements filesystem.ExclusiveCreator interface
+func (mfs *MountableFS) CreateExclusive(path string) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewPermissionDeniedError("create", path, "not allowed to create file in rootfs, use mount instead")
+	}
+	if creator, ok := mount.Plugin.GetFileSystem().(filesystem.ExclusiveCreator); ok {
+		return creator.CreateExclusive(relPath)
+	}
+	return filesystem.NewNotSupportedError("create_exclusive", path)
+}
+
+// CreateWithFlags implements filesystem.FlagCreator interface
+func (mfs *MountableFS) CreateWithFlags(path string, flags, mode uint32) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewPermissionDeniedError("create", path, "not allowed to create file in rootfs, use mount instead")
+	}
+	if creator, ok := mount.Plugin.GetFileSystem().(filesystem.FlagCreator); ok {
+		return creator.CreateWithFlags(relPath, flags, mode)
+	}
+	return filesystem.NewNotSupportedError("create_flags", path)
+}
+
 func (mfs *MountableFS) Mkdir(path string, perm uint32) error {
 	mfs.mu.RLock()
 	mount, relPath, found := mfs.findMount(path)
@@ -706,6 +815,23 @@ func (mfs *MountableFS) Rename(oldPath, newPath string) error {
 	return fmt.Errorf("cannot rename: paths not in same mounted filesystem")
 }
 
+// Link implements filesystem.Linker interface
+func (mfs *MountableFS) Link(existing, newPath string) error {
+	mfs.mu.RLock()
+	oldMount, oldRelPath, oldFound := mfs.findMount(existing)
+	newMount, newRelPath, newFound := mfs.findMount(newPath)
+	mfs.mu.RUnlock()
+
+	// Both paths must be in the same filesystem
+	if !oldFound || !newFound || oldMount != newMount {
+		return fmt.Errorf("cannot link: paths not in same mounted filesystem")
+	}
+	if linker, ok := oldMount.Plugin.GetFileSystem().(filesystem.Linker); ok {
+		return linker.Link(oldRelPath, newRelPath)
+	}
+	return filesystem.NewNotSupportedError("link", newPath)
+}
+
 func (mfs *MountableFS) Chmod(path string, mode uint32) error {
 	mfs.mu.RLock()
 	mount, relPath, found := mfs.findMount(path)
@@ -735,7 +861,7 @@ func (mfs *MountableFS) Touch(path string) error {
 			// File exists - read current content and write it back
 			if !info.IsDir {
 				data, readErr := fs.Read(relPath, 0, -1)
-				if readErr != nil {
+				if readErr != nil && readErr != io.EOF {
 					return readErr
 				}
 				_, writeErr := fs.Write(relPath, data)
@@ -751,6 +877,241 @@ func (mfs *MountableFS) Touch(path string) error {
 	return filesystem.NewNotFoundError("touch", path)
 }
 
+// Lstat implements filesystem.Lstater interface
+func (mfs *MountableFS) Lstat(path string) (*filesystem.FileInfo, error) {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return nil, filesystem.NewNotFoundError("lstat", path)
+	}
+	if lstater, ok := mount.Plugin.GetFileSystem().(filesystem.Lstater); ok {
+		return lstater.Lstat(relPath)
+	}
+	return nil, filesystem.NewNotSupportedError("lstat", path)
+}
+
+// RealPath implements filesystem.RealPather interface
+// The resolved path stays within the mount it started in
+func (mfs *MountableFS) RealPath(path string) (string, error) {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return "", filesystem.NewNotFoundError("realpath", path)
+	}
+	realPather, ok := mount.Plugin.GetFileSystem().(filesystem.RealPather)
+	if !ok {
+		return "", filesystem.NewNotSupportedError("realpath", path)
+	}
+	resolved, err := realPather.RealPath(relPath)
+	if err != nil {
+		return "", err
+	}
+	return filesystem.NormalizePath(mount.Path + resolved), nil
+}
+
+// Symlink implements filesystem.Symlinker interface
+func (mfs *MountableFS) Symlink(target, linkPath string) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(linkPath)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewPermissionDeniedError("symlink", linkPath, "not allowed to create file in rootfs, use mount instead")
+	}
+	if symlinker, ok := mount.Plugin.GetFileSystem().(filesystem.Symlinker); ok {
+		return symlinker.Symlink(target, relPath)
+	}
+	return filesystem.NewNotSupportedError("symlink", linkPath)
+}
+
+// Readlink implements filesystem.Symlinker interface
+func (mfs *MountableFS) Readlink(path string) (string, error) {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return "", filesystem.NewNotFoundError("readlink", path)
+	}
+	if symlinker, ok := mount.Plugin.GetFileSystem().(filesystem.Symlinker); ok {
+		return symlinker.Readlink(relPath)
+	}
+	return "", filesystem.NewNotSupportedError("readlink", path)
+}
+
+// Truncate implements filesystem.Truncater interface
+func (mfs *MountableFS) Truncate(path string, size int64) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("truncate", path)
+	}
+	if truncater, ok := mount.Plugin.GetFileSystem().(filesystem.Truncater); ok {
+		return truncater.Truncate(relPath, size)
+	}
+	return filesystem.NewNotSupportedError("truncate", path)
+}
+
+// SetTimes implements filesystem.TimeSetter interface
+func (mfs *MountableFS) SetTimes(path string, atime, mtime time.Time) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("set_times", path)
+	}
+	if setter, ok := mount.Plugin.GetFileSystem().(filesystem.TimeSetter); ok {
+		return setter.SetTimes(relPath, atime, mtime)
+	}
+	return filesystem.NewNotSupportedError("set_times", path)
+}
+
+// Chown implements filesystem.Chowner interface
+func (mfs *MountableFS) Chown(path string, uid, gid uint32) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("chown", path)
+	}
+	if chowner, ok := mount.Plugin.GetFileSystem().(filesystem.Chowner); ok {
+		return chowner.Chown(relPath, uid, gid)
+	}
+	return filesystem.NewNotSupportedError("chown", path)
+}
+
+// SeekData implements filesystem.DataSeeker interface
+func (mfs *MountableFS) SeekData(path string, offset int64, whence int) (int64, bool, error) {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return 0, false, filesystem.NewNotFoundError("seek", path)
+	}
+	if seeker, ok := mount.Plugin.GetFileSystem().(filesystem.DataSeeker); ok {
+		return seeker.SeekData(relPath, offset, whence)
+	}
+	return 0, false, filesystem.NewNotSupportedError("seek", path)
+}
+
+// Allocate implements filesystem.Allocator interface
+func (mfs *MountableFS) Allocate(path string, offset, length int64) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("allocate", path)
+	}
+	if allocator, ok := mount.Plugin.GetFileSystem().(filesystem.Allocator); ok {
+		return allocator.Allocate(relPath, offset, length)
+	}
+	return filesystem.NewNotSupportedError("allocate", path)
+}
+
+// Access implements filesystem.Accesser interface
+func (mfs *MountableFS) Access(path string, mode uint32) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("access", path)
+	}
+	if accesser, ok := mount.Plugin.GetFileSystem().(filesystem.Accesser); ok {
+		return accesser.Access(relPath, mode)
+	}
+	return filesystem.NewNotSupportedError("access", path)
+}
+
+// Append implements filesystem.Appender interface
+func (mfs *MountableFS) Append(path string, data []byte) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("append", path)
+	}
+	if appender, ok := mount.Plugin.GetFileSystem().(filesystem.Appender); ok {
+		return appender.Append(relPath, data)
+	}
+	return filesystem.NewNotSupportedError("append", path)
+}
+
+// Flush implements filesystem.Syncer interface
+// Mounts that do not buffer writes have nothing to flush
+func (mfs *MountableFS) Flush(path string) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("flush", path)
+	}
+	if syncer, ok := mount.Plugin.GetFileSystem().(filesystem.Syncer); ok {
+		return syncer.Flush(relPath)
+	}
+	return nil
+}
+
+// Fsync implements filesystem.Syncer interface
+// Mounts that do not buffer writes have nothing to make durable
+func (mfs *MountableFS) Fsync(path string) error {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return filesystem.NewNotFoundError("fsync", path)
+	}
+	if syncer, ok := mount.Plugin.GetFileSystem().(filesystem.Syncer); ok {
+		return syncer.Fsync(relPath)
+	}
+	return nil
+}
+
+// RenameWithFlags implements filesystem.FlagRenamer interface
+func (mfs *MountableFS) RenameWithFlags(oldPath, newPath string, flags uint32) error {
+	mfs.mu.RLock()
+	oldMount, oldRelPath, oldFound := mfs.findMount(oldPath)
+	newMount, newRelPath, newFound := mfs.findMount(newPath)
+	mfs.mu.RUnlock()
+
+	// Both paths must be in the same filesystem
+	if !oldFound || !newFound || oldMount != newMount {
+		return fmt.Errorf("cannot rename: paths not in same mounted filesystem")
+	}
+	if renamer, ok := oldMount.Plugin.GetFileSystem().(filesystem.FlagRenamer); ok {
+		return renamer.RenameWithFlags(oldRelPath, newRelPath, flags)
+	}
+	return filesystem.NewNotSupportedError("rename", newPath)
+}
+
+// WriteAt implements filesystem.WriterAt interface
+func (mfs *MountableFS) WriteAt(path string, offset int64, data []byte) (int64, error) {
+	mfs.mu.RLock()
+	mount, relPath, found := mfs.findMount(path)
+	mfs.mu.RUnlock()
+
+	if !found {
+		return 0, filesystem.NewNotFoundError("write_at", path)
+	}
+	if writer, ok := mount.Plugin.GetFileSystem().(filesystem.WriterAt); ok {
+		return writer.WriteAt(relPath, offset, data)
+	}
+	return 0, filesystem.NewNotSupportedError("write_at", path)
+}
+
 func (mfs *MountableFS) Open(path string) (io.ReadCloser, error) {
 	mfs.mu.RLock()
 	mount, relPath, found := mfs.findMount(path)
@@ -0,0 +1,104 @@
+package mountablefs
+
+import (
+	"errors"
+	"testing"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin"
+)
+
+// stubPlugin records the config it was initialized with
+type stubPlugin struct {
+	config   map[string]interface{}
+	shutdown bool
+}
+
+func (p *stubPlugin) Name() string { return "stub" }
+
+func (p *stubPlugin) Validate(config map[string]interface{}) error { return nil }
+
+func (p *stubPlugin) Initialize(config map[string]interface{}) error {
+	p.config = config
+	return nil
+}
+
+func (p *stubPlugin) GetFileSystem() filesystem.FileSystem { return nil }
+
+func (p *stubPlugin) GetReadme() string { return "" }
+
+func (p *stubPlugin) Shutdown() error {
+	p.shutdown = true
+	return nil
+}
+
+// reconfigurableStub takes new configs in place, unless inPlace is false
+type reconfigurableStub struct {
+	stubPlugin
+	inPlace bool
+}
+
+func (p *reconfigurableStub) Reconfigure(config map[string]interface{}) error {
+	if !p.inPlace {
+		return filesystem.NewNotSupportedError("reconfigure", "/")
+	}
+	p.config = config
+	return nil
+}
+
+func TestRemountInPlace(t *testing.T) {
+	mfs := NewMountableFS()
+	var created []*reconfigurableStub
+	mfs.RegisterPluginFactory("stub", func() plugin.ServicePlugin {
+		p := &reconfigurableStub{inPlace: true}
+		created = append(created, p)
+		return p
+	})
+	if err := mfs.MountPlugin("stub", "/s", map[string]interface{}{"key": "old"}); err != nil {
+		t.Fatalf("MountPlugin failed: %v", err)
+	}
+
+	if err := mfs.Remount("/s", map[string]interface{}{"key": "new"}); err != nil {
+		t.Fatalf("Remount failed: %v", err)
+	}
+	if len(created) != 1 || created[0].shutdown {
+		t.Fatalf("expected the mounted plugin to be kept running")
+	}
+	if created[0].config["key"] != "new" || created[0].config["mount_path"] != "/s" {
+		t.Errorf("unexpected config %v", created[0].config)
+	}
+	if mounts := mfs.GetMounts(); mounts[0].Config["key"] != "new" {
+		t.Errorf("mount still lists config %v", mounts[0].Config)
+	}
+}
+
+func TestRemountFallsBackToRestart(t *testing.T) {
+	mfs := NewMountableFS()
+	var created []*reconfigurableStub
+	mfs.RegisterPluginFactory("stub", func() plugin.ServicePlugin {
+		p := &reconfigurableStub{}
+		created = append(created, p)
+		return p
+	})
+	if err := mfs.MountPlugin("stub", "/s", map[string]interface{}{"key": "old"}); err != nil {
+		t.Fatalf("MountPlugin failed: %v", err)
+	}
+
+	// Reconfigure reports not supported, so the mount is restarted
+	if err := mfs.Remount("/s", map[string]interface{}{"key": "new"}); err != nil {
+		t.Fatalf("Remount failed: %v", err)
+	}
+	if len(created) != 2 || !created[0].shutdown {
+		t.Fatalf("expected the old plugin shut down and a new one created")
+	}
+	if created[1].config["key"] != "new" {
+		t.Errorf("new plugin initialized with %v", created[1].config)
+	}
+	if mounts := mfs.GetMounts(); mounts[0].Plugin != created[1] {
+		t.Errorf("mount still serves the old plugin")
+	}
+
+	if err := mfs.Remount("/missing", nil); !errors.Is(err, filesystem.ErrNotFound) {
+		t.Errorf("expected ErrNotFound, got %v", err)
+	}
+}
@@ -264,6 +264,30 @@ func (efs *ExternalFileSystem) Chmod(path string, mode uint32) error {
 	return GoError(errPtr)
 }
 
+// Flush implements filesystem.Syncer; plugins without FSFlush have
+// nothing buffered
+func (efs *ExternalFileSystem) Flush(path string) error {
+	if efs.vtable.FSFlush == nil {
+		return nil
+	}
+
+	pathCStr := CString(path)
+	errPtr := efs.vtable.FSFlush(efs.pluginPtr, pathCStr)
+	return GoError(errPtr)
+}
+
+// Fsync implements filesystem.Syncer; plugins without FSFsync have
+// nothing to make durable
+func (efs *ExternalFileSystem) Fsync(path string) error {
+	if efs.vtable.FSFsync == nil {
+		return nil
+	}
+
+	pathCStr := CString(path)
+	errPtr := efs.vtable.FSFsync(efs.pluginPtr, pathCStr)
+	return GoError(errPtr)
+}
+
 func (efs *ExternalFileSystem) Open(path string) (io.ReadCloser, error) {
 	// Default implementation using Read
 	data, err := efs.Read(path, 0, -1)
@@ -306,8 +330,10 @@ func (wc *writeCloser) Write(p []byte) (n int, err error) {
 }
 
 func (wc *writeCloser) Close() error {
-	_, err := wc.fs.Write(wc.path, wc.buf)
-	return err
+	if _, err := wc.fs.Write(wc.path, wc.buf); err != nil {
+		return err
+	}
+	return wc.fs.Flush(wc.path)
 }
 
 // FileInfoCToGo with proper time handling
@@ -0,0 +1,110 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+	"strconv"
+	"sync/atomic"
+	"time"
+
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// CallUserKey is the mount config key giving the identity a WASM plugin
+// sees as the caller of every filesystem call. The HTTP API does not
+// authenticate users itself, so the mount declares who its callers are
+const CallUserKey = "call_user"
+
+// CallUser is the caller identity sent to the plugin, as agfs-wasm-ffi's
+// context::Identity
+type CallUser struct {
+	Uid      uint32
+	Gid      uint32
+	Username string
+	Groups   []uint32
+}
+
+// callContext is the JSON plugin_set_context expects
+type callContext struct {
+	TraceParent string    `json:"TraceParent,omitempty"` // W3C traceparent of the host's span
+	User        *CallUser `json:"User"`
+	RequestId   string    `json:"RequestId,omitempty"`
+}
+
+// parseCallUser reads call_user from a mount config
+// Field names match case-insensitively, so {"uid": 1000, "username": "alice"}
+// is accepted
+func parseCallUser(config map[string]interface{}) (*CallUser, error) {
+	value, ok := config[CallUserKey]
+	if !ok || value == nil {
+		return nil, nil
+	}
+	raw, err := json.Marshal(value)
+	if err != nil {
+		return nil, fmt.Errorf("%s: %w", CallUserKey, err)
+	}
+	var user CallUser
+	if err := json.Unmarshal(raw, &user); err != nil {
+		return nil, fmt.Errorf("%s: expected an object with uid, gid, username and groups: %w", CallUserKey, err)
+	}
+	return &user, nil
+}
+
+// callRequests numbers the calls made into WASM plugins, for RequestId
+var callRequests uint64
+
+// setCallContext hands the caller identity, the request id and the span
+// of the call, if traced, to the plugin ahead of a filesystem call
+// Plugins that do not export plugin_set_context are called without one
+func (wfs *WASMFileSystem) setCallContext(requestID string, span *callSpan) error {
+	setFunc := wfs.module.ExportedFunction("plugin_set_context")
+	if setFunc == nil {
+		return nil
+	}
+
+	ctxJSON, err := json.Marshal(callContext{
+		TraceParent: span.traceParent(),
+		User:        wfs.user,
+		RequestId:   requestID,
+	})
+	if err != nil {
+		return fmt.Errorf("failed to marshal call context: %w", err)
+	}
+	ctxPtr, err := writeStringToMemory(wfs.module, string(ctxJSON))
+	if err != nil {
+		return err
+	}
+
+	results, err := setFunc.Call(wfs.ctx, uint64(ctxPtr))
+	if err != nil {
+		return fmt.Errorf("plugin_set_context failed: %w", err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
+			return fmt.Errorf("%s", errMsg)
+		}
+		return fmt.Errorf("plugin_set_context failed")
+	}
+	return nil
+}
+
+// call invokes a filesystem export after setting the call context
+// The call is registered under its request id for host_should_cancel
+// while it runs, and traced when the mount has tracing on
+func (wfs *WASMFileSystem) call(fn wazeroapi.Function, params ...uint64) ([]uint64, error) {
+	requestID := strconv.FormatUint(atomic.AddUint64(&callRequests, 1), 10)
+	ctx, done := wfs.cancel.Begin(wfs.ctx, requestID)
+	defer done()
+
+	wfs.callMu.Lock()
+	defer wfs.callMu.Unlock()
+	wfs.lastCall.Store(time.Now().UnixNano())
+
+	span := wfs.tracer.start(fn.Definition().Name())
+	if err := wfs.setCallContext(requestID, span); err != nil {
+		return nil, err
+	}
+	results, err := fn.Call(ctx, params...)
+	wfs.finishSpan(span, err)
+	return results, err
+}
@@ -0,0 +1,92 @@
+package api
+
+import (
+	"context"
+	"sync"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostCancel tracks the calls in flight into a plugin by request id, so
+// the plugin can ask through host_should_cancel whether the host has
+// given up on one. Calls still running when the plugin shuts down are
+// cancelled
+type HostCancel struct {
+	mu     sync.Mutex
+	calls  map[string]*hostCall
+	closed bool
+}
+
+type hostCall struct {
+	ctx    context.Context
+	cancel context.CancelFunc
+}
+
+// NewHostCancel creates an empty call table
+func NewHostCancel() *HostCancel {
+	return &HostCancel{calls: map[string]*hostCall{}}
+}
+
+// Begin registers the call requestID and returns its context
+// done must be called when the call returns
+func (c *HostCancel) Begin(parent context.Context, requestID string) (context.Context, func()) {
+	ctx, cancel := context.WithCancel(parent)
+	c.mu.Lock()
+	if c.closed {
+		cancel()
+	}
+	c.calls[requestID] = &hostCall{ctx: ctx, cancel: cancel}
+	c.mu.Unlock()
+
+	return ctx, func() {
+		c.mu.Lock()
+		delete(c.calls, requestID)
+		c.mu.Unlock()
+		cancel()
+	}
+}
+
+// Cancel gives up on the call requestID; false if it is not in flight
+func (c *HostCancel) Cancel(requestID string) bool {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	call, ok := c.calls[requestID]
+	if ok {
+		call.cancel()
+	}
+	return ok
+}
+
+// Cancelled reports whether the call requestID has been given up on
+// Unknown ids are not cancelled
+func (c *HostCancel) Cancelled(requestID string) bool {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	call, ok := c.calls[requestID]
+	return ok && call.ctx.Err() != nil
+}
+
+// Close cancels every call in flight and any begun afterwards
+func (c *HostCancel) Close() {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	c.closed = true
+	for _, call := range c.calls {
+		call.cancel()
+	}
+}
+
+// HostShouldCancel reports whether the host has given up on a call
+// Returns 1 if the call is cancelled, 0 otherwise
+func HostShouldCancel(ctx context.Context, mod wazeroapi.Module, params []uint64, cancel *HostCancel) []uint64 {
+	requestID, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_should_cancel: failed to read request id from memory")
+		return []uint64{0}
+	}
+	if cancel.Cancelled(requestID) {
+		return []uint64{1}
+	}
+	return []uint64{0}
+}
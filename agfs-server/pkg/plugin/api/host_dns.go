@@ -0,0 +1,124 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"net"
+	"strings"
+	"sync"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostDNSAllowKey is the mount config key listing the names a WASM plugin
+// may resolve: exact names, "*.example.com" for subdomains, or "*".
+// Without it a plugin may resolve the hosts it may connect to under
+// host_tcp_allow.
+const HostDNSAllowKey = "host_dns_allow"
+
+// HostDNSTimeout bounds one host_dns_resolve call
+const HostDNSTimeout = 10 * time.Second
+
+// HostDNS gates host_dns_resolve to an allowlist of names
+type HostDNS struct {
+	mu    sync.RWMutex
+	allow []string
+}
+
+// NewHostDNS creates a resolver that admits no name
+func NewHostDNS() *HostDNS {
+	return &HostDNS{}
+}
+
+// Configure applies host_dns_allow, or the hosts of host_tcp_allow
+func (d *HostDNS) Configure(config map[string]interface{}) error {
+	allow, ok, err := parseStringList(config, HostDNSAllowKey)
+	if err != nil {
+		return err
+	}
+	if !ok {
+		tcp, _, err := parseStringList(config, HostTCPAllowKey)
+		if err != nil {
+			return err
+		}
+		allow = []string{}
+		for _, pattern := range tcp {
+			if host, _, err := net.SplitHostPort(pattern); err == nil {
+				allow = append(allow, host)
+			}
+		}
+	}
+	d.mu.Lock()
+	d.allow = allow
+	d.mu.Unlock()
+	return nil
+}
+
+// Allows reports whether the plugin may resolve name
+func (d *HostDNS) Allows(name string) bool {
+	name = strings.TrimSuffix(name, ".")
+	d.mu.RLock()
+	defer d.mu.RUnlock()
+	for _, pattern := range d.allow {
+		if matchHost(pattern, name) {
+			return true
+		}
+	}
+	return false
+}
+
+// Resolve looks up the addresses of name
+func (d *HostDNS) Resolve(ctx context.Context, name string) ([]string, error) {
+	if name == "" {
+		return nil, fmt.Errorf("EINVAL: empty name")
+	}
+	if !d.Allows(name) {
+		return nil, fmt.Errorf("EACCES: %s not in %s", name, HostDNSAllowKey)
+	}
+
+	lookupCtx, cancel := context.WithTimeout(ctx, HostDNSTimeout)
+	defer cancel()
+	addrs, err := net.DefaultResolver.LookupHost(lookupCtx, name)
+	if err != nil {
+		var dnsErr *net.DNSError
+		switch {
+		case errors.As(err, &dnsErr) && dnsErr.IsNotFound:
+			return nil, fmt.Errorf("ENOENT: %s", name)
+		case errors.As(err, &dnsErr) && dnsErr.IsTimeout:
+			return nil, fmt.Errorf("ETIMEDOUT: resolving %s", name)
+		default:
+			return nil, fmt.Errorf("EIO: %v", err)
+		}
+	}
+	return addrs, nil
+}
+
+// HostDNSResolve returns (JSON pointer, error pointer); the JSON is a list
+// of address strings
+func HostDNSResolve(ctx context.Context, mod wazeroapi.Module, params []uint64, dns *HostDNS) []uint64 {
+	name, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_dns_resolve: failed to read name from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_dns_resolve: %s", name)
+
+	addrs, err := dns.Resolve(ctx, name)
+	if err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	jsonData, _ := json.Marshal(addrs)
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_dns_resolve: failed to write addresses to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(jsonPtr)}
+}
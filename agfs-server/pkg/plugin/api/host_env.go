@@ -0,0 +1,84 @@
+package api
+
+import (
+	"context"
+	"fmt"
+	"os"
+	"strings"
+	"sync"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostEnvAllowKey is the mount config key listing the environment
+// variables a WASM plugin may read; a trailing "*" allows a prefix
+// ("AWS_*"). Without it no variable is readable.
+const HostEnvAllowKey = "host_env_allow"
+
+// HostEnv gates host_env_get to an allowlist of variable names
+type HostEnv struct {
+	mu    sync.RWMutex
+	allow []string
+}
+
+// NewHostEnv creates an allowlist that admits nothing
+func NewHostEnv() *HostEnv {
+	return &HostEnv{}
+}
+
+// Configure applies host_env_allow from the mount config, if present
+func (e *HostEnv) Configure(config map[string]interface{}) error {
+	allow, ok, err := parseStringList(config, HostEnvAllowKey)
+	if err != nil || !ok {
+		return err
+	}
+	e.mu.Lock()
+	e.allow = allow
+	e.mu.Unlock()
+	return nil
+}
+
+// Allows reports whether the plugin may read variable key
+func (e *HostEnv) Allows(key string) bool {
+	e.mu.RLock()
+	defer e.mu.RUnlock()
+	for _, pattern := range e.allow {
+		if prefix, ok := strings.CutSuffix(pattern, "*"); ok {
+			if strings.HasPrefix(key, prefix) {
+				return true
+			}
+		} else if key == pattern {
+			return true
+		}
+	}
+	return false
+}
+
+// HostEnvGet returns (value pointer, error pointer); an unset variable is
+// ENOENT, one outside the allowlist EACCES
+func HostEnvGet(ctx context.Context, mod wazeroapi.Module, params []uint64, env *HostEnv) []uint64 {
+	key, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_env_get: failed to read key from memory")
+		return []uint64{0}
+	}
+
+	var reply string
+	if !env.Allows(key) {
+		log.Warnf("host_env_get: %s is not in %s", key, HostEnvAllowKey)
+		reply = fmt.Sprintf("EACCES: %s not in %s", key, HostEnvAllowKey)
+	} else if value, found := os.LookupEnv(key); !found {
+		reply = fmt.Sprintf("ENOENT: %s", key)
+	} else {
+		valuePtr, err := writeStringToMemory(mod, value)
+		if err != nil {
+			log.Errorf("host_env_get: failed to write value to memory: %v", err)
+			return []uint64{0}
+		}
+		return []uint64{uint64(valuePtr)}
+	}
+
+	errPtr, _ := writeStringToMemory(mod, reply)
+	return []uint64{uint64(errPtr) << 32}
+}
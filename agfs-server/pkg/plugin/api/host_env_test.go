@@ -0,0 +1,74 @@
+package api
+
+import (
+	"context"
+	"strings"
+	"testing"
+)
+
+func TestHostEnv_Allows(t *testing.T) {
+	e := NewHostEnv()
+	if err := e.Configure(map[string]interface{}{HostEnvAllowKey: []string{"AGFS_TEST_KEY", "AWS_*"}}); err != nil {
+		t.Fatalf("Configure failed: %v", err)
+	}
+
+	cases := map[string]bool{
+		"AGFS_TEST_KEY":  true,
+		"AGFS_TEST_KEY2": false,
+		"AWS_REGION":     true,
+		"AWS":            false,
+		"HOME":           false,
+	}
+	for key, want := range cases {
+		if got := e.Allows(key); got != want {
+			t.Errorf("Allows(%q) = %v, want %v", key, got, want)
+		}
+	}
+}
+
+func TestHostEnv_NoAllowlistDeniesAll(t *testing.T) {
+	e := NewHostEnv()
+	if err := e.Configure(map[string]interface{}{}); err != nil {
+		t.Fatalf("Configure failed: %v", err)
+	}
+	for _, key := range []string{"HOME", "PATH", ""} {
+		if e.Allows(key) {
+			t.Errorf("Allows(%q) = true without %s", key, HostEnvAllowKey)
+		}
+	}
+}
+
+func TestHostEnvGet(t *testing.T) {
+	t.Setenv("AGFS_TEST_SECRET", "s3cret")
+	mod := loadTestModule(t, "alloc")
+	ctx := context.Background()
+
+	call := func(env *HostEnv, key string) []uint64 {
+		keyPtr, err := writeStringToMemory(mod, key)
+		if err != nil {
+			t.Fatalf("failed to write key: %v", err)
+		}
+		return HostEnvGet(ctx, mod, []uint64{uint64(keyPtr)}, env)
+	}
+
+	if msg := replyError(t, mod, call(NewHostEnv(), "AGFS_TEST_SECRET")); !strings.HasPrefix(msg, "EACCES: ") {
+		t.Errorf("expected EACCES without an allowlist, got %q", msg)
+	}
+
+	env := NewHostEnv()
+	env.Configure(map[string]interface{}{HostEnvAllowKey: "AGFS_TEST_*"})
+	reply := call(env, "AGFS_TEST_SECRET")
+	if reply[0]>>32 != 0 {
+		t.Fatalf("expected a value, got error %q", replyError(t, mod, reply))
+	}
+	if value, _ := readStringFromMemory(mod, uint32(reply[0])); value != "s3cret" {
+		t.Errorf("expected s3cret, got %q", value)
+	}
+
+	if msg := replyError(t, mod, call(env, "AGFS_TEST_UNSET")); !strings.HasPrefix(msg, "ENOENT: ") {
+		t.Errorf("expected ENOENT for an unset variable, got %q", msg)
+	}
+	if msg := replyError(t, mod, call(env, "HOME")); !strings.HasPrefix(msg, "EACCES: ") {
+		t.Errorf("expected EACCES outside the allowlist, got %q", msg)
+	}
+}
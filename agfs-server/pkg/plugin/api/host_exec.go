@@ -0,0 +1,188 @@
+package api
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"sync"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostExecAllowKey is the mount config key listing the programs a WASM
+// plugin may run through host_exec, by name ("git") or absolute path.
+// Without it host_exec is disabled.
+const HostExecAllowKey = "host_exec_allow"
+
+// Limits on host_exec calls
+const (
+	MaxHostExecTimeout = 10 * time.Minute
+	MaxHostExecOutput  = 16 << 20
+)
+
+// HostExec gates host_exec to the programs a mount grants
+type HostExec struct {
+	mu    sync.RWMutex
+	allow []string
+}
+
+// HostExecResult is the reply to host_exec, JSON-encoded into plugin
+// memory; the output fields are base64
+type HostExecResult struct {
+	ExitCode int    `json:"ExitCode"`
+	Stdout   []byte `json:"Stdout"`
+	Stderr   []byte `json:"Stderr"`
+}
+
+// NewHostExec creates a gate that admits nothing
+func NewHostExec() *HostExec {
+	return &HostExec{}
+}
+
+// Configure applies host_exec_allow from the mount config, if present
+func (e *HostExec) Configure(config map[string]interface{}) error {
+	allow, ok, err := parseStringList(config, HostExecAllowKey)
+	if err != nil || !ok {
+		return err
+	}
+	e.mu.Lock()
+	e.allow = allow
+	e.mu.Unlock()
+	return nil
+}
+
+// Allows reports whether the plugin may run program
+// A bare name matches only a bare name; it is looked up in PATH
+func (e *HostExec) Allows(program string) bool {
+	e.mu.RLock()
+	defer e.mu.RUnlock()
+	for _, allowed := range e.allow {
+		if program == allowed {
+			return true
+		}
+	}
+	return false
+}
+
+// limitedBuffer keeps the first max bytes written and notes overflow
+type limitedBuffer struct {
+	buf      bytes.Buffer
+	max      int
+	overflow bool
+}
+
+func (b *limitedBuffer) Write(p []byte) (int, error) {
+	if room := b.max - b.buf.Len(); len(p) > room {
+		b.overflow = true
+		if room > 0 {
+			b.buf.Write(p[:room])
+		}
+		return len(p), nil
+	}
+	return b.buf.Write(p)
+}
+
+// Run executes argv with stdin under timeout
+// Output beyond MaxHostExecOutput fails the call with EFBIG
+func (e *HostExec) Run(ctx context.Context, argv []string, stdin []byte, timeout time.Duration) (*HostExecResult, error) {
+	if len(argv) == 0 || argv[0] == "" {
+		return nil, fmt.Errorf("EINVAL: empty argv")
+	}
+	if !e.Allows(argv[0]) {
+		return nil, fmt.Errorf("EACCES: %s not in %s", argv[0], HostExecAllowKey)
+	}
+	if filepath.Base(argv[0]) != argv[0] && !filepath.IsAbs(argv[0]) {
+		return nil, fmt.Errorf("EINVAL: program must be a name or an absolute path")
+	}
+	if timeout <= 0 || timeout > MaxHostExecTimeout {
+		timeout = MaxHostExecTimeout
+	}
+
+	runCtx, cancel := context.WithTimeout(ctx, timeout)
+	defer cancel()
+
+	cmd := exec.CommandContext(runCtx, argv[0], argv[1:]...)
+	// Only PATH is passed on; credentials go through host_env_get
+	cmd.Env = []string{"PATH=" + os.Getenv("PATH")}
+	cmd.Stdin = bytes.NewReader(stdin)
+	stdout := &limitedBuffer{max: MaxHostExecOutput}
+	stderr := &limitedBuffer{max: MaxHostExecOutput}
+	cmd.Stdout = stdout
+	cmd.Stderr = stderr
+
+	err := cmd.Run()
+	if runCtx.Err() == context.DeadlineExceeded {
+		return nil, fmt.Errorf("ETIMEDOUT: %s ran longer than %v", argv[0], timeout)
+	}
+	if stdout.overflow || stderr.overflow {
+		return nil, fmt.Errorf("EFBIG: %s wrote more than %d bytes", argv[0], MaxHostExecOutput)
+	}
+
+	result := &HostExecResult{Stdout: stdout.buf.Bytes(), Stderr: stderr.buf.Bytes()}
+	var exitErr *exec.ExitError
+	switch {
+	case err == nil:
+	case errors.As(err, &exitErr):
+		result.ExitCode = exitErr.ExitCode()
+	case errors.Is(err, exec.ErrNotFound), errors.Is(err, os.ErrNotExist):
+		return nil, fmt.Errorf("ENOENT: %s", argv[0])
+	default:
+		return nil, fmt.Errorf("EIO: %v", err)
+	}
+	return result, nil
+}
+
+// HostExecCall runs a program for a WASM plugin
+// Params: argv JSON, stdin pointer, stdin length, timeout in milliseconds.
+// Returns (JSON pointer, error pointer).
+func HostExecCall(ctx context.Context, mod wazeroapi.Module, params []uint64, gate *HostExec) []uint64 {
+	argvJSON, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_exec: failed to read argv from memory")
+		return []uint64{0}
+	}
+	var argv []string
+	if err := json.Unmarshal([]byte(argvJSON), &argv); err != nil {
+		errPtr, _ := writeStringToMemory(mod, fmt.Sprintf("EINVAL: bad argv: %v", err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	var stdin []byte
+	if stdinLen := uint32(params[2]); stdinLen > 0 {
+		data, ok := mod.Memory().Read(uint32(params[1]), stdinLen)
+		if !ok {
+			log.Errorf("host_exec: failed to read stdin from memory")
+			return []uint64{0}
+		}
+		stdin = append([]byte{}, data...)
+	}
+	timeout := time.Duration(uint32(params[3])) * time.Millisecond
+
+	log.Debugf("host_exec: argv=%q, stdin=%d, timeout=%v", argv, len(stdin), timeout)
+
+	result, err := gate.Run(ctx, argv, stdin, timeout)
+	if err != nil {
+		log.Warnf("host_exec: %v", err)
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	jsonData, err := json.Marshal(result)
+	if err != nil {
+		log.Errorf("host_exec: failed to marshal result: %v", err)
+		return []uint64{0}
+	}
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_exec: failed to write result to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(jsonPtr)}
+}
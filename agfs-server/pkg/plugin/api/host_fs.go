@@ -3,6 +3,12 @@ package api
 import (
 	"context"
 	"encoding/json"
+	"errors"
+	"io"
+	"os"
+	"strings"
+	"syscall"
+	"time"
 
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
 	log "github.com/sirupsen/logrus"
@@ -31,10 +37,15 @@ func HostFSRead(ctx context.Context, mod wazeroapi.Module, params []uint64, fs f
 		return []uint64{0}
 	}
 
-	data, err := fs.Read(path, offset, size)
-	if err != nil {
+	data, err := runHostCall(ctx, "host_fs_read", func() ([]byte, error) {
+		return fs.Read(path, offset, size)
+	})
+	// Filesystems report the last chunk of a file with io.EOF
+	if err != nil && !errors.Is(err, io.EOF) {
 		log.Errorf("host_fs_read: error reading file: %v", err)
-		return []uint64{0}
+		// Pack error: lower 32 bits = 0, upper 32 bits = error pointer
+		errPtr, _ := writeStringToMemory(mod, hostError("read", err))
+		return []uint64{uint64(errPtr) << 32}
 	}
 
 	// Write data to WASM memory
@@ -66,6 +77,8 @@ func HostFSWrite(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 		log.Errorf("host_fs_write: failed to read data from memory")
 		return []uint64{0}
 	}
+	// A timed-out call keeps running, so it must not hold guest memory
+	data = append([]byte{}, data...)
 
 	log.Debugf("host_fs_write: path=%s, dataLen=%d", path, dataLen)
 
@@ -74,10 +87,13 @@ func HostFSWrite(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 		return []uint64{0}
 	}
 
-	response, err := fs.Write(path, data)
+	response, err := runHostCall(ctx, "host_fs_write", func() ([]byte, error) {
+		return fs.Write(path, data)
+	})
 	if err != nil {
 		log.Errorf("host_fs_write: error writing file: %v", err)
-		return []uint64{0}
+		errPtr, _ := writeStringToMemory(mod, hostError("write", err))
+		return []uint64{uint64(errPtr) << 32}
 	}
 
 	// Write response to WASM memory
@@ -105,15 +121,17 @@ func HostFSStat(ctx context.Context, mod wazeroapi.Module, params []uint64, fs f
 
 	if fs == nil {
 		log.Errorf("host_fs_stat: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr) << 32}
 	}
 
-	fileInfo, err := fs.Stat(path)
+	fileInfo, err := runHostCall(ctx, "host_fs_stat", func() (*filesystem.FileInfo, error) {
+		return fs.Stat(path)
+	})
 	if err != nil {
 		log.Errorf("host_fs_stat: error stating file: %v", err)
 		// Pack error: upper 32 bits = error pointer
-		errStr := err.Error()
+		errStr := hostError("stat", err)
 		errPtr, err := writeStringToMemory(mod, errStr)
 		if err != nil {
 			return []uint64{0}
@@ -151,14 +169,16 @@ func HostFSReadDir(ctx context.Context, mod wazeroapi.Module, params []uint64, f
 
 	if fs == nil {
 		log.Errorf("host_fs_readdir: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr) << 32}
 	}
 
-	fileInfos, err := fs.ReadDir(path)
+	fileInfos, err := runHostCall(ctx, "host_fs_readdir", func() ([]filesystem.FileInfo, error) {
+		return fs.ReadDir(path)
+	})
 	if err != nil {
 		log.Errorf("host_fs_readdir: error reading directory: %v", err)
-		errStr := err.Error()
+		errStr := hostError("readdir", err)
 		errPtr, err := writeStringToMemory(mod, errStr)
 		if err != nil {
 			return []uint64{0}
@@ -194,14 +214,14 @@ func HostFSCreate(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	if fs == nil {
 		log.Errorf("host_fs_create: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.Create(path)
+	err := runHostOp(ctx, "host_fs_create", func() error { return fs.Create(path) })
 	if err != nil {
 		log.Errorf("host_fs_create: error creating file: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("create", err))
 		return []uint64{uint64(errPtr)}
 	}
 
@@ -221,14 +241,14 @@ func HostFSMkdir(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	if fs == nil {
 		log.Errorf("host_fs_mkdir: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.Mkdir(path, perm)
+	err := runHostOp(ctx, "host_fs_mkdir", func() error { return fs.Mkdir(path, perm) })
 	if err != nil {
 		log.Errorf("host_fs_mkdir: error creating directory: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("mkdir", err))
 		return []uint64{uint64(errPtr)}
 	}
 
@@ -247,14 +267,14 @@ func HostFSRemove(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	if fs == nil {
 		log.Errorf("host_fs_remove: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.Remove(path)
+	err := runHostOp(ctx, "host_fs_remove", func() error { return fs.Remove(path) })
 	if err != nil {
 		log.Errorf("host_fs_remove: error removing: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("remove", err))
 		return []uint64{uint64(errPtr)}
 	}
 
@@ -273,14 +293,14 @@ func HostFSRemoveAll(ctx context.Context, mod wazeroapi.Module, params []uint64,
 
 	if fs == nil {
 		log.Errorf("host_fs_remove_all: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.RemoveAll(path)
+	err := runHostOp(ctx, "host_fs_remove_all", func() error { return fs.RemoveAll(path) })
 	if err != nil {
 		log.Errorf("host_fs_remove_all: error removing: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("remove_all", err))
 		return []uint64{uint64(errPtr)}
 	}
 
@@ -305,14 +325,14 @@ func HostFSRename(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	if fs == nil {
 		log.Errorf("host_fs_rename: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.Rename(oldPath, newPath)
+	err := runHostOp(ctx, "host_fs_rename", func() error { return fs.Rename(oldPath, newPath) })
 	if err != nil {
 		log.Errorf("host_fs_rename: error renaming: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("rename", err))
 		return []uint64{uint64(errPtr)}
 	}
 
@@ -332,16 +352,545 @@ func HostFSChmod(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	if fs == nil {
 		log.Errorf("host_fs_chmod: no host filesystem provided")
-		errPtr, _ := writeStringToMemory(mod, "no host filesystem provided")
+		errPtr, _ := writeStringToMemory(mod, "EIO: no host filesystem provided")
 		return []uint64{uint64(errPtr)}
 	}
 
-	err := fs.Chmod(path, mode)
+	err := runHostOp(ctx, "host_fs_chmod", func() error { return fs.Chmod(path, mode) })
 	if err != nil {
 		log.Errorf("host_fs_chmod: error changing mode: %v", err)
-		errPtr, _ := writeStringToMemory(mod, err.Error())
+		errPtr, _ := writeStringToMemory(mod, hostError("chmod", err))
 		return []uint64{uint64(errPtr)}
 	}
 
 	return []uint64{0}
 }
+
+// HostFSAllowedRoots returns the sandbox roots as a JSON array, or null
+// when the plugin's host access is unrestricted
+func HostFSAllowedRoots(ctx context.Context, mod wazeroapi.Module, sandbox *HostSandbox) []uint64 {
+	jsonData, err := json.Marshal(sandbox.Roots())
+	if err != nil {
+		log.Errorf("host_fs_allowed_roots: failed to marshal roots: %v", err)
+		return []uint64{0}
+	}
+
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_fs_allowed_roots: failed to write JSON to memory: %v", err)
+		return []uint64{0}
+	}
+
+	// Pack: lower 32 bits = json pointer, upper 32 bits = 0 (no error)
+	return []uint64{uint64(jsonPtr)}
+}
+
+// wireCodes are the error codes agfs-wasm-ffi's Error::from_wire decodes
+var wireCodes = map[string]bool{
+	"ENOENT": true, "EACCES": true, "EEXIST": true, "EISDIR": true,
+	"ENOTDIR": true, "ENOTEMPTY": true, "EROFS": true, "EFBIG": true,
+	"ETIMEDOUT": true, "ECANCELED": true, "EINVAL": true, "EIO": true,
+	"EOTHER": true, "ENOSYS": true,
+}
+
+// wireCode is the wire code for err, found with errors.Is
+// Errors that match no filesystem or system error are sent as EIO
+func wireCode(err error) string {
+	switch {
+	case errors.Is(err, filesystem.ErrNotFound), errors.Is(err, os.ErrNotExist):
+		return "ENOENT"
+	case errors.Is(err, filesystem.ErrPermissionDenied), errors.Is(err, os.ErrPermission):
+		return "EACCES"
+	case errors.Is(err, filesystem.ErrAlreadyExists), errors.Is(err, os.ErrExist):
+		return "EEXIST"
+	case errors.Is(err, filesystem.ErrNotDirectory), errors.Is(err, syscall.ENOTDIR):
+		return "ENOTDIR"
+	case errors.Is(err, filesystem.ErrInvalidArgument), errors.Is(err, os.ErrInvalid):
+		return "EINVAL"
+	case errors.Is(err, filesystem.ErrNotSupported):
+		return "ENOSYS"
+	case errors.Is(err, syscall.EISDIR):
+		return "EISDIR"
+	case errors.Is(err, syscall.ENOTEMPTY):
+		return "ENOTEMPTY"
+	case errors.Is(err, syscall.EROFS):
+		return "EROFS"
+	case errors.Is(err, syscall.EFBIG):
+		return "EFBIG"
+	case errors.Is(err, context.DeadlineExceeded), errors.Is(err, os.ErrDeadlineExceeded):
+		return "ETIMEDOUT"
+	case errors.Is(err, context.Canceled):
+		return "ECANCELED"
+	}
+	return "EIO"
+}
+
+// wireError is err as the "CODE: message" string a host call hands the
+// plugin. Messages that already start with a wire code are sent unchanged
+func wireError(err error) string {
+	msg := err.Error()
+	if code, _, found := strings.Cut(msg, ": "); found && wireCodes[code] {
+		return msg
+	}
+	return wireCode(err) + ": " + msg
+}
+
+// hostError is the error string a host_fs_* call hands the plugin
+// Operations the host filesystem does not implement are sent as ENOSYS
+// with op, which the SDK reports as NotSupported
+func hostError(op string, err error) string {
+	if errors.Is(err, filesystem.ErrNotSupported) {
+		return "ENOSYS: " + op
+	}
+	return wireError(err)
+}
+
+// errorReply is the reply of a call that returns only an error pointer
+func errorReply(mod wazeroapi.Module, op string, err error) []uint64 {
+	if err == nil {
+		return []uint64{0}
+	}
+	log.Errorf("host_fs_%s: %v", op, err)
+	errPtr, werr := writeStringToMemory(mod, hostError(op, err))
+	if werr != nil {
+		return []uint64{1}
+	}
+	return []uint64{uint64(errPtr)}
+}
+
+// stringReply packs a (string pointer, error pointer) reply
+func stringReply(mod wazeroapi.Module, op string, s string, err error) []uint64 {
+	if err != nil {
+		return errorReplyHigh(mod, op, err)
+	}
+	ptr, err := writeStringToMemory(mod, s)
+	if err != nil {
+		log.Errorf("host_fs_%s: failed to write result to memory: %v", op, err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(ptr)}
+}
+
+// HostFSLstat stats a path without following a final symlink
+// Returns (JSON pointer, error pointer)
+func HostFSLstat(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_fs_lstat: failed to read path from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_fs_lstat: path=%s", path)
+
+	lstater, ok := fs.(filesystem.Lstater)
+	if !ok {
+		return stringReply(mod, "lstat", "", filesystem.NewNotSupportedError("lstat", path))
+	}
+	fileInfo, err := runHostCall(ctx, "host_fs_lstat", func() (*filesystem.FileInfo, error) {
+		return lstater.Lstat(path)
+	})
+	if err != nil {
+		return stringReply(mod, "lstat", "", err)
+	}
+	jsonData, err := json.Marshal(fileInfo)
+	return stringReply(mod, "lstat", string(jsonData), err)
+}
+
+// HostFSRealPath resolves every symlink in a path
+// Returns (path pointer, error pointer)
+func HostFSRealPath(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_fs_realpath: failed to read path from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_fs_realpath: path=%s", path)
+
+	realPather, ok := fs.(filesystem.RealPather)
+	if !ok {
+		return stringReply(mod, "realpath", "", filesystem.NewNotSupportedError("realpath", path))
+	}
+	resolved, err := runHostCall(ctx, "host_fs_realpath", func() (string, error) {
+		return realPather.RealPath(path)
+	})
+	return stringReply(mod, "realpath", resolved, err)
+}
+
+// HostFSCreateExclusive creates a file only if the path does not exist
+// Returns an error pointer, 0 on success
+func HostFSCreateExclusive(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+
+	log.Debugf("host_fs_create_exclusive: path=%s", path)
+
+	creator, ok := fs.(filesystem.ExclusiveCreator)
+	if !ok {
+		return errorReply(mod, "create_exclusive", filesystem.NewNotSupportedError("create_exclusive", path))
+	}
+	return errorReply(mod, "create_exclusive", runHostOp(ctx, "host_fs_create_exclusive", func() error {
+		return creator.CreateExclusive(path)
+	}))
+}
+
+// HostFSSymlink creates a symlink; the target is passed through unchanged
+// Returns an error pointer, 0 on success
+func HostFSSymlink(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	target, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	linkPath, ok := readStringFromMemory(mod, uint32(params[1]))
+	if !ok {
+		return []uint64{1}
+	}
+
+	log.Debugf("host_fs_symlink: target=%s, linkPath=%s", target, linkPath)
+
+	symlinker, ok := fs.(filesystem.Symlinker)
+	if !ok {
+		return errorReply(mod, "symlink", filesystem.NewNotSupportedError("symlink", linkPath))
+	}
+	return errorReply(mod, "symlink", runHostOp(ctx, "host_fs_symlink", func() error {
+		return symlinker.Symlink(target, linkPath)
+	}))
+}
+
+// HostFSReadlink reads the target of a symlink
+// Returns (target pointer, error pointer)
+func HostFSReadlink(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_fs_readlink: failed to read path from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_fs_readlink: path=%s", path)
+
+	symlinker, ok := fs.(filesystem.Symlinker)
+	if !ok {
+		return stringReply(mod, "readlink", "", filesystem.NewNotSupportedError("readlink", path))
+	}
+	target, err := runHostCall(ctx, "host_fs_readlink", func() (string, error) {
+		return symlinker.Readlink(path)
+	})
+	return stringReply(mod, "readlink", target, err)
+}
+
+// HostFSTruncate shrinks or zero-extends a file
+// Returns an error pointer, 0 on success
+func HostFSTruncate(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	size := int64(params[1])
+
+	log.Debugf("host_fs_truncate: path=%s, size=%d", path, size)
+
+	truncater, ok := fs.(filesystem.Truncater)
+	if !ok {
+		return errorReply(mod, "truncate", filesystem.NewNotSupportedError("truncate", path))
+	}
+	return errorReply(mod, "truncate", runHostOp(ctx, "host_fs_truncate", func() error {
+		return truncater.Truncate(path, size)
+	}))
+}
+
+// HostFSSetTimes sets access and modification times, in Unix seconds
+// Returns an error pointer, 0 on success
+func HostFSSetTimes(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	atime := time.Unix(int64(params[1]), 0)
+	mtime := time.Unix(int64(params[2]), 0)
+
+	log.Debugf("host_fs_set_times: path=%s, atime=%v, mtime=%v", path, atime, mtime)
+
+	setter, ok := fs.(filesystem.TimeSetter)
+	if !ok {
+		return errorReply(mod, "set_times", filesystem.NewNotSupportedError("set_times", path))
+	}
+	return errorReply(mod, "set_times", runHostOp(ctx, "host_fs_set_times", func() error {
+		return setter.SetTimes(path, atime, mtime)
+	}))
+}
+
+// HostFSChown changes the owner and group of a file
+// Returns an error pointer, 0 on success
+func HostFSChown(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	uid := uint32(params[1])
+	gid := uint32(params[2])
+
+	log.Debugf("host_fs_chown: path=%s, uid=%d, gid=%d", path, uid, gid)
+
+	chowner, ok := fs.(filesystem.Chowner)
+	if !ok {
+		return errorReply(mod, "chown", filesystem.NewNotSupportedError("chown", path))
+	}
+	return errorReply(mod, "chown", runHostOp(ctx, "host_fs_chown", func() error {
+		return chowner.Chown(path, uid, gid)
+	}))
+}
+
+// HostFSLink creates a hard link
+// Returns an error pointer, 0 on success
+func HostFSLink(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	existing, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	newPath, ok := readStringFromMemory(mod, uint32(params[1]))
+	if !ok {
+		return []uint64{1}
+	}
+
+	log.Debugf("host_fs_link: existing=%s, newPath=%s", existing, newPath)
+
+	linker, ok := fs.(filesystem.Linker)
+	if !ok {
+		return errorReply(mod, "link", filesystem.NewNotSupportedError("link", newPath))
+	}
+	return errorReply(mod, "link", runHostOp(ctx, "host_fs_link", func() error {
+		return linker.Link(existing, newPath)
+	}))
+}
+
+// HostFSCreateFlags creates a file as the filesystem.CreateFlag* bits ask
+// Returns an error pointer, 0 on success
+func HostFSCreateFlags(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	flags := uint32(params[1])
+	mode := uint32(params[2])
+
+	log.Debugf("host_fs_create_flags: path=%s, flags=%#x, mode=%o", path, flags, mode)
+
+	creator, ok := fs.(filesystem.FlagCreator)
+	if !ok {
+		return errorReply(mod, "create_flags", filesystem.NewNotSupportedError("create_flags", path))
+	}
+	return errorReply(mod, "create_flags", runHostOp(ctx, "host_fs_create_flags", func() error {
+		return creator.CreateWithFlags(path, flags, mode)
+	}))
+}
+
+// Errnos host_fs_seek replies with, as the SDK decodes them
+const (
+	errnoENOENT    = 2
+	errnoEIO       = 5
+	errnoENXIO     = 6
+	errnoEACCES    = 13
+	errnoEINVAL    = 22
+	errnoENOSYS    = 38
+	errnoETIMEDOUT = 110
+)
+
+// errnoReply is the negated errno reply of a call that returns a
+// non-negative value otherwise
+func errnoReply(errno int64) []uint64 {
+	return []uint64{uint64(-errno)}
+}
+
+// hostErrno is the errno for err, for host calls that reply with one
+func hostErrno(err error) int64 {
+	switch {
+	case errors.Is(err, filesystem.ErrNotFound):
+		return errnoENOENT
+	case errors.Is(err, filesystem.ErrPermissionDenied):
+		return errnoEACCES
+	case errors.Is(err, filesystem.ErrInvalidArgument):
+		return errnoEINVAL
+	case errors.Is(err, filesystem.ErrNotSupported):
+		return errnoENOSYS
+	case strings.HasPrefix(err.Error(), "ETIMEDOUT:"):
+		return errnoETIMEDOUT
+	}
+	return errnoEIO
+}
+
+// HostFSSeek finds the next data (SEEK_DATA) or hole (SEEK_HOLE) of a file
+// Returns the offset, or a negated errno: ENXIO when there is none
+func HostFSSeek(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return errnoReply(errnoEINVAL)
+	}
+	offset := int64(params[1])
+	whence := int(uint32(params[2]))
+
+	log.Debugf("host_fs_seek: path=%s, offset=%d, whence=%d", path, offset, whence)
+
+	seeker, ok := fs.(filesystem.DataSeeker)
+	if !ok {
+		return errnoReply(errnoENOSYS)
+	}
+	type seekResult struct {
+		next  int64
+		found bool
+	}
+	result, err := runHostCall(ctx, "host_fs_seek", func() (seekResult, error) {
+		next, found, err := seeker.SeekData(path, offset, whence)
+		return seekResult{next, found}, err
+	})
+	if err != nil {
+		log.Errorf("host_fs_seek: %v", err)
+		return errnoReply(hostErrno(err))
+	}
+	if !result.found {
+		return errnoReply(errnoENXIO)
+	}
+	return []uint64{uint64(result.next)}
+}
+
+// HostFSAllocate reserves space for length bytes at offset
+// Returns an error pointer, 0 on success
+func HostFSAllocate(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	offset := int64(params[1])
+	length := int64(params[2])
+
+	log.Debugf("host_fs_allocate: path=%s, offset=%d, length=%d", path, offset, length)
+
+	allocator, ok := fs.(filesystem.Allocator)
+	if !ok {
+		return errorReply(mod, "allocate", filesystem.NewNotSupportedError("allocate", path))
+	}
+	return errorReply(mod, "allocate", runHostOp(ctx, "host_fs_allocate", func() error {
+		return allocator.Allocate(path, offset, length)
+	}))
+}
+
+// HostFSAccess checks whether the host may use a path, like access(2)
+// Returns an error pointer, 0 on success
+func HostFSAccess(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	mode := uint32(params[1])
+
+	log.Debugf("host_fs_access: path=%s, mode=%o", path, mode)
+
+	accesser, ok := fs.(filesystem.Accesser)
+	if !ok {
+		return errorReply(mod, "access", filesystem.NewNotSupportedError("access", path))
+	}
+	return errorReply(mod, "access", runHostOp(ctx, "host_fs_access", func() error {
+		return accesser.Access(path, mode)
+	}))
+}
+
+// HostFSAppend adds data to the end of a file, creating it if needed
+// Returns an error pointer, 0 on success
+func HostFSAppend(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	data, ok := mod.Memory().Read(uint32(params[1]), uint32(params[2]))
+	if !ok {
+		log.Errorf("host_fs_append: failed to read data from memory")
+		return []uint64{1}
+	}
+	// A timed-out call keeps running, so it must not hold guest memory
+	data = append([]byte{}, data...)
+
+	log.Debugf("host_fs_append: path=%s, dataLen=%d", path, len(data))
+
+	appender, ok := fs.(filesystem.Appender)
+	if !ok {
+		return errorReply(mod, "append", filesystem.NewNotSupportedError("append", path))
+	}
+	return errorReply(mod, "append", runHostOp(ctx, "host_fs_append", func() error {
+		return appender.Append(path, data)
+	}))
+}
+
+// HostFSRenameFlags renames a path with renameat2(2) flags
+// Returns an error pointer, 0 on success
+func HostFSRenameFlags(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	oldPath, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	newPath, ok := readStringFromMemory(mod, uint32(params[1]))
+	if !ok {
+		return []uint64{1}
+	}
+	flags := uint32(params[2])
+
+	log.Debugf("host_fs_rename_flags: old=%s, new=%s, flags=%d", oldPath, newPath, flags)
+
+	// The SDK tells an unsupported exchange apart from an unsupported rename
+	op := "rename"
+	if flags&filesystem.RenameExchange != 0 {
+		op = "rename exchange"
+	}
+	if err := filesystem.ValidateRenameFlags(flags); err != nil {
+		return errorReply(mod, op, err)
+	}
+	renamer, ok := fs.(filesystem.FlagRenamer)
+	if !ok {
+		return errorReply(mod, op, filesystem.NewNotSupportedError(op, newPath))
+	}
+	return errorReply(mod, op, runHostOp(ctx, "host_fs_rename_flags", func() error {
+		return renamer.RenameWithFlags(oldPath, newPath, flags)
+	}))
+}
+
+// HostFSWriteAt writes data at an offset, keeping the rest of the file
+// Returns (bytes written, 0) or (0, error pointer)
+func HostFSWriteAt(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{0}
+	}
+	offset := int64(params[1])
+	data, ok := mod.Memory().Read(uint32(params[2]), uint32(params[3]))
+	if !ok {
+		log.Errorf("host_fs_write_at: failed to read data from memory")
+		return []uint64{0}
+	}
+	// A timed-out call keeps running, so it must not hold guest memory
+	data = append([]byte{}, data...)
+
+	log.Debugf("host_fs_write_at: path=%s, offset=%d, dataLen=%d", path, offset, len(data))
+
+	writer, ok := fs.(filesystem.WriterAt)
+	if !ok {
+		return errorReplyHigh(mod, "write_at", filesystem.NewNotSupportedError("write_at", path))
+	}
+	written, err := runHostCall(ctx, "host_fs_write_at", func() (int64, error) {
+		return writer.WriteAt(path, offset, data)
+	})
+	if err != nil {
+		return errorReplyHigh(mod, "write_at", err)
+	}
+	return []uint64{uint64(uint32(written))}
+}
+
+// errorReplyHigh is the (0, error pointer) reply of a call that packs a
+// value with its error
+func errorReplyHigh(mod wazeroapi.Module, op string, err error) []uint64 {
+	log.Errorf("host_fs_%s: %v", op, err)
+	errPtr, werr := writeStringToMemory(mod, hostError(op, err))
+	if werr != nil {
+		return []uint64{0}
+	}
+	return []uint64{uint64(errPtr) << 32}
+}
@@ -0,0 +1,323 @@
+package api
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"net/http"
+	"net/url"
+	"strings"
+	"sync"
+	"syscall"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostHTTPAllowKey is the mount config key listing the servers a WASM
+// plugin may send requests to, as "host" (any port) or "host:port". Hosts
+// match as in host_tcp_allow. Loopback, private and link-local addresses
+// are only reached when listed by IP ("127.0.0.1:8080"), whatever name
+// they were resolved from. Without it host_http_request is disabled.
+const HostHTTPAllowKey = "host_http_allow"
+
+// Server-side ceilings for host_http_request; a plugin may ask for less
+const (
+	MaxHostHTTPTimeout   = 2 * time.Minute
+	MaxHostHTTPResponse  = 64 << 20
+	MaxHostHTTPRedirects = 10
+)
+
+// HostHTTP gates host_http_request to the servers a mount allows
+// The allowlist is checked on the request URL and on every redirect, and
+// the address actually dialed is checked after name resolution
+type HostHTTP struct {
+	mu     sync.RWMutex
+	allow  []string
+	client *http.Client
+}
+
+// httpDeniedError is a request or dial the allowlist refused
+type httpDeniedError struct {
+	msg string
+}
+
+func (e *httpDeniedError) Error() string {
+	return "EACCES: " + e.msg
+}
+
+// NewHostHTTP creates a client that admits no server
+func NewHostHTTP() *HostHTTP {
+	h := &HostHTTP{}
+	transport := http.DefaultTransport.(*http.Transport).Clone()
+	// A proxy would dial on the plugin's behalf, past the address check
+	transport.Proxy = nil
+	transport.DialContext = (&net.Dialer{
+		Timeout: 30 * time.Second,
+		Control: h.checkDial,
+	}).DialContext
+	h.client = &http.Client{
+		Transport:     transport,
+		CheckRedirect: h.checkRedirect,
+	}
+	return h
+}
+
+// splitHTTPPattern splits an allowlist entry into host and port, "*" when
+// the entry names no port
+func splitHTTPPattern(pattern string) (string, string, error) {
+	if host, port, err := net.SplitHostPort(pattern); err == nil {
+		return host, port, nil
+	}
+	host := strings.TrimSuffix(strings.TrimPrefix(pattern, "["), "]")
+	if host == "" || (strings.Contains(host, ":") && net.ParseIP(host) == nil) {
+		return "", "", fmt.Errorf("%s: %q: expected host or host:port", HostHTTPAllowKey, pattern)
+	}
+	return host, "*", nil
+}
+
+// Configure applies host_http_allow from the mount config, if present
+func (h *HostHTTP) Configure(config map[string]interface{}) error {
+	allow, ok, err := parseStringList(config, HostHTTPAllowKey)
+	if err != nil || !ok {
+		return err
+	}
+	for _, pattern := range allow {
+		if _, _, err := splitHTTPPattern(pattern); err != nil {
+			return err
+		}
+	}
+	h.mu.Lock()
+	h.allow = allow
+	h.mu.Unlock()
+	return nil
+}
+
+// Allows reports whether the plugin may send requests to host on port
+func (h *HostHTTP) Allows(host, port string) bool {
+	h.mu.RLock()
+	defer h.mu.RUnlock()
+	for _, pattern := range h.allow {
+		pHost, pPort, _ := splitHTTPPattern(pattern)
+		if (pPort == "*" || pPort == port) && matchHost(pHost, host) {
+			return true
+		}
+	}
+	return false
+}
+
+// allowsIP reports whether the plugin may dial ip on port: any public
+// unicast address, others only when an entry names that IP
+func (h *HostHTTP) allowsIP(ip net.IP, port string) bool {
+	if ip == nil {
+		return false
+	}
+	if ip.IsGlobalUnicast() && !ip.IsPrivate() {
+		return true
+	}
+	h.mu.RLock()
+	defer h.mu.RUnlock()
+	for _, pattern := range h.allow {
+		pHost, pPort, _ := splitHTTPPattern(pattern)
+		if (pPort == "*" || pPort == port) && ip.Equal(net.ParseIP(pHost)) {
+			return true
+		}
+	}
+	return false
+}
+
+// checkURL admits a request or redirect target
+func (h *HostHTTP) checkURL(u *url.URL) error {
+	port := u.Port()
+	switch {
+	case u.Scheme != "http" && u.Scheme != "https":
+		return &httpDeniedError{fmt.Sprintf("unsupported scheme %q", u.Scheme)}
+	case port == "" && u.Scheme == "https":
+		port = "443"
+	case port == "":
+		port = "80"
+	}
+	if !h.Allows(u.Hostname(), port) {
+		return &httpDeniedError{fmt.Sprintf("%s not in %s", net.JoinHostPort(u.Hostname(), port), HostHTTPAllowKey)}
+	}
+	return nil
+}
+
+// checkRedirect admits a redirect the way checkURL admits the request
+func (h *HostHTTP) checkRedirect(req *http.Request, via []*http.Request) error {
+	if len(via) >= MaxHostHTTPRedirects {
+		return fmt.Errorf("stopped after %d redirects", MaxHostHTTPRedirects)
+	}
+	return h.checkURL(req.URL)
+}
+
+// checkDial admits the resolved address of a connection, so a name that
+// resolves to an internal address cannot reach it
+func (h *HostHTTP) checkDial(network, address string, _ syscall.RawConn) error {
+	host, port, err := net.SplitHostPort(address)
+	if err != nil {
+		return err
+	}
+	if !h.allowsIP(net.ParseIP(host), port) {
+		return &httpDeniedError{fmt.Sprintf("%s is not a public address and not in %s", address, HostHTTPAllowKey)}
+	}
+	return nil
+}
+
+// Do sends one request and reads at most limit bytes of the response
+// Errors carry a wire code: EACCES for a server outside the allowlist,
+// ETIMEDOUT, EFBIG for an oversized response, EINVAL or EIO otherwise
+func (h *HostHTTP) Do(ctx context.Context, method, rawURL string, headers map[string]string, body []byte, timeout time.Duration, limit int64) (*HostHTTPResponse, error) {
+	reqCtx, cancel := context.WithTimeout(ctx, timeout)
+	defer cancel()
+
+	req, err := http.NewRequestWithContext(reqCtx, strings.ToUpper(method), rawURL, bytes.NewReader(body))
+	if err != nil {
+		return nil, fmt.Errorf("EINVAL: %v", err)
+	}
+	if err := h.checkURL(req.URL); err != nil {
+		return nil, err
+	}
+	for name, value := range headers {
+		req.Header.Set(name, value)
+	}
+
+	resp, err := h.client.Do(req)
+	if err != nil {
+		return nil, httpError(err, method, rawURL)
+	}
+	defer resp.Body.Close()
+
+	// Read one byte past the limit to tell "exactly at" from "over"
+	data, err := io.ReadAll(io.LimitReader(resp.Body, limit+1))
+	if err != nil {
+		return nil, httpError(err, method, rawURL)
+	}
+	if int64(len(data)) > limit {
+		return nil, fmt.Errorf("EFBIG: response larger than %d bytes", limit)
+	}
+
+	reply := &HostHTTPResponse{
+		Status:  resp.StatusCode,
+		Headers: make(map[string]string, len(resp.Header)),
+		Body:    data,
+	}
+	for name, values := range resp.Header {
+		reply.Headers[name] = strings.Join(values, ", ")
+	}
+	return reply, nil
+}
+
+// Close drops the plugin's idle keep-alive connections
+func (h *HostHTTP) Close() {
+	h.client.CloseIdleConnections()
+}
+
+// httpError converts a client error to a wire error
+func httpError(err error, method, rawURL string) error {
+	var denied *httpDeniedError
+	switch {
+	case errors.As(err, &denied):
+		return denied
+	case errors.Is(err, context.DeadlineExceeded):
+		return fmt.Errorf("ETIMEDOUT: %s %s", method, rawURL)
+	default:
+		return fmt.Errorf("EIO: %v", err)
+	}
+}
+
+// HostHTTPResponse is the reply to host_http_request, JSON-encoded into
+// plugin memory. Body is base64, as encoding/json writes []byte.
+type HostHTTPResponse struct {
+	Status  int               `json:"Status"`
+	Headers map[string]string `json:"Headers"`
+	Body    []byte            `json:"Body"`
+}
+
+// HostHTTPRequest performs an HTTP request for a WASM plugin
+// Params: method, url, headers JSON, body pointer, body length, timeout in
+// milliseconds, response size limit. Returns (JSON pointer, error pointer).
+func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64, gate *HostHTTP) []uint64 {
+	methodPtr := uint32(params[0])
+	urlPtr := uint32(params[1])
+	headersPtr := uint32(params[2])
+	bodyPtr := uint32(params[3])
+	bodyLen := uint32(params[4])
+	timeoutMs := uint32(params[5])
+	maxResponse := uint32(params[6])
+
+	method, ok := readStringFromMemory(mod, methodPtr)
+	if !ok {
+		log.Errorf("host_http_request: failed to read method from memory")
+		return []uint64{0}
+	}
+	rawURL, ok := readStringFromMemory(mod, urlPtr)
+	if !ok {
+		log.Errorf("host_http_request: failed to read url from memory")
+		return []uint64{0}
+	}
+
+	headers := map[string]string{}
+	if headersJSON, ok := readStringFromMemory(mod, headersPtr); ok && headersJSON != "" {
+		if err := json.Unmarshal([]byte(headersJSON), &headers); err != nil {
+			return hostHTTPError(mod, fmt.Sprintf("EINVAL: bad headers: %v", err))
+		}
+	}
+
+	var body []byte
+	if bodyLen > 0 {
+		data, ok := mod.Memory().Read(bodyPtr, bodyLen)
+		if !ok {
+			log.Errorf("host_http_request: failed to read body from memory")
+			return []uint64{0}
+		}
+		// Copy out of plugin memory, which may move while the request runs
+		body = append([]byte{}, data...)
+	}
+
+	timeout := time.Duration(timeoutMs) * time.Millisecond
+	if timeout <= 0 || timeout > MaxHostHTTPTimeout {
+		timeout = MaxHostHTTPTimeout
+	}
+	limit := int64(maxResponse)
+	if limit <= 0 || limit > MaxHostHTTPResponse {
+		limit = MaxHostHTTPResponse
+	}
+
+	log.Debugf("host_http_request: %s %s (body=%d, timeout=%v, limit=%d)", method, rawURL, len(body), timeout, limit)
+
+	reply, err := gate.Do(ctx, method, rawURL, headers, body, timeout, limit)
+	if err != nil {
+		var denied *httpDeniedError
+		if errors.As(err, &denied) {
+			log.Warnf("host_http_request: %v", err)
+		}
+		return hostHTTPError(mod, err.Error())
+	}
+
+	jsonData, err := json.Marshal(reply)
+	if err != nil {
+		log.Errorf("host_http_request: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_http_request: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+
+	// Pack: lower 32 bits = json pointer, upper 32 bits = 0 (no error)
+	return []uint64{uint64(jsonPtr)}
+}
+
+func hostHTTPError(mod wazeroapi.Module, msg string) []uint64 {
+	log.Debugf("host_http_request: %s", msg)
+	errPtr, _ := writeStringToMemory(mod, msg)
+	return []uint64{uint64(errPtr) << 32}
+}
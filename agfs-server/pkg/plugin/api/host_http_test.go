@@ -0,0 +1,129 @@
+package api
+
+import (
+	"context"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"time"
+)
+
+func newHostHTTP(t *testing.T, allow ...string) *HostHTTP {
+	t.Helper()
+	h := NewHostHTTP()
+	if err := h.Configure(map[string]interface{}{HostHTTPAllowKey: allow}); err != nil {
+		t.Fatalf("Configure failed: %v", err)
+	}
+	return h
+}
+
+func get(h *HostHTTP, url string) (*HostHTTPResponse, error) {
+	return h.Do(context.Background(), "GET", url, nil, nil, 5*time.Second, 1<<20)
+}
+
+func expectDenied(t *testing.T, err error, want string) {
+	t.Helper()
+	if err == nil || !strings.HasPrefix(err.Error(), "EACCES: ") {
+		t.Fatalf("expected EACCES, got %v", err)
+	}
+	if !strings.Contains(err.Error(), want) {
+		t.Errorf("expected error to mention %q, got %v", want, err)
+	}
+}
+
+func TestHostHTTP_Allows(t *testing.T) {
+	h := newHostHTTP(t, "api.example.com", "*.example.org:443", "::1")
+
+	cases := []struct {
+		host, port string
+		want       bool
+	}{
+		{"api.example.com", "80", true},
+		{"API.example.com", "8443", true},
+		{"x.example.org", "443", true},
+		{"x.example.org", "80", false},
+		{"example.org", "443", false},
+		{"::1", "8080", true},
+		{"evil.com", "80", false},
+	}
+	for _, c := range cases {
+		if got := h.Allows(c.host, c.port); got != c.want {
+			t.Errorf("Allows(%q, %q) = %v, want %v", c.host, c.port, got, c.want)
+		}
+	}
+
+	if err := NewHostHTTP().Configure(map[string]interface{}{HostHTTPAllowKey: []string{"a:b:c"}}); err == nil {
+		t.Errorf("expected an error for a malformed entry")
+	}
+}
+
+func TestHostHTTP_DisabledWithoutAllowlist(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		t.Errorf("request should not reach the server")
+	}))
+	defer server.Close()
+
+	_, err := get(NewHostHTTP(), server.URL)
+	expectDenied(t, err, HostHTTPAllowKey)
+}
+
+func TestHostHTTP_Do(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Header().Set("X-Test", "yes")
+		w.Write([]byte("hello"))
+	}))
+	defer server.Close()
+
+	h := newHostHTTP(t, server.Listener.Addr().String())
+	resp, err := get(h, server.URL)
+	if err != nil {
+		t.Fatalf("Do failed: %v", err)
+	}
+	if resp.Status != http.StatusOK || string(resp.Body) != "hello" || resp.Headers["X-Test"] != "yes" {
+		t.Errorf("unexpected response: %+v", resp)
+	}
+
+	_, err = h.Do(context.Background(), "GET", server.URL, nil, nil, 5*time.Second, 4)
+	if err == nil || !strings.HasPrefix(err.Error(), "EFBIG: ") {
+		t.Errorf("expected EFBIG, got %v", err)
+	}
+}
+
+func TestHostHTTP_ChecksDialedAddress(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		t.Errorf("request should not reach the server")
+	}))
+	defer server.Close()
+
+	// The name is allowed, but it resolves to loopback, which is not listed
+	_, port, _ := net.SplitHostPort(server.Listener.Addr().String())
+	h := newHostHTTP(t, "localhost")
+	_, err := get(h, "http://localhost:"+port+"/")
+	expectDenied(t, err, "not a public address")
+}
+
+func TestHostHTTP_ChecksRedirects(t *testing.T) {
+	target := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte("moved here"))
+	}))
+	defer target.Close()
+	origin := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		http.Redirect(w, r, target.URL, http.StatusFound)
+	}))
+	defer origin.Close()
+
+	h := newHostHTTP(t, origin.Listener.Addr().String())
+	_, err := get(h, origin.URL)
+	expectDenied(t, err, target.Listener.Addr().String())
+
+	h = newHostHTTP(t, origin.Listener.Addr().String(), target.Listener.Addr().String())
+	resp, err := get(h, origin.URL)
+	if err != nil {
+		t.Fatalf("Do failed: %v", err)
+	}
+	if string(resp.Body) != "moved here" {
+		t.Errorf("expected the redirect target's body, got %q", resp.Body)
+	}
+}
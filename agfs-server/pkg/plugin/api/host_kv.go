@@ -0,0 +1,346 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"os"
+	"path/filepath"
+	"sort"
+	"strings"
+	"sync"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostKVPathKey is the mount config key naming the file that backs a
+// plugin's key-value store. Without it the store lives in memory and is
+// lost when the plugin is unmounted
+const HostKVPathKey = "host_kv_path"
+
+// Limits on host_kv_* calls
+const (
+	MaxHostKVKey   = 1024
+	MaxHostKVValue = 1 << 20
+	MaxHostKVScan  = 1000
+)
+
+// HostKV is the key-value store behind the host_kv_* imports
+// Each loaded plugin gets its own; the whole store is rewritten to its
+// file on every change, which suits the small metadata it is meant for
+type HostKV struct {
+	mu    sync.Mutex
+	store *kvStore
+}
+
+// kvStore is the contents of one store; mounts naming the same file
+// share it, so their writes are serialized and neither loses the other's
+type kvStore struct {
+	mu   sync.Mutex
+	path string // "": memory only
+	data map[string][]byte
+	refs int
+}
+
+// kvFiles are the file-backed stores in use, by cleaned path
+var kvFiles = struct {
+	sync.Mutex
+	stores map[string]*kvStore
+}{stores: map[string]*kvStore{}}
+
+// HostKVEntry is one result of host_kv_scan
+type HostKVEntry struct {
+	Key   string `json:"Key"`
+	Value []byte `json:"Value"`
+}
+
+// NewHostKV creates an empty in-memory store
+func NewHostKV() *HostKV {
+	return &HostKV{store: &kvStore{data: map[string][]byte{}}}
+}
+
+// parseHostKVPath reads host_kv_path from a mount config
+func parseHostKVPath(config map[string]interface{}) (string, error) {
+	value, ok := config[HostKVPathKey]
+	if !ok {
+		return "", nil
+	}
+	path, ok := value.(string)
+	if !ok || path == "" {
+		return "", fmt.Errorf("%s: expected a file path", HostKVPathKey)
+	}
+	abs, err := filepath.Abs(path)
+	if err != nil {
+		return "", fmt.Errorf("%s: %w", HostKVPathKey, err)
+	}
+	return abs, nil
+}
+
+// Configure opens the store file named by host_kv_path, if present, and
+// loads its contents, or joins the mount already using it
+func (kv *HostKV) Configure(config map[string]interface{}) error {
+	path, err := parseHostKVPath(config)
+	if err != nil || path == "" {
+		return err
+	}
+
+	kvFiles.Lock()
+	defer kvFiles.Unlock()
+	store, ok := kvFiles.stores[path]
+	if !ok {
+		data := map[string][]byte{}
+		raw, err := os.ReadFile(path)
+		if err != nil && !os.IsNotExist(err) {
+			return fmt.Errorf("%s: %w", HostKVPathKey, err)
+		}
+		if len(raw) > 0 {
+			if err := json.Unmarshal(raw, &data); err != nil {
+				return fmt.Errorf("%s: corrupt store %s: %w", HostKVPathKey, path, err)
+			}
+		}
+		store = &kvStore{path: path, data: data}
+		kvFiles.stores[path] = store
+	}
+	store.refs++
+
+	kv.release()
+	kv.mu.Lock()
+	kv.store = store
+	kv.mu.Unlock()
+	return nil
+}
+
+// release leaves the file-backed store, if any; kvFiles must be locked
+func (kv *HostKV) release() {
+	kv.mu.Lock()
+	store := kv.store
+	kv.store = &kvStore{data: map[string][]byte{}}
+	kv.mu.Unlock()
+	if store.path == "" {
+		return
+	}
+	store.refs--
+	if store.refs == 0 {
+		delete(kvFiles.stores, store.path)
+	}
+}
+
+// Close leaves the store file; called when the plugin shuts down
+func (kv *HostKV) Close() {
+	kvFiles.Lock()
+	kv.release()
+	kvFiles.Unlock()
+}
+
+func (kv *HostKV) current() *kvStore {
+	kv.mu.Lock()
+	defer kv.mu.Unlock()
+	return kv.store
+}
+
+// Get returns the value stored under key
+func (kv *HostKV) Get(key string) ([]byte, bool) {
+	store := kv.current()
+	store.mu.Lock()
+	defer store.mu.Unlock()
+	value, ok := store.data[key]
+	return value, ok
+}
+
+// Put stores value under key and persists the store
+func (kv *HostKV) Put(key string, value []byte) error {
+	if err := checkHostKVKey(key); err != nil {
+		return err
+	}
+	if len(value) > MaxHostKVValue {
+		return fmt.Errorf("EFBIG: value larger than %d bytes", MaxHostKVValue)
+	}
+	store := kv.current()
+	store.mu.Lock()
+	defer store.mu.Unlock()
+	old, existed := store.data[key]
+	store.data[key] = append([]byte{}, value...)
+	if err := store.save(); err != nil {
+		if existed {
+			store.data[key] = old
+		} else {
+			delete(store.data, key)
+		}
+		return err
+	}
+	return nil
+}
+
+// Delete removes key; removing a missing key is not an error
+func (kv *HostKV) Delete(key string) error {
+	store := kv.current()
+	store.mu.Lock()
+	defer store.mu.Unlock()
+	old, ok := store.data[key]
+	if !ok {
+		return nil
+	}
+	delete(store.data, key)
+	if err := store.save(); err != nil {
+		store.data[key] = old
+		return err
+	}
+	return nil
+}
+
+// Scan returns up to limit entries whose keys start with prefix and sort
+// after `after`, in key order
+func (kv *HostKV) Scan(prefix, after string, limit int) []HostKVEntry {
+	if limit <= 0 || limit > MaxHostKVScan {
+		limit = MaxHostKVScan
+	}
+	store := kv.current()
+	store.mu.Lock()
+	defer store.mu.Unlock()
+
+	keys := []string{}
+	for key := range store.data {
+		if strings.HasPrefix(key, prefix) && key > after {
+			keys = append(keys, key)
+		}
+	}
+	sort.Strings(keys)
+	if len(keys) > limit {
+		keys = keys[:limit]
+	}
+
+	entries := make([]HostKVEntry, 0, len(keys))
+	for _, key := range keys {
+		entries = append(entries, HostKVEntry{Key: key, Value: store.data[key]})
+	}
+	return entries
+}
+
+// save writes the store to its file through a rename, so a crash leaves
+// either the old or the new contents. Caller holds s.mu.
+func (s *kvStore) save() error {
+	if s.path == "" {
+		return nil
+	}
+	raw, err := json.Marshal(s.data)
+	if err != nil {
+		return fmt.Errorf("EIO: %v", err)
+	}
+	if err := os.MkdirAll(filepath.Dir(s.path), 0700); err != nil {
+		return fmt.Errorf("EIO: %v", err)
+	}
+	tmp, err := os.CreateTemp(filepath.Dir(s.path), filepath.Base(s.path)+".*")
+	if err != nil {
+		return fmt.Errorf("EIO: %v", err)
+	}
+	_, err = tmp.Write(raw)
+	if closeErr := tmp.Close(); err == nil {
+		err = closeErr
+	}
+	if err == nil {
+		err = os.Rename(tmp.Name(), s.path)
+	}
+	if err != nil {
+		os.Remove(tmp.Name())
+		return fmt.Errorf("EIO: %v", err)
+	}
+	return nil
+}
+
+func checkHostKVKey(key string) error {
+	if key == "" {
+		return fmt.Errorf("EINVAL: empty key")
+	}
+	if len(key) > MaxHostKVKey {
+		return fmt.Errorf("EINVAL: key longer than %d bytes", MaxHostKVKey)
+	}
+	return nil
+}
+
+// HostKVGet returns (JSON pointer, error pointer); the JSON is the value
+// as a base64 string
+func HostKVGet(ctx context.Context, mod wazeroapi.Module, params []uint64, kv *HostKV) []uint64 {
+	key, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_kv_get: failed to read key from memory")
+		return []uint64{0}
+	}
+
+	value, found := kv.Get(key)
+	if !found {
+		errPtr, _ := writeStringToMemory(mod, fmt.Sprintf("ENOENT: %s", key))
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	jsonData, _ := json.Marshal(value)
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_kv_get: failed to write value to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(jsonPtr)}
+}
+
+// HostKVPut returns an error pointer, 0 on success
+func HostKVPut(ctx context.Context, mod wazeroapi.Module, params []uint64, kv *HostKV) []uint64 {
+	key, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	value, ok := mod.Memory().Read(uint32(params[1]), uint32(params[2]))
+	if !ok {
+		log.Errorf("host_kv_put: failed to read value from memory")
+		return []uint64{1}
+	}
+
+	log.Debugf("host_kv_put: key=%s, len=%d", key, len(value))
+
+	if err := kv.Put(key, value); err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
+
+// HostKVDelete returns an error pointer, 0 on success
+func HostKVDelete(ctx context.Context, mod wazeroapi.Module, params []uint64, kv *HostKV) []uint64 {
+	key, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+
+	log.Debugf("host_kv_delete: key=%s", key)
+
+	if err := kv.Delete(key); err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
+
+// HostKVScan returns (JSON pointer, error pointer); the JSON is a list of
+// HostKVEntry
+func HostKVScan(ctx context.Context, mod wazeroapi.Module, params []uint64, kv *HostKV) []uint64 {
+	prefix, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		prefix = ""
+	}
+	after, ok := readStringFromMemory(mod, uint32(params[1]))
+	if !ok {
+		after = ""
+	}
+	limit := int(uint32(params[2]))
+
+	jsonData, err := json.Marshal(kv.Scan(prefix, after, limit))
+	if err != nil {
+		log.Errorf("host_kv_scan: failed to marshal entries: %v", err)
+		return []uint64{0}
+	}
+	jsonPtr, err := writeStringToMemory(mod, string(jsonData))
+	if err != nil {
+		log.Errorf("host_kv_scan: failed to write entries to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(jsonPtr)}
+}
@@ -0,0 +1,166 @@
+package api
+
+import (
+	"context"
+	"fmt"
+	"sync"
+	"sync/atomic"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Lock kinds taken by host_fs_lock, as flock(2) takes them
+const (
+	LockShared    = 1
+	LockExclusive = 2
+)
+
+// hostLocks is the advisory lock table behind host_fs_lock, shared by
+// every plugin so that plugins over the same host files exclude each
+// other. The host file systems have no lock call, so the locks only bind
+// WASM plugins, not other processes
+var hostLocks = &lockTable{locks: map[string]*pathLock{}}
+
+type lockTable struct {
+	mu    sync.Mutex
+	locks map[string]*pathLock
+}
+
+// pathLock is the holders of one path and the kind each holds
+type pathLock struct {
+	holders map[*HostLocks]uint32
+}
+
+// HostLocks are the locks one plugin holds, released when it shuts down
+type HostLocks struct {
+	mu    sync.Mutex
+	scope string // keys this plugin's scratch paths apart from others'
+	held  map[string]bool
+}
+
+var nextLockScope atomic.Uint64
+
+// NewHostLocks creates a plugin's empty lock set
+func NewHostLocks() *HostLocks {
+	return &HostLocks{
+		scope: fmt.Sprintf("plugin-%d:", nextLockScope.Add(1)),
+		held:  map[string]bool{},
+	}
+}
+
+// key is the table key for path: scratch space is private to the
+// plugin, every other path is shared
+func (l *HostLocks) key(path string) string {
+	path = filesystem.NormalizePath(path)
+	if _, ok := tempPath(path); ok {
+		return l.scope + path
+	}
+	return path
+}
+
+// Lock takes, or converts to, a lock of kind on path without waiting
+// A lock held by another plugin that conflicts fails with a permission
+// error
+func (l *HostLocks) Lock(path string, kind uint32) error {
+	if kind != LockShared && kind != LockExclusive {
+		return filesystem.NewInvalidArgumentError("kind", kind, "expected shared (1) or exclusive (2)")
+	}
+	key := l.key(path)
+
+	hostLocks.mu.Lock()
+	defer hostLocks.mu.Unlock()
+
+	lock, ok := hostLocks.locks[key]
+	if !ok {
+		lock = &pathLock{holders: map[*HostLocks]uint32{}}
+		hostLocks.locks[key] = lock
+	}
+	for holder, held := range lock.holders {
+		if holder != l && (kind == LockExclusive || held == LockExclusive) {
+			return &filesystem.PermissionDeniedError{Path: path, Op: "lock", Reason: "locked"}
+		}
+	}
+	lock.holders[l] = kind
+
+	l.mu.Lock()
+	l.held[key] = true
+	l.mu.Unlock()
+	return nil
+}
+
+// Unlock releases the plugin's lock on path; not holding one is fine
+func (l *HostLocks) Unlock(path string) {
+	key := l.key(path)
+
+	hostLocks.mu.Lock()
+	l.release(key)
+	hostLocks.mu.Unlock()
+
+	l.mu.Lock()
+	delete(l.held, key)
+	l.mu.Unlock()
+}
+
+// release drops the plugin from key's holders; hostLocks.mu must be held
+func (l *HostLocks) release(key string) {
+	lock, ok := hostLocks.locks[key]
+	if !ok {
+		return
+	}
+	delete(lock.holders, l)
+	if len(lock.holders) == 0 {
+		delete(hostLocks.locks, key)
+	}
+}
+
+// Close releases every lock the plugin holds
+func (l *HostLocks) Close() {
+	l.mu.Lock()
+	held := l.held
+	l.held = map[string]bool{}
+	l.mu.Unlock()
+
+	hostLocks.mu.Lock()
+	for key := range held {
+		l.release(key)
+	}
+	hostLocks.mu.Unlock()
+}
+
+// HostFSLock takes an advisory lock on an existing host path
+// Returns an error pointer, 0 on success
+func HostFSLock(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem, locks *HostLocks) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	kind := uint32(params[1])
+
+	log.Debugf("host_fs_lock: path=%s, kind=%d", path, kind)
+
+	// Only the stat may time out: a lock taken after the plugin gave up
+	// would be held without the plugin knowing
+	_, err := runHostCall(ctx, "host_fs_lock", func() (*filesystem.FileInfo, error) {
+		return fs.Stat(path)
+	})
+	if err == nil {
+		err = locks.Lock(path, kind)
+	}
+	return errorReply(mod, "lock", err)
+}
+
+// HostFSUnlock releases a lock taken with host_fs_lock
+// Returns an error pointer, 0 on success
+func HostFSUnlock(ctx context.Context, mod wazeroapi.Module, params []uint64, locks *HostLocks) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+
+	log.Debugf("host_fs_unlock: path=%s", path)
+
+	locks.Unlock(path)
+	return []uint64{0}
+}
@@ -0,0 +1,37 @@
+package api
+
+import (
+	"context"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostLog writes a plugin's log line to the server log
+// Params: level (1 error .. 5 trace, as in Rust's log crate), target, message
+func HostLog(ctx context.Context, mod wazeroapi.Module, params []uint64, plugin string) {
+	level := uint32(params[0])
+	target, _ := readStringFromMemory(mod, uint32(params[1]))
+	msg, ok := readStringFromMemory(mod, uint32(params[2]))
+	if !ok {
+		return
+	}
+
+	entry := log.WithField("plugin", plugin)
+	if target != "" {
+		entry = entry.WithField("target", target)
+	}
+
+	switch level {
+	case 1:
+		entry.Error(msg)
+	case 2:
+		entry.Warn(msg)
+	case 3:
+		entry.Info(msg)
+	case 4:
+		entry.Debug(msg)
+	default:
+		entry.Trace(msg)
+	}
+}
@@ -0,0 +1,255 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"math"
+	"regexp"
+	"sort"
+	"strconv"
+	"strings"
+	"sync"
+
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// MaxPluginSeries caps the series plugins may create, across all plugins,
+// so a plugin putting ids in labels cannot exhaust server memory
+const MaxPluginSeries = 10000
+
+// pluginMetricPrefix is prepended to every plugin metric name
+const pluginMetricPrefix = "agfs_plugin_"
+
+// pluginHistogramBuckets are the upper bounds of plugin histograms,
+// Prometheus' defaults, suited to latencies in seconds
+var pluginHistogramBuckets = []float64{0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10}
+
+var metricNameRe = regexp.MustCompile(`^[a-zA-Z_][a-zA-Z0-9_]*$`)
+
+type pluginHistogram struct {
+	buckets []uint64
+	count   uint64
+	sum     float64
+}
+
+// PluginMetricsRegistry aggregates the counters and histograms plugins
+// report through host_metric_*, for the server's Prometheus endpoint
+type PluginMetricsRegistry struct {
+	mu         sync.Mutex
+	kinds      map[string]string // name -> "counter" or "histogram"
+	counters   map[string]map[string]float64
+	histograms map[string]map[string]*pluginHistogram
+	series     int
+}
+
+// NewPluginMetricsRegistry creates an empty registry
+func NewPluginMetricsRegistry() *PluginMetricsRegistry {
+	return &PluginMetricsRegistry{
+		kinds:      map[string]string{},
+		counters:   map[string]map[string]float64{},
+		histograms: map[string]map[string]*pluginHistogram{},
+	}
+}
+
+// PluginMetrics is the registry all WASM plugins report to
+var PluginMetrics = NewPluginMetricsRegistry()
+
+// renderLabels formats labels in sorted order, as Prometheus expects them
+// between braces
+func renderLabels(labels map[string]string) (string, error) {
+	keys := make([]string, 0, len(labels))
+	for k := range labels {
+		if !metricNameRe.MatchString(k) || strings.HasPrefix(k, "__") {
+			return "", fmt.Errorf("EINVAL: bad label name %q", k)
+		}
+		keys = append(keys, k)
+	}
+	sort.Strings(keys)
+	parts := make([]string, 0, len(keys))
+	for _, k := range keys {
+		parts = append(parts, fmt.Sprintf("%s=%s", k, strconv.Quote(labels[k])))
+	}
+	return strings.Join(parts, ","), nil
+}
+
+// prepare validates a sample and returns its full name and label string
+func (r *PluginMetricsRegistry) prepare(plugin, name, kind string, labels map[string]string) (string, string, error) {
+	if !metricNameRe.MatchString(name) {
+		return "", "", fmt.Errorf("EINVAL: bad metric name %q", name)
+	}
+	full := pluginMetricPrefix + name
+	if labels == nil {
+		labels = map[string]string{}
+	}
+	labels["plugin"] = plugin
+	rendered, err := renderLabels(labels)
+	if err != nil {
+		return "", "", err
+	}
+	if existing, ok := r.kinds[full]; ok && existing != kind {
+		return "", "", fmt.Errorf("EINVAL: %s is a %s", name, existing)
+	}
+	return full, rendered, nil
+}
+
+// AddCounter adds value, which must not be negative, to a counter
+func (r *PluginMetricsRegistry) AddCounter(plugin, name string, value float64, labels map[string]string) error {
+	if value < 0 || math.IsNaN(value) || math.IsInf(value, 0) {
+		return fmt.Errorf("EINVAL: counter increment %v", value)
+	}
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	full, rendered, err := r.prepare(plugin, name, "counter", labels)
+	if err != nil {
+		return err
+	}
+	series := r.counters[full]
+	if series == nil {
+		series = map[string]float64{}
+		r.counters[full] = series
+	}
+	if _, ok := series[rendered]; !ok {
+		if r.series >= MaxPluginSeries {
+			return fmt.Errorf("EINVAL: more than %d plugin metric series", MaxPluginSeries)
+		}
+		r.series++
+	}
+	r.kinds[full] = "counter"
+	series[rendered] += value
+	return nil
+}
+
+// Observe records one value in a histogram
+func (r *PluginMetricsRegistry) Observe(plugin, name string, value float64, labels map[string]string) error {
+	if math.IsNaN(value) || math.IsInf(value, 0) {
+		return fmt.Errorf("EINVAL: histogram value %v", value)
+	}
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	full, rendered, err := r.prepare(plugin, name, "histogram", labels)
+	if err != nil {
+		return err
+	}
+	series := r.histograms[full]
+	if series == nil {
+		series = map[string]*pluginHistogram{}
+		r.histograms[full] = series
+	}
+	h, ok := series[rendered]
+	if !ok {
+		if r.series >= MaxPluginSeries {
+			return fmt.Errorf("EINVAL: more than %d plugin metric series", MaxPluginSeries)
+		}
+		r.series++
+		h = &pluginHistogram{buckets: make([]uint64, len(pluginHistogramBuckets))}
+		series[rendered] = h
+	}
+	r.kinds[full] = "histogram"
+	for i, bound := range pluginHistogramBuckets {
+		if value <= bound {
+			h.buckets[i]++
+		}
+	}
+	h.count++
+	h.sum += value
+	return nil
+}
+
+func withLabel(labels, extra string) string {
+	if labels == "" {
+		return "{" + extra + "}"
+	}
+	return "{" + labels + "," + extra + "}"
+}
+
+func formatFloat(v float64) string {
+	return strconv.FormatFloat(v, 'g', -1, 64)
+}
+
+// WriteText writes every plugin metric in the Prometheus text format
+func (r *PluginMetricsRegistry) WriteText(w io.Writer) error {
+	r.mu.Lock()
+	defer r.mu.Unlock()
+
+	names := make([]string, 0, len(r.kinds))
+	for name := range r.kinds {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+
+	var b strings.Builder
+	for _, name := range names {
+		kind := r.kinds[name]
+		fmt.Fprintf(&b, "# TYPE %s %s\n", name, kind)
+		if kind == "counter" {
+			series := r.counters[name]
+			for _, labels := range sortedKeys(series) {
+				fmt.Fprintf(&b, "%s{%s} %s\n", name, labels, formatFloat(series[labels]))
+			}
+			continue
+		}
+		series := r.histograms[name]
+		for _, labels := range sortedKeys(series) {
+			h := series[labels]
+			for i, bound := range pluginHistogramBuckets {
+				fmt.Fprintf(&b, "%s_bucket%s %d\n", name, withLabel(labels, `le="`+formatFloat(bound)+`"`), h.buckets[i])
+			}
+			fmt.Fprintf(&b, "%s_bucket%s %d\n", name, withLabel(labels, `le="+Inf"`), h.count)
+			fmt.Fprintf(&b, "%s_sum{%s} %s\n", name, labels, formatFloat(h.sum))
+			fmt.Fprintf(&b, "%s_count{%s} %d\n", name, labels, h.count)
+		}
+	}
+	_, err := io.WriteString(w, b.String())
+	return err
+}
+
+func sortedKeys[V any](m map[string]V) []string {
+	keys := make([]string, 0, len(m))
+	for k := range m {
+		keys = append(keys, k)
+	}
+	sort.Strings(keys)
+	return keys
+}
+
+// hostMetric decodes a host_metric_* call and applies record to it
+// Returns an error pointer, 0 on success
+func hostMetric(mod wazeroapi.Module, params []uint64, record func(name string, value float64, labels map[string]string) error) []uint64 {
+	name, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		return []uint64{1}
+	}
+	value := math.Float64frombits(params[1])
+
+	var labels map[string]string
+	if labelsJSON, ok := readStringFromMemory(mod, uint32(params[2])); ok && labelsJSON != "" {
+		if err := json.Unmarshal([]byte(labelsJSON), &labels); err != nil {
+			errPtr, _ := writeStringToMemory(mod, fmt.Sprintf("EINVAL: bad labels: %v", err))
+			return []uint64{uint64(errPtr)}
+		}
+	}
+
+	if err := record(name, value, labels); err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
+
+// HostMetricCounter adds to a counter for plugin
+// Params: name, value (float64 bits), labels JSON
+func HostMetricCounter(ctx context.Context, mod wazeroapi.Module, params []uint64, plugin string) []uint64 {
+	return hostMetric(mod, params, func(name string, value float64, labels map[string]string) error {
+		return PluginMetrics.AddCounter(plugin, name, value, labels)
+	})
+}
+
+// HostMetricHistogram records a histogram value for plugin
+// Params: name, value (float64 bits), labels JSON
+func HostMetricHistogram(ctx context.Context, mod wazeroapi.Module, params []uint64, plugin string) []uint64 {
+	return hostMetric(mod, params, func(name string, value float64, labels map[string]string) error {
+		return PluginMetrics.Observe(plugin, name, value, labels)
+	})
+}
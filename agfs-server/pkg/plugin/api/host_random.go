@@ -0,0 +1,37 @@
+package api
+
+import (
+	"context"
+	"crypto/rand"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// MaxHostRandomBytes caps one host_random_bytes call; the guest loops for more
+const MaxHostRandomBytes = 64 * 1024
+
+// HostRandomBytes fills a new buffer in plugin memory from crypto/rand
+// Returns (pointer, length) packed, or 0 on error
+func HostRandomBytes(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	n := uint32(params[0])
+	if n == 0 || n > MaxHostRandomBytes {
+		log.Errorf("host_random_bytes: length %d outside 1..%d", n, MaxHostRandomBytes)
+		return []uint64{0}
+	}
+
+	buf := make([]byte, n)
+	if _, err := rand.Read(buf); err != nil {
+		log.Errorf("host_random_bytes: %v", err)
+		return []uint64{0}
+	}
+
+	ptr, err := writeBytesToMemory(mod, buf)
+	if err != nil {
+		log.Errorf("host_random_bytes: failed to write to memory: %v", err)
+		return []uint64{0}
+	}
+
+	// Lower 32 bits = pointer, upper 32 bits = length
+	return []uint64{uint64(ptr) | (uint64(n) << 32)}
+}
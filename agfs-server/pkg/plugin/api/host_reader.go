@@ -0,0 +1,166 @@
+package api
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"io"
+	"sync"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Limits on host_fs_open_reader and host_fs_read_chunk
+const (
+	MaxHostReaders     = 64
+	MaxHostReaderChunk = 1 << 20
+)
+
+// HostReaders holds the host files a plugin streams with
+// host_fs_read_chunk, so a file never has to fit in guest memory at once
+type HostReaders struct {
+	mu      sync.Mutex
+	readers map[uint32]io.ReadCloser
+	next    uint32
+}
+
+// NewHostReaders creates an empty reader table
+func NewHostReaders() *HostReaders {
+	return &HostReaders{readers: map[uint32]io.ReadCloser{}}
+}
+
+// Open opens path on fs for reading and returns its handle
+func (r *HostReaders) Open(ctx context.Context, fs filesystem.FileSystem, path string) (uint32, error) {
+	r.mu.Lock()
+	full := len(r.readers) >= MaxHostReaders
+	r.mu.Unlock()
+	if full {
+		return 0, fmt.Errorf("EIO: more than %d open readers", MaxHostReaders)
+	}
+
+	reader, err := runHostCall(ctx, "host_fs_open_reader", func() (io.ReadCloser, error) {
+		reader, err := fs.Open(path)
+		// Nobody is left to close a file opened after the call gave up
+		if err == nil && ctx.Err() != nil {
+			reader.Close()
+			return nil, ctx.Err()
+		}
+		return reader, err
+	})
+	if err != nil {
+		return 0, err
+	}
+
+	r.mu.Lock()
+	defer r.mu.Unlock()
+	r.next++
+	if r.next == 0 {
+		r.next = 1
+	}
+	r.readers[r.next] = reader
+	return r.next, nil
+}
+
+// Read returns up to limit bytes; nil data and no error means the end of
+// the file. A read that times out closes the reader, since it may still
+// be running
+func (r *HostReaders) Read(ctx context.Context, handle uint32, limit int) ([]byte, error) {
+	r.mu.Lock()
+	reader, ok := r.readers[handle]
+	r.mu.Unlock()
+	if !ok {
+		return nil, fmt.Errorf("EINVAL: unknown reader %d", handle)
+	}
+	if limit <= 0 || limit > MaxHostReaderChunk {
+		limit = MaxHostReaderChunk
+	}
+
+	data, err := runHostCall(ctx, "host_fs_read_chunk", func() ([]byte, error) {
+		buf := make([]byte, limit)
+		n, err := io.ReadAtLeast(reader, buf, 1)
+		return buf[:n], err
+	})
+	if err != nil && ctx.Err() != nil {
+		r.CloseReader(handle)
+		return nil, err
+	}
+	if len(data) > 0 {
+		return data, nil
+	}
+	if err != nil && !errors.Is(err, io.EOF) {
+		return nil, err
+	}
+	return nil, nil
+}
+
+// CloseReader closes one reader
+func (r *HostReaders) CloseReader(handle uint32) error {
+	r.mu.Lock()
+	reader, ok := r.readers[handle]
+	delete(r.readers, handle)
+	r.mu.Unlock()
+	if !ok {
+		return fmt.Errorf("EINVAL: unknown reader %d", handle)
+	}
+	return reader.Close()
+}
+
+// Close closes every reader the plugin left open
+func (r *HostReaders) Close() error {
+	r.mu.Lock()
+	readers := r.readers
+	r.readers = map[uint32]io.ReadCloser{}
+	r.mu.Unlock()
+	for _, reader := range readers {
+		reader.Close()
+	}
+	return nil
+}
+
+// HostFSOpenReader returns (handle, error pointer)
+func HostFSOpenReader(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem, readers *HostReaders) []uint64 {
+	path, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_fs_open_reader: failed to read path from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_fs_open_reader: path=%s", path)
+
+	handle, err := readers.Open(ctx, fs, path)
+	if err != nil {
+		return errorReplyHigh(mod, "open_reader", err)
+	}
+	return []uint64{uint64(handle)}
+}
+
+// HostFSReadChunk returns (pointer, length), (0, 0) at the end of the
+// file, or (0, error pointer)
+func HostFSReadChunk(ctx context.Context, mod wazeroapi.Module, params []uint64, readers *HostReaders) []uint64 {
+	data, err := readers.Read(ctx, uint32(params[0]), int(uint32(params[1])))
+	if err != nil {
+		return errorReplyHigh(mod, "read_chunk", err)
+	}
+	if data == nil {
+		return []uint64{0}
+	}
+
+	dataPtr, err := writeBytesToMemory(mod, data)
+	if err != nil {
+		log.Errorf("host_fs_read_chunk: failed to write data to memory: %v", err)
+		errPtr, _ := writeStringToMemory(mod, "EIO: out of plugin memory")
+		return []uint64{uint64(errPtr) << 32}
+	}
+	return []uint64{uint64(dataPtr) | (uint64(len(data)) << 32)}
+}
+
+// HostFSCloseReader returns an error pointer, 0 on success
+func HostFSCloseReader(ctx context.Context, mod wazeroapi.Module, params []uint64, readers *HostReaders) []uint64 {
+	if err := readers.CloseReader(uint32(params[0])); err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
@@ -0,0 +1,420 @@
+package api
+
+import (
+	"fmt"
+	"io"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+// HostAllowedRootsKey is the mount config key listing the host path
+// prefixes a WASM plugin may reach through the host_fs_* imports
+const HostAllowedRootsKey = "host_allowed_roots"
+
+// HostSandbox limits the host paths a WASM plugin can reach
+// Until roots are configured every path is allowed, as before sandboxing
+type HostSandbox struct {
+	mu    sync.RWMutex
+	roots []string // nil: unrestricted
+}
+
+// NewHostSandbox creates an unrestricted sandbox
+func NewHostSandbox() *HostSandbox {
+	return &HostSandbox{}
+}
+
+// parseStringList reads a list of strings from a mount config
+// Accepts a list of strings or a comma-separated string; blank entries are
+// dropped
+func parseStringList(config map[string]interface{}, key string) ([]string, bool, error) {
+	value, ok := config[key]
+	if !ok {
+		return nil, false, nil
+	}
+
+	var raw []string
+	switch v := value.(type) {
+	case string:
+		raw = strings.Split(v, ",")
+	case []string:
+		raw = v
+	case []interface{}:
+		for _, item := range v {
+			s, ok := item.(string)
+			if !ok {
+				return nil, false, fmt.Errorf("%s: entries must be strings", key)
+			}
+			raw = append(raw, s)
+		}
+	default:
+		return nil, false, fmt.Errorf("%s: expected a list", key)
+	}
+
+	list := []string{}
+	for _, item := range raw {
+		if item = strings.TrimSpace(item); item != "" {
+			list = append(list, item)
+		}
+	}
+	return list, true, nil
+}
+
+// parseHostRoots reads host_allowed_roots from a mount config
+func parseHostRoots(config map[string]interface{}) ([]string, bool, error) {
+	raw, ok, err := parseStringList(config, HostAllowedRootsKey)
+	if err != nil || !ok {
+		return nil, ok, err
+	}
+	roots := make([]string, 0, len(raw))
+	for _, root := range raw {
+		roots = append(roots, filesystem.NormalizePath(root))
+	}
+	return roots, true, nil
+}
+
+// Configure applies host_allowed_roots from the mount config, if present
+// An empty list denies every host path
+func (s *HostSandbox) Configure(config map[string]interface{}) error {
+	roots, ok, err := parseHostRoots(config)
+	if err != nil || !ok {
+		return err
+	}
+	s.mu.Lock()
+	s.roots = roots
+	s.mu.Unlock()
+	return nil
+}
+
+// Roots returns the allowed prefixes, or nil if the sandbox is unrestricted
+func (s *HostSandbox) Roots() []string {
+	s.mu.RLock()
+	defer s.mu.RUnlock()
+	if s.roots == nil {
+		return nil
+	}
+	return append([]string{}, s.roots...)
+}
+
+// Allows reports whether path lies under one of the allowed roots
+func (s *HostSandbox) Allows(path string) bool {
+	s.mu.RLock()
+	defer s.mu.RUnlock()
+	if s.roots == nil {
+		return true
+	}
+	path = filesystem.NormalizePath(path)
+	for _, root := range s.roots {
+		if root == "/" || path == root || strings.HasPrefix(path, root+"/") {
+			return true
+		}
+	}
+	return false
+}
+
+func (s *HostSandbox) check(op, path string) error {
+	if s.Allows(path) {
+		return nil
+	}
+	return &filesystem.PermissionDeniedError{Path: path, Op: op, Reason: "outside host sandbox"}
+}
+
+// sandboxedFS checks every path against a HostSandbox before passing the
+// call to the host filesystem
+type sandboxedFS struct {
+	fs      filesystem.FileSystem
+	sandbox *HostSandbox
+}
+
+// NewSandboxedFS wraps fs so that calls outside sandbox fail with a
+// permission error
+func NewSandboxedFS(fs filesystem.FileSystem, sandbox *HostSandbox) filesystem.FileSystem {
+	return &sandboxedFS{fs: fs, sandbox: sandbox}
+}
+
+func (s *sandboxedFS) Create(path string) error {
+	if err := s.sandbox.check("create", path); err != nil {
+		return err
+	}
+	return s.fs.Create(path)
+}
+
+func (s *sandboxedFS) Mkdir(path string, perm uint32) error {
+	if err := s.sandbox.check("mkdir", path); err != nil {
+		return err
+	}
+	return s.fs.Mkdir(path, perm)
+}
+
+func (s *sandboxedFS) Remove(path string) error {
+	if err := s.sandbox.check("remove", path); err != nil {
+		return err
+	}
+	return s.fs.Remove(path)
+}
+
+func (s *sandboxedFS) RemoveAll(path string) error {
+	if err := s.sandbox.check("remove_all", path); err != nil {
+		return err
+	}
+	return s.fs.RemoveAll(path)
+}
+
+func (s *sandboxedFS) Read(path string, offset int64, size int64) ([]byte, error) {
+	if err := s.sandbox.check("read", path); err != nil {
+		return nil, err
+	}
+	return s.fs.Read(path, offset, size)
+}
+
+func (s *sandboxedFS) Write(path string, data []byte) ([]byte, error) {
+	if err := s.sandbox.check("write", path); err != nil {
+		return nil, err
+	}
+	return s.fs.Write(path, data)
+}
+
+func (s *sandboxedFS) ReadDir(path string) ([]filesystem.FileInfo, error) {
+	if err := s.sandbox.check("readdir", path); err != nil {
+		return nil, err
+	}
+	return s.fs.ReadDir(path)
+}
+
+func (s *sandboxedFS) Stat(path string) (*filesystem.FileInfo, error) {
+	if err := s.sandbox.check("stat", path); err != nil {
+		return nil, err
+	}
+	return s.fs.Stat(path)
+}
+
+func (s *sandboxedFS) Rename(oldPath, newPath string) error {
+	if err := s.sandbox.check("rename", oldPath); err != nil {
+		return err
+	}
+	if err := s.sandbox.check("rename", newPath); err != nil {
+		return err
+	}
+	return s.fs.Rename(oldPath, newPath)
+}
+
+func (s *sandboxedFS) Chmod(path string, mode uint32) error {
+	if err := s.sandbox.check("chmod", path); err != nil {
+		return err
+	}
+	return s.fs.Chmod(path, mode)
+}
+
+func (s *sandboxedFS) Open(path string) (io.ReadCloser, error) {
+	if err := s.sandbox.check("open", path); err != nil {
+		return nil, err
+	}
+	return s.fs.Open(path)
+}
+
+func (s *sandboxedFS) OpenWrite(path string) (io.WriteCloser, error) {
+	if err := s.sandbox.check("open", path); err != nil {
+		return nil, err
+	}
+	return s.fs.OpenWrite(path)
+}
+
+// Lstat implements filesystem.Lstater interface
+func (s *sandboxedFS) Lstat(path string) (*filesystem.FileInfo, error) {
+	if err := s.sandbox.check("lstat", path); err != nil {
+		return nil, err
+	}
+	lstater, ok := s.fs.(filesystem.Lstater)
+	if !ok {
+		return nil, filesystem.NewNotSupportedError("lstat", path)
+	}
+	return lstater.Lstat(path)
+}
+
+// RealPath implements filesystem.RealPather interface
+func (s *sandboxedFS) RealPath(path string) (string, error) {
+	if err := s.sandbox.check("realpath", path); err != nil {
+		return "", err
+	}
+	realPather, ok := s.fs.(filesystem.RealPather)
+	if !ok {
+		return "", filesystem.NewNotSupportedError("realpath", path)
+	}
+	return realPather.RealPath(path)
+}
+
+// CreateExclusive implements filesystem.ExclusiveCreator interface
+func (s *sandboxedFS) CreateExclusive(path string) error {
+	if err := s.sandbox.check("create", path); err != nil {
+		return err
+	}
+	creator, ok := s.fs.(filesystem.ExclusiveCreator)
+	if !ok {
+		return filesystem.NewNotSupportedError("create_exclusive", path)
+	}
+	return creator.CreateExclusive(path)
+}
+
+// Symlink implements filesystem.Symlinker interface
+func (s *sandboxedFS) Symlink(target, linkPath string) error {
+	if err := s.sandbox.check("symlink", linkPath); err != nil {
+		return err
+	}
+	symlinker, ok := s.fs.(filesystem.Symlinker)
+	if !ok {
+		return filesystem.NewNotSupportedError("symlink", linkPath)
+	}
+	return symlinker.Symlink(target, linkPath)
+}
+
+// Readlink implements filesystem.Symlinker interface
+func (s *sandboxedFS) Readlink(path string) (string, error) {
+	if err := s.sandbox.check("readlink", path); err != nil {
+		return "", err
+	}
+	symlinker, ok := s.fs.(filesystem.Symlinker)
+	if !ok {
+		return "", filesystem.NewNotSupportedError("readlink", path)
+	}
+	return symlinker.Readlink(path)
+}
+
+// Truncate implements filesystem.Truncater interface
+func (s *sandboxedFS) Truncate(path string, size int64) error {
+	if err := s.sandbox.check("truncate", path); err != nil {
+		return err
+	}
+	truncater, ok := s.fs.(filesystem.Truncater)
+	if !ok {
+		return filesystem.NewNotSupportedError("truncate", path)
+	}
+	return truncater.Truncate(path, size)
+}
+
+// SetTimes implements filesystem.TimeSetter interface
+func (s *sandboxedFS) SetTimes(path string, atime, mtime time.Time) error {
+	if err := s.sandbox.check("set_times", path); err != nil {
+		return err
+	}
+	setter, ok := s.fs.(filesystem.TimeSetter)
+	if !ok {
+		return filesystem.NewNotSupportedError("set_times", path)
+	}
+	return setter.SetTimes(path, atime, mtime)
+}
+
+// Chown implements filesystem.Chowner interface
+func (s *sandboxedFS) Chown(path string, uid, gid uint32) error {
+	if err := s.sandbox.check("chown", path); err != nil {
+		return err
+	}
+	chowner, ok := s.fs.(filesystem.Chowner)
+	if !ok {
+		return filesystem.NewNotSupportedError("chown", path)
+	}
+	return chowner.Chown(path, uid, gid)
+}
+
+// Link implements filesystem.Linker interface
+func (s *sandboxedFS) Link(existing, newPath string) error {
+	if err := s.sandbox.check("link", existing); err != nil {
+		return err
+	}
+	if err := s.sandbox.check("link", newPath); err != nil {
+		return err
+	}
+	linker, ok := s.fs.(filesystem.Linker)
+	if !ok {
+		return filesystem.NewNotSupportedError("link", newPath)
+	}
+	return linker.Link(existing, newPath)
+}
+
+// CreateWithFlags implements filesystem.FlagCreator interface
+func (s *sandboxedFS) CreateWithFlags(path string, flags, mode uint32) error {
+	if err := s.sandbox.check("create", path); err != nil {
+		return err
+	}
+	creator, ok := s.fs.(filesystem.FlagCreator)
+	if !ok {
+		return filesystem.NewNotSupportedError("create_flags", path)
+	}
+	return creator.CreateWithFlags(path, flags, mode)
+}
+
+// SeekData implements filesystem.DataSeeker interface
+func (s *sandboxedFS) SeekData(path string, offset int64, whence int) (int64, bool, error) {
+	if err := s.sandbox.check("seek", path); err != nil {
+		return 0, false, err
+	}
+	seeker, ok := s.fs.(filesystem.DataSeeker)
+	if !ok {
+		return 0, false, filesystem.NewNotSupportedError("seek", path)
+	}
+	return seeker.SeekData(path, offset, whence)
+}
+
+// Allocate implements filesystem.Allocator interface
+func (s *sandboxedFS) Allocate(path string, offset, length int64) error {
+	if err := s.sandbox.check("allocate", path); err != nil {
+		return err
+	}
+	allocator, ok := s.fs.(filesystem.Allocator)
+	if !ok {
+		return filesystem.NewNotSupportedError("allocate", path)
+	}
+	return allocator.Allocate(path, offset, length)
+}
+
+// Access implements filesystem.Accesser interface
+func (s *sandboxedFS) Access(path string, mode uint32) error {
+	if err := s.sandbox.check("access", path); err != nil {
+		return err
+	}
+	accesser, ok := s.fs.(filesystem.Accesser)
+	if !ok {
+		return filesystem.NewNotSupportedError("access", path)
+	}
+	return accesser.Access(path, mode)
+}
+
+// Append implements filesystem.Appender interface
+func (s *sandboxedFS) Append(path string, data []byte) error {
+	if err := s.sandbox.check("append", path); err != nil {
+		return err
+	}
+	appender, ok := s.fs.(filesystem.Appender)
+	if !ok {
+		return filesystem.NewNotSupportedError("append", path)
+	}
+	return appender.Append(path, data)
+}
+
+// RenameWithFlags implements filesystem.FlagRenamer interface
+func (s *sandboxedFS) RenameWithFlags(oldPath, newPath string, flags uint32) error {
+	if err := s.sandbox.check("rename", oldPath); err != nil {
+		return err
+	}
+	if err := s.sandbox.check("rename", newPath); err != nil {
+		return err
+	}
+	renamer, ok := s.fs.(filesystem.FlagRenamer)
+	if !ok {
+		return filesystem.NewNotSupportedError("rename", newPath)
+	}
+	return renamer.RenameWithFlags(oldPath, newPath, flags)
+}
+
+// WriteAt implements filesystem.WriterAt interface
+func (s *sandboxedFS) WriteAt(path string, offset int64, data []byte) (int64, error) {
+	if err := s.sandbox.check("write_at", path); err != nil {
+		return 0, err
+	}
+	writer, ok := s.fs.(filesystem.WriterAt)
+	if !ok {
+		return 0, filesystem.NewNotSupportedError("write_at", path)
+	}
+	return writer.WriteAt(path, offset, data)
+}
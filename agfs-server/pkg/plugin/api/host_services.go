@@ -0,0 +1,109 @@
+package api
+
+// HostServices holds the per-plugin state behind the host imports
+// The loader creates one per WASM module and the plugin configures it
+// from the mount config on Initialize
+type HostServices struct {
+	Sandbox *HostSandbox
+	KV      *HostKV
+	Env     *HostEnv
+	Temp    *HostTemp
+	Exec    *HostExec
+	TCP     *HostTCP
+	HTTP    *HostHTTP
+	DNS     *HostDNS
+	Timeout *HostTimeout
+	Cancel  *HostCancel
+	State   *HostState
+	Locks   *HostLocks
+	Readers *HostReaders
+}
+
+// NewHostServices creates services with an unrestricted sandbox, an
+// in-memory key-value store, no scratch space yet or locks held, and no
+// environment variables, programs, network destinations or names
+// granted, and no deadline on host calls
+func NewHostServices() *HostServices {
+	return &HostServices{
+		Sandbox: NewHostSandbox(),
+		KV:      NewHostKV(),
+		Env:     NewHostEnv(),
+		Temp:    NewHostTemp(),
+		Exec:    NewHostExec(),
+		TCP:     NewHostTCP(),
+		HTTP:    NewHostHTTP(),
+		DNS:     NewHostDNS(),
+		Timeout: NewHostTimeout(),
+		Cancel:  NewHostCancel(),
+		State:   NewHostState(),
+		Locks:   NewHostLocks(),
+		Readers: NewHostReaders(),
+	}
+}
+
+// Validate checks the mount config keys read by Configure
+func (h *HostServices) Validate(config map[string]interface{}) error {
+	if _, _, err := parseHostRoots(config); err != nil {
+		return err
+	}
+	if _, _, err := parseHostTimeout(config); err != nil {
+		return err
+	}
+	if _, err := parseHostStatePath(config); err != nil {
+		return err
+	}
+	if _, err := parseHostKVPath(config); err != nil {
+		return err
+	}
+	for _, key := range []string{HostEnvAllowKey, HostExecAllowKey, HostDNSAllowKey} {
+		if _, _, err := parseStringList(config, key); err != nil {
+			return err
+		}
+	}
+	if err := NewHostHTTP().Configure(config); err != nil {
+		return err
+	}
+	return NewHostTCP().Configure(config)
+}
+
+// Configure applies the mount config
+func (h *HostServices) Configure(config map[string]interface{}) error {
+	if err := h.Sandbox.Configure(config); err != nil {
+		return err
+	}
+	if err := h.Env.Configure(config); err != nil {
+		return err
+	}
+	if err := h.Exec.Configure(config); err != nil {
+		return err
+	}
+	if err := h.TCP.Configure(config); err != nil {
+		return err
+	}
+	if err := h.HTTP.Configure(config); err != nil {
+		return err
+	}
+	if err := h.DNS.Configure(config); err != nil {
+		return err
+	}
+	if err := h.Timeout.Configure(config); err != nil {
+		return err
+	}
+	if err := h.State.Configure(config); err != nil {
+		return err
+	}
+	return h.KV.Configure(config)
+}
+
+// Close releases what the plugin acquired on the host, such as its
+// scratch space, open files, connections, locks and key-value file, and
+// cancels calls still in flight; called when the plugin shuts down
+func (h *HostServices) Close() error {
+	h.Cancel.Close()
+	h.Locks.Close()
+	h.Readers.Close()
+	h.KV.Close()
+	h.TCP.Close()
+	h.HTTP.Close()
+	return h.Temp.Close()
+}
@@ -0,0 +1,201 @@
+package api
+
+import (
+	"context"
+	"fmt"
+	"os"
+	"path/filepath"
+	"sync"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostStatePathKey is the mount config key naming the file that keeps a
+// plugin's state across restarts. Without it the state lives in memory
+// and is lost when the server stops
+const HostStatePathKey = "host_state_path"
+
+// MaxHostState caps the state blob a plugin may store
+const MaxHostState = 64 << 20
+
+// HostState is the state blob behind host_state_save and host_state_load,
+// and where the plugin_snapshot taken at shutdown is kept for
+// plugin_restore. One blob per mount; every save replaces it whole
+type HostState struct {
+	mu   sync.Mutex
+	path string // "": memory only
+	data []byte // nil: nothing saved
+}
+
+// NewHostState creates an empty in-memory state
+func NewHostState() *HostState {
+	return &HostState{}
+}
+
+// parseHostStatePath reads host_state_path from a mount config
+func parseHostStatePath(config map[string]interface{}) (string, error) {
+	value, ok := config[HostStatePathKey]
+	if !ok {
+		return "", nil
+	}
+	path, ok := value.(string)
+	if !ok || path == "" {
+		return "", fmt.Errorf("%s: expected a file path", HostStatePathKey)
+	}
+	return path, nil
+}
+
+// Configure opens the state file named by host_state_path, if present,
+// and loads what was saved in it
+func (s *HostState) Configure(config map[string]interface{}) error {
+	path, err := parseHostStatePath(config)
+	if err != nil || path == "" {
+		return err
+	}
+	data, err := os.ReadFile(path)
+	if err != nil {
+		if !os.IsNotExist(err) {
+			return fmt.Errorf("%s: %w", HostStatePathKey, err)
+		}
+		data = nil
+	}
+
+	s.mu.Lock()
+	s.path = path
+	s.data = data
+	s.mu.Unlock()
+	return nil
+}
+
+// Load returns the saved state, or false if nothing was saved
+func (s *HostState) Load() ([]byte, bool) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	return s.data, s.data != nil
+}
+
+// Save replaces the state with data and persists it
+// The file is replaced by rename, so a crash leaves the old or the new
+// state, never a mix
+func (s *HostState) Save(data []byte) error {
+	if len(data) > MaxHostState {
+		return fmt.Errorf("EFBIG: state larger than %d bytes", MaxHostState)
+	}
+	s.mu.Lock()
+	defer s.mu.Unlock()
+
+	if s.path != "" {
+		tmp, err := os.CreateTemp(filepath.Dir(s.path), filepath.Base(s.path)+".*")
+		if err != nil {
+			return fmt.Errorf("EIO: %v", err)
+		}
+		_, err = tmp.Write(data)
+		if closeErr := tmp.Close(); err == nil {
+			err = closeErr
+		}
+		if err == nil {
+			err = os.Rename(tmp.Name(), s.path)
+		}
+		if err != nil {
+			os.Remove(tmp.Name())
+			return fmt.Errorf("EIO: %v", err)
+		}
+	}
+	s.data = append([]byte{}, data...)
+	return nil
+}
+
+// HostStateSave replaces the mount's stored state
+// Returns an error pointer, 0 on success
+func HostStateSave(ctx context.Context, mod wazeroapi.Module, params []uint64, state *HostState) []uint64 {
+	data, ok := mod.Memory().Read(uint32(params[0]), uint32(params[1]))
+	if !ok {
+		log.Errorf("host_state_save: failed to read data from memory")
+		return []uint64{1}
+	}
+	if err := state.Save(data); err != nil {
+		log.Errorf("host_state_save: %v", err)
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
+
+// HostStateLoad returns the mount's stored state
+// Returns (pointer, length), (0, 0) when nothing was saved or
+// (0, error pointer)
+func HostStateLoad(ctx context.Context, mod wazeroapi.Module, state *HostState) []uint64 {
+	data, ok := state.Load()
+	if !ok || len(data) == 0 {
+		return []uint64{0}
+	}
+	ptr, err := writeBytesToMemory(mod, data)
+	if err != nil {
+		log.Errorf("host_state_load: failed to write state to memory: %v", err)
+		errPtr, _ := writeStringToMemory(mod, "EIO: "+err.Error())
+		return []uint64{uint64(errPtr) << 32}
+	}
+	return []uint64{uint64(ptr) | uint64(len(data))<<32}
+}
+
+// restoreState hands a saved state to the plugin through plugin_restore
+// Plugins that do not export it, and mounts with nothing saved, are left
+// as initialized
+func (wp *WASMPlugin) restoreState() error {
+	restoreFunc := wp.module.ExportedFunction("plugin_restore")
+	data, ok := wp.host.State.Load()
+	if restoreFunc == nil || !ok || len(data) == 0 {
+		return nil
+	}
+
+	dataPtr, err := writeBytesToMemory(wp.module, data)
+	if err != nil {
+		return fmt.Errorf("failed to write state to memory: %w", err)
+	}
+	results, err := restoreFunc.Call(wp.ctx, uint64(dataPtr), uint64(len(data)))
+	if err != nil {
+		return fmt.Errorf("restore call failed: %w", err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(wp.module, uint32(results[0])); ok {
+			return fmt.Errorf("restore failed: %s", errMsg)
+		}
+		return fmt.Errorf("restore failed")
+	}
+	return nil
+}
+
+// saveState keeps the plugin's plugin_snapshot for the next restoreState
+func (wp *WASMPlugin) saveState() error {
+	snapshotFunc := wp.module.ExportedFunction("plugin_snapshot")
+	if snapshotFunc == nil {
+		return nil
+	}
+
+	results, err := snapshotFunc.Call(wp.ctx)
+	if err != nil {
+		return fmt.Errorf("snapshot call failed: %w", err)
+	}
+	if len(results) == 0 {
+		return fmt.Errorf("snapshot returned no results")
+	}
+	dataPtr := uint32(results[0] & 0xFFFFFFFF)
+	dataSize := uint32(results[0] >> 32)
+	if dataPtr == 0 && dataSize != 0 {
+		if errMsg, ok := readStringFromMemory(wp.module, dataSize); ok {
+			return fmt.Errorf("snapshot failed: %s", errMsg)
+		}
+		return fmt.Errorf("snapshot failed")
+	}
+	// Plugins without state snapshot nothing; what they stored through
+	// host_state_save is kept
+	if dataSize == 0 {
+		return nil
+	}
+	data, ok := wp.module.Memory().Read(dataPtr, dataSize)
+	if !ok {
+		return fmt.Errorf("failed to read snapshot from memory")
+	}
+	return wp.host.State.Save(data)
+}
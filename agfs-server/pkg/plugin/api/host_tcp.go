@@ -0,0 +1,272 @@
+package api
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"os"
+	"strings"
+	"sync"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostTCPAllowKey is the mount config key listing the destinations a
+// WASM plugin may connect to, as "host:port". The host may be "*" or
+// start with "*." to match subdomains, the port may be "*". Without it
+// host_tcp_connect is disabled.
+const HostTCPAllowKey = "host_tcp_allow"
+
+// Limits on host_tcp_* calls
+const (
+	MaxHostTCPConns   = 64
+	MaxHostTCPChunk   = 1 << 20
+	MaxHostTCPTimeout = 10 * time.Minute
+)
+
+// HostTCP holds a plugin's TCP connections and the destinations it may
+// open them to
+type HostTCP struct {
+	mu    sync.Mutex
+	allow []string
+	conns map[uint32]net.Conn
+	next  uint32
+}
+
+// NewHostTCP creates a connection table that admits no destination
+func NewHostTCP() *HostTCP {
+	return &HostTCP{conns: map[uint32]net.Conn{}}
+}
+
+// Configure applies host_tcp_allow from the mount config, if present
+func (t *HostTCP) Configure(config map[string]interface{}) error {
+	allow, ok, err := parseStringList(config, HostTCPAllowKey)
+	if err != nil || !ok {
+		return err
+	}
+	for _, pattern := range allow {
+		if _, _, err := net.SplitHostPort(pattern); err != nil {
+			return fmt.Errorf("%s: %q: %v", HostTCPAllowKey, pattern, err)
+		}
+	}
+	t.mu.Lock()
+	t.allow = allow
+	t.mu.Unlock()
+	return nil
+}
+
+// matchHost matches a host against a pattern: exact (case-insensitive),
+// "*", or "*.example.com" for any subdomain
+func matchHost(pattern, host string) bool {
+	pattern, host = strings.ToLower(pattern), strings.ToLower(host)
+	if pattern == "*" || pattern == host {
+		return true
+	}
+	if suffix, ok := strings.CutPrefix(pattern, "*"); ok && strings.HasPrefix(suffix, ".") {
+		return strings.HasSuffix(host, suffix)
+	}
+	return false
+}
+
+// Allows reports whether the plugin may connect to addr ("host:port")
+func (t *HostTCP) Allows(addr string) bool {
+	host, port, err := net.SplitHostPort(addr)
+	if err != nil {
+		return false
+	}
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	for _, pattern := range t.allow {
+		pHost, pPort, _ := net.SplitHostPort(pattern)
+		if (pPort == "*" || pPort == port) && matchHost(pHost, host) {
+			return true
+		}
+	}
+	return false
+}
+
+func clampTimeout(ms uint32) time.Duration {
+	timeout := time.Duration(ms) * time.Millisecond
+	if timeout <= 0 || timeout > MaxHostTCPTimeout {
+		return MaxHostTCPTimeout
+	}
+	return timeout
+}
+
+// tcpError converts a network error to a wire error
+func tcpError(err error) error {
+	var netErr net.Error
+	if (errors.As(err, &netErr) && netErr.Timeout()) || errors.Is(err, os.ErrDeadlineExceeded) {
+		return fmt.Errorf("ETIMEDOUT: %v", err)
+	}
+	return fmt.Errorf("EIO: %v", err)
+}
+
+// Connect opens a connection to addr and returns its handle
+func (t *HostTCP) Connect(ctx context.Context, addr string, timeout time.Duration) (uint32, error) {
+	if !t.Allows(addr) {
+		return 0, fmt.Errorf("EACCES: %s not in %s", addr, HostTCPAllowKey)
+	}
+	t.mu.Lock()
+	full := len(t.conns) >= MaxHostTCPConns
+	t.mu.Unlock()
+	if full {
+		return 0, fmt.Errorf("EIO: more than %d open connections", MaxHostTCPConns)
+	}
+
+	dialer := net.Dialer{Timeout: timeout}
+	conn, err := dialer.DialContext(ctx, "tcp", addr)
+	if err != nil {
+		return 0, tcpError(err)
+	}
+
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	t.next++
+	if t.next == 0 {
+		t.next = 1
+	}
+	t.conns[t.next] = conn
+	return t.next, nil
+}
+
+func (t *HostTCP) conn(handle uint32) (net.Conn, error) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	conn, ok := t.conns[handle]
+	if !ok {
+		return nil, fmt.Errorf("EINVAL: unknown connection %d", handle)
+	}
+	return conn, nil
+}
+
+// Read returns up to limit bytes; nil data and no error means the peer
+// closed its side
+func (t *HostTCP) Read(handle uint32, limit int, timeout time.Duration) ([]byte, error) {
+	conn, err := t.conn(handle)
+	if err != nil {
+		return nil, err
+	}
+	if limit <= 0 || limit > MaxHostTCPChunk {
+		limit = MaxHostTCPChunk
+	}
+	conn.SetReadDeadline(time.Now().Add(timeout))
+	buf := make([]byte, limit)
+	n, err := conn.Read(buf)
+	if n > 0 {
+		return buf[:n], nil
+	}
+	if err != nil && err != io.EOF {
+		return nil, tcpError(err)
+	}
+	return nil, nil
+}
+
+// Write sends data and returns how much was written
+func (t *HostTCP) Write(handle uint32, data []byte, timeout time.Duration) (int, error) {
+	conn, err := t.conn(handle)
+	if err != nil {
+		return 0, err
+	}
+	conn.SetWriteDeadline(time.Now().Add(timeout))
+	n, err := conn.Write(data)
+	if err != nil {
+		return n, tcpError(err)
+	}
+	return n, nil
+}
+
+// CloseConn closes one connection
+func (t *HostTCP) CloseConn(handle uint32) error {
+	t.mu.Lock()
+	conn, ok := t.conns[handle]
+	delete(t.conns, handle)
+	t.mu.Unlock()
+	if !ok {
+		return fmt.Errorf("EINVAL: unknown connection %d", handle)
+	}
+	return conn.Close()
+}
+
+// Close closes every connection the plugin left open
+func (t *HostTCP) Close() error {
+	t.mu.Lock()
+	conns := t.conns
+	t.conns = map[uint32]net.Conn{}
+	t.mu.Unlock()
+	for _, conn := range conns {
+		conn.Close()
+	}
+	return nil
+}
+
+// HostTCPConnect returns (handle, error pointer)
+func HostTCPConnect(ctx context.Context, mod wazeroapi.Module, params []uint64, tcp *HostTCP) []uint64 {
+	addr, ok := readStringFromMemory(mod, uint32(params[0]))
+	if !ok {
+		log.Errorf("host_tcp_connect: failed to read address from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_tcp_connect: %s", addr)
+
+	handle, err := tcp.Connect(ctx, addr, clampTimeout(uint32(params[1])))
+	if err != nil {
+		log.Warnf("host_tcp_connect: %v", err)
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+	return []uint64{uint64(handle)}
+}
+
+// HostTCPRead returns (pointer, length), (0, 0) once the peer has closed,
+// or (0, error pointer)
+func HostTCPRead(ctx context.Context, mod wazeroapi.Module, params []uint64, tcp *HostTCP) []uint64 {
+	handle := uint32(params[0])
+	data, err := tcp.Read(handle, int(uint32(params[1])), clampTimeout(uint32(params[2])))
+	if err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+	if data == nil {
+		return []uint64{0}
+	}
+
+	dataPtr, err := writeBytesToMemory(mod, data)
+	if err != nil {
+		log.Errorf("host_tcp_read: failed to write data to memory: %v", err)
+		errPtr, _ := writeStringToMemory(mod, "EIO: out of plugin memory")
+		return []uint64{uint64(errPtr) << 32}
+	}
+	return []uint64{uint64(dataPtr) | (uint64(len(data)) << 32)}
+}
+
+// HostTCPWrite returns (bytes written, error pointer)
+func HostTCPWrite(ctx context.Context, mod wazeroapi.Module, params []uint64, tcp *HostTCP) []uint64 {
+	handle := uint32(params[0])
+	data, ok := mod.Memory().Read(uint32(params[1]), uint32(params[2]))
+	if !ok {
+		log.Errorf("host_tcp_write: failed to read data from memory")
+		return []uint64{0}
+	}
+
+	n, err := tcp.Write(handle, data, clampTimeout(uint32(params[3])))
+	if err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(n) | (uint64(errPtr) << 32)}
+	}
+	return []uint64{uint64(n)}
+}
+
+// HostTCPClose returns an error pointer, 0 on success
+func HostTCPClose(ctx context.Context, mod wazeroapi.Module, params []uint64, tcp *HostTCP) []uint64 {
+	if err := tcp.CloseConn(uint32(params[0])); err != nil {
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr)}
+	}
+	return []uint64{0}
+}
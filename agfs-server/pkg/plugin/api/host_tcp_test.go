@@ -0,0 +1,139 @@
+package api
+
+import (
+	"bytes"
+	"context"
+	"io"
+	"net"
+	"strings"
+	"testing"
+	"time"
+)
+
+func newHostTCP(t *testing.T, allow ...string) *HostTCP {
+	t.Helper()
+	tcp := NewHostTCP()
+	if err := tcp.Configure(map[string]interface{}{HostTCPAllowKey: allow}); err != nil {
+		t.Fatalf("Configure failed: %v", err)
+	}
+	t.Cleanup(func() { tcp.Close() })
+	return tcp
+}
+
+func TestHostTCP_Allows(t *testing.T) {
+	tcp := newHostTCP(t, "redis.internal:6379", "*.mqtt.example.com:8883", "db.local:*")
+
+	cases := map[string]bool{
+		"redis.internal:6379":        true,
+		"REDIS.internal:6379":        true,
+		"redis.internal:6380":        false,
+		"a.mqtt.example.com:8883":    true,
+		"mqtt.example.com:8883":      false,
+		"a.mqtt.example.com:1883":    false,
+		"db.local:5432":              true,
+		"db.local":                   false,
+		"evil.example.com:6379":      false,
+		"redis.internal.evil.com:80": false,
+	}
+	for addr, want := range cases {
+		if got := tcp.Allows(addr); got != want {
+			t.Errorf("Allows(%q) = %v, want %v", addr, got, want)
+		}
+	}
+
+	if err := NewHostTCP().Configure(map[string]interface{}{HostTCPAllowKey: []string{"no-port"}}); err == nil {
+		t.Errorf("expected an error for an entry without a port")
+	}
+	if NewHostTCP().Allows("redis.internal:6379") {
+		t.Errorf("a table without %s allowed a destination", HostTCPAllowKey)
+	}
+}
+
+func TestHostTCP_DeniedConnect(t *testing.T) {
+	listener, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer listener.Close()
+	addr := listener.Addr().String()
+
+	tcp := newHostTCP(t, "localhost:*")
+	if _, err := tcp.Connect(context.Background(), addr, time.Second); err == nil || !strings.HasPrefix(err.Error(), "EACCES: ") {
+		t.Errorf("expected EACCES, got %v", err)
+	}
+
+	// The denial reaches the plugin as its error string
+	mod := loadTestModule(t, "alloc")
+	addrPtr, err := writeStringToMemory(mod, addr)
+	if err != nil {
+		t.Fatalf("failed to write address: %v", err)
+	}
+	reply := HostTCPConnect(context.Background(), mod, []uint64{uint64(addrPtr), 1000}, tcp)
+	if msg := replyError(t, mod, reply); !strings.HasPrefix(msg, "EACCES: ") {
+		t.Errorf("expected EACCES, got %q", msg)
+	}
+}
+
+func TestHostTCP_ReadWriteCaps(t *testing.T) {
+	listener, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatalf("failed to listen: %v", err)
+	}
+	defer listener.Close()
+
+	// Echo what the plugin sends, then flood it with more than one chunk
+	received := make(chan []byte, 1)
+	go func() {
+		conn, err := listener.Accept()
+		if err != nil {
+			return
+		}
+		defer conn.Close()
+		buf := make([]byte, 5)
+		io.ReadFull(conn, buf)
+		received <- buf
+		conn.Write(bytes.Repeat([]byte("x"), 2*MaxHostTCPChunk+1))
+	}()
+
+	tcp := newHostTCP(t, "127.0.0.1:*")
+	handle, err := tcp.Connect(context.Background(), listener.Addr().String(), time.Second)
+	if err != nil {
+		t.Fatalf("Connect failed: %v", err)
+	}
+	if n, err := tcp.Write(handle, []byte("hello"), time.Second); n != 5 || err != nil {
+		t.Fatalf("Write = %d, %v", n, err)
+	}
+	if got := <-received; string(got) != "hello" {
+		t.Errorf("server received %q", got)
+	}
+
+	data, err := tcp.Read(handle, 10, time.Second)
+	if err != nil || len(data) == 0 || len(data) > 10 {
+		t.Errorf("Read(limit 10) = %d bytes, %v", len(data), err)
+	}
+	total := len(data)
+	for total < 2*MaxHostTCPChunk+1 {
+		// A limit over the cap reads at most one chunk
+		data, err := tcp.Read(handle, 4*MaxHostTCPChunk, time.Second)
+		if err != nil {
+			t.Fatalf("Read failed: %v", err)
+		}
+		if data == nil {
+			t.Fatalf("connection closed after %d bytes", total)
+		}
+		if len(data) > MaxHostTCPChunk {
+			t.Fatalf("Read returned %d bytes, over the %d cap", len(data), MaxHostTCPChunk)
+		}
+		total += len(data)
+	}
+	if data, err := tcp.Read(handle, 0, time.Second); data != nil || err != nil {
+		t.Errorf("expected end of stream, got %d bytes, %v", len(data), err)
+	}
+
+	if err := tcp.CloseConn(handle); err != nil {
+		t.Errorf("CloseConn failed: %v", err)
+	}
+	if _, err := tcp.Read(handle, 0, time.Second); err == nil || !strings.HasPrefix(err.Error(), "EINVAL: ") {
+		t.Errorf("expected EINVAL on a closed handle, got %v", err)
+	}
+}
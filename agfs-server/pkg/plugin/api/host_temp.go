@@ -0,0 +1,508 @@
+package api
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"os"
+	"path"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugins/localfs"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// HostTempRoot is where a plugin's scratch space appears among its host
+// paths; the host_fs_* imports reach it like any other path
+const HostTempRoot = "/.host-tmp"
+
+// HostTemp is a plugin's private scratch directory on the server
+// It is created on first use in the OS temp directory and removed,
+// with everything in it, when the plugin shuts down
+type HostTemp struct {
+	mu  sync.Mutex
+	dir string
+	fs  *localfs.LocalFS
+}
+
+// NewHostTemp creates a scratch space that allocates nothing until used
+func NewHostTemp() *HostTemp {
+	return &HostTemp{}
+}
+
+func (t *HostTemp) open() (*localfs.LocalFS, error) {
+	if t.fs != nil {
+		return t.fs, nil
+	}
+	dir, err := os.MkdirTemp("", "agfs-plugin-")
+	if err != nil {
+		return nil, fmt.Errorf("EIO: %v", err)
+	}
+	fs, err := localfs.NewLocalFS(dir)
+	if err != nil {
+		os.RemoveAll(dir)
+		return nil, fmt.Errorf("EIO: %v", err)
+	}
+	t.dir, t.fs = dir, fs
+	return fs, nil
+}
+
+// cleanPrefix keeps a name prefix usable as part of a single file name
+func cleanPrefix(prefix string) string {
+	prefix = strings.NewReplacer("/", "_", "\\", "_", "*", "_").Replace(prefix)
+	if prefix == "" || prefix == "." || prefix == ".." {
+		return "tmp"
+	}
+	return prefix
+}
+
+// CreateFile makes a new empty file and returns its host path
+func (t *HostTemp) CreateFile(prefix string) (string, error) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if _, err := t.open(); err != nil {
+		return "", err
+	}
+	f, err := os.CreateTemp(t.dir, cleanPrefix(prefix)+"-*")
+	if err != nil {
+		return "", fmt.Errorf("EIO: %v", err)
+	}
+	f.Close()
+	return path.Join(HostTempRoot, path.Base(f.Name())), nil
+}
+
+// CreateDir makes a new empty directory and returns its host path
+func (t *HostTemp) CreateDir(prefix string) (string, error) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if _, err := t.open(); err != nil {
+		return "", err
+	}
+	dir, err := os.MkdirTemp(t.dir, cleanPrefix(prefix)+"-*")
+	if err != nil {
+		return "", fmt.Errorf("EIO: %v", err)
+	}
+	return path.Join(HostTempRoot, path.Base(dir)), nil
+}
+
+// Close removes the scratch directory and everything in it
+func (t *HostTemp) Close() error {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if t.dir == "" {
+		return nil
+	}
+	err := os.RemoveAll(t.dir)
+	t.dir, t.fs = "", nil
+	return err
+}
+
+// tempPath reports whether p is inside HostTempRoot, and its path there
+func tempPath(p string) (string, bool) {
+	p = filesystem.NormalizePath(p)
+	if p == HostTempRoot {
+		return "/", true
+	}
+	if rest, ok := strings.CutPrefix(p, HostTempRoot+"/"); ok {
+		return "/" + rest, true
+	}
+	return "", false
+}
+
+// route picks the filesystem for p: the scratch space for paths under
+// HostTempRoot, otherwise fs
+func (t *HostTemp) route(fs filesystem.FileSystem, p string) (filesystem.FileSystem, string, error) {
+	if rel, ok := tempPath(p); ok {
+		t.mu.Lock()
+		defer t.mu.Unlock()
+		if t.fs == nil {
+			return nil, "", &filesystem.NotFoundError{Path: p}
+		}
+		return t.fs, rel, nil
+	}
+	if fs == nil {
+		return nil, "", fmt.Errorf("no host filesystem provided")
+	}
+	return fs, p, nil
+}
+
+// tempRoutedFS serves HostTempRoot from a HostTemp and everything else
+// from the host filesystem, which may be nil
+type tempRoutedFS struct {
+	fs   filesystem.FileSystem
+	temp *HostTemp
+}
+
+// NewTempRoutedFS overlays temp's scratch space at HostTempRoot on fs
+func NewTempRoutedFS(fs filesystem.FileSystem, temp *HostTemp) filesystem.FileSystem {
+	return &tempRoutedFS{fs: fs, temp: temp}
+}
+
+func (r *tempRoutedFS) Create(p string) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	return fs.Create(p)
+}
+
+func (r *tempRoutedFS) Mkdir(p string, perm uint32) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	return fs.Mkdir(p, perm)
+}
+
+func (r *tempRoutedFS) Remove(p string) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	return fs.Remove(p)
+}
+
+func (r *tempRoutedFS) RemoveAll(p string) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	return fs.RemoveAll(p)
+}
+
+func (r *tempRoutedFS) Read(p string, offset int64, size int64) ([]byte, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.Read(p, offset, size)
+}
+
+func (r *tempRoutedFS) Write(p string, data []byte) ([]byte, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.Write(p, data)
+}
+
+func (r *tempRoutedFS) ReadDir(p string) ([]filesystem.FileInfo, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.ReadDir(p)
+}
+
+func (r *tempRoutedFS) Stat(p string) (*filesystem.FileInfo, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.Stat(p)
+}
+
+func (r *tempRoutedFS) Rename(oldPath, newPath string) error {
+	_, oldInTemp := tempPath(oldPath)
+	_, newInTemp := tempPath(newPath)
+	if oldInTemp != newInTemp {
+		return fmt.Errorf("EINVAL: cannot rename between %s and other host paths", HostTempRoot)
+	}
+	fs, oldRel, err := r.temp.route(r.fs, oldPath)
+	if err != nil {
+		return err
+	}
+	_, newRel, err := r.temp.route(r.fs, newPath)
+	if err != nil {
+		return err
+	}
+	return fs.Rename(oldRel, newRel)
+}
+
+func (r *tempRoutedFS) Chmod(p string, mode uint32) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	return fs.Chmod(p, mode)
+}
+
+func (r *tempRoutedFS) Open(p string) (io.ReadCloser, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.Open(p)
+}
+
+func (r *tempRoutedFS) OpenWrite(p string) (io.WriteCloser, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	return fs.OpenWrite(p)
+}
+
+// Lstat implements filesystem.Lstater interface
+func (r *tempRoutedFS) Lstat(p string) (*filesystem.FileInfo, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return nil, err
+	}
+	lstater, ok := fs.(filesystem.Lstater)
+	if !ok {
+		return nil, filesystem.NewNotSupportedError("lstat", p)
+	}
+	return lstater.Lstat(p)
+}
+
+// RealPath implements filesystem.RealPather interface
+// Paths in the scratch space resolve to paths under HostTempRoot
+func (r *tempRoutedFS) RealPath(p string) (string, error) {
+	_, inTemp := tempPath(p)
+	fs, rel, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return "", err
+	}
+	realPather, ok := fs.(filesystem.RealPather)
+	if !ok {
+		return "", filesystem.NewNotSupportedError("realpath", p)
+	}
+	resolved, err := realPather.RealPath(rel)
+	if err != nil || !inTemp {
+		return resolved, err
+	}
+	return path.Join(HostTempRoot, resolved), nil
+}
+
+// CreateExclusive implements filesystem.ExclusiveCreator interface
+func (r *tempRoutedFS) CreateExclusive(p string) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	creator, ok := fs.(filesystem.ExclusiveCreator)
+	if !ok {
+		return filesystem.NewNotSupportedError("create_exclusive", p)
+	}
+	return creator.CreateExclusive(p)
+}
+
+// Symlink implements filesystem.Symlinker interface
+func (r *tempRoutedFS) Symlink(target, linkPath string) error {
+	fs, linkPath, err := r.temp.route(r.fs, linkPath)
+	if err != nil {
+		return err
+	}
+	symlinker, ok := fs.(filesystem.Symlinker)
+	if !ok {
+		return filesystem.NewNotSupportedError("symlink", linkPath)
+	}
+	return symlinker.Symlink(target, linkPath)
+}
+
+// Readlink implements filesystem.Symlinker interface
+func (r *tempRoutedFS) Readlink(p string) (string, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return "", err
+	}
+	symlinker, ok := fs.(filesystem.Symlinker)
+	if !ok {
+		return "", filesystem.NewNotSupportedError("readlink", p)
+	}
+	return symlinker.Readlink(p)
+}
+
+// Truncate implements filesystem.Truncater interface
+func (r *tempRoutedFS) Truncate(p string, size int64) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	truncater, ok := fs.(filesystem.Truncater)
+	if !ok {
+		return filesystem.NewNotSupportedError("truncate", p)
+	}
+	return truncater.Truncate(p, size)
+}
+
+// SetTimes implements filesystem.TimeSetter interface
+func (r *tempRoutedFS) SetTimes(p string, atime, mtime time.Time) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	setter, ok := fs.(filesystem.TimeSetter)
+	if !ok {
+		return filesystem.NewNotSupportedError("set_times", p)
+	}
+	return setter.SetTimes(p, atime, mtime)
+}
+
+// Chown implements filesystem.Chowner interface
+func (r *tempRoutedFS) Chown(p string, uid, gid uint32) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	chowner, ok := fs.(filesystem.Chowner)
+	if !ok {
+		return filesystem.NewNotSupportedError("chown", p)
+	}
+	return chowner.Chown(p, uid, gid)
+}
+
+// Link implements filesystem.Linker interface
+func (r *tempRoutedFS) Link(existing, newPath string) error {
+	_, oldInTemp := tempPath(existing)
+	_, newInTemp := tempPath(newPath)
+	if oldInTemp != newInTemp {
+		return fmt.Errorf("EINVAL: cannot link between %s and other host paths", HostTempRoot)
+	}
+	fs, oldRel, err := r.temp.route(r.fs, existing)
+	if err != nil {
+		return err
+	}
+	_, newRel, err := r.temp.route(r.fs, newPath)
+	if err != nil {
+		return err
+	}
+	linker, ok := fs.(filesystem.Linker)
+	if !ok {
+		return filesystem.NewNotSupportedError("link", newRel)
+	}
+	return linker.Link(oldRel, newRel)
+}
+
+// CreateWithFlags implements filesystem.FlagCreator interface
+func (r *tempRoutedFS) CreateWithFlags(p string, flags, mode uint32) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	creator, ok := fs.(filesystem.FlagCreator)
+	if !ok {
+		return filesystem.NewNotSupportedError("create_flags", p)
+	}
+	return creator.CreateWithFlags(p, flags, mode)
+}
+
+// SeekData implements filesystem.DataSeeker interface
+func (r *tempRoutedFS) SeekData(p string, offset int64, whence int) (int64, bool, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return 0, false, err
+	}
+	seeker, ok := fs.(filesystem.DataSeeker)
+	if !ok {
+		return 0, false, filesystem.NewNotSupportedError("seek", p)
+	}
+	return seeker.SeekData(p, offset, whence)
+}
+
+// Allocate implements filesystem.Allocator interface
+func (r *tempRoutedFS) Allocate(p string, offset, length int64) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	allocator, ok := fs.(filesystem.Allocator)
+	if !ok {
+		return filesystem.NewNotSupportedError("allocate", p)
+	}
+	return allocator.Allocate(p, offset, length)
+}
+
+// Access implements filesystem.Accesser interface
+func (r *tempRoutedFS) Access(p string, mode uint32) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	accesser, ok := fs.(filesystem.Accesser)
+	if !ok {
+		return filesystem.NewNotSupportedError("access", p)
+	}
+	return accesser.Access(p, mode)
+}
+
+// Append implements filesystem.Appender interface
+func (r *tempRoutedFS) Append(p string, data []byte) error {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return err
+	}
+	appender, ok := fs.(filesystem.Appender)
+	if !ok {
+		return filesystem.NewNotSupportedError("append", p)
+	}
+	return appender.Append(p, data)
+}
+
+// RenameWithFlags implements filesystem.FlagRenamer interface
+func (r *tempRoutedFS) RenameWithFlags(oldPath, newPath string, flags uint32) error {
+	_, oldInTemp := tempPath(oldPath)
+	_, newInTemp := tempPath(newPath)
+	if oldInTemp != newInTemp {
+		return fmt.Errorf("EINVAL: cannot rename between %s and other host paths", HostTempRoot)
+	}
+	fs, oldRel, err := r.temp.route(r.fs, oldPath)
+	if err != nil {
+		return err
+	}
+	_, newRel, err := r.temp.route(r.fs, newPath)
+	if err != nil {
+		return err
+	}
+	renamer, ok := fs.(filesystem.FlagRenamer)
+	if !ok {
+		return filesystem.NewNotSupportedError("rename", newRel)
+	}
+	return renamer.RenameWithFlags(oldRel, newRel, flags)
+}
+
+// WriteAt implements filesystem.WriterAt interface
+func (r *tempRoutedFS) WriteAt(p string, offset int64, data []byte) (int64, error) {
+	fs, p, err := r.temp.route(r.fs, p)
+	if err != nil {
+		return 0, err
+	}
+	writer, ok := fs.(filesystem.WriterAt)
+	if !ok {
+		return 0, filesystem.NewNotSupportedError("write_at", p)
+	}
+	return writer.WriteAt(p, offset, data)
+}
+
+// HostTempCreate makes a scratch file, or directory if isDir is set
+// Returns (path pointer, error pointer)
+func HostTempCreate(ctx context.Context, mod wazeroapi.Module, params []uint64, temp *HostTemp, isDir bool) []uint64 {
+	prefix, _ := readStringFromMemory(mod, uint32(params[0]))
+
+	var p string
+	var err error
+	if isDir {
+		p, err = temp.CreateDir(prefix)
+	} else {
+		p, err = temp.CreateFile(prefix)
+	}
+	if err != nil {
+		log.Errorf("host_temp_create: %v", err)
+		errPtr, _ := writeStringToMemory(mod, wireError(err))
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	log.Debugf("host_temp_create: %s", p)
+
+	pathPtr, err := writeStringToMemory(mod, p)
+	if err != nil {
+		log.Errorf("host_temp_create: failed to write path to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(pathPtr)}
+}
@@ -0,0 +1,21 @@
+package api
+
+import (
+	"context"
+	"time"
+)
+
+// hostClockStart anchors host_time_monotonic; Go's time.Since uses the
+// monotonic clock reading
+var hostClockStart = time.Now()
+
+// HostTimeNow returns the wall clock as Unix nanoseconds
+func HostTimeNow(ctx context.Context) int64 {
+	return time.Now().UnixNano()
+}
+
+// HostTimeMonotonic returns nanoseconds since server start, unaffected by
+// changes to the wall clock
+func HostTimeMonotonic(ctx context.Context) uint64 {
+	return uint64(time.Since(hostClockStart))
+}
@@ -0,0 +1,110 @@
+package api
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"sync"
+	"time"
+)
+
+// HostCallTimeoutKey is the mount config key bounding, in milliseconds,
+// how long a host_fs_* call may take. Unset or 0 leaves calls unbounded.
+// A call that misses the deadline fails with ETIMEDOUT, which agfs-wasm-ffi
+// reports as Error::Timeout
+const HostCallTimeoutKey = "host_call_timeout_ms"
+
+// HostTimeout is the deadline applied to a plugin's host filesystem calls
+type HostTimeout struct {
+	mu      sync.RWMutex
+	timeout time.Duration // 0: no deadline
+}
+
+// NewHostTimeout creates a timeout that applies no deadline
+func NewHostTimeout() *HostTimeout {
+	return &HostTimeout{}
+}
+
+// parseHostTimeout reads host_call_timeout_ms from a mount config
+func parseHostTimeout(config map[string]interface{}) (time.Duration, bool, error) {
+	value, ok := config[HostCallTimeoutKey]
+	if !ok {
+		return 0, false, nil
+	}
+	var ms float64
+	switch v := value.(type) {
+	case int:
+		ms = float64(v)
+	case int64:
+		ms = float64(v)
+	case float64:
+		ms = v
+	default:
+		return 0, false, fmt.Errorf("%s must be an integer", HostCallTimeoutKey)
+	}
+	if ms < 0 {
+		return 0, false, fmt.Errorf("%s must not be negative", HostCallTimeoutKey)
+	}
+	return time.Duration(ms) * time.Millisecond, true, nil
+}
+
+// Configure applies host_call_timeout_ms from the mount config, if present
+func (t *HostTimeout) Configure(config map[string]interface{}) error {
+	timeout, ok, err := parseHostTimeout(config)
+	if err != nil || !ok {
+		return err
+	}
+	t.mu.Lock()
+	t.timeout = timeout
+	t.mu.Unlock()
+	return nil
+}
+
+// Context derives the context for one host call from the caller's
+func (t *HostTimeout) Context(ctx context.Context) (context.Context, context.CancelFunc) {
+	t.mu.RLock()
+	timeout := t.timeout
+	t.mu.RUnlock()
+	if timeout <= 0 {
+		return ctx, func() {}
+	}
+	return context.WithTimeout(ctx, timeout)
+}
+
+// runHostCall runs fn, giving up when ctx ends first
+// The host filesystem API takes no context, so an abandoned call keeps
+// running in the background; only the plugin stops waiting for it
+func runHostCall[T any](ctx context.Context, op string, fn func() (T, error)) (T, error) {
+	if ctx.Done() == nil {
+		return fn()
+	}
+
+	type result struct {
+		value T
+		err   error
+	}
+	done := make(chan result, 1)
+	go func() {
+		value, err := fn()
+		done <- result{value, err}
+	}()
+
+	select {
+	case r := <-done:
+		return r.value, r.err
+	case <-ctx.Done():
+		var zero T
+		if errors.Is(ctx.Err(), context.DeadlineExceeded) {
+			return zero, fmt.Errorf("ETIMEDOUT: %s did not finish in time", op)
+		}
+		return zero, fmt.Errorf("ECANCELED: %s", op)
+	}
+}
+
+// runHostOp is runHostCall for calls that return only an error
+func runHostOp(ctx context.Context, op string, fn func() error) error {
+	_, err := runHostCall(ctx, op, func() (struct{}, error) {
+		return struct{}{}, fn()
+	})
+	return err
+}
@@ -0,0 +1,40 @@
+package api
+
+import (
+	"context"
+	"os"
+	"path/filepath"
+	"testing"
+
+	"github.com/tetratelabs/wazero"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// loadTestModule instantiates testdata/<name>.wasm, built from the .wat
+// next to it (wat2wasm testdata/<name>.wat -o testdata/<name>.wasm)
+func loadTestModule(t *testing.T, name string) wazeroapi.Module {
+	t.Helper()
+	ctx := context.Background()
+	code, err := os.ReadFile(filepath.Join("testdata", name+".wasm"))
+	if err != nil {
+		t.Fatalf("failed to read test module: %v", err)
+	}
+	runtime := wazero.NewRuntime(ctx)
+	t.Cleanup(func() { runtime.Close(ctx) })
+	mod, err := runtime.Instantiate(ctx, code)
+	if err != nil {
+		t.Fatalf("failed to instantiate test module: %v", err)
+	}
+	return mod
+}
+
+// replyError reads the error string of a packed (value, error pointer) reply
+func replyError(t *testing.T, mod wazeroapi.Module, reply []uint64) string {
+	t.Helper()
+	errPtr := uint32(reply[0] >> 32)
+	if errPtr == 0 {
+		t.Fatalf("expected an error reply, got %#x", reply[0])
+	}
+	msg, _ := readStringFromMemory(mod, errPtr)
+	return msg
+}
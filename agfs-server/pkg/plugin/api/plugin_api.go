@@ -37,6 +37,8 @@ type PluginVTable struct {
 	FSStat       func(unsafe.Pointer, *byte) *FileInfoC
 	FSRename     func(unsafe.Pointer, *byte, *byte) *byte
 	FSChmod      func(unsafe.Pointer, *byte, uint32) *byte
+	FSFlush      func(unsafe.Pointer, *byte) *byte
+	FSFsync      func(unsafe.Pointer, *byte) *byte
 }
 
 // FileInfoC is the C-compatible representation of filesystem.FileInfo
@@ -0,0 +1,99 @@
+;; Fake plugin for the WASMFileSystem tests
+;;
+;; Keeps the last call context it was given. plugin_trace_drain reports
+;; one span, with the trace id copied out of that context, which must
+;; start with {"TraceParent":"00-<trace id>
+;;
+;; fs_write_result stores at most 5 bytes and answers "ok"
+;;
+;; fs_batch answers any batch with the same four results: a stat, a read,
+;; a listing and a failure
+;;
+;; plugin_reconfigure accepts any config
+;;
+;; fs_open opens any path as handle 7, through which fs_read_at reads
+;; "hello" from any offset
+;;
+;; fs_readdir_open opens any directory as cursor 3, which fs_readdir_next
+;; lists as one batch holding "c", then an empty batch
+(module
+  (memory (export "memory") 2)
+  (global $next (mut i32) (i32.const 8192))
+  (global $ctx (mut i32) (i32.const 0))
+  (global $listed (mut i32) (i32.const 0))
+
+  (data (i32.const 128) "fakefs\00")
+  (data (i32.const 256) "{\"resourceSpans\":[{\"resource\":{\"attributes\":[{\"key\":\"service.name\",\"value\":{\"stringValue\":\"fakefs\"}}]},\"scopeSpans\":[{\"spans\":[{\"traceId\":\"XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\",\"spanId\":\"00000000000000aa\",\"name\":\"agfs.remove\"}]}]}]}\00")
+
+  (data (i32.const 1024) "{\"BytesWritten\":5,\"Response\":\"b2s=\"}\00")
+  (data (i32.const 1280) "[{\"Info\":{\"Name\":\"a\",\"Size\":3,\"Mode\":420,\"ModTime\":\"2024-01-01T00:00:00Z\",\"IsDir\":false}},{\"Data\":\"aGk=\"},{\"Entries\":[{\"Name\":\"b\",\"Size\":0,\"Mode\":493,\"ModTime\":\"2024-01-01T00:00:00Z\",\"IsDir\":true}]},{\"Error\":\"ENOENT: file not found\"}]\00")
+  (data (i32.const 1792) "hello")
+  (data (i32.const 1800) "EINVAL: unknown file handle\00")
+  (data (i32.const 1840) "[{\"Name\":\"c\",\"Size\":1,\"Mode\":420,\"ModTime\":\"2024-01-01T00:00:00Z\",\"IsDir\":false}]\00")
+  (data (i32.const 1952) "[]\00")
+
+  (func (export "malloc") (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next
+      (i32.and
+        (i32.add (i32.add (local.get $ptr) (local.get $size)) (i32.const 7))
+        (i32.const -8)))
+    (local.get $ptr))
+
+  (func (export "plugin_new") (result i32)
+    (i32.const 1))
+
+  (func (export "plugin_name") (result i32)
+    (i32.const 128))
+
+  (func (export "plugin_set_context") (param $ptr i32) (result i32)
+    (global.set $ctx (local.get $ptr))
+    (i32.const 0))
+
+  (func (export "fs_remove") (param $path i32) (result i32)
+    (i32.const 0))
+
+  (func (export "plugin_trace_drain") (result i32)
+    ;; {"TraceParent":"00- is 19 bytes
+    (memory.copy
+      (i32.const 395)
+      (i32.add (global.get $ctx) (i32.const 19))
+      (i32.const 32))
+    (i32.const 256))
+
+  (func (export "fs_write_result") (param $path i32) (param $data i32) (param $size i32) (result i64)
+    (i64.const 1024))
+
+  (func (export "fs_batch") (param $ops i32) (result i64)
+    (i64.const 1280))
+
+  (func (export "plugin_reconfigure") (param $config i32) (result i32)
+    (i32.const 0))
+
+  (func (export "fs_open") (param $path i32) (param $flags i32) (result i64)
+    (i64.const 7))
+
+  ;; (1792, 5), or (0, the error at 1800) for other handles
+  (func (export "fs_read_at") (param $handle i32) (param $offset i64) (param $size i64) (result i64)
+    (if (result i64) (i32.eq (local.get $handle) (i32.const 7))
+      (then (i64.or (i64.shl (i64.const 5) (i64.const 32)) (i64.const 1792)))
+      (else (i64.shl (i64.const 1800) (i64.const 32)))))
+
+  (func (export "fs_close") (param $handle i32) (result i32)
+    (i32.const 0))
+
+  (func (export "fs_readdir_open") (param $path i32) (result i64)
+    (global.set $listed (i32.const 0))
+    (i64.const 3))
+
+  (func (export "fs_readdir_next") (param $cursor i32) (param $max i32) (result i64)
+    (if (result i64) (global.get $listed)
+      (then (i64.const 1952))
+      (else
+        (global.set $listed (i32.const 1))
+        (i64.const 1840))))
+
+  (func (export "fs_readdir_close") (param $cursor i32) (result i32)
+    (i32.const 0))
+)
@@ -0,0 +1,154 @@
+package api
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Operations a batch may hold
+const (
+	BatchStat    = "stat"
+	BatchRead    = "read"
+	BatchReadDir = "readdir"
+)
+
+// maxBatchOps is the most calls agfs-wasm-ffi accepts in one fs_batch
+const maxBatchOps = 4096
+
+// BatchOp is one read-only call of a batch, as agfs-wasm-ffi's FsOp
+type BatchOp struct {
+	Op     string // BatchStat, BatchRead or BatchReadDir
+	Path   string
+	Offset int64 // read only
+	Size   int64 // read only; negative reads to the end
+}
+
+// BatchResult is the outcome of one BatchOp; Err is set when it failed
+type BatchResult struct {
+	Info    *filesystem.FileInfo  // stat
+	Data    []byte                // read
+	Entries []filesystem.FileInfo // readdir
+	Err     error
+}
+
+// pluginBatchResult is one result of fs_batch, of which one field is set
+type pluginBatchResult struct {
+	Info    *pluginFileInfo
+	Data    []byte // base64 in JSON
+	Entries []pluginFileInfo
+	Error   *string
+}
+
+// Batch runs read-only calls in order, through a single fs_batch call
+// per 4096 when the plugin exports it, saving a crossing per entry when
+// walking a tree. One failed call does not affect the others; an error
+// is returned only when the batch as a whole could not run
+func (wfs *WASMFileSystem) Batch(ops []BatchOp) ([]BatchResult, error) {
+	for _, op := range ops {
+		switch op.Op {
+		case BatchStat, BatchRead, BatchReadDir:
+		default:
+			return nil, filesystem.NewInvalidArgumentError("op", op.Op, "expected stat, read or readdir")
+		}
+	}
+
+	batchFunc := wfs.module.ExportedFunction("fs_batch")
+	if batchFunc == nil {
+		return wfs.batchEach(ops), nil
+	}
+	results := make([]BatchResult, 0, len(ops))
+	for start := 0; start < len(ops); start += maxBatchOps {
+		chunk, err := wfs.batch(batchFunc, ops[start:min(start+maxBatchOps, len(ops))])
+		if err != nil {
+			return nil, err
+		}
+		results = append(results, chunk...)
+	}
+	return results, nil
+}
+
+// batch sends up to maxBatchOps calls through fs_batch
+func (wfs *WASMFileSystem) batch(batchFunc wazeroapi.Function, ops []BatchOp) ([]BatchResult, error) {
+	opsJSON, err := json.Marshal(ops)
+	if err != nil {
+		return nil, fmt.Errorf("failed to marshal batch: %w", err)
+	}
+	opsPtr, err := writeStringToMemory(wfs.module, string(opsJSON))
+	if err != nil {
+		return nil, err
+	}
+
+	results, err := wfs.call(batchFunc, uint64(opsPtr))
+	if err != nil {
+		return nil, fmt.Errorf("fs_batch failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_batch returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			return nil, pluginError("batch", "/", errMsg)
+		}
+		return nil, fmt.Errorf("batch failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	if !ok {
+		return nil, fmt.Errorf("failed to read batch result")
+	}
+	var replies []pluginBatchResult
+	if err := json.Unmarshal([]byte(jsonStr), &replies); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal batch result: %w", err)
+	}
+	if len(replies) != len(ops) {
+		return nil, fmt.Errorf("fs_batch returned %d results for %d calls", len(replies), len(ops))
+	}
+
+	batchResults := make([]BatchResult, len(ops))
+	for i, reply := range replies {
+		switch {
+		case reply.Error != nil:
+			batchResults[i].Err = pluginError(ops[i].Op, ops[i].Path, *reply.Error)
+		case reply.Info != nil:
+			batchResults[i].Info = &reply.Info.FileInfo
+		case reply.Entries != nil:
+			entries := make([]filesystem.FileInfo, len(reply.Entries))
+			for j := range reply.Entries {
+				entries[j] = reply.Entries[j].FileInfo
+			}
+			batchResults[i].Entries = entries
+		default:
+			batchResults[i].Data = reply.Data
+		}
+	}
+	return batchResults, nil
+}
+
+// batchEach runs the calls one at a time, for plugins without fs_batch
+func (wfs *WASMFileSystem) batchEach(ops []BatchOp) []BatchResult {
+	results := make([]BatchResult, len(ops))
+	for i, op := range ops {
+		switch op.Op {
+		case BatchStat:
+			results[i].Info, results[i].Err = wfs.Stat(op.Path)
+		case BatchRead:
+			data, err := wfs.Read(op.Path, op.Offset, op.Size)
+			if errors.Is(err, io.EOF) {
+				err = nil
+			}
+			results[i].Data, results[i].Err = data, err
+		case BatchReadDir:
+			results[i].Entries, results[i].Err = wfs.ReadDir(op.Path)
+		}
+	}
+	return results
+}
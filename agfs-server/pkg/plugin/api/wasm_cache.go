@@ -0,0 +1,237 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+	pathpkg "path"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+// CachePolicy is how long the server may keep what a WASM plugin returns,
+// as agfs-wasm-ffi's CachePolicy reports it through plugin_cache_policy
+// TTLs are in seconds; 0 disables that cache
+type CachePolicy struct {
+	AttrTTL     uint32 // Stat results
+	EntryTTL    uint32 // entries of directory listings
+	NegativeTTL uint32 // paths Stat found missing
+	DataCache   bool   // whole-file reads, kept as long as the file's Stat result
+}
+
+// pluginFileInfo is a FileInfo as agfs-wasm-ffi sends it, with the
+// per-entry hint overriding the policy's TTL
+type pluginFileInfo struct {
+	filesystem.FileInfo
+	StatValidSecs uint32
+}
+
+// cachePolicy asks the plugin how long its results may be cached
+// Plugins without plugin_cache_policy are not cached
+func (wp *WASMPlugin) cachePolicy() (*CachePolicy, error) {
+	policyFunc := wp.module.ExportedFunction("plugin_cache_policy")
+	if policyFunc == nil {
+		return nil, nil
+	}
+
+	results, err := policyFunc.Call(wp.ctx)
+	if err != nil {
+		return nil, fmt.Errorf("cache policy call failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("plugin_cache_policy returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wp.module, errPtr); ok {
+			return nil, fmt.Errorf("cache policy failed: %s", errMsg)
+		}
+		return nil, fmt.Errorf("cache policy failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wp.module, jsonPtr)
+	if !ok {
+		return nil, fmt.Errorf("failed to read cache policy")
+	}
+	var policy CachePolicy
+	if err := json.Unmarshal([]byte(jsonStr), &policy); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal cache policy: %w", err)
+	}
+	return &policy, nil
+}
+
+// statEntry is a cached Stat result
+type statEntry struct {
+	info    *filesystem.FileInfo // nil: the path does not exist
+	data    []byte               // the whole file, with DataCache; nil: not cached
+	expires time.Time
+}
+
+// statCache keeps a WASM mount's Stat results for as long as its plugin's
+// CachePolicy allows. Calls made through the mount drop what they change
+// A nil *statCache caches nothing
+type statCache struct {
+	mu      sync.Mutex
+	policy  CachePolicy
+	entries map[string]*statEntry
+	gen     uint64 // bumped by every invalidation
+}
+
+func newStatCache(policy CachePolicy) *statCache {
+	return &statCache{
+		policy:  policy,
+		entries: make(map[string]*statEntry),
+	}
+}
+
+// generation is taken before asking the plugin; results are only cached
+// if nothing was invalidated in the meantime
+func (c *statCache) generation() uint64 {
+	if c == nil {
+		return 0
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	return c.gen
+}
+
+// lookup returns the cached Stat result for path
+// found is false when nothing valid is cached; info is nil for a path
+// cached as missing
+func (c *statCache) lookup(path string) (info *filesystem.FileInfo, found bool) {
+	if c == nil {
+		return nil, false
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	entry := c.valid(pathpkg.Clean(path))
+	if entry == nil {
+		return nil, false
+	}
+	if entry.info == nil {
+		return nil, true
+	}
+	copied := *entry.info
+	return &copied, true
+}
+
+// put caches the Stat result of path for validSecs if the plugin gave
+// them, for the policy's AttrTTL otherwise
+func (c *statCache) put(gen uint64, path string, info pluginFileInfo) {
+	if c == nil {
+		return
+	}
+	c.store(gen, pathpkg.Clean(path), &info.FileInfo, info.StatValidSecs, c.policy.AttrTTL)
+}
+
+// putEntries caches the entries of a listing of dir, each for its
+// validSecs or the policy's EntryTTL
+func (c *statCache) putEntries(gen uint64, dir string, entries []pluginFileInfo) {
+	if c == nil {
+		return
+	}
+	dir = pathpkg.Clean(dir)
+	for i := range entries {
+		c.store(gen, pathpkg.Join(dir, entries[i].Name), &entries[i].FileInfo, entries[i].StatValidSecs, c.policy.EntryTTL)
+	}
+}
+
+// putMissing caches that path does not exist, for the policy's NegativeTTL
+func (c *statCache) putMissing(gen uint64, path string) {
+	if c == nil {
+		return
+	}
+	c.store(gen, pathpkg.Clean(path), nil, 0, c.policy.NegativeTTL)
+}
+
+func (c *statCache) store(gen uint64, path string, info *filesystem.FileInfo, validSecs, ttl uint32) {
+	if validSecs > 0 {
+		ttl = validSecs
+	}
+	if ttl == 0 {
+		return
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	if gen != c.gen {
+		return
+	}
+	c.entries[path] = &statEntry{
+		info:    info,
+		expires: time.Now().Add(time.Duration(ttl) * time.Second),
+	}
+}
+
+// readData returns the cached contents of the file at path
+func (c *statCache) readData(path string) ([]byte, bool) {
+	if c == nil || !c.policy.DataCache {
+		return nil, false
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	entry := c.valid(pathpkg.Clean(path))
+	if entry == nil || entry.data == nil {
+		return nil, false
+	}
+	return append([]byte(nil), entry.data...), true
+}
+
+// putData keeps the contents of the file at path for as long as its Stat
+// result is cached
+func (c *statCache) putData(gen uint64, path string, data []byte) {
+	if c == nil || !c.policy.DataCache {
+		return
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	if gen != c.gen {
+		return
+	}
+	entry := c.valid(pathpkg.Clean(path))
+	if entry == nil || entry.info == nil || entry.info.IsDir {
+		return
+	}
+	entry.data = append([]byte{}, data...)
+}
+
+// valid returns the unexpired entry of path; c.mu must be held
+func (c *statCache) valid(path string) *statEntry {
+	entry, ok := c.entries[path]
+	if !ok {
+		return nil
+	}
+	if time.Now().After(entry.expires) {
+		delete(c.entries, path)
+		return nil
+	}
+	return entry
+}
+
+// invalidate drops path, everything below it, and its parent directory,
+// whose size or modification time the change may have touched
+func (c *statCache) invalidate(path string) {
+	if c == nil {
+		return
+	}
+	path = pathpkg.Clean(path)
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	c.gen++
+	if path == "/" {
+		c.entries = make(map[string]*statEntry)
+		return
+	}
+	delete(c.entries, path)
+	delete(c.entries, pathpkg.Dir(path))
+	prefix := path + "/"
+	for p := range c.entries {
+		if strings.HasPrefix(p, prefix) {
+			delete(c.entries, p)
+		}
+	}
+}
@@ -0,0 +1,50 @@
+package api
+
+import (
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+// Capability bits reported by plugin_capabilities, as agfs-wasm-ffi's
+// Capabilities
+const (
+	CapWritable  uint32 = 1 << 0 // write, create, mkdir, remove, rename and chmod
+	CapSymlinks  uint32 = 1 << 1
+	CapHardLinks uint32 = 1 << 2
+	CapXattrs    uint32 = 1 << 3
+	CapWatch     uint32 = 1 << 4
+	CapHandles   uint32 = 1 << 5
+	CapStatfs    uint32 = 1 << 6
+	CapCopyRange uint32 = 1 << 7
+	CapAll       uint32 = 1<<8 - 1
+)
+
+// capabilities asks the plugin which optional operations it supports
+// Plugins without plugin_capabilities are assumed to support everything
+// and report what they do not implement through errors
+func (wp *WASMPlugin) capabilities() uint32 {
+	capsFunc := wp.module.ExportedFunction("plugin_capabilities")
+	if capsFunc == nil {
+		return CapAll
+	}
+
+	results, err := capsFunc.Call(wp.ctx)
+	if err != nil || len(results) < 1 {
+		return CapAll
+	}
+	return uint32(results[0])
+}
+
+// ReadOnly reports whether the plugin refuses modifying calls
+// The mount fails them without calling the plugin
+func (wp *WASMPlugin) ReadOnly() bool {
+	return wp.fileSystem.caps&CapWritable == 0
+}
+
+// checkWritable refuses a modifying call to a plugin that does not claim
+// CapWritable, with the error the plugin's own EROFS maps to (pluginError)
+func (wfs *WASMFileSystem) checkWritable(op, path string) error {
+	if wfs.caps&CapWritable != 0 {
+		return nil
+	}
+	return filesystem.NewPermissionDeniedError(op, path, "read-only filesystem")
+}
@@ -0,0 +1,139 @@
+package api
+
+import (
+	"fmt"
+	"io"
+	"os"
+	"sync"
+
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// openReadOnly is O_RDONLY, the fs_open flags of Open
+const openReadOnly = 0
+
+// wasmFile reads a file through a plugin handle from fs_open, so each
+// read fetches only the range asked for rather than the whole file
+// It implements io.ReaderAt as well as io.ReadCloser
+type wasmFile struct {
+	fs     *WASMFileSystem
+	path   string
+	readAt wazeroapi.Function
+	close  wazeroapi.Function
+
+	mu     sync.Mutex
+	handle uint32
+	offset int64 // of the next Read
+	closed bool
+}
+
+// openHandle opens path through fs_open; the caller checked that the
+// plugin exports fs_open, fs_read_at and fs_close
+func (wfs *WASMFileSystem) openHandle(openFunc wazeroapi.Function, path string) (*wasmFile, error) {
+	pathPtr, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return nil, err
+	}
+
+	results, err := wfs.call(openFunc, uint64(pathPtr), openReadOnly)
+	if err != nil {
+		return nil, fmt.Errorf("fs_open failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_open returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = handle, upper 32 bits = error pointer
+	handle := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			return nil, pluginError("open", path, errMsg)
+		}
+		return nil, fmt.Errorf("open failed")
+	}
+
+	return &wasmFile{
+		fs:     wfs,
+		path:   path,
+		readAt: wfs.module.ExportedFunction("fs_read_at"),
+		close:  wfs.module.ExportedFunction("fs_close"),
+		handle: handle,
+	}, nil
+}
+
+func (f *wasmFile) Read(p []byte) (int, error) {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	n, err := f.readAtLocked(p, f.offset)
+	f.offset += int64(n)
+	return n, err
+}
+
+// ReadAt reads len(p) bytes at off; fewer bytes come with io.EOF
+func (f *wasmFile) ReadAt(p []byte, off int64) (int, error) {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	return f.readAtLocked(p, off)
+}
+
+func (f *wasmFile) readAtLocked(p []byte, off int64) (int, error) {
+	if f.closed {
+		return 0, &os.PathError{Op: "read", Path: f.path, Err: os.ErrClosed}
+	}
+	if len(p) == 0 {
+		return 0, nil
+	}
+
+	results, err := f.fs.call(f.readAt, uint64(f.handle), uint64(off), uint64(len(p)))
+	if err != nil {
+		return 0, fmt.Errorf("fs_read_at failed: %w", err)
+	}
+	if len(results) < 1 {
+		return 0, fmt.Errorf("fs_read_at returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = pointer, upper 32 bits = size, or
+	// (0, error string pointer)
+	dataPtr := uint32(results[0] & 0xFFFFFFFF)
+	dataSize := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if dataPtr == 0 {
+		if errMsg, ok := readStringFromMemory(f.fs.module, dataSize); ok {
+			return 0, pluginError("read", f.path, errMsg)
+		}
+		return 0, fmt.Errorf("read failed")
+	}
+	data, ok := f.fs.module.Memory().Read(dataPtr, dataSize)
+	if !ok {
+		return 0, fmt.Errorf("failed to read data from memory")
+	}
+
+	n := copy(p, data)
+	if n < len(p) {
+		return n, io.EOF
+	}
+	return n, nil
+}
+
+// Close releases the plugin handle, which the plugin does even when it
+// reports an error
+func (f *wasmFile) Close() error {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	if f.closed {
+		return &os.PathError{Op: "close", Path: f.path, Err: os.ErrClosed}
+	}
+	f.closed = true
+
+	results, err := f.fs.call(f.close, uint64(f.handle))
+	if err != nil {
+		return fmt.Errorf("fs_close failed: %w", err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(f.fs.module, uint32(results[0])); ok {
+			return pluginError("close", f.path, errMsg)
+		}
+		return fmt.Errorf("close failed")
+	}
+	return nil
+}
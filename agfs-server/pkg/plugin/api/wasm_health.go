@@ -0,0 +1,101 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin"
+	log "github.com/sirupsen/logrus"
+)
+
+// HealthIntervalKey is the mount config key giving, in seconds, how often
+// a WASM plugin's backend is probed through plugin_health. Unset probes
+// every 30 seconds; 0 never probes
+const HealthIntervalKey = "health_interval_secs"
+
+const defaultHealthInterval = 30 * time.Second
+
+// parseHealthInterval reads health_interval_secs from a mount config
+func parseHealthInterval(config map[string]interface{}) (time.Duration, error) {
+	if _, ok := config[HealthIntervalKey]; !ok {
+		return defaultHealthInterval, nil
+	}
+	return parseSecs(config, HealthIntervalKey)
+}
+
+// probeHealth calls plugin_health; an error counts as unhealthy
+func (wp *WASMPlugin) probeHealth() plugin.HealthStatus {
+	healthFunc := wp.module.ExportedFunction("plugin_health")
+
+	// Not a filesystem call: it neither sets the call context nor counts
+	// against idle trimming
+	wp.fileSystem.callMu.Lock()
+	defer wp.fileSystem.callMu.Unlock()
+	results, err := healthFunc.Call(wp.ctx)
+	if err != nil {
+		return plugin.HealthStatus{State: plugin.Unhealthy, Message: fmt.Sprintf("health call failed: %v", err)}
+	}
+	if len(results) < 1 {
+		return plugin.HealthStatus{State: plugin.Unhealthy, Message: "plugin_health returned invalid results"}
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		errMsg, _ := readStringFromMemory(wp.module, errPtr)
+		return plugin.HealthStatus{State: plugin.Unhealthy, Message: errMsg}
+	}
+
+	jsonStr, ok := readStringFromMemory(wp.module, jsonPtr)
+	if !ok {
+		return plugin.HealthStatus{State: plugin.Unhealthy, Message: "failed to read health status"}
+	}
+	var status plugin.HealthStatus
+	if err := json.Unmarshal([]byte(jsonStr), &status); err != nil {
+		return plugin.HealthStatus{State: plugin.Unhealthy, Message: fmt.Sprintf("failed to unmarshal health status: %v", err)}
+	}
+	return status
+}
+
+// Health returns the result of the last probe
+// ok is false until the first probe, and for plugins that are not probed
+func (wp *WASMPlugin) Health() (status plugin.HealthStatus, ok bool) {
+	wp.healthMu.Lock()
+	defer wp.healthMu.Unlock()
+	if wp.health == nil {
+		return plugin.HealthStatus{}, false
+	}
+	return *wp.health, true
+}
+
+// checkHealth probes the plugin right away and then every interval, until
+// stop is closed, logging whenever its state changes
+func (wp *WASMPlugin) checkHealth(interval time.Duration, stop <-chan struct{}) {
+	ticker := time.NewTicker(interval)
+	defer ticker.Stop()
+
+	for {
+		status := wp.probeHealth()
+
+		wp.healthMu.Lock()
+		prev := wp.health
+		wp.health = &status
+		wp.healthMu.Unlock()
+
+		if prev == nil && status.State != plugin.Healthy || prev != nil && prev.State != status.State {
+			if status.State == plugin.Healthy {
+				log.Infof("Plugin %s is healthy again", wp.name)
+			} else {
+				log.Warnf("Plugin %s is %s: %s", wp.name, status.State, status.Message)
+			}
+		}
+
+		select {
+		case <-stop:
+			return
+		case <-ticker.C:
+		}
+	}
+}
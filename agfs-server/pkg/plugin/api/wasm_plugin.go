@@ -3,10 +3,19 @@ package api
 import (
 	"context"
 	"encoding/json"
+	"errors"
 	"fmt"
 	"io"
+	"os"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"syscall"
+	"time"
 
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin"
+	pluginconfig "github.com/c4pt0r/agfs/agfs-server/pkg/plugin/config"
 	log "github.com/sirupsen/logrus"
 	wazeroapi "github.com/tetratelabs/wazero/api"
 )
@@ -17,16 +26,40 @@ type WASMPlugin struct {
 	module     wazeroapi.Module
 	name       string
 	fileSystem *WASMFileSystem
+	host       *HostServices
+	stop       chan struct{} // closed on Shutdown, ends background tasks
+	stopOnce   sync.Once
+
+	healthMu sync.Mutex
+	health   *plugin.HealthStatus // last plugin_health probe; nil: none yet
 }
 
 // WASMFileSystem implements filesystem.FileSystem by delegating to WASM functions
 type WASMFileSystem struct {
-	ctx    context.Context
-	module wazeroapi.Module
+	ctx          context.Context
+	module       wazeroapi.Module
+	user         *CallUser // from call_user; nil: no caller identity
+	maxWriteSize int64     // from max_write_size; 0: unlimited
+	cancel       *HostCancel
+	caps         uint32      // from plugin_capabilities
+	cache        *statCache  // from plugin_cache_policy; nil: nothing cached
+	watching     bool        // fs_watch on "/" accepted; events invalidate cache
+	tracer       *callTracer // from tracing; nil: calls are not traced
+
+	// Guest calls run one at a time, so background tasks such as idle
+	// trimming never run inside a filesystem call
+	callMu   sync.Mutex
+	lastCall atomic.Int64 // unix nanos of the last filesystem call
 }
 
+// MaxWriteSizeKey is the mount config key capping the payload of a single
+// write, e.g. "16MB". Larger writes are refused before they are copied
+// into plugin memory
+const MaxWriteSizeKey = "max_write_size"
+
 // NewWASMPlugin creates a new WASM plugin wrapper
-func NewWASMPlugin(ctx context.Context, module wazeroapi.Module) (*WASMPlugin, error) {
+// host is configured from the mount config on Initialize
+func NewWASMPlugin(ctx context.Context, module wazeroapi.Module, host *HostServices) (*WASMPlugin, error) {
 	// Verify required functions exist
 	if module.ExportedFunction("plugin_new") == nil {
 		return nil, fmt.Errorf("WASM module missing required function: plugin_new")
@@ -60,7 +93,11 @@ func NewWASMPlugin(ctx context.Context, module wazeroapi.Module) (*WASMPlugin, e
 		fileSystem: &WASMFileSystem{
 			ctx:    ctx,
 			module: module,
+			cancel: host.Cancel,
+			caps:   CapAll,
 		},
+		host: host,
+		stop: make(chan struct{}),
 	}
 
 	return wp, nil
@@ -73,6 +110,25 @@ func (wp *WASMPlugin) Name() string {
 
 // Validate validates the plugin configuration
 func (wp *WASMPlugin) Validate(config map[string]interface{}) error {
+	if err := wp.host.Validate(config); err != nil {
+		return err
+	}
+	if _, err := parseCallUser(config); err != nil {
+		return err
+	}
+	if _, err := pluginconfig.GetSizeConfig(config, MaxWriteSizeKey, 0); err != nil {
+		return err
+	}
+	if _, err := parseSecs(config, IdleTrimKey); err != nil {
+		return err
+	}
+	if _, err := parseHealthInterval(config); err != nil {
+		return err
+	}
+	if _, _, err := parseTracing(config); err != nil {
+		return err
+	}
+
 	validateFunc := wp.module.ExportedFunction("plugin_validate")
 	if validateFunc == nil {
 		// If validate function is not exported, assume validation passes
@@ -110,6 +166,37 @@ func (wp *WASMPlugin) Validate(config map[string]interface{}) error {
 
 // Initialize initializes the plugin with configuration
 func (wp *WASMPlugin) Initialize(config map[string]interface{}) error {
+	// Set before the plugin runs, so it can use host services while initializing
+	if err := wp.host.Configure(config); err != nil {
+		return err
+	}
+	user, err := parseCallUser(config)
+	if err != nil {
+		return err
+	}
+	wp.fileSystem.user = user
+	maxWriteSize, err := pluginconfig.GetSizeConfig(config, MaxWriteSizeKey, 0)
+	if err != nil {
+		return err
+	}
+	wp.fileSystem.maxWriteSize = maxWriteSize
+	idleTrim, err := parseSecs(config, IdleTrimKey)
+	if err != nil {
+		return err
+	}
+	healthInterval, err := parseHealthInterval(config)
+	if err != nil {
+		return err
+	}
+	tracing, traceEndpoint, err := parseTracing(config)
+	if err != nil {
+		return err
+	}
+	if tracing {
+		wp.fileSystem.tracer = newCallTracer(wp.name, traceEndpoint)
+	}
+	wp.fileSystem.lastCall.Store(time.Now().UnixNano())
+
 	initFunc := wp.module.ExportedFunction("plugin_initialize")
 	if initFunc == nil {
 		// If initialize function is not exported, assume initialization succeeds
@@ -142,6 +229,87 @@ func (wp *WASMPlugin) Initialize(config map[string]interface{}) error {
 		return fmt.Errorf("initialization failed")
 	}
 
+	// Hand back the state saved when the mount was last shut down
+	if err := wp.restoreState(); err != nil {
+		return err
+	}
+
+	wp.fileSystem.caps = wp.capabilities()
+
+	policy, err := wp.cachePolicy()
+	if err != nil {
+		return err
+	}
+	if policy != nil {
+		wp.fileSystem.cache = newStatCache(*policy)
+		wp.fileSystem.watching = wp.fileSystem.caps&CapWatch != 0 && wp.watchRoot()
+	}
+
+	if idleTrim > 0 && wp.module.ExportedFunction("plugin_trim") != nil {
+		go wp.trimWhenIdle(idleTrim, wp.stop)
+	}
+	if healthInterval > 0 && wp.module.ExportedFunction("plugin_health") != nil {
+		go wp.checkHealth(healthInterval, wp.stop)
+	}
+	return nil
+}
+
+// Reconfigure hands a new configuration to the running plugin through
+// plugin_reconfigure, along with the host services, call_user,
+// max_write_size and tracing. Idle trimming, health checks and the cache
+// policy keep the settings of the mount. Plugins that do not export
+// plugin_reconfigure return a not supported error, untouched
+func (wp *WASMPlugin) Reconfigure(config map[string]interface{}) error {
+	reconfigureFunc := wp.module.ExportedFunction("plugin_reconfigure")
+	if reconfigureFunc == nil {
+		return filesystem.NewNotSupportedError("reconfigure", "/")
+	}
+	user, err := parseCallUser(config)
+	if err != nil {
+		return err
+	}
+	maxWriteSize, err := pluginconfig.GetSizeConfig(config, MaxWriteSizeKey, 0)
+	if err != nil {
+		return err
+	}
+	tracing, traceEndpoint, err := parseTracing(config)
+	if err != nil {
+		return err
+	}
+	configJSON, err := json.Marshal(config)
+	if err != nil {
+		return fmt.Errorf("failed to marshal config: %w", err)
+	}
+
+	// Not inside a filesystem call, which would see half of the change
+	wfs := wp.fileSystem
+	wfs.callMu.Lock()
+	defer wfs.callMu.Unlock()
+
+	if err := wp.host.Configure(config); err != nil {
+		return err
+	}
+	configPtr, err := writeStringToMemory(wp.module, string(configJSON))
+	if err != nil {
+		return fmt.Errorf("failed to write config to memory: %w", err)
+	}
+	results, err := reconfigureFunc.Call(wp.ctx, uint64(configPtr))
+	if err != nil {
+		return fmt.Errorf("reconfigure call failed: %w", err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(wp.module, uint32(results[0])); ok {
+			return pluginError("reconfigure", "/", errMsg)
+		}
+		return fmt.Errorf("reconfigure failed")
+	}
+
+	wfs.user = user
+	wfs.maxWriteSize = maxWriteSize
+	wfs.tracer = nil
+	if tracing {
+		wfs.tracer = newCallTracer(wp.name, traceEndpoint)
+	}
 	return nil
 }
 
@@ -174,6 +342,20 @@ func (wp *WASMPlugin) GetReadme() string {
 
 // Shutdown shuts down the plugin
 func (wp *WASMPlugin) Shutdown() error {
+	wp.stopOnce.Do(func() { close(wp.stop) })
+
+	// Host resources are released after the plugin, whatever it reports
+	defer func() {
+		if err := wp.host.Close(); err != nil {
+			log.Warnf("Failed to release host resources of %s: %v", wp.name, err)
+		}
+	}()
+
+	// Keep the plugin's state for its next initialize
+	if err := wp.saveState(); err != nil {
+		log.Warnf("Failed to save state of %s: %v", wp.name, err)
+	}
+
 	shutdownFunc := wp.module.ExportedFunction("plugin_shutdown")
 	if shutdownFunc == nil {
 		return nil
@@ -198,6 +380,11 @@ func (wp *WASMPlugin) Shutdown() error {
 // WASMFileSystem implementations
 
 func (wfs *WASMFileSystem) Create(path string) error {
+	if err := wfs.checkWritable("create", path); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(path)
+
 	createFunc := wfs.module.ExportedFunction("fs_create")
 	if createFunc == nil {
 		return fmt.Errorf("fs_create not implemented")
@@ -208,14 +395,14 @@ func (wfs *WASMFileSystem) Create(path string) error {
 		return err
 	}
 
-	results, err := createFunc.Call(wfs.ctx, uint64(pathPtr))
+	results, err := wfs.call(createFunc, uint64(pathPtr))
 	if err != nil {
 		return fmt.Errorf("fs_create failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("create", path, errMsg)
 		}
 		return fmt.Errorf("create failed")
 	}
@@ -224,6 +411,11 @@ func (wfs *WASMFileSystem) Create(path string) error {
 }
 
 func (wfs *WASMFileSystem) Mkdir(path string, perm uint32) error {
+	if err := wfs.checkWritable("mkdir", path); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(path)
+
 	mkdirFunc := wfs.module.ExportedFunction("fs_mkdir")
 	if mkdirFunc == nil {
 		return fmt.Errorf("fs_mkdir not implemented")
@@ -234,14 +426,14 @@ func (wfs *WASMFileSystem) Mkdir(path string, perm uint32) error {
 		return err
 	}
 
-	results, err := mkdirFunc.Call(wfs.ctx, uint64(pathPtr), uint64(perm))
+	results, err := wfs.call(mkdirFunc, uint64(pathPtr), uint64(perm))
 	if err != nil {
 		return fmt.Errorf("fs_mkdir failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("mkdir", path, errMsg)
 		}
 		return fmt.Errorf("mkdir failed")
 	}
@@ -250,6 +442,11 @@ func (wfs *WASMFileSystem) Mkdir(path string, perm uint32) error {
 }
 
 func (wfs *WASMFileSystem) Remove(path string) error {
+	if err := wfs.checkWritable("remove", path); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(path)
+
 	removeFunc := wfs.module.ExportedFunction("fs_remove")
 	if removeFunc == nil {
 		return fmt.Errorf("fs_remove not implemented")
@@ -260,14 +457,14 @@ func (wfs *WASMFileSystem) Remove(path string) error {
 		return err
 	}
 
-	results, err := removeFunc.Call(wfs.ctx, uint64(pathPtr))
+	results, err := wfs.call(removeFunc, uint64(pathPtr))
 	if err != nil {
 		return fmt.Errorf("fs_remove failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("remove", path, errMsg)
 		}
 		return fmt.Errorf("remove failed")
 	}
@@ -276,6 +473,11 @@ func (wfs *WASMFileSystem) Remove(path string) error {
 }
 
 func (wfs *WASMFileSystem) RemoveAll(path string) error {
+	if err := wfs.checkWritable("remove_all", path); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(path)
+
 	removeAllFunc := wfs.module.ExportedFunction("fs_remove_all")
 	if removeAllFunc == nil {
 		// Fall back to Remove if RemoveAll not implemented
@@ -287,14 +489,14 @@ func (wfs *WASMFileSystem) RemoveAll(path string) error {
 		return err
 	}
 
-	results, err := removeAllFunc.Call(wfs.ctx, uint64(pathPtr))
+	results, err := wfs.call(removeAllFunc, uint64(pathPtr))
 	if err != nil {
 		return fmt.Errorf("fs_remove_all failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("remove_all", path, errMsg)
 		}
 		return fmt.Errorf("remove_all failed")
 	}
@@ -302,18 +504,38 @@ func (wfs *WASMFileSystem) RemoveAll(path string) error {
 	return nil
 }
 
+// Read returns io.EOF along with the data when the plugin reports the end
+// of the file, which only plugins exporting fs_read_result can
 func (wfs *WASMFileSystem) Read(path string, offset int64, size int64) ([]byte, error) {
-	readFunc := wfs.module.ExportedFunction("fs_read")
+	// fs_read_result prefixes the data with a flag byte, 1 at end of file
+	readFunc := wfs.module.ExportedFunction("fs_read_result")
+	withEOF := readFunc != nil
+	if !withEOF {
+		readFunc = wfs.module.ExportedFunction("fs_read")
+	}
 	if readFunc == nil {
 		return nil, fmt.Errorf("fs_read not implemented")
 	}
 
+	// A whole file ends at the end of the file
+	wholeFile := offset == 0 && size < 0
+	if wholeFile {
+		wfs.drainEvents()
+		if data, ok := wfs.cache.readData(path); ok {
+			if withEOF {
+				return data, io.EOF
+			}
+			return data, nil
+		}
+	}
+	gen := wfs.cache.generation()
+
 	pathPtr, err := writeStringToMemory(wfs.module, path)
 	if err != nil {
 		return nil, err
 	}
 
-	results, err := readFunc.Call(wfs.ctx, uint64(pathPtr), uint64(offset), uint64(size))
+	results, err := wfs.call(readFunc, uint64(pathPtr), uint64(offset), uint64(size))
 	if err != nil {
 		return nil, fmt.Errorf("fs_read failed: %w", err)
 	}
@@ -327,6 +549,12 @@ func (wfs *WASMFileSystem) Read(path string, offset int64, size int64) ([]byte,
 	dataPtr := uint32(packed & 0xFFFFFFFF)
 	dataSize := uint32((packed >> 32) & 0xFFFFFFFF)
 
+	// (0, error string pointer) from plugins that report the cause
+	if dataPtr == 0 && dataSize != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, dataSize); ok {
+			return nil, pluginError("read", path, errMsg)
+		}
+	}
 	if dataPtr == 0 {
 		return nil, fmt.Errorf("read failed")
 	}
@@ -336,15 +564,43 @@ func (wfs *WASMFileSystem) Read(path string, offset int64, size int64) ([]byte,
 		return nil, fmt.Errorf("failed to read data from memory")
 	}
 
+	eof := false
+	if withEOF {
+		if len(data) == 0 || data[0] > 1 {
+			return nil, fmt.Errorf("fs_read_result returned an invalid payload")
+		}
+		eof = data[0] == 1
+		data = data[1:]
+	}
+
+	if wholeFile {
+		wfs.cache.putData(gen, path, data)
+	}
+	if eof {
+		return data, io.EOF
+	}
 	return data, nil
 }
 
 func (wfs *WASMFileSystem) Write(path string, data []byte) ([]byte, error) {
+	if err := wfs.checkWritable("write", path); err != nil {
+		return nil, err
+	}
+	defer wfs.cache.invalidate(path)
+
+	// fs_write_result tells a short write and "no response" apart
+	resultFunc := wfs.module.ExportedFunction("fs_write_result")
 	writeFunc := wfs.module.ExportedFunction("fs_write")
-	if writeFunc == nil {
+	if resultFunc == nil && writeFunc == nil {
 		return nil, fmt.Errorf("fs_write not implemented")
 	}
 
+	// Refuse oversized payloads before they are copied into guest memory,
+	// with the EFBIG error the plugin's own check maps to (pluginError)
+	if wfs.maxWriteSize > 0 && int64(len(data)) > wfs.maxWriteSize {
+		return nil, &os.PathError{Op: "write", Path: path, Err: syscall.EFBIG}
+	}
+
 	pathPtr, err := writeStringToMemory(wfs.module, path)
 	if err != nil {
 		return nil, err
@@ -355,7 +611,11 @@ func (wfs *WASMFileSystem) Write(path string, data []byte) ([]byte, error) {
 		return nil, err
 	}
 
-	results, err := writeFunc.Call(wfs.ctx, uint64(pathPtr), uint64(dataPtr), uint64(len(data)))
+	if resultFunc != nil {
+		return wfs.writeWithResult(resultFunc, path, pathPtr, dataPtr, len(data))
+	}
+
+	results, err := wfs.call(writeFunc, uint64(pathPtr), uint64(dataPtr), uint64(len(data)))
 	if err != nil {
 		return nil, fmt.Errorf("fs_write failed: %w", err)
 	}
@@ -369,6 +629,12 @@ func (wfs *WASMFileSystem) Write(path string, data []byte) ([]byte, error) {
 	responsePtr := uint32(packed & 0xFFFFFFFF)
 	responseSize := uint32((packed >> 32) & 0xFFFFFFFF)
 
+	// (0, error string pointer) from plugins that report the cause
+	if responsePtr == 0 && responseSize != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, responseSize); ok {
+			return nil, pluginError("write", path, errMsg)
+		}
+	}
 	if responsePtr == 0 {
 		return nil, fmt.Errorf("write failed")
 	}
@@ -382,7 +648,65 @@ func (wfs *WASMFileSystem) Write(path string, data []byte) ([]byte, error) {
 	return response, nil
 }
 
+// writeResult is the JSON fs_write_result returns, as agfs-wasm-ffi's
+// WriteResult
+type writeResult struct {
+	BytesWritten int64
+	Response     []byte // base64 in JSON; absent for ordinary files
+}
+
+// writeWithResult writes through fs_write_result, with the payload
+// already in plugin memory; storing fewer bytes than sent is
+// io.ErrShortWrite
+func (wfs *WASMFileSystem) writeWithResult(resultFunc wazeroapi.Function, path string, pathPtr, dataPtr uint32, size int) ([]byte, error) {
+	results, err := wfs.call(resultFunc, uint64(pathPtr), uint64(dataPtr), uint64(size))
+	if err != nil {
+		return nil, fmt.Errorf("fs_write_result failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_write_result returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			return nil, pluginError("write", path, errMsg)
+		}
+		return nil, fmt.Errorf("write failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	if !ok {
+		return nil, fmt.Errorf("failed to read write result from memory")
+	}
+	var result writeResult
+	if err := json.Unmarshal([]byte(jsonStr), &result); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal write result: %w", err)
+	}
+	if result.BytesWritten < int64(size) {
+		return result.Response, &os.PathError{Op: "write", Path: path, Err: io.ErrShortWrite}
+	}
+	return result.Response, nil
+}
+
 func (wfs *WASMFileSystem) ReadDir(path string) ([]filesystem.FileInfo, error) {
+	// A caching mount lists through fs_readdir_plus, whose entries answer
+	// the Stat calls that usually follow a listing
+	if wfs.cache != nil {
+		if plusFunc := wfs.module.ExportedFunction("fs_readdir_plus"); plusFunc != nil {
+			return wfs.readDirPlus(plusFunc, path)
+		}
+	}
+	openFunc := wfs.module.ExportedFunction("fs_readdir_open")
+	if openFunc != nil && wfs.module.ExportedFunction("fs_readdir_next") != nil && wfs.module.ExportedFunction("fs_readdir_close") != nil {
+		return wfs.readDirStream(openFunc, path)
+	}
+	if pageFunc := wfs.module.ExportedFunction("fs_readdir_page"); pageFunc != nil {
+		return wfs.readDirPaged(pageFunc, path)
+	}
+
 	readDirFunc := wfs.module.ExportedFunction("fs_readdir")
 	if readDirFunc == nil {
 		return nil, fmt.Errorf("fs_readdir not implemented")
@@ -393,7 +717,7 @@ func (wfs *WASMFileSystem) ReadDir(path string) ([]filesystem.FileInfo, error) {
 		return nil, err
 	}
 
-	results, err := readDirFunc.Call(wfs.ctx, uint64(pathPtr))
+	results, err := wfs.call(readDirFunc, uint64(pathPtr))
 	if err != nil {
 		return nil, fmt.Errorf("fs_readdir failed: %w", err)
 	}
@@ -410,7 +734,7 @@ func (wfs *WASMFileSystem) ReadDir(path string) ([]filesystem.FileInfo, error) {
 	// Check for error
 	if errPtr != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
-			return nil, fmt.Errorf("%s", errMsg)
+			return nil, pluginError("readdir", path, errMsg)
 		}
 		return nil, fmt.Errorf("readdir failed")
 	}
@@ -432,8 +756,253 @@ func (wfs *WASMFileSystem) ReadDir(path string) ([]filesystem.FileInfo, error) {
 	return fileInfos, nil
 }
 
+// pluginError turns an error string from a plugin into a typed filesystem
+// error when it carries a wire code ("ENOENT: ...", as agfs-wasm-ffi's
+// Error::to_wire writes them), so callers can test it with errors.Is
+// Codes without a filesystem sentinel wrap the matching syscall or context
+// error. Strings without a known code are passed through unchanged
+func pluginError(op, path, msg string) error {
+	code, detail, found := strings.Cut(msg, ": ")
+	if !found {
+		code = msg
+	}
+	switch code {
+	case "ENOENT":
+		return filesystem.NewNotFoundError(op, path)
+	case "EACCES":
+		// The SDK sends its own "permission denied" as the detail
+		if detail == filesystem.ErrPermissionDenied.Error() {
+			detail = ""
+		}
+		return filesystem.NewPermissionDeniedError(op, path, detail)
+	case "EROFS":
+		return filesystem.NewPermissionDeniedError(op, path, "read-only filesystem")
+	case "EEXIST":
+		return filesystem.NewAlreadyExistsError("file", path)
+	case "ENOTDIR":
+		return filesystem.NewNotDirectoryError(path)
+	case "EINVAL":
+		return filesystem.NewInvalidArgumentError("path", path, detail)
+	case "ENOSYS":
+		// The detail is the operation the plugin does not implement
+		if detail == "" {
+			detail = op
+		}
+		return filesystem.NewNotSupportedError(detail, path)
+	case "EISDIR":
+		return &os.PathError{Op: op, Path: path, Err: syscall.EISDIR}
+	case "ENOTEMPTY":
+		return &os.PathError{Op: op, Path: path, Err: syscall.ENOTEMPTY}
+	case "EFBIG":
+		return &os.PathError{Op: op, Path: path, Err: syscall.EFBIG}
+	case "EIO":
+		return fmt.Errorf("%s %s: %s: %w", op, path, detail, syscall.EIO)
+	case "ETIMEDOUT":
+		return fmt.Errorf("%s %s: %w", op, path, context.DeadlineExceeded)
+	case "ECANCELED":
+		return fmt.Errorf("%s %s: %w", op, path, context.Canceled)
+	case "EOTHER":
+		return fmt.Errorf("%s", detail)
+	}
+	return fmt.Errorf("%s", msg)
+}
+
+// readDirPlus lists a directory through fs_readdir_plus and caches every
+// entry as the Stat result of its path
+func (wfs *WASMFileSystem) readDirPlus(plusFunc wazeroapi.Function, path string) ([]filesystem.FileInfo, error) {
+	gen := wfs.cache.generation()
+
+	pathPtr, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return nil, err
+	}
+
+	results, err := wfs.call(plusFunc, uint64(pathPtr))
+	if err != nil {
+		return nil, fmt.Errorf("fs_readdir_plus failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_readdir_plus returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			return nil, pluginError("readdir", path, errMsg)
+		}
+		return nil, fmt.Errorf("readdir failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	if !ok {
+		return nil, fmt.Errorf("failed to read readdir result")
+	}
+	var entries []pluginFileInfo
+	if err := json.Unmarshal([]byte(jsonStr), &entries); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal readdir result: %w", err)
+	}
+
+	wfs.cache.putEntries(gen, path, entries)
+	fileInfos := make([]filesystem.FileInfo, len(entries))
+	for i := range entries {
+		fileInfos[i] = entries[i].FileInfo
+	}
+	return fileInfos, nil
+}
+
+// readDirPageSize is the number of entries requested per fs_readdir_page call
+const readDirPageSize = 1000
+
+// readDirPaged lists a directory through fs_readdir_page, one page at a time,
+// so the plugin never has to build the whole listing in one reply
+func (wfs *WASMFileSystem) readDirPaged(pageFunc wazeroapi.Function, path string) ([]filesystem.FileInfo, error) {
+	entries := []filesystem.FileInfo{}
+	token := ""
+	for {
+		pathPtr, err := writeStringToMemory(wfs.module, path)
+		if err != nil {
+			return nil, err
+		}
+		tokenPtr, err := writeStringToMemory(wfs.module, token)
+		if err != nil {
+			return nil, err
+		}
+
+		results, err := wfs.call(pageFunc, uint64(pathPtr), uint64(tokenPtr), readDirPageSize)
+		if err != nil {
+			return nil, fmt.Errorf("fs_readdir_page failed: %w", err)
+		}
+		if len(results) < 1 {
+			return nil, fmt.Errorf("fs_readdir_page returned invalid results")
+		}
+
+		// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+		jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+		errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+		if errPtr != 0 {
+			if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+				return nil, pluginError("readdir", path, errMsg)
+			}
+			return nil, fmt.Errorf("readdir failed")
+		}
+
+		jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+		if !ok {
+			return nil, fmt.Errorf("failed to read readdir page")
+		}
+		var page struct {
+			Entries []filesystem.FileInfo
+			Next    *string
+		}
+		if err := json.Unmarshal([]byte(jsonStr), &page); err != nil {
+			return nil, fmt.Errorf("failed to unmarshal readdir page: %w", err)
+		}
+
+		entries = append(entries, page.Entries...)
+		if page.Next == nil {
+			return entries, nil
+		}
+		token = *page.Next
+	}
+}
+
+// readDirStream lists a directory through a cursor from fs_readdir_open,
+// pulling readDirPageSize entries per fs_readdir_next call, so a plugin
+// producing entries lazily never holds the whole listing
+// The plugin releases the cursor on the empty batch ending the listing
+// and on its own errors; the host closes it only when giving up early
+func (wfs *WASMFileSystem) readDirStream(openFunc wazeroapi.Function, path string) ([]filesystem.FileInfo, error) {
+	pathPtr, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return nil, err
+	}
+
+	results, err := wfs.call(openFunc, uint64(pathPtr))
+	if err != nil {
+		return nil, fmt.Errorf("fs_readdir_open failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_readdir_open returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = cursor, upper 32 bits = error pointer
+	cursor := uint32(results[0] & 0xFFFFFFFF)
+	errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+	if errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			return nil, pluginError("readdir", path, errMsg)
+		}
+		return nil, fmt.Errorf("readdir failed")
+	}
+
+	nextFunc := wfs.module.ExportedFunction("fs_readdir_next")
+	entries := []filesystem.FileInfo{}
+	for {
+		results, err := wfs.call(nextFunc, uint64(cursor), readDirPageSize)
+		if err != nil {
+			wfs.closeReadDirCursor(cursor, path)
+			return nil, fmt.Errorf("fs_readdir_next failed: %w", err)
+		}
+		if len(results) < 1 {
+			wfs.closeReadDirCursor(cursor, path)
+			return nil, fmt.Errorf("fs_readdir_next returned invalid results")
+		}
+
+		// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+		jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+		errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF)
+		if errPtr != 0 {
+			if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+				return nil, pluginError("readdir", path, errMsg)
+			}
+			return nil, fmt.Errorf("readdir failed")
+		}
+
+		jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+		if !ok {
+			wfs.closeReadDirCursor(cursor, path)
+			return nil, fmt.Errorf("failed to read readdir batch")
+		}
+		var batch []filesystem.FileInfo
+		if err := json.Unmarshal([]byte(jsonStr), &batch); err != nil {
+			wfs.closeReadDirCursor(cursor, path)
+			return nil, fmt.Errorf("failed to unmarshal readdir batch: %w", err)
+		}
+		if len(batch) == 0 {
+			return entries, nil
+		}
+		entries = append(entries, batch...)
+	}
+}
+
+// closeReadDirCursor releases a cursor from fs_readdir_open that was not
+// read to the end
+func (wfs *WASMFileSystem) closeReadDirCursor(cursor uint32, path string) {
+	closeFunc := wfs.module.ExportedFunction("fs_readdir_close")
+	results, err := wfs.call(closeFunc, uint64(cursor))
+	if err == nil && len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
+			err = fmt.Errorf("%s", errMsg)
+		}
+	}
+	if err != nil {
+		log.Warnf("Failed to close readdir cursor of %s: %v", path, err)
+	}
+}
+
 func (wfs *WASMFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
 	log.Debugf("WASM Stat called with path: %s", path)
+	wfs.drainEvents()
+	if info, found := wfs.cache.lookup(path); found {
+		if info == nil {
+			return nil, filesystem.NewNotFoundError("stat", path)
+		}
+		return info, nil
+	}
+	gen := wfs.cache.generation()
+
 	statFunc := wfs.module.ExportedFunction("fs_stat")
 	if statFunc == nil {
 		return nil, fmt.Errorf("fs_stat not implemented")
@@ -446,7 +1015,7 @@ func (wfs *WASMFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
 	}
 
 	log.Debugf("Calling fs_stat WASM function with pathPtr=%d", pathPtr)
-	results, err := statFunc.Call(wfs.ctx, uint64(pathPtr))
+	results, err := wfs.call(statFunc, uint64(pathPtr))
 	if err != nil {
 		log.Errorf("fs_stat WASM call failed: %v", err)
 		return nil, fmt.Errorf("fs_stat failed: %w", err)
@@ -465,7 +1034,11 @@ func (wfs *WASMFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
 	// Check for error
 	if errPtr != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
-			return nil, fmt.Errorf("%s", errMsg)
+			err := pluginError("stat", path, errMsg)
+			if errors.Is(err, filesystem.ErrNotFound) {
+				wfs.cache.putMissing(gen, path)
+			}
+			return nil, err
 		}
 		return nil, fmt.Errorf("stat failed")
 	}
@@ -479,15 +1052,22 @@ func (wfs *WASMFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
 		return nil, fmt.Errorf("failed to read stat result")
 	}
 
-	var fileInfo filesystem.FileInfo
+	var fileInfo pluginFileInfo
 	if err := json.Unmarshal([]byte(jsonStr), &fileInfo); err != nil {
 		return nil, fmt.Errorf("failed to unmarshal stat result: %w", err)
 	}
 
-	return &fileInfo, nil
+	wfs.cache.put(gen, path, fileInfo)
+	return &fileInfo.FileInfo, nil
 }
 
 func (wfs *WASMFileSystem) Rename(oldPath, newPath string) error {
+	if err := wfs.checkWritable("rename", oldPath); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(newPath)
+	defer wfs.cache.invalidate(oldPath)
+
 	renameFunc := wfs.module.ExportedFunction("fs_rename")
 	if renameFunc == nil {
 		return fmt.Errorf("fs_rename not implemented")
@@ -503,14 +1083,14 @@ func (wfs *WASMFileSystem) Rename(oldPath, newPath string) error {
 		return err
 	}
 
-	results, err := renameFunc.Call(wfs.ctx, uint64(oldPathPtr), uint64(newPathPtr))
+	results, err := wfs.call(renameFunc, uint64(oldPathPtr), uint64(newPathPtr))
 	if err != nil {
 		return fmt.Errorf("fs_rename failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("rename", oldPath, errMsg)
 		}
 		return fmt.Errorf("rename failed")
 	}
@@ -519,6 +1099,11 @@ func (wfs *WASMFileSystem) Rename(oldPath, newPath string) error {
 }
 
 func (wfs *WASMFileSystem) Chmod(path string, mode uint32) error {
+	if err := wfs.checkWritable("chmod", path); err != nil {
+		return err
+	}
+	defer wfs.cache.invalidate(path)
+
 	chmodFunc := wfs.module.ExportedFunction("fs_chmod")
 	if chmodFunc == nil {
 		// Chmod is optional, silently ignore if not implemented
@@ -530,14 +1115,14 @@ func (wfs *WASMFileSystem) Chmod(path string, mode uint32) error {
 		return err
 	}
 
-	results, err := chmodFunc.Call(wfs.ctx, uint64(pathPtr), uint64(mode))
+	results, err := wfs.call(chmodFunc, uint64(pathPtr), uint64(mode))
 	if err != nil {
 		return fmt.Errorf("fs_chmod failed: %w", err)
 	}
 
 	if len(results) > 0 && results[0] != 0 {
 		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
-			return fmt.Errorf("%s", errMsg)
+			return pluginError("chmod", path, errMsg)
 		}
 		return fmt.Errorf("chmod failed")
 	}
@@ -545,12 +1130,59 @@ func (wfs *WASMFileSystem) Chmod(path string, mode uint32) error {
 	return nil
 }
 
+// Flush pushes buffered writes to path to the plugin's backend
+// Plugins without fs_flush have nothing buffered
+func (wfs *WASMFileSystem) Flush(path string) error {
+	return wfs.syncCall("fs_flush", "flush", path)
+}
+
+// Fsync makes writes to path durable before returning
+// Plugins without fs_fsync have nothing to make durable
+func (wfs *WASMFileSystem) Fsync(path string) error {
+	return wfs.syncCall("fs_fsync", "fsync", path)
+}
+
+// syncCall calls fs_flush or fs_fsync, which return an error pointer
+func (wfs *WASMFileSystem) syncCall(export, op, path string) error {
+	syncFunc := wfs.module.ExportedFunction(export)
+	if syncFunc == nil {
+		return nil
+	}
+
+	pathPtr, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return err
+	}
+
+	results, err := wfs.call(syncFunc, uint64(pathPtr))
+	if err != nil {
+		return fmt.Errorf("%s failed: %w", export, err)
+	}
+
+	if len(results) > 0 && results[0] != 0 {
+		if errMsg, ok := readStringFromMemory(wfs.module, uint32(results[0])); ok {
+			return pluginError(op, path, errMsg)
+		}
+		return fmt.Errorf("%s failed", op)
+	}
+
+	return nil
+}
+
 func (wfs *WASMFileSystem) Open(path string) (io.ReadCloser, error) {
-	// For WASM plugins, we can implement Open by reading the entire file
-	// This is a simple implementation; more sophisticated implementations
-	// could use streaming or chunked reads
+	// Plugins with handles are read range by range, as the caller asks
+	openFunc := wfs.module.ExportedFunction("fs_open")
+	if openFunc != nil && wfs.module.ExportedFunction("fs_read_at") != nil && wfs.module.ExportedFunction("fs_close") != nil {
+		file, err := wfs.openHandle(openFunc, path)
+		if err != nil {
+			return nil, err
+		}
+		return file, nil
+	}
+
+	// Otherwise, we can implement Open by reading the entire file
 	data, err := wfs.Read(path, 0, -1)
-	if err != nil {
+	if err != nil && err != io.EOF {
 		return nil, err
 	}
 	return io.NopCloser(io.NewSectionReader(&bytesReaderAt{data}, 0, int64(len(data)))), nil
@@ -579,9 +1211,13 @@ func (w *wasmWriteCloser) Write(p []byte) (n int, err error) {
 	return len(p), nil
 }
 
+// Close writes the buffered data, then flushes it as a client closing
+// the file expects
 func (w *wasmWriteCloser) Close() error {
-	_, err := w.fs.Write(w.path, w.buf)
-	return err
+	if _, err := w.fs.Write(w.path, w.buf); err != nil {
+		return err
+	}
+	return w.fs.Flush(w.path)
 }
 
 // Helper functions for memory management
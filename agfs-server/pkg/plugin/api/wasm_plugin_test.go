@@ -0,0 +1,127 @@
+package api
+
+import (
+	"errors"
+	"io"
+	"os"
+	"strings"
+	"syscall"
+	"testing"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+func TestWASMWritePrefersWriteResult(t *testing.T) {
+	fs := newFakePlugin(t, map[string]interface{}{}).GetFileSystem()
+
+	// The fake exports only fs_write_result, which answers "ok"
+	response, err := fs.Write("/ctl", []byte("hello"))
+	if err != nil {
+		t.Fatalf("Write failed: %v", err)
+	}
+	if string(response) != "ok" {
+		t.Errorf("expected response ok, got %q", response)
+	}
+
+	// It stores at most 5 bytes
+	if _, err := fs.Write("/ctl", []byte("hello world")); !errors.Is(err, io.ErrShortWrite) {
+		t.Errorf("expected io.ErrShortWrite, got %v", err)
+	}
+}
+
+func TestWASMBatch(t *testing.T) {
+	wfs := newFakePlugin(t, map[string]interface{}{}).fileSystem
+
+	// The fake answers any four calls with a stat, a read, a listing and ENOENT
+	results, err := wfs.Batch([]BatchOp{
+		{Op: BatchStat, Path: "/a"},
+		{Op: BatchRead, Path: "/a", Size: -1},
+		{Op: BatchReadDir, Path: "/"},
+		{Op: BatchStat, Path: "/missing"},
+	})
+	if err != nil {
+		t.Fatalf("Batch failed: %v", err)
+	}
+	if len(results) != 4 {
+		t.Fatalf("expected 4 results, got %d", len(results))
+	}
+	if info := results[0].Info; info == nil || info.Name != "a" || info.Size != 3 {
+		t.Errorf("unexpected stat result %+v", results[0])
+	}
+	if string(results[1].Data) != "hi" || results[1].Err != nil {
+		t.Errorf("unexpected read result %+v", results[1])
+	}
+	if entries := results[2].Entries; len(entries) != 1 || entries[0].Name != "b" || !entries[0].IsDir {
+		t.Errorf("unexpected readdir result %+v", results[2])
+	}
+	if !errors.Is(results[3].Err, filesystem.ErrNotFound) {
+		t.Errorf("expected ErrNotFound, got %v", results[3].Err)
+	}
+
+	if _, err := wfs.Batch([]BatchOp{{Op: "write", Path: "/a"}}); err == nil {
+		t.Errorf("expected an error for an unknown op")
+	}
+}
+
+func TestWASMReconfigure(t *testing.T) {
+	wp := newFakePlugin(t, map[string]interface{}{})
+	if _, err := wp.GetFileSystem().Write("/ctl", []byte("hello")); err != nil {
+		t.Fatalf("Write failed: %v", err)
+	}
+
+	// The host's settings follow the plugin's new config
+	if err := wp.Reconfigure(map[string]interface{}{MaxWriteSizeKey: 4}); err != nil {
+		t.Fatalf("Reconfigure failed: %v", err)
+	}
+	if _, err := wp.GetFileSystem().Write("/ctl", []byte("hello")); !errors.Is(err, syscall.EFBIG) {
+		t.Errorf("expected EFBIG over the new %s, got %v", MaxWriteSizeKey, err)
+	}
+}
+
+func TestWASMOpenReadsThroughHandle(t *testing.T) {
+	wfs := newFakePlugin(t, map[string]interface{}{}).fileSystem
+
+	// The fake has no fs_read, so only the handle can answer
+	reader, err := wfs.Open("/f")
+	if err != nil {
+		t.Fatalf("Open failed: %v", err)
+	}
+	data, err := io.ReadAll(reader)
+	if err != nil || string(data) != "hello" {
+		t.Errorf("ReadAll = %q, %v", data, err)
+	}
+
+	buf := make([]byte, 8)
+	n, err := reader.(io.ReaderAt).ReadAt(buf, 0)
+	if n != 5 || err != io.EOF || string(buf[:n]) != "hello" {
+		t.Errorf("ReadAt = %d, %v", n, err)
+	}
+
+	if err := reader.Close(); err != nil {
+		t.Errorf("Close failed: %v", err)
+	}
+	if err := reader.Close(); !errors.Is(err, os.ErrClosed) {
+		t.Errorf("expected os.ErrClosed on a second Close, got %v", err)
+	}
+
+	// A handle the plugin does not know fails as the plugin reports
+	stale := &wasmFile{fs: wfs, path: "/f", readAt: wfs.module.ExportedFunction("fs_read_at"), handle: 8}
+	if _, err := stale.ReadAt(buf, 0); err == nil || !strings.Contains(err.Error(), "unknown file handle") {
+		t.Errorf("expected an unknown handle error, got %v", err)
+	}
+}
+
+func TestWASMReadDirStreams(t *testing.T) {
+	fs := newFakePlugin(t, map[string]interface{}{}).GetFileSystem()
+
+	// The fake lists "c" through its cursor, then ends with an empty batch
+	for i := 0; i < 2; i++ {
+		entries, err := fs.ReadDir("/")
+		if err != nil {
+			t.Fatalf("ReadDir failed: %v", err)
+		}
+		if len(entries) != 1 || entries[0].Name != "c" {
+			t.Errorf("unexpected entries %+v", entries)
+		}
+	}
+}
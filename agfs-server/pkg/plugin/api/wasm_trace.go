@@ -0,0 +1,198 @@
+package api
+
+import (
+	"bytes"
+	"crypto/rand"
+	"encoding/hex"
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"strconv"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+)
+
+// TracingKey is the mount config key turning on tracing of a WASM mount
+// The host opens a span around every filesystem call and hands it to the
+// plugin as the TraceParent of the call context. agfs-wasm-ffi records
+// the plugin's spans under it, and the host collects them through
+// plugin_trace_drain after the call and exports them with its own
+const TracingKey = "tracing"
+
+// TraceEndpointKey is the mount config key giving the OTLP/HTTP traces
+// URL spans are posted to, e.g. "http://collector:4318/v1/traces".
+// Without it traced calls are logged at debug level
+const TraceEndpointKey = "trace_endpoint"
+
+const traceExportTimeout = 10 * time.Second
+
+// callTracer traces the filesystem calls of one mount
+type callTracer struct {
+	plugin string
+	// export receives each traced call as an OTLP/JSON
+	// ExportTraceServiceRequest
+	export func(payload []byte)
+}
+
+// callSpan is the host's span around one filesystem call
+type callSpan struct {
+	traceID string
+	spanID  string
+	name    string
+	start   time.Time
+}
+
+// parseTracing reads tracing and trace_endpoint from a mount config
+func parseTracing(config map[string]interface{}) (bool, string, error) {
+	var enabled bool
+	switch v := config[TracingKey].(type) {
+	case nil:
+	case bool:
+		enabled = v
+	case string:
+		b, err := strconv.ParseBool(v)
+		if err != nil {
+			return false, "", fmt.Errorf("%s must be a boolean", TracingKey)
+		}
+		enabled = b
+	default:
+		return false, "", fmt.Errorf("%s must be a boolean", TracingKey)
+	}
+
+	endpoint, ok := config[TraceEndpointKey].(string)
+	if _, set := config[TraceEndpointKey]; set && !ok {
+		return false, "", fmt.Errorf("%s must be a URL", TraceEndpointKey)
+	}
+	return enabled, endpoint, nil
+}
+
+// newCallTracer creates a tracer exporting to endpoint, or to the debug
+// log when endpoint is empty
+func newCallTracer(plugin, endpoint string) *callTracer {
+	t := &callTracer{plugin: plugin}
+	if endpoint == "" {
+		t.export = func(payload []byte) {
+			log.Debugf("Trace of %s: %s", plugin, payload)
+		}
+		return t
+	}
+	client := &http.Client{Timeout: traceExportTimeout}
+	t.export = func(payload []byte) {
+		// Off the call path, so a slow collector does not slow the mount
+		go postTrace(client, endpoint, payload)
+	}
+	return t
+}
+
+func postTrace(client *http.Client, endpoint string, payload []byte) {
+	resp, err := client.Post(endpoint, "application/json", bytes.NewReader(payload))
+	if err != nil {
+		log.Warnf("Failed to export trace to %s: %v", endpoint, err)
+		return
+	}
+	resp.Body.Close()
+	if resp.StatusCode >= 300 {
+		log.Warnf("Failed to export trace to %s: %s", endpoint, resp.Status)
+	}
+}
+
+func randomHex(n int) string {
+	buf := make([]byte, n)
+	rand.Read(buf)
+	return hex.EncodeToString(buf)
+}
+
+// start opens the span of a call to export name; nil when not tracing
+func (t *callTracer) start(name string) *callSpan {
+	if t == nil {
+		return nil
+	}
+	return &callSpan{
+		traceID: randomHex(16),
+		spanID:  randomHex(8),
+		name:    name,
+		start:   time.Now(),
+	}
+}
+
+// traceParent is the span as a sampled W3C traceparent; empty for nil
+func (s *callSpan) traceParent() string {
+	if s == nil {
+		return ""
+	}
+	return "00-" + s.traceID + "-" + s.spanID + "-01"
+}
+
+func otlpAttr(key, value string) map[string]interface{} {
+	return map[string]interface{}{"key": key, "value": map[string]string{"stringValue": value}}
+}
+
+// finishSpan ends span and exports it with the spans the plugin recorded
+// under it; called with callMu held, right after the call
+func (wfs *WASMFileSystem) finishSpan(span *callSpan, callErr error) {
+	if span == nil {
+		return
+	}
+	// STATUS_CODE_OK, or STATUS_CODE_ERROR when the call trapped
+	status := map[string]interface{}{"code": 1}
+	if callErr != nil {
+		status = map[string]interface{}{"code": 2, "message": callErr.Error()}
+	}
+	// The host's span is SPAN_KIND_CLIENT (3): it calls into the plugin
+	hostSpans := map[string]interface{}{
+		"resource": map[string]interface{}{
+			"attributes": []interface{}{otlpAttr("service.name", "agfs-server")},
+		},
+		"scopeSpans": []interface{}{map[string]interface{}{
+			"scope": map[string]string{"name": "agfs-server"},
+			"spans": []interface{}{map[string]interface{}{
+				"traceId":           span.traceID,
+				"spanId":            span.spanID,
+				"name":              "wasm." + span.name,
+				"kind":              3,
+				"startTimeUnixNano": strconv.FormatInt(span.start.UnixNano(), 10),
+				"endTimeUnixNano":   strconv.FormatInt(time.Now().UnixNano(), 10),
+				"attributes":        []interface{}{otlpAttr("agfs.plugin", wfs.tracer.plugin)},
+				"status":            status,
+			}},
+		}},
+	}
+
+	resourceSpans := []interface{}{hostSpans}
+	for _, pluginSpans := range wfs.drainPluginSpans() {
+		resourceSpans = append(resourceSpans, pluginSpans)
+	}
+	payload, err := json.Marshal(map[string]interface{}{"resourceSpans": resourceSpans})
+	if err != nil {
+		log.Warnf("Failed to encode trace of %s: %v", wfs.tracer.plugin, err)
+		return
+	}
+	wfs.tracer.export(payload)
+}
+
+// drainPluginSpans collects the spans the plugin queued during the call,
+// as the resourceSpans entries of its OTLP/JSON export
+func (wfs *WASMFileSystem) drainPluginSpans() []json.RawMessage {
+	drainFunc := wfs.module.ExportedFunction("plugin_trace_drain")
+	if drainFunc == nil {
+		return nil
+	}
+	results, err := drainFunc.Call(wfs.ctx)
+	if err != nil || len(results) == 0 {
+		log.Warnf("Failed to drain spans of %s: %v", wfs.tracer.plugin, err)
+		return nil
+	}
+	payload, ok := readStringFromMemory(wfs.module, uint32(results[0]))
+	if !ok {
+		return nil
+	}
+	var request struct {
+		ResourceSpans []json.RawMessage `json:"resourceSpans"`
+	}
+	if err := json.Unmarshal([]byte(payload), &request); err != nil {
+		log.Warnf("Failed to parse spans of %s: %v", wfs.tracer.plugin, err)
+		return nil
+	}
+	return request.ResourceSpans
+}
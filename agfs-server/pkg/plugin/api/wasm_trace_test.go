@@ -0,0 +1,90 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"testing"
+)
+
+// newFakePlugin loads testdata/fakefs.wasm and initializes it with config
+func newFakePlugin(t *testing.T, config map[string]interface{}) *WASMPlugin {
+	t.Helper()
+	wp, err := NewWASMPlugin(context.Background(), loadTestModule(t, "fakefs"), NewHostServices())
+	if err != nil {
+		t.Fatalf("NewWASMPlugin failed: %v", err)
+	}
+	if err := wp.Initialize(config); err != nil {
+		t.Fatalf("Initialize failed: %v", err)
+	}
+	return wp
+}
+
+type otlpSpan struct {
+	TraceId string `json:"traceId"`
+	SpanId  string `json:"spanId"`
+	Name    string `json:"name"`
+}
+
+// otlpSpans lists the spans of an OTLP/JSON export, resource by resource
+func otlpSpans(t *testing.T, payload []byte) []otlpSpan {
+	t.Helper()
+	var request struct {
+		ResourceSpans []struct {
+			ScopeSpans []struct {
+				Spans []otlpSpan `json:"spans"`
+			} `json:"scopeSpans"`
+		} `json:"resourceSpans"`
+	}
+	if err := json.Unmarshal(payload, &request); err != nil {
+		t.Fatalf("bad OTLP payload %s: %v", payload, err)
+	}
+	var spans []otlpSpan
+	for _, resource := range request.ResourceSpans {
+		for _, scope := range resource.ScopeSpans {
+			spans = append(spans, scope.Spans...)
+		}
+	}
+	return spans
+}
+
+func TestWASMTracing(t *testing.T) {
+	wp := newFakePlugin(t, map[string]interface{}{TracingKey: true})
+	var exported [][]byte
+	wp.fileSystem.tracer.export = func(payload []byte) {
+		exported = append(exported, payload)
+	}
+
+	if err := wp.GetFileSystem().Remove("/x"); err != nil {
+		t.Fatalf("Remove failed: %v", err)
+	}
+	if len(exported) != 1 {
+		t.Fatalf("expected one export per call, got %d", len(exported))
+	}
+
+	// The host's span, then the plugin's under the TraceParent it was sent
+	spans := otlpSpans(t, exported[0])
+	if len(spans) != 2 {
+		t.Fatalf("expected the host's and the plugin's span, got %+v", spans)
+	}
+	host, guest := spans[0], spans[1]
+	if host.Name != "wasm.fs_remove" || len(host.TraceId) != 32 || len(host.SpanId) != 16 {
+		t.Errorf("unexpected host span %+v", host)
+	}
+	if guest.TraceId != host.TraceId {
+		t.Errorf("plugin span has trace %s, host sent %s", guest.TraceId, host.TraceId)
+	}
+}
+
+func TestWASMTracingOff(t *testing.T) {
+	wp := newFakePlugin(t, map[string]interface{}{})
+	if wp.fileSystem.tracer != nil {
+		t.Fatalf("tracer set without %s", TracingKey)
+	}
+	if err := wp.GetFileSystem().Remove("/x"); err != nil {
+		t.Fatalf("Remove failed: %v", err)
+	}
+
+	if err := wp.Validate(map[string]interface{}{TracingKey: "maybe"}); err == nil {
+		t.Errorf("expected an error for a non-boolean %s", TracingKey)
+	}
+}
@@ -0,0 +1,104 @@
+package api
+
+import (
+	"errors"
+	"fmt"
+	"math"
+	"time"
+
+	log "github.com/sirupsen/logrus"
+)
+
+// IdleTrimKey is the mount config key giving, in seconds, how long a WASM
+// mount may go without a filesystem call before the plugin is asked to
+// drop its pools and caches through plugin_trim. Unset or 0 never trims
+const IdleTrimKey = "idle_trim_secs"
+
+// parseSecs reads a duration given in seconds from a mount config
+// Unset is 0
+func parseSecs(config map[string]interface{}, key string) (time.Duration, error) {
+	value, ok := config[key]
+	if !ok {
+		return 0, nil
+	}
+	var secs float64
+	switch v := value.(type) {
+	case int:
+		secs = float64(v)
+	case int64:
+		secs = float64(v)
+	case float64:
+		secs = v
+	default:
+		return 0, fmt.Errorf("%s must be a number", key)
+	}
+	if secs < 0 {
+		return 0, fmt.Errorf("%s must not be negative", key)
+	}
+	return time.Duration(secs * float64(time.Second)), nil
+}
+
+// trimUntracked is what plugin_trim returns when the plugin was built
+// without heap tracking and cannot count what it released
+const trimUntracked = math.MaxUint64
+
+// ErrHeapUntracked is returned by Trim after trimming a plugin that does
+// not track its heap
+var ErrHeapUntracked = errors.New("plugin does not track its heap")
+
+// Trim asks the plugin to release what it can rebuild on demand
+// Returns the heap bytes released; plugins without plugin_trim release
+// nothing, plugins without heap tracking trim but return ErrHeapUntracked
+func (wp *WASMPlugin) Trim() (uint64, error) {
+	trimFunc := wp.module.ExportedFunction("plugin_trim")
+	if trimFunc == nil {
+		return 0, nil
+	}
+
+	wp.fileSystem.callMu.Lock()
+	defer wp.fileSystem.callMu.Unlock()
+	results, err := trimFunc.Call(wp.ctx)
+	if err != nil {
+		return 0, fmt.Errorf("trim call failed: %w", err)
+	}
+	if len(results) == 0 {
+		return 0, fmt.Errorf("trim returned no results")
+	}
+	if results[0] == trimUntracked {
+		return 0, ErrHeapUntracked
+	}
+	return results[0], nil
+}
+
+// trimWhenIdle trims the plugin once every time it has gone idle for at
+// least idle, until stop is closed
+func (wp *WASMPlugin) trimWhenIdle(idle time.Duration, stop <-chan struct{}) {
+	ticker := time.NewTicker(idle)
+	defer ticker.Stop()
+
+	var trimmedAfter int64 // lastCall at the previous trim
+	for {
+		select {
+		case <-stop:
+			return
+		case <-ticker.C:
+		}
+
+		last := wp.fileSystem.lastCall.Load()
+		if last == trimmedAfter || time.Since(time.Unix(0, last)) < idle {
+			continue
+		}
+		released, err := wp.Trim()
+		if errors.Is(err, ErrHeapUntracked) {
+			trimmedAfter = last
+			log.Debugf("Trimmed idle plugin %s", wp.name)
+			continue
+		}
+		if err != nil {
+			log.Warnf("Failed to trim idle plugin %s: %v", wp.name, err)
+			continue
+		}
+		trimmedAfter = last
+		log.Debugf("Trimmed idle plugin %s, %d bytes released", wp.name, released)
+	}
+}
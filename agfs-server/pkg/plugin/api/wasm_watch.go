@@ -0,0 +1,91 @@
+package api
+
+import (
+	"encoding/json"
+
+	log "github.com/sirupsen/logrus"
+)
+
+// watchEvent is a change notification from fs_next_event, as
+// agfs-wasm-ffi's watch::WatchEvent
+type watchEvent struct {
+	Watch uint32
+	Kind  string // Created, Modified, Removed or Overflow
+	Path  string
+}
+
+// watchRoot asks the plugin to report changes anywhere in the mount, so
+// the stat cache drops what changed behind the server's back
+// Plugins without fs_watch and fs_next_event, or that refuse the watch,
+// are cached by TTL alone
+func (wp *WASMPlugin) watchRoot() bool {
+	watchFunc := wp.module.ExportedFunction("fs_watch")
+	if watchFunc == nil || wp.module.ExportedFunction("fs_next_event") == nil {
+		return false
+	}
+
+	pathPtr, err := writeStringToMemory(wp.module, "/")
+	if err != nil {
+		log.Warnf("Failed to watch %s: %v", wp.name, err)
+		return false
+	}
+	results, err := watchFunc.Call(wp.ctx, uint64(pathPtr))
+	if err != nil {
+		log.Warnf("Failed to watch %s: %v", wp.name, err)
+		return false
+	}
+	if len(results) < 1 {
+		return false
+	}
+
+	// Unpack u64: lower 32 bits = watch id, upper 32 bits = error pointer
+	if errPtr := uint32((results[0] >> 32) & 0xFFFFFFFF); errPtr != 0 {
+		if errMsg, ok := readStringFromMemory(wp.module, errPtr); ok {
+			log.Debugf("Plugin %s does not watch, caching by TTL: %s", wp.name, errMsg)
+		}
+		return false
+	}
+	return true
+}
+
+// drainEvents drops the cached results of every path the plugin reported
+// as changed since the last drain; called before the cache is consulted
+func (wfs *WASMFileSystem) drainEvents() {
+	if !wfs.watching {
+		return
+	}
+	nextFunc := wfs.module.ExportedFunction("fs_next_event")
+
+	// Not a filesystem call: it neither sets the call context nor counts
+	// against idle trimming
+	wfs.callMu.Lock()
+	defer wfs.callMu.Unlock()
+	for {
+		results, err := nextFunc.Call(wfs.ctx)
+		if err != nil {
+			log.Warnf("fs_next_event failed: %v", err)
+			return
+		}
+		if len(results) < 1 || results[0] == 0 {
+			return
+		}
+
+		jsonStr, ok := readStringFromMemory(wfs.module, uint32(results[0]))
+		if !ok {
+			return
+		}
+		var event watchEvent
+		if err := json.Unmarshal([]byte(jsonStr), &event); err != nil {
+			log.Warnf("Failed to unmarshal change event: %v", err)
+			wfs.cache.invalidate("/")
+			continue
+		}
+
+		// Events were lost, so anything may have changed
+		if event.Kind == "Overflow" {
+			wfs.cache.invalidate("/")
+			continue
+		}
+		wfs.cache.invalidate(event.Path)
+	}
+}
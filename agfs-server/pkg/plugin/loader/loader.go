@@ -63,6 +63,12 @@ func NewPluginLoader() *PluginLoader {
 }
 
 
+// SetTrustedKeys sets the keys WASM plugins must be signed with; see
+// WASMPluginLoader.SetTrustedKeys
+func (pl *PluginLoader) SetTrustedKeys(keys []string) error {
+	return pl.wasmLoader.SetTrustedKeys(keys)
+}
+
 // DetectPluginType detects the type of plugin based on file content and extension
 func DetectPluginType(libraryPath string) (PluginType, error) {
 	// Check if file exists
@@ -393,6 +399,8 @@ func loadPluginVTable(libHandle uintptr) (*api.PluginVTable, error) {
 	loadFunc(libHandle, "FSStat", &vtable.FSStat)
 	loadFunc(libHandle, "FSRename", &vtable.FSRename)
 	loadFunc(libHandle, "FSChmod", &vtable.FSChmod)
+	loadFunc(libHandle, "FSFlush", &vtable.FSFlush)
+	loadFunc(libHandle, "FSFsync", &vtable.FSFsync)
 
 	return vtable, nil
 }
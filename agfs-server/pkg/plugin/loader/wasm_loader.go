@@ -2,7 +2,9 @@ package loader
 
 import (
 	"context"
+	"crypto/ed25519"
 	"fmt"
+	"math"
 	"os"
 	"path/filepath"
 	"sync"
@@ -29,6 +31,7 @@ type LoadedWASMPlugin struct {
 // WASMPluginLoader manages loading and unloading of WASM plugins
 type WASMPluginLoader struct {
 	loadedPlugins map[string]*LoadedWASMPlugin
+	trustedKeys   []ed25519.PublicKey // set: modules must be signed by one
 	mu            sync.RWMutex
 }
 
@@ -39,6 +42,20 @@ func NewWASMPluginLoader() *WASMPluginLoader {
 	}
 }
 
+// SetTrustedKeys makes the loader refuse WASM modules whose manifest is not
+// signed by one of keys (hex-encoded Ed25519 public keys)
+// No keys loads unsigned modules, as before
+func (wl *WASMPluginLoader) SetTrustedKeys(keys []string) error {
+	parsed, err := ParseTrustedKeys(keys)
+	if err != nil {
+		return err
+	}
+	wl.mu.Lock()
+	defer wl.mu.Unlock()
+	wl.trustedKeys = parsed
+	return nil
+}
+
 // LoadWASMPlugin loads a plugin from a WASM file
 // If hostFS is provided, it will be exposed to the WASM plugin as host functions
 func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, hostFS ...interface{}) (plugin.ServicePlugin, error) {
@@ -76,6 +93,15 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, hostFS ...interface{
 		return nil, fmt.Errorf("failed to read WASM file %s: %w", wasmPath, err)
 	}
 
+	// Check provenance before any of the module runs
+	if len(wl.trustedKeys) > 0 {
+		manifest, err := VerifyWASMManifest(wasmBytes, wl.trustedKeys)
+		if err != nil {
+			return nil, fmt.Errorf("refusing WASM plugin %s: %w", wasmPath, err)
+		}
+		log.Infof("Verified WASM plugin %s: %s %s (build %q)", wasmPath, manifest.Name, manifest.Version, manifest.Build)
+	}
+
 	// Create a new WASM runtime
 	ctx := context.Background()
 	r := wazero.NewRuntime(ctx)
@@ -103,57 +129,352 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, hostFS ...interface{
 		fs = nil // Will be handled by api functions
 	}
 
+	// Host paths are checked against the mount's host_allowed_roots on
+	// every call, whatever paths the plugin constructs
+	host := api.NewHostServices()
+	if fs != nil {
+		fs = api.NewSandboxedFS(fs, host.Sandbox)
+	}
+	// The plugin's scratch space shows up at api.HostTempRoot, outside the
+	// sandbox, with or without a host filesystem
+	fs = api.NewTempRoutedFS(fs, host.Temp)
+
 	_, err = r.NewHostModuleBuilder("env").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset, size int64) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return api.HostFSRead(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(size)}, fs)[0]
 			}).
 			Export("host_fs_read").
 			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSOpenReader(ctx, mod, []uint64{uint64(pathPtr)}, fs, host.Readers)[0]
+			}).
+			Export("host_fs_open_reader").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, handle, maxLen uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSReadChunk(ctx, mod, []uint64{uint64(handle), uint64(maxLen)}, host.Readers)[0]
+			}).
+			Export("host_fs_read_chunk").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, handle uint32) uint32 {
+				return uint32(api.HostFSCloseReader(ctx, mod, []uint64{uint64(handle)}, host.Readers)[0])
+			}).
+			Export("host_fs_close_reader").
+			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, dataPtr, dataLen uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return api.HostFSWrite(ctx, mod, []uint64{uint64(pathPtr), uint64(dataPtr), uint64(dataLen)}, fs)[0]
 			}).
 			Export("host_fs_write").
 			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, dataPtr, dataLen uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSAppend(ctx, mod, []uint64{uint64(pathPtr), uint64(dataPtr), uint64(dataLen)}, fs)[0])
+			}).
+			Export("host_fs_append").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset int64, dataPtr, dataLen uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSWriteAt(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(dataPtr), uint64(dataLen)}, fs)[0]
+			}).
+			Export("host_fs_write_at").
+			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return api.HostFSStat(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
 			}).
 			Export("host_fs_stat").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSLstat(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_lstat").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSRealPath(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_realpath").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, mode uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSAccess(ctx, mod, []uint64{uint64(pathPtr), uint64(mode)}, fs)[0])
+			}).
+			Export("host_fs_access").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return api.HostFSReadDir(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
 			}).
 			Export("host_fs_readdir").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSCreate(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
 			}).
 			Export("host_fs_create").
 			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSCreateExclusive(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
+			}).
+			Export("host_fs_create_exclusive").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, flags, mode uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSCreateFlags(ctx, mod, []uint64{uint64(pathPtr), uint64(flags), uint64(mode)}, fs)[0])
+			}).
+			Export("host_fs_create_flags").
+			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, perm uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSMkdir(ctx, mod, []uint64{uint64(pathPtr), uint64(perm)}, fs)[0])
 			}).
 			Export("host_fs_mkdir").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSRemove(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
 			}).
 			Export("host_fs_remove").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSRemoveAll(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
 			}).
 			Export("host_fs_remove_all").
 			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, oldPathPtr, newPathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSRename(ctx, mod, []uint64{uint64(oldPathPtr), uint64(newPathPtr)}, fs)[0])
 			}).
 			Export("host_fs_rename").
 			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, existingPtr, newPathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSLink(ctx, mod, []uint64{uint64(existingPtr), uint64(newPathPtr)}, fs)[0])
+			}).
+			Export("host_fs_link").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, oldPathPtr, newPathPtr, flags uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSRenameFlags(ctx, mod, []uint64{uint64(oldPathPtr), uint64(newPathPtr), uint64(flags)}, fs)[0])
+			}).
+			Export("host_fs_rename_flags").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, kind uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSLock(ctx, mod, []uint64{uint64(pathPtr), uint64(kind)}, fs, host.Locks)[0])
+			}).
+			Export("host_fs_lock").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				return uint32(api.HostFSUnlock(ctx, mod, []uint64{uint64(pathPtr)}, host.Locks)[0])
+			}).
+			Export("host_fs_unlock").
+			NewFunctionBuilder().
 			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, mode uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
 				return uint32(api.HostFSChmod(ctx, mod, []uint64{uint64(pathPtr), uint64(mode)}, fs)[0])
 			}).
 			Export("host_fs_chmod").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset int64, whence uint32) int64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return int64(api.HostFSSeek(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(whence)}, fs)[0])
+			}).
+			Export("host_fs_seek").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, uid, gid uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSChown(ctx, mod, []uint64{uint64(pathPtr), uint64(uid), uint64(gid)}, fs)[0])
+			}).
+			Export("host_fs_chown").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, atime, mtime int64) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSSetTimes(ctx, mod, []uint64{uint64(pathPtr), uint64(atime), uint64(mtime)}, fs)[0])
+			}).
+			Export("host_fs_set_times").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, size int64) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSTruncate(ctx, mod, []uint64{uint64(pathPtr), uint64(size)}, fs)[0])
+			}).
+			Export("host_fs_truncate").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset, length int64) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSAllocate(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(length)}, fs)[0])
+			}).
+			Export("host_fs_allocate").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, targetPtr, linkPathPtr uint32) uint32 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return uint32(api.HostFSSymlink(ctx, mod, []uint64{uint64(targetPtr), uint64(linkPathPtr)}, fs)[0])
+			}).
+			Export("host_fs_symlink").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				ctx, cancel := host.Timeout.Context(ctx)
+				defer cancel()
+				return api.HostFSReadlink(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_readlink").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module) uint64 {
+				return api.HostFSAllowedRoots(ctx, mod, host.Sandbox)[0]
+			}).
+			Export("host_fs_allowed_roots").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, methodPtr, urlPtr, headersPtr, bodyPtr, bodyLen, timeoutMs, maxResponse uint32) uint64 {
+				return api.HostHTTPRequest(ctx, mod, []uint64{uint64(methodPtr), uint64(urlPtr), uint64(headersPtr), uint64(bodyPtr), uint64(bodyLen), uint64(timeoutMs), uint64(maxResponse)}, host.HTTP)[0]
+			}).
+			Export("host_http_request").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr uint32) uint64 {
+				return api.HostKVGet(ctx, mod, []uint64{uint64(keyPtr)}, host.KV)[0]
+			}).
+			Export("host_kv_get").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr, valuePtr, valueLen uint32) uint32 {
+				return uint32(api.HostKVPut(ctx, mod, []uint64{uint64(keyPtr), uint64(valuePtr), uint64(valueLen)}, host.KV)[0])
+			}).
+			Export("host_kv_put").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr uint32) uint32 {
+				return uint32(api.HostKVDelete(ctx, mod, []uint64{uint64(keyPtr)}, host.KV)[0])
+			}).
+			Export("host_kv_delete").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, prefixPtr, afterPtr, limit uint32) uint64 {
+				return api.HostKVScan(ctx, mod, []uint64{uint64(prefixPtr), uint64(afterPtr), uint64(limit)}, host.KV)[0]
+			}).
+			Export("host_kv_scan").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, level, targetPtr, msgPtr uint32) {
+				api.HostLog(ctx, mod, []uint64{uint64(level), uint64(targetPtr), uint64(msgPtr)}, filepath.Base(wasmPath))
+			}).
+			Export("host_log").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context) int64 {
+				return api.HostTimeNow(ctx)
+			}).
+			Export("host_time_now").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context) uint64 {
+				return api.HostTimeMonotonic(ctx)
+			}).
+			Export("host_time_monotonic").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, n uint32) uint64 {
+				return api.HostRandomBytes(ctx, mod, []uint64{uint64(n)})[0]
+			}).
+			Export("host_random_bytes").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr uint32) uint64 {
+				return api.HostEnvGet(ctx, mod, []uint64{uint64(keyPtr)}, host.Env)[0]
+			}).
+			Export("host_env_get").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, prefixPtr uint32) uint64 {
+				return api.HostTempCreate(ctx, mod, []uint64{uint64(prefixPtr)}, host.Temp, false)[0]
+			}).
+			Export("host_temp_create_file").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, prefixPtr uint32) uint64 {
+				return api.HostTempCreate(ctx, mod, []uint64{uint64(prefixPtr)}, host.Temp, true)[0]
+			}).
+			Export("host_temp_create_dir").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, argvPtr, stdinPtr, stdinLen, timeoutMs uint32) uint64 {
+				return api.HostExecCall(ctx, mod, []uint64{uint64(argvPtr), uint64(stdinPtr), uint64(stdinLen), uint64(timeoutMs)}, host.Exec)[0]
+			}).
+			Export("host_exec").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, addrPtr, timeoutMs uint32) uint64 {
+				return api.HostTCPConnect(ctx, mod, []uint64{uint64(addrPtr), uint64(timeoutMs)}, host.TCP)[0]
+			}).
+			Export("host_tcp_connect").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, handle, limit, timeoutMs uint32) uint64 {
+				return api.HostTCPRead(ctx, mod, []uint64{uint64(handle), uint64(limit), uint64(timeoutMs)}, host.TCP)[0]
+			}).
+			Export("host_tcp_read").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, handle, dataPtr, dataLen, timeoutMs uint32) uint64 {
+				return api.HostTCPWrite(ctx, mod, []uint64{uint64(handle), uint64(dataPtr), uint64(dataLen), uint64(timeoutMs)}, host.TCP)[0]
+			}).
+			Export("host_tcp_write").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, handle uint32) uint32 {
+				return uint32(api.HostTCPClose(ctx, mod, []uint64{uint64(handle)}, host.TCP)[0])
+			}).
+			Export("host_tcp_close").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, namePtr uint32) uint64 {
+				return api.HostDNSResolve(ctx, mod, []uint64{uint64(namePtr)}, host.DNS)[0]
+			}).
+			Export("host_dns_resolve").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, namePtr uint32, value float64, labelsPtr uint32) uint32 {
+				params := []uint64{uint64(namePtr), math.Float64bits(value), uint64(labelsPtr)}
+				return uint32(api.HostMetricCounter(ctx, mod, params, filepath.Base(wasmPath))[0])
+			}).
+			Export("host_metric_counter").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, namePtr uint32, value float64, labelsPtr uint32) uint32 {
+				params := []uint64{uint64(namePtr), math.Float64bits(value), uint64(labelsPtr)}
+				return uint32(api.HostMetricHistogram(ctx, mod, params, filepath.Base(wasmPath))[0])
+			}).
+			Export("host_metric_histogram").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestIDPtr uint32) uint32 {
+				return uint32(api.HostShouldCancel(ctx, mod, []uint64{uint64(requestIDPtr)}, host.Cancel)[0])
+			}).
+			Export("host_should_cancel").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, dataPtr, dataLen uint32) uint32 {
+				return uint32(api.HostStateSave(ctx, mod, []uint64{uint64(dataPtr), uint64(dataLen)}, host.State)[0])
+			}).
+			Export("host_state_save").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module) uint64 {
+				return api.HostStateLoad(ctx, mod, host.State)[0]
+			}).
+			Export("host_state_load").
 			Instantiate(ctx)
 	if err != nil {
 		r.Close(ctx)
@@ -183,7 +504,7 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, hostFS ...interface{
 	log.Infof("Loaded WASM module: %s", wasmPath)
 
 	// Create WASM plugin wrapper
-	wasmPlugin, err := api.NewWASMPlugin(ctx, module)
+	wasmPlugin, err := api.NewWASMPlugin(ctx, module, host)
 	if err != nil {
 		module.Close(ctx)
 		r.Close(ctx)
@@ -0,0 +1,162 @@
+package loader
+
+import (
+	"bytes"
+	"crypto/ed25519"
+	"crypto/sha256"
+	"encoding/hex"
+	"fmt"
+	"strings"
+)
+
+// Custom sections written by agfs-wasm-ffi's manifest module
+const (
+	manifestSection  = "agfs.manifest"
+	signatureSection = "agfs.signature"
+	codeSectionID    = 10
+)
+
+var wasmHeader = []byte("\x00asm\x01\x00\x00\x00")
+
+// WASMManifest is what a plugin declares about itself in its
+// agfs.manifest section
+type WASMManifest struct {
+	Name    string
+	Version string
+	Build   string // commit hash or similar; empty if none was given
+}
+
+// ParseTrustedKeys decodes hex-encoded Ed25519 public keys
+func ParseTrustedKeys(keys []string) ([]ed25519.PublicKey, error) {
+	parsed := make([]ed25519.PublicKey, 0, len(keys))
+	for _, key := range keys {
+		raw, err := hex.DecodeString(strings.TrimSpace(key))
+		if err != nil || len(raw) != ed25519.PublicKeySize {
+			return nil, fmt.Errorf("invalid trusted key %q: expected %d hex-encoded bytes", key, ed25519.PublicKeySize)
+		}
+		parsed = append(parsed, ed25519.PublicKey(raw))
+	}
+	return parsed, nil
+}
+
+// VerifyWASMManifest checks that the module is signed by one of keys and
+// returns the manifest the signature covers
+// The signature is Ed25519 over the digest agfs-wasm-ffi's manifest::digest
+// computes: SHA-256 of the manifest section, a zero byte, then the code
+// section
+func VerifyWASMManifest(wasm []byte, keys []ed25519.PublicKey) (*WASMManifest, error) {
+	sections, err := customSections(wasm)
+	if err != nil {
+		return nil, err
+	}
+	manifest, ok := sections.custom[manifestSection]
+	if !ok {
+		return nil, fmt.Errorf("module has no %s section", manifestSection)
+	}
+	signature, ok := sections.custom[signatureSection]
+	if !ok {
+		return nil, fmt.Errorf("module is not signed")
+	}
+
+	covered := make([]byte, 0, len(manifest)+1+len(sections.code))
+	covered = append(covered, manifest...)
+	covered = append(covered, 0)
+	covered = append(covered, sections.code...)
+	digest := sha256.Sum256(covered)
+
+	for _, key := range keys {
+		if ed25519.Verify(key, digest[:], signature) {
+			return parseManifest(manifest)
+		}
+	}
+	return nil, fmt.Errorf("signature does not match any trusted key")
+}
+
+// parseManifest reads the key=value lines of a manifest section; unknown
+// keys are ignored
+func parseManifest(data []byte) (*WASMManifest, error) {
+	var manifest WASMManifest
+	for _, line := range strings.Split(string(data), "\n") {
+		if line == "" {
+			continue
+		}
+		key, value, found := strings.Cut(line, "=")
+		if !found {
+			return nil, fmt.Errorf("malformed manifest line %q", line)
+		}
+		switch key {
+		case "name":
+			manifest.Name = value
+		case "version":
+			manifest.Version = value
+		case "build":
+			manifest.Build = value
+		}
+	}
+	if manifest.Name == "" {
+		return nil, fmt.Errorf("manifest has no name")
+	}
+	return &manifest, nil
+}
+
+// wasmSections holds the custom sections of a module by name, and its
+// code section
+type wasmSections struct {
+	custom map[string][]byte
+	code   []byte
+}
+
+func customSections(wasm []byte) (*wasmSections, error) {
+	if !bytes.HasPrefix(wasm, wasmHeader) {
+		return nil, fmt.Errorf("not a WASM module")
+	}
+	sections := &wasmSections{custom: make(map[string][]byte)}
+	pos := len(wasmHeader)
+	for pos < len(wasm) {
+		id := wasm[pos]
+		pos++
+		size, err := readLEB(wasm, &pos)
+		if err != nil {
+			return nil, err
+		}
+		if size > len(wasm)-pos {
+			return nil, fmt.Errorf("truncated section")
+		}
+		body := wasm[pos : pos+size]
+		pos += size
+
+		switch id {
+		case 0:
+			at := 0
+			nameLen, err := readLEB(body, &at)
+			if err != nil || nameLen > len(body)-at {
+				return nil, fmt.Errorf("bad custom section name")
+			}
+			name := string(body[at : at+nameLen])
+			// The first section of a name wins, as in agfs-wasm-ffi
+			if _, exists := sections.custom[name]; !exists {
+				sections.custom[name] = body[at+nameLen:]
+			}
+		case codeSectionID:
+			sections.code = body
+		}
+	}
+	return sections, nil
+}
+
+// readLEB reads an unsigned LEB128 value of at most 32 bits
+func readLEB(data []byte, pos *int) (int, error) {
+	var value uint64
+	for shift := 0; shift < 35; shift += 7 {
+		if *pos >= len(data) {
+			return 0, fmt.Errorf("truncated module")
+		}
+		b := data[*pos]
+		*pos++
+		value |= uint64(b&0x7f) << shift
+		if b&0x80 == 0 {
+			return int(value), nil
+		}
+	}
+	return 0, fmt.Errorf("malformed LEB128")
+}
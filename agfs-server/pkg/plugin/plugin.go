@@ -31,6 +31,42 @@ type ServicePlugin interface {
 	Shutdown() error
 }
 
+// ReadOnlyPlugin is implemented by plugins that can tell whether their
+// file system refuses modifying calls
+type ReadOnlyPlugin interface {
+	ReadOnly() bool
+}
+
+// ReconfigurablePlugin is implemented by plugins that can take a new
+// configuration while mounted, e.g. to rotate credentials
+type ReconfigurablePlugin interface {
+	// Reconfigure applies a configuration that passed Validate. Plugins
+	// that cannot apply it in place return an error matching
+	// filesystem.ErrNotSupported, and the mount is restarted instead
+	Reconfigure(config map[string]interface{}) error
+}
+
+// Health states reported by plugins
+const (
+	Healthy   = "Healthy"
+	Degraded  = "Degraded"
+	Unhealthy = "Unhealthy"
+)
+
+// HealthStatus is the result of probing a plugin's backend
+type HealthStatus struct {
+	State   string `json:"state"` // Healthy, Degraded or Unhealthy
+	Message string `json:"message,omitempty"`
+}
+
+// HealthPlugin is implemented by plugins the server probes periodically,
+// so a mount whose backend broke is reported before the first failed call
+type HealthPlugin interface {
+	// Health returns the result of the last probe; ok is false until
+	// there is one
+	Health() (status HealthStatus, ok bool)
+}
+
 // MountPoint represents a mounted service plugin
 type MountPoint struct {
 	Path   string
@@ -5,6 +5,7 @@ import (
 	"io"
 	"os"
 	"path/filepath"
+	"strings"
 	"sync"
 	"time"
 
@@ -91,6 +92,65 @@ func (fs *LocalFS) Create(path string) error {
 	return nil
 }
 
+// CreateExclusive implements filesystem.ExclusiveCreator interface
+func (fs *LocalFS) CreateExclusive(path string) error {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	f, err := os.OpenFile(localPath, os.O_WRONLY|os.O_CREATE|os.O_EXCL, 0644)
+	if err != nil {
+		if os.IsExist(err) {
+			return filesystem.NewAlreadyExistsError("file", path)
+		}
+		if os.IsNotExist(err) {
+			return fmt.Errorf("parent directory does not exist: %s", filepath.Dir(path))
+		}
+		return fmt.Errorf("failed to create file: %w", err)
+	}
+	f.Close()
+
+	return nil
+}
+
+// CreateWithFlags implements filesystem.FlagCreator interface
+func (fs *LocalFS) CreateWithFlags(path string, flags, mode uint32) error {
+	known := filesystem.CreateFlagExclusive | filesystem.CreateFlagTruncate | filesystem.CreateFlagMode
+	if flags&^known != 0 {
+		return filesystem.NewInvalidArgumentError("flags", flags, "unknown create flags")
+	}
+	openFlags := os.O_WRONLY | os.O_CREATE
+	if flags&filesystem.CreateFlagExclusive != 0 {
+		openFlags |= os.O_EXCL
+	}
+	if flags&filesystem.CreateFlagTruncate != 0 {
+		openFlags |= os.O_TRUNC
+	}
+	perm := os.FileMode(0644)
+	if flags&filesystem.CreateFlagMode != 0 {
+		perm = os.FileMode(mode) & os.ModePerm
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	f, err := os.OpenFile(localPath, openFlags, perm)
+	if err != nil {
+		if os.IsExist(err) {
+			return filesystem.NewAlreadyExistsError("file", path)
+		}
+		if os.IsNotExist(err) {
+			return fmt.Errorf("parent directory does not exist: %s", filepath.Dir(path))
+		}
+		return fmt.Errorf("failed to create file: %w", err)
+	}
+	f.Close()
+
+	return nil
+}
+
 func (fs *LocalFS) Mkdir(path string, perm uint32) error {
 	localPath := fs.resolvePath(path)
 
@@ -341,6 +401,133 @@ func (fs *LocalFS) Stat(path string) (*filesystem.FileInfo, error) {
 	}, nil
 }
 
+// Lstat implements filesystem.Lstater interface
+func (fs *LocalFS) Lstat(path string) (*filesystem.FileInfo, error) {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	info, err := os.Lstat(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return nil, filesystem.NewNotFoundError("lstat", path)
+		}
+		return nil, fmt.Errorf("failed to lstat: %w", err)
+	}
+
+	return &filesystem.FileInfo{
+		Name:    info.Name(),
+		Size:    info.Size(),
+		Mode:    uint32(info.Mode()),
+		ModTime: info.ModTime(),
+		IsDir:   info.IsDir(),
+		Meta: filesystem.MetaData{
+			Name: PluginName,
+			Type: "local",
+			Content: map[string]string{
+				"local_path": localPath,
+			},
+		},
+	}, nil
+}
+
+// RealPath implements filesystem.RealPather interface
+// A path whose symlinks lead outside the base directory is refused
+func (fs *LocalFS) RealPath(path string) (string, error) {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	resolved, err := filepath.EvalSymlinks(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return "", filesystem.NewNotFoundError("realpath", path)
+		}
+		return "", fmt.Errorf("failed to resolve path: %w", err)
+	}
+	base, err := filepath.EvalSymlinks(fs.basePath)
+	if err != nil {
+		return "", fmt.Errorf("failed to resolve base path: %w", err)
+	}
+
+	rel, err := filepath.Rel(base, resolved)
+	if err != nil || rel == ".." || strings.HasPrefix(rel, ".."+string(filepath.Separator)) {
+		return "", filesystem.NewPermissionDeniedError("realpath", path, "resolves outside the base path")
+	}
+	if rel == "." {
+		return "/", nil
+	}
+	return "/" + filepath.ToSlash(rel), nil
+}
+
+// Symlink implements filesystem.Symlinker interface
+// Targets must be relative and stay inside the base directory, so a link
+// can never lead reads and writes out of it
+func (fs *LocalFS) Symlink(target, linkPath string) error {
+	localPath := fs.resolvePath(linkPath)
+
+	if filepath.IsAbs(target) {
+		return filesystem.NewPermissionDeniedError("symlink", linkPath, "absolute targets are not allowed")
+	}
+	resolved := filepath.Join(filepath.Dir(localPath), target)
+	if resolved != fs.basePath && !strings.HasPrefix(resolved, fs.basePath+string(filepath.Separator)) {
+		return filesystem.NewPermissionDeniedError("symlink", linkPath, "target is outside the base path")
+	}
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if err := os.Symlink(target, localPath); err != nil {
+		if os.IsExist(err) {
+			return filesystem.NewAlreadyExistsError("file", linkPath)
+		}
+		if os.IsNotExist(err) {
+			return fmt.Errorf("parent directory does not exist: %s", filepath.Dir(linkPath))
+		}
+		return fmt.Errorf("failed to create symlink: %w", err)
+	}
+	return nil
+}
+
+// Readlink implements filesystem.Symlinker interface
+func (fs *LocalFS) Readlink(path string) (string, error) {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	target, err := os.Readlink(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return "", filesystem.NewNotFoundError("readlink", path)
+		}
+		return "", fmt.Errorf("failed to read symlink: %w", err)
+	}
+	return filepath.ToSlash(target), nil
+}
+
+// Link implements filesystem.Linker interface
+func (fs *LocalFS) Link(existing, newPath string) error {
+	existingLocalPath := fs.resolvePath(existing)
+	newLocalPath := fs.resolvePath(newPath)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if err := os.Link(existingLocalPath, newLocalPath); err != nil {
+		if os.IsExist(err) {
+			return filesystem.NewAlreadyExistsError("file", newPath)
+		}
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("link", existing)
+		}
+		return fmt.Errorf("failed to link: %w", err)
+	}
+	return nil
+}
+
 func (fs *LocalFS) Rename(oldPath, newPath string) error {
 	oldLocalPath := fs.resolvePath(oldPath)
 	newLocalPath := fs.resolvePath(newPath)
@@ -388,6 +575,287 @@ func (fs *LocalFS) Chmod(path string, mode uint32) error {
 	return nil
 }
 
+// Truncate implements filesystem.Truncater interface
+func (fs *LocalFS) Truncate(path string, size int64) error {
+	if size < 0 {
+		return filesystem.NewInvalidArgumentError("size", size, "must not be negative")
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if err := os.Truncate(localPath, size); err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("truncate", path)
+		}
+		return fmt.Errorf("failed to truncate: %w", err)
+	}
+	return nil
+}
+
+// SetTimes implements filesystem.TimeSetter interface
+func (fs *LocalFS) SetTimes(path string, atime, mtime time.Time) error {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if err := os.Chtimes(localPath, atime, mtime); err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("set_times", path)
+		}
+		return fmt.Errorf("failed to set times: %w", err)
+	}
+	return nil
+}
+
+// Chown implements filesystem.Chowner interface
+// The server needs the privileges to change ownership
+func (fs *LocalFS) Chown(path string, uid, gid uint32) error {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if err := os.Chown(localPath, int(uid), int(gid)); err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("chown", path)
+		}
+		if os.IsPermission(err) {
+			return filesystem.NewPermissionDeniedError("chown", path, "")
+		}
+		return fmt.Errorf("failed to chown: %w", err)
+	}
+	return nil
+}
+
+// SeekData implements filesystem.DataSeeker interface
+// Files are reported without holes, as by file systems that do not
+// track them: all data up to the end, which is the only hole
+func (fs *LocalFS) SeekData(path string, offset int64, whence int) (int64, bool, error) {
+	if whence != filesystem.SeekData && whence != filesystem.SeekHole {
+		return 0, false, filesystem.NewInvalidArgumentError("whence", whence, "expected SEEK_DATA or SEEK_HOLE")
+	}
+	if offset < 0 {
+		return 0, false, filesystem.NewInvalidArgumentError("offset", offset, "must not be negative")
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	info, err := os.Stat(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return 0, false, filesystem.NewNotFoundError("seek", path)
+		}
+		return 0, false, fmt.Errorf("failed to stat: %w", err)
+	}
+	if offset >= info.Size() {
+		return 0, false, nil
+	}
+	if whence == filesystem.SeekData {
+		return offset, true, nil
+	}
+	return info.Size(), true, nil
+}
+
+// Allocate implements filesystem.Allocator interface
+// Space is reserved by extending the file; the gap reads as zeros
+func (fs *LocalFS) Allocate(path string, offset, length int64) error {
+	if offset < 0 {
+		return filesystem.NewInvalidArgumentError("offset", offset, "must not be negative")
+	}
+	if length <= 0 {
+		return filesystem.NewInvalidArgumentError("length", length, "must be positive")
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	info, err := os.Stat(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("allocate", path)
+		}
+		return fmt.Errorf("failed to stat: %w", err)
+	}
+	if info.IsDir() {
+		return fmt.Errorf("is a directory: %s", path)
+	}
+	if info.Size() >= offset+length {
+		return nil
+	}
+	if err := os.Truncate(localPath, offset+length); err != nil {
+		return fmt.Errorf("failed to allocate: %w", err)
+	}
+	return nil
+}
+
+// Access implements filesystem.Accesser interface
+// Read and write access is checked by opening the path, so the answer
+// reflects the server process, not the file's owner
+func (fs *LocalFS) Access(path string, mode uint32) error {
+	if mode&^uint32(filesystem.AccessRead|filesystem.AccessWrite|filesystem.AccessExecute) != 0 {
+		return filesystem.NewInvalidArgumentError("mode", mode, "unknown mode bits")
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	info, err := os.Stat(localPath)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("access", path)
+		}
+		return fmt.Errorf("failed to stat: %w", err)
+	}
+	denied := func(reason string) error {
+		return &filesystem.PermissionDeniedError{Path: path, Op: "access", Reason: reason}
+	}
+	if mode&filesystem.AccessRead != 0 {
+		f, err := os.Open(localPath)
+		if err != nil {
+			return denied("not readable")
+		}
+		f.Close()
+	}
+	if mode&filesystem.AccessWrite != 0 {
+		if info.IsDir() {
+			if info.Mode().Perm()&0200 == 0 {
+				return denied("not writable")
+			}
+		} else {
+			f, err := os.OpenFile(localPath, os.O_WRONLY, 0)
+			if err != nil {
+				return denied("not writable")
+			}
+			f.Close()
+		}
+	}
+	if mode&filesystem.AccessExecute != 0 && info.Mode().Perm()&0111 == 0 {
+		return denied("not executable")
+	}
+	return nil
+}
+
+// Append implements filesystem.Appender interface
+func (fs *LocalFS) Append(path string, data []byte) error {
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	f, err := os.OpenFile(localPath, os.O_WRONLY|os.O_APPEND|os.O_CREATE, 0644)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("append", path)
+		}
+		return fmt.Errorf("failed to open file: %w", err)
+	}
+	_, err = f.Write(data)
+	if closeErr := f.Close(); err == nil {
+		err = closeErr
+	}
+	if err != nil {
+		return fmt.Errorf("failed to append: %w", err)
+	}
+	return nil
+}
+
+// RenameWithFlags implements filesystem.FlagRenamer interface
+// The OS rename cannot check or swap atomically, so both are done under
+// the file system lock: other LocalFS calls see one or the other, but
+// outside writers may race an exchange
+func (fs *LocalFS) RenameWithFlags(oldPath, newPath string, flags uint32) error {
+	if err := filesystem.ValidateRenameFlags(flags); err != nil {
+		return err
+	}
+	oldLocalPath := fs.resolvePath(oldPath)
+	newLocalPath := fs.resolvePath(newPath)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	if _, err := os.Lstat(oldLocalPath); os.IsNotExist(err) {
+		return filesystem.NewNotFoundError("rename", oldPath)
+	}
+	_, err := os.Lstat(newLocalPath)
+	newExists := err == nil
+	if err != nil && !os.IsNotExist(err) {
+		return fmt.Errorf("failed to stat: %w", err)
+	}
+
+	switch {
+	case flags&filesystem.RenameNoReplace != 0 && newExists:
+		return filesystem.NewAlreadyExistsError("file", newPath)
+	case flags&filesystem.RenameExchange != 0:
+		if !newExists {
+			return filesystem.NewNotFoundError("rename", newPath)
+		}
+		return exchangePaths(oldLocalPath, newLocalPath)
+	}
+	if err := os.Rename(oldLocalPath, newLocalPath); err != nil {
+		if os.IsNotExist(err) {
+			return filesystem.NewNotFoundError("rename", newPath)
+		}
+		return fmt.Errorf("failed to rename: %w", err)
+	}
+	return nil
+}
+
+// exchangePaths swaps a and b through a free sibling name of b, putting
+// everything back if a step fails
+func exchangePaths(a, b string) error {
+	tmp := fmt.Sprintf("%s.exchange-%d", b, time.Now().UnixNano())
+	if _, err := os.Lstat(tmp); err == nil {
+		return fmt.Errorf("failed to exchange: %s exists", tmp)
+	}
+	if err := os.Rename(b, tmp); err != nil {
+		return fmt.Errorf("failed to exchange: %w", err)
+	}
+	if err := os.Rename(a, b); err != nil {
+		os.Rename(tmp, b)
+		return fmt.Errorf("failed to exchange: %w", err)
+	}
+	if err := os.Rename(tmp, a); err != nil {
+		os.Rename(b, a)
+		os.Rename(tmp, b)
+		return fmt.Errorf("failed to exchange: %w", err)
+	}
+	return nil
+}
+
+// WriteAt implements filesystem.WriterAt interface
+func (fs *LocalFS) WriteAt(path string, offset int64, data []byte) (int64, error) {
+	if offset < 0 {
+		return 0, filesystem.NewInvalidArgumentError("offset", offset, "must not be negative")
+	}
+	localPath := fs.resolvePath(path)
+
+	fs.mu.Lock()
+	defer fs.mu.Unlock()
+
+	f, err := os.OpenFile(localPath, os.O_WRONLY|os.O_CREATE, 0644)
+	if err != nil {
+		if os.IsNotExist(err) {
+			return 0, filesystem.NewNotFoundError("write_at", path)
+		}
+		return 0, fmt.Errorf("failed to open file: %w", err)
+	}
+	n, err := f.WriteAt(data, offset)
+	if closeErr := f.Close(); err == nil {
+		err = closeErr
+	}
+	if err != nil {
+		return int64(n), fmt.Errorf("failed to write: %w", err)
+	}
+	return int64(n), nil
+}
+
 func (fs *LocalFS) Open(path string) (io.ReadCloser, error) {
 	localPath := fs.resolvePath(path)
 